@@ -0,0 +1,105 @@
+//! Timings of the beach operations that must stay fast on very large beaches.
+//!
+//! Run with `cargo bench`. Each benchmark prints the mean time per call over a fixed number of
+//! calls, after a short warm-up. (Criterion is not a dependency, so this is a minimal harness.)
+
+use ocean::beach::Beach;
+use ocean::color::Color;
+use ocean::crab::Crab;
+use ocean::diet::Diet;
+use ocean::prey::{Algae, Clam};
+use ocean::reef::Reef;
+use std::cell::RefCell;
+use std::hint::black_box;
+use std::rc::Rc;
+use std::time::Instant;
+
+const CRABS: usize = 100_000;
+const CLANS: usize = 10;
+const REEFS: usize = 1_000;
+
+fn bench<R, F: FnMut() -> R>(name: &str, calls: u32, mut f: F) {
+    for _ in 0..calls.div_ceil(10) {
+        black_box(f());
+    }
+    let start = Instant::now();
+    for _ in 0..calls {
+        black_box(f());
+    }
+    let per_call = start.elapsed() / calls;
+    println!("{:<40} {:>12?} per call ({} calls)", name, per_call, calls);
+}
+
+fn crab(name: String, speed: u32, diet: Diet) -> Crab {
+    Crab::new(name, speed, Color::new_blue(), diet)
+}
+
+// A beach of `CRABS` crabs spread evenly over `CLANS` clans.
+fn big_beach() -> Beach {
+    let mut beach = Beach::new();
+    for i in 0..CRABS {
+        beach.add_crab(crab(format!("crab{}", i), (i % 1000) as u32, Diet::Plants));
+    }
+    for i in 0..CRABS {
+        beach.add_member_to_clan(&format!("clan{}", i % CLANS), &format!("crab{}", i));
+    }
+    beach.drain_events();
+    beach
+}
+
+// A crab that knows `REEFS` reefs, of which only the last has prey it will eat.
+fn well_travelled_crab() -> Crab {
+    let mut hunter = crab(String::from("hunter"), 10, Diet::Plants);
+    for i in 0..REEFS {
+        let mut reef = Reef::new();
+        for _ in 0..10 {
+            reef.add_prey(Box::new(Clam::new()));
+        }
+        if i == REEFS - 1 {
+            for _ in 0..100_000 {
+                reef.add_prey(Box::new(Algae::new()));
+            }
+        }
+        hunter.discover_reef(Rc::new(RefCell::new(reef)));
+    }
+    hunter
+}
+
+// A beach of `CRABS` crabs in groups of ten, each group hunting in a reef of its own.
+fn hunting_beach() -> Beach {
+    let mut beach = Beach::new();
+    for i in 0..CRABS {
+        if i % 10 == 0 {
+            let mut reef = Reef::new();
+            for _ in 0..1_000 {
+                reef.add_prey(Box::new(Algae::new()));
+            }
+            beach.add_crab(crab(format!("crab{}", i), 10, Diet::Plants));
+            beach.get_crab_mut(i).discover_reef(Rc::new(RefCell::new(reef)));
+        } else {
+            let reef = beach.get_crab(i - i % 10).reefs().next().unwrap().clone();
+            beach.add_crab(crab(format!("crab{}", i), 10, Diet::Plants));
+            beach.get_crab_mut(i).discover_reef(reef);
+        }
+    }
+    beach
+}
+
+fn main() {
+    let mut beach = big_beach();
+    bench("find_crabs_by_name (100k crabs)", 10_000, || beach.find_crabs_by_name("crab99999").len());
+    bench("find_crabs_by_name, missing (100k crabs)", 10_000, || beach.find_crabs_by_name("nobody").len());
+    bench("get_fastest_crab (100k crabs)", 10_000, || beach.get_fastest_crab().map(Crab::speed));
+    bench("get_winner_clan (10k members each)", 10_000, || beach.get_winner_clan("clan0", "clan1").unwrap());
+    bench("add_member_to_clan (100k crabs)", 10_000, || beach.add_member_to_clan("clan2", "crab12345"));
+
+    let mut hunter = well_travelled_crab();
+    bench("hunt (1k reefs, prey in the last)", 1_000, || hunter.hunt());
+
+    let mut beach = hunting_beach();
+    bench("every crab hunts in turn (100k crabs)", 10, || {
+        (0..beach.size()).filter(|&i| beach.get_crab_mut(i).hunt()).count()
+    });
+    let mut beach = hunting_beach();
+    bench("hunt_all_parallel (100k crabs)", 10, || beach.hunt_all_parallel());
+}
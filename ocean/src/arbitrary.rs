@@ -0,0 +1,388 @@
+//! Random generation and shrinking of core types for property tests (the `proptest` feature).
+//!
+//! `check` runs a property against many generated values. When the property fails, the value is
+//! shrunk, one `Arbitrary::shrink` step at a time, to the smallest value that still fails, and the
+//! test panics with it:
+//!
+//! ```text
+//! check::<Reef, _>(256, 0, |reef| reef.population() <= reef.capacity().unwrap_or(usize::MAX));
+//! ```
+//!
+//! Every generated or shrunk value satisfies the invariants of its type: crab and recipe names
+//! are never empty, crabs on a beach have unique names, and reefs never hold more than their
+//! capacity. The proptest crate itself is not a dependency, so this module provides the same
+//! generate-and-shrink loop with the crate's own random number generators.
+
+use crate::beach::Beach;
+use crate::color::Color;
+use crate::cookbook::Recipe;
+use crate::crab::Crab;
+use crate::diet::Diet;
+use crate::pattern::Pattern;
+use crate::persist::split_record;
+use crate::prey::{Algae, Clam, Detritus, Krill, Minnow, Prey, Shrimp};
+use crate::reef::{Reef, TakePolicy};
+use crate::rng::{seeded, OceanRng};
+use ::rand::Rng;
+use std::fmt::Debug;
+
+/// A type whose values can be generated at random and shrunk towards simpler values.
+pub trait Arbitrary: Sized {
+    fn arbitrary(rng: &mut dyn OceanRng) -> Self;
+
+    /**
+     * Returns simpler values than this one, simplest first. The default has none.
+     */
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+}
+
+/// The most shrinking steps `check` takes before reporting a failure.
+pub const MAX_SHRINK_STEPS: usize = 1000;
+
+/**
+ * Checks `property` against `cases` values generated from `seed`. Panics with the smallest
+ * failing value found by shrinking if the property returns false for any of them.
+ */
+pub fn check<T, P>(cases: usize, seed: u64, property: P)
+where
+    T: Arbitrary + Debug,
+    P: Fn(&T) -> bool,
+{
+    let mut rng = seeded(seed);
+    for case in 0..cases {
+        let value = T::arbitrary(&mut rng);
+        if !property(&value) {
+            let minimal = minimize(value, &property);
+            panic!("property failed for case {} (seed {}); minimal failing value: {:#?}", case, seed, minimal);
+        }
+    }
+}
+
+/**
+ * Shrinks a value for which `property` fails until none of its shrinks fail.
+ */
+pub fn minimize<T, P>(mut value: T, property: P) -> T
+where
+    T: Arbitrary,
+    P: Fn(&T) -> bool,
+{
+    for _ in 0..MAX_SHRINK_STEPS {
+        match value.shrink().into_iter().find(|smaller| !property(smaller)) {
+            Some(smaller) => value = smaller,
+            None => break,
+        }
+    }
+    value
+}
+
+// Halves towards zero, e.g. 10 shrinks to 0, then 5.
+fn shrink_u32(n: u32) -> Vec<u32> {
+    match n {
+        0 => Vec::new(),
+        1 => vec![0],
+        n => vec![0, n / 2],
+    }
+}
+
+// Shortens towards a single character, never to an empty name.
+fn shrink_name(name: &str) -> Vec<String> {
+    let count = name.chars().count();
+    if count <= 1 {
+        return Vec::new();
+    }
+    let mut shorter = vec![name.chars().take(1).collect::<String>()];
+    if count > 2 {
+        shorter.push(name.chars().take(count / 2).collect());
+    }
+    shorter
+}
+
+fn arbitrary_name(rng: &mut dyn OceanRng) -> String {
+    let len = rng.gen_range(1..=8);
+    (0..len).map(|_| rng.gen_range(b'a'..=b'z') as char).collect()
+}
+
+impl Arbitrary for Diet {
+    fn arbitrary(rng: &mut dyn OceanRng) -> Diet {
+        Diet::ALL[rng.gen_range(0..Diet::ALL.len())]
+    }
+
+    /**
+     * Shrinks towards the diets listed first in `Diet::ALL`.
+     */
+    fn shrink(&self) -> Vec<Diet> {
+        Diet::ALL.into_iter().take_while(|diet| diet != self).collect()
+    }
+}
+
+impl Arbitrary for Pattern {
+    fn arbitrary(rng: &mut dyn OceanRng) -> Pattern {
+        [Pattern::Plain, Pattern::Spotted, Pattern::Striped][rng.gen_range(0..3)]
+    }
+
+    fn shrink(&self) -> Vec<Pattern> {
+        match self {
+            Pattern::Plain => Vec::new(),
+            _ => vec![Pattern::Plain],
+        }
+    }
+}
+
+impl Arbitrary for Color {
+    fn arbitrary(rng: &mut dyn OceanRng) -> Color {
+        Color::new(rng.gen(), rng.gen(), rng.gen())
+    }
+
+    /**
+     * Shrinks each component towards 0, one at a time.
+     */
+    fn shrink(&self) -> Vec<Color> {
+        let (r, g, b) = self.to_rgb();
+        let mut smaller = Vec::new();
+        for r in shrink_u32(r as u32) {
+            smaller.push(Color::new(r as u8, g, b));
+        }
+        for g in shrink_u32(g as u32) {
+            smaller.push(Color::new(r, g as u8, b));
+        }
+        for b in shrink_u32(b as u32) {
+            smaller.push(Color::new(r, g, b as u8));
+        }
+        smaller
+    }
+}
+
+// The parts of a crab that generation chooses, so shrinks can rebuild it.
+fn build_crab(name: String, speed: u32, color: Color, diet: Diet, pattern: Pattern) -> Crab {
+    Crab::new(name, speed, color, diet).with_pattern(pattern)
+}
+
+impl Arbitrary for Crab {
+    /**
+     * Generates a new crab (no hunts, offspring, or reefs) with a speed of at most 100.
+     */
+    fn arbitrary(rng: &mut dyn OceanRng) -> Crab {
+        let name = arbitrary_name(rng);
+        let speed = rng.gen_range(0..=100);
+        build_crab(name, speed, Color::arbitrary(rng), Diet::arbitrary(rng), Pattern::arbitrary(rng))
+    }
+
+    fn shrink(&self) -> Vec<Crab> {
+        let (name, speed, color, diet, pattern) =
+            (self.name(), self.speed(), self.color(), self.diet(), self.pattern());
+        let mut smaller = Vec::new();
+        for name in shrink_name(name) {
+            smaller.push(build_crab(name, speed, color.clone(), diet, pattern));
+        }
+        for speed in shrink_u32(speed) {
+            smaller.push(build_crab(name.to_string(), speed, color.clone(), diet, pattern));
+        }
+        for color in color.shrink() {
+            smaller.push(build_crab(name.to_string(), speed, color, diet, pattern));
+        }
+        for diet in diet.shrink() {
+            smaller.push(build_crab(name.to_string(), speed, color.clone(), diet, pattern));
+        }
+        for pattern in pattern.shrink() {
+            smaller.push(build_crab(name.to_string(), speed, color.clone(), diet, pattern));
+        }
+        smaller
+    }
+}
+
+// The kinds of prey ingredients are drawn from.
+const INGREDIENT_KINDS: [&str; 6] = ["Shrimp", "Minnow", "Algae", "Clam", "Krill", "Detritus"];
+
+// Rebuilds a recipe from its parts, leaving out the ingredient at `skip` if given.
+fn rebuild_recipe(recipe: &Recipe, name: String, skip: Option<usize>) -> Recipe {
+    let mut rebuilt = Recipe::new(name, recipe.diet());
+    if let Some(nutrition) = recipe.nutrition() {
+        rebuilt = rebuilt.with_nutrition(nutrition);
+    }
+    if let Some(prep_time) = recipe.prep_time() {
+        rebuilt = rebuilt.with_prep_time(prep_time);
+    }
+    if let Some(difficulty) = recipe.difficulty() {
+        rebuilt = rebuilt.with_difficulty(difficulty);
+    }
+    for (i, (kind, quantity)) in recipe.ingredients().enumerate() {
+        if Some(i) != skip {
+            rebuilt = rebuilt.with_ingredient(kind, quantity);
+        }
+    }
+    rebuilt
+}
+
+impl Arbitrary for Recipe {
+    /**
+     * Generates a recipe with up to three distinct ingredients, each needed one to three times.
+     */
+    fn arbitrary(rng: &mut dyn OceanRng) -> Recipe {
+        let mut recipe = Recipe::new(arbitrary_name(rng), Diet::arbitrary(rng));
+        if rng.gen_bool(0.5) {
+            recipe = recipe.with_nutrition(rng.gen_range(1..=10));
+        }
+        if rng.gen_bool(0.5) {
+            recipe = recipe.with_prep_time(rng.gen_range(5..=120));
+        }
+        if rng.gen_bool(0.5) {
+            recipe = recipe.with_difficulty(rng.gen_range(1..=5));
+        }
+        let count = rng.gen_range(0..=3);
+        for kind in ::rand::seq::index::sample(rng, INGREDIENT_KINDS.len(), count) {
+            recipe = recipe.with_ingredient(INGREDIENT_KINDS[kind], rng.gen_range(1..=3));
+        }
+        recipe
+    }
+
+    /**
+     * Shrinks by dropping an ingredient, dropping the optional fields, or shortening the name.
+     */
+    fn shrink(&self) -> Vec<Recipe> {
+        let mut smaller = Vec::new();
+        for i in 0..self.ingredients().count() {
+            smaller.push(rebuild_recipe(self, self.name().clone(), Some(i)));
+        }
+        let has_extras = self.nutrition().is_some() || self.prep_time().is_some() || self.difficulty().is_some();
+        if has_extras {
+            let mut plain = Recipe::new(self.name().clone(), self.diet());
+            for (kind, quantity) in self.ingredients() {
+                plain = plain.with_ingredient(kind, quantity);
+            }
+            smaller.push(plain);
+        }
+        for name in shrink_name(self.name()) {
+            smaller.push(rebuild_recipe(self, name, None));
+        }
+        smaller
+    }
+}
+
+// Rebuilds a beach from its crabs, keeping their clan memberships.
+fn rebuild_beach<'a, I: IntoIterator<Item = (&'a Crab, Option<&'a str>)>>(crabs: I) -> Beach {
+    let mut beach = Beach::new();
+    let mut members = Vec::new();
+    for (crab, clan) in crabs {
+        beach.add_crab(crab.clone());
+        if let Some(clan) = clan {
+            members.push((clan.to_string(), crab.name().to_string()));
+        }
+    }
+    for (clan, name) in members {
+        beach.add_member_to_clan(&clan, &name);
+    }
+    beach.drain_events();
+    beach
+}
+
+impl Arbitrary for Beach {
+    /**
+     * Generates a beach of up to eight crabs with unique names, each of which may belong to one
+     * of up to three clans.
+     */
+    fn arbitrary(rng: &mut dyn OceanRng) -> Beach {
+        let mut beach = Beach::new();
+        let clans = rng.gen_range(0..=3);
+        for i in 0..rng.gen_range(0..=8) {
+            let crab = Crab::arbitrary(rng);
+            let name = format!("{}{}", crab.name(), i);
+            beach.add_crab(build_crab(name.clone(), crab.speed(), crab.color().clone(), crab.diet(), crab.pattern()));
+            if clans > 0 && rng.gen_bool(0.5) {
+                beach.add_member_to_clan(&format!("clan{}", rng.gen_range(0..clans)), &name);
+            }
+        }
+        beach.drain_events();
+        beach
+    }
+
+    /**
+     * Shrinks by removing a crab, or by taking a crab out of its clan.
+     */
+    fn shrink(&self) -> Vec<Beach> {
+        let crabs: Vec<(&Crab, Option<&str>)> =
+            self.crabs().map(|crab| (crab, self.get_clan_of_crab(crab.name()))).collect();
+        let mut smaller = Vec::new();
+        for i in 0..crabs.len() {
+            smaller.push(rebuild_beach(crabs.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, c)| *c)));
+        }
+        for i in 0..crabs.len() {
+            if crabs[i].1.is_some() {
+                smaller.push(rebuild_beach(
+                    crabs.iter().enumerate().map(|(j, &(crab, clan))| (crab, if j == i { None } else { clan })),
+                ));
+            }
+        }
+        smaller
+    }
+}
+
+fn arbitrary_prey(rng: &mut dyn OceanRng) -> Box<dyn Prey> {
+    match rng.gen_range(0..6) {
+        0 => Box::new(Shrimp::new(rng.gen_range(0..=5))),
+        1 => Box::new(Minnow::new(rng.gen_range(0..=30))),
+        2 => Box::new(Algae::new()),
+        3 => Box::new(Clam::new()),
+        4 => Box::new(Krill::new(rng.gen_range(0..=5))),
+        _ => Box::new(Detritus::new()),
+    }
+}
+
+impl Arbitrary for Reef {
+    /**
+     * Generates a reef of up to twelve built-in prey with any take policy. Half of all reefs have
+     * a capacity, which is never below their population.
+     */
+    fn arbitrary(rng: &mut dyn OceanRng) -> Reef {
+        let mut reef = Reef::new();
+        let policies = [TakePolicy::Fifo, TakePolicy::Lifo, TakePolicy::Random, TakePolicy::SmallestFirst];
+        reef.set_take_policy(policies[rng.gen_range(0..policies.len())]);
+        let population = rng.gen_range(0..=12);
+        for _ in 0..population {
+            reef.add_prey(arbitrary_prey(rng));
+        }
+        if rng.gen_bool(0.5) {
+            reef.set_capacity(Some(rng.gen_range(population..=population + 4)));
+        }
+        reef
+    }
+
+    /**
+     * Shrinks by removing a prey or the capacity. Shrunk reefs start with fresh extraction
+     * history and no depletion callback or regeneration policy.
+     */
+    fn shrink(&self) -> Vec<Reef> {
+        // Generated reefs only hold built-in prey, which can always be saved.
+        let saved = match self.save() {
+            Ok(saved) => saved,
+            Err(_) => return Vec::new(),
+        };
+        let lines: Vec<&str> = saved.lines().collect();
+        let kind = |line: &str| split_record(line)[0].clone();
+        let reload = |kept: Vec<&str>| Reef::load(&kept.join("\n")).ok();
+
+        let mut smaller = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            match kind(line).as_str() {
+                "prey" => {
+                    // Drop the prey and the age record that follows it, if any.
+                    let skip_age = lines.get(i + 1).is_some_and(|next| kind(next) == "age");
+                    let kept = lines
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, _)| *j != i && !(skip_age && *j == i + 1))
+                        .map(|(_, line)| *line)
+                        .collect();
+                    smaller.extend(reload(kept));
+                }
+                "capacity" => {
+                    let kept = lines.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, line)| *line).collect();
+                    smaller.extend(reload(kept));
+                }
+                _ => {}
+            }
+        }
+        smaller
+    }
+}
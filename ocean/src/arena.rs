@@ -0,0 +1,240 @@
+//! Ordered storage with generational handles, used by `Beach` to hold its crabs.
+//!
+//! Items are kept in order, and can be reached by position like in a `Vec`. Every item also gets
+//! a `Handle` when it is added, which keeps pointing at it while other items are added or removed
+//! around it. Once the item itself is removed, its handle goes stale: looking it up returns None,
+//! even after the slot is reused for a new item, instead of silently finding the wrong one.
+
+use std::ops::{Index, IndexMut};
+use std::slice::{Iter, IterMut};
+
+/// A stable reference to an item in an `Arena`, which is only valid until the item is removed.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct Handle {
+    slot: usize,
+    generation: u64,
+}
+
+// One slot of the arena. `position` is where its item is, or None if the slot is free.
+#[derive(Debug, Clone)]
+struct Slot {
+    generation: u64,
+    position: Option<usize>,
+}
+
+/// Items in order, each with a `Handle` that survives other items being added and removed.
+#[derive(Debug, Clone)]
+pub struct Arena<T> {
+    items: Vec<T>,
+    // The slot of each item, by position.
+    owners: Vec<usize>,
+    slots: Vec<Slot>,
+    // Free slots, reused last freed first.
+    free: Vec<usize>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena { items: Vec::new(), owners: Vec::new(), slots: Vec::new(), free: Vec::new() }
+    }
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /**
+     * Makes room for at least `additional` more items without reallocating.
+     */
+    pub fn reserve(&mut self, additional: usize) {
+        self.items.reserve(additional);
+        self.owners.reserve(additional);
+        self.slots.reserve(additional.saturating_sub(self.free.len()));
+    }
+
+    /**
+     * Adds `item` after every other item and returns its handle.
+     */
+    pub fn push(&mut self, item: T) -> Handle {
+        self.insert(self.items.len(), item)
+    }
+
+    /**
+     * Adds `item` at `position`, shifting the items after it along, and returns its handle.
+     * Panics if `position` is greater than the number of items.
+     */
+    pub fn insert(&mut self, position: usize, item: T) -> Handle {
+        assert!(position <= self.items.len(), "insert position {} out of bounds", position);
+        let slot = match self.free.pop() {
+            Some(slot) => slot,
+            None => {
+                self.slots.push(Slot { generation: 0, position: None });
+                self.slots.len() - 1
+            }
+        };
+        self.items.insert(position, item);
+        self.owners.insert(position, slot);
+        self.slots[slot].position = Some(position);
+        self.renumber(position + 1);
+        Handle { slot, generation: self.slots[slot].generation }
+    }
+
+    /**
+     * Removes the item at `position`, shifting the items after it back, and makes its handle
+     * stale. Panics if `position` is out of bounds.
+     */
+    pub fn remove(&mut self, position: usize) -> T {
+        let item = self.items.remove(position);
+        let slot = self.owners.remove(position);
+        self.slots[slot].generation += 1;
+        self.slots[slot].position = None;
+        self.free.push(slot);
+        self.renumber(position);
+        item
+    }
+
+    /**
+     * Removes the items at `positions`, which must be in increasing order, and returns them in
+     * that order, making their handles stale. Unlike calling `remove` for each, this shifts the
+     * remaining items back and tells them where they are once, however many are removed. Panics
+     * if a position is out of bounds or out of order.
+     */
+    pub fn remove_many(&mut self, positions: &[usize]) -> Vec<T> {
+        let Some(&first) = positions.first() else {
+            return Vec::new();
+        };
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "positions to remove must be in increasing order");
+        let last = positions[positions.len() - 1];
+        assert!(last < self.items.len(), "remove position {} out of bounds", last);
+        let removed = extract_positions(&mut self.items, positions);
+        for slot in extract_positions(&mut self.owners, positions) {
+            self.slots[slot].generation += 1;
+            self.slots[slot].position = None;
+            self.free.push(slot);
+        }
+        self.renumber(first);
+        removed
+    }
+
+    /**
+     * Removes the item `handle` points at, or returns None if the handle is stale.
+     */
+    pub fn remove_handle(&mut self, handle: Handle) -> Option<T> {
+        self.position(handle).map(|position| self.remove(position))
+    }
+
+    /**
+     * Removes and returns the last item, if any.
+     */
+    pub fn pop(&mut self) -> Option<T> {
+        if self.items.is_empty() {
+            return None;
+        }
+        Some(self.remove(self.items.len() - 1))
+    }
+
+    // Tells the slots of the items from `start` onwards where their items now are.
+    fn renumber(&mut self, start: usize) {
+        for (position, &slot) in self.owners.iter().enumerate().skip(start) {
+            self.slots[slot].position = Some(position);
+        }
+    }
+
+    /**
+     * Returns the current position of the item `handle` points at, or None if the handle is
+     * stale.
+     */
+    pub fn position(&self, handle: Handle) -> Option<usize> {
+        let slot = self.slots.get(handle.slot)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.position
+    }
+
+    /**
+     * Returns the handle of the item at `position`, or None if it is out of bounds.
+     */
+    pub fn handle(&self, position: usize) -> Option<Handle> {
+        let slot = *self.owners.get(position)?;
+        Some(Handle { slot, generation: self.slots[slot].generation })
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    /**
+     * Returns the item `handle` points at, or None if the handle is stale.
+     */
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).map(|position| &self.items[position])
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.position(handle).map(|position| &mut self.items[position])
+    }
+
+    /**
+     * Returns the items in order.
+     */
+    pub fn as_slice(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        self.items.iter_mut()
+    }
+}
+
+impl<T> IntoIterator for Arena<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    /**
+     * Consumes the arena, yielding its items in order.
+     */
+    fn into_iter(self) -> std::vec::IntoIter<T> {
+        self.items.into_iter()
+    }
+}
+
+impl<T> Index<usize> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, position: usize) -> &T {
+        &self.items[position]
+    }
+}
+
+impl<T> IndexMut<usize> for Arena<T> {
+    fn index_mut(&mut self, position: usize) -> &mut T {
+        &mut self.items[position]
+    }
+}
+
+// Takes the elements at `positions`, which are in increasing order, out of `items` in one pass.
+fn extract_positions<T>(items: &mut Vec<T>, positions: &[usize]) -> Vec<T> {
+    let mut wanted = positions.iter().copied().peekable();
+    let mut position = 0;
+    items
+        .extract_if(.., |_| {
+            let hit = wanted.next_if_eq(&position).is_some();
+            position += 1;
+            hit
+        })
+        .collect()
+}
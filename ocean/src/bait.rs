@@ -0,0 +1,91 @@
+//! Bait that crabs hunt with (see `Crab::hunt_with_bait`).
+//!
+//! Bait lures prey of the kinds it is made from: a baited hunt goes after those kinds first,
+//! reef by reef, before any other prey the crab will eat. It also holds prey's attention, so
+//! each attempt a prey makes to escape fails `Bait::hold` of the time however it would have
+//! gone. A hunt uses its bait up, whether or not it catches anything.
+//!
+//! Crabs make bait from their larders (see `Crab::larder`): `Crab::craft_bait` cooks a recipe
+//! into bait that lures every kind of prey in it, and `Crab::cut_bait` cuts up a single prey,
+//! which makes weaker bait. Bait found some other way, e.g. handed out by a scenario, can be
+//! made with `Bait::new`.
+
+use crate::cookbook::Recipe;
+
+/// How often bait crafted from a recipe keeps prey from escaping.
+pub const RECIPE_BAIT_HOLD: f64 = 0.5;
+
+/// How often bait cut from a single prey keeps prey from escaping.
+pub const PREY_BAIT_HOLD: f64 = 0.25;
+
+/// A bait item, used up by the hunt it is used in.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bait {
+    name: String,
+    // The kinds of prey (see `Prey::kind`) the bait lures.
+    lures: Vec<String>,
+    hold: f64,
+}
+
+impl Bait {
+    /**
+     * Creates bait that keeps prey from escaping `hold` of the time (clamped between 0 and 1),
+     * and lures no kind of prey in particular.
+     */
+    pub fn new(name: String, hold: f64) -> Bait {
+        Bait { name, lures: Vec::new(), hold: hold.clamp(0.0, 1.0) }
+    }
+
+    /**
+     * Returns this bait, also luring prey of the given kind.
+     */
+    pub fn with_lure(mut self, kind: &str) -> Bait {
+        if !self.attracts(kind) {
+            self.lures.push(kind.to_string());
+        }
+        self
+    }
+
+    /**
+     * Returns the bait a recipe makes: named after it, luring the kinds of prey among its
+     * ingredients, and holding prey `RECIPE_BAIT_HOLD` of the time.
+     */
+    pub fn from_recipe(recipe: &Recipe) -> Bait {
+        recipe
+            .ingredients()
+            .fold(Bait::new(recipe.name().clone(), RECIPE_BAIT_HOLD), |bait, (kind, _)| bait.with_lure(kind))
+    }
+
+    /**
+     * Returns the bait a single prey of the given kind makes: luring its own kind, and holding
+     * prey `PREY_BAIT_HOLD` of the time.
+     */
+    pub fn from_prey(kind: &str) -> Bait {
+        Bait::new(format!("{} bait", kind), PREY_BAIT_HOLD).with_lure(kind)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+     * Returns the kinds of prey this bait lures, in the order they were added.
+     */
+    pub fn lures(&self) -> impl Iterator<Item = &str> {
+        self.lures.iter().map(String::as_str)
+    }
+
+    /**
+     * Returns true if this bait lures prey of the given kind.
+     */
+    pub fn attracts(&self, kind: &str) -> bool {
+        self.lures.iter().any(|lure| lure == kind)
+    }
+
+    /**
+     * Returns how often this bait keeps a prey from escaping, from 0 to 1.
+     */
+    pub fn hold(&self) -> f64 {
+        self.hold
+    }
+}
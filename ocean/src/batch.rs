@@ -0,0 +1,193 @@
+//! Headless batch runs: the same starting ocean simulated once per seed, for parameter sweeps
+//! that run unattended.
+//!
+//! ```text
+//! let config = BatchConfig::new(&world, 500)?.with_config(SimConfig::load("sweep.toml")?);
+//! let runs = run_batch(&config, &[1, 2, 3, 4])?;
+//! fs::write("runs.csv", to_csv(&runs))?;
+//! ```
+//!
+//! Each run restores the starting ocean from a snapshot (see `Simulation::snapshot`), applies
+//! the config with the run's seed in place of the config's own (see `Simulation::with_config`),
+//! and steps it to the tick limit. `ocean-cli batch` does the same from the command line.
+
+use crate::config::SimConfig;
+use crate::error::OceanError;
+use crate::rng::seeded;
+use crate::simulation::Simulation;
+
+/// The CSV columns `to_csv` writes, in order.
+pub const CSV_COLUMNS: [&str; 13] = [
+    "seed",
+    "ticks",
+    "population",
+    "average_speed",
+    "prey",
+    "biodiversity",
+    "hunts",
+    "catches",
+    "crabs_eaten",
+    "crabs_spawned",
+    "crabs_died",
+    "crabs_starved",
+    "crabs_migrated",
+];
+
+/// What every run of a batch starts from and how long it runs.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BatchConfig {
+    world: String,
+    config: SimConfig,
+    ticks: u64,
+}
+
+impl BatchConfig {
+    /**
+     * Runs start from the ocean of `world` as it is now, with the default config, for `ticks`
+     * steps each.
+     *
+     * Returns an Err if the world cannot be snapshotted (see `Simulation::snapshot`).
+     */
+    pub fn new(world: &Simulation, ticks: u64) -> Result<BatchConfig, OceanError> {
+        Ok(BatchConfig { world: world.snapshot()?, config: SimConfig::default(), ticks })
+    }
+
+    /**
+     * Like `new`, but runs start from the ocean saved in a simulation snapshot, e.g. the
+     * contents of a file written by `Simulation::save`.
+     *
+     * Returns an Err if the text is not a valid simulation snapshot.
+     */
+    pub fn from_snapshot(text: &str, ticks: u64) -> Result<BatchConfig, OceanError> {
+        Simulation::from_snapshot(text, Box::new(seeded(0)))?;
+        Ok(BatchConfig { world: text.to_string(), config: SimConfig::default(), ticks })
+    }
+
+    /**
+     * Applies `config` to every run. Its seed is ignored in favor of each run's.
+     */
+    pub fn with_config(mut self, config: SimConfig) -> BatchConfig {
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+/// The summary metrics of one run of a batch, taken at its tick limit.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RunSummary {
+    pub seed: u64,
+    /** The number of steps the run took. */
+    pub ticks: u64,
+    /** Crabs alive at the end of the run. */
+    pub population: usize,
+    /** The average speed of the crabs alive at the end, or None if there are none. */
+    pub average_speed: Option<f64>,
+    /** Prey left on every reef at the end of the run. */
+    pub prey: usize,
+    /** See `Ocean::biodiversity`. */
+    pub biodiversity: f64,
+    /** Totals over every step of the run (see `StepReport`). */
+    pub hunts: usize,
+    pub catches: usize,
+    pub crabs_eaten: usize,
+    pub crabs_spawned: usize,
+    pub crabs_died: usize,
+    pub crabs_starved: usize,
+    pub crabs_migrated: usize,
+}
+
+impl RunSummary {
+    /**
+     * Returns the summary's fields as CSV cells, in the order of `CSV_COLUMNS`.
+     */
+    pub fn to_csv_row(&self) -> String {
+        let cells = [
+            self.seed.to_string(),
+            self.ticks.to_string(),
+            self.population.to_string(),
+            self.average_speed.map_or(String::new(), |speed| speed.to_string()),
+            self.prey.to_string(),
+            self.biodiversity.to_string(),
+            self.hunts.to_string(),
+            self.catches.to_string(),
+            self.crabs_eaten.to_string(),
+            self.crabs_spawned.to_string(),
+            self.crabs_died.to_string(),
+            self.crabs_starved.to_string(),
+            self.crabs_migrated.to_string(),
+        ];
+        cells.join(",")
+    }
+}
+
+/**
+ * Runs one simulation per seed in `seeds`, in order, and returns their summaries. The same
+ * config and seeds always give the same summaries.
+ *
+ * Returns an Err, and no summaries, if the config does not fit the starting ocean (see
+ * `Simulation::with_config`).
+ */
+pub fn run_batch(config: &BatchConfig, seeds: &[u64]) -> Result<Vec<RunSummary>, OceanError> {
+    seeds.iter().map(|&seed| run_one(config, seed)).collect()
+}
+
+/**
+ * Writes `runs` as CSV: a header of `CSV_COLUMNS` and one row per run.
+ */
+pub fn to_csv(runs: &[RunSummary]) -> String {
+    let mut out = CSV_COLUMNS.join(",");
+    out.push('\n');
+    for run in runs {
+        out.push_str(&run.to_csv_row());
+        out.push('\n');
+    }
+    out
+}
+
+// Runs the batch's simulation once with `seed`.
+fn run_one(config: &BatchConfig, seed: u64) -> Result<RunSummary, OceanError> {
+    let ocean = Simulation::from_snapshot(&config.world, Box::new(seeded(seed)))?.into_ocean();
+    let sim_config = SimConfig { seed, ..config.config.clone() };
+    let mut simulation = Simulation::with_config(ocean, &sim_config)?;
+    let mut summary = RunSummary {
+        seed,
+        ticks: config.ticks,
+        population: 0,
+        average_speed: None,
+        prey: 0,
+        biodiversity: 0.0,
+        hunts: 0,
+        catches: 0,
+        crabs_eaten: 0,
+        crabs_spawned: 0,
+        crabs_died: 0,
+        crabs_starved: 0,
+        crabs_migrated: 0,
+    };
+    for report in simulation.run(config.ticks) {
+        summary.hunts += report.hunts;
+        summary.catches += report.catches;
+        summary.crabs_eaten += report.crabs_eaten;
+        summary.crabs_spawned += report.crabs_spawned;
+        summary.crabs_died += report.crabs_died;
+        summary.crabs_starved += report.crabs_starved;
+        summary.crabs_migrated += report.crabs_migrated;
+    }
+    let ocean = simulation.ocean();
+    summary.population = ocean.total_population();
+    if summary.population > 0 {
+        let total: f64 = ocean.beaches().flat_map(|beach| beach.crabs()).map(|crab| crab.speed_f()).sum();
+        summary.average_speed = Some(total / summary.population as f64);
+    }
+    summary.prey = ocean.reefs().map(|reef| reef.borrow().population()).sum();
+    summary.biodiversity = ocean.biodiversity();
+    Ok(summary)
+}
@@ -1,23 +1,175 @@
+use crate::arena::{Arena, Handle};
+use crate::crab::{Crab, CrabId, Detached, Stage};
 use crate::color::Color;
-use crate::crab::Crab;
+use crate::clans::{ClanSystem, GroupHunt, SharingRule, SpeedDistribution, RAID_LUCK, RAID_MEMBER_STRENGTH};
 use crate::diet::Diet;
-use crate::clans::ClanSystem;
-use std::slice::Iter;
+use crate::disease::Disease;
+use crate::error::OceanError;
+use crate::events::{self, OceanEvent};
+use crate::hooks::{BeachHook, Phase};
+use crate::feeding::{FeedingOrder, FeedingOutcome};
+use crate::intern::{Interner, NameId};
+use crate::leaderboard::{LeaderboardEntry, RankBy};
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::position::Position;
+use crate::population::{PopulationChange, PopulationPolicy, WILD_SPEEDS};
+use crate::predator::Predator;
+use crate::prey::Carcass;
+use crate::query::{CrabQuery, NameSearch};
+use crate::reef::Reef;
+use crate::registry::ReefId;
+use crate::rng::{seeded, OceanRng};
+use crate::summary::LifeSummary;
+use crate::trace;
+use crate::zone::Zone;
+use rand::{Rng, RngCore};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::io::BufRead;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+use std::slice::{Iter, IterMut};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// How many changes a beach remembers for `Beach::undo` unless told otherwise (see
+/// `Beach::set_undo_depth`).
+pub const DEFAULT_UNDO_DEPTH: usize = 100;
 
 #[derive(Debug)]
 pub struct Beach {
-    crabs: Vec<Crab>,
-    clan_system: ClanSystem
+    name: Option<String>,
+    crabs: Arena<Crab>,
+    clan_system: ClanSystem,
+    hall_of_fame: Vec<LifeSummary>,
+    events: Vec<OceanEvent>,
+    // Channels that get every event as it is queued (see `subscribe`), and how many of the events
+    // queued so far have been sent to them.
+    subscribers: Vec<Sender<OceanEvent>>,
+    notified: usize,
+    // Indices of the crabs with each name, in beach order. Names never change after a crab is
+    // created, and speeds only when it finishes molting, so only adding and removing crabs and
+    // molts invalidate this and `fastest`. Names are interned in `names`, which keeps every name a
+    // crab here has had.
+    name_index: HashMap<NameId, Vec<usize>>,
+    names: Interner,
+    // Index of the crab `get_fastest_crab` returns: the last of the fastest crabs.
+    fastest: Option<usize>,
+    // The parents of every crab that has lived on this beach, buried ones included.
+    lineage: HashMap<CrabId, Option<(CrabId, CrabId)>>,
+    // The id the next crab added or bred here gets. Ids only grow, so parents have lower ids than
+    // their children.
+    next_id: CrabId,
+    breeding_allowed: bool,
+    breeding_limit: Option<usize>,
+    breeding_cooldown: u32,
+    // Energy every crab burns each step, how many steps in a row a crab can go without energy
+    // before it starves, and whether crabs that starved are removed on the following step.
+    metabolism: u32,
+    starvation_limit: Option<u32>,
+    remove_dead: bool,
+    // The carcasses of crabs that died here, with how many steps each has lain on the beach, and
+    // how many steps carcasses last before they rot away, or None if dead crabs leave none.
+    carcasses: Vec<(Carcass, u32)>,
+    carcass_decay: Option<u32>,
+    life_stages_enforced: bool,
+    // Crabs more closely related than this cannot breed (see `relatedness`).
+    max_breeding_relatedness: Option<f64>,
+    feeding_order: FeedingOrder,
+    predators: Vec<Box<dyn Predator>>,
+    hooks: Vec<Box<dyn BeachHook>>,
+    population_policy: PopulationPolicy,
+    tide_line: Option<f64>,
+    // How far crabs can sense reefs they do not know yet, or None if they only learn of reefs by
+    // being shown them.
+    sensory_radius: Option<f64>,
+    // The color of the beach outside its zones, or of zones without their own background.
+    background: Option<Color>,
+    // The beach's zones, in the order they were added, and the position in `zones` of the zone
+    // each crab is in. Crabs outside every zone have no entry.
+    zones: Vec<Zone>,
+    crab_zones: HashMap<Handle, usize>,
+    // Changes `undo` can take back, oldest first, and changes it has taken back, for `redo`. At
+    // most `undo_depth` changes are kept.
+    undo_stack: VecDeque<Change>,
+    redo_stack: Vec<Change>,
+    undo_depth: usize,
+}
+
+// A change made by one of the beach's undoable operations (see `Beach::undo`), holding what is
+// needed to take it back and to make it again.
+#[derive(Debug)]
+enum Change {
+    // A crab was added to the end of the beach, bred from the crabs at the given indices if it
+    // has parents. `next_id` is the beach's next id from before the crab arrived, and `crab` holds
+    // the crab while the change is undone.
+    Added { parents: Option<(usize, usize)>, next_id: CrabId, crab: Option<Crab> },
+    // A crab joined a clan, leaving the clan in `previous`, which held it as the given copy.
+    // `created` is true if joining created the clan.
+    Joined { clan: String, crab: String, previous: Option<(String, Crab)>, created: bool },
+    // The crab at `index` was buried, leaving the clan in `clan`, which held it as the given copy,
+    // and the zone at position `zone`.
+    Buried { index: usize, crab: Box<Crab>, clan: Option<(String, Crab)>, zone: Option<usize> },
 }
 
 impl Beach {
     pub fn new() -> Beach {
         Beach {
-            crabs: Vec::new(),
-            clan_system: ClanSystem::new()
+            name: None,
+            crabs: Arena::new(),
+            clan_system: ClanSystem::new(),
+            hall_of_fame: Vec::new(),
+            events: Vec::new(),
+            subscribers: Vec::new(),
+            notified: 0,
+            name_index: HashMap::new(),
+            names: Interner::new(),
+            fastest: None,
+            lineage: HashMap::new(),
+            next_id: 1,
+            breeding_allowed: true,
+            breeding_limit: None,
+            breeding_cooldown: 0,
+            metabolism: 0,
+            starvation_limit: None,
+            remove_dead: true,
+            carcasses: Vec::new(),
+            carcass_decay: None,
+            life_stages_enforced: false,
+            max_breeding_relatedness: None,
+            feeding_order: FeedingOrder::BeachOrder,
+            predators: Vec::new(),
+            hooks: Vec::new(),
+            population_policy: PopulationPolicy::new(),
+            tide_line: None,
+            sensory_radius: None,
+            background: None,
+            zones: Vec::new(),
+            crab_zones: HashMap::new(),
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            undo_depth: DEFAULT_UNDO_DEPTH,
+        }
+    }
+
+    /**
+     * Creates a new beach with a human-readable name, e.g. for `Ocean::beach_by_name`.
+     */
+    pub fn with_name(name: String) -> Beach {
+        Beach {
+            name: Some(name),
+            ..Beach::new()
         }
     }
 
+    /**
+     * Returns the beach's name, or None if it is anonymous.
+     */
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     /**
      * Returns the number of crabs on the beach.
      */
@@ -33,38 +185,523 @@ impl Beach {
      *     - The newly added crab should be at the END of the collection.
      */
     pub fn add_crab(&mut self, crab: Crab) {
+        let next_id = self.next_id;
+        let crab = self.with_new_id(crab);
+        self.push_crab(crab);
+        self.record(Change::Added { parents: None, next_id, crab: None });
+    }
+
+    /**
+     * Adds every crab in `crabs` to the end of the beach, in order, as `add_crab` would one at a
+     * time, but making room for them all at once. Each can be undone on its own (see `undo`).
+     */
+    pub fn add_crabs<I: IntoIterator<Item = Crab>>(&mut self, crabs: I) {
+        let crabs = crabs.into_iter();
+        let (additional, _) = crabs.size_hint();
+        self.crabs.reserve(additional);
+        self.lineage.reserve(additional);
+        self.undo_stack.reserve(additional.min(self.undo_depth));
+        for crab in crabs {
+            self.add_crab(crab);
+        }
+    }
+
+    // Gives a crab arriving on the beach the next id. Its parents are kept only if both are
+    // known here; otherwise the crab starts a line of its own.
+    fn with_new_id(&mut self, mut crab: Crab) -> Crab {
+        let parents = crab
+            .parents()
+            .filter(|(p1, p2)| self.lineage.contains_key(p1) && self.lineage.contains_key(p2));
+        crab.set_lineage(self.next_id, parents);
+        self.next_id += 1;
+        crab
+    }
+
+    // Adds a crab to the end of the beach, keeping the lookup caches up to date.
+    fn push_crab(&mut self, crab: Crab) {
+        self.lineage.insert(crab.id(), crab.parents());
+        self.next_id = self.next_id.max(crab.id() + 1);
         self.crabs.push(crab);
+        self.index_crab(self.crabs.len() - 1);
+    }
+
+    // Rebuilds the lookup caches after crabs have moved, e.g. when one was removed.
+    fn reindex(&mut self) {
+        self.name_index.clear();
+        self.fastest = None;
+        for index in 0..self.crabs.len() {
+            self.index_crab(index);
+        }
+    }
+
+    // Adds the crab at `index`, which must come after every crab already indexed, to the caches.
+    fn index_crab(&mut self, index: usize) {
+        let crab = &self.crabs[index];
+        let name = self.names.intern(crab.name());
+        self.name_index.entry(name).or_default().push(index);
+        if self.fastest.is_none_or(|fastest| crab.speed() >= self.crabs[fastest].speed()) {
+            self.fastest = Some(index);
+        }
     }
 
     pub fn get_crab(&self, index: usize) -> &Crab {
         return &self.crabs[index];
     }
 
-    pub fn crabs(&self) -> Iter<Crab> {
+    /**
+     * Returns the crab at `index` mutably, e.g. to record its recipe ratings. The beach's name
+     * index and fastest crab are brought up to date when the returned guard is dropped, so the
+     * crab may even be replaced outright.
+     * Panics if the index is out of bounds.
+     */
+    pub fn get_crab_mut(&mut self, index: usize) -> CrabMut<'_> {
+        CrabMut::new(self, index)
+    }
+
+    /**
+     * Like `get_crab`, but returns `OceanError::CrabIndexOutOfBounds` instead of panicking.
+     */
+    pub fn try_get_crab(&self, index: usize) -> Result<&Crab, OceanError> {
+        self.check_index(index)?;
+        Ok(&self.crabs[index])
+    }
+
+    /**
+     * Like `get_crab_mut`, but returns `OceanError::CrabIndexOutOfBounds` instead of panicking.
+     */
+    pub fn try_get_crab_mut(&mut self, index: usize) -> Result<CrabMut<'_>, OceanError> {
+        self.check_index(index)?;
+        Ok(CrabMut::new(self, index))
+    }
+
+    /**
+     * Returns a handle to the crab at `index`, which keeps pointing at that crab as others are
+     * added and removed (see `crate::arena`), or None if the index is out of bounds.
+     */
+    pub fn crab_handle(&self, index: usize) -> Option<Handle> {
+        self.crabs.handle(index)
+    }
+
+    /**
+     * Returns the crab `handle` points at, or None if it has left the beach since. A crab brought
+     * back by `undo` gets a new handle.
+     */
+    pub fn get_crab_by_handle(&self, handle: Handle) -> Option<&Crab> {
+        self.crabs.get(handle)
+    }
+
+    pub fn get_crab_by_handle_mut(&mut self, handle: Handle) -> Option<CrabMut<'_>> {
+        let index = self.crabs.position(handle)?;
+        Some(CrabMut::new(self, index))
+    }
+
+    /**
+     * Like `get_crab_mut`, but finds the crab by its id (see `Crab::id`), returning None if no
+     * crab on the beach has it.
+     */
+    pub fn get_crab_by_id_mut(&mut self, id: CrabId) -> Option<CrabMut<'_>> {
+        let index = self.find_crab_by_id(id)?;
+        Some(CrabMut::new(self, index))
+    }
+
+    /**
+     * Returns the current index of the crab `handle` points at, or None if it has left the
+     * beach since.
+     */
+    pub fn find_crab_by_handle(&self, handle: Handle) -> Option<usize> {
+        self.crabs.position(handle)
+    }
+
+    pub fn crabs(&self) -> Iter<'_, Crab> {
         return self.crabs.iter();
     }
 
+    /**
+     * Returns the beach's crabs mutably, to iterate over with `CrabsMut::iter_mut` or a `for`
+     * loop over `&mut beach.crabs_mut()`. Like `get_crab_mut`, the beach brings its caches and
+     * clans up to date when the returned guard is dropped.
+     */
+    pub fn crabs_mut(&mut self) -> CrabsMut<'_> {
+        let before = self.crabs.iter().map(|crab| (crab.name().to_string(), crab.speed(), crab.is_hibernating())).collect();
+        CrabsMut { beach: self, before }
+    }
+
+    // The crabs, mutably, for hunts, which neither rename crabs nor change their speed, so the
+    // beach's caches and clans stay up to date without `crabs_mut`'s guard.
+    pub(crate) fn hunters_mut(&mut self) -> IterMut<'_, Crab> {
+        self.crabs.iter_mut()
+    }
+
+    /**
+     * Renames the crab at `index`, keeping its place in its clan and zone. Returns
+     * `OceanError::CrabIndexOutOfBounds`, or `OceanError::DuplicateName` if another crab on the
+     * beach has the name already. Renaming clears the undo history, which refers to crabs by name.
+     */
+    pub fn rename_crab(&mut self, index: usize, name: &str) -> Result<(), OceanError> {
+        self.check_index(index)?;
+        if self.find_crab_index(name).is_some_and(|other| other != index) {
+            return Err(OceanError::DuplicateName { kind: "crab", name: name.to_string() });
+        }
+        self.get_crab_mut(index).set_name(name);
+        Ok(())
+    }
+
+    // Brings the clan system's copy of the crab at `index`, which was called `old_name`, up to
+    // date after the crab changed. A renamed crab whose new name another clan member has already
+    // leaves its clan. Renames clear the undo history, which refers to crabs by name.
+    fn sync_clan_member(&mut self, old_name: &str, index: usize) {
+        let crab = self.crabs[index].clone();
+        let renamed = crab.name() != old_name;
+        let name = crab.name().to_string();
+        if !self.clan_system.update_member(old_name, &name, crab) && renamed {
+            self.clan_system.remove_member(old_name);
+        }
+        if renamed {
+            self.clear_history();
+        }
+        self.sync_crab_clans();
+        self.notify();
+    }
+
+    /**
+     * Starts a query over the beach's crabs, e.g. `beach.query().min_speed(5).in_clan("reds")`
+     * (see `crate::query`).
+     */
+    pub fn query(&self) -> CrabQuery<'_> {
+        CrabQuery::new(self)
+    }
+
     /**
      * Returns:
      *   - None if the beach is empty.
      *   - Some of a reference to the Crab with the highest speed.
      */
     pub fn get_fastest_crab(&self) -> Option<&Crab> {
-        return self.crabs.iter().max_by_key(|crab| crab.speed());
+        return self.fastest.map(|index| &self.crabs[index]);
+    }
 
+    /**
+     * Returns up to `n` crabs ranked by `criteria`, highest first: by the first criterion, then
+     * by the next among crabs that tie, and so on. Crabs that tie on every criterion keep their
+     * beach order.
+     */
+    pub fn leaderboard(&self, criteria: &[RankBy], n: usize) -> Vec<LeaderboardEntry> {
+        let score = |crab: &Crab, by: RankBy| -> u64 {
+            match by {
+                RankBy::Speed => crab.speed() as u64,
+                RankBy::Hunts => crab.hunts() as u64,
+                RankBy::Energy => crab.energy() as u64,
+                RankBy::SuccessRate => (crab.hunt_stats().success_rate() * 100.0).round() as u64,
+                RankBy::Offspring => crab.offspring() as u64,
+                RankBy::Age => crab.age() as u64,
+                RankBy::ClanReputation => self
+                    .clan_system
+                    .get_clan_of_member(crab.name())
+                    .map_or(0, |clan_id| self.clan_system.reputation(clan_id) as u64),
+            }
+        };
+        let mut ranked: Vec<(usize, Vec<(RankBy, u64)>)> = self
+            .crabs
+            .iter()
+            .enumerate()
+            .map(|(index, crab)| (index, criteria.iter().map(|&by| (by, score(crab, by))).collect()))
+            .collect();
+        // A stable sort, so crabs that tie stay in beach order.
+        ranked.sort_by(|(_, a), (_, b)| b.iter().map(|(_, score)| score).cmp(a.iter().map(|(_, score)| score)));
+        ranked
+            .into_iter()
+            .take(n)
+            .enumerate()
+            .map(|(place, (index, scores))| LeaderboardEntry {
+                rank: place + 1,
+                index,
+                crab: self.crabs[index].name().to_string(),
+                scores,
+            })
+            .collect()
     }
 
     /**
      * Returns a vector of references to the crabs with a given name.
      */
     pub fn find_crabs_by_name(&self, name: &str) -> Vec<&Crab> {
-        let mut crabs_with_name: Vec<&Crab> = Vec::new();
+        match self.names.get(name).and_then(|name| self.name_index.get(&name)) {
+            Some(indices) => indices.iter().map(|&index| &self.crabs[index]).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /**
+     * Like `find_crabs_by_name`, but names are compared under `search`, e.g.
+     * `beach.find_crabs_by_name_with("ferr", NameSearch::new().ignore_case().with_mode(NameMatch::Prefix))`.
+     * Crabs are returned in beach order.
+     */
+    pub fn find_crabs_by_name_with(&self, name: &str, search: NameSearch) -> Vec<&Crab> {
+        self.find_crabs_by(|crab| search.matches(name, crab.name()))
+    }
+
+    /**
+     * Returns the crabs `predicate` holds for, in beach order, e.g.
+     * `beach.find_crabs_by(|crab| (3..=7).contains(&crab.speed()) && beach.get_clan_of_crab(crab.name()).is_none())`.
+     */
+    pub fn find_crabs_by<F: FnMut(&Crab) -> bool>(&self, mut predicate: F) -> Vec<&Crab> {
+        self.crabs.iter().filter(|crab| predicate(crab)).collect()
+    }
+
+    /**
+     * Like `find_crabs_by`, but returns the indices of the crabs instead, e.g. for `bury_crab`.
+     */
+    pub fn find_crab_indices_by<F: FnMut(&Crab) -> bool>(&self, mut predicate: F) -> Vec<usize> {
+        (0..self.crabs.len()).filter(|&index| predicate(&self.crabs[index])).collect()
+    }
+
+    /**
+     * Returns the index of the first crab with the given name, or None if there is none.
+     */
+    pub fn find_crab_index(&self, name: &str) -> Option<usize> {
+        self.name_index.get(&self.names.get(name)?)?.first().copied()
+    }
+
+    /**
+     * Returns up to `n` distinct crabs chosen uniformly at random using `rng`,
+     * or every crab (in random order) if the beach has fewer than `n`.
+     *
+     * Pass a seeded generator (e.g. `rand_pcg::Pcg64::seed_from_u64`) for reproducible samples.
+     */
+    pub fn sample_crabs<R: Rng + ?Sized>(&self, rng: &mut R, n: usize) -> Vec<&Crab> {
+        let amount = n.min(self.crabs.len());
+        rand::seq::index::sample(rng, self.crabs.len(), amount)
+            .into_iter()
+            .map(|i| &self.crabs[i])
+            .collect()
+    }
+
+    /**
+     * Groups the crabs into strata using `stratum` (crabs for which it returns None are left out),
+     * then samples up to `n` crabs from each stratum as `sample_crabs` does. Strata are sampled in
+     * the order their first crabs stand on the beach, so the same seed gives the same sample.
+     */
+    pub fn sample_crabs_stratified<R, K, F>(&self, rng: &mut R, n: usize, stratum: F) -> HashMap<K, Vec<&Crab>>
+    where
+        R: Rng + ?Sized,
+        K: Eq + Hash + Clone,
+        F: Fn(&Crab) -> Option<K>,
+    {
+        // Keys in the order they were first seen, since a HashMap's own order varies between runs.
+        let mut keys = Vec::new();
+        let mut strata: HashMap<K, Vec<&Crab>> = HashMap::new();
+        for crab in self.crabs.iter() {
+            if let Some(key) = stratum(crab) {
+                let members = strata.entry(key.clone()).or_insert_with(|| {
+                    keys.push(key);
+                    Vec::new()
+                });
+                members.push(crab);
+            }
+        }
+        for key in keys.iter() {
+            let members = strata.get_mut(key).unwrap();
+            let amount = n.min(members.len());
+            let chosen = rand::seq::index::sample(rng, members.len(), amount);
+            *members = chosen.into_iter().map(|i| members[i]).collect();
+        }
+        strata
+    }
+
+    /**
+     * Samples up to `n` crabs of each diet present on the beach.
+     */
+    pub fn sample_crabs_by_diet<R: Rng + ?Sized>(&self, rng: &mut R, n: usize) -> HashMap<Diet, Vec<&Crab>> {
+        self.sample_crabs_stratified(rng, n, |crab| Some(crab.diet()))
+    }
+
+    /**
+     * Samples up to `n` crabs from each clan. Crabs that belong to no clan are not sampled.
+     */
+    pub fn sample_crabs_by_clan<R: Rng + ?Sized>(&self, rng: &mut R, n: usize) -> HashMap<String, Vec<&Crab>> {
+        self.sample_crabs_stratified(rng, n, |crab| {
+            self.clan_system.get_clan_of_member(crab.name()).map(str::to_string)
+        })
+    }
+
+    /**
+     * Returns the order crabs hunt in during a `feeding_round`.
+     */
+    pub fn feeding_order(&self) -> FeedingOrder {
+        self.feeding_order
+    }
+
+    pub fn set_feeding_order(&mut self, order: FeedingOrder) {
+        self.feeding_order = order;
+    }
+
+    /**
+     * Has every crab hunt once with `Crab::hunt`, in the beach's feeding order (see
+     * `set_feeding_order`), so crabs that share reefs compete for their prey.
+     *
+     * Returns how each crab fared, in the order they hunted.
+     */
+    pub fn feeding_round(&mut self) -> Vec<FeedingOutcome> {
+        self.sync_crab_clans();
+        self.sync_camouflage();
+        let mut order: Vec<usize> = (0..self.crabs.len()).collect();
+        match self.feeding_order {
+            FeedingOrder::BeachOrder => {}
+            FeedingOrder::FastestFirst => order.sort_by_key(|&index| std::cmp::Reverse(self.crabs[index].effective_speed())),
+            FeedingOrder::SlowestFirst => order.sort_by_key(|&index| self.crabs[index].effective_speed()),
+            FeedingOrder::HungriestFirst => order.sort_by_key(|&index| self.crabs[index].energy()),
+        }
+        let outcomes = order
+            .into_iter()
+            .map(|index| {
+                let crab = &mut self.crabs[index];
+                let energy = crab.energy();
+                let caught = crab.hunt();
+                FeedingOutcome { index, crab: crab.name().to_string(), caught, energy_gained: crab.energy() - energy }
+            })
+            .collect();
+        self.notify();
+        outcomes
+    }
+
+    /**
+     * Has every crab hunt once, as calling `Crab::hunt` on each in turn would, but spread over
+     * as many threads as the machine has cores. Returns the number of crabs that caught prey.
+     *
+     * Crabs are split into groups that share no reefs. The crabs of a group hunt one after
+     * another, in beach order, on the same thread, while other groups hunt on other threads.
+     * Each group draws its randomness from its own generator, seeded with a number drawn from the
+     * fixed-seed `crate::rand::rand32` for each group, so the outcome does not depend on the
+     * number of threads, and each call draws anew.
+     *
+     * Trace spans and events from the worker threads are not reported.
+     */
+    pub fn hunt_all_parallel(&mut self) -> usize {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.hunt_all_parallel_on(threads)
+    }
+
+    /**
+     * Like `hunt_all_parallel`, but uses at most `threads` threads.
+     */
+    pub fn hunt_all_parallel_on(&mut self, threads: usize) -> usize {
+        let mut rng = seeded(((crate::rand::rand32() as u64) << 32) | crate::rand::rand32() as u64);
+        self.hunt_all_parallel_on_with_rng(threads, &mut rng)
+    }
+
+    /**
+     * Like `hunt_all_parallel`, but each group's generator is seeded with a number drawn from
+     * `rng`, group by group, so hunts can be reproduced with a chosen seed.
+     */
+    pub fn hunt_all_parallel_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        self.hunt_all_parallel_on_with_rng(threads, rng)
+    }
+
+    /**
+     * Like `hunt_all_parallel_with_rng`, but uses at most `threads` threads.
+     */
+    pub fn hunt_all_parallel_on_with_rng<R: Rng + ?Sized>(&mut self, threads: usize, rng: &mut R) -> usize {
+        self.sync_crab_clans();
+        self.sync_camouflage();
+        // So that each crab's reefs line up with its catches in them on the worker threads.
+        for crab in self.crabs.iter_mut() {
+            crab.prune_reefs();
+        }
+        let groups = self.hunting_groups();
+        let seeds: Vec<u64> = groups.iter().map(|_| rng.next_u64()).collect();
+        if threads <= 1 || groups.len() <= 1 {
+            // Nothing to overlap, so skip moving the crabs and reefs to another thread.
+            let mut caught = 0;
+            for (group, &seed) in groups.iter().zip(seeds.iter()) {
+                let mut rng = seeded(seed);
+                caught += group.crabs.iter().filter(|&&index| self.crabs[index].hunt_with_rng(&mut rng)).count();
+            }
+            self.notify();
+            return caught;
+        }
+        let jobs: Vec<HuntJob> =
+            groups.iter().zip(seeds).map(|(group, seed)| HuntJob::new(&mut self.crabs, group, seed)).collect();
+
+        let per_thread = jobs.len().div_ceil(threads.max(1)).max(1);
+        let mut buckets: Vec<Vec<HuntJob>> = Vec::new();
+        for job in jobs {
+            match buckets.last_mut() {
+                Some(bucket) if bucket.len() < per_thread => bucket.push(job),
+                _ => buckets.push(vec![job]),
+            }
+        }
+        let finished: Vec<HuntJob> = thread::scope(|scope| {
+            let handles: Vec<_> = buckets
+                .into_iter()
+                .map(|bucket| scope.spawn(move || bucket.into_iter().map(HuntJob::run).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        });
+
+        let mut caught = 0;
+        for (job, group) in finished.into_iter().zip(groups.iter()) {
+            caught += job.caught;
+            for (handle, reef) in group.reefs.iter().zip(job.reefs) {
+                *handle.borrow_mut() = reef;
+            }
+            for (&index, crab) in group.crabs.iter().zip(job.crabs) {
+                self.crabs[index].reattach(crab);
+            }
+        }
+        self.notify();
+        caught
+    }
+
+    // Splits the crabs that have reefs into groups such that crabs in different groups never
+    // share a reef, using union-find over the reefs.
+    fn hunting_groups(&self) -> Vec<HuntGroup> {
+        fn root(parent: &mut [usize], mut id: usize) -> usize {
+            while parent[id] != id {
+                parent[id] = parent[parent[id]];
+                id = parent[id];
+            }
+            id
+        }
+        let mut reef_ids: HashMap<*const RefCell<Reef>, usize> = HashMap::new();
+        let mut reefs: Vec<Rc<RefCell<Reef>>> = Vec::new();
+        let mut parent: Vec<usize> = Vec::new();
+
         for crab in self.crabs.iter() {
-            if crab.name() == name {
-                crabs_with_name.push(crab);
+            let mut first = None;
+            for reef in crab.reefs() {
+                let id = *reef_ids.entry(Rc::as_ptr(&reef)).or_insert_with(|| {
+                    reefs.push(reef.clone());
+                    parent.push(parent.len());
+                    parent.len() - 1
+                });
+                let first = *first.get_or_insert(id);
+                let (a, b) = (root(&mut parent, first), root(&mut parent, id));
+                parent[b] = a;
             }
         }
-        return crabs_with_name;
+
+        let mut groups: Vec<HuntGroup> = Vec::new();
+        let mut group_of_root: HashMap<usize, usize> = HashMap::new();
+        for (index, crab) in self.crabs.iter().enumerate() {
+            let Some(reef) = crab.reefs().next() else {
+                continue;
+            };
+            let group_root = root(&mut parent, reef_ids[&Rc::as_ptr(&reef)]);
+            let group = *group_of_root.entry(group_root).or_insert_with(|| {
+                groups.push(HuntGroup { crabs: Vec::new(), reefs: Vec::new() });
+                groups.len() - 1
+            });
+            groups[group].crabs.push(index);
+        }
+        for (id, reef) in reefs.into_iter().enumerate() {
+            let group_root = root(&mut parent, id);
+            groups[group_of_root[&group_root]].reefs.push(reef);
+        }
+        groups
     }
 
     /**
@@ -73,76 +710,1834 @@ impl Beach {
      * the method should panic.
      */
     pub fn breed_crabs(&mut self, i: usize, j: usize, name: String) {
-        
-        if i>= self.crabs.len() || j>= self.crabs.len() {
-            panic!("Index out of bounds");
+        if let Err(e) = self.try_breed_crabs(i, j, name) {
+            panic!("{}", e);
         }
-        
+    }
+
+    /**
+     * Like `breed_crabs`, but returns `OceanError::CrabIndexOutOfBounds`, or
+     * `OceanError::BreedingUnavailable` while breeding is not allowed or either crab is in a zone
+     * without breeding, `OceanError::TooYoung` if life stages are enforced and either crab is a
+     * juvenile, `OceanError::TooCloselyRelated` if the crabs are more closely related than the
+     * beach allows (see `set_max_breeding_relatedness`), or `OceanError::BreedingCooldown` if
+     * either crab bred too recently (see `set_breeding_cooldown`), `OceanError::CrabDead` if
+     * either crab has starved (see `set_starvation_limit`), or `OceanError::CrabHibernating` if
+     * either crab is hibernating (see `Crab::hibernate`), instead of panicking.
+     */
+    pub fn try_breed_crabs(&mut self, i: usize, j: usize, name: String) -> Result<(), OceanError> {
+        self.check_breeding()?;
+        self.check_index(i)?;
+        self.check_index(j)?;
+        self.check_zones(i, j)?;
+        self.check_stage(i, "breed")?;
+        self.check_stage(j, "breed")?;
+        self.check_relatedness(i, j)?;
+        self.check_alive(i)?;
+        self.check_alive(j)?;
+        self.check_cooldown(i)?;
+        self.check_cooldown(j)?;
         let crab1 = &self.crabs[i];
         let crab2 = &self.crabs[j];
-        let mut new_crab = Crab::breed(name,crab1, crab2); 
-        self.crabs.push(new_crab);
+        let new_crab = Crab::breed(name,crab1, crab2); 
+        self.add_offspring(i, j, new_crab);
+        Ok(())
     }
 
     /**
-     * Returns a reference to the clan system associated with the beach.
+     * Like `try_breed_crabs`, but breeds with `Crab::breed_with_rng`, so that runs can be reproduced
+     * with a chosen seed.
      */
-    pub fn get_clan_system(&self) -> &ClanSystem {
-        &self.clan_system
+    pub fn breed_crabs_with_rng<R: Rng + ?Sized>(&mut self, i: usize, j: usize, name: String, rng: &mut R) -> Result<(), OceanError> {
+        self.check_breeding()?;
+        self.check_index(i)?;
+        self.check_index(j)?;
+        self.check_zones(i, j)?;
+        self.check_stage(i, "breed")?;
+        self.check_stage(j, "breed")?;
+        self.check_relatedness(i, j)?;
+        self.check_alive(i)?;
+        self.check_alive(j)?;
+        self.check_cooldown(i)?;
+        self.check_cooldown(j)?;
+        let new_crab = Crab::breed_with_rng(name, &self.crabs[i], &self.crabs[j], rng);
+        self.add_offspring(i, j, new_crab);
+        Ok(())
     }
 
     /**
-     * Adds a crab that lives on the beach as a member to the clan system for the given clan id and the crab's name.
-     * A crab can only belong to one clan.
+     * Like `breed_crabs_with_rng`, but breeds with `Crab::breed_genetic`, so the new crab's speed,
+     * color, and escape resistance are inherited through its parents' genomes.
      */
-    pub fn add_member_to_clan(&mut self, clan_id: &str, crab_name: &str) {
+    pub fn breed_crabs_genetic<R: Rng + ?Sized>(&mut self, i: usize, j: usize, name: String, rng: &mut R) -> Result<(), OceanError> {
+        self.check_breeding()?;
+        self.check_index(i)?;
+        self.check_index(j)?;
+        self.check_zones(i, j)?;
+        self.check_stage(i, "breed")?;
+        self.check_stage(j, "breed")?;
+        self.check_relatedness(i, j)?;
+        self.check_alive(i)?;
+        self.check_alive(j)?;
+        self.check_cooldown(i)?;
+        self.check_cooldown(j)?;
+        let new_crab = Crab::breed_genetic(name, &self.crabs[i], &self.crabs[j], rng);
+        self.add_offspring(i, j, new_crab);
+        Ok(())
+    }
 
-        let crab = self.crabs.iter().find(|crab| crab.name() == crab_name);
-       // Check if the clan exists, if not, create a new clan
-       if !self.clan_system.get_clan(clan_id).is_some() {
-            self.clan_system.create_clan(clan_id.to_string());
-        }
+    /**
+     * Returns false while crabs cannot breed on this beach, e.g. in winter (see `crate::weather`).
+     */
+    pub fn breeding_allowed(&self) -> bool {
+        self.breeding_allowed
+    }
 
-        // Add crab to the clan
-        self.clan_system.add_member(clan_id, crab_name.to_string(), crab.unwrap().clone());
+    /**
+     * Allows or forbids breeding on this beach. While it is forbidden, the `breed_crabs` methods
+     * return `OceanError::BreedingUnavailable`, or panic for `breed_crabs`. Beaches allow breeding
+     * unless told otherwise; a `Simulation` sets this at the start of every step from its weather.
+     */
+    pub fn set_breeding_allowed(&mut self, allowed: bool) {
+        self.breeding_allowed = allowed;
     }
 
     /**
-     * Returns the id of the clan that wins the competition given two clan ids. The winner is decided based on the average speed of the clan members.
-     * Return `None` if there are no clear winners between two different existing clans. If the inputs are invalid, return an Err string.
+     * Returns the `y` at which the sea begins on this beach (see `crate::position`), or None if the
+     * beach has no tide line.
+     */
+    pub fn tide_line(&self) -> Option<f64> {
+        self.tide_line
+    }
+
+    pub fn set_tide_line(&mut self, y: f64) {
+        self.tide_line = Some(y);
+    }
+
+    /**
+     * Returns the background crabs in the named zone blend into: the zone's own background (see
+     * `Zone::with_background`), or else the beach's (see `set_background_color`). None names the
+     * crabs outside every zone, as does a zone the beach does not have. Returns None if there is
+     * no background to blend into.
+     */
+    pub fn background_color(&self, zone: Option<&str>) -> Option<&Color> {
+        zone.and_then(|name| self.zone(name))
+            .and_then(Zone::background)
+            .or(self.background.as_ref())
+    }
+
+    /**
+     * Colors the beach's background, which crabs outside every zone, or in zones without a
+     * background of their own, blend into. Crabs whose color matches their background are
+     * camouflaged (see `Crab::color_match`): predators catch them less often and prey notice
+     * them later. None, the default, leaves crabs nothing to blend into.
      */
-     pub fn get_winner_clan(&mut self, id1: &str, id2: &str) -> Result<Option<String>, String> {
-        let clan1 = self.clan_system.get_clan(id1).cloned();
-        let clan2 = self.clan_system.get_clan(id2).cloned();
-        
-        if clan1.is_none() || clan2.is_none() {
-            return Err("Clan does not exist".to_string());
+    pub fn set_background_color(&mut self, color: Option<Color>) {
+        self.background = color;
+    }
+
+    // Tells each crab how well its color matches its background (see `Crab::color_match`).
+    // Beaches call this before their crabs hunt or face predators.
+    pub(crate) fn sync_camouflage(&mut self) {
+        for index in 0..self.crabs.len() {
+            let zone = self.zone_of_crab(index).map(Zone::name);
+            let color_match = self.background_color(zone).map_or(0.0, |background| self.crabs[index].color().similarity(background));
+            self.crabs[index].set_color_match(color_match);
         }
+    }
+
+    /**
+     * Returns how far this beach's crabs can sense reefs they do not know yet (see
+     * `discoverable_reefs`), or None if they never discover reefs on their own.
+     */
+    pub fn sensory_radius(&self) -> Option<f64> {
+        self.sensory_radius
+    }
+
+    /**
+     * Sets how far this beach's crabs can sense reefs, so that crabs learn of the reefs around them
+     * as they move instead of each being shown its reefs with `Crab::discover_reef`. A
+     * `Simulation` has every crab discover the reefs within range once a step. None, the default,
+     * turns discovery off.
+     */
+    pub fn set_sensory_radius(&mut self, radius: Option<f64>) {
+        self.sensory_radius = radius.map(|radius| radius.max(0.0));
+    }
 
+    /**
+     * Returns the reefs among `reefs` (e.g. `ocean.reefs()`) that the crab with id `id` (see
+     * `Crab::id`) does not know yet and is within the beach's sensory radius of, in the order they
+     * are given. Crabs and reefs without a position (see `crate::position`) sense nothing and
+     * cannot be sensed, and no reef is within range if the beach has no sensory radius.
+     *
+     * Returns `OceanError::CrabIdNotFound` if no crab on the beach has the id.
+     */
+    pub fn discoverable_reefs<'r, I>(&self, id: CrabId, reefs: I) -> Result<Vec<Rc<RefCell<Reef>>>, OceanError>
+    where
+        I: IntoIterator<Item = &'r Rc<RefCell<Reef>>>,
+    {
+        let index = self.find_crab_by_id(id).ok_or(OceanError::CrabIdNotFound(id))?;
+        Ok(self.reefs_in_range(&self.crabs[index], reefs))
+    }
 
-        let mut total_clan1_speed = 0; 
-        let mut total_clan2_speed = 0;  
-        
-        for crab in clan1.unwrap().values() {
-            total_clan1_speed += crab.speed();
+    /**
+     * Has every crab on the beach discover the reefs among `reefs` it can sense (see
+     * `discoverable_reefs`), adding them after the reefs it already knows.
+     *
+     * Returns the number of reefs discovered, counting a reef once for each crab that discovered it.
+     */
+    pub fn discover_nearby_reefs<'r, I>(&mut self, reefs: I) -> usize
+    where
+        I: IntoIterator<Item = &'r Rc<RefCell<Reef>>>,
+    {
+        if self.sensory_radius.is_none() {
+            return 0;
         }
-        for crab in clan2.unwrap().values() {
-            total_clan2_speed += crab.speed();
+        let reefs: Vec<&Rc<RefCell<Reef>>> = reefs.into_iter().collect();
+        let mut discovered = 0;
+        for index in 0..self.crabs.len() {
+            for reef in self.reefs_in_range(&self.crabs[index], reefs.iter().copied()) {
+                self.crabs[index].discover_reef(reef);
+                discovered += 1;
+            }
         }
+        discovered
+    }
 
-        let clan1_count = self.clan_system.get_clan_member_count(id1) as u32;
-        let clan2_count = self.clan_system.get_clan_member_count(id2) as u32;
-        let avg_clan1_speed = total_clan1_speed / clan1_count;
-        let avg_clan2_speed = total_clan2_speed / clan2_count;
+    // The reefs among `reefs` that `crab` can sense but does not know yet.
+    fn reefs_in_range<'r, I>(&self, crab: &Crab, reefs: I) -> Vec<Rc<RefCell<Reef>>>
+    where
+        I: IntoIterator<Item = &'r Rc<RefCell<Reef>>>,
+    {
+        let (Some(radius), Some(here)) = (self.sensory_radius, crab.position()) else {
+            return Vec::new();
+        };
+        reefs
+            .into_iter()
+            .filter(|reef| reef.borrow().position().is_some_and(|there| here.distance(there) <= radius))
+            .filter(|reef| !crab.reefs().any(|known| Rc::ptr_eq(&known, reef)))
+            .cloned()
+            .collect()
+    }
 
-        if avg_clan1_speed > avg_clan2_speed {
-            Ok(Some(id1.to_string()))
-        } else if avg_clan2_speed > avg_clan1_speed {
-            Ok(Some(id2.to_string()))
-        } else {
-            // If the average speeds are equal, there's no clear winner
-            Ok(None)
+    /**
+     * Adds a zone to the beach, which crabs can then be assigned to with `assign_zone`.
+     * Returns `OceanError::DuplicateName` if the beach already has a zone with the same name.
+     */
+    pub fn add_zone(&mut self, zone: Zone) -> Result<(), OceanError> {
+        if self.zone(zone.name()).is_some() {
+            return Err(OceanError::DuplicateName { kind: "zone", name: zone.name().to_string() });
         }
+        self.zones.push(zone);
+        Ok(())
+    }
+
+    /**
+     * Returns the zone with the given name, or None if the beach has no such zone.
+     */
+    pub fn zone(&self, name: &str) -> Option<&Zone> {
+        self.zones.iter().find(|zone| zone.name() == name)
+    }
+
+    /**
+     * Returns an iterator over the beach's zones, in the order they were added.
+     */
+    pub fn zones(&self) -> Iter<'_, Zone> {
+        self.zones.iter()
+    }
+
+    /**
+     * Moves the crab at `index` into the named zone, out of any zone it was in before.
+     *
+     * Returns `OceanError::CrabIndexOutOfBounds` if there is no crab at `index`, and
+     * `OceanError::ZoneNotFound` if the beach has no such zone.
+     */
+    pub fn assign_zone(&mut self, index: usize, zone: &str) -> Result<(), OceanError> {
+        self.check_index(index)?;
+        let position = self
+            .zones
+            .iter()
+            .position(|known| known.name() == zone)
+            .ok_or_else(|| OceanError::ZoneNotFound(zone.to_string()))?;
+        self.crab_zones.insert(self.crabs.handle(index).unwrap(), position);
+        Ok(())
+    }
+
+    /**
+     * Moves the crab at `index` out of its zone, if it is in one. Returns
+     * `OceanError::CrabIndexOutOfBounds` if there is no crab at `index`.
+     */
+    pub fn unassign_zone(&mut self, index: usize) -> Result<(), OceanError> {
+        self.check_index(index)?;
+        self.leave_zone(index);
+        Ok(())
+    }
+
+    /**
+     * Returns the zone the crab at `index` is in, or None if it is in no zone or there is no
+     * crab at `index`.
+     */
+    pub fn zone_of_crab(&self, index: usize) -> Option<&Zone> {
+        let position = self.crab_zones.get(&self.crabs.handle(index)?)?;
+        Some(&self.zones[*position])
+    }
+
+    /**
+     * Returns the crabs in the named zone, in beach order. The list is empty if the beach has no
+     * such zone.
+     */
+    pub fn crabs_in_zone(&self, zone: &str) -> Vec<&Crab> {
+        (0..self.crabs.len())
+            .filter(|&index| self.zone_of_crab(index).is_some_and(|known| known.name() == zone))
+            .map(|index| &self.crabs[index])
+            .collect()
+    }
+
+    // Returns the chance that each crab, in beach order, goes out hunting because of its zone.
+    pub(crate) fn zone_hunt_chances(&self) -> Vec<f64> {
+        (0..self.crabs.len())
+            .map(|index| self.zone_of_crab(index).map_or(1.0, Zone::hunt_chance))
+            .collect()
+    }
+
+    // Takes the crab at `index` out of its zone, returning the position of the zone it was in.
+    fn leave_zone(&mut self, index: usize) -> Option<usize> {
+        self.crab_zones.remove(&self.crabs.handle(index)?)
+    }
+
+    /**
+     * Draws the beach as `height` lines of `width` characters, each ending in a newline. Each
+     * character covers one unit of the beach (see `crate::position`), with `x` increasing to the
+     * right and `y` increasing downwards, out to sea:
+     *   - `.` is sand and `~` is sea, i.e. rows at or beyond the tide line;
+     *   - `#` is a reef that one of the beach's crabs hunts in;
+     *   - a crab is the first letter of its color's name, in upper case (see `Color::nearest_name`),
+     *     drawn over any reef at the same spot.
+     *
+     * Crabs and reefs without a position, or outside the drawing, are left out.
+     */
+    pub fn render_ascii(&self, width: usize, height: usize) -> String {
+        let mut grid: Vec<Vec<char>> = (0..height)
+            .map(|y| {
+                let sea = self.tide_line.is_some_and(|tide| y as f64 >= tide);
+                vec![if sea { '~' } else { '.' }; width]
+            })
+            .collect();
+        let mut plot = |position: Option<Position>, mark: char| {
+            let Some(position) = position else { return };
+            if position.x < 0.0 || position.y < 0.0 {
+                return;
+            }
+            let (x, y) = (position.x as usize, position.y as usize);
+            if x < width && y < height {
+                grid[y][x] = mark;
+            }
+        };
+        let mut reefs: Vec<Rc<RefCell<Reef>>> = Vec::new();
+        for reef in self.crabs.iter().flat_map(|crab| crab.reefs()) {
+            if !reefs.iter().any(|known| Rc::ptr_eq(known, &reef)) {
+                plot(reef.borrow().position(), '#');
+                reefs.push(reef);
+            }
+        }
+        for crab in self.crabs.iter() {
+            let name = crab.color().nearest_name();
+            plot(crab.position(), name.chars().next().unwrap().to_ascii_uppercase());
+        }
+        let mut out = String::with_capacity((width + 1) * height);
+        for row in grid {
+            out.extend(row);
+            out.push('\n');
+        }
+        out
+    }
+
+    /**
+     * Returns the population at which crabs stop breeding on this beach, or None if there is no
+     * limit.
+     */
+    pub fn breeding_limit(&self) -> Option<usize> {
+        self.breeding_limit
+    }
+
+    /**
+     * Stops crabs breeding once the beach holds `limit` crabs: the `breed_crabs` methods then fail
+     * as they do while breeding is not allowed. None removes the limit.
+     */
+    pub fn set_breeding_limit(&mut self, limit: Option<usize>) {
+        self.breeding_limit = limit;
+    }
+
+    /**
+     * Returns how many steps crabs on this beach must wait after breeding before they can breed
+     * again.
+     */
+    pub fn breeding_cooldown(&self) -> u32 {
+        self.breeding_cooldown
+    }
+
+    /**
+     * Makes both parents of every crab bred here wait `steps` simulation steps before they can
+     * breed again (see `Crab::can_breed`), so that populations grow steadily instead of on every
+     * step. Until then, the `breed_crabs` methods return `OceanError::BreedingCooldown` for them.
+     * Defaults to 0, so crabs can breed again straight away. Breeding in winter is already
+     * forbidden by the weather (see `crate::weather`).
+     */
+    pub fn set_breeding_cooldown(&mut self, steps: u32) {
+        self.breeding_cooldown = steps;
+    }
+
+    /**
+     * Returns the energy every crab on this beach burns each step.
+     */
+    pub fn metabolism(&self) -> u32 {
+        self.metabolism
+    }
+
+    /**
+     * Makes every crab on this beach burn `energy` energy each step, so crabs that stop catching
+     * prey run out and may starve (see `set_starvation_limit`). Defaults to 0.
+     */
+    pub fn set_metabolism(&mut self, energy: u32) {
+        self.metabolism = energy;
+    }
+
+    /**
+     * Returns how many steps in a row a crab here can end with no energy before it starves, or
+     * None if crabs never starve.
+     */
+    pub fn starvation_limit(&self) -> Option<u32> {
+        self.starvation_limit
+    }
+
+    /**
+     * Starves crabs that end `steps` steps in a row with no energy (see `Crab::is_dead`), emitting
+     * `OceanEvent::CrabStarved`. Dead crabs cannot hunt or breed, and unless `set_remove_dead` is
+     * turned off, are removed from the beach and their clans on the following step. None, the
+     * default, means crabs never starve.
+     */
+    pub fn set_starvation_limit(&mut self, steps: Option<u32>) {
+        self.starvation_limit = steps;
+    }
+
+    /**
+     * Returns true if crabs that starve are removed from this beach on the following step.
+     */
+    pub fn remove_dead(&self) -> bool {
+        self.remove_dead
+    }
+
+    /**
+     * Chooses whether crabs that starve are removed from this beach, as `OceanEvent::CrabDied`,
+     * on the step after they die, or left in place. Defaults to true.
+     */
+    pub fn set_remove_dead(&mut self, remove: bool) {
+        self.remove_dead = remove;
+    }
+
+    fn check_alive(&self, index: usize) -> Result<(), OceanError> {
+        let crab = &self.crabs[index];
+        if crab.is_dead() {
+            return Err(OceanError::CrabDead(crab.name().to_string()));
+        }
+        if crab.is_hibernating() {
+            return Err(OceanError::CrabHibernating(crab.name().to_string()));
+        }
+        Ok(())
+    }
+
+    fn check_cooldown(&self, index: usize) -> Result<(), OceanError> {
+        let crab = &self.crabs[index];
+        if !crab.can_breed() {
+            return Err(OceanError::BreedingCooldown { crab: crab.name().to_string(), steps: crab.breeding_cooldown() });
+        }
+        Ok(())
+    }
+
+    fn check_breeding(&self) -> Result<(), OceanError> {
+        let crowded = self.breeding_limit.is_some_and(|limit| self.crabs.len() >= limit);
+        if !self.breeding_allowed || crowded {
+            return Err(OceanError::BreedingUnavailable);
+        }
+        Ok(())
+    }
+
+    /**
+     * Returns true if crabs on this beach must be adults or elders to breed or join a clan (see
+     * `Crab::stage`).
+     */
+    pub fn life_stages_enforced(&self) -> bool {
+        self.life_stages_enforced
+    }
+
+    /**
+     * Makes juveniles unable to breed or join clans: the `breed_crabs` methods and
+     * `try_add_member_to_clan` then return `OceanError::TooYoung` for them. Beaches do not
+     * enforce life stages unless told to, so crabs can breed as soon as they arrive.
+     */
+    pub fn set_life_stages_enforced(&mut self, enforced: bool) {
+        self.life_stages_enforced = enforced;
+    }
+
+    /**
+     * Returns the closest relatedness (see `relatedness`) crabs on this beach may have and still
+     * breed, or None if related crabs may breed.
+     */
+    pub fn max_breeding_relatedness(&self) -> Option<f64> {
+        self.max_breeding_relatedness
+    }
+
+    /**
+     * Stops crabs more closely related than `max` from breeding, and any crab from breeding with
+     * itself: the `breed_crabs` methods then return `OceanError::TooCloselyRelated` for them. For
+     * example, 0.25 stops parents breeding with their children and full siblings with each other,
+     * but not half siblings. None, the default, lets any crabs breed.
+     */
+    pub fn set_max_breeding_relatedness(&mut self, max: Option<f64>) {
+        self.max_breeding_relatedness = max;
+    }
+
+    fn check_relatedness(&self, i: usize, j: usize) -> Result<(), OceanError> {
+        let Some(max) = self.max_breeding_relatedness else {
+            return Ok(());
+        };
+        let (a, b) = (&self.crabs[i], &self.crabs[j]);
+        if i != j && self.relatedness(a.id(), b.id()) <= max {
+            return Ok(());
+        }
+        Err(OceanError::TooCloselyRelated {
+            crab1: a.name().to_string(),
+            crab2: b.name().to_string(),
+            relation: self.describe_relation(i, j),
+        })
+    }
+
+    // Names how the crabs at indices `i` and `j` are related, for error messages.
+    fn describe_relation(&self, i: usize, j: usize) -> String {
+        let (a, b) = (&self.crabs[i], &self.crabs[j]);
+        let is_parent = |parent: &Crab, child: &Crab| child.parents().is_some_and(|(p1, p2)| parent.id() == p1 || parent.id() == p2);
+        if i == j {
+            return String::from("the same crab");
+        }
+        if is_parent(a, b) || is_parent(b, a) {
+            return String::from("parent and child");
+        }
+        if let (Some((a1, a2)), Some((b1, b2))) = (a.parents(), b.parents()) {
+            if (a1, a2) == (b1, b2) || (a1, a2) == (b2, b1) {
+                return String::from("full siblings");
+            }
+            if [a1, a2].iter().any(|&parent| parent == b1 || parent == b2) {
+                return String::from("half siblings");
+            }
+        }
+        if self.ancestors(a.id()).contains(&b.id()) || self.ancestors(b.id()).contains(&a.id()) {
+            return String::from("ancestor and descendant");
+        }
+        format!("relatedness {}", self.relatedness(a.id(), b.id()))
+    }
+
+    fn check_stage(&self, index: usize, action: &'static str) -> Result<(), OceanError> {
+        let crab = &self.crabs[index];
+        if self.life_stages_enforced && crab.stage() == Stage::Juvenile {
+            return Err(OceanError::TooYoung { crab: crab.name().to_string(), action });
+        }
+        Ok(())
+    }
+
+    fn check_zones(&self, i: usize, j: usize) -> Result<(), OceanError> {
+        if [i, j].iter().any(|&index| self.zone_of_crab(index).is_some_and(|zone| !zone.breeding_allowed())) {
+            return Err(OceanError::BreedingUnavailable);
+        }
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), OceanError> {
+        if index >= self.crabs.len() {
+            return Err(OceanError::CrabIndexOutOfBounds { index, len: self.crabs.len() });
+        }
+        Ok(())
+    }
+
+    fn add_offspring(&mut self, i: usize, j: usize, new_crab: Crab) {
+        trace::event!(
+            "breed",
+            parent1 = self.crabs[i].name(),
+            parent2 = self.crabs[j].name(),
+            child = new_crab.name(),
+            diet = format!("{:?}", new_crab.diet()),
+            color = new_crab.color().to_hex(),
+        );
+        self.crabs[i].record_offspring();
+        if j != i {
+            self.crabs[j].record_offspring();
+        }
+        for parent in [i, j] {
+            self.crabs[parent].start_breeding_cooldown(self.breeding_cooldown);
+        }
+        self.enqueue(OceanEvent::CrabBorn {
+            name: new_crab.name().to_string(),
+            parents: (self.crabs[i].name().to_string(), self.crabs[j].name().to_string()),
+        });
+        let zone = self.crab_handle(i).and_then(|handle| self.crab_zones.get(&handle).copied());
+        let next_id = self.next_id;
+        let new_crab = self.with_new_id(new_crab);
+        self.push_crab(new_crab);
+        if let Some(zone) = zone {
+            self.crab_zones.insert(self.crabs.handle(self.crabs.len() - 1).unwrap(), zone);
+        }
+        self.record(Change::Added { parents: Some((i, j)), next_id, crab: None });
+        self.notify();
+    }
+
+    /**
+     * Returns the ids of every known ancestor of the crab with id `id`: its parents, then their
+     * parents, and so on, each listed once. Crabs that were not bred have no ancestors, and
+     * neither do ids that never lived on this beach.
+     */
+    pub fn ancestors(&self, id: CrabId) -> Vec<CrabId> {
+        let mut ancestors = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([id]);
+        while let Some(next) = queue.pop_front() {
+            if let Some(&Some((p1, p2))) = self.lineage.get(&next) {
+                for parent in [p1, p2] {
+                    if seen.insert(parent) {
+                        ancestors.push(parent);
+                        queue.push_back(parent);
+                    }
+                }
+            }
+        }
+        ancestors
+    }
+
+    /**
+     * Returns the ids of every crab on this beach, living or buried, descended from the crab with
+     * id `id`, in the order they were bred.
+     */
+    pub fn descendants(&self, id: CrabId) -> Vec<CrabId> {
+        let mut children: HashMap<CrabId, Vec<CrabId>> = HashMap::new();
+        for (&child, parents) in self.lineage.iter() {
+            if let Some((p1, p2)) = *parents {
+                children.entry(p1).or_default().push(child);
+                if p2 != p1 {
+                    children.entry(p2).or_default().push(child);
+                }
+            }
+        }
+        let mut descendants = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([id]);
+        while let Some(next) = queue.pop_front() {
+            for &child in children.get(&next).into_iter().flatten() {
+                if seen.insert(child) {
+                    descendants.push(child);
+                    queue.push_back(child);
+                }
+            }
+        }
+        // Ids are handed out in breeding order.
+        descendants.sort_unstable();
+        descendants
+    }
+
+    /**
+     * Returns the coefficient of relationship between crabs `a` and `b`: the expected share of
+     * genes they have in common through their known ancestors, corrected for inbreeding. A crab
+     * is 1.0 related to itself, 0.5 to a parent, child, or full sibling, 0.25 to a half sibling
+     * or grandparent, and 0.0 to a crab with no ancestor in common.
+     */
+    pub fn relatedness(&self, a: CrabId, b: CrabId) -> f64 {
+        if a == b {
+            return 1.0;
+        }
+        let mut memo = HashMap::new();
+        let kinship = self.kinship(a, b, &mut memo);
+        let inbreeding_a = self.inbreeding(a, &mut memo);
+        let inbreeding_b = self.inbreeding(b, &mut memo);
+        2.0 * kinship / ((1.0 + inbreeding_a) * (1.0 + inbreeding_b)).sqrt()
+    }
+
+    // The chance that a gene drawn from `a` and one drawn from `b` are copies of the same
+    // ancestral gene. Crabs with no recorded parents are unrelated founders.
+    //
+    // The kinship of a pair depends on pairs whose younger crab is older still, so it is worked
+    // out with a stack of pairs waiting on others rather than by recursion, which long lineages
+    // would overflow.
+    fn kinship(&self, a: CrabId, b: CrabId, memo: &mut HashMap<(CrabId, CrabId), f64>) -> f64 {
+        let pair = |a: CrabId, b: CrabId| (a.min(b), a.max(b));
+        let mut pending = vec![pair(a, b)];
+        while let Some(&key) = pending.last() {
+            if memo.contains_key(&key) {
+                pending.pop();
+                continue;
+            }
+            let (older, younger) = key;
+            // A crab's kinship with itself comes from its parents' kinship with each other, and
+            // otherwise from the older crab's kinship with the younger one's parents, since a
+            // child always has a higher id than its parents.
+            let needed: Vec<(CrabId, CrabId)> = match self.parents_of(younger) {
+                Some((p1, p2)) if older == younger => vec![pair(p1, p2)],
+                Some((p1, p2)) => vec![pair(older, p1), pair(older, p2)],
+                None => Vec::new(),
+            };
+            let missing: Vec<(CrabId, CrabId)> = needed.iter().copied().filter(|key| !memo.contains_key(key)).collect();
+            if !missing.is_empty() {
+                pending.extend(missing);
+                continue;
+            }
+            let sum: f64 = needed.iter().map(|key| memo[key]).sum();
+            let kinship = if older == younger { 0.5 * (1.0 + sum) } else { 0.5 * sum };
+            memo.insert(key, kinship);
+            pending.pop();
+        }
+        memo[&pair(a, b)]
+    }
+
+    // The kinship of a crab's parents, or 0.0 if it has none.
+    fn inbreeding(&self, id: CrabId, memo: &mut HashMap<(CrabId, CrabId), f64>) -> f64 {
+        match self.parents_of(id) {
+            Some((p1, p2)) => self.kinship(p1, p2, memo),
+            None => 0.0,
+        }
+    }
+
+    // Returns the crab's recorded parents. Loading rejects parents that are not older than their
+    // child, so this never leads `kinship` round in a circle.
+    fn parents_of(&self, id: CrabId) -> Option<(CrabId, CrabId)> {
+        self.lineage.get(&id).copied().flatten().filter(|&(p1, p2)| p1 < id && p2 < id)
+    }
+
+    /**
+     * Records the death of the crab at `index`: the crab is removed from the beach and
+     * from its clan, and a summary of its life is added to the hall of fame.
+     * If the index is out of bounds, the method should panic.
+     *
+     * Returns a reference to the new hall of fame entry.
+     */
+    pub fn bury_crab(&mut self, index: usize) -> &LifeSummary {
+        match self.try_bury_crab(index) {
+            Ok(summary) => summary,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /**
+     * Like `bury_crab`, but returns `OceanError::CrabIndexOutOfBounds` instead of panicking.
+     */
+    pub fn try_bury_crab(&mut self, index: usize) -> Result<&LifeSummary, OceanError> {
+        self.check_index(index)?;
+        let clan = self.clan_member(self.crabs[index].name());
+        let zone = self.leave_zone(index);
+        let (crab, _) = self.remove_crab(index);
+        self.enqueue(OceanEvent::CrabDied { name: crab.name().to_string() });
+        self.record(Change::Buried { index, crab: Box::new(crab), clan, zone });
+        self.notify();
+        Ok(self.hall_of_fame.last().unwrap())
+    }
+
+    // Removes the crab at `index` from the beach and its clan and adds it to the hall of fame,
+    // returning the crab and the id of the clan it left.
+    fn remove_crab(&mut self, index: usize) -> (Crab, Option<String>) {
+        self.leave_zone(index);
+        let crab = self.crabs.remove(index);
+        self.reindex();
+        let clan = self.clan_system.remove_member(crab.name());
+        self.hall_of_fame.push(LifeSummary::new(&crab, clan.clone()));
+        (crab, clan)
+    }
+
+    /**
+     * Takes back the most recent change made by `add_crab`, one of the `breed_crabs` methods,
+     * `add_member_to_clan`, or `bury_crab`, so that `redo` can make it again. Returns false if
+     * there is nothing to undo. Only the most recent changes are remembered (see
+     * `set_undo_depth`).
+     *
+     * Undoing restores the beach's crabs, their clans and zones, ids, and hall of fame, but does
+     * not take back the events the change queued. Crabs leaving the beach any other way, by being
+     * eaten or migrating, clear the history (see `clear_history`).
+     */
+    pub fn undo(&mut self) -> bool {
+        let Some(change) = self.undo_stack.pop_back() else {
+            return false;
+        };
+        let change = match change {
+            Change::Added { parents, next_id, crab: _ } => {
+                self.leave_zone(self.crabs.len() - 1);
+                let crab = self.crabs.pop().expect("the added crab is the last on the beach");
+                self.reindex();
+                self.lineage.remove(&crab.id());
+                self.next_id = next_id;
+                if let Some((i, j)) = parents {
+                    self.crabs[i].forget_offspring();
+                    if j != i {
+                        self.crabs[j].forget_offspring();
+                    }
+                }
+                Change::Added { parents, next_id, crab: Some(crab) }
+            }
+            Change::Joined { clan, crab, previous, created } => {
+                self.clan_system.remove_member(&crab);
+                if let Some((previous, member)) = &previous {
+                    self.clan_system.restore_member(previous, crab.clone(), member.clone());
+                }
+                if created {
+                    self.clan_system.remove_empty_clan(&clan);
+                }
+                Change::Joined { clan, crab, previous, created }
+            }
+            Change::Buried { index, crab, clan, zone } => {
+                self.hall_of_fame.pop();
+                let handle = self.crabs.insert(index, (*crab).clone());
+                if let Some(zone) = zone {
+                    self.crab_zones.insert(handle, zone);
+                }
+                self.reindex();
+                if let Some((clan, member)) = &clan {
+                    self.clan_system.restore_member(clan, crab.name().to_string(), member.clone());
+                }
+                Change::Buried { index, crab, clan, zone }
+            }
+        };
+        self.redo_stack.push(change);
+        true
+    }
+
+    /**
+     * Makes again the most recent change taken back by `undo`. Returns false if there is nothing
+     * to redo. Making any other undoable change clears what can be redone.
+     */
+    pub fn redo(&mut self) -> bool {
+        let Some(change) = self.redo_stack.pop() else {
+            return false;
+        };
+        let redo_stack = std::mem::take(&mut self.redo_stack);
+        match change {
+            Change::Added { parents, crab, .. } => {
+                let crab = crab.expect("an undone addition holds its crab");
+                if let Some((i, j)) = parents {
+                    // The beach's next id is back to what it was, so the crab gets its id again.
+                    self.add_offspring(i, j, crab);
+                } else {
+                    let next_id = self.next_id;
+                    self.push_crab(crab);
+                    self.record(Change::Added { parents: None, next_id, crab: None });
+                }
+            }
+            Change::Joined { clan, crab, .. } => {
+                let previous = self.clan_member(&crab);
+                let created = !self.clan_system.has_clan(&clan);
+                let index = self.find_crab_index(&crab).expect("the crab that joined is on the beach");
+                self.clan_system.restore_member(&clan, crab.clone(), self.crabs[index].clone());
+                self.record(Change::Joined { clan, crab, previous, created });
+            }
+            Change::Buried { index, .. } => {
+                self.try_bury_crab(index).expect("the buried crab is back at its index");
+            }
+        }
+        self.redo_stack = redo_stack;
+        true
+    }
+
+    /**
+     * Forgets every change `undo` and `redo` could make.
+     */
+    pub fn clear_history(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /**
+     * Makes `undo` remember at most the `depth` most recent changes, forgetting older ones now if
+     * there are more. Defaults to `DEFAULT_UNDO_DEPTH`; 0 turns the history off, so undoable
+     * operations no longer keep copies of the crabs they change.
+     */
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        let excess = self.undo_stack.len().saturating_sub(depth);
+        self.undo_stack.drain(..excess);
+        // The newest changes are at the bottom of the redo stack, since they were undone first.
+        let excess = self.redo_stack.len().saturating_sub(depth);
+        self.redo_stack.drain(..excess);
+    }
+
+    /**
+     * Returns how many changes `undo` remembers at most (see `set_undo_depth`).
+     */
+    pub fn undo_depth(&self) -> usize {
+        self.undo_depth
+    }
+
+    fn record(&mut self, change: Change) {
+        self.redo_stack.clear();
+        if self.undo_depth == 0 {
+            return;
+        }
+        if self.undo_stack.len() == self.undo_depth {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back(change);
+    }
+
+    // Returns the clan the named crab belongs to and the copy of the crab the clan holds.
+    fn clan_member(&self, crab_name: &str) -> Option<(String, Crab)> {
+        let (clan, member) = self.clan_system.get_member(crab_name)?;
+        Some((clan.to_string(), member.clone()))
+    }
+
+    /**
+     * Returns the index of the crab with the given id (see `Crab::id`), or None if no crab on the
+     * beach has it.
+     */
+    pub fn find_crab_by_id(&self, id: CrabId) -> Option<usize> {
+        self.crabs.iter().position(|crab| crab.id() == id)
+    }
+
+    // Removes the crab at `index` from the beach and from its clan, for it to live on elsewhere.
+    // Returns the crab and the id of the clan it left.
+    pub(crate) fn take_crab(&mut self, index: usize) -> (Crab, Option<String>) {
+        self.clear_history();
+        self.leave_zone(index);
+        let crab = self.crabs.remove(index);
+        self.reindex();
+        let clan = self.clan_system.remove_member(crab.name());
+        (crab, clan)
+    }
+
+    /**
+     * Removes every crab `predicate` holds for from the beach, from its clan, and from its zone,
+     * and returns them in beach order, e.g. to add them to another beach. Drained crabs are
+     * not buried, so they do not join the hall of fame. Like migration, draining any crabs clears
+     * the undo history.
+     */
+    pub fn drain_matching<F: FnMut(&Crab) -> bool>(&mut self, predicate: F) -> Vec<Crab> {
+        let indices = self.find_crab_indices_by(predicate);
+        if indices.is_empty() {
+            return Vec::new();
+        }
+        self.clear_history();
+        self.remove_crabs(&indices).into_iter().map(|(crab, _)| crab).collect()
+    }
+
+    // Removes the crabs at `indices`, which are in increasing order, from the beach, their zones
+    // and their clans, all in one pass however many there are. Returns each crab, in beach order,
+    // with the id of the clan it left.
+    fn remove_crabs(&mut self, indices: &[usize]) -> Vec<(Crab, Option<String>)> {
+        for &index in indices {
+            self.leave_zone(index);
+        }
+        let crabs = self.crabs.remove_many(indices);
+        self.reindex();
+        crabs
+            .into_iter()
+            .map(|crab| {
+                let clan = self.clan_system.remove_member(crab.name());
+                (crab, clan)
+            })
+            .collect()
+    }
+
+    // Ages every crab one step (see `Crab::is_molting`), refreshing the caches and clans if a molt
+    // made any crab faster, after removing the crabs that starved on the previous step. Returns how many
+    // crabs starved this step.
+    pub(crate) fn grow_crabs_older<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        if self.remove_dead {
+            let dead: Vec<usize> = (0..self.crabs.len()).filter(|&i| self.crabs[i].is_dead()).collect();
+            for (crab, clan) in self.remove_crabs(&dead) {
+                self.mourn(crab, clan);
+            }
+            if !dead.is_empty() {
+                self.clear_history();
+                self.notify();
+            }
+        }
+        let mut molted = false;
+        let mut starved = 0;
+        let speeds: Vec<u32> = self.crabs.iter().map(Crab::speed).collect();
+        for crab in self.crabs.iter_mut() {
+            molted |= crab.grow_older(rng);
+            if crab.starve(self.metabolism, self.starvation_limit) {
+                starved += 1;
+            }
+        }
+        if molted || starved > 0 {
+            self.reindex();
+        }
+        if molted {
+            for (index, speed) in speeds.into_iter().enumerate() {
+                if self.crabs[index].speed() != speed {
+                    let name = self.crabs[index].name().to_string();
+                    self.sync_clan_member(&name, index);
+                }
+            }
+        }
+        starved
+    }
+
+    pub(crate) fn queue_event(&mut self, event: OceanEvent) {
+        self.enqueue(event);
+        self.notify();
+    }
+
+    /**
+     * Attaches a predator to this beach, which hunts its crabs whenever the beach faces its
+     * predators (see `face_predators`).
+     */
+    pub fn add_predator(&mut self, predator: Box<dyn Predator>) {
+        self.predators.push(predator);
+    }
+
+    pub fn predators(&self) -> Iter<'_, Box<dyn Predator>> {
+        self.predators.iter()
+    }
+
+    /**
+     * Registers `hook` to be called with this beach at the end of every phase of every simulation
+     * step, after the hooks already registered (see `crate::hooks`).
+     */
+    pub fn register_hook(&mut self, hook: Box<dyn BeachHook>) {
+        self.hooks.push(hook);
+    }
+
+    // Runs every hook for the end of `phase`. Hooks registered while they run are kept, after
+    // the others.
+    pub(crate) fn run_hooks(&mut self, tick: u64, phase: Phase, rng: &mut dyn OceanRng) {
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook.after_phase(tick, phase, self, rng);
+        }
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+    }
+
+    /**
+     * Lets every predator, in the order they were added, make one attempt on a crab chosen at
+     * random with `rng`. Caught crabs are removed from the beach and from their clans, added to
+     * the hall of fame, and reported with `OceanEvent::CrabEaten`.
+     *
+     * Returns the number of crabs caught.
+     */
+    pub fn face_predators<R: RngCore + ?Sized>(&mut self, rng: &mut R) -> usize {
+        let mut rng = rng;
+        self.sync_camouflage();
+        let mut predators = std::mem::take(&mut self.predators);
+        let mut eaten = 0;
+        for predator in predators.iter_mut() {
+            if self.crabs.is_empty() {
+                break;
+            }
+            let index = rng.gen_range(0..self.crabs.len());
+            if predator.try_catch(&self.crabs[index], &mut rng) {
+                trace::event!("eaten", crab = self.crabs[index].name(), predator = predator.kind());
+                let (crab, _) = self.remove_crab(index);
+                let crab = crab.name().to_string();
+                self.enqueue(OceanEvent::CrabEaten { crab, predator: predator.kind().to_string() });
+                self.clear_history();
+                eaten += 1;
+            }
+        }
+        self.predators = predators;
+        self.notify();
+        eaten
+    }
+
+    pub fn population_policy(&self) -> &PopulationPolicy {
+        &self.population_policy
+    }
+
+    /**
+     * Sets how this beach's population changes on its own each step (see `crate::population`).
+     */
+    pub fn set_population_policy(&mut self, policy: PopulationPolicy) {
+        self.population_policy = policy;
+    }
+
+    /**
+     * Applies the beach's population policy once, drawing on `rng`: crabs die of natural causes,
+     * then crabs chosen at random die until the beach is within its capacity, then wild crabs
+     * arrive. A `Simulation` does this for every beach once a step. Dead crabs are removed from
+     * their clans and added to the hall of fame, as buried ones are, and the undo history is
+     * cleared if any died.
+     *
+     * Returns how the population changed.
+     */
+    pub fn apply_population_policy<R: Rng + ?Sized>(&mut self, rng: &mut R) -> PopulationChange {
+        let policy = self.population_policy.clone();
+        let mut change = PopulationChange::default();
+        if policy.mortality() > 0.0 {
+            // Drawn from the last crab back, then removed together.
+            let mut dying: Vec<usize> = (0..self.crabs.len()).rev().filter(|_| rng.gen_bool(policy.mortality())).collect();
+            dying.reverse();
+            change.died += dying.len();
+            for (crab, clan) in self.remove_crabs(&dying) {
+                self.mourn(crab, clan);
+            }
+        }
+        let capacity = policy.capacity().unwrap_or(usize::MAX);
+        while self.crabs.len() > capacity {
+            self.die(rng.gen_range(0..self.crabs.len()));
+            change.died += 1;
+        }
+
+        let whole = policy.spawn_rate().trunc() as usize;
+        let fraction = policy.spawn_rate().fract();
+        let arrivals = whole + usize::from(fraction > 0.0 && rng.gen_bool(fraction));
+        for _ in 0..arrivals.min(capacity.saturating_sub(self.crabs.len())) {
+            let color = Color::new(rng.gen(), rng.gen(), rng.gen());
+            let speed = rng.gen_range(WILD_SPEEDS.0..=WILD_SPEEDS.1);
+            let mut crab = Crab::new(format!("Wild {}", self.next_id), speed, color, Diet::random_diet_from(rng));
+            if !self.crabs.is_empty() {
+                let guide = rng.gen_range(0..self.crabs.len());
+                for reef in self.crabs[guide].reefs() {
+                    crab.discover_reef(reef.clone());
+                }
+            }
+            let crab = self.with_new_id(crab);
+            self.enqueue(OceanEvent::CrabSpawned { name: crab.name().to_string() });
+            self.push_crab(crab);
+            change.spawned += 1;
+        }
+        if change.died > 0 {
+            self.clear_history();
+        }
+        self.notify();
+        change
+    }
+
+    // Removes the crab at `index`, which died of natural causes, leaving its carcass behind if
+    // carcasses are enabled.
+    fn die(&mut self, index: usize) {
+        let (crab, clan) = self.remove_crabs(&[index]).pop().expect("the crab was removed");
+        self.mourn(crab, clan);
+    }
+
+    // Buries a crab that died of natural causes and was removed from the beach and its clan.
+    fn mourn(&mut self, crab: Crab, clan: Option<String>) {
+        self.hall_of_fame.push(LifeSummary::new(&crab, clan));
+        if self.carcass_decay.is_some() {
+            self.carcasses.push((Carcass::of(&crab), 0));
+        }
+        self.enqueue(OceanEvent::CrabDied { name: crab.name().to_string() });
+    }
+
+    /**
+     * Returns how many steps the carcasses of crabs that die on this beach last, or None if they
+     * leave no carcass.
+     */
+    pub fn carcass_decay(&self) -> Option<u32> {
+        self.carcass_decay
+    }
+
+    /**
+     * Makes crabs that die of natural causes here (see `OceanEvent::CrabDied`) leave a carcass on
+     * the beach, which scavengers can eat for `steps` steps before it rots away (see `scavenge`).
+     * Crabs eaten by predators or buried leave nothing. None, the default, means dead crabs leave
+     * no carcass, and drops any left already.
+     */
+    pub fn set_carcass_decay(&mut self, steps: Option<u32>) {
+        self.carcass_decay = steps;
+        if steps.is_none() {
+            self.carcasses.clear();
+        }
+    }
+
+    /**
+     * Returns an iterator over the carcasses on this beach, oldest first.
+     */
+    pub fn carcasses(&self) -> impl Iterator<Item = &Carcass> {
+        self.carcasses.iter().map(|(carcass, _)| carcass)
+    }
+
+    /**
+     * Has every living scavenger on this beach (see `Diet::Scavenger`), in beach order, eat the
+     * oldest carcass left, gaining its nutrition in energy, while any remain. Molting crabs stay
+     * hidden. The carcasses left then grow one step older, and those as old as the beach's carcass
+     * decay rot away. Returns how many carcasses were eaten.
+     */
+    pub fn scavenge(&mut self) -> usize {
+        let mut eaten = 0;
+        for crab in self.crabs.iter_mut() {
+            if self.carcasses.is_empty() {
+                break;
+            }
+            if crab.diet() != Diet::Scavenger || crab.is_molting() || crab.is_dead() || crab.is_hibernating() {
+                continue;
+            }
+            let (carcass, _) = self.carcasses.remove(0);
+            crab.scavenge(&carcass);
+            eaten += 1;
+        }
+        let decay = self.carcass_decay.unwrap_or(0);
+        for (_, age) in self.carcasses.iter_mut() {
+            *age += 1;
+        }
+        self.carcasses.retain(|&(_, age)| age < decay);
+        self.notify();
+        eaten
+    }
+
+    /**
+     * Makes the crab with id `id` (see `Crab::id`) sick with `disease`. Returns false if the crab
+     * is already sick, with this or another disease, and `OceanError::CrabIdNotFound` if no crab
+     * on the beach has the id.
+     */
+    pub fn infect(&mut self, id: CrabId, disease: Disease) -> Result<bool, OceanError> {
+        let index = self.find_crab_by_id(id).ok_or(OceanError::CrabIdNotFound(id))?;
+        Ok(self.crabs[index].infect(&disease))
+    }
+
+    /**
+     * Runs one step of disease on the beach, as described in `crate::disease`, drawing on `rng`:
+     * sick crabs infect others, lose health, and may recover, while healthy crabs heal. A
+     * `Simulation` does this for every beach once a step.
+     *
+     * Returns the number of crabs newly infected.
+     */
+    pub fn spread_disease<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        let was_sick: Vec<bool> = self.crabs.iter().map(Crab::is_sick).collect();
+        let mut infected = 0;
+        for index in (0..self.crabs.len()).filter(|&index| was_sick[index]) {
+            let disease = self.crabs[index].disease().expect("sick crabs have a disease").clone();
+            let clan = self.clan_system.get_clan_of_member(self.crabs[index].name()).map(str::to_string);
+            for other in 0..self.crabs.len() {
+                if self.crabs[other].is_sick() {
+                    continue;
+                }
+                let same_clan = clan.is_some() && self.clan_system.get_clan_of_member(self.crabs[other].name()) == clan.as_deref();
+                let chance = if same_clan { disease.clan_contagion() } else { disease.contagion() };
+                if rng.gen_bool(chance) && self.crabs[other].infect(&disease) {
+                    infected += 1;
+                }
+            }
+        }
+        for (crab, was_sick) in self.crabs.iter_mut().zip(was_sick) {
+            // Crabs that only just caught a disease start suffering from it next step.
+            if was_sick || !crab.is_sick() {
+                crab.progress_disease(rng);
+            }
+        }
+        infected
+    }
+
+    /**
+     * Returns an iterator over the life summaries of every crab that has died on this beach,
+     * in order of death.
+     */
+    pub fn hall_of_fame(&self) -> Iter<'_, LifeSummary> {
+        self.hall_of_fame.iter()
+    }
+
+    /**
+     * Saves the beach's name, its tide line, sensory radius and background, its zones, its crabs, in order, with the zone each is
+     * in, the parents of its buried crabs, and its clan system in the `crate::persist` text format. The crabs' reefs, the hall of fame, the beach's
+     * predators, its breeding limit, and its undo history are not saved.
+     */
+    pub fn save(&self) -> String {
+        let mut out = String::new();
+        if let Some(name) = &self.name {
+            out.push_str(&join_record(&["name".to_string(), name.clone()]));
+            out.push('\n');
+        }
+        if let Some(tide) = self.tide_line {
+            out.push_str(&join_record(&["tide".to_string(), tide.to_string()]));
+            out.push('\n');
+        }
+        if let Some(radius) = self.sensory_radius {
+            out.push_str(&join_record(&["sense".to_string(), radius.to_string()]));
+            out.push('\n');
+        }
+        if let Some(background) = &self.background {
+            out.push_str(&join_record(&["background".to_string(), background.to_hex()]));
+            out.push('\n');
+        }
+        for zone in self.zones.iter() {
+            let mut fields = vec![zone.name().to_string(), zone.hunt_chance().to_string(), zone.breeding_allowed().to_string()];
+            fields.extend(zone.background().map(Color::to_hex));
+            out.push_str(&join_record(&[&["zone".to_string()], &fields[..]].concat()));
+            out.push('\n');
+        }
+        for crab in self.crabs.iter() {
+            let mut fields = vec!["crab".to_string()];
+            fields.extend(crab.to_fields());
+            out.push_str(&join_record(&fields));
+            out.push('\n');
+        }
+        for index in 0..self.crabs.len() {
+            if let Some(zone) = self.zone_of_crab(index) {
+                out.push_str(&join_record(&["inzone".to_string(), index.to_string(), zone.name().to_string()]));
+                out.push('\n');
+            }
+        }
+        let living: HashSet<CrabId> = self.crabs.iter().map(Crab::id).collect();
+        let mut buried: Vec<_> = self.lineage.iter().filter(|(id, _)| !living.contains(id)).collect();
+        buried.sort_unstable_by_key(|&(&id, _)| id);
+        for (id, parents) in buried {
+            let (p1, p2) = match parents {
+                Some((p1, p2)) => (p1.to_string(), p2.to_string()),
+                None => (String::new(), String::new()),
+            };
+            out.push_str(&join_record(&["ancestor".to_string(), id.to_string(), p1, p2]));
+            out.push('\n');
+        }
+        out.push_str(&self.clan_system.save());
+        out
+    }
+
+    /**
+     * Loads a beach previously written by `save`.
+     */
+    pub fn load(text: &str) -> Result<Beach, ParseError> {
+        let mut beach = Beach::new();
+        for (i, line) in text.lines().enumerate() {
+            beach.load_line(line, i + 1)?;
+        }
+        beach.finish_loading();
+        Ok(beach)
+    }
+
+    /**
+     * Like `load`, but reads the saved beach from `reader` a line at a time, so the whole text
+     * never has to be in memory at once (see also `crate::paging`).
+     *
+     * Returns `OceanError::Io` if reading fails, and `OceanError::Parse` if the text is not a
+     * saved beach.
+     */
+    pub fn load_from<R: BufRead>(reader: R) -> Result<Beach, OceanError> {
+        let mut beach = Beach::new();
+        for (i, line) in reader.lines().enumerate() {
+            beach.load_line(&line?, i + 1)?;
+        }
+        beach.finish_loading();
+        Ok(beach)
+    }
+
+    // Adds a loaded crab to the end of the beach, keeping its saved id.
+    pub(crate) fn restore_crab(&mut self, mut crab: Crab) {
+        // Crabs saved before genealogy was tracked have no id yet.
+        if crab.id() == 0 {
+            crab = self.with_new_id(crab);
+        }
+        self.push_crab(crab);
+    }
+
+    // Loads one line of a saved beach, the `line_no`th.
+    fn load_line(&mut self, line: &str, line_no: usize) -> Result<(), ParseError> {
+        if line.is_empty() {
+            return Ok(());
+        }
+        let fields = split_record(line);
+        if fields[0] == "crab" {
+            let crab = Crab::from_fields(&fields[1..], line_no)?;
+            id_after(crab.id(), line_no)?;
+            if crab.id() != 0 {
+                check_parents(crab.id(), crab.parents(), line_no)?;
+            }
+            self.restore_crab(crab);
+        } else if fields[0] == "name" && fields.len() == 2 {
+            self.name = Some(fields[1].clone());
+        } else if fields[0] == "tide" && fields.len() == 2 {
+            self.tide_line = Some(parse_field(&fields[1], "tide line", line_no)?);
+        } else if fields[0] == "sense" && fields.len() == 2 {
+            self.sensory_radius = Some(parse_field(&fields[1], "sensory radius", line_no)?);
+        } else if fields[0] == "background" && fields.len() == 2 {
+            self.background = Some(Color::from_hex(&fields[1]).map_err(|e| ParseError::new(line_no, e))?);
+        } else if fields[0] == "zone" && (fields.len() == 4 || fields.len() == 5) {
+            let mut zone = Zone::new(&fields[1])
+                .with_hunt_chance(parse_field(&fields[2], "hunt chance", line_no)?)
+                .with_breeding(parse_field(&fields[3], "breeding", line_no)?);
+            if let Some(background) = fields.get(4) {
+                zone = zone.with_background(Color::from_hex(background).map_err(|e| ParseError::new(line_no, e))?);
+            }
+            self.add_zone(zone).map_err(|e| ParseError::new(line_no, e.to_string()))?;
+        } else if fields[0] == "inzone" && fields.len() == 3 {
+            let index = parse_field(&fields[1], "crab index", line_no)?;
+            self.assign_zone(index, &fields[2]).map_err(|e| ParseError::new(line_no, e.to_string()))?;
+        } else if fields[0] == "ancestor" && fields.len() == 4 {
+            let id = parse_field(&fields[1], "crab id", line_no)?;
+            self.next_id = self.next_id.max(id_after(id, line_no)?);
+            let parents = match (fields[2].as_str(), fields[3].as_str()) {
+                ("", "") => None,
+                (p1, p2) => Some((parse_field(p1, "parent id", line_no)?, parse_field(p2, "parent id", line_no)?)),
+            };
+            check_parents(id, parents, line_no)?;
+            self.lineage.insert(id, parents);
+        } else {
+            self.clan_system.load_record(&fields, line_no)?;
+        }
+        Ok(())
+    }
+
+    // Settles a beach once every line has been loaded.
+    fn finish_loading(&mut self) {
+        // Restoring clan members is not a change worth reporting.
+        self.clan_system.drain_events();
+        self.sync_crab_clans();
+    }
+
+    /**
+     * Removes and returns the events queued on this beach: first the beach's own births and
+     * deaths, then its clan system's events, then each crab's hunting events in beach order.
+     * Events from each source are oldest first. While the beach has subscribers (see `subscribe`),
+     * events are instead returned in the order they were sent to them. Only the newest events are
+     * kept once too many are queued (see `crate::events::MAX_QUEUED_EVENTS`).
+     */
+    pub fn drain_events(&mut self) -> Vec<OceanEvent> {
+        self.notify();
+        self.gather_events();
+        self.notified = 0;
+        std::mem::take(&mut self.events)
+    }
+
+    /**
+     * Returns a channel that receives every event queued on this beach from now on (see
+     * `drain_events`), e.g. for a logger or GUI on another thread. Events are sent as soon as the
+     * beach operation that queued them returns, and stay queued for `drain_events` too.
+     *
+     * Events queued by crabs changed directly, e.g. with `Crab::hunt` through `get_crab_mut`, are
+     * sent with the beach's next change, or when `drain_events` is called. Dropping the receiver
+     * unsubscribes it.
+     */
+    pub fn subscribe(&mut self) -> Receiver<OceanEvent> {
+        let (sender, receiver) = mpsc::channel();
+        // Events queued before subscribing are not sent.
+        self.gather_events();
+        self.notified = self.events.len();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    // Queues an event, to be sent to subscribers by the next `notify`.
+    fn enqueue(&mut self, event: OceanEvent) {
+        self.events.push(event);
+        self.bound_events();
+    }
+
+    // Drops the oldest queued events once there are too many (see `events::MAX_QUEUED_EVENTS`),
+    // including any not yet sent to subscribers.
+    fn bound_events(&mut self) {
+        let dropped = events::bound(&mut self.events);
+        self.notified = self.notified.saturating_sub(dropped);
+    }
+
+    // Sends the events queued since the last call to every subscriber.
+    fn notify(&mut self) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        self.gather_events();
+        for event in &self.events[self.notified..] {
+            self.subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+        }
+        self.notified = self.events.len();
+    }
+
+    // Moves the events queued by the clan system and crabs into `events`, in the order
+    // `drain_events` would return them.
+    fn gather_events(&mut self) {
+        self.events.extend(self.clan_system.drain_events());
+        for crab in self.crabs.iter_mut() {
+            self.events.extend(crab.drain_events());
+        }
+        self.bound_events();
+    }
+
+    /**
+     * Returns a reference to the clan system associated with the beach.
+     */
+    pub fn get_clan_system(&self) -> &ClanSystem {
+        &self.clan_system
+    }
+
+    /**
+     * Adds a crab that lives on the beach as a member to the clan system for the given clan id and the crab's name.
+     * A crab can only belong to one clan.
+     *
+     * Panics if the crab cannot join; see `try_add_member_to_clan`.
+     */
+    pub fn add_member_to_clan(&mut self, clan_id: &str, crab_name: &str) {
+        self.try_add_member_to_clan(clan_id, crab_name).unwrap();
+    }
+
+    /**
+     * Like `add_member_to_clan`, but returns an Err if no crab with that name lives on
+     * the beach, it is too young to join (see `set_life_stages_enforced`), or the clan rejects
+     * it (because it is full or by one of its recruitment rules).
+     */
+    pub fn try_add_member_to_clan(&mut self, clan_id: &str, crab_name: &str) -> Result<(), OceanError> {
+        let crab = match self.find_crab_index(crab_name) {
+            Some(index) => {
+                self.check_stage(index, "join a clan")?;
+                self.crabs[index].clone()
+            }
+            None => return Err(OceanError::CrabNotFound(crab_name.to_string())),
+        };
+        let previous = self.clan_member(crab_name);
+        let created = !self.clan_system.has_clan(clan_id);
+        // Check if the clan exists, if not, create a new clan
+        if created {
+            self.clan_system.create_clan(clan_id.to_string());
+        }
+
+        // Add crab to the clan
+        self.clan_system.add_member(clan_id, crab_name.to_string(), crab)?;
+        self.record(Change::Joined { clan: clan_id.to_string(), crab: crab_name.to_string(), previous, created });
+        self.sync_crab_clans();
+        self.notify();
+        Ok(())
+    }
+
+    // Tells each crab which clan it hunts for (see `Crab::clan`), so that it pays tribute in reefs
+    // other clans hold. Beaches call this before their crabs hunt.
+    pub(crate) fn sync_crab_clans(&mut self) {
+        for crab in self.crabs.iter_mut() {
+            crab.set_clan(self.clan_system.get_clan_of_member(crab.name()).map(str::to_string));
+        }
+    }
+
+    /**
+     * Like `ClanSystem::speed_distribution`, but with the speeds of the clan's members as they are
+     * on the beach now. Returns None if the clan does not exist or has no members here.
+     */
+    pub fn clan_speed_distribution(&self, clan_id: &str) -> Option<SpeedDistribution> {
+        let members = self.clan_system.get_clan_member_names(clan_id);
+        SpeedDistribution::of(
+            members
+                .iter()
+                .filter_map(|name| self.find_crab_index(name))
+                .map(|index| self.crabs[index].speed()),
+        )
+    }
+
+    /**
+     * Returns a mutable reference to the clan system, e.g. to create clans with limits
+     * or recruitment rules before crabs join them.
+     */
+    pub fn get_clan_system_mut(&mut self) -> &mut ClanSystem {
+        &mut self.clan_system
+    }
+
+    /**
+     * Returns the id of the clan the named crab belongs to, or None if it is in no clan.
+     */
+    pub fn get_clan_of_crab(&self, crab_name: &str) -> Option<&str> {
+        self.clan_system.get_clan_of_member(crab_name)
+    }
+
+    /**
+     * Returns the id of the clan that wins the competition given two clan ids. The winner is decided based on the average speed of the clan members.
+     * Return `None` if there are no clear winners between two different existing clans. If either clan does not exist, return `OceanError::ClanNotFound`,
+     * if either has no members, return `OceanError::ClanEmpty`, and if either clan's total speed is too large to count, return `OceanError::Overflow`.
+     *
+     * Hibernating members do not count (see `Crab::hibernate`). The result is recorded in the clan system's reputation standings.
+     */
+     pub fn get_winner_clan(&mut self, id1: &str, id2: &str) -> Result<Option<String>, OceanError> {
+        let _span = trace::span!("compete", clan1 = id1, clan2 = id2);
+        let avg_clan1_speed = self.checked_average_speed(id1)?;
+        let avg_clan2_speed = self.checked_average_speed(id2)?;
+
+        trace::event!("average_speeds", clan1 = avg_clan1_speed, clan2 = avg_clan2_speed);
+        let winner = if avg_clan1_speed > avg_clan2_speed {
+            Some(id1.to_string())
+        } else if avg_clan2_speed > avg_clan1_speed {
+            Some(id2.to_string())
+        } else {
+            // If the average speeds are equal, there's no clear winner
+            None
+        };
+
+        trace::event!("result", winner = winner.as_deref().unwrap_or("draw"));
+        self.clan_system.record_result(id1, id2, winner.as_deref());
+        self.notify();
+        Ok(winner)
+    }
+
+    // The clan's average speed, rounded down, without panicking on an empty clan or a total
+    // speed that overflowed.
+    fn checked_average_speed(&self, clan_id: &str) -> Result<u64, OceanError> {
+        let total = self
+            .clan_system
+            .get_clan_total_speed(clan_id)
+            .ok_or_else(|| OceanError::ClanNotFound(clan_id.to_string()))?;
+        if total == u64::MAX {
+            return Err(OceanError::Overflow(format!("total speed of clan {}", clan_id)));
+        }
+        let count = self.clan_system.get_clan_active_member_count(clan_id) as u64;
+        total.checked_div(count).ok_or_else(|| OceanError::ClanEmpty(clan_id.to_string()))
+    }
+
+    /**
+     * Has the attacking clan raid a reef the defending clan holds (see `ClanSystem::claim_reef`).
+     * Each side's strength is its awake members' total speed plus `RAID_MEMBER_STRENGTH` per member,
+     * scaled by a random factor within `RAID_LUCK` of 1 drawn from `rng`. The attacker captures
+     * the reef if its strength is greater; otherwise the defender keeps it. Either way the winner
+     * gains `WIN_POINTS` reputation, and a `ReefRaided` event is queued.
+     *
+     * Returns true if the attacker captured the reef. Returns `OceanError::ClanNotFound` or
+     * `OceanError::ClanEmpty` if either clan is missing or has no members, and
+     * `OceanError::InvalidArgument` if the clans are the same or the defender does not hold the reef.
+     */
+    pub fn raid<R: Rng + ?Sized>(&mut self, attacker: &str, defender: &str, reef: ReefId, rng: &mut R) -> Result<bool, OceanError> {
+        let _span = trace::span!("raid", attacker = attacker, defender = defender, reef = reef);
+        if attacker == defender {
+            return Err(OceanError::InvalidArgument(format!("Clan {} cannot raid itself", attacker)));
+        }
+        let mut strengths = [0.0; 2];
+        for (strength, clan_id) in strengths.iter_mut().zip([attacker, defender]) {
+            let total_speed = self
+                .clan_system
+                .get_clan_total_speed(clan_id)
+                .ok_or_else(|| OceanError::ClanNotFound(clan_id.to_string()))?;
+            let members = self.clan_system.get_clan_active_member_count(clan_id) as u64;
+            if members == 0 {
+                return Err(OceanError::ClanEmpty(clan_id.to_string()));
+            }
+            *strength = (total_speed + RAID_MEMBER_STRENGTH * members) as f64;
+        }
+        if self.clan_system.controller(reef) != Some(defender) {
+            return Err(OceanError::InvalidArgument(format!("Clan {} does not hold reef {}", defender, reef)));
+        }
+        for strength in strengths.iter_mut() {
+            *strength *= rng.gen_range(1.0 - RAID_LUCK..=1.0 + RAID_LUCK);
+        }
+
+        let captured = strengths[0] > strengths[1];
+        trace::event!("result", captured = captured);
+        self.clan_system.record_raid(attacker, defender, reef, captured);
+        self.notify();
+        Ok(captured)
+    }
+
+    /**
+     * Has the clan's members on this beach hunt together in `reef`, drawing randomness from `rng`.
+     * Members that are molting stay behind. The group goes after any prey a member will eat, and
+     * prey try to escape it as they would a single crab as fast as all its members together.
+     *
+     * The prey's nutrition, less any tribute owed to the reef's holder (see `Reef::owner`), is
+     * split between the members by `rule`. Every member counts the hunt as a success, and the
+     * member with the biggest share (the first, if several tie) keeps the prey in its larder.
+     *
+     * Returns `OceanError::ClanNotFound` if there is no such clan, or `OceanError::ClanEmpty` if
+     * none of its members can hunt.
+     */
+    pub fn group_hunt<R: RngCore + ?Sized>(
+        &mut self,
+        clan_id: &str,
+        reef: &mut Reef,
+        rule: SharingRule,
+        rng: &mut R,
+    ) -> Result<GroupHunt, OceanError> {
+        if !self.clan_system.has_clan(clan_id) {
+            return Err(OceanError::ClanNotFound(clan_id.to_string()));
+        }
+        let mut members: Vec<usize> = self
+            .clan_system
+            .get_clan_member_names(clan_id)
+            .iter()
+            .filter_map(|name| self.find_crab_index(name))
+            .filter(|&index| {
+                let crab = &self.crabs[index];
+                !crab.is_molting() && !crab.is_dead() && !crab.is_hibernating()
+            })
+            .collect();
+        members.sort();
+        let Some(&first) = members.first() else {
+            return Err(OceanError::ClanEmpty(clan_id.to_string()));
+        };
+
+        let speeds: Vec<u32> = members.iter().map(|&index| self.crabs[index].effective_speed()).collect();
+        let combined = speeds.iter().fold(0u32, |total, &speed| total.saturating_add(speed));
+        let group = Crab::new(clan_id.to_string(), combined, self.crabs[first].color().clone(), self.crabs[first].diet());
+        let edible = |diet: Diet| members.iter().any(|&index| self.crabs[index].will_eat(diet));
+        let mut rng = rng;
+        let Some(prey) = group.hunt_together(reef, edible, &mut rng) else {
+            return Ok(GroupHunt { prey: None, shares: members.iter().map(|&index| (index, 0)).collect() });
+        };
+
+        let nutrition = reef.levy(Some(clan_id), prey.nutrition());
+        let shares = rule.split(nutrition, &speeds);
+        let keeper = (0..shares.len()).rev().max_by_key(|&i| shares[i]).unwrap_or(0);
+        for (i, (&index, &share)) in members.iter().zip(shares.iter()).enumerate() {
+            self.crabs[index].take_share(share, (i == keeper).then_some(prey.as_ref()));
+        }
+        Ok(GroupHunt { prey: Some(prey.kind().to_string()), shares: members.into_iter().zip(shares).collect() })
+    }
+    
+}
+
+impl IntoIterator for Beach {
+    type Item = Crab;
+    type IntoIter = std::vec::IntoIter<Crab>;
+
+    /**
+     * Consumes the beach, yielding its crabs in order. The beach's clans, zones, and hall of fame
+     * go with it, so every crab leaves without a clan (see `Crab::clan`).
+     */
+    fn into_iter(mut self) -> std::vec::IntoIter<Crab> {
+        for crab in self.crabs.iter_mut() {
+            crab.set_clan(None);
+        }
+        self.crabs.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Beach {
+    type Item = &'a Crab;
+    type IntoIter = Iter<'a, Crab>;
+
+    fn into_iter(self) -> Iter<'a, Crab> {
+        self.crabs()
+    }
+}
+
+impl Extend<Crab> for Beach {
+    fn extend<I: IntoIterator<Item = Crab>>(&mut self, crabs: I) {
+        self.add_crabs(crabs);
+    }
+}
+
+/// Mutable access to one crab on a beach (see `Beach::get_crab_mut`). Dropping it re-indexes
+/// the beach if the crab's name or speed changed, and updates its clan if it also fell asleep or
+/// woke up.
+pub struct CrabMut<'a> {
+    beach: &'a mut Beach,
+    index: usize,
+    // The crab's name, speed and whether it was hibernating when the guard was made.
+    name: String,
+    speed: u32,
+    hibernating: bool,
+}
+
+impl<'a> CrabMut<'a> {
+    fn new(beach: &'a mut Beach, index: usize) -> CrabMut<'a> {
+        let crab = &beach.crabs[index];
+        let (name, speed, hibernating) = (crab.name().to_string(), crab.speed(), crab.is_hibernating());
+        CrabMut { beach, index, name, speed, hibernating }
+    }
+}
+
+impl Deref for CrabMut<'_> {
+    type Target = Crab;
+
+    fn deref(&self) -> &Crab {
+        &self.beach.crabs[self.index]
+    }
+}
+
+impl DerefMut for CrabMut<'_> {
+    fn deref_mut(&mut self) -> &mut Crab {
+        &mut self.beach.crabs[self.index]
+    }
+}
+
+impl Drop for CrabMut<'_> {
+    fn drop(&mut self) {
+        let crab = &self.beach.crabs[self.index];
+        if crab.name() != self.name || crab.speed() != self.speed {
+            self.beach.reindex();
+            self.beach.sync_clan_member(&self.name, self.index);
+        } else if crab.is_hibernating() != self.hibernating {
+            self.beach.sync_clan_member(&self.name, self.index);
+        }
+    }
+}
+
+/// Mutable access to every crab on a beach (see `Beach::crabs_mut`). Dropping it re-indexes the
+/// beach, and updates the clans of crabs whose name or speed changed.
+pub struct CrabsMut<'a> {
+    beach: &'a mut Beach,
+    // Each crab's name, speed and whether it was hibernating when the guard was made.
+    before: Vec<(String, u32, bool)>,
+}
+
+impl CrabsMut<'_> {
+    pub fn iter_mut(&mut self) -> IterMut<'_, Crab> {
+        self.beach.crabs.iter_mut()
+    }
+}
+
+impl<'b> IntoIterator for &'b mut CrabsMut<'_> {
+    type Item = &'b mut Crab;
+    type IntoIter = IterMut<'b, Crab>;
+
+    fn into_iter(self) -> IterMut<'b, Crab> {
+        self.iter_mut()
+    }
+}
+
+impl Drop for CrabsMut<'_> {
+    fn drop(&mut self) {
+        self.beach.reindex();
+        for (index, (name, speed, hibernating)) in self.before.iter().enumerate() {
+            let crab = &self.beach.crabs[index];
+            if crab.name() != name || crab.speed() != *speed || crab.is_hibernating() != *hibernating {
+                self.beach.sync_clan_member(name, index);
+            }
+        }
+    }
+}
+
+// Crabs (by beach index) that share reefs, and the reefs they hunt in.
+struct HuntGroup {
+    crabs: Vec<usize>,
+    reefs: Vec<Rc<RefCell<Reef>>>,
+}
+
+// A hunting group moved out of its shared handles so that it can be sent to a worker thread.
+struct HuntJob {
+    seed: u64,
+    crabs: Vec<Detached>,
+    // orders[i] lists the positions in `reefs` of the reefs crab i hunts in, in its own order.
+    orders: Vec<Vec<usize>>,
+    reefs: Vec<Reef>,
+    caught: usize,
+}
+
+impl HuntJob {
+    // Moves the group's crabs and reefs out of the beach and their handles until the hunt is over.
+    fn new(crabs: &mut Arena<Crab>, group: &HuntGroup, seed: u64) -> HuntJob {
+        let positions: HashMap<*const RefCell<Reef>, usize> =
+            group.reefs.iter().enumerate().map(|(i, reef)| (Rc::as_ptr(reef), i)).collect();
+        HuntJob {
+            seed,
+            orders: group
+                .crabs
+                .iter()
+                .map(|&index| crabs[index].reefs().map(|reef| positions[&Rc::as_ptr(&reef)]).collect())
+                .collect(),
+            crabs: group.crabs.iter().map(|&index| crabs[index].detach()).collect(),
+            reefs: group.reefs.iter().map(|reef| std::mem::replace(&mut *reef.borrow_mut(), Reef::new())).collect(),
+            caught: 0,
+        }
+    }
+
+    fn run(mut self) -> HuntJob {
+        let mut rng = seeded(self.seed);
+        let mut hunted = Vec::with_capacity(self.crabs.len());
+        for (crab, order) in self.crabs.into_iter().zip(self.orders.iter()) {
+            let mut crab = crab.into_crab();
+            if crab.hunt_among(&mut self.reefs, order, &mut rng) {
+                self.caught += 1;
+            }
+            hunted.push(crab.detach());
+        }
+        self.crabs = hunted;
+        self
+    }
+}
+
+// Returns the id after a crab id read from a save, or an Err if there is none, since a beach
+// hands out ids after the highest it has seen.
+fn id_after(id: CrabId, line: usize) -> Result<CrabId, ParseError> {
+    id.checked_add(1).ok_or_else(|| ParseError::new(line, format!("crab id {} is too large", id)))
+}
+
+// Returns an Err unless both of a crab's parents, read from a save, are older than it, as
+// `Beach::relatedness` relies on.
+fn check_parents(id: CrabId, parents: Option<(CrabId, CrabId)>, line: usize) -> Result<(), ParseError> {
+    match parents {
+        Some((p1, p2)) if p1 >= id || p2 >= id => {
+            Err(ParseError::new(line, format!("crab {} cannot have parents {} and {}, which are not older", id, p1, p2)))
+        }
+        _ => Ok(()),
     }
-    
 }
@@ -0,0 +1,245 @@
+//! Behavior trees that decide what a crab does each step.
+//!
+//! A crab with a behavior tree (see `Crab::set_behavior`) does not hunt in a `Simulation`'s
+//! hunting phase like other crabs. Instead, the simulation runs its tree once a step, right
+//! after the other crabs have hunted. A tree is made of conditions on the crab, actions it can
+//! take, and composites that combine them, and running it either succeeds or fails:
+//!
+//! ```text
+//! Selector                      the first child that succeeds
+//!   Sequence                    every child in turn, until one fails
+//!     Condition(Hungry(5))        energy below 5
+//!     Action(Hunt)                hunt once
+//!   Sequence
+//!     Condition(Stage(Juvenile))
+//!     Action(Train)               spend energy to get faster
+//!   Action(SeekMate)              breed with another adult on the beach
+//!   Action(Migrate)               move to the least crowded other beach
+//! ```
+//!
+//! This is `Behavior::default()`: hunt if hungry, else train while young, else seek a mate,
+//! else migrate. Behavior trees are not saved with the crab.
+
+use crate::crab::{Crab, CrabId, Stage};
+use crate::ocean::Ocean;
+use crate::rng::OceanRng;
+use rand::Rng;
+
+/// The energy below which the default tree's crabs go hunting.
+pub const HUNGRY_BELOW: u32 = 5;
+
+/// The energy one `Action::Train` costs.
+pub const TRAINING_COST: u32 = 2;
+
+/// How much faster one `Action::Train` makes a crab.
+pub const TRAINING_GAIN: f64 = 0.25;
+
+/// A node of a behavior tree.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Behavior {
+    /** Runs each child in order, failing at the first that fails. Succeeds if they all do. */
+    Sequence(Vec<Behavior>),
+    /** Runs each child in order, succeeding at the first that succeeds. Fails if none do. */
+    Selector(Vec<Behavior>),
+    /** Runs the child, succeeding if it fails and failing if it succeeds. */
+    Invert(Box<Behavior>),
+    /** Succeeds if the condition holds for the crab. */
+    Condition(Condition),
+    /** Takes the action, succeeding if the crab could take it. */
+    Action(Action),
+}
+
+/// Something a behavior tree can check about its crab.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Condition {
+    /** The crab has less than this much energy. */
+    Hungry(u32),
+    /** The crab is in this stage of life. */
+    Stage(Stage),
+    /** The crab has discovered at least one reef. */
+    HasReef,
+}
+
+/// Something a behavior tree can have its crab do.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    /**
+     * Hunts once, as in the simulation's hunting phase. Fails without hunting if the crab knows
+     * no reef, or if the weather or its zone keeps it sheltering.
+     */
+    Hunt,
+    /** Spends `TRAINING_COST` energy to become `TRAINING_GAIN` faster. Fails if the crab has too little energy. */
+    Train,
+    /**
+     * Breeds with the first other adult or elder crab on the beach it can breed with (see
+     * `Beach::breed_crabs_with_rng`). Fails if the crab is a juvenile or no crab will do.
+     */
+    SeekMate,
+    /** Moves to the least crowded other beach (see `Ocean::migrate`). Fails if there is none. */
+    Migrate,
+}
+
+impl Default for Behavior {
+    fn default() -> Behavior {
+        Behavior::Selector(vec![
+            Behavior::Sequence(vec![
+                Behavior::Condition(Condition::Hungry(HUNGRY_BELOW)),
+                Behavior::Action(Action::Hunt),
+            ]),
+            Behavior::Sequence(vec![
+                Behavior::Condition(Condition::Stage(Stage::Juvenile)),
+                Behavior::Action(Action::Train),
+            ]),
+            Behavior::Action(Action::SeekMate),
+            Behavior::Action(Action::Migrate),
+        ])
+    }
+}
+
+impl Condition {
+    /**
+     * Returns whether the condition holds for `crab`.
+     */
+    pub fn holds(&self, crab: &Crab) -> bool {
+        match *self {
+            Condition::Hungry(energy) => crab.energy() < energy,
+            Condition::Stage(stage) => crab.stage() == stage,
+            Condition::HasReef => crab.reefs().next().is_some(),
+        }
+    }
+}
+
+/// What the behavior trees run in one step did, for the step's `StepReport`.
+#[derive(Debug, Default)]
+pub(crate) struct Tally {
+    pub(crate) hunts: usize,
+    pub(crate) catches: usize,
+    pub(crate) migrated: usize,
+}
+
+// Where the crab running a tree is, by beach index and id. It changes when the crab migrates.
+type Place = (usize, CrabId);
+
+// Runs the behavior tree of every living, non-molting, awake crab that has one, beach by beach
+// and crab by crab. Weather and zones keep crabs from hunting as in the hunting phase, with the
+// weather's chance `hunt_chance`.
+pub(crate) fn run_all(ocean: &mut Ocean, hunt_chance: f64, rng: &mut dyn OceanRng) -> Tally {
+    let mut tally = Tally::default();
+    // Listed up front, so crabs that migrate or are born this step do not run again.
+    let places: Vec<Place> = ocean
+        .beaches()
+        .enumerate()
+        .flat_map(|(b, beach)| beach.crabs().filter(|crab| crab.behavior().is_some()).map(move |crab| (b, crab.id())))
+        .collect();
+    for mut place in places {
+        let tree = match find(ocean, place) {
+            Some(crab) if !crab.is_dead() && !crab.is_molting() && !crab.is_hibernating() => crab.behavior().cloned(),
+            _ => None,
+        };
+        if let Some(tree) = tree {
+            tree.run(ocean, &mut place, hunt_chance, rng, &mut tally);
+        }
+    }
+    tally
+}
+
+impl Behavior {
+    // Runs this node for the crab at `place`, returning true if it succeeded.
+    fn run(&self, ocean: &mut Ocean, place: &mut Place, hunt_chance: f64, rng: &mut dyn OceanRng, tally: &mut Tally) -> bool {
+        match self {
+            Behavior::Sequence(children) => {
+                for child in children {
+                    if !child.run(ocean, place, hunt_chance, rng, tally) {
+                        return false;
+                    }
+                }
+                true
+            }
+            Behavior::Selector(children) => {
+                for child in children {
+                    if child.run(ocean, place, hunt_chance, rng, tally) {
+                        return true;
+                    }
+                }
+                false
+            }
+            Behavior::Invert(child) => !child.run(ocean, place, hunt_chance, rng, tally),
+            Behavior::Condition(condition) => find(ocean, *place).is_some_and(|crab| condition.holds(crab)),
+            Behavior::Action(action) => action.take(ocean, place, hunt_chance, rng, tally),
+        }
+    }
+}
+
+impl Action {
+    // Has the crab at `place` take this action, returning true if it could.
+    fn take(&self, ocean: &mut Ocean, place: &mut Place, hunt_chance: f64, rng: &mut dyn OceanRng, tally: &mut Tally) -> bool {
+        let (b, id) = *place;
+        let Some(beach) = ocean.beaches_mut().nth(b) else {
+            return false;
+        };
+        let Some(index) = beach.find_crab_by_id(id) else {
+            return false;
+        };
+        match self {
+            Action::Hunt => {
+                if beach.get_crab(index).reefs().next().is_none() {
+                    return false;
+                }
+                let chance = hunt_chance * beach.zone_hunt_chances()[index];
+                if chance < 1.0 && !rng.gen_bool(chance) {
+                    return false;
+                }
+                tally.hunts += 1;
+                if beach.hunters_mut().nth(index).is_some_and(|crab| crab.hunt_with_rng(rng)) {
+                    tally.catches += 1;
+                }
+                true
+            }
+            Action::Train => beach.get_crab_by_id_mut(id).is_some_and(|mut crab| crab.train(TRAINING_COST, TRAINING_GAIN)),
+            Action::SeekMate => {
+                let seeker = beach.get_crab(index);
+                if seeker.stage() == Stage::Juvenile {
+                    return false;
+                }
+                let mut n = seeker.offspring() + 1;
+                let name = loop {
+                    let name = format!("{} {}", seeker.name(), n);
+                    if beach.find_crabs_by_name(&name).is_empty() {
+                        break name;
+                    }
+                    n += 1;
+                };
+                for mate in 0..beach.size() {
+                    let candidate = beach.get_crab(mate);
+                    if mate == index || candidate.stage() == Stage::Juvenile || candidate.is_molting() || candidate.is_hibernating() {
+                        continue;
+                    }
+                    if beach.breed_crabs_with_rng(index, mate, name.clone(), rng).is_ok() {
+                        return true;
+                    }
+                }
+                false
+            }
+            Action::Migrate => {
+                let sizes: Vec<usize> = ocean.beaches().map(|beach| beach.size()).collect();
+                let Some(to) = (0..sizes.len()).filter(|&to| to != b).min_by_key(|&to| sizes[to]) else {
+                    return false;
+                };
+                match ocean.migrate(id, b, to) {
+                    Ok(new_id) => {
+                        *place = (to, new_id);
+                        tally.migrated += 1;
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+        }
+    }
+}
+
+// Returns the crab at `place`, if it is still there.
+fn find(ocean: &Ocean, (b, id): Place) -> Option<&Crab> {
+    let beach = ocean.beaches().nth(b)?;
+    beach.find_crab_by_id(id).map(|index| beach.get_crab(index))
+}
@@ -0,0 +1,235 @@
+//! A command-line driver for a beach saved in a state file.
+//!
+//! Every command loads the beach from the state file (starting with an empty beach if the file
+//! does not exist yet), applies the command, and saves the beach back if the command changed it.
+//!
+//! `ocean-cli repl` instead runs the same commands against an in-memory beach (see `repl`), and
+//! `ocean-cli batch` runs a saved simulation once per seed and prints a CSV summary of each run
+//! (see `ocean::batch`).
+
+mod repl;
+
+use ocean::batch::{run_batch, to_csv, BatchConfig};
+use ocean::beach::Beach;
+use ocean::color::Color;
+use ocean::config::SimConfig;
+use ocean::crab::Crab;
+use ocean::diet::Diet;
+use ocean::events::OceanEvent;
+use ocean::leaderboard::RankBy;
+use ocean::reef::Reef;
+use ocean::rng::seeded;
+use std::fs;
+use std::io;
+use std::process::ExitCode;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "\
+usage: ocean-cli <state-file> <command> [args...]
+       ocean-cli repl [state-file]
+       ocean-cli batch <simulation-file> <config-file> <ticks> <seed>...";
+
+const COMMAND_HELP: &str = "\
+commands:
+  add-crab <name> <speed> <color> <diet>   add a crab; color is a name (e.g. blue) or #rrggbb
+  breed <parent1> <parent2> <child> [--seed <n>]
+                                           breed two crabs into a new one, drawing its diet and
+                                           pattern from a generator seeded with n, or the clock
+  hunt <crab> <reef-file>                  have a crab hunt in a saved reef
+  clan add-member <clan> <crab>            add a crab to a clan, creating the clan if needed
+  compete <clan1> <clan2>                  compare two clans by average speed
+  stats                                    show the beach's crabs and clan standings
+  leaderboard <n> <criterion>...           rank the top n crabs by speed, hunts, energy,
+                                           offspring, age or reputation, in that priority";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(output) => {
+            if !output.is_empty() {
+                println!("{}", output);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("ocean-cli: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Runs the command line in `args`, returning what to print.
+fn run(args: &[String]) -> Result<String, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["repl"] => repl::run(Beach::new()),
+        ["repl", path] => repl::run(load_beach(path)?),
+        ["batch", world, config, ticks, seeds @ ..] if !seeds.is_empty() => batch(world, config, ticks, seeds),
+        [path, command, args @ ..] => {
+            let mut beach = load_beach(path)?;
+            let (output, changed) = apply(&mut beach, command, args)?;
+            if changed {
+                fs::write(path, beach.save()).map_err(|e| format!("{}: {}", path, e))?;
+            }
+            Ok(output)
+        }
+        _ => Err(format!("{}\n\n{}", USAGE, COMMAND_HELP)),
+    }
+}
+
+/// The commands `apply` understands.
+const COMMANDS: [&str; 7] = ["add-crab", "breed", "hunt", "clan", "compete", "stats", "leaderboard"];
+
+// Applies a single command to `beach`, returning what to print and whether the beach changed.
+fn apply(beach: &mut Beach, command: &str, args: &[&str]) -> Result<(String, bool), String> {
+    let output = match (command, args) {
+        ("add-crab", [name, speed, color, diet]) => {
+            if !beach.find_crabs_by_name(name).is_empty() {
+                return Err(format!("a crab named {} already lives on this beach", name));
+            }
+            let speed: u32 = speed.parse().map_err(|_| format!("invalid speed: {:?}", speed))?;
+            let crab = Crab::new(name.to_string(), speed, parse_color(color)?, diet.parse::<Diet>()?);
+            beach.add_crab(crab);
+            format!("added {}", name)
+        }
+        ("breed", [parent1, parent2, child, seed @ ..]) => {
+            let seed = match seed {
+                [] => clock_seed(),
+                ["--seed", seed] => seed.parse().map_err(|_| format!("invalid seed: {:?}", seed))?,
+                _ => return Err(format!("unknown command or wrong arguments: {}\n\n{}", command, COMMAND_HELP)),
+            };
+            if !beach.find_crabs_by_name(child).is_empty() {
+                return Err(format!("a crab named {} already lives on this beach", child));
+            }
+            let i = crab_index(beach, parent1)?;
+            let j = crab_index(beach, parent2)?;
+            // Each run of the program starts the crate's fixed-seed generator over, so breed
+            // with a generator of our own to keep successive runs from breeding alike.
+            beach.breed_crabs_with_rng(i, j, child.to_string(), &mut seeded(seed))?;
+            let crab = beach.get_crab(beach.size() - 1);
+            format!("{} was born: {:?} diet, color {}", child, crab.diet(), crab.color().to_hex())
+        }
+        ("hunt", [name, reef_path]) => {
+            let index = crab_index(beach, name)?;
+            let text = fs::read_to_string(reef_path).map_err(|e| format!("{}: {}", reef_path, e))?;
+            let mut reef = Reef::load(&text).map_err(|e| format!("{}: {}", reef_path, e))?;
+            let mut crab = beach.get_crab_mut(index);
+            crab.hunt_in(&mut reef);
+            let report = describe_hunt(&crab.drain_events(), name);
+            fs::write(reef_path, reef.save()?).map_err(|e| format!("{}: {}", reef_path, e))?;
+            report
+        }
+        ("clan", ["add-member", clan, name]) => {
+            beach.try_add_member_to_clan(clan, name)?;
+            format!("{} joined {}", name, clan)
+        }
+        ("compete", [clan1, clan2]) => {
+            for clan in [clan1, clan2] {
+                if beach.get_clan_system().get_clan_member_count(clan) == 0 {
+                    return Err(format!("clan {} has no members", clan));
+                }
+            }
+            match beach.get_winner_clan(clan1, clan2)? {
+                Some(winner) => format!("{} wins", winner),
+                None => String::from("draw"),
+            }
+        }
+        ("stats", []) => return Ok((stats(beach), false)),
+        ("leaderboard", [n, criteria @ ..]) if !criteria.is_empty() => {
+            let n: usize = n.parse().map_err(|_| format!("invalid count: {:?}", n))?;
+            let criteria = criteria.iter().map(|c| c.parse()).collect::<Result<Vec<RankBy>, _>>()?;
+            let entries = beach.leaderboard(&criteria, n);
+            let lines: Vec<String> = entries.iter().map(|entry| entry.to_string()).collect();
+            return Ok((lines.join("\n"), false));
+        }
+        _ => return Err(format!("unknown command or wrong arguments: {}\n\n{}", command, COMMAND_HELP)),
+    };
+    Ok((output, true))
+}
+
+// A seed that differs from one run of the program to the next.
+fn clock_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
+
+// Runs the simulation saved in `world` once per seed and returns the runs' summaries as CSV.
+fn batch(world: &str, config: &str, ticks: &str, seeds: &[&str]) -> Result<String, String> {
+    let ticks: u64 = ticks.parse().map_err(|_| format!("invalid tick count: {:?}", ticks))?;
+    let seeds = seeds
+        .iter()
+        .map(|seed| seed.parse().map_err(|_| format!("invalid seed: {:?}", seed)))
+        .collect::<Result<Vec<u64>, _>>()?;
+    let text = fs::read_to_string(world).map_err(|e| format!("{}: {}", world, e))?;
+    let batch = BatchConfig::from_snapshot(&text, ticks).map_err(|e| format!("{}: {}", world, e))?;
+    let batch = batch.with_config(SimConfig::load(config).map_err(|e| format!("{}: {}", config, e))?);
+    let runs = run_batch(&batch, &seeds)?;
+    Ok(to_csv(&runs).trim_end().to_string())
+}
+
+fn load_beach(path: &str) -> Result<Beach, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => Beach::load(&text).map_err(|e| format!("{}: {}", path, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Beach::new()),
+        Err(e) => Err(format!("{}: {}", path, e)),
+    }
+}
+
+fn parse_color(s: &str) -> Result<Color, String> {
+    match s {
+        "red" => Ok(Color::new_red()),
+        "green" => Ok(Color::new_green()),
+        "blue" => Ok(Color::new_blue()),
+        "orange" => Ok(Color::new_orange()),
+        "teal" => Ok(Color::new_teal()),
+        "violet" => Ok(Color::new_violet()),
+        _ => Color::from_hex(s),
+    }
+}
+
+fn crab_index(beach: &Beach, name: &str) -> Result<usize, String> {
+    beach
+        .find_crab_index(name)
+        .ok_or_else(|| format!("no crab named {} lives on this beach", name))
+}
+
+fn describe_hunt(events: &[OceanEvent], name: &str) -> String {
+    let mut lines = Vec::new();
+    for event in events {
+        match event {
+            OceanEvent::PreyEscaped { prey, .. } => lines.push(format!("{} escaped", prey)),
+            OceanEvent::PreyCaught { prey, .. } => lines.push(format!("{} caught {}", name, prey)),
+            _ => {}
+        }
+    }
+    if !events.iter().any(|event| matches!(event, OceanEvent::PreyCaught { .. })) {
+        lines.push(format!("{} caught nothing", name));
+    }
+    lines.join("\n")
+}
+
+fn stats(beach: &Beach) -> String {
+    let mut lines = vec![format!("{} crabs", beach.size())];
+    for crab in beach.crabs() {
+        lines.push(format!(
+            "  {}: speed {}, {:?} diet, color {}, {} hunts, {} offspring, {} energy",
+            crab.name(),
+            crab.speed(),
+            crab.diet(),
+            crab.color().to_hex(),
+            crab.hunts(),
+            crab.offspring(),
+            crab.energy()
+        ));
+    }
+    let clans = beach.get_clan_system();
+    lines.push(format!("{} clans", clans.get_clan_count()));
+    for (clan_id, reputation) in clans.leaderboard() {
+        lines.push(format!(
+            "  {}: {} members, {} reputation",
+            clan_id,
+            clans.get_clan_member_count(&clan_id),
+            reputation
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,111 @@
+//! An interactive shell that runs `ocean-cli` commands against an in-memory beach.
+//!
+//! Besides the usual commands, the shell understands `help`, `save <file>`, `complete <line>`,
+//! and `quit`. `complete` lists the ways the last word of the rest of its line can be finished:
+//! command names, then crab or clan names depending on the command. It is a command rather than
+//! tab completion because the shell reads standard input as-is, without taking over the terminal.
+
+use super::{apply, COMMANDS, COMMAND_HELP};
+use ocean::beach::Beach;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+// Starts with a newline rather than a backslash, which would strip the first line's indentation.
+const REPL_HELP: &str = "
+  help                                     show this help
+  save <file>                              save the beach, to load with `ocean-cli <file> ...`
+  complete <line>                          list the ways the last word of <line> can be finished
+                                           (end <line> with a space to list whole next words)
+  quit                                     leave the shell (so does end of input)";
+
+/**
+ * Reads commands from standard input until `quit` or the end of input.
+ */
+pub fn run(mut beach: Beach) -> Result<String, String> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("ocean> ");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|e| e.to_string())?,
+            None => return Ok(String::new()),
+        };
+
+        if let Some(partial) = completion_request(&line) {
+            println!("{}", complete(&beach, partial).join("  "));
+            continue;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] => return Ok(String::new()),
+            ["help"] => Ok(format!("{}{}", COMMAND_HELP, REPL_HELP)),
+            ["save", path] => fs::write(path, beach.save())
+                .map(|_| format!("saved to {}", path))
+                .map_err(|e| format!("{}: {}", path, e)),
+            [command, args @ ..] => apply(&mut beach, command, args).map(|(output, _)| output),
+        };
+        match result {
+            Ok(output) => println!("{}", output),
+            Err(message) => println!("error: {}", message),
+        }
+    }
+}
+
+// Returns the rest of the line if it is a `complete` command, keeping any trailing whitespace,
+// which asks for the next word rather than the end of the last one.
+fn completion_request(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    match line.strip_prefix("complete") {
+        Some("") => Some(""),
+        Some(rest) if rest.starts_with(char::is_whitespace) => Some(&rest[1..]),
+        _ => None,
+    }
+}
+
+// What kind of word each argument of a command is, for completion.
+#[derive(Clone, Copy)]
+enum Arg {
+    Crab,
+    Clan,
+    Keyword(&'static str),
+    Other,
+}
+
+fn argument_kinds(command: &str) -> &'static [Arg] {
+    match command {
+        "breed" => &[Arg::Crab, Arg::Crab, Arg::Other, Arg::Keyword("--seed")],
+        "hunt" => &[Arg::Crab, Arg::Other],
+        "clan" => &[Arg::Keyword("add-member"), Arg::Clan, Arg::Crab],
+        "compete" => &[Arg::Clan, Arg::Clan],
+        _ => &[],
+    }
+}
+
+// Returns the sorted completions of the last word of `partial` (an empty word if `partial` ends
+// in whitespace).
+fn complete(beach: &Beach, partial: &str) -> Vec<String> {
+    let mut words: Vec<&str> = partial.split_whitespace().collect();
+    let prefix = if partial.is_empty() || partial.ends_with(char::is_whitespace) {
+        ""
+    } else {
+        words.pop().unwrap_or("")
+    };
+
+    let candidates: Vec<String> = match words.split_first() {
+        None => COMMANDS.iter().chain(["help", "save", "complete", "quit"].iter()).map(|c| c.to_string()).collect(),
+        Some((command, args)) => match argument_kinds(command).get(args.len()) {
+            Some(Arg::Crab) => beach.crabs().map(|crab| crab.name().to_string()).collect(),
+            Some(Arg::Clan) => beach.get_clan_system().get_clan_ids(),
+            Some(Arg::Keyword(keyword)) => vec![keyword.to_string()],
+            Some(Arg::Other) | None => Vec::new(),
+        },
+    };
+
+    let mut matches: Vec<String> = candidates.into_iter().filter(|c| c.starts_with(prefix)).collect();
+    matches.sort();
+    matches.dedup();
+    matches
+}
@@ -0,0 +1,138 @@
+//! Just enough HTTP/1.1 for `ocean-server`: one request per connection, no request bodies, and
+//! responses that are either complete or a stream of server-sent events.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+
+/// The most bytes read for a request line and its headers together; the rest is ignored.
+const MAX_REQUEST_BYTES: u64 = 16 * 1024;
+
+/// A request line and its query string. Headers and bodies are ignored.
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /** The path's segments, e.g. `["beaches", "0"]` for `/beaches/0`. */
+    pub path: Vec<String>,
+    pub query: HashMap<String, String>,
+}
+
+impl Request {
+    /**
+     * Reads a request from `stream`, up to the blank line that ends its headers. At most
+     * `MAX_REQUEST_BYTES` are read, so a request line that doesn't fit is malformed.
+     */
+    pub fn read(stream: &TcpStream) -> io::Result<Request> {
+        let mut reader = BufReader::new(stream.take(MAX_REQUEST_BYTES));
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request line too long"));
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed request line"));
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let request = Request {
+            method: method.to_string(),
+            path: path.split('/').filter(|segment| !segment.is_empty()).map(decode).collect(),
+            query: query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    (decode(key), decode(value))
+                })
+                .collect(),
+        };
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+                return Ok(request);
+            }
+        }
+    }
+
+    /**
+     * Returns the query parameter `key`, or an Err naming it if it is missing.
+     */
+    pub fn param(&self, key: &str) -> Result<&str, String> {
+        self.query.get(key).map(String::as_str).ok_or_else(|| format!("missing query parameter {:?}", key))
+    }
+}
+
+// Decodes `%xx` escapes and `+` in a path segment or query parameter.
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+                out.push(u8::from_str_radix(hex, 16).unwrap());
+                i += 2;
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// A complete response with a JSON body.
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(body: String) -> Response {
+        Response { status: 200, body }
+    }
+
+    /**
+     * A response whose body is `{"error": message}`.
+     */
+    pub fn error(status: u16, message: &str) -> Response {
+        Response { status, body: format!("{{\"error\":{}}}", ocean::persist::json_string(message)) }
+    }
+
+    pub fn write_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason(self.status),
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+/**
+ * Starts a stream of server-sent events on `stream`; send each event with `write_event`.
+ */
+pub fn start_event_stream(stream: &mut TcpStream) -> io::Result<()> {
+    write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n")?;
+    stream.flush()
+}
+
+pub fn write_event(stream: &mut TcpStream, data: &str) -> io::Result<()> {
+    write!(stream, "data: {}\n\n", data)?;
+    stream.flush()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
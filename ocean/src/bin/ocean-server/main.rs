@@ -0,0 +1,255 @@
+//! An HTTP server exposing a simulation as JSON, for front ends (the `server` feature).
+//!
+//! The simulation runs in a `SharedSimulation`, so requests are handled on threads of their own
+//! and take turns with the simulation. Every response is JSON in the shapes of `ocean::wasm`, and
+//! errors are `{"error": message}`.
+//!
+//! ```text
+//! GET  /ocean                                        the whole ocean (see `ocean_json`)
+//! GET  /beaches/<b>                                  a beach's crabs and clans (see `beach_json`)
+//! GET  /beaches/<b>/leaderboard?n=<n>&by=<criteria>  the top n crabs, criteria separated by commas
+//! POST /step?steps=<n>                               run n steps (default 1, at most MAX_STEPS), returning their reports
+//! POST /beaches/<b>/hunt                             run a feeding round, returning each crab's outcome
+//! POST /beaches/<b>/breed?parent1=&parent2=&child=   breed two crabs, by name
+//! POST /beaches/<b>/compete?clan1=&clan2=            run a clan competition, returning the winner
+//! GET  /events                                       server-sent events, one per `OceanEvent`
+//! ```
+
+mod http;
+
+use http::{Request, Response};
+use ocean::error::OceanError;
+use ocean::events::OceanEvent;
+use ocean::leaderboard::RankBy;
+use ocean::ocean::Ocean;
+use ocean::persist::json_string;
+use ocean::shared::SharedSimulation;
+use ocean::simulation::Simulation;
+use ocean::wasm::{beach_json, events_json, ocean_json, report_json};
+use std::net::{TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const USAGE: &str = "usage: ocean-server [--addr <host:port>] [snapshot-file]";
+
+/// The most steps one `POST /step` may run, so a request can't hold the simulation indefinitely.
+const MAX_STEPS: u64 = 5000;
+
+// The event streams open on `GET /events`. Each gets every event as a JSON object.
+type Streams = Arc<Mutex<Vec<Sender<String>>>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match serve(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("ocean-server: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn serve(args: &[String]) -> Result<(), String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (addr, snapshot) = match args.as_slice() {
+        [] => ("127.0.0.1:8080", None),
+        [path] => ("127.0.0.1:8080", Some(*path)),
+        ["--addr", addr] => (*addr, None),
+        ["--addr", addr, path] => (*addr, Some(*path)),
+        _ => return Err(USAGE.to_string()),
+    };
+    let shared = match snapshot {
+        Some(path) => SharedSimulation::load(path).map_err(|e| format!("{}: {}", path, e))?,
+        None => SharedSimulation::spawn(|| Simulation::new(Ocean::new(), 0)),
+    };
+    let streams: Streams = Arc::new(Mutex::new(Vec::new()));
+    let publish_to = streams.clone();
+    shared.with(move |simulation| {
+        simulation.subscribe(Box::new(move |event: &OceanEvent| {
+            let data = events_json(std::slice::from_ref(event));
+            // The object inside the one-element array.
+            let data = data[1..data.len() - 1].to_string();
+            publish_to.lock().unwrap().retain(|stream| stream.send(data.clone()).is_ok());
+        }))
+    })?;
+
+    let listener = TcpListener::bind(addr).map_err(|e| format!("{}: {}", addr, e))?;
+    eprintln!("ocean-server: listening on http://{}", addr);
+    for stream in listener.incoming().flatten() {
+        let shared = shared.clone();
+        let streams = streams.clone();
+        thread::spawn(move || handle(stream, &shared, &streams));
+    }
+    Ok(())
+}
+
+// Answers one connection. I/O errors just drop it, since the client has gone.
+fn handle(mut stream: TcpStream, shared: &SharedSimulation, streams: &Streams) {
+    let Ok(request) = Request::read(&stream) else {
+        let _ = Response::error(400, "malformed request").write_to(&mut stream);
+        return;
+    };
+    if request.method == "GET" && request.path == ["events"] {
+        let (sender, events) = mpsc::channel();
+        streams.lock().unwrap().push(sender);
+        if http::start_event_stream(&mut stream).is_err() {
+            return;
+        }
+        for data in events {
+            if http::write_event(&mut stream, &data).is_err() {
+                return;
+            }
+        }
+        return;
+    }
+    let response = route(&request, shared).unwrap_or_else(|(status, message)| Response::error(status, &message));
+    let _ = response.write_to(&mut stream);
+}
+
+// Runs a request against the simulation. Errors are a status and a message.
+fn route(request: &Request, shared: &SharedSimulation) -> Result<Response, (u16, String)> {
+    let path: Vec<&str> = request.path.iter().map(String::as_str).collect();
+    let bad_request = |message: String| (400, message);
+    let body = match (request.method.as_str(), path.as_slice()) {
+        ("GET", ["ocean"]) => shared.with(|simulation| ocean_json(simulation)).map_err(status)?,
+        ("GET", ["beaches", beach]) => {
+            let beach = parse_index(beach)?;
+            with_beach(shared, beach, move |simulation| Ok(beach_json(nth_beach(simulation, beach))))?
+        }
+        ("GET", ["beaches", beach, "leaderboard"]) => {
+            let beach = parse_index(beach)?;
+            let n: usize = parse(request.query.get("n").map_or("10", String::as_str), "count")?;
+            let criteria = request
+                .param("by")
+                .map_err(bad_request)?
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<RankBy>, _>>()
+                .map_err(bad_request)?;
+            with_beach(shared, beach, move |simulation| {
+                let entries: Vec<String> = nth_beach(simulation, beach)
+                    .leaderboard(&criteria, n)
+                    .iter()
+                    .map(|entry| {
+                        let scores: Vec<String> = entry
+                            .scores
+                            .iter()
+                            .map(|(by, score)| format!("{}:{}", json_string(by.name()), score))
+                            .collect();
+                        format!(
+                            "{{\"rank\":{},\"crab\":{},\"scores\":{{{}}}}}",
+                            entry.rank,
+                            json_string(&entry.crab),
+                            scores.join(",")
+                        )
+                    })
+                    .collect();
+                Ok(format!("[{}]", entries.join(",")))
+            })?
+        }
+        ("POST", ["step"]) => {
+            let steps: u64 = parse(request.query.get("steps").map_or("1", String::as_str), "step count")?;
+            if steps > MAX_STEPS {
+                return Err((400, format!("step count {} is over the limit of {}", steps, MAX_STEPS)));
+            }
+            let reports = shared.run(steps).map_err(status)?;
+            let reports: Vec<String> = reports.iter().map(report_json).collect();
+            format!("[{}]", reports.join(","))
+        }
+        ("POST", ["beaches", beach, "hunt"]) => {
+            let beach = parse_index(beach)?;
+            with_beach(shared, beach, move |simulation| {
+                let outcomes = simulation.ocean_mut().beaches_mut().nth(beach).unwrap().feeding_round();
+                simulation.publish_events();
+                let outcomes: Vec<String> = outcomes
+                    .iter()
+                    .map(|outcome| {
+                        format!(
+                            "{{\"crab\":{},\"caught\":{},\"energy_gained\":{}}}",
+                            json_string(&outcome.crab),
+                            outcome.caught,
+                            outcome.energy_gained
+                        )
+                    })
+                    .collect();
+                Ok(format!("[{}]", outcomes.join(",")))
+            })?
+        }
+        ("POST", ["beaches", beach, "breed"]) => {
+            let beach = parse_index(beach)?;
+            let parent1 = request.param("parent1").map_err(bad_request)?.to_string();
+            let parent2 = request.param("parent2").map_err(bad_request)?.to_string();
+            let child = request.param("child").map_err(bad_request)?.to_string();
+            with_beach(shared, beach, move |simulation| {
+                let (i, j) = {
+                    let crabs = nth_beach(simulation, beach);
+                    let index = |name: &str| crabs.find_crab_index(name).ok_or(OceanError::CrabNotFound(name.to_string()));
+                    (index(&parent1)?, index(&parent2)?)
+                };
+                simulation.breed_crabs(beach, i, j, child)?;
+                simulation.publish_events();
+                let crabs = nth_beach(simulation, beach);
+                Ok(beach_json(crabs))
+            })?
+        }
+        ("POST", ["beaches", beach, "compete"]) => {
+            let beach = parse_index(beach)?;
+            let clan1 = request.param("clan1").map_err(bad_request)?.to_string();
+            let clan2 = request.param("clan2").map_err(bad_request)?.to_string();
+            with_beach(shared, beach, move |simulation| {
+                let winner = simulation.ocean_mut().beaches_mut().nth(beach).unwrap().get_winner_clan(&clan1, &clan2)?;
+                simulation.publish_events();
+                Ok(format!("{{\"winner\":{}}}", winner.as_deref().map_or(String::from("null"), json_string)))
+            })?
+        }
+        (_, ["ocean"] | ["step"] | ["events"] | ["beaches", _] | ["beaches", _, "leaderboard" | "hunt" | "breed" | "compete"]) => {
+            return Err((405, format!("{} is not allowed here", request.method)))
+        }
+        _ => return Err((404, format!("no such endpoint: /{}", path.join("/")))),
+    };
+    Ok(Response::ok(body))
+}
+
+// Runs `f` against the simulation once the beach at index `beach` is known to exist.
+fn with_beach<F>(shared: &SharedSimulation, beach: usize, f: F) -> Result<String, (u16, String)>
+where
+    F: FnOnce(&mut Simulation) -> Result<String, OceanError> + Send + 'static,
+{
+    shared
+        .with(move |simulation| {
+            if beach >= simulation.ocean().beaches().len() {
+                return Err(OceanError::BeachNotFound(beach.to_string()));
+            }
+            f(simulation)
+        })
+        .map_err(status)?
+        .map_err(status)
+}
+
+fn nth_beach(simulation: &Simulation, beach: usize) -> &ocean::beach::Beach {
+    simulation.ocean().beaches().nth(beach).unwrap()
+}
+
+fn parse_index(s: &str) -> Result<usize, (u16, String)> {
+    parse(s, "beach index")
+}
+
+fn parse<T: std::str::FromStr>(s: &str, what: &str) -> Result<T, (u16, String)> {
+    s.parse().map_err(|_| (400, format!("invalid {}: {:?}", what, s)))
+}
+
+// The status to answer an error with.
+fn status(error: OceanError) -> (u16, String) {
+    let status = match error {
+        OceanError::CrabNotFound(_)
+        | OceanError::CrabIdNotFound(_)
+        | OceanError::ClanNotFound(_)
+        | OceanError::BeachNotFound(_)
+        | OceanError::ReefNotFound(_) => 404,
+        OceanError::SimulationStopped => 503,
+        _ => 400,
+    };
+    (status, error.to_string())
+}
@@ -0,0 +1,124 @@
+//! Runtime discovery of which feature-gated subsystems were compiled into this build.
+
+use crate::error::OceanError;
+use std::fmt;
+
+/// A subsystem that is only available when its cargo feature is enabled.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Capability {
+    /** Clan stockpiles and points (`src/economy.rs`). */
+    Economy,
+    /** Spans and events around hunts, breeding, and competitions (`src/trace.rs`). */
+    Trace,
+    /** Random generation and shrinking of crabs, beaches, reefs, and recipes (`src/arbitrary.rs`). */
+    Proptest,
+    /** Handles and JSON snapshots for JavaScript (`src/wasm.rs`). */
+    Wasm,
+    /** Drivers for the fuzz targets in `fuzz/` (`src/fuzz.rs`). */
+    Fuzzing,
+    /** Futures for awaiting a `SharedSimulation` (`src/nonblocking.rs`). */
+    Async,
+    /** Pure strategies in place of the fixed-seed generator, for bit-identical runs (`src/deterministic.rs`). */
+    Deterministic,
+    /** The `ocean-server` HTTP API over a shared simulation (`src/bin/ocean-server`). */
+    Server,
+}
+
+impl Capability {
+    /** Every known capability, whether or not it is compiled in. */
+    pub const ALL: [Capability; 8] = [
+        Capability::Economy,
+        Capability::Trace,
+        Capability::Proptest,
+        Capability::Wasm,
+        Capability::Fuzzing,
+        Capability::Async,
+        Capability::Deterministic,
+        Capability::Server,
+    ];
+
+    /**
+     * The name of the cargo feature that enables this capability.
+     */
+    pub fn feature(&self) -> &'static str {
+        match self {
+            Capability::Economy => "economy",
+            Capability::Trace => "trace",
+            Capability::Proptest => "proptest",
+            Capability::Wasm => "wasm",
+            Capability::Fuzzing => "fuzzing",
+            Capability::Async => "async",
+            Capability::Deterministic => "deterministic",
+            Capability::Server => "server",
+        }
+    }
+
+    /**
+     * Looks up a capability by its feature name, e.g. when reading the sections a scenario needs.
+     */
+    pub fn from_feature(feature: &str) -> Option<Capability> {
+        Capability::ALL.into_iter().find(|capability| capability.feature() == feature)
+    }
+
+    /**
+     * Returns true if this capability was compiled into this build.
+     */
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Capability::Economy => cfg!(feature = "economy"),
+            Capability::Trace => cfg!(feature = "trace"),
+            Capability::Proptest => cfg!(feature = "proptest"),
+            Capability::Wasm => cfg!(feature = "wasm"),
+            Capability::Fuzzing => cfg!(feature = "fuzzing"),
+            Capability::Async => cfg!(feature = "async"),
+            Capability::Deterministic => cfg!(feature = "deterministic"),
+            Capability::Server => cfg!(feature = "server"),
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.feature())
+    }
+}
+
+/// The set of capabilities compiled into this build, as returned by `ocean::capabilities()`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Capabilities {
+    enabled: Vec<Capability>,
+}
+
+impl Capabilities {
+    pub fn has(&self, capability: Capability) -> bool {
+        self.enabled.contains(&capability)
+    }
+
+    pub fn enabled(&self) -> std::slice::Iter<'_, Capability> {
+        self.enabled.iter()
+    }
+
+    /**
+     * Returns Ok if `capability` is available, or `OceanError::FeatureDisabled`, whose message
+     * tells the user how to enable it.
+     * Loaders should call this for every subsystem their input uses instead of skipping it.
+     */
+    pub fn require(&self, capability: Capability) -> Result<(), OceanError> {
+        if self.has(capability) {
+            return Ok(());
+        }
+        Err(OceanError::FeatureDisabled(capability))
+    }
+}
+
+/**
+ * Reports which feature-gated subsystems are compiled into this build.
+ */
+pub fn capabilities() -> Capabilities {
+    Capabilities {
+        enabled: Capability::ALL
+            .into_iter()
+            .filter(Capability::is_enabled)
+            .collect(),
+    }
+}
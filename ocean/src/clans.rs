@@ -1,44 +1,542 @@
-use std::collections::HashMap;
-use crate::color::Color;
+use std::collections::{HashMap, HashSet};
 use crate::crab::Crab;
 use crate::diet::Diet;
-use std::slice::Iter;
+use crate::error::OceanError;
+use crate::events::{self, OceanEvent};
+use crate::intern::{Interner, NameId};
+use crate::persist::{csv_field, join_record, json_string, parse_field, split_record, ParseError};
+use crate::registry::ReefId;
+
+/// Anything that can belong to a clan. Members are keyed by name and clans compete on speed.
+pub trait ClanMember: Clone {
+    fn name(&self) -> &str;
+    fn speed(&self) -> u32;
+
+    /**
+     * The member's speed, fractions included. Defaults to `speed`.
+     */
+    fn speed_f(&self) -> f64 {
+        self.speed() as f64
+    }
+
+    /**
+     * Whether the member counts toward its clan's average speed and competitions. Defaults to
+     * true.
+     */
+    fn is_active(&self) -> bool {
+        true
+    }
+}
+
+impl ClanMember for Crab {
+    fn name(&self) -> &str {
+        Crab::name(self)
+    }
+
+    fn speed(&self) -> u32 {
+        Crab::speed(self)
+    }
+
+    fn speed_f(&self) -> f64 {
+        Crab::speed_f(self)
+    }
+
+    fn is_active(&self) -> bool {
+        !self.is_hibernating()
+    }
+}
+
+// The speed a member adds to its clan's total: none if it is not active.
+fn counted_speed<M: ClanMember>(member: &M) -> u64 {
+    if member.is_active() {
+        member.speed() as u64
+    } else {
+        0
+    }
+}
+
+/// A condition a prospective member must meet to join a clan.
+/// Any `Fn(&M) -> Result<(), String>` closure can be used as a rule.
+pub trait RecruitmentRule<M> {
+    /**
+     * Returns Ok if `member` may join, or an Err string explaining why it was rejected.
+     */
+    fn check(&self, member: &M) -> Result<(), String>;
+}
+
+impl<M, F: Fn(&M) -> Result<(), String>> RecruitmentRule<M> for F {
+    fn check(&self, member: &M) -> Result<(), String> {
+        self(member)
+    }
+}
+
+/// Only members at least this fast may join.
+#[derive(Debug, Clone, Copy)]
+pub struct MinSpeed(pub u32);
+
+impl<M: ClanMember> RecruitmentRule<M> for MinSpeed {
+    fn check(&self, member: &M) -> Result<(), String> {
+        if member.speed() < self.0 {
+            return Err(format!(
+                "{} has speed {}, below the minimum of {}",
+                member.name(),
+                member.speed(),
+                self.0
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Only crabs with this diet may join.
+#[derive(Debug, Clone, Copy)]
+pub struct RequiredDiet(pub Diet);
+
+impl RecruitmentRule<Crab> for RequiredDiet {
+    fn check(&self, crab: &Crab) -> Result<(), String> {
+        if crab.diet() != self.0 {
+            return Err(format!("{} eats {:?}, but the clan requires {:?}", crab.name(), crab.diet(), self.0));
+        }
+        Ok(())
+    }
+}
+
+// The size limit and recruitment rules of a single clan.
+struct ClanRules<M> {
+    max_members: Option<usize>,
+    rules: Vec<Box<dyn RecruitmentRule<M>>>,
+}
+
+impl<M> Default for ClanRules<M> {
+    fn default() -> Self {
+        ClanRules {
+            max_members: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl<M> std::fmt::Debug for ClanRules<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ClanRules")
+            .field("max_members", &self.max_members)
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+/// How the members of a clan split the catch of a group hunt (see `Beach::group_hunt`).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum SharingRule {
+    /** Every member gets the same share. What cannot be split evenly goes to the first members. */
+    #[default]
+    Equal,
+    /**
+     * Members get shares in proportion to their speed. What is left after rounding down goes to
+     * the fastest members. If no member has any speed, the catch is split equally.
+     */
+    BySpeed,
+    /** The fastest member takes the whole catch. */
+    FastestTakesAll,
+}
+
+impl SharingRule {
+    /**
+     * Splits `total` energy between members with the given speeds, returning each member's share
+     * in the same order. Members with equal claims are favored in the order they are given.
+     */
+    pub fn split(&self, total: u32, speeds: &[u32]) -> Vec<u32> {
+        let n = speeds.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let speed_total: u64 = speeds.iter().map(|&speed| speed as u64).sum();
+        match self {
+            SharingRule::Equal => {
+                let share = total / n as u32;
+                let left = total as usize % n;
+                (0..n).map(|i| share + (i < left) as u32).collect()
+            }
+            SharingRule::BySpeed if speed_total == 0 => SharingRule::Equal.split(total, speeds),
+            SharingRule::BySpeed => {
+                let mut shares: Vec<u32> =
+                    speeds.iter().map(|&speed| (total as u64 * speed as u64 / speed_total) as u32).collect();
+                let left = (total - shares.iter().sum::<u32>()) as usize;
+                let mut fastest: Vec<usize> = (0..n).collect();
+                fastest.sort_by_key(|&i| std::cmp::Reverse(speeds[i]));
+                for &i in fastest.iter().take(left) {
+                    shares[i] += 1;
+                }
+                shares
+            }
+            SharingRule::FastestTakesAll => {
+                let fastest = (0..n).rev().max_by_key(|&i| speeds[i]).unwrap();
+                (0..n).map(|i| if i == fastest { total } else { 0 }).collect()
+            }
+        }
+    }
+}
+
+/// What a clan caught in a group hunt and how it was shared (see `Beach::group_hunt`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GroupHunt {
+    /** The kind of prey caught (see `Prey::kind`), or None if the clan caught nothing. */
+    pub prey: Option<String>,
+    /** The beach index of every member that took part, in beach order, with its share of energy. */
+    pub shares: Vec<(usize, u32)>,
+}
+
+/// The spread of speeds among a clan's members (see `ClanSystem::speed_distribution`), for
+/// comparing clans by more than their average speed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SpeedDistribution {
+    // Never empty, and sorted slowest first.
+    speeds: Vec<u32>,
+}
+
+impl SpeedDistribution {
+    /**
+     * Returns the distribution of the given speeds, or None if there are none.
+     */
+    pub fn of<I: IntoIterator<Item = u32>>(speeds: I) -> Option<SpeedDistribution> {
+        let mut speeds: Vec<u32> = speeds.into_iter().collect();
+        if speeds.is_empty() {
+            return None;
+        }
+        speeds.sort_unstable();
+        Some(SpeedDistribution { speeds })
+    }
+
+    pub fn count(&self) -> usize {
+        self.speeds.len()
+    }
+
+    pub fn min(&self) -> u32 {
+        self.speeds[0]
+    }
+
+    pub fn max(&self) -> u32 {
+        self.speeds[self.speeds.len() - 1]
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.speeds.iter().map(|&speed| speed as f64).sum::<f64>() / self.speeds.len() as f64
+    }
+
+    /**
+     * Returns the speed `p` percent of the members are at most as fast as (the nearest-rank
+     * percentile), e.g. 50.0 for the median. `p` is clamped to between 0.0 and 100.0, and 0.0
+     * gives the slowest speed.
+     */
+    pub fn percentile(&self, p: f64) -> u32 {
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * self.speeds.len() as f64).ceil() as usize;
+        self.speeds[rank.saturating_sub(1)]
+    }
+
+    pub fn median(&self) -> u32 {
+        self.percentile(50.0)
+    }
+
+    /**
+     * Counts the members in buckets of `width` speeds, starting from 0: the first bucket holds
+     * speeds 0 to `width - 1`, and so on up to the bucket of the fastest member. Returns each
+     * bucket's lowest speed with its count, including empty buckets in between. A `width` of 0 is
+     * treated as 1.
+     */
+    pub fn histogram(&self, width: u32) -> Vec<(u32, usize)> {
+        let width = width.max(1);
+        let mut buckets = vec![0; (self.max() / width) as usize + 1];
+        for &speed in &self.speeds {
+            buckets[(speed / width) as usize] += 1;
+        }
+        buckets.into_iter().enumerate().map(|(i, count)| (i as u32 * width, count)).collect()
+    }
+}
+
+/// The formats `ClanSystem::export_rosters` can produce.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum RosterFormat {
+    Json,
+    Csv,
+}
+
+/// The membership and territory changes between two snapshots of a clan system, as produced by
+/// `ClanSystem::diff`.
+/// Every list is sorted so diffs can be compared and printed directly.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ClanDiff {
+    /** Clans that exist only in the newer snapshot. */
+    pub added_clans: Vec<String>,
+    /** Clans that exist only in the older snapshot. */
+    pub removed_clans: Vec<String>,
+    /** (clan id, member name) pairs present only in the newer snapshot. */
+    pub joined: Vec<(String, String)>,
+    /** (clan id, member name) pairs present only in the older snapshot. */
+    pub left: Vec<(String, String)>,
+    /** (reef id, clan id) pairs for reefs held in the newer snapshot by a clan that did not hold them in the older one. */
+    pub captured: Vec<(ReefId, String)>,
+    /** (reef id, clan id) pairs for reefs held in the older snapshot by a clan that no longer holds them. */
+    pub lost: Vec<(ReefId, String)>,
+}
+
+impl ClanDiff {
+    // Compares two sets of rosters (member names by clan id) and territories (the clan id holding
+    // each reef), older first, as `ClanSystem::diff` describes.
+    pub(crate) fn between(
+        old: &HashMap<&str, HashSet<&str>>,
+        old_territory: &HashMap<ReefId, &str>,
+        new: &HashMap<&str, HashSet<&str>>,
+        new_territory: &HashMap<ReefId, &str>,
+    ) -> ClanDiff {
+        let mut diff = ClanDiff::default();
+        for (&reef, &clan_id) in new_territory {
+            if old_territory.get(&reef) != Some(&clan_id) {
+                diff.captured.push((reef, clan_id.to_string()));
+            }
+        }
+        for (&reef, &clan_id) in old_territory {
+            if new_territory.get(&reef) != Some(&clan_id) {
+                diff.lost.push((reef, clan_id.to_string()));
+            }
+        }
+        for (&clan_id, clan) in new {
+            let old_clan = old.get(clan_id);
+            if old_clan.is_none() {
+                diff.added_clans.push(clan_id.to_string());
+            }
+            for &name in clan {
+                if !old_clan.is_some_and(|old_clan| old_clan.contains(name)) {
+                    diff.joined.push((clan_id.to_string(), name.to_string()));
+                }
+            }
+        }
+        for (&clan_id, old_clan) in old {
+            let clan = new.get(clan_id);
+            if clan.is_none() {
+                diff.removed_clans.push(clan_id.to_string());
+            }
+            for &name in old_clan {
+                if !clan.is_some_and(|clan| clan.contains(name)) {
+                    diff.left.push((clan_id.to_string(), name.to_string()));
+                }
+            }
+        }
+        diff.added_clans.sort();
+        diff.removed_clans.sort();
+        diff.joined.sort();
+        diff.left.sort();
+        diff.captured.sort();
+        diff.lost.sort();
+        diff
+    }
+
+    /**
+     * Returns true if the two snapshots had identical clans, memberships, and territory.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.added_clans.is_empty()
+            && self.removed_clans.is_empty()
+            && self.joined.is_empty()
+            && self.left.is_empty()
+            && self.captured.is_empty()
+            && self.lost.is_empty()
+    }
+}
 
 #[derive(Debug)]
-pub struct ClanSystem {
-    clans: HashMap<String, HashMap<String, Crab>>
+pub struct ClanSystem<M = Crab> {
+    // Clan ids and member names are interned; everything below is keyed by their ids.
+    names: Interner,
+    clans: HashMap<NameId, HashMap<NameId, M>>,
+    reputation: HashMap<NameId, u32>,
+    // Reverse index from member name to the id of the clan it belongs to.
+    member_clans: HashMap<NameId, NameId>,
+    // Sum of each clan's member speeds, kept up to date so competitions need not visit every member.
+    speed_totals: HashMap<NameId, u64>,
+    rules: HashMap<NameId, ClanRules<M>>,
+    // The clan holding each reef that has been claimed or captured (see `Beach::raid`).
+    territory: HashMap<ReefId, NameId>,
+    events: Vec<OceanEvent>
 }
 
-impl ClanSystem {
-    pub fn new() -> ClanSystem {
+/// Reputation points awarded to the winner of a clan competition.
+pub const WIN_POINTS: u32 = 3;
+/// Reputation points awarded to each clan when a competition is a draw.
+pub const DRAW_POINTS: u32 = 1;
+/// How much each member adds to its clan's strength in a raid, on top of its speed.
+pub const RAID_MEMBER_STRENGTH: u64 = 5;
+/// How much luck sways a raid: each side's strength is scaled by a random factor within this much
+/// of 1.
+pub const RAID_LUCK: f64 = 0.5;
+
+impl<M: ClanMember> ClanSystem<M> {
+    pub fn new() -> ClanSystem<M> {
         ClanSystem {
-            clans: HashMap::new()
+            names: Interner::new(),
+            clans: HashMap::new(),
+            reputation: HashMap::new(),
+            member_clans: HashMap::new(),
+            speed_totals: HashMap::new(),
+            rules: HashMap::new(),
+            territory: HashMap::new(),
+            events: Vec::new()
         }
     }
 
+    // Returns the id of the clan with the given name, or None if there is no such clan.
+    fn clan_key(&self, clan_id: &str) -> Option<NameId> {
+        self.names.get(clan_id).filter(|key| self.clans.contains_key(key))
+    }
+
     pub fn create_clan(&mut self, clan_id: String) {
-        self.clans.insert(clan_id, HashMap::new());
+        let key = self.names.intern(&clan_id);
+        self.rules.remove(&key);
+        self.speed_totals.insert(key, 0);
+        if let Some(old_clan) = self.clans.insert(key, HashMap::new()) {
+            for name in old_clan.keys() {
+                self.member_clans.remove(name);
+            }
+        }
+    }
+
+    /**
+     * Creates a clan that can hold at most `max_members` members.
+     */
+    pub fn create_clan_with_limit(&mut self, clan_id: String, max_members: usize) {
+        let key = self.names.intern(&clan_id);
+        self.create_clan(clan_id);
+        self.rules.entry(key).or_default().max_members = Some(max_members);
+    }
+
+    /**
+     * Returns the maximum number of members of the given clan, or None if it is unlimited.
+     */
+    pub fn get_clan_limit(&self, clan_id: &str) -> Option<usize> {
+        self.rules.get(&self.names.get(clan_id)?)?.max_members
+    }
+
+    /**
+     * Adds a rule that every new member of the given clan must satisfy.
+     * Members who already belong to the clan are not re-checked.
+     */
+    pub fn add_recruitment_rule<R: RecruitmentRule<M> + 'static>(&mut self, clan_id: &str, rule: R) {
+        let key = self.names.intern(clan_id);
+        self.rules.entry(key).or_default().rules.push(Box::new(rule));
     }
-    
-    pub fn add_member(&mut self, clan_id: &str, member_name: String, crab: Crab) {
-        self.clans.get_mut(clan_id).unwrap().insert(member_name, crab);
+
+    /**
+     * Adds a member to the given clan. A member can only belong to one clan, so it
+     * leaves any clan it was already in.
+     *
+     * Returns an Err, leaving every clan unchanged, if the clan does not exist,
+     * is already full, or the member fails one of its recruitment rules.
+     */
+    pub fn add_member(&mut self, clan_id: &str, member_name: String, member: M) -> Result<(), OceanError> {
+        let Some(clan_key) = self.clan_key(clan_id) else {
+            return Err(OceanError::ClanNotFound(clan_id.to_string()));
+        };
+        let clan = &self.clans[&clan_key];
+        if let Some(rules) = self.rules.get(&clan_key) {
+            if let Some(max_members) = rules.max_members {
+                let already_in = self.names.get(&member_name).is_some_and(|key| clan.contains_key(&key));
+                if clan.len() >= max_members && !already_in {
+                    return Err(OceanError::ClanFull { clan: clan_id.to_string(), max_members });
+                }
+            }
+            for rule in &rules.rules {
+                rule.check(&member).map_err(OceanError::Rejected)?;
+            }
+        }
+
+        self.remove_member(&member_name);
+        self.insert_member(clan_key, member_name, member);
+        Ok(())
+    }
+
+    // Adds a member, which must not be in any clan, to the clan with the given key.
+    fn insert_member(&mut self, clan_key: NameId, member_name: String, member: M) {
+        let member_key = self.names.intern(&member_name);
+        let total = self.speed_totals.entry(clan_key).or_default();
+        *total = total.saturating_add(counted_speed(&member));
+        self.clans.get_mut(&clan_key).unwrap().insert(member_key, member);
+        self.member_clans.insert(member_key, clan_key);
+        let clan = self.names.resolve(clan_key).to_string();
+        events::queue(&mut self.events, OceanEvent::ClanJoined { clan, crab: member_name });
     }
 
-    pub fn get_clan(&mut self, clan_id: &str) -> Option<&HashMap<String, Crab>> {
-        self.clans.get(clan_id)
+    /**
+     * Removes the member with the given name from whichever clan it belongs to,
+     * returning that clan's id, or None if it was not in any clan.
+     */
+    pub fn remove_member(&mut self, member_name: &str) -> Option<String> {
+        let member_key = self.names.get(member_name)?;
+        let clan_key = self.member_clans.remove(&member_key)?;
+        if let Some(member) = self.clans.get_mut(&clan_key).and_then(|clan| clan.remove(&member_key)) {
+            if let Some(total) = self.speed_totals.get_mut(&clan_key) {
+                if *total == u64::MAX {
+                    // A saturated total no longer says what to take away, so count it again.
+                    *total = self.clans[&clan_key].values().fold(0, |sum: u64, m| sum.saturating_add(counted_speed(m)));
+                } else {
+                    *total -= counted_speed(&member);
+                }
+            }
+        }
+        let clan_id = self.names.resolve(clan_key).to_string();
+        events::queue(&mut self.events, OceanEvent::ClanLeft { clan: clan_id.clone(), crab: member_name.to_string() });
+        Some(clan_id)
+    }
+
+    /**
+     * Returns the id of the clan the named member belongs to, or None if it is in no clan.
+     */
+    pub fn get_clan_of_member(&self, member_name: &str) -> Option<&str> {
+        let clan_key = self.member_clans.get(&self.names.get(member_name)?)?;
+        Some(self.names.resolve(*clan_key))
+    }
+
+    // Returns the clan the named member belongs to and the member as the clan holds it, which
+    // may have been copied before the member last changed.
+    pub(crate) fn get_member(&self, member_name: &str) -> Option<(&str, &M)> {
+        let member_key = self.names.get(member_name)?;
+        let clan_key = *self.member_clans.get(&member_key)?;
+        Some((self.names.resolve(clan_key), self.clans.get(&clan_key)?.get(&member_key)?))
+    }
+
+    /**
+     * Returns the members of the given clan by name, or None if the clan does not exist.
+     */
+    pub fn get_clan(&self, clan_id: &str) -> Option<HashMap<&str, &M>> {
+        let clan = &self.clans[&self.clan_key(clan_id)?];
+        Some(clan.iter().map(|(&key, member)| (self.names.resolve(key), member)).collect())
+    }
+
+    /**
+     * Returns true if a clan with the given id exists.
+     */
+    pub fn has_clan(&self, clan_id: &str) -> bool {
+        self.clan_key(clan_id).is_some()
     }
 
     /**
      * Returns a list of the names of the clan members for the given clan id.
      */
     pub fn get_clan_member_names(&self, clan_id: &str) -> Vec<String> {
-        if let Some(clan) = self.clans.get(clan_id) {
-            clan.keys().cloned().collect()
+        if let Some(clan) = self.clan_key(clan_id).map(|key| &self.clans[&key]) {
+            clan.keys().map(|&key| self.names.resolve(key).to_string()).collect()
         } else {
             Vec::new() // Return an empty vector if the clan doesn't exist
         }
     }
 
+    /**
+     * Returns the ids of every clan, in sorted order.
+     */
+    pub fn get_clan_ids(&self) -> Vec<String> {
+        let mut clan_ids: Vec<String> = self.clans.keys().map(|&key| self.names.resolve(key).to_string()).collect();
+        clan_ids.sort();
+        clan_ids
+    }
+
     /**
      * Returns the number of clans currently in existence.
      */
@@ -50,8 +548,8 @@ impl ClanSystem {
      * Returns the number of clan members for the given clan id.
      */
     pub fn get_clan_member_count(&self, clan_id: &str) -> usize {
-        if let Some(clan) = self.clans.get(clan_id) {
-            clan.len()
+        if let Some(key) = self.clan_key(clan_id) {
+            self.clans[&key].len()
         } else {
             0 // Return 0 if the clan doesn't exist
         }
@@ -59,21 +557,451 @@ impl ClanSystem {
 
     /**
      * Returns the id of the clan with the most number of members, or None if such a clan does not exist.
+     * If several clans tie, returns the first of their ids in lexicographic order (see `get_largest_clans`).
      */
     pub fn get_largest_clan_id(&self) -> Option<String> {
-        let mut largest_clan_id = None;
-        let mut largest_clan_size = 0;
-        for (clan_id, clan) in &self.clans {
-            let clan_size = self.get_clan_member_count(clan_id);
-            if clan_size > largest_clan_size {
-                largest_clan_size = clan_size;
-                largest_clan_id = Some(clan_id.clone());
+        self.get_largest_clans().into_iter().next()
+    }
+
+    /**
+     * Returns the ids of every clan with the most members, in lexicographic order, so ties are
+     * reported the same way on every run. The list is empty if no clan has any members.
+     */
+    pub fn get_largest_clans(&self) -> Vec<String> {
+        let largest_clan_size = self.clans.values().map(HashMap::len).max().unwrap_or(0);
+        if largest_clan_size == 0 {
+            return Vec::new();
+        }
+        let mut largest: Vec<String> = self
+            .clans
+            .iter()
+            .filter(|(_, clan)| clan.len() == largest_clan_size)
+            .map(|(&key, _)| self.names.resolve(key).to_string())
+            .collect();
+        largest.sort();
+        largest
+    }
+
+    /**
+     * Returns the number of members of the given clan that count toward its average speed and
+     * competitions (see `ClanMember::is_active`).
+     */
+    pub fn get_clan_active_member_count(&self, clan_id: &str) -> usize {
+        self.clan_key(clan_id).map_or(0, |key| self.clans[&key].values().filter(|m| m.is_active()).count())
+    }
+
+    /**
+     * Returns the average speed of the active members of the given clan (see
+     * `ClanMember::is_active`), rounded down, or None if the clan does not exist or has no
+     * active members.
+     */
+    pub fn get_clan_average_speed(&self, clan_id: &str) -> Option<u32> {
+        let count = self.get_clan_active_member_count(clan_id) as u64;
+        if count == 0 {
+            return None;
+        }
+        Some((self.get_clan_total_speed(clan_id)? / count) as u32)
+    }
+
+    /**
+     * Like `get_clan_average_speed`, but from the members' fractional speeds (see
+     * `ClanMember::speed_f`), without rounding. This takes time in proportion to the clan's size.
+     */
+    pub fn get_clan_average_speed_f(&self, clan_id: &str) -> Option<f64> {
+        let members: Vec<&M> = self.clans.get(&self.clan_key(clan_id)?)?.values().filter(|m| m.is_active()).collect();
+        if members.is_empty() {
+            return None;
+        }
+        Some(members.iter().map(|m| m.speed_f()).sum::<f64>() / members.len() as f64)
+    }
+
+    /**
+     * Returns the spread of speeds among the members of the given clan, or None if the clan does
+     * not exist or has no members. See `Beach::clan_speed_distribution` for the speeds of the
+     * crabs on a beach as they are now.
+     */
+    pub fn speed_distribution(&self, clan_id: &str) -> Option<SpeedDistribution> {
+        let key = self.clan_key(clan_id)?;
+        SpeedDistribution::of(self.clans[&key].values().map(M::speed))
+    }
+
+    /**
+     * Returns the sum of the speeds of the active members of the given clan (see
+     * `ClanMember::is_active`), or None if the clan does not exist. This takes constant time, however large the clan. The sum stops at `u64::MAX`
+     * rather than overflowing.
+     */
+    pub fn get_clan_total_speed(&self, clan_id: &str) -> Option<u64> {
+        self.speed_totals.get(&self.names.get(clan_id)?).copied()
+    }
+
+    /**
+     * Records the result of a competition between two clans: the winner gains
+     * `WIN_POINTS` reputation, or both clans gain `DRAW_POINTS` if there was no winner.
+     */
+    pub fn record_result(&mut self, id1: &str, id2: &str, winner: Option<&str>) {
+        match winner {
+            Some(winner) => {
+                let key = self.names.intern(winner);
+                self.add_reputation(key, WIN_POINTS);
+                let loser = if winner == id1 { id2 } else { id1 };
+                events::queue(&mut self.events, OceanEvent::ClanWon { winner: winner.to_string(), loser: loser.to_string() });
+            }
+            None => {
+                for id in [id1, id2] {
+                    let key = self.names.intern(id);
+                    self.add_reputation(key, DRAW_POINTS);
+                }
+                events::queue(&mut self.events, OceanEvent::ClanDrew { clans: (id1.to_string(), id2.to_string()) });
+            }
+        }
+    }
+
+    /**
+     * Removes and returns the events queued by changes to this clan system, oldest first.
+     */
+    pub fn drain_events(&mut self) -> Vec<OceanEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Adds to a clan's reputation, which stops at `u32::MAX` rather than overflowing.
+    fn add_reputation(&mut self, key: NameId, points: u32) {
+        let reputation = self.reputation.entry(key).or_insert(0);
+        *reputation = reputation.saturating_add(points);
+    }
+
+    /**
+     * Returns the reputation the given clan has earned this season.
+     */
+    pub fn reputation(&self, clan_id: &str) -> u32 {
+        self.names.get(clan_id).and_then(|key| self.reputation.get(&key)).copied().unwrap_or(0)
+    }
+
+    /**
+     * Returns every clan with its reputation this season, highest first.
+     * Clans with equal reputation are ordered by id.
+     */
+    pub fn leaderboard(&self) -> Vec<(String, u32)> {
+        let mut standings: Vec<(String, u32)> = self
+            .clans
+            .keys()
+            .map(|key| (self.names.resolve(*key).to_string(), self.reputation.get(key).copied().unwrap_or(0)))
+            .collect();
+        standings.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        standings
+    }
+
+    // Returns the names of every clan's members, by clan id.
+    fn rosters(&self) -> HashMap<&str, HashSet<&str>> {
+        self.clans
+            .iter()
+            .map(|(&key, clan)| (self.names.resolve(key), clan.keys().map(|&name| self.names.resolve(name)).collect()))
+            .collect()
+    }
+
+    /**
+     * Compares this (older) clan system against `other` (newer), listing every clan that was
+     * created or disbanded, every member that joined or left a clan, and every reef that changed
+     * hands in between. A reef taken from one clan by another is both lost and captured.
+     */
+    pub fn diff(&self, other: &ClanSystem<M>) -> ClanDiff {
+        // The two systems intern names separately, so they are compared by name.
+        ClanDiff::between(&self.rosters(), &self.holders(), &other.rosters(), &other.holders())
+    }
+
+    // Returns the id of the clan holding each held reef.
+    fn holders(&self) -> HashMap<ReefId, &str> {
+        self.territory.iter().map(|(&reef, &key)| (reef, self.names.resolve(key))).collect()
+    }
+
+    // Returns the id of the clan holding each held reef, owning the ids (see `owned_rosters`).
+    pub(crate) fn owned_holders(&self) -> HashMap<ReefId, String> {
+        self.holders().into_iter().map(|(reef, clan_id)| (reef, clan_id.to_string())).collect()
+    }
+
+    // Returns the names of every clan's members, by clan id, owning the names so they can outlive
+    // the clan system (see `crate::snapshot::Snapshot`).
+    pub(crate) fn owned_rosters(&self) -> HashMap<String, HashSet<String>> {
+        self.rosters()
+            .into_iter()
+            .map(|(clan_id, members)| (clan_id.to_string(), members.into_iter().map(str::to_string).collect()))
+            .collect()
+    }
+
+    /**
+     * Returns the id of the clan holding the given reef, or None if no clan holds it.
+     */
+    pub fn controller(&self, reef: ReefId) -> Option<&str> {
+        self.territory.get(&reef).map(|&key| self.names.resolve(key))
+    }
+
+    /**
+     * Returns the ids of the reefs the given clan holds, in increasing order.
+     */
+    pub fn territory(&self, clan_id: &str) -> Vec<ReefId> {
+        let Some(key) = self.clan_key(clan_id) else {
+            return Vec::new();
+        };
+        let mut reefs: Vec<ReefId> = self.territory.iter().filter(|(_, &holder)| holder == key).map(|(&reef, _)| reef).collect();
+        reefs.sort();
+        reefs
+    }
+
+    /**
+     * Gives the clan a reef that no clan holds yet. Other clans can only take it by raiding the
+     * clan (see `Beach::raid`).
+     *
+     * Returns `OceanError::ClanNotFound` if there is no such clan, or
+     * `OceanError::InvalidArgument` if another clan already holds the reef.
+     */
+    pub fn claim_reef(&mut self, clan_id: &str, reef: ReefId) -> Result<(), OceanError> {
+        let key = self.clan_key(clan_id).ok_or_else(|| OceanError::ClanNotFound(clan_id.to_string()))?;
+        match self.territory.get(&reef) {
+            Some(&holder) if holder != key => Err(OceanError::InvalidArgument(format!(
+                "Reef {} is already held by clan {}",
+                reef,
+                self.names.resolve(holder)
+            ))),
+            _ => {
+                self.territory.insert(reef, key);
+                Ok(())
             }
-        
-        
+        }
     }
 
-    return largest_clan_id;
+    // Records a raid on the defender's reef: the reef changes hands if the attacker captured it,
+    // and whichever clan won gains `WIN_POINTS` reputation.
+    pub(crate) fn record_raid(&mut self, attacker: &str, defender: &str, reef: ReefId, captured: bool) {
+        let winner = self.names.intern(if captured { attacker } else { defender });
+        if captured {
+            self.territory.insert(reef, winner);
+        }
+        self.add_reputation(winner, WIN_POINTS);
+        events::queue(&mut self.events, OceanEvent::ReefRaided {
+            attacker: attacker.to_string(),
+            defender: defender.to_string(),
+            reef,
+            captured,
+        });
+    }
+
+    // Records that a scheduled match between two clans could not be played.
+    pub(crate) fn record_cancelled(&mut self, id1: &str, id2: &str, reason: String) {
+        events::queue(&mut self.events, OceanEvent::MatchCancelled { clans: (id1.to_string(), id2.to_string()), reason });
+    }
+
+    /**
+     * Starts a new season, resetting every clan's reputation to zero. Clans keep their reefs.
+     */
+    pub fn start_new_season(&mut self) {
+        self.reputation.clear();
+    }
+
+    // Puts a member back into a clan it was taken out of (see `Beach::undo`), creating the clan if
+    // it is gone. The clan's limit and recruitment rules are not checked again.
+    pub(crate) fn restore_member(&mut self, clan_id: &str, member_name: String, member: M) {
+        if !self.has_clan(clan_id) {
+            self.create_clan(clan_id.to_string());
+        }
+        self.remove_member(&member_name);
+        let clan_key = self.names.intern(clan_id);
+        self.insert_member(clan_key, member_name, member);
+    }
+
+    // Replaces the stored copy of the named member with `member`, now named `new_name`, in the
+    // same clan, and updates the clan's total speed, e.g. after the crab was renamed or changed.
+    // Returns false, changing nothing, if the member is in no clan or another member has the new
+    // name already.
+    pub(crate) fn update_member(&mut self, member_name: &str, new_name: &str, member: M) -> bool {
+        let Some(member_key) = self.names.get(member_name) else {
+            return false;
+        };
+        let Some(&clan_key) = self.member_clans.get(&member_key) else {
+            return false;
+        };
+        if new_name != member_name && self.names.get(new_name).is_some_and(|key| self.member_clans.contains_key(&key)) {
+            return false;
+        }
+        self.member_clans.remove(&member_key);
+        let clan = self.clans.get_mut(&clan_key).unwrap();
+        let old = clan.remove(&member_key).unwrap();
+        let new_key = self.names.intern(new_name);
+        let speed = counted_speed(&member);
+        clan.insert(new_key, member);
+        self.member_clans.insert(new_key, clan_key);
+        let total = self.speed_totals.entry(clan_key).or_default();
+        *total = if *total == u64::MAX {
+            clan.values().fold(0, |sum: u64, m| sum.saturating_add(counted_speed(m)))
+        } else {
+            (*total - counted_speed(&old)).saturating_add(speed)
+        };
+        true
+    }
+
+    // Removes a clan if it has no members, e.g. when undoing the join that created it.
+    pub(crate) fn remove_empty_clan(&mut self, clan_id: &str) {
+        let Some(key) = self.clan_key(clan_id) else {
+            return;
+        };
+        if self.clans[&key].is_empty() {
+            self.clans.remove(&key);
+            self.speed_totals.remove(&key);
+            self.rules.remove(&key);
+            self.reputation.remove(&key);
+            self.territory.retain(|_, holder| *holder != key);
+        }
+    }
 }
 
+impl ClanSystem<Crab> {
+    /**
+     * Counts the members of the given clan by diet, e.g. to check that its recruitment rules keep
+     * it diverse. Diets no member has are left out, and an unknown clan has no members.
+     */
+    pub fn diet_breakdown(&self, clan_id: &str) -> HashMap<Diet, usize> {
+        let mut counts = HashMap::new();
+        if let Some(key) = self.clan_key(clan_id) {
+            for crab in self.clans[&key].values() {
+                *counts.entry(crab.diet()).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /**
+     * Exports every clan member as a row of (clan_id, member_name, speed, diet) in the given format.
+     * Rows are ordered by clan id and then member name.
+     *
+     *   - CSV output starts with a header row.
+     *   - JSON output is an array with one object per member.
+     */
+    pub fn export_rosters(&self, format: RosterFormat) -> String {
+        let names = &self.names;
+        let mut rows: Vec<(&str, &Crab)> = self
+            .clans
+            .iter()
+            .flat_map(|(&clan_key, clan)| clan.values().map(move |crab| (names.resolve(clan_key), crab)))
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(b.0).then_with(|| a.1.name().cmp(b.1.name())));
+
+        match format {
+            RosterFormat::Csv => {
+                let mut out = String::from("clan_id,member_name,speed,diet\n");
+                for (clan_id, crab) in rows {
+                    out.push_str(&format!(
+                        "{},{},{},{:?}\n",
+                        csv_field(clan_id),
+                        csv_field(crab.name()),
+                        crab.speed(),
+                        crab.diet()
+                    ));
+                }
+                out
+            }
+            RosterFormat::Json => {
+                let objects: Vec<String> = rows
+                    .into_iter()
+                    .map(|(clan_id, crab)| {
+                        format!(
+                            "{{\"clan_id\":{},\"member_name\":{},\"speed\":{},\"diet\":\"{:?}\"}}",
+                            json_string(clan_id),
+                            json_string(crab.name()),
+                            crab.speed(),
+                            crab.diet()
+                        )
+                    })
+                    .collect();
+                format!("[{}]", objects.join(","))
+            }
+        }
+    }
+
+    /**
+     * Saves every clan and its members in the `crate::persist` text format.
+     * Clans and members are written in name order so the output is stable.
+     */
+    pub fn save(&self) -> String {
+        let mut out = String::new();
+        for clan_id in self.get_clan_ids() {
+            out.push_str(&join_record(&["clan".to_string(), clan_id.clone()]));
+            out.push('\n');
+
+            let key = self.names.get(&clan_id).unwrap();
+            let mut members: Vec<(&str, &Crab)> =
+                self.clans[&key].iter().map(|(&name, crab)| (self.names.resolve(name), crab)).collect();
+            members.sort_by_key(|&(name, _)| name);
+            for (_, crab) in members {
+                let mut fields = vec!["member".to_string(), clan_id.clone()];
+                fields.extend(crab.to_fields());
+                out.push_str(&join_record(&fields));
+                out.push('\n');
+            }
+
+            if let Some(max_members) = self.get_clan_limit(&clan_id) {
+                out.push_str(&join_record(&["limit".to_string(), clan_id.clone(), max_members.to_string()]));
+                out.push('\n');
+            }
+
+            if let Some(points) = self.reputation.get(&key) {
+                out.push_str(&join_record(&["reputation".to_string(), clan_id.clone(), points.to_string()]));
+                out.push('\n');
+            }
+
+            for reef in self.territory(&clan_id) {
+                out.push_str(&join_record(&["territory".to_string(), clan_id.clone(), reef.to_string()]));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /**
+     * Loads a clan system previously written by `save`.
+     */
+    pub fn load(text: &str) -> Result<ClanSystem, ParseError> {
+        let mut clan_system = ClanSystem::new();
+        for (i, line) in text.lines().enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+            clan_system.load_record(&split_record(line), i + 1)?;
+        }
+        Ok(clan_system)
+    }
+
+    /**
+     * Applies a single saved `clan` or `member` record to this clan system.
+     */
+    pub(crate) fn load_record(&mut self, fields: &[String], line: usize) -> Result<(), ParseError> {
+        match fields[0].as_str() {
+            "clan" if fields.len() == 2 => {
+                self.create_clan(fields[1].clone());
+                Ok(())
+            }
+            "limit" if fields.len() == 3 => {
+                let max_members = parse_field(&fields[2], "member limit", line)?;
+                let key = self.names.intern(&fields[1]);
+                self.rules.entry(key).or_default().max_members = Some(max_members);
+                Ok(())
+            }
+            "reputation" if fields.len() == 3 => {
+                let points = parse_field(&fields[2], "reputation", line)?;
+                let key = self.names.intern(&fields[1]);
+                self.reputation.insert(key, points);
+                Ok(())
+            }
+            "territory" if fields.len() == 3 => {
+                let reef = parse_field(&fields[2], "reef id", line)?;
+                self.claim_reef(&fields[1], reef).map_err(|e| ParseError::new(line, e.to_string()))
+            }
+            "member" if fields.len() > 2 => {
+                let crab = Crab::from_fields(&fields[2..], line)?;
+                if !self.has_clan(&fields[1]) {
+                    return Err(ParseError::new(line, format!("member of unknown clan {:?}", fields[1])));
+                }
+                self.add_member(&fields[1], crab.name().to_string(), crab)
+                    .map_err(|e| ParseError::new(line, e.to_string()))
+            }
+            _ => Err(ParseError::new(line, format!("unexpected record {:?}", fields[0]))),
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use rand::Rng;
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 pub struct Color {
     pub r: u8,
@@ -22,6 +24,85 @@ impl Color {
         Color::new(0, 0, 255)
     }
 
+    pub fn new_orange() -> Color {
+        Color::new(255, 165, 0)
+    }
+
+    pub fn new_teal() -> Color {
+        Color::new(0, 128, 128)
+    }
+
+    pub fn new_violet() -> Color {
+        Color::new(238, 130, 238)
+    }
+
+    /**
+     * Returns the name of the named color (red, green, blue, orange, teal, or violet) closest to
+     * this one, e.g. "teal" for the blend of blue and green.
+     */
+    pub fn nearest_name(&self) -> &'static str {
+        self.nearest().0
+    }
+
+    /**
+     * Returns the named color (see `nearest_name`) closest to this one, for code that only
+     * handles the six named colors, e.g. red for a blended `#f01008`.
+     */
+    pub fn nearest_variant(&self) -> Color {
+        self.nearest().1
+    }
+
+    // The named color closest to this one, with its name.
+    fn nearest(&self) -> (&'static str, Color) {
+        let named = [
+            ("red", Color::new_red()),
+            ("green", Color::new_green()),
+            ("blue", Color::new_blue()),
+            ("orange", Color::new_orange()),
+            ("teal", Color::new_teal()),
+            ("violet", Color::new_violet()),
+        ];
+        named.into_iter().min_by_key(|(_, color)| self.distance_squared(color)).unwrap()
+    }
+
+    /**
+     * Returns how alike this color and `other` are, from 0.0 for opposite corners of the RGB
+     * cube, such as black and white, to 1.0 for the same color.
+     */
+    pub fn similarity(&self, other: &Color) -> f64 {
+        let farthest = 3.0 * 255.0 * 255.0;
+        1.0 - (self.distance_squared(other) as f64 / farthest).sqrt()
+    }
+
+    fn distance_squared(&self, other: &Color) -> i32 {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(self.r, other.r) + d(self.g, other.g) + d(self.b, other.b)
+    }
+
+    pub fn to_rgb(&self) -> (u8, u8, u8) {
+        (self.r, self.g, self.b)
+    }
+
+    /**
+     * Formats this color as a CSS-style hex string, e.g. `#ff00ff`.
+     */
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /**
+     * Parses a hex string such as `#FF00ff` or `ff00ff`, as written by `to_hex`.
+     * Returns an Err string if it is not six hex digits, optionally preceded by `#`.
+     */
+    pub fn from_hex(hex: &str) -> Result<Color, String> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("invalid hex color: {:?}", hex));
+        }
+        let component = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).unwrap();
+        Ok(Color::new(component(0), component(2), component(4)))
+    }
+
     /**
      * Returns a new `Color` whose components are the sum of `c1` and `c2`'s components, modulo 256.
      *
@@ -43,4 +124,109 @@ impl Color {
 
     }
 
+    /**
+     * Returns a new `Color` mixed continuously from `c1` and `c2`: each component lies between
+     * the parents' components, `weight` of the way from `c2`'s to `c1`'s, rounded to the nearest
+     * whole value. A `weight` of 0.5 gives their average, so colors can drift gradually over
+     * generations instead of wrapping around as with `cross`.
+     */
+    pub fn blend(c1: &Color, c2: &Color, weight: f64) -> Color {
+        let weight = weight.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f64 * weight + b as f64 * (1.0 - weight)).round() as u8;
+        Color {
+            r: mix(c1.r, c2.r),
+            g: mix(c1.g, c2.g),
+            b: mix(c1.b, c2.b),
+        }
+    }
+
+    /**
+     * Ranks colors for dominant/recessive inheritance (see `Genotype`): darker colors are
+     * dominant over lighter ones, so black has the highest rank and white the lowest.
+     */
+    pub fn dominance_rank(&self) -> u32 {
+        765 - (self.r as u32 + self.g as u32 + self.b as u32)
+    }
+
+    /**
+     * Returns a new `Color` inherited from two parents, genetics-style: each of the red, green and
+     * blue components is an allele taken whole from one parent or the other.
+     *
+     * `dominance` is the chance (between 0.0 and 1.0) that each component comes from `c1`
+     * rather than `c2`, so 0.5 weighs both parents equally. A NaN `dominance` counts as 0.5.
+     */
+    pub fn cross_weighted<R: Rng + ?Sized>(c1: &Color, c2: &Color, dominance: f64, rng: &mut R) -> Color {
+        let dominance = if dominance.is_nan() { 0.5 } else { dominance.clamp(0.0, 1.0) };
+        let mut inherit = |a: u8, b: u8| if rng.gen_bool(dominance) { a } else { b };
+        Color {
+            r: inherit(c1.r, c2.r),
+            g: inherit(c1.g, c2.g),
+            b: inherit(c1.b, c2.b),
+        }
+    }
+}
+
+/// A crab's pair of color alleles, one inherited from each parent.
+///
+/// Only the dominant allele (see `Color::dominance_rank`) shows in the crab's color. The other
+/// stays hidden, but can still be passed on and reappear in later generations.
+#[derive(Eq, PartialEq, Debug, Clone)]
+pub struct Genotype {
+    dominant: Color,
+    recessive: Color,
+}
+
+impl Genotype {
+    /**
+     * Creates a genotype from two alleles, in either order. Ties in dominance go to `a`.
+     */
+    pub fn new(a: Color, b: Color) -> Genotype {
+        if b.dominance_rank() > a.dominance_rank() {
+            Genotype { dominant: b, recessive: a }
+        } else {
+            Genotype { dominant: a, recessive: b }
+        }
+    }
+
+    /**
+     * Creates a genotype with two copies of the same allele.
+     */
+    pub fn homozygous(color: Color) -> Genotype {
+        Genotype::new(color.clone(), color)
+    }
+
+    /**
+     * The color that shows.
+     */
+    pub fn expressed(&self) -> &Color {
+        &self.dominant
+    }
+
+    /**
+     * The hidden allele, which is the same as the expressed one for homozygous genotypes.
+     */
+    pub fn recessive(&self) -> &Color {
+        &self.recessive
+    }
+
+    pub fn is_heterozygous(&self) -> bool {
+        self.dominant != self.recessive
+    }
+
+    /**
+     * Returns the genotype of an offspring: each parent passes on one of its two alleles,
+     * chosen at random with `rng`.
+     */
+    pub fn cross<R: Rng + ?Sized>(g1: &Genotype, g2: &Genotype, rng: &mut R) -> Genotype {
+        let mut pass_on = |g: &Genotype| {
+            if rng.gen_bool(0.5) {
+                g.dominant.clone()
+            } else {
+                g.recessive.clone()
+            }
+        };
+        let a = pass_on(g1);
+        let b = pass_on(g2);
+        Genotype::new(a, b)
+    }
 }
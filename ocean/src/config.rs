@@ -0,0 +1,364 @@
+//! Simulation parameters read from a config file, so experiments can be tweaked without
+//! recompiling. Apply them with `Simulation::with_config`:
+//!
+//! ```text
+//! [simulation]
+//! seed = 42
+//! tick_length = 2          # reef ticks per step
+//! migration_rate = 0.1     # see Simulation::set_migration_rate
+//! regen_rate = 1.5         # multiplies every reef's regeneration, on top of the weather's
+//!
+//! [reefs]                  # defaults for every reef
+//! capacity = 100
+//! escape_chance = 0.3      # see Reef::set_escape_chance
+//!
+//! [reefs.kelp]             # overrides for the reef named "kelp"
+//! capacity = 40
+//!
+//! [reefs.kelp.regen]       # new prey per tick, by kind (see RegenPolicy)
+//! Algae = 2.5
+//! Shrimp = 0.5
+//!
+//! [breeding]
+//! allowed = true
+//! max_population = 50      # see Beach::set_breeding_limit
+//! ```
+//!
+//! Files are written in a subset of TOML: tables, `key = value` pairs of integers, floats,
+//! booleans and quoted strings, and `#` comments. Arrays, inline tables and multi-line strings
+//! are not supported. Every key is optional, and unknown keys are an error so that typos do not
+//! go unnoticed.
+
+use crate::error::OceanError;
+use crate::persist::ParseError;
+use crate::prey::{Algae, Clam, Detritus, Minnow, Plankton, Prey, Shrimp};
+use crate::reef::{RegenPolicy, Reef};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+/// The kinds of prey a reef can be configured to regrow, as named by `Prey::kind`.
+pub const REGEN_KINDS: [&str; 6] = ["Algae", "Clam", "Detritus", "Minnow", "Plankton", "Shrimp"];
+
+/// The settings of one reef, or the defaults for every reef. Unset settings are left alone.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReefConfig {
+    /** See `Reef::set_capacity`. */
+    pub capacity: Option<usize>,
+    /** See `Reef::set_escape_chance`. */
+    pub escape_chance: Option<f64>,
+    /** New prey per tick of each kind (see `REGEN_KINDS`), in the order they were listed. */
+    pub regen: Vec<(String, f64)>,
+}
+
+impl ReefConfig {
+    /**
+     * Applies the settings that are set to `reef`. Regeneration rates replace the reef's
+     * regeneration policy, and spawn minnows of speed 25 and shrimp of energy 1.
+     *
+     * Returns `OceanError::InvalidArgument`, leaving the reef unchanged, if a regeneration rate is
+     * for a kind of prey not in `REGEN_KINDS`.
+     */
+    pub fn apply(&self, reef: &mut Reef) -> Result<(), OceanError> {
+        let mut policy = RegenPolicy::new();
+        for (kind, rate) in self.regen.iter() {
+            let spawn = spawner(kind)
+                .ok_or_else(|| OceanError::InvalidArgument(format!("Reefs cannot regrow {} prey", kind)))?;
+            policy = policy.with_rate(*rate, spawn);
+        }
+        if self.capacity.is_some() {
+            reef.set_capacity(self.capacity);
+        }
+        if self.escape_chance.is_some() {
+            reef.set_escape_chance(self.escape_chance);
+        }
+        if !self.regen.is_empty() {
+            reef.set_regen_policy(policy);
+        }
+        Ok(())
+    }
+}
+
+// Returns a function spawning prey of the given kind, or None if it is not one of `REGEN_KINDS`.
+pub(crate) fn spawner(kind: &str) -> Option<fn() -> Box<dyn Prey>> {
+    let spawn: fn() -> Box<dyn Prey> = match kind {
+        "Algae" => || Box::new(Algae::new()),
+        "Clam" => || Box::new(Clam::new()),
+        "Detritus" => || Box::new(Detritus::new()),
+        "Minnow" => || Box::new(Minnow::new(25)),
+        "Plankton" => || Box::new(Plankton::new()),
+        "Shrimp" => || Box::new(Shrimp::new(1)),
+        _ => return None,
+    };
+    Some(spawn)
+}
+
+/// Everything a config file can set, with the defaults of a new `Simulation` for anything it
+/// leaves out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SimConfig {
+    /** Seeds the simulation's generator (see `crate::rng::seeded`). */
+    pub seed: u64,
+    /** How many times every reef ticks per step (see `Simulation::set_tick_length`). */
+    pub tick_length: u32,
+    /** See `Simulation::set_migration_rate`. */
+    pub migration_rate: f64,
+    /** Multiplies every reef's regeneration rates each step, on top of the weather's. */
+    pub regen_rate: f64,
+    /** Whether crabs can ever breed. When true, the weather still decides each step. */
+    pub breeding: bool,
+    /** Stops breeding on beaches with this many crabs (see `Beach::set_breeding_limit`). */
+    pub max_population: Option<usize>,
+    /** Settings for every reef, which those in `reefs` override. */
+    pub reef_defaults: ReefConfig,
+    /** Settings for the reefs with the given names. */
+    pub reefs: BTreeMap<String, ReefConfig>,
+}
+
+impl Default for SimConfig {
+    fn default() -> SimConfig {
+        SimConfig {
+            seed: 0,
+            tick_length: 1,
+            migration_rate: 0.0,
+            regen_rate: 1.0,
+            breeding: true,
+            max_population: None,
+            reef_defaults: ReefConfig::default(),
+            reefs: BTreeMap::new(),
+        }
+    }
+}
+
+impl SimConfig {
+    /**
+     * Reads a config file (see the module documentation).
+     *
+     * Returns `OceanError::Io` if the file cannot be read, and `OceanError::Parse` if it is not
+     * a valid config.
+     */
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SimConfig, OceanError> {
+        Ok(SimConfig::parse(&fs::read_to_string(path)?)?)
+    }
+
+    /**
+     * Parses the text of a config file (see the module documentation). Returns an Err for
+     * malformed lines, unknown tables or keys, values of the wrong type, and values out of
+     * range, such as a tick length of 0 or an escape chance above 1.
+     */
+    pub fn parse(text: &str) -> Result<SimConfig, ParseError> {
+        let mut config = SimConfig::default();
+        let mut table: Vec<String> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header
+                    .strip_suffix(']')
+                    .ok_or_else(|| ParseError::new(line_no, format!("unterminated table header {:?}", line)))?;
+                table = parse_key_path(header, line_no)?;
+                config.open_table(&table, line_no)?;
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| ParseError::new(line_no, format!("expected `key = value`, found {:?}", line)))?;
+            let key = parse_key_path(key, line_no)?;
+            if key.len() != 1 {
+                return Err(ParseError::new(line_no, "dotted keys are not supported".to_string()));
+            }
+            let value = Value::parse(value.trim(), line_no)?;
+            config.set(&table, &key[0], value, line_no)?;
+        }
+        Ok(config)
+    }
+
+    // Checks that `table` is one this format knows, creating the reef it configures if needed.
+    fn open_table(&mut self, table: &[String], line: usize) -> Result<(), ParseError> {
+        let path: Vec<&str> = table.iter().map(String::as_str).collect();
+        match path.as_slice() {
+            ["simulation"] | ["reefs"] | ["breeding"] => {}
+            ["reefs", name] | ["reefs", name, "regen"] => {
+                self.reefs.entry(name.to_string()).or_default();
+            }
+            _ => return Err(ParseError::new(line, format!("unknown table [{}]", table.join(".")))),
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, table: &[String], key: &str, value: Value, line: usize) -> Result<(), ParseError> {
+        let path: Vec<&str> = table.iter().map(String::as_str).collect();
+        match (path.as_slice(), key) {
+            (["simulation"], "seed") => self.seed = value.integer(key, line)?,
+            (["simulation"], "tick_length") => {
+                self.tick_length = value.integer(key, line)?;
+                if self.tick_length == 0 {
+                    return Err(ParseError::new(line, "tick_length must be at least 1".to_string()));
+                }
+            }
+            (["simulation"], "migration_rate") => {
+                self.migration_rate = value.float(key, line)?;
+                if !(0.0..=1.0).contains(&self.migration_rate) {
+                    return Err(ParseError::new(line, "migration_rate must be between 0 and 1".to_string()));
+                }
+            }
+            (["simulation"], "regen_rate") => self.regen_rate = value.rate(key, line)?,
+            (["reefs"], _) => set_reef(&mut self.reef_defaults, key, value, line)?,
+            (["reefs", name], _) => set_reef(self.reefs.get_mut(*name).unwrap(), key, value, line)?,
+            (["reefs", name, "regen"], _) => {
+                if spawner(key).is_none() {
+                    let message = format!("unknown prey kind {:?} (expected one of {})", key, REGEN_KINDS.join(", "));
+                    return Err(ParseError::new(line, message));
+                }
+                let rate = value.rate(key, line)?;
+                self.reefs.get_mut(*name).unwrap().regen.push((key.to_string(), rate));
+            }
+            (["breeding"], "allowed") => self.breeding = value.boolean(key, line)?,
+            (["breeding"], "max_population") => self.max_population = Some(value.integer(key, line)?),
+            ([], _) => return Err(ParseError::new(line, format!("key {:?} is outside any table", key))),
+            _ => return Err(ParseError::new(line, format!("unknown key {:?} in [{}]", key, table.join(".")))),
+        }
+        Ok(())
+    }
+}
+
+fn set_reef(reef: &mut ReefConfig, key: &str, value: Value, line: usize) -> Result<(), ParseError> {
+    match key {
+        "capacity" => reef.capacity = Some(value.integer(key, line)?),
+        "escape_chance" => {
+            let chance = value.float(key, line)?;
+            if !(0.0..=1.0).contains(&chance) {
+                return Err(ParseError::new(line, "escape_chance must be between 0 and 1".to_string()));
+            }
+            reef.escape_chance = Some(chance);
+        }
+        _ => return Err(ParseError::new(line, format!("unknown reef setting {:?}", key))),
+    }
+    Ok(())
+}
+
+// Drops a trailing `#` comment, unless the `#` is inside a quoted string.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Splits a table header or key such as `reefs."reef 1".regen` into its parts.
+fn parse_key_path(text: &str, line: usize) -> Result<Vec<String>, ParseError> {
+    let mut parts = Vec::new();
+    for part in split_unquoted(text, '.') {
+        let part = part.trim();
+        let key = match part.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+            Some(quoted) => quoted.to_string(),
+            None if !part.is_empty()
+                && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                part.to_string()
+            }
+            None => return Err(ParseError::new(line, format!("invalid key {:?}", part))),
+        };
+        parts.push(key);
+    }
+    Ok(parts)
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// A value on the right of a `key = value` line.
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+}
+
+impl Value {
+    fn parse(text: &str, line: usize) -> Result<Value, ParseError> {
+        if let Some(string) = text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+            return Ok(Value::String(string.to_string()));
+        }
+        match text {
+            "true" => return Ok(Value::Boolean(true)),
+            "false" => return Ok(Value::Boolean(false)),
+            _ => {}
+        }
+        // TOML allows underscores between digits, e.g. `1_000`.
+        let digits = text.replace('_', "");
+        if let Ok(integer) = digits.parse() {
+            return Ok(Value::Integer(integer));
+        }
+        // Rust also parses "inf" and "NaN", and numbers too large for an f64 as infinity, none of
+        // which make sense as a setting.
+        match digits.parse::<f64>() {
+            Ok(float) if !digits.is_empty() && float.is_finite() => Ok(Value::Float(float)),
+            _ => Err(ParseError::new(line, format!("invalid value {:?}", text))),
+        }
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Boolean(_) => "a boolean",
+            Value::String(_) => "a string",
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&self, key: &str, line: usize) -> Result<T, ParseError> {
+        match self {
+            Value::Integer(integer) => T::try_from(*integer)
+                .map_err(|_| ParseError::new(line, format!("{} is out of range: {}", key, integer))),
+            _ => Err(ParseError::new(line, format!("{} must be an integer, not {}", key, self.describe()))),
+        }
+    }
+
+    // Integers are accepted too, so `regen_rate = 2` means 2.0.
+    fn float(&self, key: &str, line: usize) -> Result<f64, ParseError> {
+        match self {
+            Value::Integer(integer) => Ok(*integer as f64),
+            Value::Float(float) => Ok(*float),
+            _ => Err(ParseError::new(line, format!("{} must be a number, not {}", key, self.describe()))),
+        }
+    }
+
+    // A number of things per tick, which can't be negative.
+    fn rate(&self, key: &str, line: usize) -> Result<f64, ParseError> {
+        let rate = self.float(key, line)?;
+        if rate < 0.0 {
+            return Err(ParseError::new(line, format!("{} must not be negative", key)));
+        }
+        Ok(rate)
+    }
+
+    fn boolean(&self, key: &str, line: usize) -> Result<bool, ParseError> {
+        match self {
+            Value::Boolean(boolean) => Ok(*boolean),
+            _ => Err(ParseError::new(line, format!("{} must be true or false, not {}", key, self.describe()))),
+        }
+    }
+}
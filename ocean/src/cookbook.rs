@@ -1,4 +1,16 @@
+use crate::crab::Crab;
 use crate::diet::Diet;
+use crate::error::OceanError;
+use crate::persist::{parse_json, JsonValue, ParseError};
+use crate::prey::PreyView;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::io::Read;
+use std::path::Path;
+
+// Words that `Cookbook::generate` builds recipe names from, e.g. "smoky clam chowder".
+const ADJECTIVES: [&str; 8] = ["smoky", "spicy", "tangy", "briny", "crispy", "golden", "hearty", "zesty"];
+const DISHES: [&str; 8] = ["stew", "chowder", "salad", "skewers", "bisque", "pie", "curry", "rolls"];
 
 #[derive(Debug)]
 pub struct Cookbook {
@@ -15,20 +27,393 @@ impl Cookbook {
         }
     }
 
-    pub fn recipes(&self) -> std::slice::Iter<Recipe> {
+    /**
+     * Creates a cookbook with no recipes.
+     */
+    pub fn empty() -> Cookbook {
+        Cookbook { recipes: Vec::new() }
+    }
+
+    /**
+     * Makes up a cookbook of `count` recipes from the kinds of prey in `prey_kinds` (e.g. from
+     * `Crab::visible_prey`, or `PreyView::of` for each prey in a reef), for simulations too big to
+     * write every recipe by hand. Repeated kinds count once.
+     *
+     * Each recipe is for one diet: it uses one to three kinds of prey of that diet, one to three
+     * of each, and its nutrition is the total nutrition of its ingredients. Its prep time and
+     * difficulty grow with its ingredients. Names are made from the main ingredient, e.g.
+     * "smoky clam chowder", with a number added if a name is already taken.
+     *
+     * Returns an empty cookbook if there are no prey kinds.
+     */
+    pub fn generate<R: Rng + ?Sized>(rng: &mut R, prey_kinds: &[PreyView], count: usize) -> Cookbook {
+        let mut by_diet: Vec<(Diet, Vec<&PreyView>)> = Vec::new();
+        for prey in prey_kinds {
+            match by_diet.iter_mut().find(|(diet, _)| *diet == prey.diet) {
+                Some((_, kinds)) if kinds.iter().any(|kind| kind.kind == prey.kind) => {}
+                Some((_, kinds)) => kinds.push(prey),
+                None => by_diet.push((prey.diet, vec![prey])),
+            }
+        }
+        let mut cookbook = Cookbook::empty();
+        if by_diet.is_empty() {
+            return cookbook;
+        }
+        for _ in 0..count {
+            let (diet, kinds) = by_diet.choose(rng).unwrap();
+            let n = rng.gen_range(1..=kinds.len().min(3));
+            let chosen: Vec<&&PreyView> = kinds.choose_multiple(rng, n).collect();
+
+            let base = format!(
+                "{} {} {}",
+                ADJECTIVES.choose(rng).unwrap(),
+                chosen[0].kind.to_lowercase(),
+                DISHES.choose(rng).unwrap()
+            );
+            let mut name = base.clone();
+            let mut number = 2;
+            while cookbook.find_by_name(&name).is_some() {
+                name = format!("{} {}", base, number);
+                number += 1;
+            }
+
+            let mut recipe = Recipe::new(name, *diet);
+            let mut nutrition = 0;
+            let mut portions = 0;
+            for prey in &chosen {
+                let quantity = rng.gen_range(1..=3);
+                recipe = recipe.with_ingredient(prey.kind, quantity);
+                nutrition += prey.nutrition * quantity;
+                portions += quantity;
+            }
+            let recipe = recipe
+                .with_nutrition(nutrition)
+                .with_prep_time(10 * portions + rng.gen_range(0..20))
+                .with_difficulty(n as u32 + portions / 3);
+            cookbook.recipes.push(recipe);
+        }
+        cookbook
+    }
+
+    /**
+     * Parses a cookbook from the text of a recipe file. See `from_path` for the formats.
+     */
+    pub fn parse(text: &str, format: CookbookFormat) -> Result<Cookbook, ParseError> {
+        let recipes = match format {
+            CookbookFormat::Toml => parse_toml_recipes(text)?,
+            CookbookFormat::Json => parse_json_recipes(text)?,
+        };
+        let mut cookbook = Cookbook::empty();
+        for (recipe, line) in recipes {
+            cookbook.add_recipe(recipe).map_err(|e| ParseError::new(line, e.to_string()))?;
+        }
+        Ok(cookbook)
+    }
+
+    /**
+     * Reads and parses a cookbook in the given format.
+     * Returns `OceanError::Io` if reading fails or `OceanError::Parse` if the recipes are invalid.
+     */
+    pub fn from_reader<R: Read>(mut reader: R, format: CookbookFormat) -> Result<Cookbook, OceanError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Cookbook::parse(&text, format)?)
+    }
+
+    /**
+     * Loads a cookbook from a `.toml` or `.json` recipe file, so recipes can be written
+     * without touching any Rust. Every recipe needs a `name` and a `diet` (a `Diet` variant
+     * name), and may have a `nutrition`, a `prep_time`, a `difficulty`, and `ingredients` (prey kinds
+     * and quantities):
+     *
+     * ```text
+     * # recipes.toml
+     * [[recipe]]
+     * name = "chowder"
+     * diet = "Shellfish"
+     * prep_time = 30
+     *
+     * [recipe.ingredients]
+     * Clam = 2
+     *
+     * // recipes.json
+     * [{"name": "chowder", "diet": "Shellfish", "prep_time": 30, "ingredients": {"Clam": 2}}]
+     * ```
+     *
+     * Returns an Err if the file has another extension, cannot be read, or is invalid.
+     * Errors reading the file include the path.
+     */
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Cookbook, OceanError> {
+        let path = path.as_ref();
+        let format = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => CookbookFormat::Toml,
+            Some("json") => CookbookFormat::Json,
+            _ => {
+                return Err(OceanError::InvalidArgument(format!(
+                    "{}: recipe files must end in .toml or .json",
+                    path.display()
+                )))
+            }
+        };
+        let file = std::fs::File::open(path).map_err(|e| OceanError::Io(format!("{}: {}", path.display(), e)))?;
+        Cookbook::from_reader(file, format).map_err(|e| match e {
+            OceanError::Io(message) => OceanError::Io(format!("{}: {}", path.display(), message)),
+            e => e,
+        })
+    }
+
+    pub fn recipes(&self) -> std::slice::Iter<'_, Recipe> {
         self.recipes.iter()
     }
+
+    /**
+     * Returns the recipe with the given name, if there is one.
+     */
+    pub fn find_by_name(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.name() == name)
+    }
+
+    /**
+     * Returns the recipes of the given diet, in cookbook order.
+     */
+    pub fn recipes_for_diet(&self, diet: Diet) -> impl Iterator<Item = &Recipe> {
+        self.recipes.iter().filter(move |recipe| recipe.diet() == diet)
+    }
+
+    /**
+     * Returns up to `n` recipes with the highest average rating among the given crabs
+     * (e.g. `beach.crabs()`), best first, with their averages. Recipes no crab has rated are
+     * left out, and ties keep cookbook order.
+     */
+    pub fn top_rated<'a, 'c, I>(&'a self, crabs: I, n: usize) -> Vec<(&'a Recipe, f64)>
+    where
+        I: IntoIterator<Item = &'c Crab>,
+    {
+        let crabs: Vec<&Crab> = crabs.into_iter().collect();
+        let mut rated: Vec<(&Recipe, f64)> = self
+            .recipes
+            .iter()
+            .filter_map(|recipe| {
+                let stars: Vec<u8> = crabs.iter().filter_map(|crab| crab.rating(recipe.name())).collect();
+                if stars.is_empty() {
+                    return None;
+                }
+                let total: u32 = stars.iter().map(|&s| s as u32).sum();
+                Some((recipe, total as f64 / stars.len() as f64))
+            })
+            .collect();
+        // sort_by is stable, so equal averages stay in cookbook order.
+        rated.sort_by(|a, b| b.1.total_cmp(&a.1));
+        rated.truncate(n);
+        rated
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    /**
+     * Adds a recipe to the end of the cookbook.
+     * Returns `OceanError::DuplicateName` if the cookbook already has a recipe with that name.
+     */
+    pub fn add_recipe(&mut self, recipe: Recipe) -> Result<(), OceanError> {
+        if self.find_by_name(recipe.name()).is_some() {
+            return Err(OceanError::DuplicateName { kind: "recipe", name: recipe.name().clone() });
+        }
+        self.recipes.push(recipe);
+        Ok(())
+    }
+
+    /**
+     * Adds the recipes of `other` to the end of this cookbook, in order, e.g. to combine cookbooks
+     * loaded from several files. A recipe whose name is already taken is handled by `policy`.
+     *
+     * Returns the number of recipes added or replaced.
+     */
+    pub fn merge(&mut self, other: Cookbook, policy: MergePolicy) -> usize {
+        let mut merged = 0;
+        for mut recipe in other.recipes {
+            let Some(existing) = self.find_by_name(recipe.name()) else {
+                self.recipes.push(recipe);
+                merged += 1;
+                continue;
+            };
+            match policy {
+                MergePolicy::KeepFirst => {}
+                MergePolicy::KeepHigherNutrition => {
+                    if recipe.nutrition() > existing.nutrition() {
+                        let _ = self.replace_recipe(recipe);
+                        merged += 1;
+                    }
+                }
+                MergePolicy::Rename => {
+                    let base = recipe.name().clone();
+                    let mut number = 2;
+                    while self.find_by_name(recipe.name()).is_some() {
+                        recipe.name = format!("{} ({})", base, number);
+                        number += 1;
+                    }
+                    self.recipes.push(recipe);
+                    merged += 1;
+                }
+            }
+        }
+        merged
+    }
+
+    /**
+     * Removes and returns the recipe with the given name, or None if there is no such recipe.
+     */
+    pub fn remove_recipe(&mut self, name: &str) -> Option<Recipe> {
+        let index = self.recipes.iter().position(|recipe| recipe.name() == name)?;
+        Some(self.recipes.remove(index))
+    }
+
+    /**
+     * Replaces the recipe with the same name as `recipe`, keeping its place in the cookbook,
+     * and returns the old one. Returns `OceanError::RecipeNotFound` if there is no recipe with that name.
+     */
+    pub fn replace_recipe(&mut self, recipe: Recipe) -> Result<Recipe, OceanError> {
+        match self.recipes.iter_mut().find(|old| old.name() == recipe.name()) {
+            Some(old) => Ok(std::mem::replace(old, recipe)),
+            None => Err(OceanError::RecipeNotFound(recipe.name().clone())),
+        }
+    }
+}
+
+/// Decides which recipes a crab will consider in `Crab::choose_recipe_where`.
+/// Implemented for `RecipeFilter` and for any `Fn(&Recipe) -> bool`.
+pub trait RecipeCriteria {
+    fn accepts(&self, recipe: &Recipe) -> bool;
+}
+
+impl<F> RecipeCriteria for F
+where
+    F: Fn(&Recipe) -> bool,
+{
+    fn accepts(&self, recipe: &Recipe) -> bool {
+        self(recipe)
+    }
+}
+
+/// Common limits on which recipes to consider. Limits left as None are not checked, and
+/// recipes that do not state a prep time or difficulty are never excluded by them.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct RecipeFilter {
+    pub max_prep_time: Option<u32>,
+    pub max_difficulty: Option<u32>,
+}
+
+impl RecipeCriteria for RecipeFilter {
+    fn accepts(&self, recipe: &Recipe) -> bool {
+        let within = |limit: Option<u32>, value: Option<u32>| match (limit, value) {
+            (Some(limit), Some(value)) => value <= limit,
+            _ => true,
+        };
+        within(self.max_prep_time, recipe.prep_time()) && within(self.max_difficulty, recipe.difficulty())
+    }
+}
+
+/// What `Cookbook::merge` does with a recipe whose name the cookbook already has.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum MergePolicy {
+    /** Keep the recipe already in the cookbook and drop the new one. */
+    KeepFirst,
+    /**
+     * Keep whichever recipe has the higher nutrition, in the old one's place. A recipe that does
+     * not state its nutrition counts as the least nutritious, and ties keep the old recipe.
+     */
+    KeepHigherNutrition,
+    /** Keep both, adding the new one under its name with a number, e.g. "chowder (2)". */
+    Rename,
+}
+
+/// The recipe file formats `Cookbook::from_reader` understands.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum CookbookFormat {
+    Toml,
+    Json,
 }
 
 #[derive(Debug)]
 pub struct Recipe {
     name: String,
     diet: Diet,
+    nutrition: Option<u32>,
+    prep_time: Option<u32>,
+    difficulty: Option<u32>,
+    ingredients: Vec<(String, u32)>,
 }
 
 impl Recipe {
     pub fn new(name: String, diet: Diet) -> Recipe {
-        Recipe { name, diet }
+        Recipe {
+            name,
+            diet,
+            nutrition: None,
+            prep_time: None,
+            difficulty: None,
+            ingredients: Vec::new(),
+        }
+    }
+
+    /**
+     * Returns this recipe with `quantity` more prey of the given kind (see `Prey::kind`)
+     * among its ingredients.
+     */
+    pub fn with_ingredient(mut self, kind: &str, quantity: u32) -> Recipe {
+        match self.ingredients.iter_mut().find(|(k, _)| k == kind) {
+            Some((_, q)) => *q = q.saturating_add(quantity),
+            None => self.ingredients.push((kind.to_string(), quantity)),
+        }
+        self
+    }
+
+    /**
+     * Returns the kinds of prey this recipe needs, and how many of each, in the order they were added.
+     */
+    pub fn ingredients(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.ingredients.iter().map(|(kind, quantity)| (kind.as_str(), *quantity))
+    }
+
+    /**
+     * Returns this recipe with the given nutrition.
+     */
+    pub fn with_nutrition(mut self, nutrition: u32) -> Recipe {
+        self.nutrition = Some(nutrition);
+        self
+    }
+
+    /**
+     * Returns this recipe with the given preparation time, in minutes.
+     */
+    pub fn with_prep_time(mut self, prep_time: u32) -> Recipe {
+        self.prep_time = Some(prep_time);
+        self
+    }
+
+    /**
+     * Returns this recipe with the given difficulty, where higher is harder.
+     */
+    pub fn with_difficulty(mut self, difficulty: u32) -> Recipe {
+        self.difficulty = Some(difficulty);
+        self
+    }
+
+    pub fn difficulty(&self) -> Option<u32> {
+        self.difficulty
+    }
+
+    pub fn nutrition(&self) -> Option<u32> {
+        self.nutrition
+    }
+
+    pub fn prep_time(&self) -> Option<u32> {
+        self.prep_time
     }
 
     pub fn diet(&self) -> Diet {
@@ -39,3 +424,175 @@ impl Recipe {
         &self.name
     }
 }
+
+// A field value read from a recipe file.
+enum FieldValue {
+    Str(String),
+    Int(u32),
+    Ingredients(Vec<(String, u32)>),
+}
+
+// Builds a recipe from the fields of one entry in a recipe file, which starts on `line`.
+fn recipe_from_fields(fields: Vec<(String, FieldValue)>, line: usize) -> Result<Recipe, ParseError> {
+    let error = |message: String| ParseError::new(line, message);
+    let mut name = None;
+    let mut diet = None;
+    let mut nutrition = None;
+    let mut prep_time = None;
+    let mut difficulty = None;
+    let mut ingredients = Vec::new();
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("name", FieldValue::Str(s)) => name = Some(s),
+            ("diet", FieldValue::Str(s)) => diet = Some(s.parse::<Diet>().map_err(error)?),
+            ("nutrition", FieldValue::Int(n)) => nutrition = Some(n),
+            ("prep_time", FieldValue::Int(n)) => prep_time = Some(n),
+            ("difficulty", FieldValue::Int(n)) => difficulty = Some(n),
+            ("ingredients", FieldValue::Ingredients(list)) => ingredients.extend(list),
+            ("name" | "diet", _) => return Err(error(format!("recipe {} must be a string", key))),
+            ("nutrition" | "prep_time" | "difficulty", _) => return Err(error(format!("recipe {} must be a whole number", key))),
+            ("ingredients", _) => return Err(error("recipe ingredients must map prey kinds to quantities".to_string())),
+            _ => return Err(error(format!("unknown recipe field {:?}", key))),
+        }
+    }
+    let name = name.ok_or_else(|| error("recipe is missing a name".to_string()))?;
+    let diet = diet.ok_or_else(|| error(format!("recipe {} is missing a diet", name)))?;
+    let mut recipe = Recipe::new(name, diet);
+    recipe.nutrition = nutrition;
+    recipe.prep_time = prep_time;
+    recipe.difficulty = difficulty;
+    for (kind, quantity) in ingredients {
+        recipe = recipe.with_ingredient(&kind, quantity);
+    }
+    Ok(recipe)
+}
+
+// Parses the `[[recipe]]` tables of a TOML recipe file. Only the subset of TOML that recipe
+// files need is supported: comments, `[[recipe]]` and `[recipe.ingredients]` headers,
+// and `key = "string"` or `key = 123`.
+fn parse_toml_recipes(text: &str) -> Result<Vec<(Recipe, usize)>, ParseError> {
+    let mut tables: Vec<(Vec<(String, FieldValue)>, usize)> = Vec::new();
+    // Set while reading a `[recipe.ingredients]` table.
+    let mut ingredients: Option<Vec<(String, u32)>> = None;
+    for (i, line) in text.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            if let (Some(list), Some((fields, _))) = (ingredients.take(), tables.last_mut()) {
+                fields.push(("ingredients".to_string(), FieldValue::Ingredients(list)));
+            }
+        }
+        if line == "[[recipe]]" {
+            tables.push((Vec::new(), line_no));
+            continue;
+        }
+        if line == "[recipe.ingredients]" && !tables.is_empty() {
+            ingredients = Some(Vec::new());
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(ParseError::new(line_no, format!("unexpected table {}", line)));
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| ParseError::new(line_no, "expected key = value".to_string()))?;
+        let value = parse_toml_value(value.trim(), line_no)?;
+        if let Some(list) = ingredients.as_mut() {
+            let FieldValue::Int(quantity) = value else {
+                return Err(ParseError::new(line_no, "ingredient quantities must be whole numbers".to_string()));
+            };
+            list.push((key.trim().to_string(), quantity));
+            continue;
+        }
+        let (fields, _) = tables
+            .last_mut()
+            .ok_or_else(|| ParseError::new(line_no, "expected [[recipe]] before the first field".to_string()))?;
+        fields.push((key.trim().to_string(), value));
+    }
+    if let (Some(list), Some((fields, _))) = (ingredients, tables.last_mut()) {
+        fields.push(("ingredients".to_string(), FieldValue::Ingredients(list)));
+    }
+    tables
+        .into_iter()
+        .map(|(fields, line)| Ok((recipe_from_fields(fields, line)?, line)))
+        .collect()
+}
+
+fn parse_toml_value(value: &str, line: usize) -> Result<FieldValue, ParseError> {
+    let error = |message: &str| ParseError::new(line, message.to_string());
+    let Some(rest) = value.strip_prefix('"') else {
+        let number = value.split('#').next().unwrap_or("").trim();
+        return number
+            .parse()
+            .map(FieldValue::Int)
+            .map_err(|_| error("expected a quoted string or a whole number"));
+    };
+    let mut s = String::new();
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let trailing = chars.as_str().trim();
+                if !trailing.is_empty() && !trailing.starts_with('#') {
+                    return Err(error("unexpected text after string"));
+                }
+                return Ok(FieldValue::Str(s));
+            }
+            '\\' => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some(c @ ('"' | '\\')) => s.push(c),
+                _ => return Err(error("invalid escape in string")),
+            },
+            c => s.push(c),
+        }
+    }
+    Err(error("unterminated string"))
+}
+
+// Parses a JSON recipe file: an array of recipe objects, or an object with a "recipes" array.
+fn parse_json_recipes(text: &str) -> Result<Vec<(Recipe, usize)>, ParseError> {
+    let document = parse_json(text)?;
+    let entries = match &document {
+        JsonValue::Array(entries) => entries,
+        JsonValue::Object { line, .. } => match document.get("recipes") {
+            Some(JsonValue::Array(entries)) => entries,
+            _ => return Err(ParseError::new(*line, "expected a \"recipes\" array".to_string())),
+        },
+        _ => return Err(ParseError::new(1, "expected an array of recipes".to_string())),
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let JsonValue::Object { line, fields } = entry else {
+                return Err(ParseError::new(1, "expected each recipe to be an object".to_string()));
+            };
+            let fields = fields
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        JsonValue::String(s) => FieldValue::Str(s.clone()),
+                        JsonValue::Object { fields, .. } => FieldValue::Ingredients(
+                            fields
+                                .iter()
+                                .map(|(kind, quantity)| match quantity.as_u32() {
+                                    Some(n) => Ok((kind.clone(), n)),
+                                    None => Err(ParseError::new(*line, format!("invalid quantity of {}", kind))),
+                                })
+                                .collect::<Result<Vec<_>, ParseError>>()?,
+                        ),
+                        other => match other.as_u32() {
+                            Some(n) => FieldValue::Int(n),
+                            None => return Err(ParseError::new(*line, format!("invalid value for recipe {}", key))),
+                        },
+                    };
+                    Ok((key.clone(), value))
+                })
+                .collect::<Result<Vec<_>, ParseError>>()?;
+            Ok((recipe_from_fields(fields, *line)?, *line))
+        })
+        .collect()
+}
@@ -1,19 +1,289 @@
-use crate::color::Color;
-use crate::cookbook::{Cookbook, Recipe};
+use crate::bait::Bait;
+use crate::behavior::Behavior;
+use crate::color::{Color, Genotype};
+use crate::cookbook::{Cookbook, Recipe, RecipeCriteria};
 use crate::diet::Diet;
-use crate::prey::Prey;
+use crate::disease::{Disease, HEALTH_REGEN, MAX_HEALTH};
+use crate::error::OceanError;
+use crate::escape::{ChanceEscape, EscapeModel, PreyTraits};
+use crate::events::{self, OceanEvent};
+use crate::genome::{Genome, COLOR_STEP, MUTATION_RATE};
+use crate::pattern::Pattern;
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::position::Position;
+use crate::prey::{Carcass, Prey, PreyView};
 use crate::reef::Reef;
+use crate::trace;
+use rand::{Rng, RngCore};
 use std::cell::RefCell;
-use std::rc::Rc;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::{Rc, Weak};
+
+/// Identifies a crab within its beach's genealogy. Crabs get an id when they are added to or bred
+/// on a beach, and keep it when saved and loaded. Crabs that have never been on a beach have id 0.
+pub type CrabId = u64;
+
+/// The age, in simulation steps, at which a juvenile crab becomes an adult.
+pub const ADULT_AGE: u32 = 5;
+
+/// The age, in simulation steps, at which an adult crab becomes an elder.
+pub const ELDER_AGE: u32 = 50;
+
+/// How often, in simulation steps of age, a crab molts.
+pub const MOLT_INTERVAL: u32 = 20;
+
+/// How many simulation steps a molt lasts.
+pub const MOLT_STEPS: u32 = 2;
+
+/// How much faster a crab is after each molt.
+pub const MOLT_SPEED_GAIN: u32 = 1;
+
+/// The chance that a crab's shell comes out a slightly different color after a molt.
+pub const MOLT_COLOR_SHIFT_CHANCE: f64 = 0.25;
+
+/// How many notable events a crab remembers for its life summary (see `Crab::notable_events`).
+pub const MAX_NOTABLE_EVENTS: usize = 16;
+
+/// A crab's stage of life, which follows from its age (see `Crab::stage`).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Stage {
+    Juvenile,
+    Adult,
+    Elder,
+}
+
+/// A crab's lifetime record as a hunter (see `Crab::hunt_stats`). Only the crab's own hunts count:
+/// its shares of clan group hunts (see `Beach::group_hunt`) are counted in `Crab::hunts` alone.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct HuntStats {
+    /** The number of hunts the crab has gone on, caught or not. Molting crabs do not hunt. */
+    pub attempts: u32,
+    /** The number of those hunts that caught prey. */
+    pub successes: u32,
+    /** The number of prey that escaped the crab. */
+    pub escapes: u32,
+    /** The index of the reef (see `Crab::reefs`) where the crab has caught the most prey, the first if several tie, or None if it has caught none in its reefs. */
+    pub favorite_reef: Option<usize>,
+}
+
+impl HuntStats {
+    /**
+     * Returns the fraction of hunts that caught prey, between 0.0 and 1.0, or 0.0 if the crab
+     * has never hunted.
+     */
+    pub fn success_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            return 0.0;
+        }
+        self.successes as f64 / self.attempts as f64
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Crab {
     // TODO: Add fields here (some in part 1, some in part 2)
     name: String,
-    speed: u32,
-    color: Color,
+    id: CrabId,
+    parents: Option<(CrabId, CrabId)>,
+    genome: Genome,
+    pattern: Pattern,
+    diet: Diet,
+    reefs: Vec<ReefLink>,
+    // Whether reefs the crab discovers from now on are held weakly (see `Crab::set_weak_reefs`).
+    weak_reefs: bool,
+    hunts: u32,
+    // The crab's own hunts, caught or not, and the prey that escaped them (see `HuntStats`).
+    hunt_attempts: u32,
+    hunt_successes: u32,
+    escapes: u32,
+    // The prey caught in each of the crab's reefs, by index. Reefs past the end have none.
+    reef_catches: Vec<u32>,
+    offspring: u32,
+    energy: u32,
+    diet_threshold: f32,
+    // Whether hunts start from the reef `best_reef` picks rather than the first.
+    best_reef_first: bool,
+    larder: HashMap<String, u32>,
+    ratings: HashMap<String, u8>,
+    age: u32,
+    // Steps left in the crab's current molt, or 0 if it is not molting.
+    molt: u32,
+    // Steps left before the crab can breed again (see `Beach::set_breeding_cooldown`).
+    breeding_cooldown: u32,
+    // Steps in a row the crab has ended with no energy, and whether it has starved to death (see
+    // `Beach::set_starvation_limit`).
+    starving: u32,
+    dead: bool,
+    // Whether the crab is hibernating (see `Crab::hibernate`).
+    hibernating: bool,
+    health: u32,
+    disease: Option<Disease>,
+    position: Option<Position>,
+    // User-defined attributes (see `Crab::set_meta`), sorted by key so saves are stable.
+    meta: BTreeMap<String, String>,
+    // The clan the crab hunts for, which its beach sets before hunts (see `Reef::owner`).
+    clan: Option<String>,
+    // How well the crab's color matches its background, which its beach sets before hunts and
+    // predator attacks (see `Beach::background_color`).
+    color_match: f64,
+    // The behavior tree a simulation runs for the crab each step, if any (see `crate::behavior`).
+    behavior: Option<Behavior>,
+    events: Vec<OceanEvent>,
+    // The newest of the crab's notable events, for its life summary (see `Crab::notable_events`).
+    notable: Vec<OceanEvent>,
+}
+
+// A reef a crab has discovered. Weak links do not keep the reef alive, and are pruned once it
+// is dropped.
+#[derive(Debug, Clone)]
+enum ReefLink {
+    Strong(Rc<RefCell<Reef>>),
+    Weak(Weak<RefCell<Reef>>),
+}
+
+impl ReefLink {
+    fn new(reef: Rc<RefCell<Reef>>, weak: bool) -> ReefLink {
+        if weak {
+            ReefLink::Weak(Rc::downgrade(&reef))
+        } else {
+            ReefLink::Strong(reef)
+        }
+    }
+
+    // Returns the reef, or None if it has been dropped.
+    fn get(&self) -> Option<Rc<RefCell<Reef>>> {
+        match self {
+            ReefLink::Strong(reef) => Some(reef.clone()),
+            ReefLink::Weak(reef) => reef.upgrade(),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        match self {
+            ReefLink::Strong(_) => true,
+            ReefLink::Weak(reef) => reef.strong_count() > 0,
+        }
+    }
+
+    fn is(&self, reef: &Rc<RefCell<Reef>>) -> bool {
+        match self {
+            ReefLink::Strong(known) => Rc::ptr_eq(known, reef),
+            ReefLink::Weak(known) => std::ptr::eq(known.as_ptr(), Rc::as_ptr(reef)),
+        }
+    }
+}
+
+/// Everything about a crab but its reef handles, which tie a crab to one thread. This is how
+/// `Beach::hunt_all_parallel` sends crabs to its worker threads (see `Crab::detach`).
+pub(crate) struct Detached {
+    name: String,
+    id: CrabId,
+    parents: Option<(CrabId, CrabId)>,
+    genome: Genome,
+    pattern: Pattern,
     diet: Diet,
-    reefs: Vec<Rc<RefCell<Reef>>>
+    hunts: u32,
+    hunt_attempts: u32,
+    hunt_successes: u32,
+    escapes: u32,
+    reef_catches: Vec<u32>,
+    offspring: u32,
+    energy: u32,
+    diet_threshold: f32,
+    best_reef_first: bool,
+    larder: HashMap<String, u32>,
+    ratings: HashMap<String, u8>,
+    age: u32,
+    molt: u32,
+    breeding_cooldown: u32,
+    starving: u32,
+    dead: bool,
+    hibernating: bool,
+    health: u32,
+    disease: Option<Disease>,
+    position: Option<Position>,
+    meta: BTreeMap<String, String>,
+    clan: Option<String>,
+    color_match: f64,
+    behavior: Option<Behavior>,
+    events: Vec<OceanEvent>,
+    notable: Vec<OceanEvent>,
+}
+
+impl Detached {
+    // Rebuilds the crab, with no reefs.
+    pub(crate) fn into_crab(self) -> Crab {
+        let Detached {
+            name,
+            id,
+            parents,
+            genome,
+            pattern,
+            diet,
+            hunts,
+            hunt_attempts,
+            hunt_successes,
+            escapes,
+            reef_catches,
+            offspring,
+            energy,
+            diet_threshold,
+            best_reef_first,
+            larder,
+            ratings,
+            age,
+            molt,
+            breeding_cooldown,
+            starving,
+            dead,
+            hibernating,
+            health,
+            disease,
+            position,
+            meta,
+            clan,
+            color_match,
+            behavior,
+            events,
+            notable,
+        } = self;
+        Crab {
+            name,
+            id,
+            parents,
+            genome,
+            pattern,
+            diet,
+            reefs: Vec::new(),
+            weak_reefs: false,
+            hunts,
+            hunt_attempts,
+            hunt_successes,
+            escapes,
+            reef_catches,
+            offspring,
+            energy,
+            diet_threshold,
+            best_reef_first,
+            larder,
+            ratings,
+            age,
+            molt,
+            breeding_cooldown,
+            starving,
+            dead,
+            hibernating,
+            health,
+            disease,
+            position,
+            meta,
+            clan,
+            color_match,
+            behavior,
+            events,
+            notable,
+        }
+    }
 }
 
 // Do NOT implement Copy for Crab.
@@ -21,10 +291,39 @@ impl Crab {
     pub fn new(name: String, speed: u32, color: Color, diet: Diet) -> Crab {
         Crab {
             name,
-            speed,
-            color,
-            diet, 
-            reefs: Vec::new()
+            id: 0,
+            parents: None,
+            genome: Genome::new(speed, Genotype::homozygous(color), 0.0),
+            pattern: Pattern::Plain,
+            diet,
+            reefs: Vec::new(),
+            weak_reefs: false,
+            hunts: 0,
+            hunt_attempts: 0,
+            hunt_successes: 0,
+            escapes: 0,
+            reef_catches: Vec::new(),
+            offspring: 0,
+            energy: 0,
+            diet_threshold: 1.0,
+            best_reef_first: false,
+            larder: HashMap::new(),
+            ratings: HashMap::new(),
+            age: 0,
+            molt: 0,
+            breeding_cooldown: 0,
+            starving: 0,
+            dead: false,
+            hibernating: false,
+            health: MAX_HEALTH,
+            disease: None,
+            position: None,
+            meta: BTreeMap::new(),
+            clan: None,
+            color_match: 0.0,
+            behavior: None,
+            events: Vec::new(),
+            notable: Vec::new(),
         }
     }
 
@@ -32,27 +331,777 @@ impl Crab {
         &self.name
     }
 
+    // Renames the crab (see `Beach::rename_crab`).
+    pub(crate) fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn id(&self) -> CrabId {
+        self.id
+    }
+
+    /**
+     * Returns the ids of the crabs this crab was bred from, or None if it was not bred on its beach.
+     */
+    pub fn parents(&self) -> Option<(CrabId, CrabId)> {
+        self.parents
+    }
+
+    // Gives the crab its place in a beach's genealogy.
+    pub(crate) fn set_lineage(&mut self, id: CrabId, parents: Option<(CrabId, CrabId)>) {
+        self.id = id;
+        self.parents = parents;
+    }
+
+    /**
+     * Returns this crab's speed rounded down to a whole number. Prefer `speed_f`, which this will
+     * give way to.
+     */
     pub fn speed(&self) -> u32 {
-        self.speed
+        self.genome.speed()
     }
 
+    /**
+     * Returns this crab's speed, fractions included, e.g. after a molt or a mutation.
+     */
+    pub fn speed_f(&self) -> f64 {
+        self.genome.speed_f()
+    }
+
+    /**
+     * Returns the color this crab shows, which is the dominant allele of its genotype.
+     */
     pub fn color(&self) -> &Color {
-        &self.color
+        self.genome.color().expressed()
+    }
+
+    pub fn genotype(&self) -> &Genotype {
+        self.genome.color()
+    }
+
+    /**
+     * Returns how much this crab lowers a prey's chance of escaping it, from 0.0 to 1.0
+     * (see `ChanceEscape`). Crabs made with `new` have none.
+     */
+    pub fn escape_resistance(&self) -> f64 {
+        self.genome.escape_resistance()
+    }
+
+    /**
+     * Returns the genes this crab's speed, color, and escape resistance come from.
+     */
+    pub fn genome(&self) -> &Genome {
+        &self.genome
+    }
+
+    /**
+     * Returns this crab with the given genes, which replace its speed, color, and escape
+     * resistance.
+     */
+    pub fn with_genome(mut self, genome: Genome) -> Crab {
+        self.genome = genome;
+        self
+    }
+
+    /**
+     * Returns this crab with a behavior tree for simulations to run each step instead of its
+     * usual hunt (see `crate::behavior`).
+     */
+    pub fn with_behavior(mut self, behavior: Behavior) -> Crab {
+        self.behavior = Some(behavior);
+        self
+    }
+
+    /**
+     * Returns the crab's behavior tree, if it has one. Offspring are born without one.
+     */
+    pub fn behavior(&self) -> Option<&Behavior> {
+        self.behavior.as_ref()
+    }
+
+    /**
+     * Gives the crab a behavior tree, or takes its tree away with None so that it hunts every
+     * step like other crabs.
+     */
+    pub fn set_behavior(&mut self, behavior: Option<Behavior>) {
+        self.behavior = behavior;
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        self.pattern
+    }
+
+    /**
+     * Returns this crab with the given shell pattern. Crabs made with `new` are plain.
+     */
+    pub fn with_pattern(mut self, pattern: Pattern) -> Crab {
+        self.pattern = pattern;
+        self
     }
 
     pub fn diet(&self) -> Diet {
         self.diet
     }
 
+    /**
+     * Returns the lowest `Diet::affinity` of food this crab will eat. Defaults to 1.0,
+     * meaning the crab only eats food of its own diet.
+     */
+    pub fn diet_threshold(&self) -> f32 {
+        self.diet_threshold
+    }
+
+    /**
+     * Sets the lowest `Diet::affinity` of food this crab will eat, e.g. lowering it
+     * so a starving crab grudgingly eats prey it would normally pass over.
+     */
+    pub fn set_diet_threshold(&mut self, threshold: f32) {
+        self.diet_threshold = threshold;
+    }
+
+    pub fn best_reef_first(&self) -> bool {
+        self.best_reef_first
+    }
+
+    /**
+     * Sets whether this crab's hunts start from the reef `best_reef` picks, instead of from its
+     * first reef. Either way, the crab moves on through its other reefs in order. Defaults to false.
+     */
+    pub fn set_best_reef_first(&mut self, best_first: bool) {
+        self.best_reef_first = best_first;
+    }
+
+    /**
+     * Returns true if this crab is willing to eat food of the given diet.
+     */
+    pub fn will_eat(&self, diet: Diet) -> bool {
+        self.diet.affinity(diet) >= self.diet_threshold
+    }
+
+    /**
+     * Returns the number of successful hunts this crab has made.
+     */
+    pub fn hunts(&self) -> u32 {
+        self.hunts
+    }
+
+    /**
+     * Returns this crab's lifetime hunting record, e.g. to compare how often fast and slow crabs
+     * catch their prey.
+     */
+    pub fn hunt_stats(&self) -> HuntStats {
+        let (favorite, catches) = self
+            .reef_catches
+            .iter()
+            .zip(self.reefs.iter())
+            .filter(|(_, link)| link.is_alive())
+            .map(|(catches, _)| catches)
+            .enumerate()
+            .fold((0, 0), |best, (index, &catches)| if catches > best.1 { (index, catches) } else { best });
+        HuntStats {
+            attempts: self.hunt_attempts,
+            successes: self.hunt_successes,
+            escapes: self.escapes,
+            favorite_reef: (catches > 0).then_some(favorite),
+        }
+    }
+
+    /**
+     * Returns the number of crabs this crab has parented.
+     */
+    pub fn offspring(&self) -> u32 {
+        self.offspring
+    }
+
+    /**
+     * Returns the total nutrition of all the prey this crab has eaten.
+     */
+    pub fn energy(&self) -> u32 {
+        self.energy
+    }
+
+    pub(crate) fn record_offspring(&mut self) {
+        self.offspring = self.offspring.saturating_add(1);
+    }
+
+    // Crabs can only breed once their cooldown is over, so undoing a breeding ends it again.
+    pub(crate) fn forget_offspring(&mut self) {
+        self.offspring = self.offspring.saturating_sub(1);
+        self.breeding_cooldown = 0;
+    }
+
+    /**
+     * Returns how many more steps this crab must wait before it can breed again, or 0 if it can
+     * breed now (see `Beach::set_breeding_cooldown`).
+     */
+    pub fn breeding_cooldown(&self) -> u32 {
+        self.breeding_cooldown
+    }
+
+    pub(crate) fn start_breeding_cooldown(&mut self, steps: u32) {
+        self.breeding_cooldown = steps;
+    }
+
+    /**
+     * Returns false while this crab is resting after breeding (see `breeding_cooldown`). Its beach
+     * and the weather may still keep it from breeding (see `Beach::breeding_allowed`).
+     */
+    pub fn can_breed(&self) -> bool {
+        self.breeding_cooldown == 0
+    }
+
+    /**
+     * Returns how many simulation steps this crab has lived through (see `crate::simulation`).
+     */
+    pub fn age(&self) -> u32 {
+        self.age
+    }
+
+    /**
+     * Returns the crab's stage of life: a juvenile until it is `ADULT_AGE` steps old, an elder
+     * from `ELDER_AGE` steps on, and an adult in between. Beaches that enforce life stages (see
+     * `Beach::set_life_stages_enforced`) keep juveniles from breeding and joining clans.
+     */
+    pub fn stage(&self) -> Stage {
+        if self.age < ADULT_AGE {
+            Stage::Juvenile
+        } else if self.age < ELDER_AGE {
+            Stage::Adult
+        } else {
+            Stage::Elder
+        }
+    }
+
+    /**
+     * Returns how fast the crab can actually run: its speed, less a quarter for elders, scaled
+     * by its health (see `health`), and nothing at all while it is molting or if it is dead. Prey and predators
+     * race against this, while breeding and clans go by the crab's `speed`.
+     */
+    pub fn effective_speed(&self) -> u32 {
+        if self.is_molting() || self.dead {
+            return 0;
+        }
+        let speed = match self.stage() {
+            Stage::Elder => self.speed() - self.speed() / 4,
+            _ => self.speed(),
+        };
+        (speed as u64 * self.health as u64 / MAX_HEALTH as u64) as u32
+    }
+
+    /**
+     * Like `effective_speed`, but from `speed_f`, and without rounding down along the way: elders
+     * run at three quarters of their speed.
+     */
+    pub fn effective_speed_f(&self) -> f64 {
+        if self.is_molting() || self.dead {
+            return 0.0;
+        }
+        let speed = match self.stage() {
+            Stage::Elder => self.speed_f() * 0.75,
+            _ => self.speed_f(),
+        };
+        speed * self.health as f64 / MAX_HEALTH as f64
+    }
+
+    /**
+     * Returns the crab's health, from 0 to `MAX_HEALTH`. Diseases wear it down, and it grows back
+     * once the crab recovers (see `crate::disease`).
+     */
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    /**
+     * Returns the disease the crab is sick with, or None if it is healthy.
+     */
+    pub fn disease(&self) -> Option<&Disease> {
+        self.disease.as_ref()
+    }
+
+    pub fn is_sick(&self) -> bool {
+        self.disease.is_some()
+    }
+
+    /**
+     * Sets a user-defined attribute of this crab, such as the beach it came from or its
+     * experiment group, returning the value it replaces, if any. Attributes are saved with the
+     * crab, but are not inherited by its offspring.
+     */
+    pub fn set_meta(&mut self, key: &str, value: &str) -> Option<String> {
+        self.meta.insert(key.to_string(), value.to_string())
+    }
+
+    /**
+     * Returns the attribute set under `key` with `set_meta`, if any.
+     */
+    pub fn get_meta(&self, key: &str) -> Option<&str> {
+        self.meta.get(key).map(|value| value.as_str())
+    }
+
+    /**
+     * Removes and returns the attribute set under `key`.
+     */
+    pub fn remove_meta(&mut self, key: &str) -> Option<String> {
+        self.meta.remove(key)
+    }
+
+    /**
+     * Returns all of this crab's attributes, sorted by key.
+     */
+    pub fn meta(&self) -> &BTreeMap<String, String> {
+        &self.meta
+    }
+
+    /**
+     * Returns the clan this crab hunts for, as last set by its beach. Crabs hunting in a reef held
+     * by another clan pay it tribute (see `Reef::owner`).
+     */
+    pub fn clan(&self) -> Option<&str> {
+        self.clan.as_deref()
+    }
+
+    pub(crate) fn set_clan(&mut self, clan: Option<String>) {
+        self.clan = clan;
+    }
+
+    /**
+     * Returns how well this crab's color matches the background of the zone it is in, as last
+     * set by its beach (see `Beach::background_color`), from 0.0 (not at all) to 1.0. Crabs on
+     * beaches without a background match nothing.
+     */
+    pub fn color_match(&self) -> f64 {
+        self.color_match
+    }
+
+    pub(crate) fn set_color_match(&mut self, color_match: f64) {
+        self.color_match = color_match;
+    }
+
+    /**
+     * Returns how well this crab hides from predators, from 0.0 (not at all) to 1.0: the
+     * camouflage of its pattern (see `Pattern::camouflage`), together with how well its color
+     * matches its background (see `color_match`). Either alone hides the crab that well, and
+     * both together hide it better.
+     */
+    pub fn camouflage(&self) -> f64 {
+        1.0 - (1.0 - self.pattern.camouflage()) * (1.0 - self.color_match)
+    }
+
+    // Makes the crab sick with `disease`, unless it already has one. Returns true if it caught it.
+    pub(crate) fn infect(&mut self, disease: &Disease) -> bool {
+        if self.disease.is_some() {
+            return false;
+        }
+        self.queue_notable(OceanEvent::CrabInfected { crab: self.name.clone(), disease: disease.name().to_string() });
+        self.disease = Some(disease.clone());
+        true
+    }
+
+    // Runs one step of the crab's disease, if it has one: it loses health and may recover.
+    // Healthy crabs heal instead. Randomness comes from `rng`.
+    pub(crate) fn progress_disease<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        if self.hibernating {
+            return;
+        }
+        let Some(disease) = &self.disease else {
+            self.health = self.health.saturating_add(HEALTH_REGEN).min(MAX_HEALTH);
+            return;
+        };
+        self.health = self.health.saturating_sub(disease.damage());
+        if rng.gen_bool(disease.recovery_chance()) {
+            self.queue_notable(OceanEvent::CrabRecovered { crab: self.name.clone(), disease: disease.name().to_string() });
+            self.disease = None;
+        }
+    }
+
+    /**
+     * Returns true while the crab is molting. Every `MOLT_INTERVAL` steps of its life a crab
+     * sheds its shell for `MOLT_STEPS` steps, during which it cannot hunt and cannot outrun
+     * predators. It comes out `MOLT_SPEED_GAIN` faster, and perhaps a slightly different color.
+     */
+    pub fn is_molting(&self) -> bool {
+        self.molt > 0
+    }
+
+    /**
+     * Returns true if the crab has starved to death (see `Beach::set_starvation_limit`). Dead
+     * crabs cannot hunt or breed, and stay on their beach until it removes them.
+     */
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /**
+     * Puts the crab into hibernation, benching it without taking it off its beach. Until it wakes
+     * (see `wake`), it skips the steps of a simulation: it does not age, molt, starve, scavenge,
+     * or get better or worse from a disease. It cannot hunt or breed, its behavior tree does not
+     * run, and it counts for nothing in its clan's average speed and competitions. Predators can
+     * still catch it, and it can still be moved between beaches.
+     */
+    pub fn hibernate(&mut self) {
+        self.hibernating = true;
+    }
+
+    /**
+     * Wakes the crab from hibernation, if it is hibernating.
+     */
+    pub fn wake(&mut self) {
+        self.hibernating = false;
+    }
+
+    pub fn is_hibernating(&self) -> bool {
+        self.hibernating
+    }
+
+    /**
+     * Returns how many steps in a row this crab has ended with no energy.
+     */
+    pub fn starving_steps(&self) -> u32 {
+        self.starving
+    }
+
+    // Burns `metabolism` energy at the end of a step, and starves the crab to death if it has now
+    // had no energy for `limit` steps in a row. Returns true if the crab died.
+    pub(crate) fn starve(&mut self, metabolism: u32, limit: Option<u32>) -> bool {
+        if self.dead || self.hibernating {
+            return false;
+        }
+        self.energy = self.energy.saturating_sub(metabolism);
+        self.starving = if self.energy == 0 { self.starving.saturating_add(1) } else { 0 };
+        if limit.is_some_and(|limit| self.starving >= limit) {
+            self.dead = true;
+            self.queue_notable(OceanEvent::CrabStarved { crab: self.name.clone() });
+            return true;
+        }
+        false
+    }
+
+    // Ages the crab one step, starting or finishing a molt when it is due. Returns true if the
+    // crab finished a molt, which changes its speed. Randomness comes from `rng`.
+    pub(crate) fn grow_older<R: Rng + ?Sized>(&mut self, rng: &mut R) -> bool {
+        if self.hibernating {
+            return false;
+        }
+        self.age = self.age.saturating_add(1);
+        self.breeding_cooldown = self.breeding_cooldown.saturating_sub(1);
+        if self.molt > 0 {
+            self.molt -= 1;
+            if self.molt == 0 {
+                self.finish_molt(rng);
+                return true;
+            }
+        } else if self.age.is_multiple_of(MOLT_INTERVAL) {
+            self.molt = MOLT_STEPS;
+            events::queue(&mut self.events, OceanEvent::MoltStarted { crab: self.name.clone() });
+        }
+        false
+    }
+
+    // Gives the crab its new shell: it gets faster, and its expressed color may shift a little.
+    // Spends `cost` energy on training, which makes the crab `gain` faster. Returns false, and
+    // does nothing, if the crab has less than `cost` energy.
+    pub(crate) fn train(&mut self, cost: u32, gain: f64) -> bool {
+        if self.energy < cost {
+            return false;
+        }
+        self.energy -= cost;
+        self.genome = self.genome.clone().with_speed_f(self.speed_f() + gain);
+        true
+    }
+
+    fn finish_molt<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        let mut genome = self.genome.clone().with_speed_f(self.speed_f() + MOLT_SPEED_GAIN as f64);
+        if rng.gen_bool(MOLT_COLOR_SHIFT_CHANCE) {
+            let mut color = self.color().clone();
+            let component = match rng.gen_range(0..3) {
+                0 => &mut color.r,
+                1 => &mut color.g,
+                _ => &mut color.b,
+            };
+            *component = if rng.gen_bool(0.5) { component.saturating_add(COLOR_STEP) } else { component.saturating_sub(COLOR_STEP) };
+            genome = genome.with_color(Genotype::new(color, self.genotype().recessive().clone()));
+        }
+        self.genome = genome;
+        self.queue_notable(OceanEvent::MoltEnded { crab: self.name.clone(), speed: self.speed() });
+    }
+
+    /**
+     * Returns where this crab is on its beach (see `crate::position`), or None if it has not been
+     * placed.
+     */
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.position = Some(position);
+    }
+
+    /**
+     * Removes and returns the events queued by this crab's hunts, oldest first.
+     */
+    pub fn drain_events(&mut self) -> Vec<OceanEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /**
+     * Returns the crab's notable events, oldest first: falling ill, recovering, finishing a molt,
+     * and starving. Only the newest `MAX_NOTABLE_EVENTS` are kept, and draining the crab's events
+     * does not remove them.
+     */
+    pub fn notable_events(&self) -> &[OceanEvent] {
+        &self.notable
+    }
+
+    // Queues an event that is also worth remembering in the crab's life summary.
+    fn queue_notable(&mut self, event: OceanEvent) {
+        if self.notable.len() == MAX_NOTABLE_EVENTS {
+            self.notable.remove(0);
+        }
+        self.notable.push(event.clone());
+        events::queue(&mut self.events, event);
+    }
 
     pub fn breed(name: String, crab1: &Crab, crab2: &Crab) -> Crab {
+        let genome = Genome::blend(&crab1.genome, &crab2.genome);
+        Crab::newborn(name, crab1, crab2, genome, Diet::random_diet(), Pattern::cross(crab1.pattern, crab2.pattern))
+    }
+
+    // A newborn crab of the two parents, with everything that is not inherited at its start.
+    fn newborn(name: String, crab1: &Crab, crab2: &Crab, genome: Genome, diet: Diet, pattern: Pattern) -> Crab {
         Crab {
-            name: name,
-            speed: 1,
-            color: Color::cross(&crab1.color, &crab2.color),
-            diet: Diet::random_diet(),
-            reefs: Vec::new()
+            name,
+            id: 0,
+            parents: Some((crab1.id, crab2.id)),
+            genome,
+            diet,
+            pattern,
+            reefs: Vec::new(),
+            weak_reefs: false,
+            hunts: 0,
+            hunt_attempts: 0,
+            hunt_successes: 0,
+            escapes: 0,
+            reef_catches: Vec::new(),
+            offspring: 0,
+            energy: 0,
+            diet_threshold: 1.0,
+            best_reef_first: false,
+            larder: HashMap::new(),
+            ratings: HashMap::new(),
+            age: 0,
+            molt: 0,
+            breeding_cooldown: 0,
+            starving: 0,
+            dead: false,
+            hibernating: false,
+            health: MAX_HEALTH,
+            disease: None,
+            position: None,
+            meta: BTreeMap::new(),
+            clan: None,
+            color_match: 0.0,
+            behavior: None,
+            events: Vec::new(),
+            notable: Vec::new(),
+        }
+    }
+
+    /**
+     * Like `breed`, but the offspring's diet and pattern are drawn from `rng` instead of the
+     * crate's fixed-seed generator, so that runs can be reproduced with a chosen seed.
+     */
+    pub fn breed_with_rng<R: Rng + ?Sized>(name: String, crab1: &Crab, crab2: &Crab, rng: &mut R) -> Crab {
+        let genome = Genome::blend(&crab1.genome, &crab2.genome);
+        Crab::newborn_with_rng(name, crab1, crab2, genome, rng)
+    }
+
+    // Like `newborn`, but the diet and pattern are drawn from `rng`.
+    fn newborn_with_rng<R: Rng + ?Sized>(name: String, crab1: &Crab, crab2: &Crab, genome: Genome, rng: &mut R) -> Crab {
+        let diet = Diet::random_diet_from(rng);
+        let pattern = Pattern::cross_with(crab1.pattern, crab2.pattern, rng);
+        Crab::newborn(name, crab1, crab2, genome, diet, pattern)
+    }
+
+    /**
+     * Like `breed_with_rng`, but the offspring's color is inherited with `Color::cross_weighted`,
+     * where `dominance` is the chance that each color component comes from `crab1`.
+     */
+    pub fn breed_weighted<R: Rng + ?Sized>(name: String, crab1: &Crab, crab2: &Crab, dominance: f64, rng: &mut R) -> Crab {
+        let color = Color::cross_weighted(crab1.color(), crab2.color(), dominance, rng);
+        let genome = Genome::blend(&crab1.genome, &crab2.genome).with_color(Genotype::homozygous(color));
+        Crab::newborn_with_rng(name, crab1, crab2, genome, rng)
+    }
+
+    /**
+     * Like `breed`, but the offspring's color is the even mix of its parents' colors (see
+     * `Color::blend`), so a line of crabs shades gradually from one color to another.
+     */
+    pub fn breed_blended(name: String, crab1: &Crab, crab2: &Crab) -> Crab {
+        let color = Color::blend(crab1.color(), crab2.color(), 0.5);
+        let genome = Genome::blend(&crab1.genome, &crab2.genome).with_color(Genotype::homozygous(color));
+        Crab::newborn(name, crab1, crab2, genome, Diet::random_diet(), Pattern::cross(crab1.pattern, crab2.pattern))
+    }
+
+    /**
+     * Like `breed_with_rng`, but with dominant/recessive color inheritance: each parent passes on
+     * one of its two color alleles (see `Genotype::cross`), so a color hidden in both parents can
+     * reappear in their offspring.
+     */
+    pub fn breed_mendelian<R: Rng + ?Sized>(name: String, crab1: &Crab, crab2: &Crab, rng: &mut R) -> Crab {
+        let genotype = Genotype::cross(crab1.genotype(), crab2.genotype(), rng);
+        let genome = Genome::blend(&crab1.genome, &crab2.genome).with_color(genotype);
+        Crab::newborn_with_rng(name, crab1, crab2, genome, rng)
+    }
+
+    /**
+     * Like `breed_with_rng`, but the offspring inherits its speed, color, and escape resistance
+     * through its parents' genomes: each gene is crossed over from one parent or the other and
+     * may mutate (see `Genome::cross`), instead of following `breed`'s fixed rules.
+     */
+    pub fn breed_genetic<R: Rng + ?Sized>(name: String, crab1: &Crab, crab2: &Crab, rng: &mut R) -> Crab {
+        let genome = Genome::cross(&crab1.genome, &crab2.genome, MUTATION_RATE, rng);
+        Crab::breed_with_rng(name, crab1, crab2, rng).with_genome(genome)
+    }
+
+    /**
+     * Returns this crab's fields for saving with `crate::persist`.
+     * The crab's reefs are shared handles and are not saved.
+     */
+    pub fn to_fields(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.speed_f().to_string(),
+            self.color().r.to_string(),
+            self.color().g.to_string(),
+            self.color().b.to_string(),
+            format!("{:?}", self.diet),
+            self.hunts.to_string(),
+            self.offspring.to_string(),
+            self.energy.to_string(),
+            format!("{:?}", self.pattern),
+            self.genotype().recessive().to_hex(),
+            self.age.to_string(),
+            self.id.to_string(),
+            self.parents.map_or(String::new(), |(p1, _)| p1.to_string()),
+            self.parents.map_or(String::new(), |(_, p2)| p2.to_string()),
+            self.escape_resistance().to_string(),
+            self.position.map_or(String::new(), |position| position.x.to_string()),
+            self.position.map_or(String::new(), |position| position.y.to_string()),
+            self.molt.to_string(),
+            self.health.to_string(),
+            self.disease.as_ref().map_or(String::new(), |disease| disease.name().to_string()),
+            self.disease.as_ref().map_or(String::new(), |disease| disease.contagion().to_string()),
+            self.disease.as_ref().map_or(String::new(), |disease| disease.clan_contagion().to_string()),
+            self.disease.as_ref().map_or(String::new(), |disease| disease.damage().to_string()),
+            self.disease.as_ref().map_or(String::new(), |disease| disease.recovery_chance().to_string()),
+            encode_meta(&self.meta),
+            self.breeding_cooldown.to_string(),
+            self.hunt_attempts.to_string(),
+            self.hunt_successes.to_string(),
+            self.escapes.to_string(),
+            self.reef_catches.iter().map(u32::to_string).collect::<Vec<_>>().join(","),
+            self.starving.to_string(),
+            self.dead.to_string(),
+            self.hibernating.to_string(),
+        ]
+    }
+
+    /**
+     * Rebuilds a crab from fields produced by `to_fields`, reporting errors against `line`.
+     * Crabs saved before energy (8 fields), patterns (9 fields), recessive colors (10 fields),
+     * ages (11 fields), ids (12 fields), escape resistance (15 fields), positions (16 fields),
+     * molts (18 fields), health (19 fields), attributes (25 fields), breeding cooldowns (26
+     * fields), hunt statistics (27 fields), starvation (31 fields) or hibernation (33 fields)
+     * were tracked load with no energy, a plain pattern, two copies of their color, an age of 0,
+     * id 0 and no parents, no escape resistance, no position, no molt, full health, no
+     * attributes, no cooldown, no hunt statistics, alive and fed, and awake.
+     */
+    pub fn from_fields(fields: &[String], line: usize) -> Result<Crab, ParseError> {
+        if !matches!(fields.len(), 8..=12 | 15 | 16 | 18 | 19 | 25..=27 | 31 | 33 | 34) {
+            return Err(ParseError::new(line, format!("expected 34 crab fields, found {}", fields.len())));
+        }
+        let color = Color::new(
+            parse_field(&fields[2], "red component", line)?,
+            parse_field(&fields[3], "green component", line)?,
+            parse_field(&fields[4], "blue component", line)?,
+        );
+        let diet = fields[5].parse().map_err(|e| ParseError::new(line, e))?;
+        let mut crab = Crab::new(fields[0].clone(), 0, color, diet);
+        crab.genome = crab.genome.with_speed_f(parse_field(&fields[1], "speed", line)?);
+        crab.hunts = parse_field(&fields[6], "hunt count", line)?;
+        crab.offspring = parse_field(&fields[7], "offspring count", line)?;
+        if let Some(energy) = fields.get(8) {
+            crab.energy = parse_field(energy, "energy", line)?;
+        }
+        if let Some(pattern) = fields.get(9) {
+            crab.pattern = pattern.parse().map_err(|e| ParseError::new(line, e))?;
+        }
+        if let Some(recessive) = fields.get(10) {
+            let recessive = Color::from_hex(recessive).map_err(|e| ParseError::new(line, e))?;
+            let genotype = Genotype::new(crab.color().clone(), recessive);
+            crab.genome = crab.genome.with_color(genotype);
+        }
+        if let Some(age) = fields.get(11) {
+            crab.age = parse_field(age, "age", line)?;
+        }
+        if let Some(id) = fields.get(12) {
+            crab.id = parse_field(id, "crab id", line)?;
+            crab.parents = match (fields[13].as_str(), fields[14].as_str()) {
+                ("", "") => None,
+                (p1, p2) => Some((parse_field(p1, "parent id", line)?, parse_field(p2, "parent id", line)?)),
+            };
+        }
+        if let Some(resistance) = fields.get(15) {
+            let resistance: f64 = parse_field(resistance, "escape resistance", line)?;
+            if resistance.is_nan() {
+                return Err(ParseError::new(line, format!("invalid escape resistance: {:?}", fields[15])));
+            }
+            crab.genome = crab.genome.with_escape_resistance(resistance);
         }
+        if let (Some(x), Some(y)) = (fields.get(16), fields.get(17)) {
+            if !x.is_empty() || !y.is_empty() {
+                let position = Position::new(parse_field(x, "x position", line)?, parse_field(y, "y position", line)?);
+                crab.position = Some(position);
+            }
+        }
+        if let Some(molt) = fields.get(18) {
+            crab.molt = parse_field(molt, "molt", line)?;
+        }
+        if let Some(health) = fields.get(19) {
+            crab.health = parse_field::<u32>(health, "health", line)?.min(MAX_HEALTH);
+            if !fields[20].is_empty() {
+                let disease = Disease::new(&fields[20])
+                    .with_contagion(parse_field(&fields[21], "contagion", line)?)
+                    .with_clan_contagion(parse_field(&fields[22], "clan contagion", line)?)
+                    .with_damage(parse_field(&fields[23], "damage", line)?)
+                    .with_recovery_chance(parse_field(&fields[24], "recovery chance", line)?);
+                crab.disease = Some(disease);
+            }
+        }
+        if let Some(meta) = fields.get(25) {
+            crab.meta = decode_meta(meta, line)?;
+        }
+        if let Some(cooldown) = fields.get(26) {
+            crab.breeding_cooldown = parse_field(cooldown, "breeding cooldown", line)?;
+        }
+        if let Some(attempts) = fields.get(27) {
+            crab.hunt_attempts = parse_field(attempts, "hunt attempts", line)?;
+            crab.hunt_successes = parse_field(&fields[28], "hunt successes", line)?;
+            crab.escapes = parse_field(&fields[29], "escape count", line)?;
+            crab.reef_catches = match fields[30].as_str() {
+                "" => Vec::new(),
+                catches => catches.split(',').map(|n| parse_field(n, "reef catches", line)).collect::<Result<_, _>>()?,
+            };
+        }
+        if let Some(starving) = fields.get(31) {
+            crab.starving = parse_field(starving, "starving steps", line)?;
+            crab.dead = parse_field(&fields[32], "dead", line)?;
+        }
+        if let Some(hibernating) = fields.get(33) {
+            crab.hibernating = parse_field(hibernating, "hibernating", line)?;
+        }
+        Ok(crab)
     }
 
     // PART 2 BELOW
@@ -62,42 +1111,182 @@ impl Crab {
      * Have this crab discover a new reef, adding it to its list of reefs.
      */
     pub fn discover_reef(&mut self, reef: Rc<RefCell<Reef>>) {
-        self.reefs.push(reef);
+        self.prune_reefs();
+        self.reefs.push(ReefLink::new(reef, self.weak_reefs));
+    }
+
+    pub fn weak_reefs(&self) -> bool {
+        self.weak_reefs
     }
 
     /**
-     * Returns Some prey from one of the reefs this crab feeds from,
-     * and the index of that reef in self.reefs if able to find Some prey
-     * using the `take_prey` method of Reef.
+     * Sets whether this crab holds its reefs weakly, so that they are freed once nothing else
+     * (such as the `Ocean`) holds them, and forgotten by the crab then. Applies to the reefs it
+     * knows now and those it discovers later. Defaults to false: the crab keeps its reefs alive.
+     */
+    pub fn set_weak_reefs(&mut self, weak: bool) {
+        self.weak_reefs = weak;
+        for link in self.reefs.iter_mut() {
+            if let Some(reef) = link.get() {
+                *link = ReefLink::new(reef, weak);
+            }
+        }
+        self.prune_reefs();
+    }
+
+    /**
+     * Forgets the reefs this crab held weakly that have since been freed, along with its
+     * catches there. Returns the number forgotten.
      *
-     * If `take_prey` returns None, try the next reef. Try each reef only once.
+     * Crabs do this themselves before hunting or learning reefs, and skip freed reefs otherwise,
+     * so this is only needed to free the memory of the links sooner.
+     */
+    pub fn prune_reefs(&mut self) -> usize {
+        let mut index = 0;
+        let mut pruned = 0;
+        while index < self.reefs.len() {
+            if self.reefs[index].is_alive() {
+                index += 1;
+                continue;
+            }
+            self.reefs.remove(index);
+            if index < self.reef_catches.len() {
+                self.reef_catches.remove(index);
+            }
+            pruned += 1;
+        }
+        pruned
+    }
+
+    /**
+     * Teaches `other` the reef at `index` in this crab's reefs, as when a parent shows its
+     * offspring where to feed. Both crabs keep hunting there. The reef is added after `other`'s
+     * own reefs, unless `other` already knows it.
      *
-     * If all reefs are empty, or this crab has no reefs, return None.
+     * Returns true if `other` did not know the reef yet, or `OceanError::ReefNotFound` if this
+     * crab has no reef at `index`.
      */
-    fn catch_prey(&mut self) -> Option<(Box<dyn Prey>, usize)> {
-        for i in 0..self.reefs.len() {
-            let reef = self.reefs[i].clone();
-            let prey = reef.borrow_mut().take_prey();
-            if let Some(prey_box) = prey {
-                return Some((prey_box, i));
+    pub fn share_reef(&self, other: &mut Crab, index: usize) -> Result<bool, OceanError> {
+        let reef = self.reefs().nth(index).ok_or_else(|| OceanError::ReefNotFound(format!("at index {}", index)))?;
+        Ok(other.learn_reef(reef))
+    }
+
+    /**
+     * Like `share_reef`, but this crab gives the reef up: it is removed from this crab's reefs,
+     * and the crab no longer hunts there.
+     */
+    pub fn transfer_reef(&mut self, other: &mut Crab, index: usize) -> Result<bool, OceanError> {
+        self.prune_reefs();
+        if index >= self.reefs.len() {
+            return Err(OceanError::ReefNotFound(format!("at index {}", index)));
+        }
+        if index < self.reef_catches.len() {
+            self.reef_catches.remove(index);
+        }
+        match self.reefs.remove(index).get() {
+            Some(reef) => Ok(other.learn_reef(reef)),
+            None => Err(OceanError::ReefNotFound(format!("at index {}", index))),
+        }
+    }
+
+    // Adds a reef to the end of this crab's reefs unless it already has it. Returns true if added.
+    fn learn_reef(&mut self, reef: Rc<RefCell<Reef>>) -> bool {
+        self.prune_reefs();
+        if self.reefs.iter().any(|known| known.is(&reef)) {
+            return false;
+        }
+        self.reefs.push(ReefLink::new(reef, self.weak_reefs));
+        true
+    }
+
+    /**
+     * Returns handles to the reefs this crab has discovered, in the order it hunts in them.
+     * Reefs it held weakly that have since been freed are skipped.
+     */
+    pub fn reefs(&self) -> std::vec::IntoIter<Rc<RefCell<Reef>>> {
+        self.reefs.iter().filter_map(ReefLink::get).collect::<Vec<_>>().into_iter()
+    }
+
+    /**
+     * Lists the prey in this crab's reefs, reef by reef in hunting order, without taking any of
+     * them out, e.g. so a strategy can plan a hunt first. Each reef is borrowed only while its
+     * prey are listed, so it must not be mutably borrowed elsewhere at that moment.
+     */
+    pub fn visible_prey(&self) -> impl Iterator<Item = PreyView> + '_ {
+        self.reefs().enumerate().flat_map(|(index, reef)| {
+            reef.borrow().prey().map(|prey| PreyView::of(index, prey.as_ref())).collect::<Vec<_>>()
+        })
+    }
+
+    /**
+     * Returns the index of the reef holding the most prey this crab will eat (the first, if
+     * several tie), or None if none of its reefs holds any.
+     */
+    pub fn best_reef(&self) -> Option<usize> {
+        let edible = |reef: Rc<RefCell<Reef>>| reef.borrow().prey().filter(|prey| self.will_eat(prey.diet())).count();
+        let (best, count) = self
+            .reefs()
+            .map(edible)
+            .enumerate()
+            .fold((0, 0), |best, (index, count)| if count > best.1 { (index, count) } else { best });
+        (count > 0).then_some(best)
+    }
+
+    /**
+     * Returns Some prey this crab will eat from one of the reefs it feeds from, how many ticks it
+     * had lived there, and the index of that reef in self.reefs if able to find Some prey
+     * using the `take_prey_where` method of Reef.
+     *
+     * If `take_prey_where` returns None, try the next reef. Try each reef only once.
+     *
+     * If all reefs are empty of edible prey, or this crab has no reefs, return None.
+     *
+     * Reefs held weakly that have been freed are skipped. The crab prunes them before it hunts,
+     * so the indices of the rest match `best_reef`'s.
+     *
+     * With `bait` that lures some kinds of prey, every reef is tried for those kinds first.
+     */
+    fn catch_prey(&mut self, rng: Option<&mut (dyn RngCore + '_)>, bait: Option<&Bait>) -> Option<(Box<dyn Prey>, u32, usize)> {
+        let mut rng = rng;
+        let mut order: Vec<usize> = (0..self.reefs.len()).collect();
+        if let Some(best) = self.best_reef().filter(|_| self.best_reef_first) {
+            order.remove(best);
+            order.insert(0, best);
+        }
+        let lure = bait.filter(|bait| bait.lures().next().is_some());
+        let passes: &[bool] = if lure.is_some() { &[true, false] } else { &[false] };
+        for &lured_only in passes {
+            for &i in order.iter() {
+                let Some(reef) = self.reefs[i].get() else {
+                    continue;
+                };
+                let lured = |prey: &dyn Prey| lure.is_some_and(|bait| bait.attracts(prey.kind()));
+                let wanted = |prey: &dyn Prey| self.will_eat(prey.diet()) && (!lured_only || lured(prey));
+                let prey = reef.borrow_mut().take_prey_using(wanted, rng.as_deref_mut());
+                if let Some((prey_box, age)) = prey {
+                    return Some((prey_box, age, i));
+                }
             }
         }
-        
+
         None
     }
 
     /**
-     * Releases the given prey back into the reef at the given index.
+     * Releases the given prey back into the reef at the given index, as old as when it was taken.
      */
-    fn release_prey(&mut self, prey: Box<dyn Prey>, reef_index: usize) {
-        self.reefs[reef_index].borrow_mut().add_prey(prey);
+    fn release_prey(&mut self, prey: Box<dyn Prey>, age: u32, reef_index: usize) {
+        if let Some(reef) = self.reefs[reef_index].get() {
+            reef.borrow_mut().return_prey(prey, age);
+        }
     }
 
     /**
      * Have this crab go hunting.
      *
      * A crab will keep trying to catch prey until it succeeds,
-     * or runs out of remaining prey to try to catch.
+     * or runs out of remaining prey to try to catch. Prey the crab cannot eat
+     * are never taken out of their reefs, so they cannot escape or be disturbed.
      *
      * You should keep track of all escaped prey in a local.
      *
@@ -130,59 +1319,501 @@ impl Crab {
      * Note: this pseudocode reads like a terrible poem.
      */
     pub fn hunt(&mut self) -> bool {
+        self.hunt_using(None, None)
+    }
+
+    /**
+     * Like `hunt`, but every random choice is drawn from `rng`: which prey a reef with
+     * `TakePolicy::Random` hands out, and whether it escapes (see `Prey::try_escape_with`).
+     * This lets hunts be reproduced with a chosen seed.
+     */
+    pub fn hunt_with_rng<R: RngCore + ?Sized>(&mut self, rng: &mut R) -> bool {
+        let mut rng = rng;
+        self.hunt_using(Some(&mut rng), None)
+    }
+
+    /**
+     * Like `hunt`, but with `bait`, which is used up whether or not prey is caught: prey of the
+     * kinds it lures are hunted first, and it keeps prey from escaping `Bait::hold` of the time
+     * (see `crate::bait`).
+     */
+    pub fn hunt_with_bait(&mut self, bait: Bait) -> bool {
+        self.hunt_using(None, Some(&bait))
+    }
+
+    /**
+     * Like `hunt_with_bait`, but every random choice is drawn from `rng`, as with `hunt_with_rng`.
+     */
+    pub fn hunt_with_bait_and_rng<R: RngCore + ?Sized>(&mut self, bait: Bait, rng: &mut R) -> bool {
+        let mut rng = rng;
+        self.hunt_using(Some(&mut rng), Some(&bait))
+    }
+
+    // Hunts as described on `hunt`, with `bait` if given, drawing randomness from `rng` if given,
+    // or from the crate's fixed-seed generator otherwise.
+    fn hunt_using(&mut self, mut rng: Option<&mut dyn RngCore>, bait: Option<&Bait>) -> bool {
+        let _span = trace::span!("hunt", crab = self.name, reefs = self.reefs.len());
+        if self.is_molting() || self.dead || self.hibernating {
+            return false;
+        }
+        self.prune_reefs();
+        let mut escaped_prey: Vec<(Box<dyn Prey>, u32, usize)> = Vec::new();
+        let mut caught_in = None;
+
+        let hold = bait.map_or(0.0, Bait::hold);
+        while let Some((mut prey_box, age, reef_index)) = self.catch_prey(rng.as_deref_mut(), bait) {
+            let chance = self.reefs[reef_index].get().and_then(|reef| reef.borrow().escape_chance());
+            let mut escapes = |prey: &mut dyn Prey, crab: &Crab| {
+                attempt_escape(prey, crab, chance, rng.as_deref_mut()) && !held(hold, rng.as_deref_mut())
+            };
+            // if prey escapes or is not edible, mark as escaped
+            if !self.catches(prey_box.as_mut(), &mut escapes) {
+                trace::event!("escaped", reef = reef_index, prey = prey_box.kind());
+                self.record_escape(prey_box.as_ref());
+                escaped_prey.push((prey_box, age, reef_index));
+                continue;
+            }
 
-        let mut escaped_prey: Vec<Box<dyn Prey>> = Vec::new();
+            // else it is caught
+            trace::event!("caught", reef = reef_index, prey = prey_box.kind());
+            if let Some(reef) = self.reefs[reef_index].get() {
+                self.eat(prey_box.as_ref(), &mut reef.borrow_mut());
+            }
+            caught_in = Some(reef_index);
+            break;
+        }
+
+        // release each escaped prey back to its reef
+        for (prey_box, age, reef_index) in escaped_prey {
+            self.release_prey(prey_box, age, reef_index);
+        }
+
+        let prey_caught = caught_in.is_some();
+        self.record_hunt(prey_caught, caught_in);
+        trace::event!("finished", caught = prey_caught);
+
+        return prey_caught;
+    }
+
+    /**
+     * Like `hunt`, but hunts only in the given reef rather than in the reefs this crab
+     * has discovered. This is how crabs hunt in a `SyncReef` shared between threads.
+     */
+    pub fn hunt_in(&mut self, reef: &mut Reef) -> bool {
+        let _span = trace::span!("hunt_in", crab = self.name, reef = reef.name().unwrap_or("unnamed"));
+        if self.is_molting() || self.dead || self.hibernating {
+            return false;
+        }
+        let mut escaped_prey: Vec<(Box<dyn Prey>, u32)> = Vec::new();
         let mut prey_caught = false;
-        let mut reef_index = 0;
-
-        while !prey_caught {
-            
-            if let Some((mut prey_box, reef_index)) = self.catch_prey() {
-                
-                // if prey escapes or is not edible, mark as escaped
-                if prey_box.try_escape(&self) || self.diet != prey_box.diet()  {
-                    escaped_prey.push(prey_box);
-                }
 
-                //else it is caught
-                else{
-                    prey_caught = true;
-                }
-               
+        while let Some((mut prey_box, age)) = reef.take_prey_using(|prey| self.will_eat(prey.diet()), None) {
+            let chance = reef.escape_chance();
+            if !self.catches(prey_box.as_mut(), &mut |prey, crab| attempt_escape(prey, crab, chance, None)) {
+                trace::event!("escaped", prey = prey_box.kind());
+                self.record_escape(prey_box.as_ref());
+                escaped_prey.push((prey_box, age));
+                continue;
             }
-            else{
-                //no more prey to catch
+            trace::event!("caught", prey = prey_box.kind());
+            self.eat(prey_box.as_ref(), reef);
+            prey_caught = true;
+            break;
+        }
+
+        for (prey_box, age) in escaped_prey {
+            reef.return_prey(prey_box, age);
+        }
+
+        self.record_hunt(prey_caught, None);
+        trace::event!("finished", caught = prey_caught);
+        return prey_caught;
+    }
+
+    // Hunts like `hunt_with_rng`, but in reefs the caller owns: `reefs[order[0]]`, then
+    // `reefs[order[1]]`, and so on. This is how crabs hunt on `Beach::hunt_all_parallel`'s threads.
+    pub(crate) fn hunt_among(&mut self, reefs: &mut [Reef], order: &[usize], rng: &mut dyn RngCore) -> bool {
+        let _span = trace::span!("hunt", crab = self.name, reefs = order.len());
+        if self.is_molting() || self.dead || self.hibernating {
+            return false;
+        }
+        let mut escaped_prey: Vec<(Box<dyn Prey>, u32, usize)> = Vec::new();
+        let mut caught_in = None;
+
+        loop {
+            let found = order.iter().find_map(|&i| {
+                let wanted = |prey: &dyn Prey| self.will_eat(prey.diet());
+                reefs[i].take_prey_using(wanted, Some(&mut *rng)).map(|(prey, age)| (prey, age, i))
+            });
+            let Some((mut prey_box, age, reef_index)) = found else {
                 break;
+            };
+            let chance = reefs[reef_index].escape_chance();
+            let mut escapes = |prey: &mut dyn Prey, crab: &Crab| attempt_escape(prey, crab, chance, Some(&mut *rng));
+            if !self.catches(prey_box.as_mut(), &mut escapes) {
+                trace::event!("escaped", reef = reef_index, prey = prey_box.kind());
+                self.record_escape(prey_box.as_ref());
+                escaped_prey.push((prey_box, age, reef_index));
+                continue;
+            }
+            trace::event!("caught", reef = reef_index, prey = prey_box.kind());
+            self.eat(prey_box.as_ref(), &mut reefs[reef_index]);
+            // The crab's own reefs are `reefs[order[0]]`, `reefs[order[1]]`, and so on.
+            caught_in = order.iter().position(|&i| i == reef_index);
+            break;
+        }
+
+        for (prey_box, age, reef_index) in escaped_prey {
+            reefs[reef_index].return_prey(prey_box, age);
+        }
+
+        let prey_caught = caught_in.is_some();
+        self.record_hunt(prey_caught, caught_in);
+        trace::event!("finished", caught = prey_caught);
+        return prey_caught;
+    }
+
+    // Hunts in `reef` for a clan hunting together (see `Beach::group_hunt`). This crab stands in
+    // for the whole group, with the members' combined speed, and goes after any prey `edible`
+    // accepts. Returns the caught prey uneaten, after releasing the prey that escaped.
+    pub(crate) fn hunt_together<F>(&self, reef: &mut Reef, mut edible: F, rng: &mut dyn RngCore) -> Option<Box<dyn Prey>>
+    where
+        F: FnMut(Diet) -> bool,
+    {
+        let _span = trace::span!("group_hunt", clan = self.name, speed = self.speed());
+        let mut escaped_prey: Vec<(Box<dyn Prey>, u32)> = Vec::new();
+        let mut caught = None;
+        while let Some((mut prey_box, age)) = reef.take_prey_using(|prey| edible(prey.diet()), Some(&mut *rng)) {
+            let chance = reef.escape_chance();
+            let attempts = prey_box.size().escape_attempts();
+            if (0..attempts).any(|_| attempt_escape(prey_box.as_mut(), self, chance, Some(&mut *rng))) {
+                trace::event!("escaped", prey = prey_box.kind());
+                escaped_prey.push((prey_box, age));
+                continue;
+            }
+            trace::event!("caught", prey = prey_box.kind());
+            caught = Some(prey_box);
+            break;
+        }
+        for (prey_box, age) in escaped_prey {
+            reef.return_prey(prey_box, age);
+        }
+        caught
+    }
+
+    // Eats a carcass found on the crab's beach (see `Beach::scavenge`).
+    pub(crate) fn scavenge(&mut self, carcass: &Carcass) {
+        self.energy = self.energy.saturating_add(carcass.nutrition());
+        events::queue(&mut self.events, OceanEvent::CarcassEaten { crab: self.name.clone(), carcass: carcass.name().to_string() });
+    }
+
+    // Gives this crab its share of a group hunt's catch, counting the hunt as a success. The
+    // crab that keeps the prey in its larder is also given the prey itself.
+    pub(crate) fn take_share(&mut self, energy: u32, prey: Option<&dyn Prey>) {
+        self.energy = self.energy.saturating_add(energy);
+        self.hunts = self.hunts.saturating_add(1);
+        if let Some(prey) = prey {
+            let stock = self.larder.entry(prey.kind().to_string()).or_insert(0);
+            *stock = stock.saturating_add(1);
+            events::queue(&mut self.events, OceanEvent::PreyCaught {
+                crab: self.name.clone(),
+                prey: prey.kind().to_string(),
+            });
+        }
+    }
+
+    // Moves everything but the crab's reef handles, which cannot leave this thread, out of the
+    // crab. Until `reattach` puts it back, the crab has no name, larder, ratings, or events.
+    pub(crate) fn detach(&mut self) -> Detached {
+        Detached {
+            name: std::mem::take(&mut self.name),
+            id: self.id,
+            parents: self.parents,
+            genome: self.genome.clone(),
+            pattern: self.pattern,
+            diet: self.diet,
+            hunts: self.hunts,
+            hunt_attempts: self.hunt_attempts,
+            hunt_successes: self.hunt_successes,
+            escapes: self.escapes,
+            reef_catches: std::mem::take(&mut self.reef_catches),
+            offspring: self.offspring,
+            energy: self.energy,
+            diet_threshold: self.diet_threshold,
+            best_reef_first: self.best_reef_first,
+            larder: std::mem::take(&mut self.larder),
+            ratings: std::mem::take(&mut self.ratings),
+            age: self.age,
+            molt: self.molt,
+            breeding_cooldown: self.breeding_cooldown,
+            starving: self.starving,
+            dead: self.dead,
+            hibernating: self.hibernating,
+            health: self.health,
+            disease: self.disease.clone(),
+            position: self.position,
+            meta: std::mem::take(&mut self.meta),
+            clan: self.clan.take(),
+            color_match: self.color_match,
+            behavior: self.behavior.take(),
+            events: std::mem::take(&mut self.events),
+            notable: std::mem::take(&mut self.notable),
+        }
+    }
+
+    // Takes on the state of a detached copy of this crab, keeping this crab's reefs.
+    pub(crate) fn reattach(&mut self, detached: Detached) {
+        let reefs = std::mem::take(&mut self.reefs);
+        let weak_reefs = self.weak_reefs;
+        *self = detached.into_crab();
+        self.reefs = reefs;
+        self.weak_reefs = weak_reefs;
+    }
+
+    // Counts one of the crab's own hunts, which caught prey in the reef at index `reef` of its
+    // reefs if given.
+    fn record_hunt(&mut self, caught: bool, reef: Option<usize>) {
+        self.hunt_attempts = self.hunt_attempts.saturating_add(1);
+        if !caught {
+            return;
+        }
+        self.hunts = self.hunts.saturating_add(1);
+        self.hunt_successes = self.hunt_successes.saturating_add(1);
+        if let Some(reef) = reef {
+            if self.reef_catches.len() <= reef {
+                self.reef_catches.resize(reef + 1, 0);
+            }
+            self.reef_catches[reef] = self.reef_catches[reef].saturating_add(1);
+        }
+    }
+
+    // Records a prey caught in `reef`: the crab gains its nutrition, less any tribute it owes the
+    // reef's holder, and keeps it in its larder for cooking.
+    fn eat(&mut self, prey: &dyn Prey, reef: &mut Reef) {
+        let nutrition = reef.levy(self.clan.as_deref(), prey.nutrition());
+        self.energy = self.energy.saturating_add(nutrition);
+        let stock = self.larder.entry(prey.kind().to_string()).or_insert(0);
+        *stock = stock.saturating_add(1);
+        events::queue(&mut self.events, OceanEvent::PreyCaught {
+            crab: self.name.clone(),
+            prey: prey.kind().to_string(),
+        });
+    }
+
+    fn record_escape(&mut self, prey: &dyn Prey) {
+        self.escapes = self.escapes.saturating_add(1);
+        events::queue(&mut self.events, OceanEvent::PreyEscaped {
+            crab: self.name.clone(),
+            prey: prey.kind().to_string(),
+        });
+    }
+
+    /**
+     * Returns how many prey of the given kind (see `Prey::kind`) this crab has caught and not
+     * yet cooked with.
+     */
+    pub fn larder(&self, kind: &str) -> u32 {
+        self.larder.get(kind).copied().unwrap_or(0)
+    }
+
+    /**
+     * Returns true if this crab's larder holds every ingredient of `recipe`.
+     */
+    pub fn can_cook(&self, recipe: &Recipe) -> bool {
+        recipe
+            .ingredients()
+            .all(|(kind, quantity)| self.larder(kind) >= quantity)
+    }
+
+    /**
+     * Cooks `recipe`, using up its ingredients from this crab's larder.
+     * Returns `OceanError::MissingIngredients`, leaving the larder unchanged, if the crab cannot cook it.
+     */
+    pub fn cook(&mut self, recipe: &Recipe) -> Result<(), OceanError> {
+        if !self.can_cook(recipe) {
+            return Err(OceanError::MissingIngredients { crab: self.name.clone(), recipe: recipe.name().clone() });
+        }
+        for (kind, quantity) in recipe.ingredients() {
+            // Ingredients needed 0 times may be missing from the larder altogether.
+            let Some(stock) = self.larder.get_mut(kind) else {
+                continue;
+            };
+            *stock -= quantity;
+            if *stock == 0 {
+                self.larder.remove(kind);
             }
-        
         }
+        Ok(())
+    }
 
+    /**
+     * Cooks `recipe` as with `cook`, but into bait rather than a dish (see `Bait::from_recipe`).
+     * Returns `OceanError::MissingIngredients`, leaving the larder unchanged, if the crab cannot
+     * cook it.
+     */
+    pub fn craft_bait(&mut self, recipe: &Recipe) -> Result<Bait, OceanError> {
+        self.cook(recipe)?;
+        Ok(Bait::from_recipe(recipe))
+    }
 
-        //release each escaped prey back to its reef
-        for prey_box in escaped_prey {
-            self.release_prey(prey_box, reef_index);
+    /**
+     * Cuts one prey of the given kind from this crab's larder into bait (see `Bait::from_prey`).
+     * Returns `OceanError::MissingIngredients` if the larder has none.
+     */
+    pub fn cut_bait(&mut self, kind: &str) -> Result<Bait, OceanError> {
+        let bait = Bait::from_prey(kind);
+        let Some(stock) = self.larder.get_mut(kind) else {
+            return Err(OceanError::MissingIngredients { crab: self.name.clone(), recipe: bait.name().to_string() });
+        };
+        *stock -= 1;
+        if *stock == 0 {
+            self.larder.remove(kind);
         }
+        Ok(bait)
+    }
 
-        return prey_caught; 
+    /**
+     * Cooks `recipe` as with `cook`, then eats the dish: the crab gains the recipe's nutrition
+     * (see `Recipe::with_nutrition`) in energy, and as much health, up to `MAX_HEALTH`. Recipes
+     * without a nutrition are eaten for nothing. Returns `OceanError::MissingIngredients`, leaving
+     * the crab unchanged, if it cannot cook the recipe.
+     */
+    pub fn cook_and_eat(&mut self, recipe: &Recipe) -> Result<(), OceanError> {
+        self.cook(recipe)?;
+        let nutrition = recipe.nutrition().unwrap_or(0);
+        self.energy = self.energy.saturating_add(nutrition);
+        self.health = self.health.saturating_add(nutrition).min(MAX_HEALTH);
+        Ok(())
+    }
 
+    /**
+     * Records this crab's rating of the named recipe, from 1 to 5 stars, replacing any earlier
+     * rating. Returns `OceanError::InvalidArgument` if `stars` is out of range.
+     */
+    pub fn rate_recipe(&mut self, name: &str, stars: u8) -> Result<(), OceanError> {
+        if !(1..=5).contains(&stars) {
+            return Err(OceanError::InvalidArgument(format!("Ratings are from 1 to 5 stars, not {}", stars)));
+        }
+        self.ratings.insert(name.to_string(), stars);
+        Ok(())
+    }
 
-        
+    /**
+     * Returns this crab's rating of the named recipe, or None if it has not rated it.
+     */
+    pub fn rating(&self, name: &str) -> Option<u8> {
+        self.ratings.get(name).copied()
+    }
+
+    /**
+     * Like `choose_recipe`, but prefers the recipes this crab has rated highest. Diet affinity
+     * breaks ties, and unrated recipes come after all rated ones.
+     */
+    pub fn choose_best_recipe<'a>(&self, cookbook: &'a Cookbook) -> Option<&'a Recipe> {
+        let mut best: Option<&Recipe> = None;
+        let score = |recipe: &Recipe| (self.rating(recipe.name()).unwrap_or(0), self.diet.affinity(recipe.diet()));
+        for recipe in cookbook.recipes().filter(|recipe| self.will_eat(recipe.diet())) {
+            if best.is_none_or(|best| score(recipe) > score(best)) {
+                best = Some(recipe);
+            }
+        }
+        best
     }
 
     /**
-     * Returns Some of any recipe from the given     cookbook that matches the crab's diet
-     * preferences, or None if no such recipe exists.
+     * Tries to catch the given prey: it is caught if this crab will eat it and it fails to escape.
+     * Bigger prey get more than one chance to escape (see `Size::escape_attempts`).
+     */
+    fn catches<F>(&self, prey: &mut dyn Prey, escapes: &mut F) -> bool
+    where
+        F: FnMut(&mut dyn Prey, &Crab) -> bool,
+    {
+        let attempts = prey.size().escape_attempts();
+        let escaped = (0..attempts).any(|_| escapes(&mut *prey, self));
+        !escaped && self.will_eat(prey.diet())
+    }
+
+    /**
+     * Returns Some of the recipe from the given cookbook that best matches the crab's diet
+     * preferences (the first one with the highest `Diet::affinity`), or None if the crab
+     * will not eat any of them.
      *
      * IMPORTANT: you will need to add lifetime parameters to this function. It is
      * up to you to figure out which ones and where. Do not make any other changes
      * to the signature.
      */
     pub fn choose_recipe<'a>(&self, cookbook: &'a Cookbook) -> Option<&'a Recipe> {
-        for recipe in cookbook.recipes() {
-            if self.diet == recipe.diet() {
-                return Some(recipe);
+        self.choose_recipe_where(cookbook, |_: &Recipe| true)
+    }
+
+    /**
+     * Like `choose_recipe`, but only considers recipes that `criteria` accepts, e.g. a
+     * `RecipeFilter` or a closure.
+     */
+    pub fn choose_recipe_where<'a, C: RecipeCriteria>(&self, cookbook: &'a Cookbook, criteria: C) -> Option<&'a Recipe> {
+        let mut best: Option<&Recipe> = None;
+        let candidates = cookbook
+            .recipes()
+            .filter(|recipe| self.will_eat(recipe.diet()) && criteria.accepts(recipe));
+        for recipe in candidates {
+            let affinity = self.diet.affinity(recipe.diet());
+            if best.is_none_or(|best| affinity > self.diet.affinity(best.diet())) {
+                best = Some(recipe);
             }
         }
-        None
+        best
+    }
+}
+
+// Decides whether `prey` escapes `crab` on one attempt: with the escape chance of the reef it
+// came from if that reef sets one (see `Reef::set_escape_chance`), or as the prey itself decides
+// otherwise. Randomness comes from `rng` if given, or from the crate's fixed-seed generator.
+fn attempt_escape(prey: &mut dyn Prey, crab: &Crab, reef_chance: Option<f64>, rng: Option<&mut (dyn RngCore + '_)>) -> bool {
+    let Some(chance) = reef_chance else {
+        return match rng {
+            Some(rng) => prey.try_escape_with(crab, rng),
+            None => prey.try_escape(crab),
+        };
+    };
+    let mut model = ChanceEscape::new(chance, 0.0, 0.0, 0.0);
+    let traits = PreyTraits { speed: 0, camouflage: 0.0, size: prey.size() };
+    match rng {
+        Some(rng) => model.escapes_with(&traits, crab, rng),
+        None => model.escapes(&traits, crab),
+    }
+}
+
+// Decides whether bait that holds prey `hold` of the time keeps an escaping prey from getting
+// away, drawing nothing for no hold. Randomness comes from `rng` if given, or from the crate's
+// fixed-seed generator (or the `deterministic` feature's strategies) otherwise.
+fn held(hold: f64, rng: Option<&mut (dyn RngCore + '_)>) -> bool {
+    if hold <= 0.0 {
+        return false;
+    }
+    match rng {
+        Some(rng) => rng.gen_bool(hold),
+        #[cfg(feature = "deterministic")]
+        None => crate::deterministic::escape(hold),
+        #[cfg(not(feature = "deterministic"))]
+        None => (crate::rand::rand32() as f64 / (u32::MAX as f64 + 1.0)) < hold,
+    }
+}
+
+// Packs a crab's attributes into a single saved field: a record of alternating keys and values,
+// whose tabs the enclosing record escapes. No attributes make an empty field.
+fn encode_meta(meta: &BTreeMap<String, String>) -> String {
+    let fields: Vec<String> = meta.iter().flat_map(|(key, value)| [key.clone(), value.clone()]).collect();
+    join_record(&fields)
+}
+
+// Reverses `encode_meta`.
+fn decode_meta(field: &str, line: usize) -> Result<BTreeMap<String, String>, ParseError> {
+    if field.is_empty() {
+        return Ok(BTreeMap::new());
+    }
+    let fields = split_record(field);
+    if !fields.len().is_multiple_of(2) {
+        return Err(ParseError::new(line, format!("invalid crab attributes: {:?}", field)));
     }
+    Ok(fields.chunks(2).map(|pair| (pair[0].clone(), pair[1].clone())).collect())
 }
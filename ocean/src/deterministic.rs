@@ -0,0 +1,105 @@
+//! Pure strategies for the random choices made without a generator, for bit-identical runs in
+//! grading and golden-file tests (the `deterministic` feature).
+//!
+//! The variants of random functions that take no generator (see `crate::rng`) normally draw from
+//! the fixed-seed `crate::rand::rand32`. With this feature they call the current thread's
+//! `Strategies` instead, which are plain functions of their arguments and of the draw number:
+//! how many such choices the thread has made since its strategies were last set.
+//!   - `Diet::random_diet` (and so `Crab::breed`'s diets) calls `Strategies::diet`.
+//!   - `Pattern::cross` (and so `Crab::breed`'s patterns) calls `Strategies::pattern`.
+//!   - `ChanceEscape::escapes` (and so `Crab::hunt`'s escapes) calls `Strategies::escape`.
+//!   - Reefs with `TakePolicy::Random` (see `Reef::take_prey`) call `Strategies::pick`.
+//!
+//! The default strategies follow `roll`, an evenly spread sequence computed in integers, so they
+//! make each choice at its usual rate and give the same results on every platform. Call
+//! `set_strategies` at the start of a test to start from draw 0 whatever ran before it on the
+//! thread.
+//!
+//! Code that passes a generator is unaffected: `crate::rng::seeded` generators are already the
+//! same on every platform.
+
+use crate::diet::Diet;
+use crate::pattern::Pattern;
+use std::cell::Cell;
+
+/// The functions the random choices made without a generator fall back to. Each is given the
+/// draw number first.
+#[derive(Debug, Clone, Copy)]
+pub struct Strategies {
+    /** Chooses a crab's diet. By default at the natural rates (see `Diet::random_diet`). */
+    pub diet: fn(u64) -> Diet,
+    /** Chooses the pattern passed on by parents with two different patterns. By default either, evenly. */
+    pub pattern: fn(u64, Pattern, Pattern) -> Pattern,
+    /** Decides whether prey with the given chance of escaping gets away. By default at that rate. */
+    pub escape: fn(u64, f64) -> bool,
+    /** Chooses one of the given number (at least one) of candidates by index, modulo the number. By default evenly. */
+    pub pick: fn(u64, usize) -> usize,
+}
+
+impl Default for Strategies {
+    fn default() -> Strategies {
+        Strategies {
+            diet: |draw| Diet::from_percentile((roll(draw) * 100.0) as u32),
+            pattern: |draw, p1, p2| if roll(draw) < 0.5 { p1 } else { p2 },
+            escape: |draw, chance| roll(draw) < chance,
+            pick: |draw, count| (roll(draw) * count as f64) as usize,
+        }
+    }
+}
+
+thread_local!(
+    static STRATEGIES: Cell<Strategies> = Cell::new(Strategies::default());
+    static DRAWS: Cell<u64> = const { Cell::new(0) };
+);
+
+/**
+ * Returns the `draw`th number of the default strategies' sequence, between 0.0 and 1.0
+ * (exclusive). Consecutive draws are spread evenly over that range (a Weyl sequence).
+ */
+pub fn roll(draw: u64) -> f64 {
+    // The golden ratio in 64-bit fixed point, keeping the top 53 bits of each multiple.
+    (draw.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/**
+ * Returns the strategies in use on this thread.
+ */
+pub fn strategies() -> Strategies {
+    STRATEGIES.with(Cell::get)
+}
+
+/**
+ * Replaces the strategies in use on this thread, and starts counting draws from 0 again. Pass
+ * `Strategies::default()` to restart the default sequence.
+ */
+pub fn set_strategies(strategies: Strategies) {
+    STRATEGIES.with(|cell| cell.set(strategies));
+    DRAWS.with(|draws| draws.set(0));
+}
+
+// Returns the current draw number, counting it as taken.
+fn next_draw() -> u64 {
+    DRAWS.with(|draws| {
+        let draw = draws.get();
+        draws.set(draw.wrapping_add(1));
+        draw
+    })
+}
+
+// The current strategies' choices, each taking the next draw.
+
+pub(crate) fn diet() -> Diet {
+    (strategies().diet)(next_draw())
+}
+
+pub(crate) fn pattern(p1: Pattern, p2: Pattern) -> Pattern {
+    (strategies().pattern)(next_draw(), p1, p2)
+}
+
+pub(crate) fn escape(chance: f64) -> bool {
+    (strategies().escape)(next_draw(), chance)
+}
+
+pub(crate) fn pick(count: usize) -> usize {
+    (strategies().pick)(next_draw(), count) % count
+}
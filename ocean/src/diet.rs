@@ -1,24 +1,107 @@
-use crate::rand;
+use ::rand::Rng;
 
-/// A crab's diet: fish, shellfish, or plants (seaweed, algae, etc).
-#[derive(Debug, PartialEq, Copy, Clone)]
+/// A crab's diet: fish, shellfish, or plants (seaweed, algae, etc), or for the rarer
+/// scavengers and detritivores, carcasses and detritus.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Diet {
     Fish,
     Shellfish,
     Plants,
+    /** Eats the remains of dead animals, including crabs (see `crate::prey::Carcass`). */
+    Scavenger,
+    /** Eats decaying organic matter (see `crate::prey::Detritus`). */
+    Detritivore,
 }
 
 impl Diet {
+    /** Every diet. */
+    pub const ALL: [Diet; 5] = [Diet::Fish, Diet::Shellfish, Diet::Plants, Diet::Scavenger, Diet::Detritivore];
+
+    /**
+     * The diets of the food a crab with this diet eats without hesitation. This is the single
+     * place that decides what counts as a crab's own food; e.g. an omnivore would list every diet.
+     */
+    pub fn compatible_diets(&self) -> &'static [Diet] {
+        use Diet::*;
+        match self {
+            Fish => &[Fish],
+            Shellfish => &[Shellfish],
+            Plants => &[Plants],
+            Scavenger => &[Scavenger],
+            Detritivore => &[Detritivore],
+        }
+    }
+
+    /**
+     * Returns true if a crab with this diet eats prey of `prey_diet` without hesitation.
+     */
+    pub fn can_eat(&self, prey_diet: Diet) -> bool {
+        self.compatible_diets().contains(&prey_diet)
+    }
+
+    /**
+     * How willing a crab with this diet is to eat food of the `other` diet, from 0.0 (never)
+     * to 1.0 (food it `can_eat`). Fish and shellfish eaters will make do with each other's food
+     * more readily than with plants, and the other way around. Scavengers readily take fresh
+     * fish and shellfish, and detritivores plants.
+     */
+    pub fn affinity(&self, other: Diet) -> f32 {
+        use Diet::*;
+        match (self, other) {
+            _ if self.can_eat(other) => 1.0,
+            (Fish, Shellfish) | (Shellfish, Fish) => 0.5,
+            (Scavenger, Fish | Shellfish) | (Detritivore, Plants) => 0.5,
+            _ => 0.25,
+        }
+    }
+
     pub fn random_diet() -> Diet {
-        // This brings the names in Diet into scope, so we can write
-        // `Fish` rather than `Diet::Fish` (and so on) below.
+        #[cfg(feature = "deterministic")]
+        {
+            crate::deterministic::diet()
+        }
+        #[cfg(not(feature = "deterministic"))]
+        {
+            let r: u32 = crate::rand::rand32() % 100;
+            Diet::from_percentile(r)
+        }
+    }
+
+    /**
+     * Like `random_diet`, but draws from `rng` so that runs can be reproduced with a chosen seed.
+     */
+    pub fn random_diet_from<R: Rng + ?Sized>(rng: &mut R) -> Diet {
+        Diet::from_percentile(rng.gen_range(0..100))
+    }
+
+    // Maps 0..100 onto the diets at their natural rates: scavengers and detritivores are rare.
+    pub(crate) fn from_percentile(r: u32) -> Diet {
         use Diet::*;
-        let r: u32 = rand::rand32() % 3;
         match r {
-            0 => Fish,
-            1 => Shellfish,
-            2 => Plants,
-            _ => unreachable!(),
+            0..=29 => Fish,
+            30..=59 => Shellfish,
+            60..=89 => Plants,
+            90..=95 => Scavenger,
+            _ => Detritivore,
+        }
+    }
+}
+
+impl std::str::FromStr for Diet {
+    type Err = String;
+
+    /**
+     * Parses a diet from its variant name, as printed by `{:?}`.
+     */
+    fn from_str(s: &str) -> Result<Diet, String> {
+        use Diet::*;
+        match s {
+            "Fish" => Ok(Fish),
+            "Shellfish" => Ok(Shellfish),
+            "Plants" => Ok(Plants),
+            "Scavenger" => Ok(Scavenger),
+            "Detritivore" => Ok(Detritivore),
+            _ => Err(format!("unknown diet: {:?}", s)),
         }
     }
 }
@@ -0,0 +1,103 @@
+//! Diseases that spread between crabs and wear down their health (see `Crab::health`).
+//!
+//! A crab catches a disease when it is infected with `Beach::infect`, or from a sick crab on the
+//! same beach. Once a simulation step, every beach spreads and runs its diseases (see
+//! `Beach::spread_disease`):
+//!   1. Each crab that was already sick may infect each healthy crab on its beach, with the
+//!      disease's contagion, or its clan contagion if the two crabs share a clan.
+//!   2. Each crab that was already sick loses the disease's damage in health, then recovers with
+//!      the disease's recovery chance. Recovered crabs can catch a disease again.
+//!   3. Each healthy crab regains `HEALTH_REGEN` health, up to `MAX_HEALTH`.
+//!
+//! A crab's effective speed (see `Crab::effective_speed`) shrinks with its health, so sick crabs
+//! catch less prey and fall to predators more easily until they are cured and heal.
+
+/// The health of a crab in perfect shape, which every crab starts with.
+pub const MAX_HEALTH: u32 = 100;
+
+/// How much health a crab without a disease regains each simulation step.
+pub const HEALTH_REGEN: u32 = 5;
+
+/// A disease and how it spreads, harms, and ends.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Disease {
+    name: String,
+    contagion: f64,
+    clan_contagion: f64,
+    damage: u32,
+    recovery_chance: f64,
+}
+
+impl Disease {
+    /**
+     * Creates a disease that infects a tenth of the crabs a sick crab shares its beach with, and
+     * a quarter of those that share its clan, each step. It costs 10 health a step, and a crab
+     * recovers from it with chance 0.2 each step.
+     */
+    pub fn new(name: &str) -> Disease {
+        Disease { name: name.to_string(), contagion: 0.1, clan_contagion: 0.25, damage: 10, recovery_chance: 0.2 }
+    }
+
+    /**
+     * Sets the chance, clamped to [0, 1], that a sick crab infects each healthy crab on its beach
+     * each step.
+     */
+    pub fn with_contagion(mut self, chance: f64) -> Disease {
+        self.contagion = clamp_chance(chance);
+        self
+    }
+
+    /**
+     * Sets the chance, clamped to [0, 1], that a sick crab infects each healthy member of its
+     * clan each step, instead of the beach-wide contagion.
+     */
+    pub fn with_clan_contagion(mut self, chance: f64) -> Disease {
+        self.clan_contagion = clamp_chance(chance);
+        self
+    }
+
+    /**
+     * Sets how much health a sick crab loses each step.
+     */
+    pub fn with_damage(mut self, damage: u32) -> Disease {
+        self.damage = damage;
+        self
+    }
+
+    /**
+     * Sets the chance, clamped to [0, 1], that a sick crab recovers each step.
+     */
+    pub fn with_recovery_chance(mut self, chance: f64) -> Disease {
+        self.recovery_chance = clamp_chance(chance);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn contagion(&self) -> f64 {
+        self.contagion
+    }
+
+    pub fn clan_contagion(&self) -> f64 {
+        self.clan_contagion
+    }
+
+    pub fn damage(&self) -> u32 {
+        self.damage
+    }
+
+    pub fn recovery_chance(&self) -> f64 {
+        self.recovery_chance
+    }
+}
+
+// Chances that are not numbers count as 0.
+fn clamp_chance(chance: f64) -> f64 {
+    if chance.is_nan() {
+        0.0
+    } else {
+        chance.clamp(0.0, 1.0)
+    }
+}
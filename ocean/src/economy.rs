@@ -0,0 +1,221 @@
+//! An optional economy layer (the `economy` feature) where clans turn surplus prey into points.
+//!   - Prey deposited into a clan's stockpile is converted into points.
+//!   - Points decay by a fixed percentage every time `Economy::decay` is called.
+//!   - Points can be spent on territory claims or training sessions.
+
+use crate::error::OceanError;
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::prey::Prey;
+use std::collections::HashMap;
+
+/// Points credited for each prey deposited into a stockpile.
+pub const POINTS_PER_PREY: u32 = 10;
+/// Cost in points of claiming a reef as clan territory.
+pub const TERRITORY_CLAIM_COST: u32 = 50;
+/// Cost in points of a clan training session.
+pub const TRAINING_SESSION_COST: u32 = 20;
+
+/// Something a clan can buy with its points.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Purchase {
+    /** A claim on the named reef. */
+    TerritoryClaim(String),
+    TrainingSession,
+}
+
+impl Purchase {
+    pub fn cost(&self) -> u32 {
+        match self {
+            Purchase::TerritoryClaim(_) => TERRITORY_CLAIM_COST,
+            Purchase::TrainingSession => TRAINING_SESSION_COST,
+        }
+    }
+}
+
+/// A single change to a clan's balance.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LedgerEntry {
+    Deposit { points: u32 },
+    Decay { points: u32 },
+    Purchase { purchase: Purchase, cost: u32 },
+}
+
+/// A clan's point balance and the history of how it got there.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Ledger {
+    balance: u32,
+    entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn balance(&self) -> u32 {
+        self.balance
+    }
+
+    pub fn entries(&self) -> std::slice::Iter<'_, LedgerEntry> {
+        self.entries.iter()
+    }
+}
+
+#[derive(Debug)]
+pub struct Economy {
+    decay_percent: u32,
+    ledgers: HashMap<String, Ledger>,
+}
+
+impl Economy {
+    /**
+     * Creates an economy where balances lose `decay_percent` percent (at most 100) on every decay step.
+     */
+    pub fn new(decay_percent: u32) -> Economy {
+        Economy {
+            decay_percent: decay_percent.min(100),
+            ledgers: HashMap::new(),
+        }
+    }
+
+    pub fn decay_percent(&self) -> u32 {
+        self.decay_percent
+    }
+
+    /**
+     * Returns the ledger of the given clan, or None if it has never deposited anything.
+     */
+    pub fn ledger(&self, clan_id: &str) -> Option<&Ledger> {
+        self.ledgers.get(clan_id)
+    }
+
+    pub fn balance(&self, clan_id: &str) -> u32 {
+        self.ledger(clan_id).map_or(0, Ledger::balance)
+    }
+
+    /**
+     * Deposits prey into the clan's stockpile, taking ownership of it.
+     * Returns the number of points credited. Balances stop at `u32::MAX`, so a deposit that
+     * would pass it is credited only up to it.
+     */
+    pub fn deposit(&mut self, clan_id: &str, prey: Vec<Box<dyn Prey>>) -> u32 {
+        let earned = u32::try_from(prey.len()).unwrap_or(u32::MAX).saturating_mul(POINTS_PER_PREY);
+        let ledger = self.ledgers.entry(clan_id.to_string()).or_default();
+        let points = earned.min(u32::MAX - ledger.balance);
+        ledger.balance += points;
+        ledger.entries.push(LedgerEntry::Deposit { points });
+        points
+    }
+
+    /**
+     * Applies one step of decay to every balance. Any non-zero loss is rounded up,
+     * so idle balances eventually reach zero.
+     */
+    pub fn decay(&mut self) {
+        for ledger in self.ledgers.values_mut() {
+            // Multiplied in u64 so large balances don't overflow; the loss is at most the balance.
+            let points = (u64::from(ledger.balance) * u64::from(self.decay_percent)).div_ceil(100) as u32;
+            if points > 0 {
+                ledger.balance -= points;
+                ledger.entries.push(LedgerEntry::Decay { points });
+            }
+        }
+    }
+
+    /**
+     * Spends the clan's points on `purchase`.
+     * Returns `OceanError::InsufficientPoints` if the clan cannot afford it, leaving the balance unchanged.
+     */
+    pub fn purchase(&mut self, clan_id: &str, purchase: Purchase) -> Result<(), OceanError> {
+        let cost = purchase.cost();
+        let balance = self.balance(clan_id);
+        if balance < cost {
+            return Err(OceanError::InsufficientPoints { clan: clan_id.to_string(), balance, cost });
+        }
+        let ledger = self.ledgers.get_mut(clan_id).unwrap();
+        ledger.balance -= cost;
+        ledger.entries.push(LedgerEntry::Purchase { purchase, cost });
+        Ok(())
+    }
+
+    /**
+     * Saves the decay rate and every ledger in the `crate::persist` text format.
+     */
+    pub fn save(&self) -> String {
+        let mut out = join_record(&["economy".to_string(), self.decay_percent.to_string()]);
+        out.push('\n');
+
+        let mut clan_ids: Vec<&String> = self.ledgers.keys().collect();
+        clan_ids.sort();
+        for clan_id in clan_ids {
+            let ledger = &self.ledgers[clan_id];
+            out.push_str(&join_record(&["ledger".to_string(), clan_id.clone(), ledger.balance.to_string()]));
+            out.push('\n');
+            for entry in &ledger.entries {
+                let mut fields = vec!["entry".to_string(), clan_id.clone()];
+                match entry {
+                    LedgerEntry::Deposit { points } => fields.extend(["deposit".to_string(), points.to_string()]),
+                    LedgerEntry::Decay { points } => fields.extend(["decay".to_string(), points.to_string()]),
+                    LedgerEntry::Purchase { purchase, cost } => {
+                        fields.extend(["purchase".to_string(), cost.to_string()]);
+                        match purchase {
+                            Purchase::TerritoryClaim(reef) => fields.extend(["territory".to_string(), reef.clone()]),
+                            Purchase::TrainingSession => fields.push("training".to_string()),
+                        }
+                    }
+                }
+                out.push_str(&join_record(&fields));
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /**
+     * Loads an economy previously written by `save`.
+     */
+    pub fn load(text: &str) -> Result<Economy, ParseError> {
+        let mut economy: Option<Economy> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            if line.is_empty() {
+                continue;
+            }
+            let fields = split_record(line);
+            let field = |n: usize| fields.get(n).map(String::as_str).unwrap_or("");
+
+            if fields[0] == "economy" {
+                economy = Some(Economy::new(parse_field(field(1), "decay percent", line_no)?));
+                continue;
+            }
+            let economy = economy
+                .as_mut()
+                .ok_or_else(|| ParseError::new(line_no, "missing economy header".to_string()))?;
+            match field(0) {
+                "ledger" => {
+                    let ledger = economy.ledgers.entry(field(1).to_string()).or_default();
+                    ledger.balance = parse_field(field(2), "balance", line_no)?;
+                }
+                "entry" => {
+                    let points = parse_field(field(3), "points", line_no)?;
+                    let entry = match (field(2), field(4)) {
+                        ("deposit", _) => LedgerEntry::Deposit { points },
+                        ("decay", _) => LedgerEntry::Decay { points },
+                        ("purchase", "territory") => LedgerEntry::Purchase {
+                            purchase: Purchase::TerritoryClaim(field(5).to_string()),
+                            cost: points,
+                        },
+                        ("purchase", "training") => LedgerEntry::Purchase {
+                            purchase: Purchase::TrainingSession,
+                            cost: points,
+                        },
+                        (kind, _) => return Err(ParseError::new(line_no, format!("unknown ledger entry {:?}", kind))),
+                    };
+                    let ledger = economy
+                        .ledgers
+                        .get_mut(field(1))
+                        .ok_or_else(|| ParseError::new(line_no, format!("entry for unknown ledger {:?}", field(1))))?;
+                    ledger.entries.push(entry);
+                }
+                other => return Err(ParseError::new(line_no, format!("unexpected record {:?}", other))),
+            }
+        }
+        economy.ok_or_else(|| ParseError::new(1, "missing economy header".to_string()))
+    }
+}
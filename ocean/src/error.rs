@@ -0,0 +1,142 @@
+//! The error type returned by every fallible operation on ocean state.
+//!
+//! Parsing a single value (`Diet`, `Pattern`, `TakePolicy` and `Color::from_hex`) still reports
+//! a plain message, which loaders wrap in a `ParseError` with the line it was found on.
+
+use crate::capabilities::Capability;
+use crate::persist::ParseError;
+use std::fmt;
+
+/// Something that went wrong while changing, saving, or loading the ocean.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OceanError {
+    /** No crab with this name lives on the beach. */
+    CrabNotFound(String),
+    /** No crab with this id (see `Crab::id`) lives on the beach. */
+    CrabIdNotFound(u64),
+    /** A crab index was past the end of a beach with `len` crabs. */
+    CrabIndexOutOfBounds { index: usize, len: usize },
+    /** No clan has this id. */
+    ClanNotFound(String),
+    /** The clan already has its maximum number of members. */
+    ClanFull { clan: String, max_members: usize },
+    /** The clan has no members, e.g. so it cannot compete. */
+    ClanEmpty(String),
+    /** A computation overflowed, such as the named quantity growing too large to count. */
+    Overflow(String),
+    /** One of the clan's recruitment rules turned the member away, for the given reason. */
+    Rejected(String),
+    /** No beach has this index or name. */
+    BeachNotFound(String),
+    /** No reef has this id or name. */
+    ReefNotFound(String),
+    /** The beach has no zone with this name. */
+    ZoneNotFound(String),
+    /** The crab is a juvenile (see `Crab::stage`), so it cannot yet do `action`, e.g. "breed". */
+    TooYoung { crab: String, action: &'static str },
+    /** The cookbook has no recipe with this name. */
+    RecipeNotFound(String),
+    /** A name that must be unique is already taken by another `kind` (e.g. "reef" or "recipe"). */
+    DuplicateName { kind: &'static str, name: String },
+    /** The crab's larder does not hold every ingredient of the recipe. */
+    MissingIngredients { crab: String, recipe: String },
+    /** The clan cannot afford a purchase. */
+    InsufficientPoints { clan: String, balance: u32, cost: u32 },
+    /** The subsystem was not compiled into this build. */
+    FeatureDisabled(Capability),
+    /** An argument was out of range or otherwise invalid. */
+    InvalidArgument(String),
+    /** A prey of this kind has state that cannot be saved. */
+    NotSaveable(String),
+    /** Saved state could not be loaded. */
+    Parse(ParseError),
+    /** Reading or writing a file failed, with the operating system's message. */
+    Io(String),
+    /** The crabs are more closely related than their beach lets breed, as `relation` describes (see `Beach::set_max_breeding_relatedness`). */
+    TooCloselyRelated { crab1: String, crab2: String, relation: String },
+    /** The crab bred recently, and must wait this many more steps to breed again (see `Beach::set_breeding_cooldown`). */
+    BreedingCooldown { crab: String, steps: u32 },
+    /** The crab has starved to death, and cannot breed (see `Beach::set_starvation_limit`). */
+    CrabDead(String),
+    /** The crab is hibernating, and cannot breed (see `Crab::hibernate`). */
+    CrabHibernating(String),
+    /** Crabs cannot breed on the beach, in the current weather (see `Beach::set_breeding_allowed`) or because it is full (see `Beach::set_breeding_limit`). */
+    BreedingUnavailable,
+    /** A `SharedSimulation`'s thread has stopped, because building its simulation or a closure panicked. */
+    SimulationStopped,
+    /** The action on this line of a replay drew differently than when it was recorded. */
+    ReplayDiverged { line: usize },
+}
+
+impl fmt::Display for OceanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OceanError::CrabNotFound(name) => write!(f, "No crab named {} lives on this beach", name),
+            OceanError::CrabIdNotFound(id) => write!(f, "No crab with id {} lives on this beach", id),
+            OceanError::CrabIndexOutOfBounds { index, len } => {
+                write!(f, "Index out of bounds: the beach has {} crabs but the index is {}", len, index)
+            }
+            OceanError::ClanNotFound(clan) => write!(f, "Clan {} does not exist", clan),
+            OceanError::ClanFull { clan, max_members } => {
+                write!(f, "Clan {} is full ({} members)", clan, max_members)
+            }
+            OceanError::ClanEmpty(clan) => write!(f, "Clan {} has no members", clan),
+            OceanError::Overflow(what) => write!(f, "The {} is too large to compute", what),
+            OceanError::Rejected(reason) => write!(f, "{}", reason),
+            OceanError::BeachNotFound(beach) => write!(f, "No beach {}", beach),
+            OceanError::ReefNotFound(reef) => write!(f, "No reef {}", reef),
+            OceanError::ZoneNotFound(zone) => write!(f, "No zone {}", zone),
+            OceanError::TooYoung { crab, action } => write!(f, "{} is a juvenile and too young to {}", crab, action),
+            OceanError::RecipeNotFound(name) => write!(f, "The cookbook has no recipe called {}", name),
+            OceanError::DuplicateName { kind, name } => write!(f, "A {} named {} already exists", kind, name),
+            OceanError::MissingIngredients { crab, recipe } => {
+                write!(f, "{} is missing ingredients for {}", crab, recipe)
+            }
+            OceanError::InsufficientPoints { clan, balance, cost } => {
+                write!(f, "Clan {} has {} points but needs {}", clan, balance, cost)
+            }
+            OceanError::FeatureDisabled(capability) => write!(
+                f,
+                "this build of ocean does not include the {} subsystem; rebuild with `--features {}`",
+                capability,
+                capability.feature()
+            ),
+            OceanError::InvalidArgument(message) => write!(f, "{}", message),
+            OceanError::NotSaveable(kind) => write!(f, "{} prey cannot be saved", kind),
+            OceanError::Parse(error) => write!(f, "{}", error),
+            OceanError::Io(message) => write!(f, "{}", message),
+            OceanError::TooCloselyRelated { crab1, crab2, relation } => {
+                write!(f, "{} and {} are too closely related to breed ({})", crab1, crab2, relation)
+            }
+            OceanError::BreedingCooldown { crab, steps } => {
+                write!(f, "{} bred recently and cannot breed for another {} steps", crab, steps)
+            }
+            OceanError::CrabDead(crab) => write!(f, "{} has starved to death", crab),
+            OceanError::CrabHibernating(crab) => write!(f, "{} is hibernating", crab),
+            OceanError::BreedingUnavailable => write!(f, "Crabs cannot breed on this beach right now"),
+            OceanError::SimulationStopped => write!(f, "The simulation's thread has stopped"),
+            OceanError::ReplayDiverged { line } => write!(f, "The replay diverged from its recording at line {}", line),
+        }
+    }
+}
+
+impl std::error::Error for OceanError {}
+
+impl From<ParseError> for OceanError {
+    fn from(error: ParseError) -> OceanError {
+        OceanError::Parse(error)
+    }
+}
+
+impl From<std::io::Error> for OceanError {
+    fn from(error: std::io::Error) -> OceanError {
+        OceanError::Io(error.to_string())
+    }
+}
+
+/// Lets code that reports errors as strings use `?` on ocean operations.
+impl From<OceanError> for String {
+    fn from(error: OceanError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,209 @@
+//! Pluggable escape behaviour for prey.
+//!   - An `EscapeModel` decides whether a prey escapes a crab, given the prey's `PreyTraits`.
+//!   - `ChanceEscape` rolls against a base probability adjusted for speed and camouflage, and
+//!     lowered by the crab's escape resistance and by how well it blends into its background.
+//!   - `ScriptedEscape` replays fixed outcomes, for tests.
+//!
+//! `ModeledPrey` is a prey whose `try_escape` delegates to a model, so new kinds of prey can be
+//! described without writing a `Prey` implementation.
+
+use crate::crab::Crab;
+use crate::diet::Diet;
+use crate::prey::{Prey, PreyTags, Size};
+use ::rand::{Rng, RngCore};
+
+/// The properties of a prey that an `EscapeModel` takes into account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PreyTraits {
+    pub speed: u32,
+    /** How well the prey blends into its surroundings, from 0.0 (not at all) to 1.0. */
+    pub camouflage: f64,
+    pub size: Size,
+}
+
+pub trait EscapeModel: Send {
+    /**
+     * Returns true if a prey with the given traits escapes `crab` on this attempt.
+     */
+    fn escapes(&mut self, traits: &PreyTraits, crab: &Crab) -> bool;
+
+    /**
+     * Returns a copy of this model, with its state, in a new box, so that `ModeledPrey` can be
+     * cloned.
+     */
+    fn clone_box(&self) -> Box<dyn EscapeModel>;
+
+    /**
+     * Like `escapes`, but any randomness must come from `rng`. The default just calls `escapes`.
+     */
+    fn escapes_with(&mut self, traits: &PreyTraits, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        let _ = rng;
+        self.escapes(traits, crab)
+    }
+}
+
+/// Escapes at random, using the crate's deterministic random numbers.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChanceEscape {
+    /** The chance of escaping a crab exactly as fast as the prey, with no camouflage. */
+    pub base: f64,
+    /** Added for every unit of speed the prey has over the crab (subtracted if slower). */
+    pub per_speed: f64,
+    /** Added in proportion to the prey's camouflage. */
+    pub per_camouflage: f64,
+    /** Added for every size class the prey is above `Size::Small`. */
+    pub per_size: f64,
+}
+
+impl ChanceEscape {
+    pub fn new(base: f64, per_speed: f64, per_camouflage: f64, per_size: f64) -> ChanceEscape {
+        ChanceEscape {
+            base,
+            per_speed,
+            per_camouflage,
+            per_size,
+        }
+    }
+
+    /**
+     * Returns the chance, between 0.0 and 1.0, that a prey with the given traits escapes `crab`.
+     * The crab's escape resistance (see `Crab::escape_resistance`) takes away that share of it,
+     * and a crab that blends into its background (see `Crab::color_match`) is noticed later, up
+     * to halving what is left. A chance that works out to NaN, e.g. from a NaN camouflage,
+     * counts as 0.0.
+     */
+    pub fn probability(&self, traits: &PreyTraits, crab: &Crab) -> f64 {
+        let speed_difference = traits.speed as f64 - crab.effective_speed() as f64;
+        let size_steps = traits.size as u32 as f64;
+        let p = self.base
+            + self.per_speed * speed_difference
+            + self.per_camouflage * traits.camouflage
+            + self.per_size * size_steps;
+        let p = if p.is_nan() { 0.0 } else { p.clamp(0.0, 1.0) };
+        let chance = p * (1.0 - crab.escape_resistance()) * (1.0 - crab.color_match() / 2.0);
+        if chance.is_nan() {
+            0.0
+        } else {
+            chance.clamp(0.0, 1.0)
+        }
+    }
+}
+
+impl EscapeModel for ChanceEscape {
+    fn clone_box(&self) -> Box<dyn EscapeModel> {
+        Box::new(self.clone())
+    }
+
+    fn escapes(&mut self, traits: &PreyTraits, crab: &Crab) -> bool {
+        #[cfg(feature = "deterministic")]
+        {
+            crate::deterministic::escape(self.probability(traits, crab))
+        }
+        #[cfg(not(feature = "deterministic"))]
+        {
+            let roll = crate::rand::rand32() as f64 / (u32::MAX as f64 + 1.0);
+            roll < self.probability(traits, crab)
+        }
+    }
+
+    fn escapes_with(&mut self, traits: &PreyTraits, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        rng.gen_bool(self.probability(traits, crab))
+    }
+}
+
+/// Replays a fixed sequence of outcomes, starting over when it runs out.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScriptedEscape {
+    outcomes: Vec<bool>,
+    next: usize,
+}
+
+impl ScriptedEscape {
+    /**
+     * Creates a model that returns `outcomes` in order, over and over.
+     * With no outcomes the prey never escapes.
+     */
+    pub fn new(outcomes: Vec<bool>) -> ScriptedEscape {
+        ScriptedEscape { outcomes, next: 0 }
+    }
+}
+
+impl EscapeModel for ScriptedEscape {
+    fn clone_box(&self) -> Box<dyn EscapeModel> {
+        Box::new(self.clone())
+    }
+
+    fn escapes(&mut self, _traits: &PreyTraits, _crab: &Crab) -> bool {
+        if self.outcomes.is_empty() {
+            return false;
+        }
+        let outcome = self.outcomes[self.next];
+        self.next = (self.next + 1) % self.outcomes.len();
+        outcome
+    }
+}
+
+/// A prey whose escapes are decided by an `EscapeModel`.
+pub struct ModeledPrey {
+    kind: &'static str,
+    diet: Diet,
+    traits: PreyTraits,
+    model: Box<dyn EscapeModel>,
+    tags: PreyTags,
+}
+
+impl ModeledPrey {
+    pub fn new(kind: &'static str, diet: Diet, traits: PreyTraits, model: Box<dyn EscapeModel>) -> ModeledPrey {
+        ModeledPrey {
+            kind,
+            diet,
+            traits,
+            model,
+            tags: PreyTags::default(),
+        }
+    }
+
+    pub fn traits(&self) -> &PreyTraits {
+        &self.traits
+    }
+}
+
+impl Prey for ModeledPrey {
+    fn diet(&self) -> Diet {
+        self.diet
+    }
+
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(ModeledPrey {
+            kind: self.kind,
+            diet: self.diet,
+            traits: self.traits.clone(),
+            model: self.model.clone_box(),
+            tags: self.tags.clone(),
+        })
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
+    fn try_escape(&mut self, crab: &Crab) -> bool {
+        self.model.escapes(&self.traits, crab)
+    }
+
+    fn try_escape_with(&mut self, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        self.model.escapes_with(&self.traits, crab, rng)
+    }
+
+    fn size(&self) -> Size {
+        self.traits.size
+    }
+
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+}
@@ -0,0 +1,96 @@
+//! Events describing changes to the ocean, for UIs and loggers that want to react to them
+//! without polling.
+//!
+//! Beaches, crabs, clan systems, and reefs queue an event at each change they make. A
+//! `Simulation` collects the queued events and hands them to its listeners (see
+//! `Simulation::subscribe`); code that does not use a simulation can collect them itself with
+//! `Beach::drain_events` and `Reef::drain_events`. Each queue holds at most `MAX_QUEUED_EVENTS`,
+//! so code that never collects events doesn't grow them without bound.
+
+use crate::registry::ReefId;
+
+/// The most events a beach, crab, clan system, or reef keeps queued. When a queue grows past
+/// this, its oldest events are dropped until it is half full.
+pub const MAX_QUEUED_EVENTS: usize = 4096;
+
+/// Something that happened in the ocean.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OceanEvent {
+    /** A crab was bred on a beach. */
+    CrabBorn { name: String, parents: (String, String) },
+    /** A wild crab arrived on a beach (see `crate::population`). */
+    CrabSpawned { name: String },
+    /** A crab was buried (see `Beach::bury_crab`). */
+    CrabDied { name: String },
+    /** A crab moved from the beach at index `from` in its ocean to the one at index `to`. */
+    CrabMigrated { name: String, from: usize, to: usize },
+    /** A crab was caught by a predator of the given kind (see `Predator::kind`). */
+    CrabEaten { crab: String, predator: String },
+    /** A crab starved to death (see `Beach::set_starvation_limit`). */
+    CrabStarved { crab: String },
+    /** A scavenger ate the carcass of the named crab (see `Beach::scavenge`). */
+    CarcassEaten { crab: String, carcass: String },
+    /** A crab began molting (see `Crab::is_molting`). */
+    MoltStarted { crab: String },
+    /** A crab finished molting, coming out with the given speed. */
+    MoltEnded { crab: String, speed: u32 },
+    /** A crab caught the named disease (see `crate::disease`). */
+    CrabInfected { crab: String, disease: String },
+    /** A crab recovered from the named disease. */
+    CrabRecovered { crab: String, disease: String },
+    /** A crab caught and ate a prey of the given kind (see `Prey::kind`). */
+    PreyCaught { crab: String, prey: String },
+    /** A prey of the given kind escaped a crab and was released back into its reef. */
+    PreyEscaped { crab: String, prey: String },
+    /** A crab joined a clan. */
+    ClanJoined { clan: String, crab: String },
+    /** A crab left its clan, or was removed from it. */
+    ClanLeft { clan: String, crab: String },
+    /** A clan won a competition against another clan. */
+    ClanWon { winner: String, loser: String },
+    /** A competition between two clans ended in a draw. */
+    ClanDrew { clans: (String, String) },
+    /** A scheduled match between two clans could not be played, for the given reason (see `Simulation::schedule_match`). */
+    MatchCancelled { clans: (String, String), reason: String },
+    /** A clan raided a reef held by another clan, capturing it or being driven off (see `Beach::raid`). */
+    ReefRaided { attacker: String, defender: String, reef: ReefId, captured: bool },
+    /** New prey grew in a reef during a tick. */
+    PreySpawned { reef: Option<String>, count: usize },
+    /** Prey in a reef reached the end of their lifespan during a tick. */
+    PreyDied { reef: Option<String>, count: usize },
+    /** The last prey was taken out of a reef. */
+    ReefDepleted { reef: Option<String> },
+    /** A `Simulation` finished the step with the given tick. */
+    StepFinished { tick: u64 },
+}
+
+/// Receives every event published by a `Simulation` it is subscribed to.
+pub trait EventListener {
+    fn on_event(&mut self, event: &OceanEvent);
+}
+
+impl<F> EventListener for F
+where
+    F: FnMut(&OceanEvent),
+{
+    fn on_event(&mut self, event: &OceanEvent) {
+        self(event)
+    }
+}
+
+// Queues `event`, dropping the oldest queued events if there are too many (see `bound`).
+pub(crate) fn queue(queue: &mut Vec<OceanEvent>, event: OceanEvent) {
+    queue.push(event);
+    bound(queue);
+}
+
+// Drops the oldest events in `queue` once it holds more than `MAX_QUEUED_EVENTS`, leaving it half
+// full, and returns how many were dropped. Dropping half at a time keeps queueing cheap.
+pub(crate) fn bound(queue: &mut Vec<OceanEvent>) -> usize {
+    if queue.len() <= MAX_QUEUED_EVENTS {
+        return 0;
+    }
+    let dropped = queue.len() - MAX_QUEUED_EVENTS / 2;
+    queue.drain(..dropped);
+    dropped
+}
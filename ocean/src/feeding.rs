@@ -0,0 +1,30 @@
+//! Feeding rounds, in which every crab on a beach hunts once (see `Beach::feeding_round`).
+//!
+//! Crabs that share reefs compete for the same prey, so the order they hunt in decides who eats.
+//! A beach's `FeedingOrder` sets that order, and each round returns a `FeedingOutcome` per crab.
+
+/// The order in which crabs hunt in a feeding round. Crabs that tie keep their beach order.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub enum FeedingOrder {
+    /** Crabs hunt in the order they are on the beach. */
+    #[default]
+    BeachOrder,
+    /** The fastest crabs hunt first. */
+    FastestFirst,
+    /** The slowest crabs hunt first. */
+    SlowestFirst,
+    /** The crabs with the least energy hunt first. */
+    HungriestFirst,
+}
+
+/// How one crab fared in a feeding round.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FeedingOutcome {
+    /** The crab's index on the beach. */
+    pub index: usize,
+    pub crab: String,
+    /** Whether the crab caught anything. */
+    pub caught: bool,
+    /** The energy the crab gained from what it caught. */
+    pub energy_gained: u32,
+}
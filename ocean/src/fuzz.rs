@@ -0,0 +1,361 @@
+//! Drivers for the cargo-fuzz targets in `fuzz/` (the `fuzzing` feature).
+//!
+//! Each driver reads its input as a sequence of operations, one byte choosing the operation and
+//! the following bytes its arguments, and applies them through the public API with whatever
+//! indices, names and numbers the input gives. Errors are expected and ignored; a panic, whether
+//! from the crate or from one of the invariants checked after every operation, is a bug.
+//!
+//! ```text
+//! cargo fuzz run beach_ops
+//! ```
+//!
+//! The panicking conveniences that the assignment's API requires (`Beach::get_crab`,
+//! `Beach::breed_crabs`, `Beach::bury_crab`, `Beach::add_member_to_clan`) are driven through their
+//! `try_` counterparts instead.
+
+use crate::beach::Beach;
+use crate::clans::{ClanSystem, MinSpeed};
+use crate::color::Color;
+use crate::config::SimConfig;
+use crate::cookbook::{Cookbook, CookbookFormat, Recipe};
+use crate::crab::Crab;
+use crate::diet::Diet;
+use crate::disease::Disease;
+use crate::ocean::Ocean;
+use crate::persist::split_record;
+use crate::prey::{Algae, Clam, Minnow, Shrimp};
+use crate::reef::Reef;
+use crate::rng::seeded;
+use crate::simulation::Simulation;
+use crate::zone::Zone;
+
+// Names and clan ids come from small pools so that operations keep running into each other.
+const NAMES: [&str; 6] = ["Ferris", "Crusty", "Pinchy", "Sandy", "", "Krabbé"];
+const CLANS: [&str; 4] = ["reds", "blues", "greens", ""];
+const KINDS: [&str; 4] = ["Minnow", "Shrimp", "Clam", "Algae"];
+const ZONES: [&str; 3] = ["rocky", "sandy", "tidepool"];
+
+// Reads operations and their arguments from the fuzzer's bytes. Arguments past the end of the
+// input read as zero, so every input is a valid program.
+struct Input<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Input<'a> {
+    fn new(data: &'a [u8]) -> Input<'a> {
+        Input { data }
+    }
+
+    fn op(&mut self) -> Option<u8> {
+        let (&first, rest) = self.data.split_first()?;
+        self.data = rest;
+        Some(first)
+    }
+
+    fn byte(&mut self) -> u8 {
+        self.op().unwrap_or(0)
+    }
+
+    fn index(&mut self) -> usize {
+        self.byte() as usize
+    }
+
+    fn flag(&mut self) -> bool {
+        self.byte() % 2 == 1
+    }
+
+    // Mostly small numbers, with the extremes thrown in to find overflows.
+    fn number(&mut self) -> u32 {
+        match self.byte() {
+            255 => u32::MAX,
+            254 => u32::MAX - 1,
+            byte => byte as u32,
+        }
+    }
+
+    fn name(&mut self) -> String {
+        NAMES[self.index() % NAMES.len()].to_string()
+    }
+
+    fn clan(&mut self) -> String {
+        CLANS[self.index() % CLANS.len()].to_string()
+    }
+
+    fn diet(&mut self) -> Diet {
+        [Diet::Fish, Diet::Shellfish, Diet::Plants, Diet::Scavenger, Diet::Detritivore][self.index() % 5]
+    }
+
+    fn crab(&mut self) -> Crab {
+        let name = self.name();
+        let speed = self.number();
+        let color = Color::new(self.byte(), self.byte(), self.byte());
+        Crab::new(name, speed, color, self.diet())
+    }
+
+    fn reef(&mut self) -> Reef {
+        let mut reef = Reef::new();
+        if self.flag() {
+            reef.set_capacity(Some(self.index()));
+        }
+        for _ in 0..self.byte() % 8 {
+            match self.byte() % 4 {
+                0 => reef.add_prey(Box::new(Minnow::new(self.number()))),
+                1 => reef.add_prey(Box::new(Shrimp::new(self.number()))),
+                2 => reef.add_prey(Box::new(Clam::new())),
+                _ => reef.add_prey(Box::new(Algae::new())),
+            };
+        }
+        reef
+    }
+
+    fn text(&mut self) -> String {
+        let len = self.index();
+        let take = len.min(self.data.len());
+        let (text, rest) = self.data.split_at(take);
+        self.data = rest;
+        String::from_utf8_lossy(text).into_owned()
+    }
+}
+
+/**
+ * Drives a beach, its clans, its zones, its diseases, and its undo history.
+ */
+pub fn beach_ops(data: &[u8]) {
+    let mut input = Input::new(data);
+    let mut beach = Beach::new();
+    let mut rng = seeded(0);
+    while let Some(op) = input.op() {
+        match op % 19 {
+            0 => beach.add_crab(input.crab()),
+            1 => {
+                let _ = beach.try_breed_crabs(input.index(), input.index(), input.name());
+            }
+            2 => {
+                let _ = beach.breed_crabs_genetic(input.index(), input.index(), input.name(), &mut rng);
+            }
+            3 => {
+                let _ = beach.try_bury_crab(input.index());
+            }
+            4 => beach.get_clan_system_mut().create_clan(input.clan()),
+            5 => {
+                let _ = beach.try_add_member_to_clan(&input.clan(), &input.name());
+            }
+            6 => {
+                let _ = beach.get_winner_clan(&input.clan(), &input.clan());
+            }
+            7 => {
+                beach.undo();
+            }
+            8 => {
+                beach.redo();
+            }
+            9 => {
+                let _ = beach.try_get_crab(input.index());
+            }
+            10 => beach.set_breeding_allowed(input.flag()),
+            11 => beach.set_breeding_limit(input.flag().then(|| input.index())),
+            12 => {
+                beach.sample_crabs(&mut rng, input.index());
+            }
+            13 => {
+                let _ = Beach::load(&beach.save());
+            }
+            14 => beach.clear_history(),
+            15 => {
+                let zone = Zone::new(ZONES[input.index() % ZONES.len()]).with_hunt_chance(input.byte() as f64 / 128.0);
+                let _ = beach.add_zone(zone.with_breeding(input.flag()));
+            }
+            16 => {
+                let _ = beach.assign_zone(input.index(), ZONES[input.index() % ZONES.len()]);
+            }
+            17 => {
+                let disease = Disease::new("shell rot").with_contagion(input.byte() as f64 / 255.0).with_damage(input.number());
+                let _ = beach.infect(input.byte() as u64, disease);
+                beach.spread_disease(&mut rng);
+            }
+            _ => {
+                let (a, b) = (input.byte() as u64, input.byte() as u64);
+                beach.relatedness(a, b);
+                beach.ancestors(a);
+                beach.descendants(b);
+            }
+        }
+        check_beach(&beach);
+    }
+}
+
+/**
+ * Drives a clan system on its own, without a beach keeping its members in line.
+ */
+pub fn clan_ops(data: &[u8]) {
+    let mut input = Input::new(data);
+    let mut clans: ClanSystem = ClanSystem::new();
+    while let Some(op) = input.op() {
+        match op % 10 {
+            0 => clans.create_clan(input.clan()),
+            1 => clans.create_clan_with_limit(input.clan(), input.index() % 4),
+            2 => {
+                let crab = input.crab();
+                let _ = clans.add_member(&input.clan(), crab.name().to_string(), crab);
+            }
+            3 => {
+                clans.remove_member(&input.name());
+            }
+            4 => clans.add_recruitment_rule(&input.clan(), MinSpeed(input.number())),
+            5 => {
+                let (a, b) = (input.clan(), input.clan());
+                let winner = if input.flag() { Some(a.clone()) } else { None };
+                clans.record_result(&a, &b, winner.as_deref());
+            }
+            6 => clans.start_new_season(),
+            7 => {
+                let _ = ClanSystem::load(&clans.save());
+            }
+            8 => {
+                clans.get_largest_clan_id();
+                clans.leaderboard();
+            }
+            _ => {
+                clans.get_clan_average_speed(&input.clan());
+            }
+        }
+        check_clans(&clans);
+    }
+}
+
+/**
+ * Drives a single crab: breeding, hunting, cooking, rating, and saving.
+ */
+pub fn crab_ops(data: &[u8]) {
+    let mut input = Input::new(data);
+    let mut crab = input.crab();
+    let mut rng = seeded(0);
+    while let Some(op) = input.op() {
+        match op % 8 {
+            0 => {
+                let mate = input.crab();
+                crab = match input.byte() % 4 {
+                    0 => Crab::breed_with_rng(input.name(), &crab, &mate, &mut rng),
+                    1 => Crab::breed_weighted(input.name(), &crab, &mate, input.byte() as f64 / 255.0, &mut rng),
+                    2 => Crab::breed_mendelian(input.name(), &crab, &mate, &mut rng),
+                    _ => Crab::breed_genetic(input.name(), &crab, &mate, &mut rng),
+                };
+            }
+            1 => {
+                let mut reef = input.reef();
+                let before = reef.population();
+                let caught = crab.hunt_in(&mut reef);
+                assert!(reef.population() + caught as usize <= before, "a hunt created prey");
+            }
+            2 => {
+                let mut recipe = Recipe::new(input.name(), input.diet());
+                for _ in 0..input.byte() % 3 {
+                    recipe = recipe.with_ingredient(KINDS[input.index() % KINDS.len()], input.number());
+                }
+                let _ = crab.cook(&recipe);
+            }
+            3 => {
+                let _ = crab.rate_recipe(&input.name(), input.byte());
+            }
+            4 => crab.set_diet_threshold(input.byte() as f32 / 128.0),
+            5 => {
+                crab.grow_older(&mut rng);
+            }
+            6 => {
+                let fields = crab.to_fields();
+                let loaded = Crab::from_fields(&fields, 1).expect("a saved crab loads");
+                assert_eq!(loaded.to_fields(), fields, "a saved crab loads unchanged");
+            }
+            _ => {
+                let fields = split_record(&input.text());
+                let _ = Crab::from_fields(&fields, 1);
+            }
+        }
+    }
+}
+
+/**
+ * Feeds arbitrary text to every loader and parser, then puts whatever loads to work: reefs tick,
+ * beaches breed, hunt, age and compete, clans record results, configs run a simulation over
+ * reefs they regrow, and snapshots run on. Loading is only half the job; what loads must not
+ * panic later either.
+ */
+pub fn load_text(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    let mut rng = seeded(0);
+    if let Ok(mut beach) = Beach::load(&text) {
+        check_beach(&beach);
+        let ids: Vec<u64> = beach.crabs().map(Crab::id).take(4).chain(0..4).collect();
+        for &a in ids.iter() {
+            for &b in ids.iter() {
+                beach.relatedness(a, b);
+            }
+        }
+        let _ = beach.try_breed_crabs(0, 1, String::from("Fuzzed"));
+        beach.feeding_round();
+        beach.grow_crabs_older(&mut rng);
+        let clan_ids = beach.get_clan_system().get_clan_ids();
+        if let [first, second, ..] = clan_ids.as_slice() {
+            let _ = beach.get_winner_clan(first, second);
+        }
+        check_beach(&beach);
+    }
+    if let Ok(mut reef) = Reef::load(&text) {
+        for regen_scale in [1.0, 0.5, 1e300] {
+            reef.tick_scaled(regen_scale, &mut rng);
+        }
+    }
+    if let Ok(mut clans) = ClanSystem::load(&text) {
+        let clan_ids = clans.get_clan_ids();
+        if let [first, second, ..] = clan_ids.as_slice() {
+            clans.record_result(first, second, Some(first));
+            clans.record_result(first, second, None);
+        }
+        check_clans(&clans);
+    }
+    if let Ok(config) = SimConfig::parse(&text) {
+        let mut ocean = Ocean::new();
+        for name in config.reefs.keys() {
+            let _ = ocean.add_reef(Reef::with_name(name.clone()));
+        }
+        let mut beach = Beach::new();
+        beach.add_crab(Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Fish));
+        beach.add_crab(Crab::new(String::from("Crusty"), 5, Color::new_red(), Diet::Plants));
+        ocean.add_beach(beach);
+        if let Ok(mut simulation) = Simulation::with_config(ocean, &config) {
+            simulation.run(LOADED_STEPS);
+        }
+    }
+    let _ = Cookbook::parse(&text, CookbookFormat::Toml);
+    let _ = Cookbook::parse(&text, CookbookFormat::Json);
+    let _ = Color::from_hex(&text);
+    if let Ok(mut simulation) = Simulation::from_snapshot(&text, Box::new(seeded(0))) {
+        simulation.run(LOADED_STEPS);
+    }
+}
+
+// How many steps `load_text` runs the simulations it loads or configures.
+const LOADED_STEPS: u64 = 3;
+
+// Checks that the beach's lookups agree with its crabs and its clan system with itself.
+fn check_beach(beach: &Beach) {
+    let fastest = beach.crabs().map(Crab::speed).max();
+    assert_eq!(beach.get_fastest_crab().map(Crab::speed), fastest, "the fastest crab is out of date");
+    for (index, crab) in beach.crabs().enumerate() {
+        let first = beach.find_crab_index(crab.name());
+        assert!(first.is_some_and(|first| first <= index), "{} is missing from the name index", crab.name());
+    }
+    check_clans(beach.get_clan_system());
+}
+
+fn check_clans(clans: &ClanSystem) {
+    for clan_id in clans.get_clan_ids() {
+        let members = clans.get_clan(&clan_id).expect("listed clans exist");
+        assert_eq!(clans.get_clan_member_count(&clan_id), members.len());
+        let total: u64 = members.values().map(|crab| crab.speed() as u64).sum();
+        assert_eq!(clans.get_clan_total_speed(&clan_id), Some(total), "clan {} has a stale speed total", clan_id);
+        for name in members.keys() {
+            assert_eq!(clans.get_clan_of_member(name), Some(clan_id.as_str()), "{} is in two clans", name);
+        }
+    }
+}
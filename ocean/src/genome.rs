@@ -0,0 +1,166 @@
+//! Crab genetics. A `Genome` holds a crab's genes, and the crab's traits are read from it:
+//!   - speed, at least 1 in bred crabs, which may be fractional (see `Genome::speed_f`);
+//!   - color, a pair of alleles of which the dominant one shows (see `Genotype`);
+//!   - escape resistance, from 0.0 to 1.0, which makes prey less likely to slip away
+//!     (see `ChanceEscape`).
+//!
+//! `Genome::cross` combines two parents' genomes: each gene is crossed over from one parent or
+//! the other, and may then mutate.
+
+use crate::color::{Color, Genotype};
+use rand::Rng;
+
+/// The chance that each gene mutates when crabs are bred with `Crab::breed_genetic`.
+pub const MUTATION_RATE: f64 = 0.05;
+
+/// How far a mutation moves escape resistance, up or down.
+const RESISTANCE_STEP: f64 = 0.1;
+
+/// How far a mutation moves one component of a color allele, up or down.
+pub(crate) const COLOR_STEP: u8 = 16;
+
+/// A crab's genes.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Genome {
+    speed: f64,
+    color: Genotype,
+    escape_resistance: f64,
+}
+
+impl Genome {
+    /**
+     * Creates a genome. `escape_resistance` is clamped to between 0.0 and 1.0, and NaN counts
+     * as 0.0.
+     */
+    pub fn new(speed: u32, color: Genotype, escape_resistance: f64) -> Genome {
+        Genome {
+            speed: speed as f64,
+            color,
+            escape_resistance: clamp_resistance(escape_resistance),
+        }
+    }
+
+    /**
+     * Returns the speed gene rounded down to a whole number, as crabs' speeds used to be. Prefer
+     * `speed_f`, which this will give way to.
+     */
+    pub fn speed(&self) -> u32 {
+        self.speed as u32
+    }
+
+    /**
+     * Returns the speed gene, fractions included.
+     */
+    pub fn speed_f(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn color(&self) -> &Genotype {
+        &self.color
+    }
+
+    pub fn escape_resistance(&self) -> f64 {
+        self.escape_resistance
+    }
+
+    /**
+     * Returns this genome with its speed gene replaced.
+     */
+    pub fn with_speed(mut self, speed: u32) -> Genome {
+        self.speed = speed as f64;
+        self
+    }
+
+    /**
+     * Like `with_speed`, but keeps fractions. Negative speeds are raised to 0.0.
+     */
+    pub fn with_speed_f(mut self, speed: f64) -> Genome {
+        self.speed = if speed.is_nan() { 0.0 } else { speed.max(0.0) };
+        self
+    }
+
+    /**
+     * Returns this genome with its color gene replaced.
+     */
+    pub fn with_color(mut self, color: Genotype) -> Genome {
+        self.color = color;
+        self
+    }
+
+    /**
+     * Returns this genome with its escape resistance replaced, clamped to between 0.0 and 1.0
+     * (NaN counts as 0.0).
+     */
+    pub fn with_escape_resistance(mut self, escape_resistance: f64) -> Genome {
+        self.escape_resistance = clamp_resistance(escape_resistance);
+        self
+    }
+
+    /**
+     * Returns the genome of an offspring of `g1` and `g2`. Speed and escape resistance are each
+     * crossed over whole from one parent or the other, while each parent passes on one of its
+     * two color alleles (see `Genotype::cross`). Each gene then mutates with chance
+     * `mutation_rate` (see `mutate`).
+     */
+    pub fn cross<R: Rng + ?Sized>(g1: &Genome, g2: &Genome, mutation_rate: f64, rng: &mut R) -> Genome {
+        let speed = if rng.gen_bool(0.5) { g1.speed } else { g2.speed };
+        let color = Genotype::cross(&g1.color, &g2.color, rng);
+        let escape_resistance = if rng.gen_bool(0.5) { g1.escape_resistance } else { g2.escape_resistance };
+        let mut child = Genome::new(0, color, escape_resistance).with_speed_f(speed);
+        child.mutate(mutation_rate, rng);
+        child
+    }
+
+    /**
+     * Mutates each gene with chance `rate`, which is clamped to between 0.0 and 1.0 (NaN counts
+     * as 0.0):
+     *   - speed goes up or down by 1, but never below 1;
+     *   - one component of one color allele goes up or down by 16, staying within 0 to 255;
+     *   - escape resistance goes up or down by 0.1, staying within 0.0 to 1.0.
+     */
+    pub fn mutate<R: Rng + ?Sized>(&mut self, rate: f64, rng: &mut R) {
+        let rate = if rate.is_nan() { 0.0 } else { rate.clamp(0.0, 1.0) };
+        if rng.gen_bool(rate) {
+            let speed = if rng.gen_bool(0.5) { self.speed + 1.0 } else { self.speed - 1.0 };
+            self.speed = speed.max(1.0);
+        }
+        if rng.gen_bool(rate) {
+            let (mut a, mut b) = (self.color.expressed().clone(), self.color.recessive().clone());
+            let allele = if rng.gen_bool(0.5) { &mut a } else { &mut b };
+            let component = match rng.gen_range(0..3) {
+                0 => &mut allele.r,
+                1 => &mut allele.g,
+                _ => &mut allele.b,
+            };
+            *component = if rng.gen_bool(0.5) {
+                component.saturating_add(COLOR_STEP)
+            } else {
+                component.saturating_sub(COLOR_STEP)
+            };
+            self.color = Genotype::new(a, b);
+        }
+        if rng.gen_bool(rate) {
+            let step = if rng.gen_bool(0.5) { RESISTANCE_STEP } else { -RESISTANCE_STEP };
+            self.escape_resistance = clamp_resistance(self.escape_resistance + step);
+        }
+    }
+
+    /**
+     * Returns the genome the original breeding rules give an offspring: a speed of 1, two copies
+     * of the parents' blended color (see `Color::cross`), and the parents' average escape
+     * resistance. This is what `Crab::breed` uses.
+     */
+    pub fn blend(g1: &Genome, g2: &Genome) -> Genome {
+        let color = Color::cross(g1.color.expressed(), g2.color.expressed());
+        Genome::new(1, Genotype::homozygous(color), (g1.escape_resistance + g2.escape_resistance) / 2.0)
+    }
+}
+
+// Keeps an escape resistance between 0.0 and 1.0, treating NaN as no resistance.
+fn clamp_resistance(escape_resistance: f64) -> f64 {
+    if escape_resistance.is_nan() {
+        0.0
+    } else {
+        escape_resistance.clamp(0.0, 1.0)
+    }
+}
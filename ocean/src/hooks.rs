@@ -0,0 +1,66 @@
+//! Hooks that extend each step of a simulation without changing this crate.
+//!
+//! A hook is called at the end of every phase of every step (see `crate::simulation`), with
+//! mutable access to what it was registered on and the simulation's generator, which it must
+//! draw any randomness from so runs stay reproducible. Hooks can be registered on:
+//!   - a simulation (see `Simulation::register_hook`), to act on the whole ocean,
+//!   - a beach (see `Beach::register_hook`), to act on that beach alone,
+//!   - a reef (see `Reef::register_hook`), to act on that reef alone.
+//!
+//! At the end of a phase, the hooks of every beach run first, beach by beach, then those of
+//! every reef, then the simulation's own, each in the order they were registered. Events queued
+//! by hooks are published with the phase's own. Hooks are not saved with a simulation,
+//! recorded for replays, or kept by cloned reefs.
+
+use crate::beach::Beach;
+use crate::ocean::Ocean;
+use crate::reef::Reef;
+use crate::rng::OceanRng;
+use std::fmt;
+
+/// The phases of a simulation step, in order (see `crate::simulation`).
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Phase {
+    /** The weather is looked up and seasonal effects run. */
+    Weather,
+    /** Crabs grow older, scavenge, and catch and shake off diseases. */
+    Aging,
+    /** Reefs tick and prey migrate between them. */
+    Reefs,
+    /** Crabs discover nearby reefs. */
+    Discovery,
+    /** Crabs hunt, and crabs with behavior trees run them. */
+    Hunting,
+    /** Beaches face their predators. */
+    Predators,
+    /** Beaches apply their population policies. */
+    Population,
+    /** Crabs move between beaches. */
+    Migration,
+    /** Scheduled events run, and scheduled clan matches are played. */
+    Scheduled,
+}
+
+/// A hook on a whole simulation (see `Simulation::register_hook`).
+pub trait TickHook {
+    /**
+     * Called at the end of `phase` of the step at `tick`.
+     */
+    fn after_phase(&mut self, tick: u64, phase: Phase, ocean: &mut Ocean, rng: &mut dyn OceanRng);
+}
+
+/// A hook on one beach (see `Beach::register_hook`).
+pub trait BeachHook: fmt::Debug {
+    /**
+     * Called at the end of `phase` of the step at `tick`.
+     */
+    fn after_phase(&mut self, tick: u64, phase: Phase, beach: &mut Beach, rng: &mut dyn OceanRng);
+}
+
+/// A hook on one reef (see `Reef::register_hook`).
+pub trait ReefHook: fmt::Debug + Send {
+    /**
+     * Called at the end of `phase` of the step at `tick`.
+     */
+    fn after_phase(&mut self, tick: u64, phase: Phase, reef: &mut Reef, rng: &mut dyn OceanRng);
+}
@@ -0,0 +1,67 @@
+//! String interning for the names `Beach` and `ClanSystem` look things up by.
+//!
+//! Each distinct name is stored once, and stands for itself everywhere else as a small `NameId`
+//! that is cheap to copy, hash and compare. Names are only turned back into `&str` at the API
+//! boundary, so callers never see the ids.
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// An interned name, only meaningful to the `Interner` that produced it.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct NameId(u32);
+
+/// Gives each distinct name a `NameId`. Names are never forgotten, so ids stay valid for as long
+/// as the interner lives.
+#[derive(Debug, Default, Clone)]
+pub struct Interner {
+    names: Vec<Rc<str>>,
+    ids: HashMap<Rc<str>, NameId>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /**
+     * Returns the id of `name`, giving it a new one if it has not been seen before.
+     */
+    pub fn intern(&mut self, name: &str) -> NameId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = NameId(u32::try_from(self.names.len()).expect("fewer than 2^32 distinct names"));
+        let name: Rc<str> = Rc::from(name);
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        id
+    }
+
+    /**
+     * Returns the id of `name`, or None if it has never been interned. Unlike `intern`, this
+     * never allocates, so lookups of unknown names stay cheap.
+     */
+    pub fn get(&self, name: &str) -> Option<NameId> {
+        self.ids.get(name).copied()
+    }
+
+    /**
+     * Returns the name `id` stands for. `id` must come from this interner: one from another
+     * interner may panic or resolve to the wrong name.
+     */
+    pub fn resolve(&self, id: NameId) -> &str {
+        &self.names[id.0 as usize]
+    }
+
+    /**
+     * Returns the number of distinct names interned so far.
+     */
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
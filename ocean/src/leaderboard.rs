@@ -0,0 +1,89 @@
+//! Ranked listings of a beach's crabs (see `Beach::leaderboard`).
+//!
+//! A leaderboard ranks crabs by a list of criteria: crabs are ordered by the first criterion,
+//! crabs that tie on it by the second, and so on, with the highest scores first. Crabs that tie
+//! on every criterion keep their beach order, so the same beach always gives the same listing.
+
+use std::fmt;
+
+/// Something crabs can be ranked by.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum RankBy {
+    /** The crab's genetic speed (see `Crab::speed`). */
+    Speed,
+    /** The number of successful hunts the crab has made. */
+    Hunts,
+    /** The total nutrition the crab has eaten, a measure of its experience as a hunter. */
+    Energy,
+    /** The percentage of the crab's own hunts that caught prey (see `Crab::hunt_stats`). */
+    SuccessRate,
+    /** The number of crabs the crab has parented. */
+    Offspring,
+    /** The crab's age in simulation steps. */
+    Age,
+    /** The reputation of the crab's clan this season, or 0 if it is in no clan. */
+    ClanReputation,
+}
+
+impl RankBy {
+    /**
+     * The criterion's name, as `from_str` parses it and leaderboards print it.
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            RankBy::Speed => "speed",
+            RankBy::Hunts => "hunts",
+            RankBy::Energy => "energy",
+            RankBy::SuccessRate => "success",
+            RankBy::Offspring => "offspring",
+            RankBy::Age => "age",
+            RankBy::ClanReputation => "reputation",
+        }
+    }
+}
+
+impl std::str::FromStr for RankBy {
+    type Err = String;
+
+    /**
+     * Parses a criterion from its name (see `RankBy::name`).
+     */
+    fn from_str(s: &str) -> Result<RankBy, String> {
+        match s {
+            "speed" => Ok(RankBy::Speed),
+            "hunts" => Ok(RankBy::Hunts),
+            "energy" => Ok(RankBy::Energy),
+            "success" => Ok(RankBy::SuccessRate),
+            "offspring" => Ok(RankBy::Offspring),
+            "age" => Ok(RankBy::Age),
+            "reputation" => Ok(RankBy::ClanReputation),
+            _ => Err(format!("unknown ranking criterion: {:?}", s)),
+        }
+    }
+}
+
+/// One crab's place on a leaderboard.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LeaderboardEntry {
+    /** The crab's place, starting from 1. No two crabs share a place. */
+    pub rank: usize,
+    /** The crab's index on the beach. */
+    pub index: usize,
+    pub crab: String,
+    /** The crab's score on each criterion, in the order the criteria were given. */
+    pub scores: Vec<(RankBy, u64)>,
+}
+
+impl fmt::Display for LeaderboardEntry {
+    /**
+     * Formats the entry as one line, e.g. `1. Ferris (speed 7, hunts 3)`.
+     */
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}. {}", self.rank, self.crab)?;
+        if !self.scores.is_empty() {
+            let scores: Vec<String> = self.scores.iter().map(|(by, score)| format!("{} {}", by.name(), score)).collect();
+            write!(f, " ({})", scores.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -1,10 +1,61 @@
+#![allow(clippy::needless_return, clippy::new_without_default)]
+
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+pub mod arena;
+pub mod bait;
+pub mod batch;
 pub mod beach;
+pub mod behavior;
+pub mod capabilities;
 pub mod color;
+pub mod config;
 pub mod cookbook;
 pub mod crab;
+#[cfg(feature = "deterministic")]
+pub mod deterministic;
 pub mod diet;
+pub mod disease;
+#[cfg(feature = "economy")]
+pub mod economy;
+pub mod error;
+pub mod escape;
+pub mod events;
+pub mod feeding;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+pub mod genome;
+pub mod hooks;
+pub mod intern;
+pub mod leaderboard;
+pub mod metrics;
+#[cfg(feature = "async")]
+pub mod nonblocking;
 pub mod ocean;
+pub mod paging;
+pub mod pattern;
+pub mod persist;
+pub mod population;
+pub mod position;
+pub mod predator;
 pub mod prey;
+pub mod query;
 pub mod rand;
 pub mod reef;
+pub mod replay;
+pub mod report;
+pub mod registry;
+pub mod rng;
+pub mod scenario;
+pub mod shared;
+pub mod simulation;
+pub mod snapshot;
 pub mod clans;
+pub mod summary;
+pub mod trace;
+pub mod weather;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod zone;
+
+pub use capabilities::capabilities;
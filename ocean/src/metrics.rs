@@ -0,0 +1,108 @@
+//! Time series recorded by a `Simulation` at the end of every step, once enabled with
+//! `Simulation::enable_metrics`. Each series is named after what it measures:
+//!   - `population`: the number of crabs in the ocean;
+//!   - `average_speed`: the crabs' average speed, left out of steps with no crabs;
+//!   - `prey/<reef>`: the number of prey in each reef, named as in `Ocean::to_dot`, e.g.
+//!     `prey/kelp` or `prey/reef 1`;
+//!   - `clan/<beach>/<clan>`: the number of members of each clan, e.g. `clan/beach 0/pinchers`.
+//!
+//! Reefs and clans only have points for the steps they existed in.
+
+use crate::ocean::Ocean;
+use crate::persist::csv_field;
+use std::collections::BTreeMap;
+
+/// Every series recorded so far, as (tick, value) points in tick order.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metrics {
+    ticks: Vec<u64>,
+    series: BTreeMap<String, Vec<(u64, f64)>>,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics::default()
+    }
+
+    /**
+     * Measures `ocean` and adds a point for `tick` to each series. A `Simulation` calls this at
+     * the end of each step.
+     */
+    pub fn record(&mut self, tick: u64, ocean: &Ocean) {
+        self.ticks.push(tick);
+        let population = ocean.total_population();
+        self.push("population".to_string(), tick, population as f64);
+        if population > 0 {
+            let total: u64 = ocean.beaches().flat_map(|beach| beach.crabs()).map(|crab| crab.speed() as u64).sum();
+            self.push("average_speed".to_string(), tick, total as f64 / population as f64);
+        }
+        for (id, reef) in ocean.reefs().enumerate() {
+            let reef = reef.borrow();
+            let label = reef.name().map_or(format!("reef {}", id), str::to_string);
+            self.push(format!("prey/{}", label), tick, reef.population() as f64);
+        }
+        for (b, beach) in ocean.beaches().enumerate() {
+            let label = beach.name().map_or(format!("beach {}", b), str::to_string);
+            let clans = beach.get_clan_system();
+            for clan in clans.get_clan_ids() {
+                let members = clans.get_clan_member_count(&clan);
+                self.push(format!("clan/{}/{}", label, clan), tick, members as f64);
+            }
+        }
+    }
+
+    fn push(&mut self, name: String, tick: u64, value: f64) {
+        self.series.entry(name).or_default().push((tick, value));
+    }
+
+    /**
+     * Returns the points of the series called `name`, or None if nothing has been recorded
+     * under that name.
+     */
+    pub fn series(&self, name: &str) -> Option<&[(u64, f64)]> {
+        self.series.get(name).map(Vec::as_slice)
+    }
+
+    /**
+     * Returns the names of every series, in sorted order.
+     */
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.series.keys().map(String::as_str)
+    }
+
+    /**
+     * Returns the ticks that have been recorded, in order.
+     */
+    pub fn ticks(&self) -> &[u64] {
+        &self.ticks
+    }
+
+    /**
+     * Exports every series as CSV: a `tick` column followed by one column per series in sorted
+     * order, and one row per recorded tick. Cells for steps a series has no point for are empty.
+     */
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("tick");
+        for name in self.series.keys() {
+            out.push(',');
+            out.push_str(&csv_field(name));
+        }
+        out.push('\n');
+        // Points are in tick order, so each series is read with one cursor.
+        let mut cursors = vec![0; self.series.len()];
+        for &tick in self.ticks.iter() {
+            out.push_str(&tick.to_string());
+            for (points, cursor) in self.series.values().zip(cursors.iter_mut()) {
+                out.push(',');
+                if let Some(&(point_tick, value)) = points.get(*cursor) {
+                    if point_tick == tick {
+                        out.push_str(&value.to_string());
+                        *cursor += 1;
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
@@ -0,0 +1,110 @@
+//! Awaiting a `SharedSimulation` from async code, e.g. a server whose request handlers must not
+//! stall while a step runs (the `async` feature).
+//!
+//! The blocking methods of `SharedSimulation` park the calling thread until the simulation's
+//! thread answers, which also parks every task an async runtime has scheduled on it. The methods
+//! here send the same closures but return a `Reply`, a future that resolves once the closure has
+//! run, so the caller's thread is free in the meantime. `Reply` only uses `std::task`, so it works
+//! under any runtime. With tokio, ticks can be driven by an interval:
+//!
+//! ```text
+//! let shared = SharedSimulation::spawn(|| Simulation::new(Ocean::new(), 7));
+//! let mut ticks = tokio::time::interval(Duration::from_millis(100));
+//! loop {
+//!     ticks.tick().await;
+//!     let report = shared.step_async().await?;
+//! }
+//! ```
+//!
+//! Beaches are not run as separate tasks: they share reefs through `Rc<RefCell<Reef>>` handles,
+//! so the whole ocean steps on the simulation's thread, and crabs migrate between beaches there
+//! as in any other step.
+
+use crate::error::OceanError;
+use crate::shared::SharedSimulation;
+use crate::simulation::{Simulation, StepReport};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// The result of a closure sent to a `SharedSimulation`, once it has run. Dropping a `Reply`
+/// does not cancel the closure.
+#[derive(Debug)]
+pub struct Reply<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+// Where the simulation's thread leaves a closure's result for its `Reply`.
+#[derive(Debug)]
+struct Slot<R> {
+    result: Option<Result<R, OceanError>>,
+    waker: Option<Waker>,
+}
+
+// Fills a `Reply`'s slot. If it is dropped unfilled, because the simulation's thread stopped
+// before running the closure, the reply resolves to `OceanError::SimulationStopped`.
+struct Filler<R> {
+    slot: Arc<Mutex<Slot<R>>>,
+}
+
+impl<R> Filler<R> {
+    fn fill(&self, result: Result<R, OceanError>) {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        if slot.result.is_none() {
+            slot.result = Some(result);
+            if let Some(waker) = slot.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<R> Drop for Filler<R> {
+    fn drop(&mut self) {
+        self.fill(Err(OceanError::SimulationStopped));
+    }
+}
+
+impl<R> Future for Reply<R> {
+    type Output = Result<R, OceanError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl SharedSimulation {
+    /**
+     * Like `with`, but returns a future of `f`'s result instead of waiting for it.
+     *
+     * The future resolves to `OceanError::SimulationStopped` if the simulation's thread is gone,
+     * because building the simulation or an earlier closure panicked.
+     */
+    pub fn with_async<R, F>(&self, f: F) -> Reply<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Simulation) -> R + Send + 'static,
+    {
+        let slot = Arc::new(Mutex::new(Slot { result: None, waker: None }));
+        let filler = Filler { slot: slot.clone() };
+        // If the thread is gone, the job and its filler are dropped here, which fills the slot.
+        let _ = self.send(Box::new(move |simulation| filler.fill(Ok(f(simulation)))));
+        Reply { slot }
+    }
+
+    pub fn step_async(&self) -> Reply<StepReport> {
+        self.with_async(Simulation::step)
+    }
+
+    pub fn run_async(&self, steps: u64) -> Reply<Vec<StepReport>> {
+        self.with_async(move |simulation| simulation.run(steps))
+    }
+}
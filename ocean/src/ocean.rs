@@ -1,37 +1,613 @@
 use crate::beach::Beach;
+use crate::clans::{ClanMember, ClanSystem};
+use crate::crab::{Crab, CrabId};
+use crate::diet::Diet;
+use crate::error::OceanError;
+use crate::events::OceanEvent;
+use crate::persist::dot_string;
 use crate::prey::{Algae, Clam, Minnow, Shrimp};
-use crate::reef::Reef;
+use crate::query::{Found, OceanQuery};
+use crate::reef::{shannon_index, Reef};
+use crate::registry::{ReefId, ReefRegistry};
+use rand::Rng;
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::rc::Rc;
-use std::slice::Iter;
+use std::slice::{Iter, IterMut};
 
+/// When crabs leave their beach for another one on their own (see `Ocean::apply_migration_policy`).
+/// The default policy moves no crabs.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MigrationPolicy {
+    max_population: Option<usize>,
+    leave_depleted: bool,
+}
+
+impl MigrationPolicy {
+    pub fn new() -> MigrationPolicy {
+        MigrationPolicy::default()
+    }
+
+    /**
+     * Returns this policy with overcrowding: crabs leave beaches with more than `max_population`
+     * crabs, newest first, for the least crowded beach with room for them.
+     */
+    pub fn with_max_population(mut self, max_population: usize) -> MigrationPolicy {
+        self.max_population = Some(max_population);
+        self
+    }
+
+    /**
+     * Returns this policy with crabs whose reefs are all empty leaving for the beach whose crabs'
+     * reefs hold the most prey, where they discover those reefs.
+     */
+    pub fn with_leave_depleted(mut self, leave_depleted: bool) -> MigrationPolicy {
+        self.leave_depleted = leave_depleted;
+        self
+    }
+
+    pub fn max_population(&self) -> Option<usize> {
+        self.max_population
+    }
+
+    pub fn leave_depleted(&self) -> bool {
+        self.leave_depleted
+    }
+}
+
+/// A crab in one of an ocean's clans (see `Ocean::join_ocean_clan`), which may have members on
+/// any of its beaches. Members are found by their beach's index and their id there.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OceanClanMember {
+    beach: usize,
+    crab: CrabId,
+    speed: u32,
+    // The member's name in its clan system, unique across the ocean.
+    key: String,
+}
+
+impl OceanClanMember {
+    fn new(beach: usize, crab: &Crab) -> OceanClanMember {
+        OceanClanMember { beach, crab: crab.id(), speed: crab.speed(), key: OceanClanMember::key(beach, crab.id()) }
+    }
+
+    fn key(beach: usize, crab: CrabId) -> String {
+        format!("{}/{}", beach, crab)
+    }
+
+    pub fn beach(&self) -> usize {
+        self.beach
+    }
+
+    pub fn crab(&self) -> CrabId {
+        self.crab
+    }
+}
+
+impl ClanMember for OceanClanMember {
+    /**
+     * The member's beach index and crab id, e.g. "1/7".
+     */
+    fn name(&self) -> &str {
+        &self.key
+    }
+
+    /**
+     * The crab's speed when it joined; competitions go by its speed now.
+     */
+    fn speed(&self) -> u32 {
+        self.speed
+    }
+}
+
+/// A world of beaches, which may be named, and the reefs their crabs share.
 #[derive(Debug)]
 pub struct Ocean {
     beaches: Vec<Beach>,
-    reefs: Vec<Rc<RefCell<Reef>>>
+    reefs: ReefRegistry,
+    // Clans whose members live on any of the beaches, apart from each beach's own clans.
+    clans: ClanSystem<OceanClanMember>,
 }
 
 impl Ocean {
     pub fn new() -> Ocean {
         Ocean {
             beaches: Vec::new(),
-            reefs: Vec::new()
+            reefs: ReefRegistry::new(),
+            clans: ClanSystem::new(),
         }
     }
 
+    /**
+     * Adds `beach` to the end of the ocean's beaches.
+     * Names are not checked: lookups by name find the first beach with that name.
+     * Use `try_add_beach` to refuse a beach whose name is taken.
+     */
     pub fn add_beach(&mut self, beach: Beach) {
-       self.beaches.push(beach);
+        self.beaches.push(beach);
     }
 
-    pub fn beaches(&self) -> Iter<Beach> {
+    /**
+     * Like `add_beach`, but returns `OceanError::DuplicateName` if the beach is named and the
+     * ocean already has a beach with that name.
+     * Returns the index of the new beach, e.g. for `migrate`.
+     */
+    pub fn try_add_beach(&mut self, beach: Beach) -> Result<usize, OceanError> {
+        if let Some(name) = beach.name() {
+            if self.beach_index(name).is_some() {
+                return Err(OceanError::DuplicateName { kind: "beach", name: name.to_string() });
+            }
+        }
+        self.beaches.push(beach);
+        Ok(self.beaches.len() - 1)
+    }
+
+    /**
+     * Returns the index of the beach with the given name, or None if no beach has that name.
+     */
+    pub fn beach_index(&self, name: &str) -> Option<usize> {
+        self.beaches.iter().position(|beach| beach.name() == Some(name))
+    }
+
+    pub fn beach_by_name(&self, name: &str) -> Option<&Beach> {
+        self.beaches.iter().find(|beach| beach.name() == Some(name))
+    }
+
+    pub fn beach_by_name_mut(&mut self, name: &str) -> Option<&mut Beach> {
+        self.beaches.iter_mut().find(|beach| beach.name() == Some(name))
+    }
+
+    /**
+     * Returns the number of crabs on all of the ocean's beaches.
+     */
+    pub fn total_population(&self) -> usize {
+        self.beaches.iter().map(Beach::size).sum()
+    }
+
+    /**
+     * Returns the Shannon diversity index (see `Reef::biodiversity`) of the prey in all of the
+     * ocean's reefs taken together, so a kind thriving in one reef counts however empty the
+     * others are.
+     */
+    pub fn biodiversity(&self) -> f64 {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for reef in self.reefs.iter() {
+            for (kind, count) in reef.borrow().census() {
+                *counts.entry(kind).or_insert(0) += count;
+            }
+        }
+        shannon_index(counts.into_values())
+    }
+
+    /**
+     * Like `ClanSystem::diet_breakdown`, but counts the members of the clans with the given id on
+     * every beach together.
+     */
+    pub fn clan_diet_breakdown(&self, clan_id: &str) -> HashMap<Diet, usize> {
+        let mut counts: HashMap<Diet, usize> = HashMap::new();
+        for beach in self.beaches.iter() {
+            for (diet, count) in beach.get_clan_system().diet_breakdown(clan_id) {
+                *counts.entry(diet).or_insert(0) += count;
+            }
+        }
+        counts
+    }
+
+    /**
+     * Returns Some of the fastest crab on any beach, or None if every beach is empty.
+     * Like `Beach::get_fastest_crab`, ties go to the crab that comes last, beach by beach.
+     */
+    pub fn fastest_crab_overall(&self) -> Option<&Crab> {
+        let mut fastest: Option<&Crab> = None;
+        for crab in self.beaches.iter().filter_map(Beach::get_fastest_crab) {
+            if fastest.is_none_or(|fastest| crab.speed() >= fastest.speed()) {
+                fastest = Some(crab);
+            }
+        }
+        fastest
+    }
+
+    /**
+     * Returns everything in the ocean matching `query`: first the crabs, beach by beach in beach
+     * order, then the prey, reef by reef in id order (see `crate::query`).
+     */
+    pub fn find(&self, query: &OceanQuery) -> Vec<Found> {
+        let mut found = Vec::new();
+        for (b, beach) in self.beaches.iter().enumerate() {
+            found.extend(beach.crabs().filter(|crab| query.matches_crab(crab)).map(|crab| Found::Crab { beach: b, id: crab.id() }));
+        }
+        for (id, reef) in self.reefs.iter().enumerate() {
+            for (index, prey) in reef.borrow().prey().enumerate() {
+                if query.matches_prey(prey.as_ref()) {
+                    found.push(Found::Prey { reef: id, index, kind: prey.kind(), diet: prey.diet() });
+                }
+            }
+        }
+        found
+    }
+
+    pub fn beaches(&self) -> Iter<'_, Beach> {
         self.beaches.iter()
     
     }
 
-    pub fn reefs(&self) -> Iter<Rc<RefCell<Reef>>> {
+    pub fn beaches_mut(&mut self) -> IterMut<'_, Beach> {
+        self.beaches.iter_mut()
+    }
+
+    /**
+     * Returns a Graphviz DOT graph of the ocean, for rendering with e.g. `dot -Tsvg`:
+     *   - every reef is a box, labelled with its name or registry id;
+     *   - every beach is a cluster holding its crabs, filled with their colors, and its clans,
+     *     drawn as diamonds;
+     *   - solid edges lead from each crab to the reefs it feeds from, dashed edges to its clan,
+     *     and bold edges from each living parent to its offspring.
+     */
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph ocean {\n");
+        for (id, reef) in self.reefs.iter().enumerate() {
+            let label = reef.borrow().name().map_or(format!("reef {}", id), str::to_string);
+            out.push_str(&format!("    reef_{} [label={}, shape=box];\n", id, dot_string(&label)));
+        }
+        let mut edges = Vec::new();
+        for (b, beach) in self.beaches.iter().enumerate() {
+            let crab_node = |crab: &Crab| format!("crab_{}_{}", b, crab.id());
+            out.push_str(&format!("    subgraph cluster_beach_{} {{\n", b));
+            let label = beach.name().map_or(format!("beach {}", b), str::to_string);
+            out.push_str(&format!("        label={};\n", dot_string(&label)));
+            for crab in beach.crabs() {
+                out.push_str(&format!(
+                    "        {} [label={}, style=filled, fillcolor=\"{}\"];\n",
+                    crab_node(crab),
+                    dot_string(crab.name()),
+                    crab.color().to_hex()
+                ));
+                for reef in crab.reefs() {
+                    if let Some(id) = self.reefs.id_of(&reef) {
+                        edges.push(format!("{} -> reef_{}", crab_node(crab), id));
+                    }
+                }
+                if let Some((p1, p2)) = crab.parents() {
+                    let parents = if p1 == p2 { vec![p1] } else { vec![p1, p2] };
+                    for parent in parents.into_iter().filter_map(|id| beach.find_crab_by_id(id)) {
+                        edges.push(format!("{} -> {} [style=bold]", crab_node(beach.get_crab(parent)), crab_node(crab)));
+                    }
+                }
+            }
+            let mut clans = beach.get_clan_system().get_clan_ids();
+            clans.sort();
+            for (c, clan) in clans.iter().enumerate() {
+                out.push_str(&format!("        clan_{}_{} [label={}, shape=diamond];\n", b, c, dot_string(clan)));
+                let mut members = beach.get_clan_system().get_clan_member_names(clan);
+                members.sort();
+                for index in members.iter().filter_map(|name| beach.find_crab_index(name)) {
+                    edges.push(format!("{} -> clan_{}_{} [style=dashed]", crab_node(beach.get_crab(index)), b, c));
+                }
+            }
+            out.push_str("    }\n");
+        }
+        for edge in edges {
+            out.push_str(&format!("    {};\n", edge));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /**
+     * Moves the crab with id `crab_id` from the beach at index `from` to the one at index `to`,
+     * returning its id on the new beach (see `Beach::add_crab`). It keeps the reefs it has
+     * discovered.
+     *
+     * The crab leaves its clan. If the new beach has a clan with the same id, the crab joins it
+     * unless the clan turns it away or a crab with its name is already in a clan there.
+     *
+     * Returns `OceanError::BeachNotFound` if either index is out of range,
+     * `OceanError::InvalidArgument` if they are the same, and `OceanError::CrabIdNotFound` if no
+     * crab on the first beach has that id.
+     */
+    pub fn migrate(&mut self, crab_id: CrabId, from: usize, to: usize) -> Result<CrabId, OceanError> {
+        for index in [from, to] {
+            if index >= self.beaches.len() {
+                return Err(OceanError::BeachNotFound(format!("at index {}", index)));
+            }
+        }
+        if from == to {
+            return Err(OceanError::InvalidArgument(format!("The crab is already on beach {}", to)));
+        }
+        let index = self.beaches[from]
+            .find_crab_by_id(crab_id)
+            .ok_or(OceanError::CrabIdNotFound(crab_id))?;
+        Ok(self.move_crab(from, index, to))
+    }
+
+    // Moves the crab at `index` on beach `from` to the end of beach `to`, as described on
+    // `migrate`, returning its new id.
+    fn move_crab(&mut self, from: usize, index: usize, to: usize) -> CrabId {
+        let old_key = OceanClanMember::key(from, self.beaches[from].get_crab(index).id());
+        let (crab, clan) = self.beaches[from].take_crab(index);
+        let name = crab.name().to_string();
+        let destination = &mut self.beaches[to];
+        destination.add_crab(crab);
+        let arrived = destination.get_crab(destination.size() - 1).clone();
+        if let Some(clan) = clan {
+            let clans = destination.get_clan_system_mut();
+            if clans.has_clan(&clan) && clans.get_clan_of_member(&name).is_none() {
+                // A clan that turns the crab away just means it arrives without one.
+                let _ = clans.add_member(&clan, name.clone(), arrived.clone());
+            }
+        }
+        // Neither beach can take the move back one half at a time.
+        destination.clear_history();
+        destination.queue_event(OceanEvent::CrabMigrated { name, from, to });
+        // The crab stays in its ocean clan under its new id.
+        let member = OceanClanMember::new(to, &arrived);
+        let new_key = member.key.clone();
+        self.clans.update_member(&old_key, &new_key, member);
+        arrived.id()
+    }
+
+    /**
+     * Moves crabs between beaches as `policy` describes: first away from overcrowded beaches,
+     * then away from depleted reefs, beach by beach. Crabs move as with `migrate`, and stay put
+     * if no beach suits them.
+     *
+     * Returns the number of crabs moved.
+     */
+    pub fn apply_migration_policy(&mut self, policy: &MigrationPolicy) -> usize {
+        let mut moved = 0;
+        let has_room = |beach: &Beach| policy.max_population.is_none_or(|max| beach.size() < max);
+        if let Some(max) = policy.max_population {
+            for from in 0..self.beaches.len() {
+                while self.beaches[from].size() > max {
+                    let destination = (0..self.beaches.len())
+                        .filter(|&to| to != from && has_room(&self.beaches[to]))
+                        .min_by_key(|&to| self.beaches[to].size());
+                    let Some(to) = destination else {
+                        break;
+                    };
+                    self.move_crab(from, self.beaches[from].size() - 1, to);
+                    moved += 1;
+                }
+            }
+        }
+        if policy.leave_depleted {
+            for from in 0..self.beaches.len() {
+                let starving: Vec<usize> = self.beaches[from]
+                    .crabs()
+                    .enumerate()
+                    .filter(|(_, crab)| {
+                        crab.reefs().next().is_some() && crab.reefs().all(|reef| reef.borrow().population() == 0)
+                    })
+                    .map(|(index, _)| index)
+                    .collect();
+                // Later crabs first, so the indices of earlier ones stay put.
+                for index in starving.into_iter().rev() {
+                    let mut best: Option<(usize, usize)> = None;
+                    for to in (0..self.beaches.len()).filter(|&to| to != from && has_room(&self.beaches[to])) {
+                        let prey: usize = home_reefs(&self.beaches[to]).iter().map(|reef| reef.borrow().population()).sum();
+                        if prey > 0 && best.is_none_or(|(_, most)| prey > most) {
+                            best = Some((to, prey));
+                        }
+                    }
+                    let Some((to, _)) = best else {
+                        continue;
+                    };
+                    let reefs = home_reefs(&self.beaches[to]);
+                    self.move_crab(from, index, to);
+                    let beach = &mut self.beaches[to];
+                    let mut crab = beach.get_crab_mut(beach.size() - 1);
+                    for reef in reefs {
+                        if !crab.reefs().any(|known| Rc::ptr_eq(&known, &reef)) {
+                            crab.discover_reef(reef);
+                        }
+                    }
+                    moved += 1;
+                }
+            }
+        }
+        moved
+    }
+
+    /**
+     * Adds `reef` to the ocean, returning a shared handle to it.
+     *
+     * Returns `OceanError::DuplicateName` if the reef is named and the ocean already has a reef
+     * with that name.
+     */
+    pub fn add_reef(&mut self, reef: Reef) -> Result<Rc<RefCell<Reef>>, OceanError> {
+        let id = self.reefs.register(reef)?;
+        Ok(self.reefs.get(id).unwrap())
+    }
+
+    /**
+     * Returns Some handle to the reef with the given name, or None if no reef has that name.
+     */
+    pub fn reef_by_name(&self, name: &str) -> Option<Rc<RefCell<Reef>>> {
+        self.reefs.by_name(name)
+    }
+
+    pub fn reefs(&self) -> Iter<'_, Rc<RefCell<Reef>>> {
         self.reefs.iter()
     }
 
+    /**
+     * Has the crabs on every beach discover the ocean's reefs within their beach's sensory radius
+     * (see `Beach::discover_nearby_reefs`). Returns the number of reefs discovered.
+     */
+    pub fn discover_nearby_reefs(&mut self) -> usize {
+        let reefs = &self.reefs;
+        self.beaches.iter_mut().map(|beach| beach.discover_nearby_reefs(reefs.iter())).sum()
+    }
+
+    /**
+     * Gives a clan on the beach at index `beach` the reef with the given id, as
+     * `ClanSystem::claim_reef` does, and records the clan as the reef's owner (see `Reef::owner`).
+     *
+     * Returns `OceanError::BeachNotFound` or `OceanError::ReefNotFound` if there is no such beach
+     * or reef, and otherwise the errors of `ClanSystem::claim_reef`.
+     */
+    pub fn claim_reef(&mut self, beach: usize, clan_id: &str, reef: ReefId) -> Result<(), OceanError> {
+        let handle = self.reef_for_clans(beach, reef)?;
+        self.beaches[beach].get_clan_system_mut().claim_reef(clan_id, reef)?;
+        handle.borrow_mut().set_owner(Some(clan_id.to_string()));
+        Ok(())
+    }
+
+    /**
+     * Has clans on the beach at index `beach` raid the reef with the given id, as `Beach::raid`
+     * does, and makes the attacker the reef's owner if it captures it.
+     *
+     * Returns `OceanError::BeachNotFound` or `OceanError::ReefNotFound` if there is no such beach
+     * or reef, and otherwise the errors of `Beach::raid`.
+     */
+    pub fn raid<R: Rng + ?Sized>(
+        &mut self,
+        beach: usize,
+        attacker: &str,
+        defender: &str,
+        reef: ReefId,
+        rng: &mut R,
+    ) -> Result<bool, OceanError> {
+        let handle = self.reef_for_clans(beach, reef)?;
+        let captured = self.beaches[beach].raid(attacker, defender, reef, rng)?;
+        if captured {
+            handle.borrow_mut().set_owner(Some(attacker.to_string()));
+        }
+        Ok(captured)
+    }
+
+    // Checks that the beach exists and returns a handle to the reef its clans are fighting over.
+    fn reef_for_clans(&self, beach: usize, reef: ReefId) -> Result<Rc<RefCell<Reef>>, OceanError> {
+        if beach >= self.beaches.len() {
+            return Err(OceanError::BeachNotFound(format!("at index {}", beach)));
+        }
+        self.reefs.get(reef).ok_or_else(|| OceanError::ReefNotFound(reef.to_string()))
+    }
+
+    /**
+     * Returns the ocean's own clans, whose members may live on any of its beaches (see
+     * `join_ocean_clan`). They are separate from each beach's clans.
+     */
+    pub fn ocean_clans(&self) -> &ClanSystem<OceanClanMember> {
+        &self.clans
+    }
+
+    /**
+     * Adds the crab with id `crab` on the beach at index `beach` to the ocean clan `clan_id`,
+     * creating the clan if it does not exist. A crab belongs to at most one ocean clan, leaving
+     * any other it was in, and stays in it when it migrates (see `migrate`). Its clans on its
+     * own beach are unaffected.
+     *
+     * Returns `OceanError::BeachNotFound` or `OceanError::CrabIdNotFound` if there is no such
+     * beach or crab, and otherwise the errors of `ClanSystem::add_member`.
+     */
+    pub fn join_ocean_clan(&mut self, clan_id: &str, beach: usize, crab: CrabId) -> Result<(), OceanError> {
+        let member = OceanClanMember::new(beach, self.find_crab(beach, crab)?);
+        if !self.clans.has_clan(clan_id) {
+            self.clans.create_clan(clan_id.to_string());
+        }
+        self.clans.add_member(clan_id, member.key.clone(), member)
+    }
+
+    /**
+     * Takes the crab with id `crab` on the beach at index `beach` out of its ocean clan,
+     * returning the clan's id, or None if it was in none.
+     */
+    pub fn leave_ocean_clan(&mut self, beach: usize, crab: CrabId) -> Option<String> {
+        self.clans.remove_member(&OceanClanMember::key(beach, crab))
+    }
+
+    /**
+     * Returns the members of the ocean clan `clan_id` still living on the ocean's beaches, by
+     * beach and then by id, with the beach each lives on. The list is empty if there is no such
+     * clan.
+     */
+    pub fn ocean_clan_members(&self, clan_id: &str) -> Vec<(usize, &Crab)> {
+        let Some(clan) = self.clans.get_clan(clan_id) else {
+            return Vec::new();
+        };
+        let mut members: Vec<(usize, &Crab)> = clan
+            .values()
+            .filter_map(|member| Some((member.beach, self.find_crab(member.beach, member.crab).ok()?)))
+            .collect();
+        members.sort_by_key(|&(beach, crab)| (beach, crab.id()));
+        members
+    }
+
+    /**
+     * Like `Beach::get_winner_clan`, but for two ocean clans: the clan whose living members,
+     * across every beach, have the higher average speed wins, and the result is recorded in the
+     * ocean clans' reputation standings. Hibernating members do not count. Returns None for a draw.
+     *
+     * Returns `OceanError::ClanNotFound` if either clan does not exist, `OceanError::ClanEmpty` if
+     * either has no living, awake members, and `OceanError::Overflow` if their speeds are too large to
+     * add up.
+     */
+    pub fn compete_ocean_clans(&mut self, id1: &str, id2: &str) -> Result<Option<String>, OceanError> {
+        let average1 = self.ocean_clan_average_speed(id1)?;
+        let average2 = self.ocean_clan_average_speed(id2)?;
+        let winner = if average1 > average2 {
+            Some(id1.to_string())
+        } else if average2 > average1 {
+            Some(id2.to_string())
+        } else {
+            None
+        };
+        self.clans.record_result(id1, id2, winner.as_deref());
+        Ok(winner)
+    }
+
+    // Plays a scheduled match between two ocean clans with `compete_ocean_clans`, or queues a
+    // `MatchCancelled` event if it cannot be played. Returns true if it was played.
+    pub(crate) fn play_ocean_match(&mut self, id1: &str, id2: &str) -> bool {
+        match self.compete_ocean_clans(id1, id2) {
+            Ok(_) => true,
+            Err(e) => {
+                self.clans.record_cancelled(id1, id2, e.to_string());
+                false
+            }
+        }
+    }
+
+    // The average speed of an ocean clan's living, awake members, rounded down.
+    fn ocean_clan_average_speed(&self, clan_id: &str) -> Result<u64, OceanError> {
+        if !self.clans.has_clan(clan_id) {
+            return Err(OceanError::ClanNotFound(clan_id.to_string()));
+        }
+        let members: Vec<_> = self.ocean_clan_members(clan_id).into_iter().filter(|(_, crab)| !crab.is_hibernating()).collect();
+        let total = members
+            .iter()
+            .try_fold(0u64, |total, (_, crab)| total.checked_add(crab.speed() as u64))
+            .ok_or_else(|| OceanError::Overflow(format!("total speed of clan {}", clan_id)))?;
+        total.checked_div(members.len() as u64).ok_or_else(|| OceanError::ClanEmpty(clan_id.to_string()))
+    }
+
+    // Removes and returns the events queued by the ocean's own clans.
+    pub(crate) fn drain_clan_events(&mut self) -> Vec<OceanEvent> {
+        self.clans.drain_events()
+    }
+
+    // The crab with id `crab` on the beach at index `beach`.
+    fn find_crab(&self, beach: usize, crab: CrabId) -> Result<&Crab, OceanError> {
+        let found = self.beaches.get(beach).ok_or_else(|| OceanError::BeachNotFound(format!("at index {}", beach)))?;
+        let index = found.find_crab_by_id(crab).ok_or(OceanError::CrabIdNotFound(crab))?;
+        Ok(found.get_crab(index))
+    }
+
+    /**
+     * Returns the registry that owns this ocean's reefs, for lookups by id or name.
+     */
+    pub fn reef_registry(&self) -> &ReefRegistry {
+        &self.reefs
+    }
+
+    /**
+     * Returns the registry mutably, e.g. to link reefs for migration.
+     */
+    pub fn reef_registry_mut(&mut self) -> &mut ReefRegistry {
+        &mut self.reefs
+    }
+
     /**
      * Generate a reef with the specified number of each concrete type of prey, and then add it to the ocean.
      *   - Minnows should have a speed of 25.
@@ -59,9 +635,19 @@ impl Ocean {
         for _ in 0..n_algae {
             reef.add_prey(Box::new(Algae::new()));
         }
-        let reef_rc = Rc::new(RefCell::new(reef));
-        self.reefs.push(reef_rc.clone());
-        reef_rc
+        // Anonymous reefs never clash with an existing name.
+        self.add_reef(reef).unwrap()
 
     }
 }
+
+// The reefs discovered by any crab on `beach`, each listed once.
+fn home_reefs(beach: &Beach) -> Vec<Rc<RefCell<Reef>>> {
+    let mut reefs: Vec<Rc<RefCell<Reef>>> = Vec::new();
+    for reef in beach.crabs().flat_map(|crab| crab.reefs()) {
+        if !reefs.iter().any(|known| Rc::ptr_eq(known, &reef)) {
+            reefs.push(reef);
+        }
+    }
+    reefs
+}
@@ -0,0 +1,244 @@
+//! Working with saved beaches too big to load into memory at once.
+//!
+//! Three tools, from lightest to heaviest:
+//!   - `crab_chunks` reads the crabs of a saved beach (see `Beach::save`) in chunks of a chosen
+//!     size, for one pass over them (counting, filtering, exporting) with one chunk in memory at
+//!     a time.
+//!   - `PagedCrabs` holds every crab of a saved beach, but keeps each one serialized in its
+//!     compact saved form until it is touched, and serializes the least recently touched crabs
+//!     again once more than a chosen number are loaded.
+//!   - `Beach::load_from` loads a whole beach, reading it a line at a time rather than needing
+//!     the whole text in memory first.
+//!
+//! Serialized crabs are saved the way `Beach::save` saves them, so a crab paged out loses what
+//! saving loses: its reef handles and its undrained events.
+
+use crate::beach::Beach;
+use crate::crab::Crab;
+use crate::error::OceanError;
+use crate::persist::{join_record, split_record, ParseError};
+use std::collections::VecDeque;
+use std::io::{BufRead, Lines, Write};
+
+/// Reads the crabs of a saved beach in chunks (see `crab_chunks`).
+#[derive(Debug)]
+pub struct CrabChunks<R> {
+    lines: Lines<R>,
+    line_no: usize,
+    chunk_size: usize,
+    failed: bool,
+}
+
+/**
+ * Returns an iterator over the crabs of the beach saved in `reader`, in beach order, in chunks
+ * of `chunk_size` crabs (the last may be smaller; a size of 0 counts as 1). Every other record
+ * of the saved beach is skipped.
+ *
+ * An Err for a line that cannot be read or a crab that cannot be parsed ends the iteration.
+ */
+pub fn crab_chunks<R: BufRead>(reader: R, chunk_size: usize) -> CrabChunks<R> {
+    CrabChunks { lines: reader.lines(), line_no: 0, chunk_size: chunk_size.max(1), failed: false }
+}
+
+impl<R: BufRead> Iterator for CrabChunks<R> {
+    type Item = Result<Vec<Crab>, OceanError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let mut chunk = Vec::new();
+        while chunk.len() < self.chunk_size {
+            let Some(line) = self.lines.next() else {
+                break;
+            };
+            self.line_no += 1;
+            let crab = line.map_err(OceanError::from).and_then(|line| Ok(parse_crab(&line, self.line_no)?));
+            match crab {
+                Ok(Some(crab)) => chunk.push(crab),
+                Ok(None) => {}
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(Ok(chunk))
+        }
+    }
+}
+
+// Parses a line of a saved beach, returning its crab, or None if it is another kind of record.
+fn parse_crab(line: &str, line_no: usize) -> Result<Option<Crab>, ParseError> {
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let fields = split_record(line);
+    if fields[0] != "crab" {
+        return Ok(None);
+    }
+    Crab::from_fields(&fields[1..], line_no).map(Some)
+}
+
+// A crab of a `PagedCrabs`, serialized with `Crab::to_fields` or loaded. Both are boxed so a
+// paged crab takes as little room as possible.
+#[derive(Debug)]
+enum Slot {
+    Paged(Box<str>),
+    Loaded(Box<Crab>),
+}
+
+/// Every crab of a saved beach, each kept serialized until it is touched (see `crate::paging`).
+#[derive(Debug)]
+pub struct PagedCrabs {
+    slots: Vec<Slot>,
+    // Indices of the loaded crabs, least recently touched first.
+    loaded: VecDeque<usize>,
+    max_loaded: Option<usize>,
+}
+
+impl PagedCrabs {
+    /**
+     * Reads the crabs of the beach saved in `reader`, checking each one parses but keeping it
+     * serialized. Every other record of the saved beach is skipped.
+     *
+     * Returns `OceanError::Io` if reading fails, and `OceanError::Parse` if a crab cannot be parsed.
+     */
+    pub fn load<R: BufRead>(reader: R) -> Result<PagedCrabs, OceanError> {
+        let mut slots = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            if let Some(crab) = parse_crab(&line?, i + 1)? {
+                slots.push(Slot::Paged(serialize(&crab)));
+            }
+        }
+        Ok(PagedCrabs { slots, loaded: VecDeque::new(), max_loaded: None })
+    }
+
+    /**
+     * Keeps at most `max` crabs loaded, serializing the least recently touched ones to make room,
+     * or lifts the limit with None. At least the crab being touched is always loaded.
+     */
+    pub fn set_max_loaded(&mut self, max: Option<usize>) {
+        self.max_loaded = max;
+        self.evict(0);
+    }
+
+    pub fn max_loaded(&self) -> Option<usize> {
+        self.max_loaded
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /**
+     * Returns how many crabs are loaded right now.
+     */
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /**
+     * Loads the crab at `index` if it is not loaded yet and returns it, or None if the index is
+     * out of range.
+     */
+    pub fn get(&mut self, index: usize) -> Option<&Crab> {
+        self.touch(index).map(|crab| &*crab)
+    }
+
+    /**
+     * Like `get`, but returns the crab mutably. Changes are kept when it is serialized again.
+     */
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Crab> {
+        self.touch(index)
+    }
+
+    /**
+     * Adds `crab` to the end, loaded.
+     */
+    pub fn push(&mut self, crab: Crab) {
+        self.slots.push(Slot::Loaded(Box::new(crab)));
+        self.loaded.push_back(self.slots.len() - 1);
+        self.evict(1);
+    }
+
+    /**
+     * Writes every crab as the crab records of a saved beach, in order, without loading the
+     * serialized ones.
+     */
+    pub fn save_to<W: Write>(&self, mut writer: W) -> Result<(), OceanError> {
+        for slot in self.slots.iter() {
+            match slot {
+                Slot::Paged(record) => writeln!(writer, "{}", record)?,
+                Slot::Loaded(crab) => writeln!(writer, "{}", serialize(crab))?,
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Loads every crab into a new beach, in order, keeping their saved ids.
+     */
+    pub fn into_beach(self) -> Beach {
+        let mut beach = Beach::new();
+        for slot in self.slots {
+            beach.restore_crab(match slot {
+                Slot::Paged(record) => deserialize(&record),
+                Slot::Loaded(crab) => *crab,
+            });
+        }
+        beach
+    }
+
+    // Loads the crab at `index`, marks it the most recently touched, and returns it.
+    fn touch(&mut self, index: usize) -> Option<&mut Crab> {
+        let slot = self.slots.get_mut(index)?;
+        if let Slot::Paged(record) = slot {
+            *slot = Slot::Loaded(Box::new(deserialize(record)));
+        } else if let Some(position) = self.loaded.iter().position(|&loaded| loaded == index) {
+            self.loaded.remove(position);
+        }
+        self.loaded.push_back(index);
+        self.evict(1);
+        match &mut self.slots[index] {
+            Slot::Loaded(crab) => Some(crab),
+            Slot::Paged(_) => None,
+        }
+    }
+
+    // Serializes the least recently touched crabs until no more than the limit are loaded,
+    // sparing the `keep` most recently touched.
+    fn evict(&mut self, keep: usize) {
+        let Some(max) = self.max_loaded else {
+            return;
+        };
+        while self.loaded.len() > max.max(keep) {
+            let Some(index) = self.loaded.pop_front() else {
+                break;
+            };
+            if let Slot::Loaded(crab) = &self.slots[index] {
+                self.slots[index] = Slot::Paged(serialize(crab));
+            }
+        }
+    }
+}
+
+// Serializes a crab as its saved beach record.
+fn serialize(crab: &Crab) -> Box<str> {
+    let mut fields = vec!["crab".to_string()];
+    fields.extend(crab.to_fields());
+    join_record(&fields).into_boxed_str()
+}
+
+// Reverses `serialize`.
+fn deserialize(record: &str) -> Crab {
+    let fields = split_record(record);
+    Crab::from_fields(&fields[1..], 1).expect("serialized crabs always parse")
+}
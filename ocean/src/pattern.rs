@@ -0,0 +1,74 @@
+use ::rand::Rng;
+
+/// The pattern on a crab's shell, inherited independently of its color.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub enum Pattern {
+    #[default]
+    Plain,
+    Spotted,
+    Striped,
+}
+
+impl Pattern {
+    /**
+     * Returns the pattern of the offspring of crabs with patterns `p1` and `p2`:
+     * parents that share a pattern pass it on, otherwise either parent's pattern is equally likely.
+     */
+    pub fn cross(p1: Pattern, p2: Pattern) -> Pattern {
+        if p1 == p2 {
+            return p1;
+        }
+        #[cfg(feature = "deterministic")]
+        {
+            crate::deterministic::pattern(p1, p2)
+        }
+        #[cfg(not(feature = "deterministic"))]
+        {
+            if crate::rand::rand32().is_multiple_of(2) {
+                p1
+            } else {
+                p2
+            }
+        }
+    }
+
+    /**
+     * Returns how well this pattern hides a crab from predators, from 0.0 (not at all) to 1.0
+     * (see `crate::predator`): plain shells hide nothing, spots a quarter, and stripes half.
+     */
+    pub fn camouflage(self) -> f64 {
+        match self {
+            Pattern::Plain => 0.0,
+            Pattern::Spotted => 0.25,
+            Pattern::Striped => 0.5,
+        }
+    }
+
+    /**
+     * Like `cross`, but draws from `rng` so that runs can be reproduced with a chosen seed.
+     */
+    pub fn cross_with<R: Rng + ?Sized>(p1: Pattern, p2: Pattern, rng: &mut R) -> Pattern {
+        if p1 == p2 || rng.gen_bool(0.5) {
+            p1
+        } else {
+            p2
+        }
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = String;
+
+    /**
+     * Parses a pattern from its variant name, as printed by `{:?}`.
+     */
+    fn from_str(s: &str) -> Result<Pattern, String> {
+        use Pattern::*;
+        match s {
+            "Plain" => Ok(Plain),
+            "Spotted" => Ok(Spotted),
+            "Striped" => Ok(Striped),
+            _ => Err(format!("unknown pattern: {:?}", s)),
+        }
+    }
+}
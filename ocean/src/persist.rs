@@ -0,0 +1,379 @@
+//! A small, dependency-free text format for saving and reloading ocean state.
+//!   - Each record is one line of tab-separated fields, the first of which names the record.
+//!   - Tabs, newlines and backslashes inside a field are escaped with a backslash.
+//!
+//! It also provides the quoting helpers used when exporting to JSON, CSV and Graphviz DOT.
+
+use std::fmt;
+
+/// An error encountered while loading saved state.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    line: usize,
+    message: String,
+}
+
+impl ParseError {
+    pub fn new(line: usize, message: String) -> ParseError {
+        ParseError { line, message }
+    }
+
+    /** The 1-based line number the error was found on. */
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/**
+ * Escapes a single field so it can be embedded in a record.
+ */
+pub fn escape(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/**
+ * Reverses `escape`.
+ */
+pub fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/**
+ * Joins already-formatted fields into a single record line (without the trailing newline).
+ */
+pub fn join_record(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| escape(field))
+        .collect::<Vec<String>>()
+        .join("\t")
+}
+
+/**
+ * Splits a record line back into its unescaped fields.
+ */
+pub fn split_record(line: &str) -> Vec<String> {
+    line.split('\t').map(unescape).collect()
+}
+
+/**
+ * Parses a numeric field, reporting `what` it was on failure.
+ */
+pub fn parse_field<T: std::str::FromStr>(field: &str, what: &str, line: usize) -> Result<T, ParseError> {
+    field
+        .parse()
+        .map_err(|_| ParseError::new(line, format!("invalid {}: {:?}", what, field)))
+}
+
+/**
+ * Quotes `s` as a JSON string literal.
+ */
+pub fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/**
+ * Quotes `s` as a Graphviz DOT string, e.g. for a node label. Line breaks become `\n`, which
+ * DOT draws as centered line breaks.
+ */
+pub fn dot_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/**
+ * Formats `s` as a CSV field, quoting it only if it contains a comma, quote, or line break.
+ */
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// A parsed JSON value, as returned by `parse_json`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /** An object's fields in the order they were written, and the line its `{` was on. */
+    Object { line: usize, fields: Vec<(String, JsonValue)> },
+}
+
+impl JsonValue {
+    /**
+     * Returns the value of the given field if this is an object that has it.
+     */
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object { fields, .. } => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /**
+     * Returns this value as a u32 if it is a whole number in range.
+     */
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            JsonValue::Number(n) if n.fract() == 0.0 && *n >= 0.0 && *n <= u32::MAX as f64 => Some(*n as u32),
+            _ => None,
+        }
+    }
+}
+
+/// How deeply `parse_json` lets arrays and objects nest, so that hostile input cannot exhaust
+/// the stack.
+pub const MAX_JSON_DEPTH: usize = 128;
+
+/**
+ * Parses a complete JSON document. Returns an Err for arrays and objects nested more than
+ * `MAX_JSON_DEPTH` deep.
+ */
+pub fn parse_json(text: &str) -> Result<JsonValue, ParseError> {
+    let mut parser = JsonParser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.error("unexpected text after the JSON value"));
+    }
+    Ok(value)
+}
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    // How many arrays and objects enclose the value being parsed.
+    depth: usize,
+}
+
+impl JsonParser {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::new(self.line, message.to_string())
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(self.error(&format!("expected {:?}", expected))),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, ParseError> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error(&format!("expected {}", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') | Some('[') => {
+                if self.depth == MAX_JSON_DEPTH {
+                    return Err(self.error(&format!("arrays and objects nested more than {} deep", MAX_JSON_DEPTH)));
+                }
+                self.depth += 1;
+                let value = if self.peek() == Some('{') { self.object() } else { self.array() };
+                self.depth -= 1;
+                value
+            }
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('t') => self.keyword("true", JsonValue::Bool(true)),
+            Some('f') => self.keyword("false", JsonValue::Bool(false)),
+            Some('n') => self.keyword("null", JsonValue::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) => Err(self.error("expected a JSON value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, ParseError> {
+        let line = self.line;
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(JsonValue::Object { line, fields });
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a field name"));
+            }
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object { line, fields }),
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(items)),
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('r') => s.push('\r'),
+                    Some('t') => s.push('\t'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex: String = (0..4).filter_map(|_| self.next()).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error("invalid \\u escape"))?;
+                        s.push(c);
+                    }
+                    _ => return Err(self.error("invalid escape in string")),
+                },
+                Some(c) => s.push(c),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, ParseError> {
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.next();
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse()
+            .map(JsonValue::Number)
+            .map_err(|_| self.error(&format!("invalid number {:?}", text)))
+    }
+}
@@ -0,0 +1,71 @@
+//! How a beach's population changes on its own each step (see `Beach::apply_population_policy`).
+//!
+//! Left alone, a beach only gains crabs by breeding and loses them to predators, so an
+//! unattended beach stays frozen. A `PopulationPolicy` adds the rest of the cycle: wild crabs
+//! arrive, crabs die of old age and bad luck, and a carrying capacity keeps the beach from
+//! growing without bound. A `Simulation` applies every beach's policy once a step.
+
+/// The slowest and fastest speeds a wild crab can arrive with.
+pub const WILD_SPEEDS: (u32, u32) = (1, 10);
+
+/// How a beach's population changes each step. The default policy changes nothing.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PopulationPolicy {
+    spawn_rate: f64,
+    mortality: f64,
+    capacity: Option<usize>,
+}
+
+impl PopulationPolicy {
+    pub fn new() -> PopulationPolicy {
+        PopulationPolicy::default()
+    }
+
+    /**
+     * Returns this policy with wild crabs arriving at an average of `rate` per step, e.g. 0.5 for
+     * one every other step. Wild crabs have a random speed, color, and diet, and discover the
+     * reefs of a crab already on the beach, if there is one.
+     */
+    pub fn with_spawn_rate(mut self, rate: f64) -> PopulationPolicy {
+        self.spawn_rate = rate.max(0.0);
+        self
+    }
+
+    /**
+     * Returns this policy with every crab dying of natural causes with chance `mortality` each step.
+     */
+    pub fn with_mortality(mut self, mortality: f64) -> PopulationPolicy {
+        self.mortality = mortality.clamp(0.0, 1.0);
+        self
+    }
+
+    /**
+     * Returns this policy with a carrying capacity: no wild crabs arrive once the beach holds
+     * `capacity` crabs, and crabs chosen at random die until a beach with more is back down to it.
+     */
+    pub fn with_capacity(mut self, capacity: usize) -> PopulationPolicy {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    pub fn spawn_rate(&self) -> f64 {
+        self.spawn_rate
+    }
+
+    pub fn mortality(&self) -> f64 {
+        self.mortality
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+}
+
+/// How a beach's population changed when its policy was applied.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct PopulationChange {
+    /** The number of wild crabs that arrived. */
+    pub spawned: usize,
+    /** The number of crabs that died, of natural causes or overcrowding. */
+    pub died: usize,
+}
@@ -0,0 +1,25 @@
+//! Where crabs and reefs are on a beach.
+//!
+//! Positions are in beach units: `x` runs along the shore and `y` runs out to sea, so the
+//! larger `y` is, the further from land. A beach's tide line (see `Beach::set_tide_line`) is the
+//! `y` at which the sea begins. `Beach::render_ascii` draws one character per unit.
+
+/// A point on a beach.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Position {
+    pub fn new(x: f64, y: f64) -> Position {
+        Position { x, y }
+    }
+
+    /**
+     * Returns the straight-line distance between this position and `other`.
+     */
+    pub fn distance(self, other: Position) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
@@ -0,0 +1,80 @@
+//! Predators that hunt crabs.
+//!   - A `Predator` decides whether it catches a crab, given the crab's speed and camouflage
+//!     (see `Crab::camouflage`).
+//!   - `Seagull` swoops on crabs in the open, and only catches crabs slower than itself.
+//!   - `Octopus` lies in wait, so speed does not help a crab get away from it.
+//!
+//! Predators are attached to a beach with `Beach::add_predator`, and each makes one attempt on
+//! a crab chosen at random whenever the beach faces its predators (see `Beach::face_predators`),
+//! which a `Simulation` does once a step.
+
+use crate::crab::Crab;
+use rand::{Rng, RngCore};
+use std::fmt;
+
+pub trait Predator: fmt::Debug {
+    /**
+     * The kind of predator, e.g. "Seagull", as reported in `OceanEvent::CrabEaten`.
+     */
+    fn kind(&self) -> &'static str;
+
+    /**
+     * Returns true if this predator catches `crab` on this attempt. Any randomness must come
+     * from `rng`.
+     */
+    fn try_catch(&mut self, crab: &Crab, rng: &mut dyn RngCore) -> bool;
+}
+
+/// Swoops on crabs from above. It can only catch crabs slower than itself, and misses
+/// camouflaged crabs more often.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Seagull {
+    pub speed: u32,
+    /** The chance of catching a slower crab with no camouflage, from 0.0 to 1.0. */
+    pub accuracy: f64,
+}
+
+impl Seagull {
+    pub fn new(speed: u32, accuracy: f64) -> Seagull {
+        Seagull { speed, accuracy }
+    }
+}
+
+impl Predator for Seagull {
+    fn kind(&self) -> &'static str {
+        "Seagull"
+    }
+
+    fn try_catch(&mut self, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        if crab.effective_speed() >= self.speed {
+            return false;
+        }
+        let chance = self.accuracy.clamp(0.0, 1.0) * (1.0 - crab.camouflage());
+        rng.gen_bool(chance)
+    }
+}
+
+/// Ambushes crabs from a crevice. No crab is fast enough to escape it once it strikes, but it
+/// strikes at camouflaged crabs less often.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Octopus {
+    /** The chance of catching a crab with no camouflage, from 0.0 to 1.0. */
+    pub grip: f64,
+}
+
+impl Octopus {
+    pub fn new(grip: f64) -> Octopus {
+        Octopus { grip }
+    }
+}
+
+impl Predator for Octopus {
+    fn kind(&self) -> &'static str {
+        "Octopus"
+    }
+
+    fn try_catch(&mut self, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        let chance = self.grip.clamp(0.0, 1.0) * (1.0 - crab.camouflage());
+        rng.gen_bool(chance)
+    }
+}
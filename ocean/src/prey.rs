@@ -1,10 +1,166 @@
 use crate::crab::Crab;
 use crate::diet::Diet;
+use crate::persist::{parse_field, ParseError};
+use rand::RngCore;
+use std::any::Any;
+use std::collections::BTreeMap;
 
-pub trait Prey {
+/// Lets a `Box<dyn Prey>` be downcast back to its concrete type. Implemented for every
+/// `'static` prey, so prey never need to implement it themselves.
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Prey + 'static> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// How big a prey is. Bigger prey are harder to catch but more nutritious.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum Size {
+    Small,
+    Medium,
+    Large,
+}
+
+impl Size {
+    /**
+     * How many times a crab must stop a prey of this size from escaping to catch it.
+     */
+    pub fn escape_attempts(&self) -> u32 {
+        match self {
+            Size::Small => 1,
+            Size::Medium => 2,
+            Size::Large => 3,
+        }
+    }
+
+    /**
+     * The default nutrition of a prey of this size.
+     */
+    pub fn nutrition(&self) -> u32 {
+        match self {
+            Size::Small => 1,
+            Size::Medium => 2,
+            Size::Large => 3,
+        }
+    }
+}
+
+/// What a crab can see of a prey in one of its reefs without taking it out (see
+/// `Crab::visible_prey`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PreyView {
+    /** The index of the prey's reef among the crab's reefs (see `Crab::reefs`). */
+    pub reef: usize,
+    pub kind: &'static str,
+    pub size: Size,
+    pub diet: Diet,
+    pub nutrition: u32,
+}
+
+impl PreyView {
+    pub fn of(reef: usize, prey: &dyn Prey) -> PreyView {
+        PreyView { reef, kind: prey.kind(), size: prey.size(), diet: prey.diet(), nutrition: prey.nutrition() }
+    }
+}
+
+/// What a reef can tell about one of its prey without taking it out (see `Reef::peek_prey`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PreyInfo {
+    pub kind: &'static str,
+    pub size: Size,
+    pub diet: Diet,
+    pub nutrition: u32,
+    /** How many ticks the prey has lived in its reef. */
+    pub age: u32,
+}
+
+/// Tags and key-value metadata attached to one prey, e.g. by a researcher marking prey for a
+/// mark-recapture study. They stay with the prey as it moves between reefs and crabs, but are not
+/// saved by `crate::persist`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PreyTags {
+    tags: Vec<String>,
+    metadata: BTreeMap<String, String>,
+}
+
+impl PreyTags {
+    /**
+     * Adds `tag`, unless it is already there. Returns true if it was added.
+     */
+    pub fn add_tag(&mut self, tag: &str) -> bool {
+        if self.has_tag(tag) {
+            return false;
+        }
+        self.tags.push(tag.to_string());
+        true
+    }
+
+    /**
+     * Removes `tag`, returning true if it was there.
+     */
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|t| t != tag);
+        self.tags.len() != before
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /**
+     * The tags, in the order they were added.
+     */
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /**
+     * Sets the metadata under `key` to `value`, returning the value it replaces, if any.
+     */
+    pub fn set(&mut self, key: &str, value: &str) -> Option<String> {
+        self.metadata.insert(key.to_string(), value.to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.metadata.get(key).map(|value| value.as_str())
+    }
+
+    /**
+     * Removes and returns the metadata under `key`.
+     */
+    pub fn unset(&mut self, key: &str) -> Option<String> {
+        self.metadata.remove(key)
+    }
+
+    /**
+     * The metadata, sorted by key.
+     */
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+}
+
+/// Prey must be `Send` so that reefs can be shared between threads (see `crate::reef::SyncReef`).
+pub trait Prey: AsAny + Send {
     /** What diet does this `Prey` fit into? */
     fn diet(&self) -> Diet;
 
+    /**
+     * Returns a copy of this prey in a new box, so that reefs can be cloned (see `Reef`'s `Clone`
+     * implementation). Prey that are `Clone` can just return `Box::new(self.clone())`.
+     */
+    fn clone_box(&self) -> Box<dyn Prey>;
+
     /**
      * `Prey` are eaten by `Crab`s. This method is called when a crab tries to
      * eat this prey. Return true if the prey gets away, and false if it does not.
@@ -12,22 +168,176 @@ pub trait Prey {
      * See the implementations below for some examples of different behaviors.
      */
     fn try_escape(&mut self, crab: &Crab) -> bool;
+
+    /**
+     * Like `try_escape`, but any randomness must come from `rng`, so that hunts can be reproduced
+     * with a chosen seed. Prey whose escapes are random should override this; the default just
+     * calls `try_escape`.
+     */
+    fn try_escape_with(&mut self, crab: &Crab, rng: &mut dyn RngCore) -> bool {
+        let _ = rng;
+        self.try_escape(crab)
+    }
+
+    /**
+     * How big this prey is. Defaults to `Size::Small`.
+     */
+    fn size(&self) -> Size {
+        Size::Small
+    }
+
+    /**
+     * How many reef ticks this kind of prey lives for, or None if it never dies of old age.
+     * Defaults to None.
+     */
+    fn lifespan(&self) -> Option<u32> {
+        None
+    }
+
+    /**
+     * How much energy a crab gains from eating this prey. Defaults to the nutrition of its size.
+     */
+    fn nutrition(&self) -> u32 {
+        self.size().nutrition()
+    }
+
+    /**
+     * A short name for this kind of prey, used to group prey in reports such as `Reef::census`.
+     * Defaults to the full name of the implementing type.
+     */
+    fn kind(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    /**
+     * Returns the state of this prey (not including its kind) for saving with `crate::persist`,
+     * or None if this type of prey cannot be saved. `load_prey` must be able to rebuild it.
+     */
+    fn to_fields(&self) -> Option<Vec<String>> {
+        None
+    }
+
+    /**
+     * The tags and metadata attached to this prey, or None if this type of prey cannot be tagged.
+     * Defaults to None; every built-in prey can be tagged.
+     */
+    fn tags(&self) -> Option<&PreyTags> {
+        None
+    }
+
+    /**
+     * Like `tags`, but for attaching tags and metadata. Prey that override one should override
+     * both.
+     */
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        None
+    }
+}
+
+impl Clone for Box<dyn Prey> {
+    fn clone(&self) -> Box<dyn Prey> {
+        self.clone_box()
+    }
+}
+
+/**
+ * Rebuilds one of the built-in prey from its kind and the fields returned by `Prey::to_fields`,
+ * reporting errors against `line`.
+ */
+pub fn load_prey(kind: &str, fields: &[String], line: usize) -> Result<Box<dyn Prey>, ParseError> {
+    let expect = |n: usize| {
+        if fields.len() == n {
+            Ok(())
+        } else {
+            Err(ParseError::new(line, format!("expected {} field(s) for {}, found {}", n, kind, fields.len())))
+        }
+    };
+    match kind {
+        "Shrimp" => {
+            expect(1)?;
+            Ok(Box::new(Shrimp::new(parse_field(&fields[0], "shrimp energy", line)?)))
+        }
+        "Minnow" => {
+            expect(1)?;
+            Ok(Box::new(Minnow::new(parse_field(&fields[0], "minnow speed", line)?)))
+        }
+        "Algae" => {
+            expect(0)?;
+            Ok(Box::new(Algae::new()))
+        }
+        "Clam" => {
+            expect(0)?;
+            Ok(Box::new(Clam::new()))
+        }
+        "Plankton" => {
+            expect(1)?;
+            Ok(Box::new(Plankton {
+                drifting: parse_field(&fields[0], "plankton drift", line)?,
+                tags: PreyTags::default(),
+            }))
+        }
+        "Carcass" => {
+            expect(2)?;
+            Ok(Box::new(Carcass {
+                name: fields[0].clone(),
+                nutrition: parse_field(&fields[1], "carcass nutrition", line)?,
+                tags: PreyTags::default(),
+            }))
+        }
+        "Detritus" => {
+            expect(0)?;
+            Ok(Box::new(Detritus::new()))
+        }
+        "Krill" => {
+            expect(1)?;
+            Ok(Box::new(Krill::new(parse_field(&fields[0], "krill speed", line)?)))
+        }
+        _ => Err(ParseError::new(line, format!("unknown prey kind {:?}", kind))),
+    }
+}
+
+impl dyn Prey + '_ {
+    /**
+     * Returns true if this prey is a `T`.
+     */
+    pub fn is<T: Prey + 'static>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    /**
+     * Returns this prey as a `T`, or None if it is some other kind of prey.
+     */
+    pub fn downcast_ref<T: Prey + 'static>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Prey + 'static>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut::<T>()
+    }
+
+    /**
+     * Returns true if this prey can be tagged and has `tag`.
+     */
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().is_some_and(|tags| tags.has_tag(tag))
+    }
 }
 
 impl core::fmt::Debug for dyn Prey {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        write!(f, "Prey")
+        write!(f, "{}", self.kind())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Shrimp {
     energy: u32,
+    tags: PreyTags,
 }
 
 impl Shrimp {
     pub fn new(energy: u32) -> Shrimp {
-        Shrimp { energy }
+        Shrimp { energy, tags: PreyTags::default() }
     }
 }
 
@@ -36,6 +346,18 @@ impl Prey for Shrimp {
         Diet::Shellfish
     }
 
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
     /**
      * Shrimp move in bursts, and can escape from crabs if they have enough energy.
      */
@@ -49,16 +371,33 @@ impl Prey for Shrimp {
             return true;
         }
     }
+
+    fn nutrition(&self) -> u32 {
+        2
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(15)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Shrimp"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(vec![self.energy.to_string()])
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Minnow {
     speed: u32,
+    tags: PreyTags,
 }
 
 impl Minnow {
     pub fn new(speed: u32) -> Minnow {
-        Minnow { speed }
+        Minnow { speed, tags: PreyTags::default() }
     }
 }
 
@@ -67,20 +406,54 @@ impl Prey for Minnow {
         Diet::Fish
     }
 
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
     /**
      * Minnows are fast and tireless, and can always escape from crabs faster than them.
      */
     fn try_escape(&mut self, crab: &Crab) -> bool {
-        self.speed > crab.speed()
+        self.speed > crab.effective_speed()
+    }
+
+    fn size(&self) -> Size {
+        Size::Medium
+    }
+
+    fn nutrition(&self) -> u32 {
+        4
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(25)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Minnow"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(vec![self.speed.to_string()])
     }
 }
 
-#[derive(Debug)]
-pub struct Algae {}
+#[derive(Debug, Clone)]
+pub struct Algae {
+    tags: PreyTags,
+}
 
 impl Algae {
     pub fn new() -> Algae {
-        Algae {}
+        Algae { tags: PreyTags::default() }
     }
 }
 
@@ -89,20 +462,50 @@ impl Prey for Algae {
         Diet::Plants
     }
 
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
     /**
      * Algae can't move. They're plants.
      */
     fn try_escape(&mut self, _crab: &Crab) -> bool {
         return false;
     }
+
+    fn nutrition(&self) -> u32 {
+        1
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(20)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Algae"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(Vec::new())
+    }
 }
 
-#[derive(Debug)]
-pub struct Clam {}
+#[derive(Debug, Clone)]
+pub struct Clam {
+    tags: PreyTags,
+}
 
 impl Clam {
     pub fn new() -> Clam {
-        Clam {}
+        Clam { tags: PreyTags::default() }
     }
 }
 
@@ -111,10 +514,278 @@ impl Prey for Clam {
         Diet::Shellfish
     }
 
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
     /**
      * There is no escape for the clam.
      */
     fn try_escape(&mut self, _crab: &Crab) -> bool {
         return false;
     }
+
+    fn size(&self) -> Size {
+        Size::Large
+    }
+
+    fn nutrition(&self) -> u32 {
+        3
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(60)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Clam"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(Vec::new())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Plankton {
+    drifting: bool,
+    tags: PreyTags,
+}
+
+impl Plankton {
+    pub fn new() -> Plankton {
+        Plankton { drifting: false, tags: PreyTags::default() }
+    }
+}
+
+impl Prey for Plankton {
+    fn diet(&self) -> Diet {
+        Diet::Plants
+    }
+
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
+    /**
+     * Plankton can't swim, but they drift with the current: every other attempt to eat them
+     * finds them carried just out of reach.
+     */
+    fn try_escape(&mut self, _crab: &Crab) -> bool {
+        self.drifting = !self.drifting;
+        return self.drifting;
+    }
+
+    fn nutrition(&self) -> u32 {
+        1
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(5)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Plankton"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(vec![self.drifting.to_string()])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Krill {
+    speed: u32,
+    tags: PreyTags,
+}
+
+impl Krill {
+    pub fn new(speed: u32) -> Krill {
+        Krill { speed, tags: PreyTags::default() }
+    }
+}
+
+impl Prey for Krill {
+    fn diet(&self) -> Diet {
+        Diet::Shellfish
+    }
+
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
+    /**
+     * Krill flick away from any crab that is not faster than them, but each escape tires them out
+     * and slows them down.
+     */
+    fn try_escape(&mut self, crab: &Crab) -> bool {
+        if self.speed >= crab.effective_speed() && self.speed > 0 {
+            self.speed -= 1;
+            return true;
+        }
+        return false;
+    }
+
+    fn nutrition(&self) -> u32 {
+        2
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(10)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Krill"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(vec![self.speed.to_string()])
+    }
+}
+
+/// The remains of a dead crab, eaten by scavengers.
+#[derive(Debug, Clone)]
+pub struct Carcass {
+    name: String,
+    nutrition: u32,
+    tags: PreyTags,
+}
+
+impl Carcass {
+    /**
+     * Returns the carcass of the given crab, which is as nutritious as all the energy it
+     * gathered in its life (and at least 1).
+     */
+    pub fn of(crab: &Crab) -> Carcass {
+        Carcass {
+            name: crab.name().to_string(),
+            nutrition: crab.energy().max(1),
+            tags: PreyTags::default(),
+        }
+    }
+
+    /**
+     * The name of the crab this carcass was.
+     */
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Prey for Carcass {
+    fn diet(&self) -> Diet {
+        Diet::Scavenger
+    }
+
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
+    /**
+     * Carcasses don't go anywhere.
+     */
+    fn try_escape(&mut self, _crab: &Crab) -> bool {
+        return false;
+    }
+
+    fn size(&self) -> Size {
+        Size::Large
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(10)
+    }
+
+    fn nutrition(&self) -> u32 {
+        self.nutrition
+    }
+
+    fn kind(&self) -> &'static str {
+        "Carcass"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(vec![self.name.clone(), self.nutrition.to_string()])
+    }
+}
+
+/// Leftover scraps and decaying matter on the reef floor, eaten by detritivores.
+#[derive(Debug, Clone)]
+pub struct Detritus {
+    tags: PreyTags,
+}
+
+impl Detritus {
+    pub fn new() -> Detritus {
+        Detritus { tags: PreyTags::default() }
+    }
+}
+
+impl Prey for Detritus {
+    fn diet(&self) -> Diet {
+        Diet::Detritivore
+    }
+
+    fn clone_box(&self) -> Box<dyn Prey> {
+        Box::new(self.clone())
+    }
+
+    fn tags(&self) -> Option<&PreyTags> {
+        Some(&self.tags)
+    }
+
+    fn tags_mut(&mut self) -> Option<&mut PreyTags> {
+        Some(&mut self.tags)
+    }
+
+    /**
+     * Detritus just lies there.
+     */
+    fn try_escape(&mut self, _crab: &Crab) -> bool {
+        return false;
+    }
+
+    fn lifespan(&self) -> Option<u32> {
+        Some(30)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Detritus"
+    }
+
+    fn to_fields(&self) -> Option<Vec<String>> {
+        Some(Vec::new())
+    }
 }
@@ -0,0 +1,361 @@
+//! Fluent queries over the crabs on a beach, started with `Beach::query`:
+//!
+//! ```text
+//! let fast_blue_reds = beach
+//!     .query()
+//!     .diet(Diet::Shellfish)
+//!     .min_speed(5)
+//!     .color(Color::new_blue())
+//!     .in_clan("reds")
+//!     .collect();
+//! ```
+//!
+//! Every filter narrows the query further, and results are in beach order.
+//!
+//! `Ocean::find` searches the whole ocean at once, every beach for crabs and every registered
+//! reef for prey, with an `OceanQuery`:
+//!
+//! ```text
+//! let found = ocean.find(&OceanQuery::new().name_like("ferr", NameSearch::new().with_mode(NameMatch::Prefix)).min_speed(5));
+//! let clams = ocean.find(&OceanQuery::new().prey_kind("Clam"));
+//! ```
+
+use crate::beach::Beach;
+use crate::color::Color;
+use crate::crab::{Crab, CrabId};
+use crate::diet::Diet;
+use crate::pattern::Pattern;
+use crate::prey::Prey;
+use crate::registry::ReefId;
+use std::fmt;
+
+type Filter<'a> = Box<dyn Fn(&Crab) -> bool + 'a>;
+
+/// A query over the crabs on one beach. Build it with `Beach::query`, narrow it with filters, and
+/// finish it with `collect`, `count`, `first` or `indices`.
+pub struct CrabQuery<'a> {
+    beach: &'a Beach,
+    filters: Vec<Filter<'a>>,
+}
+
+impl fmt::Debug for CrabQuery<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CrabQuery").field("filters", &self.filters.len()).finish()
+    }
+}
+
+impl<'a> CrabQuery<'a> {
+    /**
+     * Starts a query that matches every crab on `beach`.
+     */
+    pub fn new(beach: &'a Beach) -> CrabQuery<'a> {
+        CrabQuery { beach, filters: Vec::new() }
+    }
+
+    /**
+     * Keeps only crabs for which `filter` returns true.
+     */
+    pub fn matching<F: Fn(&Crab) -> bool + 'a>(mut self, filter: F) -> CrabQuery<'a> {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn name(self, name: &'a str) -> CrabQuery<'a> {
+        self.matching(move |crab| crab.name() == name)
+    }
+
+    /**
+     * Keeps only crabs whose names match `text` under `search`, e.g. case-insensitively.
+     */
+    pub fn name_like(self, text: &'a str, search: NameSearch) -> CrabQuery<'a> {
+        self.matching(move |crab| search.matches(text, crab.name()))
+    }
+
+    pub fn diet(self, diet: Diet) -> CrabQuery<'a> {
+        self.matching(move |crab| crab.diet() == diet)
+    }
+
+    /**
+     * Keeps only crabs with the given color, as expressed (see `Crab::color`).
+     */
+    pub fn color(self, color: Color) -> CrabQuery<'a> {
+        self.matching(move |crab| *crab.color() == color)
+    }
+
+    pub fn pattern(self, pattern: Pattern) -> CrabQuery<'a> {
+        self.matching(move |crab| crab.pattern() == pattern)
+    }
+
+    /**
+     * Keeps only crabs at least as fast as `speed`.
+     */
+    pub fn min_speed(self, speed: u32) -> CrabQuery<'a> {
+        self.matching(move |crab| crab.speed() >= speed)
+    }
+
+    /**
+     * Keeps only crabs no faster than `speed`.
+     */
+    pub fn max_speed(self, speed: u32) -> CrabQuery<'a> {
+        self.matching(move |crab| crab.speed() <= speed)
+    }
+
+    /**
+     * Keeps only members of the clan with the given id.
+     */
+    pub fn in_clan(self, clan_id: &'a str) -> CrabQuery<'a> {
+        let beach = self.beach;
+        self.matching(move |crab| beach.get_clan_of_crab(crab.name()) == Some(clan_id))
+    }
+
+    /**
+     * Keeps only crabs that belong to no clan.
+     */
+    pub fn without_clan(self) -> CrabQuery<'a> {
+        let beach = self.beach;
+        self.matching(move |crab| beach.get_clan_of_crab(crab.name()).is_none())
+    }
+
+    fn matches(&self, crab: &Crab) -> bool {
+        self.filters.iter().all(|filter| filter(crab))
+    }
+
+    /**
+     * Returns every matching crab, in beach order.
+     */
+    pub fn collect(&self) -> Vec<&'a Crab> {
+        self.beach.crabs().filter(|crab| self.matches(crab)).collect()
+    }
+
+    /**
+     * Returns the beach indices of every matching crab, in order.
+     */
+    pub fn indices(&self) -> Vec<usize> {
+        self.beach
+            .crabs()
+            .enumerate()
+            .filter(|(_, crab)| self.matches(crab))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.beach.crabs().filter(|crab| self.matches(crab)).count()
+    }
+
+    /**
+     * Returns the first matching crab in beach order, or None if no crab matches.
+     */
+    pub fn first(&self) -> Option<&'a Crab> {
+        self.beach.crabs().find(|crab| self.matches(crab))
+    }
+}
+
+/// A search across every beach and reef of an ocean (see `Ocean::find`). An empty query finds
+/// every crab and every prey. Filters on something only crabs have (a name or a speed) leave out
+/// prey, and a prey kind filter leaves out crabs.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct OceanQuery {
+    name: Option<(String, NameSearch)>,
+    diet: Option<Diet>,
+    min_speed: Option<u32>,
+    max_speed: Option<u32>,
+    prey_kind: Option<String>,
+}
+
+/// Something `Ocean::find` found, with where it is.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Found {
+    /** A crab, by the index of its beach in the ocean and its id there. */
+    Crab { beach: usize, id: CrabId },
+    /** A prey, by the id of its reef and its position in `Reef::prey`. */
+    Prey { reef: ReefId, index: usize, kind: &'static str, diet: Diet },
+}
+
+impl OceanQuery {
+    pub fn new() -> OceanQuery {
+        OceanQuery::default()
+    }
+
+    /**
+     * Keeps only crabs whose names match `text` under `search` (see `CrabQuery::name_like`).
+     */
+    pub fn name_like(mut self, text: &str, search: NameSearch) -> OceanQuery {
+        self.name = Some((text.to_string(), search));
+        self
+    }
+
+    /**
+     * Keeps only crabs and prey with the given diet.
+     */
+    pub fn diet(mut self, diet: Diet) -> OceanQuery {
+        self.diet = Some(diet);
+        self
+    }
+
+    /**
+     * Keeps only crabs at least as fast as `speed`.
+     */
+    pub fn min_speed(mut self, speed: u32) -> OceanQuery {
+        self.min_speed = Some(speed);
+        self
+    }
+
+    /**
+     * Keeps only crabs no faster than `speed`.
+     */
+    pub fn max_speed(mut self, speed: u32) -> OceanQuery {
+        self.max_speed = Some(speed);
+        self
+    }
+
+    /**
+     * Keeps only prey of the given kind (see `Prey::kind`).
+     */
+    pub fn prey_kind(mut self, kind: &str) -> OceanQuery {
+        self.prey_kind = Some(kind.to_string());
+        self
+    }
+
+    /**
+     * Returns true if `crab` matches every filter of the query.
+     */
+    pub fn matches_crab(&self, crab: &Crab) -> bool {
+        self.prey_kind.is_none()
+            && self.name.as_ref().is_none_or(|(text, search)| search.matches(text, crab.name()))
+            && self.diet.is_none_or(|diet| crab.diet() == diet)
+            && self.min_speed.is_none_or(|speed| crab.speed() >= speed)
+            && self.max_speed.is_none_or(|speed| crab.speed() <= speed)
+    }
+
+    /**
+     * Returns true if `prey` matches every filter of the query.
+     */
+    pub fn matches_prey(&self, prey: &dyn Prey) -> bool {
+        self.name.is_none()
+            && self.min_speed.is_none()
+            && self.max_speed.is_none()
+            && self.diet.is_none_or(|diet| prey.diet() == diet)
+            && self.prey_kind.as_ref().is_none_or(|kind| prey.kind() == kind)
+    }
+}
+
+/// How `Beach::find_crabs_by_name_with` and `CrabQuery::name_like` compare names. By default, a
+/// name must equal the search text exactly, as with `Beach::find_crabs_by_name`.
+///
+/// Names are always compared in canonical form, so a name typed with a combining accent (`e`
+/// followed by U+0301) matches the same name with a precomposed one (`é`). Only the accented
+/// letters of Latin-1 and Latin Extended-A are recognized; other characters compare as they are.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct NameSearch {
+    ignore_case: bool,
+    mode: NameMatch,
+}
+
+/// Which part of a name the search text must match (see `NameSearch`).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum NameMatch {
+    /** The whole name. */
+    #[default]
+    Exact,
+    /** The start of the name. */
+    Prefix,
+    /** Any part of the name. */
+    Substring,
+}
+
+impl NameSearch {
+    pub fn new() -> NameSearch {
+        NameSearch::default()
+    }
+
+    /**
+     * Returns this search with upper and lower case treated alike, so "ferris" finds "Ferris".
+     */
+    pub fn ignore_case(mut self) -> NameSearch {
+        self.ignore_case = true;
+        self
+    }
+
+    pub fn with_mode(mut self, mode: NameMatch) -> NameSearch {
+        self.mode = mode;
+        self
+    }
+
+    /**
+     * Returns true if `name` matches the search text `text`.
+     */
+    pub fn matches(&self, text: &str, name: &str) -> bool {
+        let (text, name) = (self.normalize(text), self.normalize(name));
+        match self.mode {
+            NameMatch::Exact => name == text,
+            NameMatch::Prefix => name.starts_with(&text),
+            NameMatch::Substring => name.contains(&text),
+        }
+    }
+
+    // Decomposes accented letters into a base letter and a combining mark, then lowers the case
+    // if the search ignores it.
+    fn normalize(&self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            match DECOMPOSITIONS.binary_search_by_key(&c, |&(composed, _, _)| composed) {
+                Ok(i) => {
+                    out.push(DECOMPOSITIONS[i].1);
+                    out.push(DECOMPOSITIONS[i].2);
+                }
+                Err(_) => out.push(c),
+            }
+        }
+        if self.ignore_case {
+            out.to_lowercase()
+        } else {
+            out
+        }
+    }
+}
+
+// The canonical decompositions of the accented letters from U+00C0 to U+017F, by letter.
+const DECOMPOSITIONS: [(char, char, char); 161] = [
+    ('À', 'A', '\u{300}'), ('Á', 'A', '\u{301}'), ('Â', 'A', '\u{302}'), ('Ã', 'A', '\u{303}'),
+    ('Ä', 'A', '\u{308}'), ('Å', 'A', '\u{30a}'), ('Ç', 'C', '\u{327}'), ('È', 'E', '\u{300}'),
+    ('É', 'E', '\u{301}'), ('Ê', 'E', '\u{302}'), ('Ë', 'E', '\u{308}'), ('Ì', 'I', '\u{300}'),
+    ('Í', 'I', '\u{301}'), ('Î', 'I', '\u{302}'), ('Ï', 'I', '\u{308}'), ('Ñ', 'N', '\u{303}'),
+    ('Ò', 'O', '\u{300}'), ('Ó', 'O', '\u{301}'), ('Ô', 'O', '\u{302}'), ('Õ', 'O', '\u{303}'),
+    ('Ö', 'O', '\u{308}'), ('Ù', 'U', '\u{300}'), ('Ú', 'U', '\u{301}'), ('Û', 'U', '\u{302}'),
+    ('Ü', 'U', '\u{308}'), ('Ý', 'Y', '\u{301}'), ('à', 'a', '\u{300}'), ('á', 'a', '\u{301}'),
+    ('â', 'a', '\u{302}'), ('ã', 'a', '\u{303}'), ('ä', 'a', '\u{308}'), ('å', 'a', '\u{30a}'),
+    ('ç', 'c', '\u{327}'), ('è', 'e', '\u{300}'), ('é', 'e', '\u{301}'), ('ê', 'e', '\u{302}'),
+    ('ë', 'e', '\u{308}'), ('ì', 'i', '\u{300}'), ('í', 'i', '\u{301}'), ('î', 'i', '\u{302}'),
+    ('ï', 'i', '\u{308}'), ('ñ', 'n', '\u{303}'), ('ò', 'o', '\u{300}'), ('ó', 'o', '\u{301}'),
+    ('ô', 'o', '\u{302}'), ('õ', 'o', '\u{303}'), ('ö', 'o', '\u{308}'), ('ù', 'u', '\u{300}'),
+    ('ú', 'u', '\u{301}'), ('û', 'u', '\u{302}'), ('ü', 'u', '\u{308}'), ('ý', 'y', '\u{301}'),
+    ('ÿ', 'y', '\u{308}'), ('Ā', 'A', '\u{304}'), ('ā', 'a', '\u{304}'), ('Ă', 'A', '\u{306}'),
+    ('ă', 'a', '\u{306}'), ('Ą', 'A', '\u{328}'), ('ą', 'a', '\u{328}'), ('Ć', 'C', '\u{301}'),
+    ('ć', 'c', '\u{301}'), ('Ĉ', 'C', '\u{302}'), ('ĉ', 'c', '\u{302}'), ('Ċ', 'C', '\u{307}'),
+    ('ċ', 'c', '\u{307}'), ('Č', 'C', '\u{30c}'), ('č', 'c', '\u{30c}'), ('Ď', 'D', '\u{30c}'),
+    ('ď', 'd', '\u{30c}'), ('Ē', 'E', '\u{304}'), ('ē', 'e', '\u{304}'), ('Ĕ', 'E', '\u{306}'),
+    ('ĕ', 'e', '\u{306}'), ('Ė', 'E', '\u{307}'), ('ė', 'e', '\u{307}'), ('Ę', 'E', '\u{328}'),
+    ('ę', 'e', '\u{328}'), ('Ě', 'E', '\u{30c}'), ('ě', 'e', '\u{30c}'), ('Ĝ', 'G', '\u{302}'),
+    ('ĝ', 'g', '\u{302}'), ('Ğ', 'G', '\u{306}'), ('ğ', 'g', '\u{306}'), ('Ġ', 'G', '\u{307}'),
+    ('ġ', 'g', '\u{307}'), ('Ģ', 'G', '\u{327}'), ('ģ', 'g', '\u{327}'), ('Ĥ', 'H', '\u{302}'),
+    ('ĥ', 'h', '\u{302}'), ('Ĩ', 'I', '\u{303}'), ('ĩ', 'i', '\u{303}'), ('Ī', 'I', '\u{304}'),
+    ('ī', 'i', '\u{304}'), ('Ĭ', 'I', '\u{306}'), ('ĭ', 'i', '\u{306}'), ('Į', 'I', '\u{328}'),
+    ('į', 'i', '\u{328}'), ('İ', 'I', '\u{307}'), ('Ĵ', 'J', '\u{302}'), ('ĵ', 'j', '\u{302}'),
+    ('Ķ', 'K', '\u{327}'), ('ķ', 'k', '\u{327}'), ('Ĺ', 'L', '\u{301}'), ('ĺ', 'l', '\u{301}'),
+    ('Ļ', 'L', '\u{327}'), ('ļ', 'l', '\u{327}'), ('Ľ', 'L', '\u{30c}'), ('ľ', 'l', '\u{30c}'),
+    ('Ń', 'N', '\u{301}'), ('ń', 'n', '\u{301}'), ('Ņ', 'N', '\u{327}'), ('ņ', 'n', '\u{327}'),
+    ('Ň', 'N', '\u{30c}'), ('ň', 'n', '\u{30c}'), ('Ō', 'O', '\u{304}'), ('ō', 'o', '\u{304}'),
+    ('Ŏ', 'O', '\u{306}'), ('ŏ', 'o', '\u{306}'), ('Ő', 'O', '\u{30b}'), ('ő', 'o', '\u{30b}'),
+    ('Ŕ', 'R', '\u{301}'), ('ŕ', 'r', '\u{301}'), ('Ŗ', 'R', '\u{327}'), ('ŗ', 'r', '\u{327}'),
+    ('Ř', 'R', '\u{30c}'), ('ř', 'r', '\u{30c}'), ('Ś', 'S', '\u{301}'), ('ś', 's', '\u{301}'),
+    ('Ŝ', 'S', '\u{302}'), ('ŝ', 's', '\u{302}'), ('Ş', 'S', '\u{327}'), ('ş', 's', '\u{327}'),
+    ('Š', 'S', '\u{30c}'), ('š', 's', '\u{30c}'), ('Ţ', 'T', '\u{327}'), ('ţ', 't', '\u{327}'),
+    ('Ť', 'T', '\u{30c}'), ('ť', 't', '\u{30c}'), ('Ũ', 'U', '\u{303}'), ('ũ', 'u', '\u{303}'),
+    ('Ū', 'U', '\u{304}'), ('ū', 'u', '\u{304}'), ('Ŭ', 'U', '\u{306}'), ('ŭ', 'u', '\u{306}'),
+    ('Ů', 'U', '\u{30a}'), ('ů', 'u', '\u{30a}'), ('Ű', 'U', '\u{30b}'), ('ű', 'u', '\u{30b}'),
+    ('Ų', 'U', '\u{328}'), ('ų', 'u', '\u{328}'), ('Ŵ', 'W', '\u{302}'), ('ŵ', 'w', '\u{302}'),
+    ('Ŷ', 'Y', '\u{302}'), ('ŷ', 'y', '\u{302}'), ('Ÿ', 'Y', '\u{308}'), ('Ź', 'Z', '\u{301}'),
+    ('ź', 'z', '\u{301}'), ('Ż', 'Z', '\u{307}'), ('ż', 'z', '\u{307}'), ('Ž', 'Z', '\u{30c}'),
+    ('ž', 'z', '\u{30c}'),
+];
@@ -1,12 +1,196 @@
-use crate::prey::Prey;
+use crate::diet::Diet;
+use crate::error::OceanError;
+use crate::events::{self, OceanEvent};
+use crate::hooks::{Phase, ReefHook};
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::position::Position;
+use crate::prey::{load_prey, Prey, PreyInfo};
+use crate::registry::ReefId;
+use crate::rng::OceanRng;
+use crate::trace;
+use ::rand::{Rng, RngCore};
 
 // VecDeque is Rust's implementation of a double-ended queue, and
 // is used only if we only need to use it in a single-ended manner.
-use std::collections::vec_deque::{Iter, VecDeque};
+use std::collections::VecDeque;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
 
+/// A reef's prey and settings. Cloning a reef deep-clones its prey (see `Prey::clone_box`) for
+/// what-if branches of a simulation; the clone has no depletion watch or hooks.
 #[derive(Debug)]
 pub struct Reef {
-    prey: VecDeque<Box<dyn Prey>>
+    id: Option<ReefId>,
+    name: Option<String>,
+    prey: VecDeque<Resident>,
+    capacity: Option<usize>,
+    escape_chance: Option<f64>,
+    regen_policy: RegenPolicy,
+    take_policy: TakePolicy,
+    // Net prey taken during the current tick, and during each of the last `HEALTH_WINDOW` ticks.
+    taken_this_tick: usize,
+    extraction_history: VecDeque<usize>,
+    depletion_watch: Option<DepletionWatch>,
+    hooks: Vec<Box<dyn ReefHook>>,
+    position: Option<Position>,
+    // The clan holding the reef, and the nutrition crabs from other clans have paid it.
+    owner: Option<String>,
+    tribute: u32,
+    events: Vec<OceanEvent>
+}
+
+// A prey living in the reef, and how many ticks it has lived there.
+#[derive(Debug, Clone)]
+struct Resident {
+    prey: Box<dyn Prey>,
+    age: u32,
+}
+
+impl Resident {
+    fn info(&self) -> PreyInfo {
+        let prey = self.prey.as_ref();
+        PreyInfo { kind: prey.kind(), size: prey.size(), diet: prey.diet(), nutrition: prey.nutrition(), age: self.age }
+    }
+}
+
+/// The percentage of each catch's nutrition a crab pays as tribute when it hunts in a reef held by
+/// a clan it does not belong to (see `Reef::owner`).
+pub const TRIBUTE_PERCENT: u32 = 50;
+
+/// The number of recent ticks `Reef::extraction_rate` and `Reef::health` look back over.
+pub const HEALTH_WINDOW: usize = 10;
+
+/// The most prey a single `RegenPolicy` rule spawns per tick, however high its rate is set or
+/// scaled (see `Reef::tick_scaled`).
+pub const MAX_REGEN_RATE: f64 = 10_000.0;
+
+/// Called with the reef when its health falls below a watch's threshold.
+pub type DepletionCallback = Box<dyn FnMut(&Reef) + Send>;
+
+// A registered depletion callback, and whether the reef was below its threshold when last checked.
+struct DepletionWatch {
+    threshold: f64,
+    callback: DepletionCallback,
+    below: bool,
+}
+
+impl fmt::Debug for DepletionWatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DepletionWatch")
+            .field("threshold", &self.threshold)
+            .field("below", &self.below)
+            .finish()
+    }
+}
+
+/// Which prey `Reef::take_prey` hands out next.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum TakePolicy {
+    /** The prey that has been in the reef the longest. */
+    #[default]
+    Fifo,
+    /** The prey that was added most recently. */
+    Lifo,
+    /** Any prey, chosen with the crate's deterministic random number generator. */
+    Random,
+    /** The smallest prey, oldest first among prey of the same size. */
+    SmallestFirst,
+}
+
+impl std::str::FromStr for TakePolicy {
+    type Err = String;
+
+    /**
+     * Parses a take policy from its variant name, as printed by `{:?}`.
+     */
+    fn from_str(s: &str) -> Result<TakePolicy, String> {
+        match s {
+            "Fifo" => Ok(TakePolicy::Fifo),
+            "Lifo" => Ok(TakePolicy::Lifo),
+            "Random" => Ok(TakePolicy::Random),
+            "SmallestFirst" => Ok(TakePolicy::SmallestFirst),
+            _ => Err(format!("unknown take policy: {:?}", s)),
+        }
+    }
+}
+
+/// Creates a new prey each time it is called.
+pub type PreySpawner = Arc<dyn Fn() -> Box<dyn Prey> + Send + Sync>;
+
+/// How a reef regrows its prey on each `Reef::tick`.
+///
+/// Each rule gives an expected number of new prey per tick and a function that spawns one.
+/// A rate of 2.5 spawns two prey every tick and a third with probability 0.5.
+///
+/// A saved reef (see `Reef::save`) keeps each rule as its rate and the prey its function spawned
+/// when it was saved, so a loaded reef spawns copies of that prey.
+#[derive(Clone)]
+pub struct RegenPolicy {
+    rules: Vec<(f64, PreySpawner)>,
+}
+
+impl RegenPolicy {
+    /**
+     * Creates a policy that spawns nothing.
+     */
+    pub fn new() -> RegenPolicy {
+        RegenPolicy { rules: Vec::new() }
+    }
+
+    /**
+     * Adds a rule spawning `per_tick` prey on average per tick, each created by `spawn`.
+     * Negative and NaN rates are treated as zero, and rates over `MAX_REGEN_RATE` as that.
+     */
+    pub fn with_rate<F>(mut self, per_tick: f64, spawn: F) -> RegenPolicy
+    where
+        F: Fn() -> Box<dyn Prey> + Send + Sync + 'static,
+    {
+        self.rules.push((clamp_rate(per_tick), Arc::new(spawn)));
+        self
+    }
+}
+
+impl fmt::Debug for RegenPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rates: Vec<f64> = self.rules.iter().map(|(rate, _)| *rate).collect();
+        f.debug_struct("RegenPolicy").field("rates", &rates).finish()
+    }
+}
+
+/// The error returned when adding prey to a reef that is already at capacity.
+/// Ownership of the rejected prey is handed back to the caller.
+#[derive(Debug)]
+pub struct ReefFull(pub Box<dyn Prey>);
+
+impl fmt::Display for ReefFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the reef is at capacity")
+    }
+}
+
+impl Clone for Reef {
+    fn clone(&self) -> Reef {
+        Reef {
+            id: self.id,
+            name: self.name.clone(),
+            prey: self.prey.clone(),
+            capacity: self.capacity,
+            escape_chance: self.escape_chance,
+            regen_policy: self.regen_policy.clone(),
+            take_policy: self.take_policy,
+            taken_this_tick: self.taken_this_tick,
+            extraction_history: self.extraction_history.clone(),
+            // Callbacks and hooks cannot be cloned, and the clone should not report to the
+            // original's watcher.
+            depletion_watch: None,
+            hooks: Vec::new(),
+            position: self.position,
+            owner: self.owner.clone(),
+            tribute: self.tribute,
+            events: self.events.clone(),
+        }
+    }
 }
 
 impl Reef {
@@ -15,37 +199,721 @@ impl Reef {
      */
     pub fn new() -> Self {
         Reef{
-            prey: VecDeque::new()
+            id: None,
+            name: None,
+            prey: VecDeque::new(),
+            capacity: None,
+            escape_chance: None,
+            regen_policy: RegenPolicy::new(),
+            take_policy: TakePolicy::Fifo,
+            taken_this_tick: 0,
+            extraction_history: VecDeque::new(),
+            depletion_watch: None,
+            hooks: Vec::new(),
+            position: None,
+            owner: None,
+            tribute: 0,
+            events: Vec::new()
+        }
+    }
+
+    /**
+     * Creates a new reef with a human-readable name.
+     */
+    pub fn with_name(name: String) -> Self {
+        Reef {
+            name: Some(name),
+            ..Reef::new()
+        }
+    }
+
+    /**
+     * Returns where the reef is (see `crate::position`), or None if it has not been placed.
+     */
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn set_position(&mut self, position: Position) {
+        self.position = Some(position);
+    }
+
+    /**
+     * Returns the reef's name, or None if it is anonymous.
+     */
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /**
+     * Returns the id the reef was given when it was added to a `ReefRegistry`,
+     * or None if it has not been registered.
+     */
+    pub fn id(&self) -> Option<ReefId> {
+        self.id
+    }
+
+    pub(crate) fn set_id(&mut self, id: ReefId) {
+        self.id = Some(id);
+    }
+
+    /**
+     * Returns the id of the clan holding the reef, or None if no clan holds it. Crabs from other
+     * clans pay `TRIBUTE_PERCENT` of the nutrition of what they catch here to the reef's holder.
+     * `Ocean::claim_reef` and `Ocean::raid` keep this in step with the clans' territory.
+     */
+    pub fn owner(&self) -> Option<&str> {
+        self.owner.as_deref()
+    }
+
+    pub fn set_owner(&mut self, owner: Option<String>) {
+        self.owner = owner;
+    }
+
+    /**
+     * Returns the nutrition paid as tribute to the reef's holder that has not been collected.
+     */
+    pub fn tribute(&self) -> u32 {
+        self.tribute
+    }
+
+    /**
+     * Takes the tribute paid to the reef's holder, leaving none.
+     */
+    pub fn collect_tribute(&mut self) -> u32 {
+        std::mem::take(&mut self.tribute)
+    }
+
+    // Takes the tribute owed on a catch of the given nutrition by a crab of the given clan, and
+    // returns the nutrition the crab keeps.
+    pub(crate) fn levy(&mut self, clan: Option<&str>, nutrition: u32) -> u32 {
+        if self.owner.is_none() || self.owner.as_deref() == clan {
+            return nutrition;
         }
+        let tribute = (nutrition as u64 * TRIBUTE_PERCENT as u64 / 100) as u32;
+        self.tribute = self.tribute.saturating_add(tribute);
+        nutrition - tribute
+    }
+
+    pub fn prey(&self) -> impl Iterator<Item = &Box<dyn Prey>> {
+        return self.prey.iter().map(|resident| &resident.prey);
     }
 
-    pub fn prey(&self) -> Iter<Box<dyn Prey>> {
-        return self.prey.iter(); 
+    /**
+     * Like `prey`, but also returns how many ticks each prey has lived in this reef.
+     */
+    pub fn prey_with_ages(&self) -> impl Iterator<Item = (&Box<dyn Prey>, u32)> {
+        self.prey.iter().map(|resident| (&resident.prey, resident.age))
+    }
+
+    /**
+     * Describes the prey at `index` in the reef's queue (the next one `TakePolicy::Fifo` hands
+     * out is at 0), without taking it out, or returns None if there is no prey at `index`.
+     */
+    pub fn peek_prey(&self, index: usize) -> Option<PreyInfo> {
+        self.prey.get(index).map(Resident::info)
+    }
+
+    /**
+     * Describes every prey in the reef, in queue order, without taking any of them out, e.g. so a
+     * monitor can watch a reef's contents from step to step.
+     */
+    pub fn iter_prey(&self) -> impl Iterator<Item = PreyInfo> + '_ {
+        self.prey.iter().map(Resident::info)
     }
 
     pub fn population(&self) -> usize {
         return self.prey.len();
     }
 
+    /**
+     * Counts the prey in the reef by kind (see `Prey::kind`), without removing any of them.
+     */
+    pub fn census(&self) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for prey in self.prey() {
+            *counts.entry(prey.kind().to_string()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /**
+     * Returns the Shannon diversity index of the reef's prey kinds, `-sum(p * ln p)` over the
+     * share `p` of the population each kind makes up. It is 0.0 for an empty reef or one with a
+     * single kind, and grows with both the number of kinds and how evenly they are spread, up to
+     * `ln k` for `k` equally common kinds.
+     */
+    pub fn biodiversity(&self) -> f64 {
+        shannon_index(self.census().values().copied())
+    }
+
+    /**
+     * Returns the Gini-Simpson diversity index of the reef's prey kinds, `1 - sum(p * p)`: the
+     * chance that two prey picked at random, with replacement, are of different kinds. It is 0.0
+     * for an empty reef or one with a single kind, and stays below 1.0.
+     */
+    pub fn simpson_diversity(&self) -> f64 {
+        let total = self.prey.len() as f64;
+        if total == 0.0 {
+            return 0.0;
+        }
+        1.0 - self.census().values().map(|&count| (count as f64 / total).powi(2)).sum::<f64>()
+    }
+
+    /**
+     * Returns the maximum population of the reef, or None if it is unbounded.
+     */
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /**
+     * Sets the maximum population of the reef (None for unbounded). Prey already living
+     * in the reef are kept even if there are more of them than the new capacity.
+     */
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+    }
+
+    /**
+     * Returns the chance that prey from this reef escape a crab on each attempt, or None if
+     * each prey decides for itself (see `Prey::try_escape`).
+     */
+    pub fn escape_chance(&self) -> Option<f64> {
+        self.escape_chance
+    }
+
+    /**
+     * Overrides how prey from this reef escape: with `Some(chance)`, every escape attempt
+     * succeeds with that chance, less the crab's escape resistance, as with a `ChanceEscape`
+     * with no modifiers. None goes back to letting each prey decide.
+     */
+    pub fn set_escape_chance(&mut self, chance: Option<f64>) {
+        self.escape_chance = chance.map(|chance| chance.clamp(0.0, 1.0));
+    }
+
+    /**
+     * Returns true if no more prey can be added to the reef.
+     */
+    pub fn is_full(&self) -> bool {
+        self.capacity.is_some_and(|capacity| self.prey.len() >= capacity)
+    }
+
     /**
      * Adds a prey to the reef.
      *
-     * This function takes ownership of the boxed prey.
+     * This function takes ownership of the boxed prey. Returns false, dropping the prey,
+     * if the reef is full; use `try_add_prey` to get the prey back instead.
      */
-    pub fn add_prey(&mut self, prey: Box<dyn Prey>) {
-       self.prey.push_back(prey);
+    pub fn add_prey(&mut self, prey: Box<dyn Prey>) -> bool {
+        self.try_add_prey(prey).is_ok()
     }
 
     /**
-     * Returns the next available prey.
+     * Adds a prey to the reef, or returns it inside `Err(ReefFull)` if the reef is full.
+     */
+    pub fn try_add_prey(&mut self, prey: Box<dyn Prey>) -> Result<(), ReefFull> {
+        self.try_add_prey_aged(prey, 0)
+    }
+
+    fn try_add_prey_aged(&mut self, prey: Box<dyn Prey>, age: u32) -> Result<(), ReefFull> {
+        if self.is_full() {
+            return Err(ReefFull(prey));
+        }
+        self.prey.push_back(Resident { prey, age });
+        Ok(())
+    }
+
+    pub fn take_policy(&self) -> TakePolicy {
+        self.take_policy
+    }
+
+    /**
+     * Sets which prey `take_prey` returns next.
+     */
+    pub fn set_take_policy(&mut self, policy: TakePolicy) {
+        self.take_policy = policy;
+    }
+
+    /**
+     * Returns the next available prey, as chosen by the reef's take policy.
      *
      * The callee of this function receives ownership of the boxed prey.
      */
     pub fn take_prey(&mut self) -> Option<Box<dyn Prey>> {
         if self.prey.is_empty() {
-            None
+            return None;
+        }
+        let index = match self.take_policy {
+            TakePolicy::Fifo => 0,
+            TakePolicy::Lifo => self.prey.len() - 1,
+            TakePolicy::Random => random_index(self.prey.len()),
+            TakePolicy::SmallestFirst => (0..self.prey.len()).min_by_key(|&i| self.prey[i].prey.size())?,
+        };
+        self.remove_prey_at(index).map(|(prey, _)| prey)
+    }
+
+    /**
+     * Like `take_prey`, but only considers prey a crab of the given diet can eat (see `Diet::can_eat`),
+     * leaving all other prey in place.
+     * The take policy picks among the matching prey.
+     */
+    pub fn take_prey_matching(&mut self, diet: Diet) -> Option<Box<dyn Prey>> {
+        self.take_prey_where(|prey| diet.can_eat(prey.diet()))
+    }
+
+    /**
+     * Like `take_prey`, but only considers prey for which `wanted` returns true.
+     */
+    pub fn take_prey_where<F>(&mut self, wanted: F) -> Option<Box<dyn Prey>>
+    where
+        F: FnMut(&dyn Prey) -> bool,
+    {
+        self.take_prey_using(wanted, None).map(|(prey, _)| prey)
+    }
+
+    /**
+     * Like `take_prey_where`, but a `TakePolicy::Random` reef picks among the matching prey
+     * using `rng` rather than the crate's fixed-seed generator.
+     */
+    pub fn take_prey_where_with<F, R>(&mut self, wanted: F, rng: &mut R) -> Option<Box<dyn Prey>>
+    where
+        F: FnMut(&dyn Prey) -> bool,
+        R: RngCore + ?Sized,
+    {
+        let mut rng = rng;
+        self.take_prey_using(wanted, Some(&mut rng)).map(|(prey, _)| prey)
+    }
+
+    // Like `take_prey_where` or `take_prey_where_with`, but also returns how many ticks the prey
+    // had lived in the reef, so a hunt can hand it back with `return_prey` if it escapes.
+    pub(crate) fn take_prey_using<F>(&mut self, mut wanted: F, rng: Option<&mut (dyn RngCore + '_)>) -> Option<(Box<dyn Prey>, u32)>
+    where
+        F: FnMut(&dyn Prey) -> bool,
+    {
+        let mut matching = self
+            .prey
+            .iter()
+            .enumerate()
+            .filter(|(_, resident)| wanted(resident.prey.as_ref()))
+            .map(|(i, _)| i);
+        let index = match self.take_policy {
+            TakePolicy::Fifo => matching.next()?,
+            TakePolicy::Lifo => matching.next_back()?,
+            TakePolicy::Random => {
+                let matching: Vec<usize> = matching.collect();
+                if matching.is_empty() {
+                    return None;
+                }
+                let pick = match rng {
+                    Some(rng) => rng.gen_range(0..matching.len()),
+                    None => random_index(matching.len()),
+                };
+                matching[pick]
+            }
+            TakePolicy::SmallestFirst => matching.min_by_key(|&i| self.prey[i].prey.size())?,
+        };
+        self.remove_prey_at(index)
+    }
+
+    // Every prey taken out of the reef goes through here, so extraction can be tracked.
+    fn remove_prey_at(&mut self, index: usize) -> Option<(Box<dyn Prey>, u32)> {
+        let resident = self.prey.remove(index)?;
+        trace::event!(
+            "take_prey",
+            reef = self.name.as_deref().unwrap_or("unnamed"),
+            policy = format!("{:?}", self.take_policy),
+            prey = resident.prey.kind(),
+            remaining = self.prey.len(),
+        );
+        self.taken_this_tick += 1;
+        if self.prey.is_empty() {
+            events::queue(&mut self.events, OceanEvent::ReefDepleted { reef: self.name.clone() });
+        }
+        self.check_depletion();
+        Some((resident.prey, resident.age))
+    }
+
+    /**
+     * Puts back prey that was taken from this reef but escaped. Unlike `add_prey`, this ignores
+     * the capacity (the prey was already counted) and does not count towards extraction.
+     * The prey keeps the age it had when it was taken (see `take_prey_using`).
+     */
+    pub(crate) fn return_prey(&mut self, prey: Box<dyn Prey>, age: u32) {
+        self.prey.push_back(Resident { prey, age });
+        self.taken_this_tick = self.taken_this_tick.saturating_sub(1);
+    }
+
+    /**
+     * Removes up to `n` of the longest-resident prey, with their ages, so they can migrate to
+     * another reef. Migration does not count towards extraction.
+     */
+    pub(crate) fn take_migrants(&mut self, n: usize) -> Vec<(Box<dyn Prey>, u32)> {
+        let n = n.min(self.prey.len());
+        self.prey.drain(..n).map(|resident| (resident.prey, resident.age)).collect()
+    }
+
+    /**
+     * Settles a migrant from another reef, keeping its age, unless this reef is full.
+     */
+    pub(crate) fn try_add_migrant(&mut self, prey: Box<dyn Prey>, age: u32) -> Result<(), ReefFull> {
+        self.try_add_prey_aged(prey, age)
+    }
+
+    /**
+     * Puts back a migrant that could not settle elsewhere.
+     */
+    pub(crate) fn restore_migrant(&mut self, prey: Box<dyn Prey>, age: u32) {
+        self.prey.push_back(Resident { prey, age });
+    }
+
+    /**
+     * Returns the average number of prey taken (and not returned) per tick over the current
+     * tick and the last `HEALTH_WINDOW` completed ticks.
+     */
+    pub fn extraction_rate(&self) -> f64 {
+        let total: usize = self.extraction_history.iter().sum::<usize>() + self.taken_this_tick;
+        total as f64 / (self.extraction_history.len() + 1) as f64
+    }
+
+    /**
+     * Returns the reef's health between 0.0 (depleted) and 1.0 (thriving).
+     *
+     * Health is the product of two factors:
+     *   - How full the reef is: population / capacity, or 1.0 for an unbounded reef that is not empty.
+     *   - How long the prey would last at the current extraction rate, relative to `HEALTH_WINDOW`
+     *     ticks (1.0 if nothing is being taken).
+     */
+    pub fn health(&self) -> f64 {
+        let population = self.prey.len() as f64;
+        let stock = match self.capacity {
+            Some(0) => 1.0,
+            Some(capacity) => (population / capacity as f64).min(1.0),
+            None if self.prey.is_empty() => 0.0,
+            None => 1.0,
+        };
+        let rate = self.extraction_rate();
+        let pressure = if rate == 0.0 {
+            1.0
         } else {
-            Some(self.prey.remove(0)?)
+            (population / rate / HEALTH_WINDOW as f64).min(1.0)
+        };
+        stock * pressure
+    }
+
+    /**
+     * Registers `callback` to be called whenever the reef's health falls below `threshold`,
+     * replacing any previous callback. It is called once each time health crosses the threshold,
+     * not again until health has recovered to at least the threshold.
+     */
+    pub fn on_depletion<F>(&mut self, threshold: f64, callback: F)
+    where
+        F: FnMut(&Reef) + Send + 'static,
+    {
+        let below = self.health() < threshold;
+        self.depletion_watch = Some(DepletionWatch {
+            threshold,
+            callback: Box::new(callback),
+            below,
+        });
+    }
+
+    /**
+     * Registers `hook` to be called with this reef at the end of every phase of every simulation
+     * step, after the hooks already registered (see `crate::hooks`).
+     */
+    pub fn register_hook(&mut self, hook: Box<dyn ReefHook>) {
+        self.hooks.push(hook);
+    }
+
+    // Runs every hook for the end of `phase`. Hooks registered while they run are kept, after
+    // the others.
+    pub(crate) fn run_hooks(&mut self, tick: u64, phase: Phase, rng: &mut dyn OceanRng) {
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in hooks.iter_mut() {
+            hook.after_phase(tick, phase, self, rng);
+        }
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+    }
+
+    fn check_depletion(&mut self) {
+        let health = self.health();
+        if let Some(mut watch) = self.depletion_watch.take() {
+            let below = health < watch.threshold;
+            if below && !watch.below {
+                (watch.callback)(self);
+            }
+            watch.below = below;
+            self.depletion_watch = Some(watch);
         }
     }
+
+    /**
+     * Replaces the policy used by `tick` to regrow prey.
+     */
+    pub fn set_regen_policy(&mut self, policy: RegenPolicy) {
+        self.regen_policy = policy;
+    }
+
+    /**
+     * Removes and returns the events queued by changes to this reef, oldest first.
+     */
+    pub fn drain_events(&mut self) -> Vec<OceanEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /**
+     * Advances the reef by one simulation tick. Every prey ages by one tick, and prey that reach
+     * their lifespan (see `Prey::lifespan`) die and are removed. Then new prey are spawned according
+     * to the reef's regeneration policy, stopping once the reef is full.
+     *
+     * This also closes the current extraction period used by `extraction_rate` and `health`.
+     *
+     * Returns the number of prey spawned.
+     */
+    pub fn tick<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        self.tick_scaled(1.0, rng)
+    }
+
+    /**
+     * Like `tick`, but every regeneration rate is multiplied by `regen_scale`, e.g. 0.5 while a
+     * storm halves regrowth (see `crate::weather`).
+     */
+    pub fn tick_scaled<R: Rng + ?Sized>(&mut self, regen_scale: f64, rng: &mut R) -> usize {
+        let population = self.prey.len();
+        self.prey.retain_mut(|resident| {
+            resident.age = resident.age.saturating_add(1);
+            resident.prey.lifespan().is_none_or(|lifespan| resident.age < lifespan)
+        });
+        let died = population - self.prey.len();
+        if died > 0 {
+            events::queue(&mut self.events, OceanEvent::PreyDied { reef: self.name.clone(), count: died });
+        }
+
+        // Every rule still draws from `rng` once the reef is full, so a full reef doesn't change
+        // what later draws give.
+        let mut added = 0;
+        let mut full = false;
+        let rules = self.regen_policy.rules.clone();
+        for (rate, spawn) in rules.iter() {
+            let rate = clamp_rate(rate * regen_scale);
+            let mut count = rate.trunc() as usize;
+            if rng.gen_bool(rate.fract()) {
+                count += 1;
+            }
+            for _ in 0..count {
+                if full || !self.add_prey(spawn()) {
+                    full = true;
+                    break;
+                }
+                added += 1;
+            }
+        }
+        if added > 0 {
+            events::queue(&mut self.events, OceanEvent::PreySpawned { reef: self.name.clone(), count: added });
+        }
+
+        self.extraction_history.push_back(self.taken_this_tick);
+        if self.extraction_history.len() > HEALTH_WINDOW {
+            self.extraction_history.pop_front();
+        }
+        self.taken_this_tick = 0;
+        self.check_depletion();
+        added
+    }
+
+    /**
+     * Saves the reef's name, capacity, escape chance, take policy, regeneration policy (see
+     * `RegenPolicy`), and every prey (with its state and age) in the `crate::persist` text format.
+     *
+     * The registry id is not saved. Returns an Err string if the reef holds or spawns prey whose
+     * type does not support saving (see `Prey::to_fields`).
+     */
+    pub fn save(&self) -> Result<String, OceanError> {
+        let mut out = join_record(&["reef".to_string(), format!("{:?}", self.take_policy)]);
+        out.push('\n');
+        if let Some(name) = &self.name {
+            out.push_str(&join_record(&["name".to_string(), name.clone()]));
+            out.push('\n');
+        }
+        if let Some(capacity) = self.capacity {
+            out.push_str(&join_record(&["capacity".to_string(), capacity.to_string()]));
+            out.push('\n');
+        }
+        if let Some(chance) = self.escape_chance {
+            out.push_str(&join_record(&["escape".to_string(), chance.to_string()]));
+            out.push('\n');
+        }
+        if let Some(position) = self.position {
+            out.push_str(&join_record(&["position".to_string(), position.x.to_string(), position.y.to_string()]));
+            out.push('\n');
+        }
+        if let Some(owner) = &self.owner {
+            out.push_str(&join_record(&["owner".to_string(), owner.clone(), self.tribute.to_string()]));
+            out.push('\n');
+        }
+        for (rate, spawn) in self.regen_policy.rules.iter() {
+            let prey = spawn();
+            let state = prey
+                .to_fields()
+                .ok_or_else(|| OceanError::NotSaveable(prey.kind().to_string()))?;
+            let mut fields = vec!["regen".to_string(), rate.to_string(), prey.kind().to_string()];
+            fields.extend(state);
+            out.push_str(&join_record(&fields));
+            out.push('\n');
+        }
+        for Resident { prey, age } in self.prey.iter() {
+            let state = prey
+                .to_fields()
+                .ok_or_else(|| OceanError::NotSaveable(prey.kind().to_string()))?;
+            let mut fields = vec!["prey".to_string(), prey.kind().to_string()];
+            fields.extend(state);
+            out.push_str(&join_record(&fields));
+            out.push('\n');
+            // Ages are only written when non-zero, so saves from before prey aged still load.
+            if *age > 0 {
+                out.push_str(&join_record(&["age".to_string(), age.to_string()]));
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+
+    /**
+     * Loads a reef previously written by `save`. Only the built-in prey kinds can be loaded.
+     */
+    pub fn load(text: &str) -> Result<Reef, ParseError> {
+        let mut reef: Option<Reef> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            if line.is_empty() {
+                continue;
+            }
+            let fields = split_record(line);
+            if fields[0] == "reef" && fields.len() == 2 {
+                let mut new_reef = Reef::new();
+                new_reef.take_policy = fields[1].parse().map_err(|e| ParseError::new(line_no, e))?;
+                reef = Some(new_reef);
+                continue;
+            }
+            let reef = reef
+                .as_mut()
+                .ok_or_else(|| ParseError::new(line_no, "missing reef header".to_string()))?;
+            match fields[0].as_str() {
+                "name" if fields.len() == 2 => reef.name = Some(fields[1].clone()),
+                "capacity" if fields.len() == 2 => {
+                    reef.capacity = Some(parse_field(&fields[1], "capacity", line_no)?)
+                }
+                "escape" if fields.len() == 2 => {
+                    reef.escape_chance = Some(parse_field(&fields[1], "escape chance", line_no)?)
+                }
+                "position" if fields.len() == 3 => {
+                    let x = parse_field(&fields[1], "x position", line_no)?;
+                    reef.position = Some(Position::new(x, parse_field(&fields[2], "y position", line_no)?));
+                }
+                "owner" if fields.len() == 3 => {
+                    reef.owner = Some(fields[1].clone());
+                    reef.tribute = parse_field(&fields[2], "tribute", line_no)?;
+                }
+                "prey" if fields.len() >= 2 => reef.prey.push_back(Resident {
+                    prey: load_prey(&fields[1], &fields[2..], line_no)?,
+                    age: 0,
+                }),
+                "regen" if fields.len() >= 3 => {
+                    let rate: f64 = parse_field(&fields[1], "regeneration rate", line_no)?;
+                    if !rate.is_finite() || rate < 0.0 {
+                        return Err(ParseError::new(line_no, format!("invalid regeneration rate: {}", rate)));
+                    }
+                    let (kind, state) = (fields[2].clone(), fields[3..].to_vec());
+                    load_prey(&kind, &state, line_no)?;
+                    let policy = std::mem::replace(&mut reef.regen_policy, RegenPolicy::new());
+                    reef.regen_policy = policy.with_rate(rate, move || {
+                        load_prey(&kind, &state, 0).expect("regrown prey were checked when the reef was loaded")
+                    });
+                }
+                "age" if fields.len() == 2 => {
+                    let resident = reef
+                        .prey
+                        .back_mut()
+                        .ok_or_else(|| ParseError::new(line_no, "age without a prey".to_string()))?;
+                    resident.age = parse_field(&fields[1], "age", line_no)?;
+                }
+                other => return Err(ParseError::new(line_no, format!("unexpected record {:?}", other))),
+            }
+        }
+        reef.ok_or_else(|| ParseError::new(1, "missing reef header".to_string()))
+    }
+}
+
+/// A reef that can be shared between threads, unlike the `Rc<RefCell<Reef>>` handles crabs hold.
+///
+/// Cloning a `SyncReef` yields another handle to the same reef. Crabs hunt in it with
+/// `crab.hunt_in(&mut sync_reef.lock())`.
+#[derive(Debug, Clone)]
+pub struct SyncReef {
+    reef: Arc<Mutex<Reef>>,
+}
+
+impl SyncReef {
+    pub fn new(reef: Reef) -> SyncReef {
+        SyncReef {
+            reef: Arc::new(Mutex::new(reef)),
+        }
+    }
+
+    /**
+     * Locks the reef for exclusive access, blocking until no other thread holds it.
+     *
+     * If another thread panicked while holding the lock, the reef is still returned:
+     * reef operations leave it consistent even when interrupted.
+     */
+    pub fn lock(&self) -> MutexGuard<'_, Reef> {
+        self.reef.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /**
+     * Returns the reef if this is the last handle to it.
+     */
+    pub fn into_inner(self) -> Option<Reef> {
+        Arc::into_inner(self.reef).map(|reef| reef.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+// Returns the Shannon diversity index of a population made up of groups of the given sizes.
+pub(crate) fn shannon_index<I: IntoIterator<Item = usize>>(counts: I) -> f64 {
+    let counts: Vec<usize> = counts.into_iter().filter(|&count| count > 0).collect();
+    let total: usize = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    // Written as a sum of positive terms, so a single kind gives exactly 0.0 rather than -0.0.
+    counts
+        .iter()
+        .map(|&count| {
+            let p = count as f64 / total as f64;
+            p * (1.0 / p).ln()
+        })
+        .sum()
+}
+
+// Picks one of `count` candidates without a generator: with `crate::rand::rand32`, or with the
+// `deterministic` feature, with the thread's strategy (see `crate::deterministic`).
+fn random_index(count: usize) -> usize {
+    #[cfg(feature = "deterministic")]
+    {
+        crate::deterministic::pick(count)
+    }
+    #[cfg(not(feature = "deterministic"))]
+    {
+        crate::rand::rand32() as usize % count
+    }
+}
+
+// Keeps a regeneration rate between 0 and `MAX_REGEN_RATE`, treating NaN as 0.
+fn clamp_rate(rate: f64) -> f64 {
+    if rate.is_nan() {
+        return 0.0;
+    }
+    rate.clamp(0.0, MAX_REGEN_RATE)
 }
@@ -0,0 +1,141 @@
+use crate::error::OceanError;
+use crate::reef::{Reef, ReefFull};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::slice::Iter;
+
+/// Identifies a reef within the `ReefRegistry` it was registered with.
+pub type ReefId = usize;
+
+/// Owns the shared handles to a set of reefs, giving each an id and keeping names unique.
+#[derive(Debug)]
+pub struct ReefRegistry {
+    reefs: Vec<Rc<RefCell<Reef>>>,
+    // links[id] lists the reefs that reef `id` is linked to.
+    links: Vec<Vec<ReefId>>,
+}
+
+impl ReefRegistry {
+    pub fn new() -> ReefRegistry {
+        ReefRegistry {
+            reefs: Vec::new(),
+            links: Vec::new(),
+        }
+    }
+
+    /**
+     * Registers `reef`, assigning it the next id.
+     *
+     * Returns `OceanError::DuplicateName` if the reef is named and a registered reef already has that name.
+     */
+    pub fn register(&mut self, mut reef: Reef) -> Result<ReefId, OceanError> {
+        if let Some(name) = reef.name() {
+            if self.id_of_name(name).is_some() {
+                return Err(OceanError::DuplicateName { kind: "reef", name: name.to_string() });
+            }
+        }
+        let id = self.reefs.len();
+        reef.set_id(id);
+        self.reefs.push(Rc::new(RefCell::new(reef)));
+        self.links.push(Vec::new());
+        Ok(id)
+    }
+
+    /**
+     * Links two reefs so that prey can migrate between them in both directions.
+     * Linking reefs that are already linked does nothing.
+     *
+     * Returns an Err if either id is unknown or both ids are the same reef.
+     */
+    pub fn link(&mut self, a: ReefId, b: ReefId) -> Result<(), OceanError> {
+        if let Some(unknown) = [a, b].into_iter().find(|&id| id >= self.reefs.len()) {
+            return Err(OceanError::ReefNotFound(unknown.to_string()));
+        }
+        if a == b {
+            return Err(OceanError::InvalidArgument(format!("Cannot link reef {} to itself", a)));
+        }
+        if !self.links[a].contains(&b) {
+            self.links[a].push(b);
+            self.links[b].push(a);
+        }
+        Ok(())
+    }
+
+    /**
+     * Returns the ids of the reefs linked to the given reef, in the order they were linked.
+     */
+    pub fn links_of(&self, id: ReefId) -> &[ReefId] {
+        self.links.get(id).map_or(&[], Vec::as_slice)
+    }
+
+    /**
+     * Runs one migration step: every reef sends `fraction` (0.0 to 1.0) of its prey, rounded down,
+     * to its linked reefs, spread as evenly as possible in link order.
+     *
+     * Emigrant counts are based on populations before anyone moves. Prey that would arrive at a
+     * full reef stay where they are. Returns the number of prey that moved.
+     */
+    pub fn migrate(&self, fraction: f64) -> usize {
+        let fraction = fraction.clamp(0.0, 1.0);
+        let emigrants: Vec<usize> = self
+            .reefs
+            .iter()
+            .map(|reef| (reef.borrow().population() as f64 * fraction) as usize)
+            .collect();
+
+        let mut moved = 0;
+        for (from, count) in emigrants.into_iter().enumerate() {
+            let neighbors = &self.links[from];
+            if neighbors.is_empty() {
+                continue;
+            }
+            let migrants = self.reefs[from].borrow_mut().take_migrants(count);
+            for (i, (prey, age)) in migrants.into_iter().enumerate() {
+                let to = neighbors[i % neighbors.len()];
+                let result = self.reefs[to].borrow_mut().try_add_migrant(prey, age);
+                match result {
+                    Ok(()) => moved += 1,
+                    Err(ReefFull(prey)) => self.reefs[from].borrow_mut().restore_migrant(prey, age),
+                }
+            }
+        }
+        moved
+    }
+
+    /**
+     * Returns a new handle to the reef with the given id, e.g. to pass to `Crab::discover_reef`.
+     */
+    pub fn get(&self, id: ReefId) -> Option<Rc<RefCell<Reef>>> {
+        self.reefs.get(id).cloned()
+    }
+
+    /**
+     * Returns a new handle to the reef with the given name.
+     */
+    pub fn by_name(&self, name: &str) -> Option<Rc<RefCell<Reef>>> {
+        self.id_of_name(name).and_then(|id| self.get(id))
+    }
+
+    pub fn id_of_name(&self, name: &str) -> Option<ReefId> {
+        self.reefs.iter().position(|reef| reef.borrow().name() == Some(name))
+    }
+
+    /**
+     * Returns the id of the reef `handle` points to, or None if it is not registered here.
+     */
+    pub fn id_of(&self, handle: &Rc<RefCell<Reef>>) -> Option<ReefId> {
+        self.reefs.iter().position(|reef| Rc::ptr_eq(reef, handle))
+    }
+
+    pub fn iter(&self) -> Iter<'_, Rc<RefCell<Reef>>> {
+        self.reefs.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.reefs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reefs.is_empty()
+    }
+}
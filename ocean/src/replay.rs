@@ -0,0 +1,306 @@
+//! Recording a simulation's run so it can be replayed exactly, e.g. to track down a rare
+//! divergence or to compare a run against a reference one.
+//!
+//! Once `Simulation::start_recording` is called, the simulation remembers its state at that
+//! moment and every action taken through it afterwards: crabs added with `Simulation::add_crab`,
+//! crabs bred with `Simulation::breed_crabs`, and steps. Each action is stored with every number
+//! it drew from the simulation's generator, so a replay does not depend on the generator at all.
+//! `Simulation::save_recording` writes the recording in the `crate::persist` format:
+//!
+//! ```text
+//! replay    1
+//! snapshot  12
+//! ocean     1
+//! ...                 (the 12 lines of the starting snapshot, see `crate::simulation`)
+//! add       0  Ferris  3  ...  0  2      (a beach, the crab's fields, then the ids of its reefs)
+//! breed     0  0  1  Junior  9f3c...     (a beach, two crab indices, a name, then the draws)
+//! step      a2b1...  77e0...             (the draws made during the step)
+//! ```
+//!
+//! `Simulation::replay` loads the snapshot and applies each action again, feeding it the numbers
+//! it drew the first time. Only what the snapshot holds and the recorded actions are replayed:
+//! changes made through `Simulation::ocean_mut`, scheduled events and matches, listeners, seasons, predators,
+//! and the other settings `Simulation::save` leaves out are not.
+//!
+//! `Simulation::state_at` replays a simulation's own recording only up to an earlier tick, to
+//! answer questions about the past such as who was in a clan at that tick.
+
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::rng::OceanRng;
+use rand::RngCore;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// The version of the replay format written by `Simulation::save_recording`.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// Something done to a simulation while it was being recorded.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Action {
+    /** `Simulation::add_crab`, with the crab's fields (see `Crab::to_fields`) and reef ids. */
+    AddCrab { beach: usize, crab: Vec<String>, reefs: Vec<usize> },
+    BreedCrabs { beach: usize, i: usize, j: usize, name: String },
+    Step,
+}
+
+impl Action {
+    // The number of fields before the draws in a record, or None if the record has no draws.
+    fn draws_start(&self) -> Option<usize> {
+        match self {
+            Action::AddCrab { .. } => None,
+            Action::BreedCrabs { .. } => Some(5),
+            Action::Step => Some(1),
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        match self {
+            Action::AddCrab { beach, crab, reefs } => {
+                let mut fields = vec!["add".to_string(), beach.to_string()];
+                fields.extend(crab.iter().cloned());
+                fields.extend(reefs.iter().map(usize::to_string));
+                fields
+            }
+            Action::BreedCrabs { beach, i, j, name } => {
+                vec!["breed".to_string(), beach.to_string(), i.to_string(), j.to_string(), name.clone()]
+            }
+            Action::Step => vec!["step".to_string()],
+        }
+    }
+
+    fn from_fields(fields: &[String], line: usize) -> Result<(Action, Vec<u64>), ParseError> {
+        let action = match (fields[0].as_str(), fields.len()) {
+            ("add", len) if len >= 2 + CRAB_FIELDS => {
+                let reefs = fields[2 + CRAB_FIELDS..]
+                    .iter()
+                    .map(|field| parse_field(field, "reef id", line))
+                    .collect::<Result<_, _>>()?;
+                Action::AddCrab {
+                    beach: parse_field(&fields[1], "beach index", line)?,
+                    crab: fields[2..2 + CRAB_FIELDS].to_vec(),
+                    reefs,
+                }
+            }
+            ("breed", len) if len >= 5 => Action::BreedCrabs {
+                beach: parse_field(&fields[1], "beach index", line)?,
+                i: parse_field(&fields[2], "crab index", line)?,
+                j: parse_field(&fields[3], "crab index", line)?,
+                name: fields[4].clone(),
+            },
+            ("step", _) => Action::Step,
+            _ => return Err(ParseError::new(line, format!("unexpected record {:?}", fields[0]))),
+        };
+        let mut draws = Vec::new();
+        for field in &fields[action.draws_start().unwrap_or(fields.len())..] {
+            let draw = u64::from_str_radix(field, 16)
+                .map_err(|_| ParseError::new(line, format!("invalid draw {:?}", field)))?;
+            draws.push(draw);
+        }
+        Ok((action, draws))
+    }
+}
+
+// The number of fields `Crab::to_fields` writes.
+const CRAB_FIELDS: usize = 34;
+
+/// A simulation's starting snapshot and the actions taken since, each with its draws.
+#[derive(Debug)]
+pub(crate) struct Recording {
+    snapshot: String,
+    actions: Vec<(Action, Vec<u64>)>,
+    draws: Rc<RefCell<Vec<u64>>>,
+}
+
+impl Recording {
+    /**
+     * Starts a recording from `snapshot`. Returns the recording and the generator the simulation
+     * must draw from while it is recorded, which wraps `rng`.
+     */
+    pub(crate) fn new(snapshot: String, rng: Box<dyn OceanRng>) -> (Recording, Box<dyn OceanRng>) {
+        let draws = Rc::new(RefCell::new(Vec::new()));
+        let recording = Recording { snapshot, actions: Vec::new(), draws: draws.clone() };
+        (recording, Box::new(RecordingRng { inner: rng, draws }))
+    }
+
+    /**
+     * Throws away every recorded action and starts again from `snapshot`.
+     */
+    pub(crate) fn restart(&mut self, snapshot: String) {
+        self.snapshot = snapshot;
+        self.actions.clear();
+    }
+
+    /**
+     * Forgets the draws made since the last action, which were not made by a recorded action.
+     */
+    pub(crate) fn begin(&mut self) {
+        self.draws.borrow_mut().clear();
+    }
+
+    /**
+     * Records `action` with the draws made since `begin`.
+     */
+    pub(crate) fn end(&mut self, action: Action) {
+        let draws = std::mem::take(&mut *self.draws.borrow_mut());
+        self.actions.push((action, draws));
+    }
+
+    pub(crate) fn save(&self) -> String {
+        let mut out = join_record(&["replay".to_string(), REPLAY_VERSION.to_string()]);
+        out.push('\n');
+        let lines: Vec<&str> = self.snapshot.lines().collect();
+        out.push_str(&join_record(&["snapshot".to_string(), lines.len().to_string()]));
+        out.push('\n');
+        for line in lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+        for (action, draws) in self.actions.iter() {
+            let mut fields = action.to_fields();
+            fields.extend(draws.iter().map(|draw| format!("{:x}", draw)));
+            out.push_str(&join_record(&fields));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// An action read back from a replay, with the line it was on and the draws it made.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    pub(crate) line: usize,
+    pub(crate) action: Action,
+    pub(crate) draws: Vec<u64>,
+}
+
+/**
+ * Splits a replay written by `Recording::save` into its snapshot and its actions.
+ */
+pub(crate) fn parse_replay(text: &str) -> Result<(String, Vec<Entry>), ParseError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+    let header = lines.next().map(|(_, line)| split_record(line)).unwrap_or_default();
+    if header.len() != 2 || header[0] != "replay" {
+        return Err(ParseError::new(1, "missing replay header".to_string()));
+    }
+    let version: u32 = parse_field(&header[1], "replay version", 1)?;
+    if version != REPLAY_VERSION {
+        let message = format!("unsupported replay version {} (expected {})", version, REPLAY_VERSION);
+        return Err(ParseError::new(1, message));
+    }
+    let fields = lines.next().map(|(_, line)| split_record(line)).unwrap_or_default();
+    if fields.len() != 2 || fields[0] != "snapshot" {
+        return Err(ParseError::new(2, "missing snapshot record".to_string()));
+    }
+    let count: usize = parse_field(&fields[1], "snapshot length", 2)?;
+    let snapshot: Vec<&str> = lines.by_ref().take(count).map(|(_, line)| line).collect();
+    if snapshot.len() != count {
+        return Err(ParseError::new(2 + snapshot.len(), "the snapshot ends early".to_string()));
+    }
+    let mut actions = Vec::new();
+    for (line_no, line) in lines {
+        if line.is_empty() {
+            continue;
+        }
+        let (action, draws) = Action::from_fields(&split_record(line), line_no)?;
+        actions.push(Entry { line: line_no, action, draws });
+    }
+    Ok((snapshot.join("\n"), actions))
+}
+
+// Passes draws through from the simulation's own generator, noting each one.
+struct RecordingRng {
+    inner: Box<dyn OceanRng>,
+    draws: Rc<RefCell<Vec<u64>>>,
+}
+
+impl RngCore for RecordingRng {
+    fn next_u32(&mut self) -> u32 {
+        let draw = self.inner.next_u32();
+        self.draws.borrow_mut().push(draw as u64);
+        draw
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let draw = self.inner.next_u64();
+        self.draws.borrow_mut().push(draw);
+        draw
+    }
+
+    // Built from `next_u64` so that `Playback` can give back the same bytes.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// The draws one replayed action may make, in order.
+#[derive(Debug, Default)]
+pub(crate) struct Playback {
+    draws: VecDeque<u64>,
+    overdrawn: bool,
+}
+
+impl Playback {
+    /**
+     * Returns a generator that gives back the draws loaded into `playback`.
+     */
+    pub(crate) fn rng(playback: &Rc<RefCell<Playback>>) -> Box<dyn OceanRng> {
+        Box::new(PlaybackRng { playback: playback.clone() })
+    }
+
+    /**
+     * Loads the draws of the next action.
+     */
+    pub(crate) fn load(&mut self, draws: Vec<u64>) {
+        self.draws = draws.into();
+        self.overdrawn = false;
+    }
+
+    /**
+     * Returns true if the action drew exactly as many numbers as it did when it was recorded.
+     */
+    pub(crate) fn matched(&self) -> bool {
+        self.draws.is_empty() && !self.overdrawn
+    }
+
+    fn next(&mut self) -> u64 {
+        self.draws.pop_front().unwrap_or_else(|| {
+            self.overdrawn = true;
+            0
+        })
+    }
+}
+
+struct PlaybackRng {
+    playback: Rc<RefCell<Playback>>,
+}
+
+impl RngCore for PlaybackRng {
+    fn next_u32(&mut self) -> u32 {
+        self.playback.borrow_mut().next() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.playback.borrow_mut().next()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+//! Human-readable reports on the state of an ocean, in Markdown or HTML.
+//!
+//! A report has four sections: a population table with one row per beach, clan standings for
+//! each beach's clans and for the ocean-wide clans, a reef health table, and the notable events
+//! of the last few ticks. Events are not kept by the ocean itself, so the last section is only
+//! filled in when the report is given an event log (see `Report::with_events`), e.g. one
+//! collected by a `Simulation` listener:
+//!
+//! ```text
+//! let log = Rc::new(RefCell::new(Vec::new()));
+//! let sink = Rc::clone(&log);
+//! simulation.subscribe(Box::new(move |event: &OceanEvent| sink.borrow_mut().push(event.clone())));
+//! simulation.run(20);
+//! let html = Report::new()
+//!     .with_format(ReportFormat::Html)
+//!     .with_events(log.borrow().iter().cloned())
+//!     .render(simulation.ocean());
+//! ```
+
+use crate::clans::{ClanMember, ClanSystem};
+use crate::events::OceanEvent;
+use crate::ocean::Ocean;
+
+/// The number of ticks of events a report includes by default.
+pub const DEFAULT_RECENT_TICKS: u64 = 10;
+
+/// The markup a report is written in.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ReportFormat {
+    /** GitHub-flavored Markdown, with pipe tables. */
+    #[default]
+    Markdown,
+    /** A standalone HTML document. */
+    Html,
+}
+
+/// Renders a report on an ocean. Build one with `Report::new` and the `with_*` methods, or use
+/// `Report::generate` for a Markdown report with no events.
+#[derive(Debug, Clone)]
+pub struct Report {
+    format: ReportFormat,
+    events: Vec<OceanEvent>,
+    recent_ticks: u64,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report::new()
+    }
+}
+
+impl Report {
+    /**
+     * Returns a Markdown report with no events, covering the last `DEFAULT_RECENT_TICKS` ticks.
+     */
+    pub fn new() -> Report {
+        Report { format: ReportFormat::Markdown, events: Vec::new(), recent_ticks: DEFAULT_RECENT_TICKS }
+    }
+
+    /**
+     * Returns a Markdown report on `ocean` with no event section entries.
+     */
+    pub fn generate(ocean: &Ocean) -> String {
+        Report::new().render(ocean)
+    }
+
+    /**
+     * Writes the report in `format`.
+     */
+    pub fn with_format(mut self, format: ReportFormat) -> Report {
+        self.format = format;
+        self
+    }
+
+    /**
+     * Uses `events`, oldest first, as the event log. Ticks are told apart by the
+     * `OceanEvent::StepFinished` events a `Simulation` publishes at the end of each step; events
+     * after the last of them belong to a step still in progress and are always included.
+     */
+    pub fn with_events<I>(mut self, events: I) -> Report
+    where
+        I: IntoIterator<Item = OceanEvent>,
+    {
+        self.events = events.into_iter().collect();
+        self
+    }
+
+    /**
+     * Includes the notable events of the last `ticks` finished ticks.
+     */
+    pub fn with_recent_ticks(mut self, ticks: u64) -> Report {
+        self.recent_ticks = ticks;
+        self
+    }
+
+    pub fn format(&self) -> ReportFormat {
+        self.format
+    }
+
+    pub fn recent_ticks(&self) -> u64 {
+        self.recent_ticks
+    }
+
+    /**
+     * Renders the report on `ocean`.
+     */
+    pub fn render(&self, ocean: &Ocean) -> String {
+        let mut out = Writer::new(self.format);
+        out.heading(1, "Ocean report");
+        out.paragraph(&format!(
+            "{} crab(s) on {} beach(es); biodiversity {:.2}.",
+            ocean.total_population(),
+            ocean.beaches().len(),
+            ocean.biodiversity()
+        ));
+
+        out.heading(2, "Population");
+        let rows: Vec<Vec<String>> = ocean
+            .beaches()
+            .enumerate()
+            .map(|(i, beach)| {
+                vec![
+                    beach_label(ocean, i),
+                    beach.size().to_string(),
+                    beach
+                        .get_fastest_crab()
+                        .map_or("-".to_string(), |crab| format!("{} ({})", crab.name(), crab.speed())),
+                    beach.get_clan_system().get_clan_ids().len().to_string(),
+                    beach.hall_of_fame().len().to_string(),
+                ]
+            })
+            .collect();
+        out.table(&["Beach", "Crabs", "Fastest", "Clans", "Deaths"], &rows);
+
+        out.heading(2, "Clan standings");
+        for (i, beach) in ocean.beaches().enumerate() {
+            out.heading(3, &beach_label(ocean, i));
+            clan_table(&mut out, beach.get_clan_system());
+        }
+        out.heading(3, "Ocean-wide clans");
+        clan_table(&mut out, ocean.ocean_clans());
+
+        out.heading(2, "Reef health");
+        let rows: Vec<Vec<String>> = ocean
+            .reefs()
+            .enumerate()
+            .map(|(i, reef)| {
+                let reef = reef.borrow();
+                vec![
+                    reef.name().map_or_else(|| format!("Reef {}", i), str::to_string),
+                    reef.population().to_string(),
+                    reef.capacity().map_or("-".to_string(), |capacity| capacity.to_string()),
+                    format!("{:.0}%", reef.health() * 100.0),
+                ]
+            })
+            .collect();
+        out.table(&["Reef", "Prey", "Capacity", "Health"], &rows);
+
+        out.heading(2, &format!("Notable events (last {} tick(s))", self.recent_ticks));
+        let rows: Vec<Vec<String>> = self
+            .recent_events()
+            .into_iter()
+            .filter_map(|(tick, event)| {
+                let tick = tick.map_or("-".to_string(), |tick| tick.to_string());
+                describe(ocean, event).map(|text| vec![tick, text])
+            })
+            .collect();
+        out.table(&["Tick", "Event"], &rows);
+        out.finish()
+    }
+
+    // Returns the events of the last `recent_ticks` finished ticks and of any unfinished one,
+    // oldest first, each with the tick it happened in (if known).
+    fn recent_events(&self) -> Vec<(Option<u64>, &OceanEvent)> {
+        let mut recent = Vec::new();
+        let mut tick = None;
+        let mut finished = 0;
+        for event in self.events.iter().rev() {
+            if let OceanEvent::StepFinished { tick: ended } = event {
+                if finished == self.recent_ticks {
+                    break;
+                }
+                finished += 1;
+                tick = Some(*ended);
+                continue;
+            }
+            let current = match tick {
+                Some(tick) => Some(tick),
+                None => self.last_tick().map(|last| last + 1),
+            };
+            recent.push((current, event));
+        }
+        recent.reverse();
+        recent
+    }
+
+    // Returns the tick of the last `StepFinished` event in the log.
+    fn last_tick(&self) -> Option<u64> {
+        self.events.iter().rev().find_map(|event| match event {
+            OceanEvent::StepFinished { tick } => Some(*tick),
+            _ => None,
+        })
+    }
+}
+
+// Returns the name of the beach at `index`, or a placeholder for an unnamed beach.
+fn beach_label(ocean: &Ocean, index: usize) -> String {
+    ocean
+        .beaches()
+        .nth(index)
+        .and_then(|beach| beach.name())
+        .map_or_else(|| format!("Beach {}", index), str::to_string)
+}
+
+// Writes the standings of `clans` as a table, best first.
+fn clan_table<M: ClanMember>(out: &mut Writer, clans: &ClanSystem<M>) {
+    let rows: Vec<Vec<String>> = clans
+        .leaderboard()
+        .into_iter()
+        .map(|(id, reputation)| {
+            vec![
+                id.clone(),
+                clans.get_clan_member_count(&id).to_string(),
+                clans.get_clan_average_speed(&id).map_or("-".to_string(), |speed| speed.to_string()),
+                reputation.to_string(),
+            ]
+        })
+        .collect();
+    out.table(&["Clan", "Members", "Average speed", "Reputation"], &rows);
+}
+
+// Describes `event` in a sentence, or returns None if it is too routine to report.
+fn describe(ocean: &Ocean, event: &OceanEvent) -> Option<String> {
+    let reef_name = |reef: &Option<String>| reef.clone().unwrap_or_else(|| "an unnamed reef".to_string());
+    let text = match event {
+        OceanEvent::CrabBorn { name, parents } => format!("{} was born to {} and {}", name, parents.0, parents.1),
+        OceanEvent::CrabSpawned { name } => format!("{} arrived from the open ocean", name),
+        OceanEvent::CrabDied { name } => format!("{} died", name),
+        OceanEvent::CrabMigrated { name, from, to } => {
+            format!("{} migrated from {} to {}", name, beach_label(ocean, *from), beach_label(ocean, *to))
+        }
+        OceanEvent::CrabEaten { crab, predator } => format!("{} was eaten by {}", crab, predator),
+        OceanEvent::CrabStarved { crab } => format!("{} starved", crab),
+        OceanEvent::CarcassEaten { crab, carcass } => format!("{} scavenged the carcass of {}", crab, carcass),
+        OceanEvent::MoltEnded { crab, speed } => format!("{} finished molting with speed {}", crab, speed),
+        OceanEvent::CrabInfected { crab, disease } => format!("{} caught {}", crab, disease),
+        OceanEvent::CrabRecovered { crab, disease } => format!("{} recovered from {}", crab, disease),
+        OceanEvent::ClanWon { winner, loser } => format!("{} beat {}", winner, loser),
+        OceanEvent::ClanDrew { clans } => format!("{} and {} drew", clans.0, clans.1),
+        OceanEvent::MatchCancelled { clans, reason } => {
+            format!("The match between {} and {} was called off: {}", clans.0, clans.1, reason)
+        }
+        OceanEvent::ReefRaided { attacker, defender, reef, captured } => {
+            let reef = ocean
+                .reefs()
+                .find(|handle| handle.borrow().id() == Some(*reef))
+                .and_then(|handle| handle.borrow().name().map(str::to_string))
+                .unwrap_or_else(|| format!("reef {}", reef));
+            if *captured {
+                format!("{} took {} from {}", attacker, reef, defender)
+            } else {
+                format!("{} raided {} but {} held it", attacker, reef, defender)
+            }
+        }
+        OceanEvent::ReefDepleted { reef } => format!("{} was depleted", reef_name(reef)),
+        OceanEvent::MoltStarted { .. }
+        | OceanEvent::PreyCaught { .. }
+        | OceanEvent::PreyEscaped { .. }
+        | OceanEvent::ClanJoined { .. }
+        | OceanEvent::ClanLeft { .. }
+        | OceanEvent::PreySpawned { .. }
+        | OceanEvent::PreyDied { .. }
+        | OceanEvent::StepFinished { .. } => return None,
+    };
+    Some(text)
+}
+
+// Accumulates a report's text in one format.
+struct Writer {
+    format: ReportFormat,
+    text: String,
+}
+
+impl Writer {
+    fn new(format: ReportFormat) -> Writer {
+        let text = match format {
+            ReportFormat::Markdown => String::new(),
+            ReportFormat::Html => {
+                "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Ocean report</title></head>\n<body>\n"
+                    .to_string()
+            }
+        };
+        Writer { format, text }
+    }
+
+    fn heading(&mut self, level: usize, title: &str) {
+        match self.format {
+            ReportFormat::Markdown => {
+                self.text.push_str(&format!("{} {}\n\n", "#".repeat(level), markdown_text(title)))
+            }
+            ReportFormat::Html => self.text.push_str(&format!("<h{0}>{1}</h{0}>\n", level, html_text(title))),
+        }
+    }
+
+    fn paragraph(&mut self, text: &str) {
+        match self.format {
+            ReportFormat::Markdown => self.text.push_str(&format!("{}\n\n", markdown_text(text))),
+            ReportFormat::Html => self.text.push_str(&format!("<p>{}</p>\n", html_text(text))),
+        }
+    }
+
+    // Writes a table, or a "None." paragraph if there are no rows.
+    fn table(&mut self, header: &[&str], rows: &[Vec<String>]) {
+        if rows.is_empty() {
+            self.paragraph("None.");
+            return;
+        }
+        match self.format {
+            ReportFormat::Markdown => {
+                let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+                self.text.push_str(&line(header.iter().map(|cell| markdown_text(cell)).collect()));
+                self.text.push_str(&line(header.iter().map(|_| "---".to_string()).collect()));
+                for row in rows {
+                    self.text.push_str(&line(row.iter().map(|cell| markdown_text(cell)).collect()));
+                }
+                self.text.push('\n');
+            }
+            ReportFormat::Html => {
+                let line = |tag: &str, cells: Vec<String>| {
+                    let cells: String = cells.iter().map(|cell| format!("<{0}>{1}</{0}>", tag, cell)).collect();
+                    format!("<tr>{}</tr>\n", cells)
+                };
+                self.text.push_str("<table>\n");
+                self.text.push_str(&line("th", header.iter().map(|cell| html_text(cell)).collect()));
+                for row in rows {
+                    self.text.push_str(&line("td", row.iter().map(|cell| html_text(cell)).collect()));
+                }
+                self.text.push_str("</table>\n");
+            }
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if self.format == ReportFormat::Html {
+            self.text.push_str("</body>\n</html>\n");
+        }
+        self.text
+    }
+}
+
+// Escapes the characters Markdown would read as table or inline markup.
+fn markdown_text(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '|' | '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Escapes the characters HTML would read as markup.
+fn html_text(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
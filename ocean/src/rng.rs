@@ -0,0 +1,87 @@
+//! Injectable randomness, so that runs can be reproduced and population drift debugged.
+//!
+//! Every random choice the crate makes has a variant that draws from a generator passed in by
+//! the caller:
+//!   - Breeding: `Crab::breed_with_rng` (diet and pattern), `Crab::breed_weighted` and
+//!     `Crab::breed_mendelian` (color, diet and pattern), and `Beach::breed_crabs_with_rng`.
+//!   - Hunting: `Crab::hunt_with_rng`, `Reef::take_prey_where_with`, `Prey::try_escape_with`,
+//!     and `EscapeModel::escapes_with`.
+//!   - Reef regrowth: `Reef::tick`.
+//!
+//! The variants without a generator use the fixed-seed `crate::rand::rand32`, so existing code
+//! keeps producing the same results, or with the `deterministic` feature, pure strategies that
+//! do not depend on earlier draws (see `crate::deterministic`). A `Simulation` owns one `OceanRng` and passes it to every
+//! step and scheduled event. A simulation made with `Simulation::new` owns a `ResumableRng`,
+//! whose position its snapshots save.
+
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+/// A source of randomness for the ocean. Every `rand::RngCore` is one.
+pub trait OceanRng: RngCore {}
+
+impl<R: RngCore + ?Sized> OceanRng for R {}
+
+/**
+ * Returns the standard generator seeded with `seed`. The same seed always gives the same run.
+ */
+pub fn seeded(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// The standard generator (see `seeded`), which can also tell how far through its sequence it
+/// is and pick up from there, so a saved `Simulation` goes on drawing where it left off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumableRng {
+    seed: u64,
+    inner: ChaCha12Rng,
+}
+
+impl ResumableRng {
+    /**
+     * Returns a generator seeded with `seed`, drawing the same numbers as `seeded(seed)`.
+     */
+    pub fn new(seed: u64) -> ResumableRng {
+        ResumableRng { seed, inner: ChaCha12Rng::seed_from_u64(seed) }
+    }
+
+    /**
+     * Returns the generator seeded with `seed` that has already drawn `position` words (see
+     * `position`).
+     */
+    pub fn resume(seed: u64, position: u128) -> ResumableRng {
+        let mut rng = ResumableRng::new(seed);
+        rng.inner.set_word_pos(position);
+        rng
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /**
+     * Returns how many 32-bit words the generator has drawn since it was seeded.
+     */
+    pub fn position(&self) -> u128 {
+        self.inner.get_word_pos()
+    }
+}
+
+impl RngCore for ResumableRng {
+    fn next_u32(&mut self) -> u32 {
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,582 @@
+//! Declarative setup of populated oceans, for tests and experiments:
+//!
+//! ```text
+//! let simulation = ScenarioBuilder::new(42)
+//!     .with_reef(ReefSpec::new("kelp").with_algae(20).with_shrimp(5))
+//!     .with_beach(
+//!         BeachSpec::new("north")
+//!             .with_crabs(CrabSpec::new(12).with_speed(3, 8).with_diets(vec![(Diet::Plants, 1)]).with_reefs(&["kelp"]))
+//!             .with_clan("reds", 4),
+//!     )
+//!     .build()?;
+//! ```
+//!
+//! Everything random about the scenario (each crab's speed, color and diet, and which crabs join
+//! each clan) is drawn from one generator seeded with the scenario's seed, which the built
+//! simulation then goes on using. The same builder always gives the same simulation.
+//!
+//! When every crab matters, e.g. in a test, the `ocean!` macro writes out an ocean crab by crab
+//! instead, with nothing random about it:
+//!
+//! ```text
+//! let ocean = ocean! {
+//!     reef "kelp" [Algae, Algae, Shrimp]
+//!     beach "north" {
+//!         crab "Ferris" speed 5 color Blue diet Shellfish,
+//!         crab "Bob" speed 3 reefs ["kelp"],
+//!         clan "reds" ["Ferris", "Bob"]
+//!         reef "shoal" [Minnow, Clam]
+//!     }
+//! }?;
+//! ```
+//!
+//! A reef written inside a beach is added to the ocean like any other, and discovered by every
+//! crab on that beach. The macro fills in an `OceanSketch` and builds it.
+
+use crate::beach::Beach;
+use crate::color::Color;
+use crate::config::{spawner, REGEN_KINDS};
+use crate::crab::Crab;
+use crate::diet::Diet;
+use crate::error::OceanError;
+use crate::ocean::Ocean;
+use crate::prey::{Algae, Clam, Minnow, Shrimp};
+use crate::reef::Reef;
+use crate::rng::seeded;
+use crate::simulation::Simulation;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// A reef to stock, with the same kinds of prey as `Ocean::generate_reef`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReefSpec {
+    name: String,
+    minnows: u32,
+    shrimp: u32,
+    clams: u32,
+    algae: u32,
+    capacity: Option<usize>,
+}
+
+impl ReefSpec {
+    /**
+     * Describes an empty reef with the given name, which crabs use to find it.
+     */
+    pub fn new(name: &str) -> ReefSpec {
+        ReefSpec { name: name.to_string(), minnows: 0, shrimp: 0, clams: 0, algae: 0, capacity: None }
+    }
+
+    /**
+     * Stocks the reef with `n` minnows of speed 25.
+     */
+    pub fn with_minnows(mut self, n: u32) -> ReefSpec {
+        self.minnows = n;
+        self
+    }
+
+    /**
+     * Stocks the reef with `n` shrimp of energy 1.
+     */
+    pub fn with_shrimp(mut self, n: u32) -> ReefSpec {
+        self.shrimp = n;
+        self
+    }
+
+    pub fn with_clams(mut self, n: u32) -> ReefSpec {
+        self.clams = n;
+        self
+    }
+
+    pub fn with_algae(mut self, n: u32) -> ReefSpec {
+        self.algae = n;
+        self
+    }
+
+    /**
+     * Limits the reef to `capacity` prey (see `Reef::set_capacity`).
+     */
+    pub fn with_capacity(mut self, capacity: usize) -> ReefSpec {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    fn build(&self) -> Reef {
+        let mut reef = Reef::with_name(self.name.clone());
+        reef.set_capacity(self.capacity);
+        for _ in 0..self.minnows {
+            reef.add_prey(Box::new(Minnow::new(25)));
+        }
+        for _ in 0..self.shrimp {
+            reef.add_prey(Box::new(Shrimp::new(1)));
+        }
+        for _ in 0..self.clams {
+            reef.add_prey(Box::new(Clam::new()));
+        }
+        for _ in 0..self.algae {
+            reef.add_prey(Box::new(Algae::new()));
+        }
+        reef
+    }
+}
+
+/// A batch of randomly generated crabs. Unless told otherwise, speeds are spread evenly from 1
+/// to 10, and colors (red, green, or blue) and diets (fish, shellfish, or plants) are equally
+/// likely.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CrabSpec {
+    count: usize,
+    name_prefix: String,
+    speed: (u32, u32),
+    colors: Vec<(Color, u32)>,
+    diets: Vec<(Diet, u32)>,
+    reefs: Vec<String>,
+}
+
+impl CrabSpec {
+    /**
+     * Describes `count` crabs, named after their beach order: `Crab1`, `Crab2`, and so on.
+     */
+    pub fn new(count: usize) -> CrabSpec {
+        CrabSpec {
+            count,
+            name_prefix: "Crab".to_string(),
+            speed: (1, 10),
+            colors: vec![(Color::new_red(), 1), (Color::new_green(), 1), (Color::new_blue(), 1)],
+            diets: vec![(Diet::Fish, 1), (Diet::Shellfish, 1), (Diet::Plants, 1)],
+            reefs: Vec::new(),
+        }
+    }
+
+    /**
+     * Names the crabs `prefix` followed by their position on the beach, instead of `Crab`.
+     */
+    pub fn with_name_prefix(mut self, prefix: &str) -> CrabSpec {
+        self.name_prefix = prefix.to_string();
+        self
+    }
+
+    /**
+     * Spreads the crabs' speeds evenly from `min` to `max`, inclusive.
+     */
+    pub fn with_speed(mut self, min: u32, max: u32) -> CrabSpec {
+        self.speed = (min, max);
+        self
+    }
+
+    /**
+     * Picks each crab's color with the given relative weights, e.g. `[(red, 1), (blue, 3)]`
+     * makes three in four crabs blue.
+     */
+    pub fn with_colors(mut self, colors: Vec<(Color, u32)>) -> CrabSpec {
+        self.colors = colors;
+        self
+    }
+
+    /**
+     * Picks each crab's diet with the given relative weights (see `with_colors`).
+     */
+    pub fn with_diets(mut self, diets: Vec<(Diet, u32)>) -> CrabSpec {
+        self.diets = diets;
+        self
+    }
+
+    /**
+     * Has every crab discover the reefs with the given names, in order.
+     */
+    pub fn with_reefs(mut self, reefs: &[&str]) -> CrabSpec {
+        self.reefs = reefs.iter().map(|name| name.to_string()).collect();
+        self
+    }
+}
+
+/// A beach, its crabs, and its clans.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BeachSpec {
+    name: String,
+    crabs: Vec<CrabSpec>,
+    clans: Vec<(String, usize)>,
+}
+
+impl BeachSpec {
+    pub fn new(name: &str) -> BeachSpec {
+        BeachSpec { name: name.to_string(), crabs: Vec::new(), clans: Vec::new() }
+    }
+
+    /**
+     * Adds a batch of crabs after those already described.
+     */
+    pub fn with_crabs(mut self, crabs: CrabSpec) -> BeachSpec {
+        self.crabs.push(crabs);
+        self
+    }
+
+    /**
+     * Creates a clan of `members` crabs, picked at random from those not yet in a clan.
+     */
+    pub fn with_clan(mut self, clan_id: &str, members: usize) -> BeachSpec {
+        self.clans.push((clan_id.to_string(), members));
+        self
+    }
+}
+
+/// Builds a `Simulation` of an ocean described by reefs and beaches.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScenarioBuilder {
+    seed: u64,
+    reefs: Vec<ReefSpec>,
+    beaches: Vec<BeachSpec>,
+}
+
+impl ScenarioBuilder {
+    /**
+     * Starts an empty scenario whose randomness is seeded with `seed`.
+     */
+    pub fn new(seed: u64) -> ScenarioBuilder {
+        ScenarioBuilder { seed, reefs: Vec::new(), beaches: Vec::new() }
+    }
+
+    pub fn with_reef(mut self, reef: ReefSpec) -> ScenarioBuilder {
+        self.reefs.push(reef);
+        self
+    }
+
+    pub fn with_beach(mut self, beach: BeachSpec) -> ScenarioBuilder {
+        self.beaches.push(beach);
+        self
+    }
+
+    /**
+     * Builds the ocean and returns a simulation of it: reefs first, in order, then each beach
+     * with its crabs and clans.
+     *
+     * Returns `OceanError::DuplicateName` if two reefs or two beaches share a name,
+     * `OceanError::ReefNotFound` if crabs discover a reef that is not described, and
+     * `OceanError::InvalidArgument` if a speed range is empty, a color or diet distribution has
+     * no weight, or a clan wants more members than there are crabs left to join it.
+     */
+    pub fn build(&self) -> Result<Simulation, OceanError> {
+        let mut rng = seeded(self.seed);
+        let mut ocean = Ocean::new();
+        for reef in self.reefs.iter() {
+            ocean.add_reef(reef.build())?;
+        }
+        for spec in self.beaches.iter() {
+            let mut beach = Beach::with_name(spec.name.clone());
+            for crabs in spec.crabs.iter() {
+                let (min, max) = crabs.speed;
+                if min > max {
+                    return Err(OceanError::InvalidArgument(format!("No speeds from {} to {}", min, max)));
+                }
+                let colors = weighted(&crabs.colors, "color")?;
+                let diets = weighted(&crabs.diets, "diet")?;
+                let mut reefs = Vec::new();
+                for name in crabs.reefs.iter() {
+                    reefs.push(ocean.reef_by_name(name).ok_or_else(|| OceanError::ReefNotFound(name.clone()))?);
+                }
+                for _ in 0..crabs.count {
+                    let name = format!("{}{}", crabs.name_prefix, beach.size() + 1);
+                    let speed = rng.gen_range(min..=max);
+                    let color = crabs.colors[colors.sample(&mut rng)].0.clone();
+                    let diet = crabs.diets[diets.sample(&mut rng)].0;
+                    let mut crab = Crab::new(name, speed, color, diet);
+                    for reef in reefs.iter() {
+                        crab.discover_reef(reef.clone());
+                    }
+                    beach.add_crab(crab);
+                }
+            }
+            let mut unclanned: Vec<String> = beach.crabs().map(|crab| crab.name().to_string()).collect();
+            for (clan, members) in spec.clans.iter() {
+                if *members > unclanned.len() {
+                    let message = format!("Clan {} wants {} members but only {} crabs are left", clan, members, unclanned.len());
+                    return Err(OceanError::InvalidArgument(message));
+                }
+                unclanned.shuffle(&mut rng);
+                beach.get_clan_system_mut().create_clan(clan.clone());
+                for name in unclanned.split_off(unclanned.len() - members) {
+                    beach.try_add_member_to_clan(clan, &name)?;
+                }
+            }
+            // A scenario's setup is not something to undo.
+            beach.clear_history();
+            ocean.try_add_beach(beach)?;
+        }
+        Ok(Simulation::with_rng(ocean, Box::new(rng)))
+    }
+}
+
+/// An ocean described crab by crab, usually through the `ocean!` macro. Crabs are fast 1, red
+/// and eat fish unless told otherwise, and the `crab_*` setters describe the last crab added.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct OceanSketch {
+    // Each reef, with the kinds of its prey and the index of the beach it was written in, if any.
+    reefs: Vec<(String, Vec<String>, Option<usize>)>,
+    beaches: Vec<BeachSketch>,
+    // The first mistake made describing the ocean, reported by `build`.
+    error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct BeachSketch {
+    name: String,
+    crabs: Vec<CrabSketch>,
+    clans: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct CrabSketch {
+    name: String,
+    speed: u32,
+    color: Color,
+    diet: Diet,
+    reefs: Vec<String>,
+}
+
+impl OceanSketch {
+    pub fn new() -> OceanSketch {
+        OceanSketch::default()
+    }
+
+    /**
+     * Adds a reef stocked with one prey of each kind listed, e.g. `["Algae", "Algae", "Clam"]`.
+     * Each kind must be one of `REGEN_KINDS`, stocked as `Ocean::generate_reef` stocks it.
+     */
+    pub fn with_reef(mut self, name: &str, prey: &[&str]) -> OceanSketch {
+        let kinds = prey.iter().map(|kind| kind.to_string()).collect();
+        self.reefs.push((name.to_string(), kinds, None));
+        self
+    }
+
+    /**
+     * Like `with_reef`, but the reef belongs to the last beach added: every crab on it discovers
+     * the reef.
+     */
+    pub fn with_beach_reef(mut self, name: &str, prey: &[&str]) -> OceanSketch {
+        let beach = self.beaches.len().checked_sub(1);
+        if beach.is_none() {
+            self.fail(format!("Reef {} belongs to no beach", name));
+        }
+        self = self.with_reef(name, prey);
+        self.reefs.last_mut().unwrap().2 = beach;
+        self
+    }
+
+    pub fn with_beach(mut self, name: &str) -> OceanSketch {
+        self.beaches.push(BeachSketch { name: name.to_string(), crabs: Vec::new(), clans: Vec::new() });
+        self
+    }
+
+    /**
+     * Adds a crab to the last beach added.
+     */
+    pub fn with_crab(mut self, name: &str) -> OceanSketch {
+        let crab = CrabSketch {
+            name: name.to_string(),
+            speed: 1,
+            color: Color::new_red(),
+            diet: Diet::Fish,
+            reefs: Vec::new(),
+        };
+        match self.beaches.last_mut() {
+            Some(beach) => beach.crabs.push(crab),
+            None => self.fail(format!("Crab {} lives on no beach", name)),
+        }
+        self
+    }
+
+    pub fn crab_speed(self, speed: u32) -> OceanSketch {
+        self.describe_crab(|crab| crab.speed = speed)
+    }
+
+    pub fn crab_color(self, color: Color) -> OceanSketch {
+        self.describe_crab(|crab| crab.color = color)
+    }
+
+    pub fn crab_diet(self, diet: Diet) -> OceanSketch {
+        self.describe_crab(|crab| crab.diet = diet)
+    }
+
+    /**
+     * Has the last crab added discover the reefs with the given names, in order, after the reefs
+     * of its beach.
+     */
+    pub fn crab_reefs(self, reefs: &[&str]) -> OceanSketch {
+        self.describe_crab(|crab| crab.reefs.extend(reefs.iter().map(|name| name.to_string())))
+    }
+
+    /**
+     * Creates a clan on the last beach added, with the crabs of the given names as its members.
+     */
+    pub fn with_clan(mut self, clan_id: &str, members: &[&str]) -> OceanSketch {
+        let members = members.iter().map(|name| name.to_string()).collect();
+        match self.beaches.last_mut() {
+            Some(beach) => beach.clans.push((clan_id.to_string(), members)),
+            None => self.fail(format!("Clan {} lives on no beach", clan_id)),
+        }
+        self
+    }
+
+    fn describe_crab<F: FnOnce(&mut CrabSketch)>(mut self, describe: F) -> OceanSketch {
+        match self.beaches.last_mut().and_then(|beach| beach.crabs.last_mut()) {
+            Some(crab) => describe(crab),
+            None => self.fail(String::from("No crab to describe")),
+        }
+        self
+    }
+
+    fn fail(&mut self, message: String) {
+        self.error.get_or_insert(message);
+    }
+
+    /**
+     * Builds the ocean: reefs first, in order, then each beach with its crabs, in order, and its
+     * clans. Crabs discover their beach's reefs, then the reefs they were given.
+     *
+     * Returns `OceanError::InvalidArgument` if a crab, clan or reef of a beach was described
+     * before any beach, a crab was described before any crab, or a reef has a kind of prey not in
+     * `REGEN_KINDS`; `OceanError::DuplicateName` if two reefs or two beaches share a name;
+     * `OceanError::ReefNotFound` if a crab discovers a reef that is not described; and the errors
+     * of `Beach::try_add_member_to_clan` if a clan cannot take a member.
+     */
+    pub fn build(&self) -> Result<Ocean, OceanError> {
+        if let Some(message) = &self.error {
+            return Err(OceanError::InvalidArgument(message.clone()));
+        }
+        let mut ocean = Ocean::new();
+        for (name, kinds, _) in self.reefs.iter() {
+            let mut reef = Reef::with_name(name.clone());
+            for kind in kinds.iter() {
+                let spawn = spawner(kind).ok_or_else(|| {
+                    let message = format!("Unknown prey kind {} (expected one of {})", kind, REGEN_KINDS.join(", "));
+                    OceanError::InvalidArgument(message)
+                })?;
+                reef.add_prey(spawn());
+            }
+            ocean.add_reef(reef)?;
+        }
+        for (b, sketch) in self.beaches.iter().enumerate() {
+            let mut beach = Beach::with_name(sketch.name.clone());
+            let own: Vec<&String> =
+                self.reefs.iter().filter(|(_, _, beach)| *beach == Some(b)).map(|(name, _, _)| name).collect();
+            for crab in sketch.crabs.iter() {
+                let mut built = Crab::new(crab.name.clone(), crab.speed, crab.color.clone(), crab.diet);
+                for name in own.iter().copied().chain(crab.reefs.iter()) {
+                    built.discover_reef(ocean.reef_by_name(name).ok_or_else(|| OceanError::ReefNotFound(name.clone()))?);
+                }
+                beach.add_crab(built);
+            }
+            for (clan, members) in sketch.clans.iter() {
+                beach.get_clan_system_mut().create_clan(clan.clone());
+                for name in members.iter() {
+                    beach.try_add_member_to_clan(clan, name)?;
+                }
+            }
+            // As with a `ScenarioBuilder`, the setup is not something to undo.
+            beach.clear_history();
+            ocean.try_add_beach(beach)?;
+        }
+        Ok(ocean)
+    }
+}
+
+/**
+ * Builds an `Ocean` written out reef by reef and beach by beach, returning
+ * `Result<Ocean, OceanError>` (see `OceanSketch::build` and `crate::scenario`):
+ *
+ * ```text
+ * reef "name" [Kind, ...]           a reef with one prey of each kind listed
+ * beach "name" { ... }              a beach, holding:
+ *   crab "name" speed 5 color Blue diet Shellfish reefs ["name", ...]
+ *                                   a crab; every part after its name is optional
+ *   clan "id" ["name", ...]         a clan and its members
+ *   reef "name" [Kind, ...]         a reef every crab on the beach discovers
+ * ```
+ *
+ * Each item may be followed by a comma. Colors are the named colors (`Red`, `Green`, `Blue`,
+ * `Orange`, `Teal` or `Violet`), and diets are variants of `Diet`. Oceans of any size expand
+ * without raising the crate's `recursion_limit`.
+ */
+#[macro_export]
+macro_rules! ocean {
+    ($($kind:ident $name:literal $body:tt $(,)?)*) => {{
+        let sketch = $crate::scenario::OceanSketch::new();
+        $(let sketch = $crate::__ocean_item!(sketch; $kind $name $body);)*
+        sketch.build()
+    }};
+}
+
+// Adds one top-level item of an `ocean!` to the sketch. Items are matched by repetition rather
+// than munched one at a time, so large oceans don't run into the recursion limit.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ocean_item {
+    ($s:expr; reef $name:literal [$($kind:ident),* $(,)?]) => {
+        $s.with_reef($name, &[$(stringify!($kind)),*])
+    };
+    ($s:expr; beach $name:literal { $($body:tt)* }) => {
+        $crate::__ocean_beach!($s.with_beach($name); $($body)*)
+    };
+}
+
+// Adds the inside of a `beach` block of an `ocean!` to the sketch. Each keyword and its argument,
+// e.g. `speed 5` or `clan "id" [...]`, is one item, so a crab's optional parts can come in any
+// order.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ocean_beach {
+    ($s:expr; $($key:ident $arg:tt $([$($list:tt)*])? $(,)?)*) => {{
+        let sketch = $s;
+        $(let sketch = $crate::__ocean_beach_item!(sketch; $key $arg $([$($list)*])?);)*
+        sketch
+    }};
+}
+
+// Adds one item of a `beach` block of an `ocean!` to the sketch.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ocean_beach_item {
+    ($s:expr; crab $name:literal) => {
+        $s.with_crab($name)
+    };
+    ($s:expr; speed $speed:literal) => {
+        $s.crab_speed($speed)
+    };
+    ($s:expr; color Red) => {
+        $s.crab_color($crate::color::Color::new_red())
+    };
+    ($s:expr; color Green) => {
+        $s.crab_color($crate::color::Color::new_green())
+    };
+    ($s:expr; color Blue) => {
+        $s.crab_color($crate::color::Color::new_blue())
+    };
+    ($s:expr; color Orange) => {
+        $s.crab_color($crate::color::Color::new_orange())
+    };
+    ($s:expr; color Teal) => {
+        $s.crab_color($crate::color::Color::new_teal())
+    };
+    ($s:expr; color Violet) => {
+        $s.crab_color($crate::color::Color::new_violet())
+    };
+    ($s:expr; diet $diet:ident) => {
+        $s.crab_diet($crate::diet::Diet::$diet)
+    };
+    ($s:expr; reefs [$($reef:literal),* $(,)?]) => {
+        $s.crab_reefs(&[$($reef),*])
+    };
+    ($s:expr; clan $id:literal [$($member:literal),* $(,)?]) => {
+        $s.with_clan($id, &[$($member),*])
+    };
+    ($s:expr; reef $name:literal [$($kind:ident),* $(,)?]) => {
+        $s.with_beach_reef($name, &[$(stringify!($kind)),*])
+    };
+}
+
+// Returns a distribution picking an index of `choices` by weight, or an Err if no choice has
+// any weight.
+fn weighted<T>(choices: &[(T, u32)], what: &str) -> Result<WeightedIndex<u32>, OceanError> {
+    WeightedIndex::new(choices.iter().map(|&(_, weight)| weight))
+        .map_err(|_| OceanError::InvalidArgument(format!("Every {} has a weight of 0", what)))
+}
@@ -0,0 +1,137 @@
+//! A `Send + Sync` handle to a `Simulation`, for hosts that share state between threads, such as
+//! web servers.
+//!
+//! The ocean itself stays single-threaded: crabs share reefs through `Rc<RefCell<Reef>>`
+//! handles, and simulations hold boxed generators, listeners, and scheduled events. Instead of
+//! making every handle in that graph thread-safe, a `SharedSimulation` owns its simulation on a
+//! thread of its own and runs closures against it one at a time, in the order they are sent.
+//! Handles are cheap to clone, and the thread exits once the last handle is dropped. With the
+//! `async` feature, the closures can also be awaited instead (see `crate::nonblocking`).
+//!
+//! ```text
+//! let shared = SharedSimulation::spawn(|| Simulation::new(Ocean::new(), 7));
+//! let report = shared.step()?;
+//! let crabs = shared.with(|simulation| simulation.ocean().beaches().map(Beach::size).sum::<usize>())?;
+//! ```
+
+use crate::error::OceanError;
+use crate::simulation::{Simulation, StepReport};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+// A closure to run against the simulation on its thread.
+pub(crate) type Job = Box<dyn FnOnce(&mut Simulation) + Send>;
+
+/// A handle to a simulation running on its own thread. Cloning it yields another handle to the
+/// same simulation.
+#[derive(Debug, Clone)]
+pub struct SharedSimulation {
+    jobs: Sender<Job>,
+}
+
+impl SharedSimulation {
+    /**
+     * Starts a thread that builds a simulation with `build` and then runs the closures sent to it.
+     * The simulation is built on that thread because it cannot be moved between threads.
+     */
+    pub fn spawn<F>(build: F) -> SharedSimulation
+    where
+        F: FnOnce() -> Simulation + Send + 'static,
+    {
+        SharedSimulation::start(move || Some(build()))
+    }
+
+    /**
+     * Starts a thread running the simulation saved at `path` (see `Simulation::load`).
+     * Returns an Err if it cannot be loaded, in which case no thread is left running.
+     */
+    pub fn load<P: Into<PathBuf>>(path: P) -> Result<SharedSimulation, OceanError> {
+        let path = path.into();
+        let (loaded, result) = mpsc::channel();
+        let shared = SharedSimulation::start(move || match Simulation::load(&path) {
+            Ok(simulation) => {
+                let _ = loaded.send(Ok(()));
+                Some(simulation)
+            }
+            Err(e) => {
+                let _ = loaded.send(Err(e));
+                None
+            }
+        });
+        result.recv().map_err(|_| OceanError::SimulationStopped)??;
+        Ok(shared)
+    }
+
+    // Starts the simulation's thread, which ends straight away if `build` returns None.
+    fn start<F>(build: F) -> SharedSimulation
+    where
+        F: FnOnce() -> Option<Simulation> + Send + 'static,
+    {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            if let Some(mut simulation) = build() {
+                for job in receiver {
+                    job(&mut simulation);
+                }
+            }
+        });
+        SharedSimulation { jobs }
+    }
+
+    /**
+     * Runs `f` against the simulation, waiting for any earlier closures to finish first, and
+     * returns its result.
+     *
+     * Returns `OceanError::SimulationStopped` if the simulation's thread is gone, because
+     * building the simulation or an earlier closure panicked.
+     */
+    pub fn with<R, F>(&self, f: F) -> Result<R, OceanError>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Simulation) -> R + Send + 'static,
+    {
+        let (reply, result) = mpsc::channel();
+        let job: Job = Box::new(move |simulation| {
+            let _ = reply.send(f(simulation));
+        });
+        self.send(job)?;
+        result.recv().map_err(|_| OceanError::SimulationStopped)
+    }
+
+    /**
+     * Sends `job` to the simulation's thread without waiting for it to run.
+     *
+     * Returns `OceanError::SimulationStopped` if the thread is gone, in which case `job` is dropped.
+     */
+    pub(crate) fn send(&self, job: Job) -> Result<(), OceanError> {
+        self.jobs.send(job).map_err(|_| OceanError::SimulationStopped)
+    }
+
+    pub fn step(&self) -> Result<StepReport, OceanError> {
+        self.with(Simulation::step)
+    }
+
+    pub fn run(&self, steps: u64) -> Result<Vec<StepReport>, OceanError> {
+        self.with(move |simulation| simulation.run(steps))
+    }
+
+    pub fn clock(&self) -> Result<u64, OceanError> {
+        self.with(|simulation| simulation.clock())
+    }
+
+    /**
+     * Returns a snapshot of the simulation, as written by `Simulation::save`.
+     */
+    pub fn snapshot(&self) -> Result<String, OceanError> {
+        self.with(|simulation| simulation.snapshot())?
+    }
+
+    /**
+     * Checkpoints the simulation to `path` (see `Simulation::save`).
+     */
+    pub fn save<P: Into<PathBuf>>(&self, path: P) -> Result<(), OceanError> {
+        let path = path.into();
+        self.with(move |simulation| simulation.save(&path))?
+    }
+}
@@ -0,0 +1,1001 @@
+//! A simulation engine that advances an `Ocean` one step at a time, in a fixed order:
+//!   1. The step's weather is looked up in the simulation's seasons, and seasonal effects run
+//!      (see `crate::weather`). Every beach allows or forbids breeding for the step.
+//!   2. Crabs that starved on the previous step are removed, then every crab grows one step
+//!      older, which may start or finish a molt (see `Crab::is_molting`) or starve it (see
+//!      `Beach::set_starvation_limit`), scavengers eat the carcasses of dead crabs (see
+//!      `Beach::scavenge`), and diseases spread and run their course (see `crate::disease`).
+//!   3. Every reef ticks (prey age, die, and regrow at the weather's rate) as many times as the
+//!      simulation's tick length, then prey migrate between linked reefs.
+//!   4. Crabs discover the reefs within their beach's sensory radius (see
+//!      `Beach::set_sensory_radius`).
+//!   5. Every crab on every beach hunts once, beach by beach, unless the weather or its zone
+//!      (see `crate::zone`) keeps it sheltering. Then crabs with a behavior tree, which skip that
+//!      hunt, run their trees instead (see `crate::behavior`).
+//!   6. Every beach faces its predators (see `Beach::face_predators`), beach by beach.
+//!   7. Every beach applies its population policy (see `crate::population`): crabs die, and wild
+//!      crabs arrive.
+//!   8. Crabs move between beaches under the crab migration policy (see `MigrationPolicy`).
+//!   9. Events scheduled for this step run, in the order they were scheduled, then the clan
+//!      matches on the calendar for this step are played (see `Simulation::schedule_match`).
+//!   10. If metrics are enabled, the ocean is measured (see `crate::metrics`).
+//!
+//! All randomness comes from the simulation's generator (see `crate::rng`), so a run can be
+//! replayed from its seed.
+//!
+//! Events queued by each phase (see `crate::events`) are published to the simulation's listeners
+//! when the phase ends, followed by `OceanEvent::StepFinished` at the end of the step. Just
+//! before they are, the hooks of the beaches, reefs and simulation run (see `crate::hooks`).
+//!
+//! `Simulation::save` checkpoints a simulation to a single file in the `crate::persist` format,
+//! and `Simulation::load` resumes it. A snapshot starts with its format version, the clock and
+//! where the generator is in its sequence, followed by every reef (as written by `Reef::save`)
+//! with the links between them, then every beach (as written by `Beach::save`), each with the
+//! reefs its crabs have discovered:
+//!
+//! ```text
+//! ocean      1
+//! clock      12
+//! migration  0.1
+//! rng        42  96
+//! reef       Fifo
+//! name       kelp
+//! prey       Algae
+//! link       0  1
+//! beach
+//! crab       Ferris  5  ...
+//! discovered 0  0  1
+//! ```
+
+use crate::beach::Beach;
+use crate::behavior;
+use crate::config::SimConfig;
+use crate::crab::Crab;
+use crate::error::OceanError;
+use crate::events::{EventListener, OceanEvent};
+use crate::hooks::{Phase, TickHook};
+use crate::metrics::Metrics;
+use crate::ocean::{MigrationPolicy, Ocean};
+use crate::persist::{join_record, parse_field, split_record, ParseError};
+use crate::reef::Reef;
+use crate::replay::{parse_replay, Action, Entry, Playback, Recording};
+use crate::rng::{seeded, OceanRng, ResumableRng};
+use crate::weather::{Conditions, SeasonalEffect, Seasons, Weather};
+use rand::{Rng, RngCore};
+use std::cell::RefCell;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::fmt;
+
+/// An action to run against the ocean at a chosen step. It is given the simulation's generator
+/// for any random choices it makes, e.g. with `Beach::breed_crabs_with_rng`.
+pub type ScheduledEvent = Box<dyn FnOnce(&mut Ocean, &mut dyn OceanRng)>;
+
+/// A match between two ocean clans on a simulation's calendar (see `Simulation::schedule_match`).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ClanMatch {
+    /** The tick of the step at whose end the match is played. */
+    pub tick: u64,
+    pub clans: (String, String),
+}
+
+/// What happened during one `Simulation::step`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StepReport {
+    /** The clock reading of the step, starting from 0. */
+    pub tick: u64,
+    pub weather: Weather,
+    pub crabs_infected: usize,
+    pub prey_spawned: usize,
+    pub prey_migrated: usize,
+    /** Reefs crabs discovered within their beach's sensory radius (see `Beach::set_sensory_radius`). */
+    pub reefs_discovered: usize,
+    pub hunts: usize,
+    pub catches: usize,
+    pub crabs_eaten: usize,
+    /** Wild crabs that arrived under the beaches' population policies. */
+    pub crabs_spawned: usize,
+    /** Crabs that died under the beaches' population policies. */
+    pub crabs_died: usize,
+    /** Crabs that starved to death (see `Beach::set_starvation_limit`). */
+    pub crabs_starved: usize,
+    /** Carcasses scavengers ate (see `Beach::scavenge`). */
+    pub carcasses_eaten: usize,
+    pub crabs_migrated: usize,
+    pub events_run: usize,
+    /** Scheduled clan matches played, not counting those called off (see `Simulation::schedule_match`). */
+    pub matches_played: usize,
+}
+
+pub struct Simulation {
+    ocean: Ocean,
+    clock: u64,
+    rng: Generator,
+    migration_rate: f64,
+    tick_length: u32,
+    crab_migration: MigrationPolicy,
+    scheduled: Vec<(u64, ScheduledEvent)>,
+    // Clan matches still to be played, by tick and then in the order they were scheduled.
+    calendar: Vec<ClanMatch>,
+    listeners: Vec<Box<dyn EventListener>>,
+    hooks: Vec<Box<dyn TickHook>>,
+    seasons: Seasons,
+    seasonal_effects: Vec<SeasonalEffect>,
+    metrics: Option<Metrics>,
+    recording: Option<Recording>,
+}
+
+// The generator a simulation draws from: its own, which snapshots save, or one its caller chose.
+enum Generator {
+    Own(Box<ResumableRng>),
+    Chosen(Box<dyn OceanRng>),
+}
+
+impl RngCore for Generator {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            Generator::Own(rng) => rng.next_u32(),
+            Generator::Chosen(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            Generator::Own(rng) => rng.next_u64(),
+            Generator::Chosen(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            Generator::Own(rng) => rng.fill_bytes(dest),
+            Generator::Chosen(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        match self {
+            Generator::Own(rng) => rng.try_fill_bytes(dest),
+            Generator::Chosen(rng) => rng.try_fill_bytes(dest),
+        }
+    }
+}
+
+impl fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Simulation")
+            .field("ocean", &self.ocean)
+            .field("clock", &self.clock)
+            .field("migration_rate", &self.migration_rate)
+            .field("tick_length", &self.tick_length)
+            .field("crab_migration", &self.crab_migration)
+            .field("scheduled", &self.scheduled.len())
+            .field("calendar", &self.calendar)
+            .field("listeners", &self.listeners.len())
+            .field("hooks", &self.hooks.len())
+            .field("seasons", &self.seasons)
+            .field("seasonal_effects", &self.seasonal_effects.len())
+            .field("metrics", &self.metrics)
+            .field("recording", &self.recording.is_some())
+            .finish()
+    }
+}
+
+impl Simulation {
+    /**
+     * Creates a simulation of `ocean` whose randomness is seeded with `seed` (see `crate::rng::seeded`).
+     * Snapshots of the simulation save how far through its generator's sequence it is.
+     */
+    pub fn new(ocean: Ocean, seed: u64) -> Simulation {
+        let mut simulation = Simulation::with_rng(ocean, Box::new(seeded(0)));
+        simulation.rng = Generator::Own(Box::new(ResumableRng::new(seed)));
+        simulation
+    }
+
+    /**
+     * Creates a simulation of `ocean` that draws all of its randomness from `rng`.
+     */
+    pub fn with_rng(ocean: Ocean, rng: Box<dyn OceanRng>) -> Simulation {
+        Simulation {
+            ocean,
+            clock: 0,
+            rng: Generator::Chosen(rng),
+            migration_rate: 0.0,
+            tick_length: 1,
+            crab_migration: MigrationPolicy::new(),
+            scheduled: Vec::new(),
+            calendar: Vec::new(),
+            listeners: Vec::new(),
+            hooks: Vec::new(),
+            seasons: Seasons::new(),
+            seasonal_effects: Vec::new(),
+            metrics: None,
+            recording: None,
+        }
+    }
+
+    /**
+     * Creates a simulation of `ocean` with the parameters of `config` (see `crate::config`): the
+     * seed, tick length and migration rate, every reef's settings, every beach's breeding limit,
+     * and a seasonal effect that scales regeneration and forbids breeding as configured.
+     *
+     * Returns `OceanError::ReefNotFound` if the config has settings for a reef that is not in
+     * `ocean`, and `OceanError::InvalidArgument` if a reef is set to regrow an unknown kind of prey
+     * (see `ReefConfig::apply`).
+     */
+    pub fn with_config(mut ocean: Ocean, config: &SimConfig) -> Result<Simulation, OceanError> {
+        if let Some(name) = config.reefs.keys().find(|name| ocean.reef_by_name(name).is_none()) {
+            return Err(OceanError::ReefNotFound(name.clone()));
+        }
+        for reef in ocean.reefs() {
+            let mut reef = reef.borrow_mut();
+            config.reef_defaults.apply(&mut reef)?;
+            if let Some(own) = reef.name().and_then(|name| config.reefs.get(name)) {
+                own.apply(&mut reef)?;
+            }
+        }
+        for beach in ocean.beaches_mut() {
+            beach.set_breeding_limit(config.max_population);
+        }
+        let mut simulation = Simulation::new(ocean, config.seed);
+        simulation.set_migration_rate(config.migration_rate);
+        simulation.set_tick_length(config.tick_length);
+        let (regen_rate, breeding) = (config.regen_rate, config.breeding);
+        if regen_rate != 1.0 || !breeding {
+            simulation.add_seasonal_effect(move |_, conditions, _| {
+                conditions.regen_rate *= regen_rate;
+                conditions.breeding &= breeding;
+            });
+        }
+        Ok(simulation)
+    }
+
+    pub fn ocean(&self) -> &Ocean {
+        &self.ocean
+    }
+
+    pub fn ocean_mut(&mut self) -> &mut Ocean {
+        &mut self.ocean
+    }
+
+    /**
+     * Ends the simulation, returning its ocean.
+     */
+    pub fn into_ocean(self) -> Ocean {
+        self.ocean
+    }
+
+    /**
+     * Returns the number of steps taken so far, which is also the tick of the next step.
+     */
+    pub fn clock(&self) -> u64 {
+        self.clock
+    }
+
+    /**
+     * Returns the simulation's generator, for random choices made between steps.
+     */
+    pub fn rng_mut(&mut self) -> &mut dyn OceanRng {
+        &mut self.rng
+    }
+
+    /**
+     * Sets the fraction of each reef's prey that migrates to linked reefs every step
+     * (see `ReefRegistry::migrate`). Defaults to 0.0.
+     */
+    pub fn set_migration_rate(&mut self, rate: f64) {
+        self.migration_rate = rate;
+    }
+
+    /**
+     * Sets how many times every reef ticks each step, so that prey age and regrow faster
+     * relative to the crabs. Defaults to 1; a length of 0 is treated as 1.
+     */
+    pub fn set_tick_length(&mut self, ticks: u32) {
+        self.tick_length = ticks.max(1);
+    }
+
+    pub fn tick_length(&self) -> u32 {
+        self.tick_length
+    }
+
+    /**
+     * Sets when crabs move between beaches on their own, once a step (see
+     * `Ocean::apply_migration_policy`). Defaults to a policy that moves no crabs.
+     */
+    pub fn set_crab_migration_policy(&mut self, policy: MigrationPolicy) {
+        self.crab_migration = policy;
+    }
+
+    /**
+     * Sets the cycle of weather the simulation's steps go through. Defaults to an empty cycle,
+     * under which every step is sunny.
+     */
+    pub fn set_seasons(&mut self, seasons: Seasons) {
+        self.seasons = seasons;
+    }
+
+    pub fn seasons(&self) -> &Seasons {
+        &self.seasons
+    }
+
+    /**
+     * Returns the weather of the next step.
+     */
+    pub fn weather(&self) -> Weather {
+        self.seasons.weather_at(self.clock)
+    }
+
+    /**
+     * Adds a seasonal effect, which runs at the start of every step after those already added.
+     * It is given the step's weather, and may adjust the step's conditions or change the ocean.
+     */
+    pub fn add_seasonal_effect<F>(&mut self, effect: F)
+    where
+        F: FnMut(Weather, &mut Conditions, &mut Ocean) + 'static,
+    {
+        self.seasonal_effects.push(Box::new(effect));
+    }
+
+    /**
+     * Starts recording metrics at the end of every step (see `crate::metrics`). Does nothing if
+     * they are already being recorded.
+     */
+    pub fn enable_metrics(&mut self) {
+        self.metrics.get_or_insert_with(Metrics::new);
+    }
+
+    /**
+     * Returns the metrics recorded so far, or None if they were never enabled.
+     */
+    pub fn metrics(&self) -> Option<&Metrics> {
+        self.metrics.as_ref()
+    }
+
+    /**
+     * Schedules `event` to run at the end of the step with the given tick.
+     * Events scheduled for a tick that has already passed run at the end of the next step.
+     */
+    pub fn schedule<F>(&mut self, tick: u64, event: F)
+    where
+        F: FnOnce(&mut Ocean, &mut dyn OceanRng) + 'static,
+    {
+        self.scheduled.push((tick, Box::new(event)));
+    }
+
+    /**
+     * Puts a match between the ocean clans `id1` and `id2` on the calendar, to be played at the
+     * end of the step with the given tick, after that step's scheduled events. Matches on the
+     * same tick are played in the order they were scheduled, and matches scheduled for a tick
+     * that has already passed are played at the end of the next step.
+     *
+     * A match is played with `Ocean::compete_ocean_clans`, so its result is recorded in the ocean
+     * clans' reputation standings and published as a `ClanWon` or `ClanDrew` event. A match that
+     * cannot be played then, e.g. because a clan has no living, awake members, is called off
+     * with a `MatchCancelled` event instead.
+     */
+    pub fn schedule_match(&mut self, tick: u64, id1: &str, id2: &str) {
+        let index = self.calendar.partition_point(|planned| planned.tick <= tick);
+        self.calendar.insert(index, ClanMatch { tick, clans: (id1.to_string(), id2.to_string()) });
+    }
+
+    /**
+     * Returns the clan matches still to be played, in the order they will be played.
+     */
+    pub fn calendar(&self) -> &[ClanMatch] {
+        &self.calendar
+    }
+
+    /**
+     * Registers `listener` to receive every event published from now on, after the listeners
+     * already registered.
+     */
+    pub fn subscribe(&mut self, listener: Box<dyn EventListener>) {
+        self.listeners.push(listener);
+    }
+
+    /**
+     * Publishes the events queued in the ocean's beaches and reefs to every listener: those of
+     * each beach in order (see `Beach::drain_events`), then those of each reef, then those of the
+     * ocean's own clans (see `Ocean::ocean_clans`).
+     *
+     * `step` does this itself; call it to publish changes made between steps straight away.
+     */
+    pub fn publish_events(&mut self) {
+        let mut events = Vec::new();
+        for beach in self.ocean.beaches_mut() {
+            events.extend(beach.drain_events());
+        }
+        for reef in self.ocean.reefs() {
+            events.extend(reef.borrow_mut().drain_events());
+        }
+        events.extend(self.ocean.drain_clan_events());
+        for event in &events {
+            self.publish(event);
+        }
+    }
+
+    /**
+     * Registers `hook` to be called with the ocean at the end of every phase of every step, after
+     * the hooks already registered (see `crate::hooks`).
+     */
+    pub fn register_hook(&mut self, hook: Box<dyn TickHook>) {
+        self.hooks.push(hook);
+    }
+
+    // Ends a phase of the current step: runs the hooks of every beach, every reef, and the
+    // simulation, then publishes the phase's events.
+    fn end_phase(&mut self, phase: Phase) {
+        let tick = self.clock;
+        for beach in self.ocean.beaches_mut() {
+            beach.run_hooks(tick, phase, &mut self.rng);
+        }
+        for reef in self.ocean.reefs() {
+            reef.borrow_mut().run_hooks(tick, phase, &mut self.rng);
+        }
+        for hook in self.hooks.iter_mut() {
+            hook.after_phase(tick, phase, &mut self.ocean, &mut self.rng);
+        }
+        self.publish_events();
+    }
+
+    fn publish(&mut self, event: &OceanEvent) {
+        for listener in self.listeners.iter_mut() {
+            listener.on_event(event);
+        }
+    }
+
+    /**
+     * Advances the simulation by one step, in the order described in the module documentation.
+     */
+    pub fn step(&mut self) -> StepReport {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.begin();
+        }
+        let weather = self.weather();
+        let mut report = StepReport {
+            tick: self.clock,
+            weather,
+            crabs_infected: 0,
+            prey_spawned: 0,
+            prey_migrated: 0,
+            reefs_discovered: 0,
+            hunts: 0,
+            catches: 0,
+            crabs_eaten: 0,
+            crabs_spawned: 0,
+            crabs_died: 0,
+            crabs_starved: 0,
+            carcasses_eaten: 0,
+            crabs_migrated: 0,
+            events_run: 0,
+            matches_played: 0,
+        };
+
+        let mut conditions = weather.conditions();
+        for effect in self.seasonal_effects.iter_mut() {
+            effect(weather, &mut conditions, &mut self.ocean);
+        }
+        for beach in self.ocean.beaches_mut() {
+            beach.set_breeding_allowed(conditions.breeding);
+        }
+        self.end_phase(Phase::Weather);
+
+        for beach in self.ocean.beaches_mut() {
+            report.crabs_starved += beach.grow_crabs_older(&mut self.rng);
+            report.carcasses_eaten += beach.scavenge();
+            report.crabs_infected += beach.spread_disease(&mut self.rng);
+        }
+        self.end_phase(Phase::Aging);
+
+        for reef in self.ocean.reefs() {
+            for _ in 0..self.tick_length {
+                report.prey_spawned += reef.borrow_mut().tick_scaled(conditions.regen_rate, &mut self.rng);
+            }
+        }
+        report.prey_migrated = self.ocean.reef_registry().migrate(self.migration_rate);
+        self.end_phase(Phase::Reefs);
+
+        report.reefs_discovered = self.ocean.discover_nearby_reefs();
+        self.end_phase(Phase::Discovery);
+
+        let rng = &mut self.rng;
+        let hunt_chance = conditions.hunt_chance.clamp(0.0, 1.0);
+        for beach in self.ocean.beaches_mut() {
+            beach.sync_crab_clans();
+            beach.sync_camouflage();
+            let zone_chances = beach.zone_hunt_chances();
+            for (crab, zone_chance) in beach.hunters_mut().zip(zone_chances) {
+                // Molting and hibernating crabs stay hidden, and crabs with a behavior tree hunt
+                // when it says so.
+                if crab.is_molting() || crab.is_dead() || crab.is_hibernating() || crab.behavior().is_some() {
+                    continue;
+                }
+                // Fair weather outside any zone keeps a crab hunting without drawing on the
+                // generator.
+                let chance = hunt_chance * zone_chance;
+                if chance < 1.0 && !rng.gen_bool(chance) {
+                    continue;
+                }
+                report.hunts += 1;
+                if crab.hunt_with_rng(rng) {
+                    report.catches += 1;
+                }
+            }
+        }
+        let tally = behavior::run_all(&mut self.ocean, hunt_chance, &mut self.rng);
+        report.hunts += tally.hunts;
+        report.catches += tally.catches;
+        report.crabs_migrated += tally.migrated;
+        self.end_phase(Phase::Hunting);
+
+        for beach in self.ocean.beaches_mut() {
+            report.crabs_eaten += beach.face_predators(&mut self.rng);
+        }
+        self.end_phase(Phase::Predators);
+
+        for beach in self.ocean.beaches_mut() {
+            let change = beach.apply_population_policy(&mut self.rng);
+            report.crabs_spawned += change.spawned;
+            report.crabs_died += change.died;
+        }
+        self.end_phase(Phase::Population);
+
+        report.crabs_migrated += self.ocean.apply_migration_policy(&self.crab_migration);
+        self.end_phase(Phase::Migration);
+
+        let (due, later): (Vec<_>, Vec<_>) = self
+            .scheduled
+            .drain(..)
+            .partition(|(tick, _)| *tick <= self.clock);
+        self.scheduled = later;
+        for (_, event) in due {
+            event(&mut self.ocean, &mut self.rng);
+            report.events_run += 1;
+        }
+        let due = self.calendar.partition_point(|planned| planned.tick <= self.clock);
+        for planned in self.calendar.drain(..due) {
+            if self.ocean.play_ocean_match(&planned.clans.0, &planned.clans.1) {
+                report.matches_played += 1;
+            }
+        }
+        self.end_phase(Phase::Scheduled);
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record(self.clock, &self.ocean);
+        }
+        self.publish(&OceanEvent::StepFinished { tick: self.clock });
+
+        self.clock += 1;
+        if let Some(recording) = self.recording.as_mut() {
+            recording.end(Action::Step);
+        }
+        report
+    }
+
+    /**
+     * Takes `steps` steps, returning the report of each.
+     */
+    pub fn run(&mut self, steps: u64) -> Vec<StepReport> {
+        (0..steps).map(|_| self.step()).collect()
+    }
+
+    /**
+     * Adds `crab` to the end of the beach at index `beach`. Unlike adding it through `ocean_mut`,
+     * this is recorded (see `crate::replay`).
+     *
+     * Returns `OceanError::BeachNotFound` if there is no such beach, or
+     * `OceanError::ReefNotFound` if the crab hunts in a reef that is not in the ocean.
+     */
+    pub fn add_crab(&mut self, beach: usize, crab: Crab) -> Result<(), OceanError> {
+        if beach >= self.ocean.beaches().len() {
+            return Err(OceanError::BeachNotFound(beach.to_string()));
+        }
+        let mut reefs = Vec::new();
+        for reef in crab.reefs() {
+            let id = self.ocean.reef_registry().id_of(&reef).ok_or_else(|| {
+                OceanError::ReefNotFound(format!("discovered by {} but not in the ocean", crab.name()))
+            })?;
+            reefs.push(id);
+        }
+        let action = Action::AddCrab { beach, crab: crab.to_fields(), reefs };
+        self.ocean.beaches_mut().nth(beach).unwrap().add_crab(crab);
+        if let Some(recording) = self.recording.as_mut() {
+            recording.begin();
+            recording.end(action);
+        }
+        Ok(())
+    }
+
+    /**
+     * Breeds the crabs at indices `i` and `j` of the beach at index `beach`, drawing from the
+     * simulation's generator (see `Beach::breed_crabs_with_rng`). Unlike breeding them through
+     * `ocean_mut`, this is recorded (see `crate::replay`).
+     *
+     * Returns `OceanError::BeachNotFound` if there is no such beach, or any error from
+     * `Beach::breed_crabs_with_rng`, in which case nothing is recorded.
+     */
+    pub fn breed_crabs(&mut self, beach: usize, i: usize, j: usize, name: String) -> Result<(), OceanError> {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.begin();
+        }
+        let action = Action::BreedCrabs { beach, i, j, name: name.clone() };
+        self.ocean
+            .beaches_mut()
+            .nth(beach)
+            .ok_or_else(|| OceanError::BeachNotFound(beach.to_string()))?
+            .breed_crabs_with_rng(i, j, name, &mut self.rng)?;
+        if let Some(recording) = self.recording.as_mut() {
+            recording.end(action);
+        }
+        Ok(())
+    }
+
+    /**
+     * Starts recording the simulation from its current state, for `save_recording` (see
+     * `crate::replay`). Starting again throws away the earlier recording.
+     *
+     * Returns an Err if the simulation cannot be snapshotted (see `snapshot`).
+     */
+    pub fn start_recording(&mut self) -> Result<(), OceanError> {
+        let snapshot = self.snapshot()?;
+        match self.recording.as_mut() {
+            Some(recording) => recording.restart(snapshot),
+            None => {
+                let rng = std::mem::replace(&mut self.rng, Generator::Chosen(Box::new(seeded(0))));
+                let (recording, rng) = Recording::new(snapshot, Box::new(rng));
+                self.recording = Some(recording);
+                self.rng = Generator::Chosen(rng);
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Writes everything recorded since `start_recording` to `path` (see `crate::replay`).
+     *
+     * Returns `OceanError::InvalidArgument` if the simulation is not being recorded, or an Err
+     * if the file cannot be written.
+     */
+    pub fn save_recording<P: AsRef<Path>>(&self, path: P) -> Result<(), OceanError> {
+        let recording = self
+            .recording
+            .as_ref()
+            .ok_or_else(|| OceanError::InvalidArgument("The simulation is not being recorded".to_string()))?;
+        fs::write(path, recording.save())?;
+        Ok(())
+    }
+
+    /**
+     * Replays a run written by `save_recording`, returning the simulation as it was when the
+     * recording was saved. Like `load`, the replayed simulation's generator is then seeded with
+     * its clock.
+     *
+     * Returns `OceanError::ReplayDiverged` if a replayed action draws differently than it did
+     * when it was recorded, an error from the action itself, or an Err if the file cannot be
+     * read or parsed.
+     */
+    pub fn replay<P: AsRef<Path>>(path: P) -> Result<Simulation, OceanError> {
+        let mut simulation = Simulation::replay_until(&fs::read_to_string(path)?, None, None)?;
+        simulation.rng = Generator::Chosen(Box::new(seeded(simulation.clock)));
+        Ok(simulation)
+    }
+
+    /**
+     * Rebuilds the simulation as it was when the step with the given tick began, by replaying
+     * what has been recorded since `start_recording` (see `crate::replay`). The rebuilt
+     * simulation can then be queried like any other, e.g. for a clan's roster at that tick.
+     * Its generator is seeded with `tick`, and it is not being recorded.
+     *
+     * The rebuilt simulation takes this one's tick length, seasons and crab migration policy.
+     * Otherwise only what a replay holds is rebuilt, so seasonal effects, scheduled events and
+     * matches, and changes made through `ocean_mut` are left out.
+     *
+     * Returns `OceanError::InvalidArgument` if the simulation is not being recorded or `tick` is
+     * before the recording started or after the current clock, or `OceanError::ReplayDiverged`
+     * if the recording cannot be replayed.
+     */
+    pub fn state_at(&self, tick: u64) -> Result<Simulation, OceanError> {
+        let recording = self
+            .recording
+            .as_ref()
+            .ok_or_else(|| OceanError::InvalidArgument("The simulation is not being recorded".to_string()))?;
+        if tick > self.clock {
+            let message = format!("Tick {} has not happened yet (the clock is at {})", tick, self.clock);
+            return Err(OceanError::InvalidArgument(message));
+        }
+        let mut simulation = Simulation::replay_until(&recording.save(), Some(tick), Some(self))?;
+        if simulation.clock != tick {
+            let message = format!("The recording starts after tick {}", tick);
+            return Err(OceanError::InvalidArgument(message));
+        }
+        simulation.rng = Generator::Chosen(Box::new(seeded(tick)));
+        Ok(simulation)
+    }
+
+    // Replays the actions of the replay `text`, stopping before the step with the given tick, if
+    // any, with the settings a replay leaves out taken from `settings`. The returned simulation
+    // still draws from the replay's generator.
+    fn replay_until(text: &str, tick: Option<u64>, settings: Option<&Simulation>) -> Result<Simulation, OceanError> {
+        let (snapshot, actions) = parse_replay(text)?;
+        let playback = Rc::new(RefCell::new(Playback::default()));
+        let mut simulation = Simulation::from_snapshot(&snapshot, Playback::rng(&playback))
+            // The snapshot starts on the third line of the replay.
+            .map_err(|e| match e {
+                OceanError::Parse(e) => ParseError::new(e.line() + 2, e.message().to_string()).into(),
+                e => e,
+            })?;
+        if let Some(settings) = settings {
+            simulation.tick_length = settings.tick_length;
+            simulation.seasons = settings.seasons.clone();
+            simulation.crab_migration = settings.crab_migration.clone();
+        }
+        for Entry { line, action, draws } in actions {
+            playback.borrow_mut().load(draws);
+            match action {
+                Action::AddCrab { beach, crab, reefs } => {
+                    let mut crab = Crab::from_fields(&crab, line)?;
+                    for id in reefs {
+                        let reef = simulation
+                            .ocean
+                            .reef_registry()
+                            .get(id)
+                            .ok_or_else(|| ParseError::new(line, format!("no reef with id {}", id)))?;
+                        crab.discover_reef(reef);
+                    }
+                    simulation.add_crab(beach, crab)?;
+                }
+                Action::BreedCrabs { beach, i, j, name } => simulation.breed_crabs(beach, i, j, name)?,
+                Action::Step if Some(simulation.clock) == tick => break,
+                Action::Step => {
+                    simulation.step();
+                }
+            }
+            if !playback.borrow().matched() {
+                return Err(OceanError::ReplayDiverged { line });
+            }
+        }
+        Ok(simulation)
+    }
+
+    /**
+     * Writes a snapshot of the simulation to `path` (see the module documentation).
+     *
+     * The snapshot holds the clock, the migration rate, the generator's seed and position if it is
+     * the simulation's own (see `Simulation::new`) and the simulation is not being recorded, the
+     * reefs with their prey, regeneration policies and links, and the beaches with their crabs,
+     * clans, and discovered reefs. A generator chosen with `with_rng`, scheduled events and
+     * matches, listeners, seasons and seasonal effects, tick length, crab migration policy,
+     * metrics, hall of fame, predators and crab larders are not saved.
+     *
+     * Returns an Err if a reef holds or regrows prey that cannot be saved, a crab hunts in a reef
+     * that is not in the ocean, or the file cannot be written.
+     */
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), OceanError> {
+        fs::write(path, self.snapshot()?)?;
+        Ok(())
+    }
+
+    /**
+     * Resumes a simulation saved with `save`. If the snapshot holds the generator's position, the
+     * resumed simulation goes on drawing where the saved one left off, so it continues the saved
+     * run exactly. Otherwise its generator is seeded with the saved clock, so resuming the same
+     * snapshot twice gives the same run. Use `load_with_rng` to choose another generator.
+     */
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Simulation, OceanError> {
+        let text = fs::read_to_string(path)?;
+        let (mut simulation, saved) = Simulation::parse_snapshot(&text, Box::new(seeded(0)))?;
+        simulation.rng = Generator::Own(Box::new(saved.unwrap_or_else(|| ResumableRng::new(simulation.clock))));
+        Ok(simulation)
+    }
+
+    /**
+     * Like `load`, but the resumed simulation draws its randomness from `rng`.
+     */
+    pub fn load_with_rng<P: AsRef<Path>>(path: P, rng: Box<dyn OceanRng>) -> Result<Simulation, OceanError> {
+        Simulation::from_snapshot(&fs::read_to_string(path)?, rng)
+    }
+
+    /**
+     * Returns the snapshot `save` writes.
+     */
+    pub fn snapshot(&self) -> Result<String, OceanError> {
+        let registry = self.ocean.reef_registry();
+        let mut out = String::new();
+        let mut push = |fields: &[String]| {
+            out.push_str(&join_record(fields));
+            out.push('\n');
+        };
+        push(&["ocean".to_string(), SNAPSHOT_VERSION.to_string()]);
+        push(&["clock".to_string(), self.clock.to_string()]);
+        push(&["migration".to_string(), self.migration_rate.to_string()]);
+        if let Generator::Own(rng) = &self.rng {
+            push(&["rng".to_string(), rng.seed().to_string(), rng.position().to_string()]);
+        }
+
+        let mut reefs = String::new();
+        for reef in registry.iter() {
+            reefs.push_str(&reef.borrow().save()?);
+        }
+        let mut links = Vec::new();
+        for id in 0..registry.len() {
+            for &other in registry.links_of(id).iter().filter(|&&other| other > id) {
+                links.push(vec!["link".to_string(), id.to_string(), other.to_string()]);
+            }
+        }
+
+        let mut beaches = Vec::new();
+        for beach in self.ocean.beaches() {
+            let mut text = String::from("beach\n");
+            text.push_str(&beach.save());
+            for (index, crab) in beach.crabs().enumerate() {
+                if crab.reefs().next().is_none() {
+                    continue;
+                }
+                let mut fields = vec!["discovered".to_string(), index.to_string()];
+                for reef in crab.reefs() {
+                    let id = registry.id_of(&reef).ok_or_else(|| {
+                        OceanError::ReefNotFound(format!("discovered by {} but not in the ocean", crab.name()))
+                    })?;
+                    fields.push(id.to_string());
+                }
+                text.push_str(&join_record(&fields));
+                text.push('\n');
+            }
+            beaches.push(text);
+        }
+
+        out.push_str(&reefs);
+        for link in links {
+            out.push_str(&join_record(&link));
+            out.push('\n');
+        }
+        for beach in beaches {
+            out.push_str(&beach);
+        }
+        Ok(out)
+    }
+
+    /**
+     * Rebuilds a simulation from a snapshot written by `snapshot`, drawing its randomness from `rng`.
+     */
+    pub fn from_snapshot(text: &str, rng: Box<dyn OceanRng>) -> Result<Simulation, OceanError> {
+        Ok(Simulation::parse_snapshot(text, rng)?.0)
+    }
+
+    // Like `from_snapshot`, but also returns the saved generator, if the snapshot holds one.
+    fn parse_snapshot(text: &str, rng: Box<dyn OceanRng>) -> Result<(Simulation, Option<ResumableRng>), OceanError> {
+        let mut simulation = Simulation::with_rng(Ocean::new(), rng);
+        let mut saved = None;
+        let mut block: Option<Block> = None;
+        let mut seen_header = false;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            if line.is_empty() {
+                continue;
+            }
+            let fields = split_record(line);
+            if !seen_header {
+                if fields[0] != "ocean" || fields.len() != 2 {
+                    return Err(ParseError::new(line_no, "missing ocean snapshot header".to_string()).into());
+                }
+                let version: u32 = parse_field(&fields[1], "snapshot version", line_no)?;
+                if version != SNAPSHOT_VERSION {
+                    let message = format!("unsupported snapshot version {} (expected {})", version, SNAPSHOT_VERSION);
+                    return Err(ParseError::new(line_no, message).into());
+                }
+                seen_header = true;
+                continue;
+            }
+            match (fields[0].as_str(), fields.len()) {
+                ("clock", 2) => simulation.clock = parse_field(&fields[1], "clock", line_no)?,
+                ("migration", 2) => simulation.migration_rate = parse_field(&fields[1], "migration rate", line_no)?,
+                ("rng", 3) => {
+                    let seed = parse_field(&fields[1], "generator seed", line_no)?;
+                    saved = Some(ResumableRng::resume(seed, parse_field(&fields[2], "generator position", line_no)?));
+                }
+                ("link", 3) => {
+                    simulation.finish_block(block.take())?;
+                    let a = parse_field(&fields[1], "reef id", line_no)?;
+                    let b = parse_field(&fields[2], "reef id", line_no)?;
+                    simulation
+                        .ocean
+                        .reef_registry_mut()
+                        .link(a, b)
+                        .map_err(|e| ParseError::new(line_no, e.to_string()))?;
+                }
+                ("reef", _) | ("beach", 1) => {
+                    simulation.finish_block(block.take())?;
+                    let kind = if fields[0] == "reef" { BlockKind::Reef } else { BlockKind::Beach };
+                    block = Some(Block { kind, first_line: line_no, lines: vec![line] });
+                }
+                _ => match block.as_mut() {
+                    Some(block) => block.lines.push(line),
+                    None => {
+                        let message = format!("unexpected record {:?}", fields[0]);
+                        return Err(ParseError::new(line_no, message).into());
+                    }
+                },
+            }
+        }
+        if !seen_header {
+            return Err(ParseError::new(1, "missing ocean snapshot header".to_string()).into());
+        }
+        simulation.finish_block(block)?;
+        Ok((simulation, saved))
+    }
+
+    // Adds the reef or beach whose records were collected in `block` to the ocean.
+    fn finish_block(&mut self, block: Option<Block>) -> Result<(), OceanError> {
+        let Some(block) = block else {
+            return Ok(());
+        };
+        // The loaders count lines from the start of the block; report them against the file.
+        let offset = |e: ParseError| ParseError::new(e.line() + block.first_line - 1, e.message().to_string());
+        match block.kind {
+            BlockKind::Reef => {
+                let reef = Reef::load(&block.lines.join("\n")).map_err(offset)?;
+                self.ocean
+                    .add_reef(reef)
+                    .map_err(|e| ParseError::new(block.first_line, e.to_string()))?;
+            }
+            BlockKind::Beach => {
+                // Everything but the header and the discovered reefs is the beach's own save.
+                let mut beach_lines = vec![""];
+                let mut discovered = Vec::new();
+                for (i, line) in block.lines.iter().enumerate().skip(1) {
+                    if line.starts_with("discovered\t") {
+                        discovered.push((block.first_line + i, split_record(line)));
+                        beach_lines.push("");
+                    } else {
+                        beach_lines.push(line);
+                    }
+                }
+                let mut beach = Beach::load(&beach_lines.join("\n")).map_err(offset)?;
+                for (line_no, fields) in discovered {
+                    self.discover(&mut beach, &fields, line_no)?;
+                }
+                self.ocean
+                    .try_add_beach(beach)
+                    .map_err(|e| ParseError::new(block.first_line, e.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Restores a `discovered` record: a crab index followed by the ids of its reefs.
+    fn discover(&self, beach: &mut Beach, fields: &[String], line: usize) -> Result<(), ParseError> {
+        if fields.len() < 2 {
+            return Err(ParseError::new(line, "discovered record without a crab index".to_string()));
+        }
+        let index: usize = parse_field(&fields[1], "crab index", line)?;
+        if index >= beach.size() {
+            return Err(ParseError::new(line, format!("no crab at index {}", index)));
+        }
+        for field in &fields[2..] {
+            let id = parse_field(field, "reef id", line)?;
+            let reef = self
+                .ocean
+                .reef_registry()
+                .get(id)
+                .ok_or_else(|| ParseError::new(line, format!("no reef with id {}", id)))?;
+            beach.get_crab_mut(index).discover_reef(reef);
+        }
+        Ok(())
+    }
+}
+
+/// The version of the snapshot format written by `Simulation::save`.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+// The records of one reef or beach in a snapshot, collected until the next reef or beach.
+struct Block<'a> {
+    kind: BlockKind,
+    first_line: usize,
+    lines: Vec<&'a str>,
+}
+
+enum BlockKind {
+    Reef,
+    Beach,
+}
@@ -0,0 +1,251 @@
+//! Comparing two states of an ocean, e.g. the same simulation before and after a run, or two runs
+//! with different seeds.
+//!
+//! A `Snapshot` records what is worth comparing about an ocean: every crab's speed, energy and
+//! clan, every reef's population, and every clan's members and territory. `Snapshot::diff` then lists what
+//! changed between two snapshots as an `OceanDiff`:
+//!
+//! ```text
+//! let before = Snapshot::of(simulation.ocean());
+//! simulation.run(100);
+//! println!("{}", Snapshot::diff(&before, &Snapshot::of(simulation.ocean())));
+//! ```
+//!
+//! Crabs are matched by their beach's index and their id (see `Crab::id`), and reefs by their id
+//! in the ocean's `ReefRegistry`.
+
+use crate::clans::ClanDiff;
+use crate::crab::CrabId;
+use crate::error::OceanError;
+use crate::ocean::Ocean;
+use crate::registry::ReefId;
+use crate::rng::seeded;
+use crate::simulation::Simulation;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// The state of an ocean that `Snapshot::diff` compares.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Snapshot {
+    crabs: BTreeMap<(usize, CrabId), CrabState>,
+    reefs: Vec<usize>,
+    // Member names by clan id, for each beach.
+    clans: Vec<HashMap<String, HashSet<String>>>,
+    // The id of the clan holding each held reef, for each beach.
+    territory: Vec<HashMap<ReefId, String>>,
+}
+
+// What a snapshot records about one crab.
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct CrabState {
+    name: String,
+    speed: u32,
+    energy: u32,
+    clan: Option<String>,
+}
+
+impl Snapshot {
+    /**
+     * Takes a snapshot of the ocean as it is now.
+     */
+    pub fn of(ocean: &Ocean) -> Snapshot {
+        let mut snapshot = Snapshot::default();
+        for (beach_index, beach) in ocean.beaches().enumerate() {
+            let clan_system = beach.get_clan_system();
+            for crab in beach.crabs() {
+                let state = CrabState {
+                    name: crab.name().to_string(),
+                    speed: crab.speed(),
+                    energy: crab.energy(),
+                    clan: clan_system.get_clan_of_member(crab.name()).map(str::to_string),
+                };
+                snapshot.crabs.insert((beach_index, crab.id()), state);
+            }
+            snapshot.clans.push(clan_system.owned_rosters());
+            snapshot.territory.push(clan_system.owned_holders());
+        }
+        snapshot.reefs = ocean.reefs().map(|reef| reef.borrow().population()).collect();
+        snapshot
+    }
+
+    /**
+     * Takes a snapshot of the ocean saved in a simulation snapshot (see `Simulation::snapshot`).
+     *
+     * Returns an Err if the text is not a valid simulation snapshot.
+     */
+    pub fn parse(text: &str) -> Result<Snapshot, OceanError> {
+        let simulation = Simulation::from_snapshot(text, Box::new(seeded(0)))?;
+        Ok(Snapshot::of(simulation.ocean()))
+    }
+
+    /**
+     * Lists what changed between the `older` and `newer` snapshots.
+     */
+    pub fn diff(older: &Snapshot, newer: &Snapshot) -> OceanDiff {
+        let mut diff = OceanDiff::default();
+        for (&(beach, id), new) in &newer.crabs {
+            let Some(old) = older.crabs.get(&(beach, id)) else {
+                diff.added_crabs.push(CrabEntry { beach, id, name: new.name.clone() });
+                continue;
+            };
+            let changed = |old, new| if old != new { Some((old, new)) } else { None };
+            let change = CrabChange {
+                beach,
+                id,
+                name: new.name.clone(),
+                speed: changed(old.speed, new.speed),
+                energy: changed(old.energy, new.energy),
+                clan: if old.clan != new.clan { Some((old.clan.clone(), new.clan.clone())) } else { None },
+            };
+            if change.speed.is_some() || change.energy.is_some() || change.clan.is_some() {
+                diff.changed_crabs.push(change);
+            }
+        }
+        for (&(beach, id), old) in &older.crabs {
+            if !newer.crabs.contains_key(&(beach, id)) {
+                diff.removed_crabs.push(CrabEntry { beach, id, name: old.name.clone() });
+            }
+        }
+
+        // Reefs are only ever added to an ocean, so a reef missing from one snapshot had no prey.
+        for id in 0..older.reefs.len().max(newer.reefs.len()) {
+            let old = older.reefs.get(id).copied().unwrap_or(0) as i64;
+            let new = newer.reefs.get(id).copied().unwrap_or(0) as i64;
+            if old != new {
+                diff.reef_deltas.push((id, new - old));
+            }
+        }
+
+        for beach in 0..older.clans.len().max(newer.clans.len()) {
+            let clans = ClanDiff::between(
+                &borrowed(older.clans.get(beach)),
+                &borrowed_territory(older.territory.get(beach)),
+                &borrowed(newer.clans.get(beach)),
+                &borrowed_territory(newer.territory.get(beach)),
+            );
+            if !clans.is_empty() {
+                diff.clan_changes.push((beach, clans));
+            }
+        }
+        diff
+    }
+}
+
+// Borrows a beach's rosters for `ClanDiff::between`. A beach missing from a snapshot has no clans.
+fn borrowed(rosters: Option<&HashMap<String, HashSet<String>>>) -> HashMap<&str, HashSet<&str>> {
+    let Some(rosters) = rosters else {
+        return HashMap::new();
+    };
+    rosters
+        .iter()
+        .map(|(clan_id, members)| (clan_id.as_str(), members.iter().map(String::as_str).collect()))
+        .collect()
+}
+
+// Borrows a beach's territory for `ClanDiff::between`, like `borrowed`.
+fn borrowed_territory(territory: Option<&HashMap<ReefId, String>>) -> HashMap<ReefId, &str> {
+    territory.map_or_else(HashMap::new, |territory| {
+        territory.iter().map(|(&reef, clan_id)| (reef, clan_id.as_str())).collect()
+    })
+}
+
+/// A crab that is in only one of two snapshots.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CrabEntry {
+    /** The index of the crab's beach in its ocean. */
+    pub beach: usize,
+    pub id: CrabId,
+    pub name: String,
+}
+
+/// How a crab in both of two snapshots changed. Each field is None if it did not change, or Some
+/// of its older and newer values.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CrabChange {
+    /** The index of the crab's beach in its ocean. */
+    pub beach: usize,
+    pub id: CrabId,
+    pub name: String,
+    pub speed: Option<(u32, u32)>,
+    pub energy: Option<(u32, u32)>,
+    pub clan: Option<(Option<String>, Option<String>)>,
+}
+
+/// Everything that changed between two snapshots (see `Snapshot::diff`). Crabs are listed by beach
+/// and then id, reefs by id, and clan changes by beach.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct OceanDiff {
+    pub added_crabs: Vec<CrabEntry>,
+    pub removed_crabs: Vec<CrabEntry>,
+    pub changed_crabs: Vec<CrabChange>,
+    /** The change in population of each reef whose population changed. */
+    pub reef_deltas: Vec<(ReefId, i64)>,
+    /** The clan membership changes on each beach where there were any. */
+    pub clan_changes: Vec<(usize, ClanDiff)>,
+}
+
+impl OceanDiff {
+    /**
+     * Returns true if nothing a snapshot records changed.
+     */
+    pub fn is_empty(&self) -> bool {
+        self.added_crabs.is_empty()
+            && self.removed_crabs.is_empty()
+            && self.changed_crabs.is_empty()
+            && self.reef_deltas.is_empty()
+            && self.clan_changes.is_empty()
+    }
+}
+
+impl fmt::Display for OceanDiff {
+    /**
+     * Writes one line per change, e.g. `~ beach 0 crab 3 Ferris: energy 2 -> 6`.
+     */
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let clan_name = |clan: &Option<String>| clan.clone().unwrap_or_else(|| "none".to_string());
+        let mut lines = Vec::new();
+        for crab in &self.added_crabs {
+            lines.push(format!("+ beach {} crab {} {}", crab.beach, crab.id, crab.name));
+        }
+        for crab in &self.removed_crabs {
+            lines.push(format!("- beach {} crab {} {}", crab.beach, crab.id, crab.name));
+        }
+        for crab in &self.changed_crabs {
+            let mut changes = Vec::new();
+            if let Some((old, new)) = crab.speed {
+                changes.push(format!("speed {} -> {}", old, new));
+            }
+            if let Some((old, new)) = crab.energy {
+                changes.push(format!("energy {} -> {}", old, new));
+            }
+            if let Some((old, new)) = &crab.clan {
+                changes.push(format!("clan {} -> {}", clan_name(old), clan_name(new)));
+            }
+            lines.push(format!("~ beach {} crab {} {}: {}", crab.beach, crab.id, crab.name, changes.join(", ")));
+        }
+        for (reef, delta) in &self.reef_deltas {
+            lines.push(format!("~ reef {}: {:+} prey", reef, delta));
+        }
+        for (beach, clans) in &self.clan_changes {
+            for clan in &clans.added_clans {
+                lines.push(format!("+ beach {} clan {}", beach, clan));
+            }
+            for clan in &clans.removed_clans {
+                lines.push(format!("- beach {} clan {}", beach, clan));
+            }
+            for (clan, crab) in &clans.joined {
+                lines.push(format!("~ beach {} clan {}: {} joined", beach, clan, crab));
+            }
+            for (clan, crab) in &clans.left {
+                lines.push(format!("~ beach {} clan {}: {} left", beach, clan, crab));
+            }
+            for (reef, clan) in &clans.captured {
+                lines.push(format!("~ beach {} clan {}: captured reef {}", beach, clan, reef));
+            }
+            for (reef, clan) in &clans.lost {
+                lines.push(format!("~ beach {} clan {}: lost reef {}", beach, clan, reef));
+            }
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,96 @@
+use crate::crab::Crab;
+use crate::events::OceanEvent;
+use std::fmt;
+
+/// A record of a crab's life, written when it dies and kept in the beach's hall of fame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifeSummary {
+    name: String,
+    speed: u32,
+    lifespan: u32,
+    hunts: u32,
+    offspring: u32,
+    clan: Option<String>,
+    notable_events: Vec<OceanEvent>,
+}
+
+impl LifeSummary {
+    /**
+     * Summarizes the life of `crab`, who belonged to `clan` (if any) when it died.
+     */
+    pub fn new(crab: &Crab, clan: Option<String>) -> LifeSummary {
+        LifeSummary {
+            name: crab.name().to_string(),
+            speed: crab.speed(),
+            lifespan: crab.age(),
+            hunts: crab.hunts(),
+            offspring: crab.offspring(),
+            clan,
+            notable_events: crab.notable_events().to_vec(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    /**
+     * Returns how many steps the crab lived (see `Crab::age`).
+     */
+    pub fn lifespan(&self) -> u32 {
+        self.lifespan
+    }
+
+    pub fn hunts(&self) -> u32 {
+        self.hunts
+    }
+
+    pub fn offspring(&self) -> u32 {
+        self.offspring
+    }
+
+    pub fn clan(&self) -> Option<&str> {
+        self.clan.as_deref()
+    }
+
+    /**
+     * Returns the crab's notable events, oldest first (see `Crab::notable_events`).
+     */
+    pub fn notable_events(&self) -> &[OceanEvent] {
+        &self.notable_events
+    }
+}
+
+impl fmt::Display for LifeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (speed {}) lived {} step(s), made {} successful hunt(s) and parented {} crab(s)",
+            self.name, self.speed, self.lifespan, self.hunts, self.offspring
+        )?;
+        match &self.clan {
+            Some(clan) => write!(f, ", and was a member of {}.", clan)?,
+            None => write!(f, ", and never joined a clan.")?,
+        }
+        if !self.notable_events.is_empty() {
+            let events: Vec<String> = self.notable_events.iter().map(describe).collect();
+            write!(f, " Along the way it {}.", events.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// Describes one of a crab's notable events as something the crab did.
+fn describe(event: &OceanEvent) -> String {
+    match event {
+        OceanEvent::CrabInfected { disease, .. } => format!("caught {}", disease),
+        OceanEvent::CrabRecovered { disease, .. } => format!("recovered from {}", disease),
+        OceanEvent::MoltEnded { speed, .. } => format!("molted to speed {}", speed),
+        OceanEvent::CrabStarved { .. } => "starved".to_string(),
+        event => format!("{:?}", event),
+    }
+}
@@ -0,0 +1,217 @@
+//! Optional instrumentation (the `trace` feature) of hunts, prey taken from reefs, breeding,
+//! and clan competitions, for diagnosing why things happen without println debugging.
+//!
+//! Instrumented code opens spans (e.g. one per hunt) and emits events inside them, each with
+//! structured fields such as the crab's name, the reef, and the outcome. They are passed to the
+//! current thread's `Subscriber`, if one is installed with `set_subscriber`.
+//!
+//! Without the feature the instrumentation compiles away to nothing.
+
+#[cfg(feature = "trace")]
+use std::cell::{Cell, RefCell};
+#[cfg(feature = "trace")]
+use std::rc::Rc;
+
+/// The fields of a span or event: names and formatted values.
+pub type Fields = [(&'static str, String)];
+
+/// Receives the spans and events of instrumented code. `depth` is the number of spans the
+/// event or span is nested in.
+#[cfg(feature = "trace")]
+pub trait Subscriber {
+    fn enter(&mut self, span: &'static str, fields: &Fields, depth: usize);
+    fn event(&mut self, name: &'static str, fields: &Fields, depth: usize);
+    fn exit(&mut self, span: &'static str, depth: usize);
+}
+
+#[cfg(feature = "trace")]
+thread_local!(
+    static SUBSCRIBER: RefCell<Option<Box<dyn Subscriber>>> = RefCell::new(None);
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+);
+
+/**
+ * Installs `subscriber` for the current thread, returning the one it replaces.
+ */
+#[cfg(feature = "trace")]
+pub fn set_subscriber(subscriber: Box<dyn Subscriber>) -> Option<Box<dyn Subscriber>> {
+    SUBSCRIBER.with(|s| s.borrow_mut().replace(subscriber))
+}
+
+/**
+ * Removes and returns the current thread's subscriber.
+ */
+#[cfg(feature = "trace")]
+pub fn take_subscriber() -> Option<Box<dyn Subscriber>> {
+    SUBSCRIBER.with(|s| s.borrow_mut().take())
+}
+
+/**
+ * Returns true if instrumented code on this thread has anywhere to send its spans and events.
+ * Always false without the `trace` feature.
+ */
+pub fn enabled() -> bool {
+    #[cfg(feature = "trace")]
+    return SUBSCRIBER.with(|s| s.borrow().is_some());
+    #[cfg(not(feature = "trace"))]
+    return false;
+}
+
+#[cfg(feature = "trace")]
+fn with_subscriber<F: FnOnce(&mut dyn Subscriber)>(f: F) {
+    SUBSCRIBER.with(|s| {
+        if let Some(subscriber) = s.borrow_mut().as_mut() {
+            f(subscriber.as_mut());
+        }
+    });
+}
+
+/// An open span, which is closed when the guard is dropped. Returned by `span!`.
+#[must_use = "the span closes as soon as its guard is dropped"]
+pub struct SpanGuard {
+    #[cfg(feature = "trace")]
+    name: Option<&'static str>,
+}
+
+impl SpanGuard {
+    #[cfg(feature = "trace")]
+    pub(crate) fn enter(name: &'static str, fields: &Fields) -> SpanGuard {
+        let depth = DEPTH.with(|d| d.replace(d.get() + 1));
+        with_subscriber(|s| s.enter(name, fields, depth));
+        SpanGuard { name: Some(name) }
+    }
+
+    // A guard for a span that was not entered because nothing is listening.
+    pub(crate) fn none() -> SpanGuard {
+        SpanGuard {
+            #[cfg(feature = "trace")]
+            name: None,
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Drop for SpanGuard {
+    fn drop(&mut self) {
+        if let Some(name) = self.name {
+            let depth = DEPTH.with(|d| {
+                d.set(d.get() - 1);
+                d.get()
+            });
+            with_subscriber(|s| s.exit(name, depth));
+        }
+    }
+}
+
+#[cfg(feature = "trace")]
+pub(crate) fn emit(name: &'static str, fields: &Fields) {
+    let depth = DEPTH.with(Cell::get);
+    with_subscriber(|s| s.event(name, fields, depth));
+}
+
+/**
+ * Opens a span with the given name and fields, returning a `SpanGuard`:
+ * `let _span = span!("hunt", crab = self.name());`
+ */
+macro_rules! span {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "trace")]
+        let guard = if $crate::trace::enabled() {
+            $crate::trace::SpanGuard::enter($name, &[$((stringify!($field), $value.to_string())),*])
+        } else {
+            $crate::trace::SpanGuard::none()
+        };
+        #[cfg(not(feature = "trace"))]
+        let guard = $crate::trace::SpanGuard::none();
+        guard
+    }};
+}
+
+/**
+ * Emits an event with the given name and fields inside the current span:
+ * `event!("caught", prey = prey.kind());`. The fields are only formatted if a subscriber is installed.
+ */
+macro_rules! event {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {
+        #[cfg(feature = "trace")]
+        if $crate::trace::enabled() {
+            $crate::trace::emit($name, &[$((stringify!($field), $value.to_string())),*]);
+        }
+    };
+}
+
+pub(crate) use event;
+pub(crate) use span;
+
+/// One span entry, event, or span exit, as recorded by a `Recorder`.
+#[cfg(feature = "trace")]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Record {
+    Enter { span: &'static str, fields: Vec<(&'static str, String)>, depth: usize },
+    Event { name: &'static str, fields: Vec<(&'static str, String)>, depth: usize },
+    Exit { span: &'static str, depth: usize },
+}
+
+/// A subscriber that keeps every record in a shared list, e.g. for tests.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    records: Rc<RefCell<Vec<Record>>>,
+}
+
+#[cfg(feature = "trace")]
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder::default()
+    }
+
+    /**
+     * Returns a copy of everything recorded so far, by this recorder or any of its clones.
+     */
+    pub fn records(&self) -> Vec<Record> {
+        self.records.borrow().clone()
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Subscriber for Recorder {
+    fn enter(&mut self, span: &'static str, fields: &Fields, depth: usize) {
+        self.records.borrow_mut().push(Record::Enter { span, fields: fields.to_vec(), depth });
+    }
+
+    fn event(&mut self, name: &'static str, fields: &Fields, depth: usize) {
+        self.records.borrow_mut().push(Record::Event { name, fields: fields.to_vec(), depth });
+    }
+
+    fn exit(&mut self, span: &'static str, depth: usize) {
+        self.records.borrow_mut().push(Record::Exit { span, depth });
+    }
+}
+
+/// A subscriber that prints an indented line to standard error for every span and event.
+#[cfg(feature = "trace")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StderrSubscriber;
+
+#[cfg(feature = "trace")]
+fn format_fields(fields: &Fields) -> String {
+    fields
+        .iter()
+        .map(|(name, value)| format!(" {}={}", name, value))
+        .collect()
+}
+
+#[cfg(feature = "trace")]
+impl Subscriber for StderrSubscriber {
+    fn enter(&mut self, span: &'static str, fields: &Fields, depth: usize) {
+        eprintln!("{}> {}{}", "  ".repeat(depth), span, format_fields(fields));
+    }
+
+    fn event(&mut self, name: &'static str, fields: &Fields, depth: usize) {
+        eprintln!("{}- {}{}", "  ".repeat(depth), name, format_fields(fields));
+    }
+
+    fn exit(&mut self, span: &'static str, depth: usize) {
+        eprintln!("{}< {}", "  ".repeat(depth), span);
+    }
+}
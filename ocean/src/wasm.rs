@@ -0,0 +1,489 @@
+//! Handles for driving the ocean from JavaScript, e.g. in a browser teaching tool (the `wasm`
+//! feature).
+//!
+//! The handles are shaped for `wasm-bindgen`: each one owns its state, every method takes and
+//! returns only numbers, booleans, strings, options, and other handles, and errors are strings.
+//! Anything richer (a beach with all its crabs, a step report, the events since the last call)
+//! is returned as a JSON snapshot, so the page can render it with `JSON.parse`.
+//!
+//! Counts and indices are `u32` and the simulation clock is an `f64`, because JavaScript numbers
+//! cannot hold every `u64` or `usize`.
+
+use crate::beach::Beach;
+use crate::color::Color;
+use crate::crab::Crab;
+use crate::events::OceanEvent;
+use crate::ocean::Ocean;
+use crate::persist::json_string;
+use crate::prey::{Algae, Clam, Minnow, Shrimp};
+use crate::reef::Reef;
+use crate::simulation::{Simulation, StepReport};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A beach owned by JavaScript. Hand it to `SimulationHandle::add_beach` to simulate it.
+#[derive(Debug)]
+pub struct BeachHandle {
+    beach: Beach,
+}
+
+impl BeachHandle {
+    pub fn new() -> BeachHandle {
+        BeachHandle { beach: Beach::new() }
+    }
+
+    /**
+     * Loads a beach written by `save` (or `Beach::save`).
+     */
+    pub fn load(text: &str) -> Result<BeachHandle, String> {
+        let beach = Beach::load(text).map_err(|e| e.to_string())?;
+        Ok(BeachHandle { beach })
+    }
+
+    pub fn save(&self) -> String {
+        self.beach.save()
+    }
+
+    /**
+     * Adds a crab with a `#rrggbb` color and a diet such as "Plants", returning its index.
+     */
+    pub fn add_crab(&mut self, name: &str, speed: u32, color: &str, diet: &str) -> Result<u32, String> {
+        let crab = Crab::new(name.to_string(), speed, Color::from_hex(color)?, diet.parse()?);
+        self.beach.add_crab(crab);
+        Ok(self.crab_count() - 1)
+    }
+
+    pub fn crab_count(&self) -> u32 {
+        self.beach.size() as u32
+    }
+
+    /**
+     * Returns a copy of the crab at `index`. Changes to the beach are not reflected in it.
+     */
+    pub fn crab(&self, index: u32) -> Result<CrabHandle, String> {
+        self.check_index(index)?;
+        Ok(CrabHandle { crab: self.beach.get_crab(index as usize).clone() })
+    }
+
+    /**
+     * Returns the index of the first crab with the given name.
+     */
+    pub fn find_crab(&self, name: &str) -> Option<u32> {
+        self.beach.find_crab_index(name).map(|index| index as u32)
+    }
+
+    /**
+     * Breeds the crabs at indices `i` and `j`, returning the index of their offspring.
+     */
+    pub fn breed(&mut self, i: u32, j: u32, name: &str) -> Result<u32, String> {
+        self.beach.try_breed_crabs(i as usize, j as usize, name.to_string())?;
+        Ok(self.crab_count() - 1)
+    }
+
+    /**
+     * Buries the crab at `index`, returning the summary of its life.
+     */
+    pub fn bury(&mut self, index: u32) -> Result<String, String> {
+        Ok(self.beach.try_bury_crab(index as usize)?.to_string())
+    }
+
+    pub fn join_clan(&mut self, clan: &str, crab: &str) -> Result<(), String> {
+        Ok(self.beach.try_add_member_to_clan(clan, crab)?)
+    }
+
+    /**
+     * Holds a competition between two clans, returning the winner, or None for a draw.
+     */
+    pub fn compete(&mut self, clan1: &str, clan2: &str) -> Result<Option<String>, String> {
+        Ok(self.beach.get_winner_clan(clan1, clan2)?)
+    }
+
+    /**
+     * Returns the beach as JSON: `{"crabs": [...], "clans": [{"id", "members", "reputation"}]}`,
+     * with each crab as described on `CrabHandle::snapshot`.
+     */
+    pub fn snapshot(&self) -> String {
+        beach_json(&self.beach)
+    }
+
+    /**
+     * Returns the events that happened on this beach since the last call, as a JSON array
+     * (see `events_json`).
+     */
+    pub fn take_events(&mut self) -> String {
+        events_json(&self.beach.drain_events())
+    }
+
+    fn check_index(&self, index: u32) -> Result<(), String> {
+        if index >= self.crab_count() {
+            return Err(format!("no crab at index {}; the beach has {} crabs", index, self.crab_count()));
+        }
+        Ok(())
+    }
+}
+
+/// A copy of a crab, as returned by `BeachHandle::crab`.
+#[derive(Debug, Clone)]
+pub struct CrabHandle {
+    crab: Crab,
+}
+
+impl CrabHandle {
+    pub fn name(&self) -> String {
+        self.crab.name().to_string()
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.crab.speed()
+    }
+
+    /**
+     * Returns the crab's color as `#rrggbb`, ready for CSS.
+     */
+    pub fn color(&self) -> String {
+        self.crab.color().to_hex()
+    }
+
+    pub fn diet(&self) -> String {
+        format!("{:?}", self.crab.diet())
+    }
+
+    pub fn pattern(&self) -> String {
+        format!("{:?}", self.crab.pattern())
+    }
+
+    pub fn hunts(&self) -> u32 {
+        self.crab.hunts()
+    }
+
+    pub fn offspring(&self) -> u32 {
+        self.crab.offspring()
+    }
+
+    pub fn energy(&self) -> u32 {
+        self.crab.energy()
+    }
+
+    pub fn age(&self) -> u32 {
+        self.crab.age()
+    }
+
+    /**
+     * Returns the crab as a JSON object with the fields `name`, `speed`, `color`, `diet`,
+     * `pattern`, `hunts`, `offspring`, `energy`, and `age`.
+     */
+    pub fn snapshot(&self) -> String {
+        crab_json(&self.crab)
+    }
+}
+
+/// A simulation owned by JavaScript. Reports, snapshots, and events are JSON.
+pub struct SimulationHandle {
+    simulation: Simulation,
+    // Every event published since the last `take_events`.
+    events: Rc<RefCell<Vec<OceanEvent>>>,
+}
+
+impl SimulationHandle {
+    /**
+     * Creates a simulation of an empty ocean, seeded with `seed`.
+     */
+    pub fn new(seed: u32) -> SimulationHandle {
+        let mut simulation = Simulation::new(Ocean::new(), seed as u64);
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let queue = events.clone();
+        simulation.subscribe(Box::new(move |event: &OceanEvent| queue.borrow_mut().push(event.clone())));
+        SimulationHandle { simulation, events }
+    }
+
+    /**
+     * Moves the beach into the ocean, returning its index.
+     */
+    pub fn add_beach(&mut self, beach: BeachHandle) -> u32 {
+        self.simulation.ocean_mut().add_beach(beach.beach);
+        self.simulation.ocean().beaches().count() as u32 - 1
+    }
+
+    /**
+     * Adds a reef stocked like `Ocean::generate_reef`, under a name that crabs can be sent to
+     * with `discover_reef`.
+     */
+    pub fn add_reef(&mut self, name: &str, minnows: u32, shrimp: u32, clams: u32, algae: u32) -> Result<(), String> {
+        let mut reef = Reef::with_name(name.to_string());
+        for _ in 0..minnows {
+            reef.add_prey(Box::new(Minnow::new(25)));
+        }
+        for _ in 0..shrimp {
+            reef.add_prey(Box::new(Shrimp::new(1)));
+        }
+        for _ in 0..clams {
+            reef.add_prey(Box::new(Clam::new()));
+        }
+        for _ in 0..algae {
+            reef.add_prey(Box::new(Algae::new()));
+        }
+        self.simulation.ocean_mut().add_reef(reef)?;
+        Ok(())
+    }
+
+    /**
+     * Lets the crab at index `crab` on the beach at index `beach` hunt in the named reef.
+     */
+    pub fn discover_reef(&mut self, beach: u32, crab: u32, reef: &str) -> Result<(), String> {
+        let ocean = self.simulation.ocean_mut();
+        let reef = ocean.reef_by_name(reef).ok_or_else(|| format!("no reef named {}", reef))?;
+        let beach = ocean
+            .beaches_mut()
+            .nth(beach as usize)
+            .ok_or_else(|| format!("no beach at index {}", beach))?;
+        if crab as usize >= beach.size() {
+            return Err(format!("no crab at index {}; the beach has {} crabs", crab, beach.size()));
+        }
+        beach.get_crab_mut(crab as usize).discover_reef(reef);
+        Ok(())
+    }
+
+    pub fn set_migration_rate(&mut self, rate: f64) {
+        self.simulation.set_migration_rate(rate);
+    }
+
+    pub fn clock(&self) -> f64 {
+        self.simulation.clock() as f64
+    }
+
+    /**
+     * Runs one step, returning its report as JSON: `{"tick", "weather", "crabs_infected",
+     * "prey_spawned", "prey_migrated", "reefs_discovered", "hunts", "catches", "crabs_eaten",
+     * "crabs_spawned", "crabs_died", "crabs_starved", "carcasses_eaten", "crabs_migrated",
+     * "events_run", "matches_played"}`.
+     */
+    pub fn step(&mut self) -> String {
+        report_json(&self.simulation.step())
+    }
+
+    /**
+     * Runs `steps` steps, returning a JSON array of their reports.
+     */
+    pub fn run(&mut self, steps: u32) -> String {
+        let reports: Vec<String> = self.simulation.run(steps as u64).iter().map(report_json).collect();
+        format!("[{}]", reports.join(","))
+    }
+
+    /**
+     * Returns the whole ocean as JSON: `{"clock", "beaches": [...], "reefs": [{"name",
+     * "population", "capacity"}]}`, with each beach as described on `BeachHandle::snapshot`.
+     */
+    pub fn snapshot(&self) -> String {
+        ocean_json(&self.simulation)
+    }
+
+    /**
+     * Returns the events published since the last call, as a JSON array (see `events_json`).
+     */
+    pub fn take_events(&mut self) -> String {
+        events_json(&self.events.borrow_mut().drain(..).collect::<Vec<_>>())
+    }
+}
+
+/**
+ * Formats a simulation's ocean as JSON, as described on `SimulationHandle::snapshot`.
+ */
+pub fn ocean_json(simulation: &Simulation) -> String {
+    let ocean = simulation.ocean();
+    let beaches: Vec<String> = ocean.beaches().map(beach_json).collect();
+    let reefs: Vec<String> = ocean
+        .reefs()
+        .map(|reef| {
+            let reef = reef.borrow();
+            format!(
+                "{{\"name\":{},\"population\":{},\"capacity\":{}}}",
+                reef.name().map_or(String::from("null"), json_string),
+                reef.population(),
+                reef.capacity().map_or(String::from("null"), |capacity| capacity.to_string())
+            )
+        })
+        .collect();
+    format!(
+        "{{\"clock\":{},\"beaches\":[{}],\"reefs\":[{}]}}",
+        simulation.clock(),
+        beaches.join(","),
+        reefs.join(",")
+    )
+}
+
+fn crab_json(crab: &Crab) -> String {
+    format!(
+        "{{\"name\":{},\"speed\":{},\"color\":\"{}\",\"diet\":\"{:?}\",\"pattern\":\"{:?}\",\"hunts\":{},\"offspring\":{},\"energy\":{},\"age\":{}}}",
+        json_string(crab.name()),
+        crab.speed(),
+        crab.color().to_hex(),
+        crab.diet(),
+        crab.pattern(),
+        crab.hunts(),
+        crab.offspring(),
+        crab.energy(),
+        crab.age()
+    )
+}
+
+/**
+ * Formats a beach as JSON, as described on `BeachHandle::snapshot`.
+ */
+pub fn beach_json(beach: &Beach) -> String {
+    let crabs: Vec<String> = beach.crabs().map(crab_json).collect();
+    let clan_system = beach.get_clan_system();
+    let clans: Vec<String> = clan_system
+        .get_clan_ids()
+        .iter()
+        .map(|id| {
+            let mut members = clan_system.get_clan_member_names(id);
+            members.sort();
+            let members: Vec<String> = members.iter().map(|name| json_string(name)).collect();
+            format!(
+                "{{\"id\":{},\"members\":[{}],\"reputation\":{}}}",
+                json_string(id),
+                members.join(","),
+                clan_system.reputation(id)
+            )
+        })
+        .collect();
+    format!("{{\"crabs\":[{}],\"clans\":[{}]}}", crabs.join(","), clans.join(","))
+}
+
+/**
+ * Formats a step report as JSON, as described on `SimulationHandle::step`.
+ */
+pub fn report_json(report: &StepReport) -> String {
+    format!(
+        "{{\"tick\":{},\"weather\":\"{:?}\",\"crabs_infected\":{},\"prey_spawned\":{},\"prey_migrated\":{},\"reefs_discovered\":{},\"hunts\":{},\"catches\":{},\"crabs_eaten\":{},\"crabs_spawned\":{},\"crabs_died\":{},\"crabs_starved\":{},\"carcasses_eaten\":{},\"crabs_migrated\":{},\"events_run\":{},\"matches_played\":{}}}",
+        report.tick,
+        report.weather,
+        report.crabs_infected,
+        report.prey_spawned,
+        report.prey_migrated,
+        report.reefs_discovered,
+        report.hunts,
+        report.catches,
+        report.crabs_eaten,
+        report.crabs_spawned,
+        report.crabs_died,
+        report.crabs_starved,
+        report.carcasses_eaten,
+        report.crabs_migrated,
+        report.events_run,
+        report.matches_played
+    )
+}
+
+fn optional_name(name: &Option<String>) -> String {
+    name.as_deref().map_or(String::from("null"), json_string)
+}
+
+/**
+ * Formats events as a JSON array of objects. Each has a `type` naming its `OceanEvent` variant,
+ * plus the variant's fields; tuple fields become arrays.
+ */
+pub fn events_json(events: &[OceanEvent]) -> String {
+    let objects: Vec<String> = events
+        .iter()
+        .map(|event| match event {
+            OceanEvent::CrabBorn { name, parents } => format!(
+                "{{\"type\":\"CrabBorn\",\"name\":{},\"parents\":[{},{}]}}",
+                json_string(name),
+                json_string(&parents.0),
+                json_string(&parents.1)
+            ),
+            OceanEvent::CrabSpawned { name } => format!("{{\"type\":\"CrabSpawned\",\"name\":{}}}", json_string(name)),
+            OceanEvent::CrabStarved { crab } => format!("{{\"type\":\"CrabStarved\",\"crab\":{}}}", json_string(crab)),
+            OceanEvent::CarcassEaten { crab, carcass } => format!(
+                "{{\"type\":\"CarcassEaten\",\"crab\":{},\"carcass\":{}}}",
+                json_string(crab),
+                json_string(carcass)
+            ),
+            OceanEvent::CrabDied { name } => format!("{{\"type\":\"CrabDied\",\"name\":{}}}", json_string(name)),
+            OceanEvent::CrabMigrated { name, from, to } => format!(
+                "{{\"type\":\"CrabMigrated\",\"name\":{},\"from\":{},\"to\":{}}}",
+                json_string(name),
+                from,
+                to
+            ),
+            OceanEvent::CrabEaten { crab, predator } => format!(
+                "{{\"type\":\"CrabEaten\",\"crab\":{},\"predator\":{}}}",
+                json_string(crab),
+                json_string(predator)
+            ),
+            OceanEvent::MoltStarted { crab } => format!("{{\"type\":\"MoltStarted\",\"crab\":{}}}", json_string(crab)),
+            OceanEvent::MoltEnded { crab, speed } => {
+                format!("{{\"type\":\"MoltEnded\",\"crab\":{},\"speed\":{}}}", json_string(crab), speed)
+            }
+            OceanEvent::CrabInfected { crab, disease } => format!(
+                "{{\"type\":\"CrabInfected\",\"crab\":{},\"disease\":{}}}",
+                json_string(crab),
+                json_string(disease)
+            ),
+            OceanEvent::CrabRecovered { crab, disease } => format!(
+                "{{\"type\":\"CrabRecovered\",\"crab\":{},\"disease\":{}}}",
+                json_string(crab),
+                json_string(disease)
+            ),
+            OceanEvent::PreyCaught { crab, prey } => format!(
+                "{{\"type\":\"PreyCaught\",\"crab\":{},\"prey\":{}}}",
+                json_string(crab),
+                json_string(prey)
+            ),
+            OceanEvent::PreyEscaped { crab, prey } => format!(
+                "{{\"type\":\"PreyEscaped\",\"crab\":{},\"prey\":{}}}",
+                json_string(crab),
+                json_string(prey)
+            ),
+            OceanEvent::ClanJoined { clan, crab } => format!(
+                "{{\"type\":\"ClanJoined\",\"clan\":{},\"crab\":{}}}",
+                json_string(clan),
+                json_string(crab)
+            ),
+            OceanEvent::ClanLeft { clan, crab } => format!(
+                "{{\"type\":\"ClanLeft\",\"clan\":{},\"crab\":{}}}",
+                json_string(clan),
+                json_string(crab)
+            ),
+            OceanEvent::ClanWon { winner, loser } => format!(
+                "{{\"type\":\"ClanWon\",\"winner\":{},\"loser\":{}}}",
+                json_string(winner),
+                json_string(loser)
+            ),
+            OceanEvent::ClanDrew { clans } => format!(
+                "{{\"type\":\"ClanDrew\",\"clans\":[{},{}]}}",
+                json_string(&clans.0),
+                json_string(&clans.1)
+            ),
+            OceanEvent::MatchCancelled { clans, reason } => format!(
+                "{{\"type\":\"MatchCancelled\",\"clans\":[{},{}],\"reason\":{}}}",
+                json_string(&clans.0),
+                json_string(&clans.1),
+                json_string(reason)
+            ),
+            OceanEvent::ReefRaided { attacker, defender, reef, captured } => format!(
+                "{{\"type\":\"ReefRaided\",\"attacker\":{},\"defender\":{},\"reef\":{},\"captured\":{}}}",
+                json_string(attacker),
+                json_string(defender),
+                reef,
+                captured
+            ),
+            OceanEvent::PreySpawned { reef, count } => format!(
+                "{{\"type\":\"PreySpawned\",\"reef\":{},\"count\":{}}}",
+                optional_name(reef),
+                count
+            ),
+            OceanEvent::PreyDied { reef, count } => format!(
+                "{{\"type\":\"PreyDied\",\"reef\":{},\"count\":{}}}",
+                optional_name(reef),
+                count
+            ),
+            OceanEvent::ReefDepleted { reef } => {
+                format!("{{\"type\":\"ReefDepleted\",\"reef\":{}}}", optional_name(reef))
+            }
+            OceanEvent::StepFinished { tick } => format!("{{\"type\":\"StepFinished\",\"tick\":{}}}", tick),
+        })
+        .collect();
+    format!("[{}]", objects.join(","))
+}
@@ -0,0 +1,108 @@
+//! Weather and seasons for a `Simulation`.
+//!
+//! Each step has a `Weather`, taken from the simulation's `Seasons`. The weather sets the step's
+//! `Conditions`: how likely each crab is to go out hunting, how fast reefs regrow, and whether
+//! crabs can breed. Seasonal effects added with `Simulation::add_seasonal_effect` can adjust the
+//! conditions, or change the ocean itself, before the step runs.
+//!
+//! ```text
+//! let seasons = Seasons::new().then(Weather::Sunny, 6).then(Weather::Storm, 1).then(Weather::Winter, 3);
+//! simulation.set_seasons(seasons);
+//! simulation.add_seasonal_effect(|weather, conditions, _ocean| {
+//!     if weather == Weather::Storm {
+//!         conditions.regen_rate = 2.0;
+//!     }
+//! });
+//! ```
+
+use crate::ocean::Ocean;
+
+/// The weather over the ocean during one simulation step.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Weather {
+    Sunny,
+    Storm,
+    Winter,
+}
+
+impl Weather {
+    /**
+     * Returns the conditions this weather brings, before any seasonal effects:
+     *   - Sunny: every crab hunts, reefs regrow at their usual rate, and crabs can breed.
+     *   - Storm: half the crabs shelter instead of hunting and reefs regrow at half the rate.
+     *   - Winter: a quarter of the crabs shelter, reefs regrow at a quarter of the rate, and
+     *     crabs cannot breed.
+     */
+    pub fn conditions(self) -> Conditions {
+        match self {
+            Weather::Sunny => Conditions { hunt_chance: 1.0, regen_rate: 1.0, breeding: true },
+            Weather::Storm => Conditions { hunt_chance: 0.5, regen_rate: 0.5, breeding: true },
+            Weather::Winter => Conditions { hunt_chance: 0.75, regen_rate: 0.25, breeding: false },
+        }
+    }
+}
+
+/// How the weather changes one simulation step.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Conditions {
+    /** The chance that each crab goes out to hunt, from 0.0 to 1.0. */
+    pub hunt_chance: f64,
+    /** Multiplies every reef's regeneration rates (see `Reef::tick_scaled`). */
+    pub regen_rate: f64,
+    /** Whether crabs can breed (see `Beach::set_breeding_allowed`). */
+    pub breeding: bool,
+}
+
+/// A hook run at the start of every step with that step's weather, which may adjust the step's
+/// conditions or change the ocean.
+pub type SeasonalEffect = Box<dyn FnMut(Weather, &mut Conditions, &mut Ocean)>;
+
+/// A repeating cycle of weather, e.g. six sunny steps, a storm, then three steps of winter.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Seasons {
+    cycle: Vec<(Weather, u64)>,
+}
+
+impl Seasons {
+    /**
+     * Creates an empty cycle, under which every step is sunny.
+     */
+    pub fn new() -> Seasons {
+        Seasons { cycle: Vec::new() }
+    }
+
+    /**
+     * Adds `steps` steps of `weather` to the end of the cycle. Adding 0 steps does nothing.
+     */
+    pub fn then(mut self, weather: Weather, steps: u64) -> Seasons {
+        if steps > 0 {
+            self.cycle.push((weather, steps));
+        }
+        self
+    }
+
+    /**
+     * Returns the number of steps before the cycle repeats, which is 0 for an empty cycle.
+     */
+    pub fn year(&self) -> u64 {
+        self.cycle.iter().map(|&(_, steps)| steps).sum()
+    }
+
+    /**
+     * Returns the weather of the step with the given tick.
+     */
+    pub fn weather_at(&self, tick: u64) -> Weather {
+        let year = self.year();
+        if year == 0 {
+            return Weather::Sunny;
+        }
+        let mut day = tick % year;
+        for &(weather, steps) in self.cycle.iter() {
+            if day < steps {
+                return weather;
+            }
+            day -= steps;
+        }
+        unreachable!("the day of the year is within the cycle")
+    }
+}
@@ -0,0 +1,73 @@
+//! Zones: named parts of a beach, such as its rocks, its sand, or its tidepools, each with its own
+//! effect on the crabs assigned to it (see `Beach::add_zone` and `Beach::assign_zone`).
+//!
+//! ```text
+//! beach.add_zone(Zone::new("rocky").with_hunt_chance(0.5))?;
+//! beach.add_zone(Zone::new("tidepool").with_breeding(false))?;
+//! beach.assign_zone(0, "rocky")?;
+//! ```
+//!
+//! A zone's hunt chance multiplies the weather's in each simulation step, so crabs on the rocks
+//! above hunt half as often as the weather allows. Crabs in a zone without breeding cannot be bred.
+//! Crabs whose color matches their zone's background are camouflaged (see
+//! `Beach::background_color`). Crabs outside every zone are unaffected.
+
+use crate::color::Color;
+
+/// A named part of a beach and its effect on the crabs in it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Zone {
+    name: String,
+    hunt_chance: f64,
+    breeding: bool,
+    background: Option<Color>,
+}
+
+impl Zone {
+    /**
+     * Creates a zone that leaves its crabs hunting and breeding as usual.
+     */
+    pub fn new(name: &str) -> Zone {
+        Zone { name: name.to_string(), hunt_chance: 1.0, breeding: true, background: None }
+    }
+
+    /**
+     * Scales how likely the zone's crabs are to hunt in each step by `chance`, clamped to [0, 1].
+     */
+    pub fn with_hunt_chance(mut self, chance: f64) -> Zone {
+        self.hunt_chance = if chance.is_nan() { 1.0 } else { chance.clamp(0.0, 1.0) };
+        self
+    }
+
+    /**
+     * Allows or forbids breeding the zone's crabs.
+     */
+    pub fn with_breeding(mut self, allowed: bool) -> Zone {
+        self.breeding = allowed;
+        self
+    }
+
+    /**
+     * Colors the zone's background, e.g. sand or rock, which crabs of a similar color blend into.
+     */
+    pub fn with_background(mut self, color: Color) -> Zone {
+        self.background = Some(color);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn hunt_chance(&self) -> f64 {
+        self.hunt_chance
+    }
+
+    pub fn breeding_allowed(&self) -> bool {
+        self.breeding
+    }
+
+    pub fn background(&self) -> Option<&Color> {
+        self.background.as_ref()
+    }
+}
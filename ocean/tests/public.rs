@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants, clippy::bool_assert_comparison)]
+
 extern crate ocean;
 
 /*
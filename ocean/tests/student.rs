@@ -1,3 +1,5 @@
+#![allow(clippy::assertions_on_constants, clippy::bool_assert_comparison)]
+
 extern crate ocean;
 
 /*
@@ -7,7 +9,4713 @@ extern crate ocean;
  * Then, run `cargo test student` to run all of the student tests.
  */
 
+use ocean::arena::*;
+use ocean::bait::*;
+use ocean::batch::*;
+use ocean::beach::*;
+use ocean::behavior::*;
+use ocean::clans::*;
+use ocean::color::*;
+use ocean::config::*;
+use ocean::cookbook::*;
+use ocean::crab::*;
+use ocean::diet::*;
+use ocean::disease::*;
+use ocean::error::*;
+use ocean::escape::*;
+use ocean::events::*;
+use ocean::feeding::*;
+use ocean::genome::*;
+use ocean::hooks::*;
+use ocean::intern::*;
+use ocean::leaderboard::*;
+use ocean::metrics::*;
+use ocean::ocean::*;
+use ocean::paging::*;
+use ocean::pattern::*;
+use ocean::population::*;
+use ocean::position::*;
+use ocean::predator::*;
+use ocean::prey::*;
+use ocean::query::*;
+use ocean::reef::*;
+use ocean::report::*;
+use ocean::rng::*;
+use ocean::scenario::*;
+use ocean::simulation::*;
+use ocean::snapshot::*;
+use ocean::weather::*;
+use ocean::zone::*;
+
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+fn new_crab(name: &str, speed: u32) -> Crab {
+    Crab::new(String::from(name), speed, Color::new_blue(), Diet::Plants)
+}
+
 #[test]
 fn student_example() {
     assert_eq!(true, true);
 }
+
+#[test]
+fn student_beach_bury_crab_records_life_summary() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Corro", 7));
+    beach.add_member_to_clan("Reef Raiders", "Ferris");
+    beach.breed_crabs(0, 1, String::from("Junior"));
+
+    let summary = beach.bury_crab(0).clone();
+    assert_eq!(summary.name(), "Ferris");
+    assert_eq!(summary.offspring(), 1);
+    assert_eq!(summary.hunts(), 0);
+    assert_eq!(summary.clan(), Some("Reef Raiders"));
+    assert_eq!(
+        summary.to_string(),
+        "Ferris (speed 5) lived 0 step(s), made 0 successful hunt(s) and parented 1 crab(s), and was a member of Reef Raiders."
+    );
+
+    assert_eq!(beach.size(), 2);
+    assert_eq!(beach.get_clan_system().get_clan_member_count("Reef Raiders"), 0);
+    assert_eq!(beach.hall_of_fame().count(), 1);
+}
+
+#[test]
+fn student_clan_system_save_load_round_trip() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Tab\tName", 7));
+    beach.add_member_to_clan("Reef Raiders", "Ferris");
+    beach.add_member_to_clan("Reef Raiders", "Tab\tName");
+
+    let mut clan_system = ClanSystem::new();
+    clan_system.create_clan(String::from("Empty Shells"));
+    let saved = beach.get_clan_system().save();
+    let loaded = ClanSystem::load(&saved).unwrap();
+
+    assert_eq!(loaded.get_clan_count(), 1);
+    let mut names = loaded.get_clan_member_names("Reef Raiders");
+    names.sort();
+    assert_eq!(names, vec!["Ferris", "Tab\tName"]);
+    assert_eq!(loaded.save(), saved);
+
+    let empty = ClanSystem::load(&clan_system.save()).unwrap();
+    assert_eq!(empty.get_clan_count(), 1);
+    assert_eq!(empty.get_clan_member_count("Empty Shells"), 0);
+
+    let err = ClanSystem::load("member\tNowhere\tFerris\t5\t0\t0\t255\tPlants\t0\t0").unwrap_err();
+    assert_eq!(err.line(), 1);
+}
+
+#[derive(Clone)]
+struct Lobster {
+    name: String,
+    speed: u32,
+}
+
+impl ClanMember for Lobster {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed
+    }
+}
+
+#[test]
+fn student_clan_system_generic_members() {
+    let mut clan_system: ClanSystem<Lobster> = ClanSystem::new();
+    clan_system.create_clan(String::from("Claws"));
+    for (name, speed) in [("Larry", 4), ("Lola", 9)] {
+        let lobster = Lobster { name: name.to_string(), speed };
+        clan_system.add_member("Claws", lobster.name.clone(), lobster).unwrap();
+    }
+
+    assert_eq!(clan_system.get_clan_member_count("Claws"), 2);
+    assert_eq!(clan_system.get_largest_clan_id(), Some(String::from("Claws")));
+    assert_eq!(clan_system.get_clan_average_speed("Claws"), Some(6));
+    assert_eq!(clan_system.get_clan_average_speed("Pincers"), None);
+}
+
+#[test]
+fn student_ocean_reef_by_name() {
+    let mut ocean = Ocean::new();
+    ocean.generate_reef(0, 0, 1, 0);
+    let kelp = ocean.add_reef(Reef::with_name(String::from("Kelp Forest"))).unwrap();
+    kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+
+    let found = ocean.reef_by_name("Kelp Forest").unwrap();
+    assert!(Rc::ptr_eq(&found, &kelp));
+    assert_eq!(found.borrow().population(), 1);
+    assert!(ocean.reef_by_name("Coral Garden").is_none());
+
+    // Names must be unique, but anonymous reefs may be added freely.
+    assert!(ocean.add_reef(Reef::with_name(String::from("Kelp Forest"))).is_err());
+    assert!(ocean.add_reef(Reef::new()).is_ok());
+    assert_eq!(ocean.reefs().len(), 3);
+}
+
+#[test]
+fn student_beach_leaderboard_ranks_by_criteria() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 10), ("Corro", 4), ("Pinchy", 10), ("Loner", 12)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("Fast", "Ferris");
+    beach.add_member_to_clan("Slow", "Corro");
+    beach.add_member_to_clan("Also Fast", "Pinchy");
+    beach.get_winner_clan("Fast", "Slow").unwrap();
+
+    let board = beach.leaderboard(&[RankBy::Speed, RankBy::ClanReputation], 3);
+    let names: Vec<&str> = board.iter().map(|entry| entry.crab.as_str()).collect();
+    assert_eq!(names, vec!["Loner", "Ferris", "Pinchy"]);
+    assert_eq!(board[1].rank, 2);
+    assert_eq!(board[1].index, 0);
+    assert_eq!(board[1].to_string(), "2. Ferris (speed 10, reputation 3)");
+
+    // Crabs that tie on every criterion keep their beach order.
+    let board = beach.leaderboard(&[RankBy::Hunts], 10);
+    let indices: Vec<usize> = board.iter().map(|entry| entry.index).collect();
+    assert_eq!(indices, vec![0, 1, 2, 3]);
+    assert_eq!("reputation".parse::<RankBy>(), Ok(RankBy::ClanReputation));
+    assert!("xp".parse::<RankBy>().is_err());
+}
+
+#[test]
+fn student_clan_raid_captures_reef() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 100), ("Pinchy", 90), ("Corro", 1)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("Raiders", "Ferris");
+    beach.add_member_to_clan("Raiders", "Pinchy");
+    beach.add_member_to_clan("Homebodies", "Corro");
+    beach.get_clan_system_mut().claim_reef("Homebodies", 2).unwrap();
+    assert!(beach.get_clan_system_mut().claim_reef("Raiders", 2).is_err());
+    beach.drain_events();
+
+    let mut rng = Pcg64::seed_from_u64(3);
+    assert!(matches!(beach.raid("Raiders", "Homebodies", 5, &mut rng), Err(OceanError::InvalidArgument(_))));
+    assert_eq!(beach.raid("Raiders", "Nobody", 2, &mut rng), Err(OceanError::ClanNotFound("Nobody".to_string())));
+    // The raiders are far stronger than luck can make up for.
+    assert_eq!(beach.raid("Raiders", "Homebodies", 2, &mut rng), Ok(true));
+    assert_eq!(beach.get_clan_system().controller(2), Some("Raiders"));
+    assert_eq!(beach.get_clan_system().territory("Raiders"), vec![2]);
+    assert!(beach.get_clan_system().territory("Homebodies").is_empty());
+    assert_eq!(beach.raid("Homebodies", "Raiders", 2, &mut rng), Ok(false));
+    assert_eq!(beach.get_clan_system().reputation("Raiders"), 2 * WIN_POINTS);
+
+    let events = beach.drain_events();
+    assert_eq!(
+        events[0],
+        OceanEvent::ReefRaided { attacker: "Raiders".to_string(), defender: "Homebodies".to_string(), reef: 2, captured: true }
+    );
+    let reloaded = ClanSystem::load(&beach.get_clan_system().save()).unwrap();
+    assert_eq!(reloaded.controller(2), Some("Raiders"));
+}
+
+#[test]
+fn student_crab_share_and_transfer_reef() {
+    let (home, away) = (Rc::new(RefCell::new(Reef::new())), Rc::new(RefCell::new(Reef::new())));
+    let mut parent = new_crab("Parent", 3);
+    parent.discover_reef(home.clone());
+    parent.discover_reef(away.clone());
+    let mut child = new_crab("Child", 1);
+
+    assert_eq!(parent.share_reef(&mut child, 0), Ok(true));
+    assert_eq!(parent.share_reef(&mut child, 0), Ok(false));
+    assert_eq!(parent.share_reef(&mut child, 2), Err(OceanError::ReefNotFound("at index 2".to_string())));
+    assert_eq!(parent.reefs().len(), 2);
+    assert!(Rc::ptr_eq(&child.reefs().next().unwrap(), &home));
+
+    assert_eq!(parent.transfer_reef(&mut child, 1), Ok(true));
+    assert_eq!(parent.reefs().len(), 1);
+    let reefs: Vec<_> = child.reefs().collect();
+    assert!(Rc::ptr_eq(&reefs[1], &away));
+    assert!(parent.transfer_reef(&mut child, 1).is_err());
+}
+
+#[test]
+fn student_clan_group_hunt_catches_fast_prey() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 5), ("Loner", 20), ("Pinchy", 6)] {
+        beach.add_crab(Crab::new(name.to_string(), speed, Color::new_red(), Diet::Fish));
+    }
+    beach.add_member_to_clan("Pack", "Ferris");
+    beach.add_member_to_clan("Pack", "Pinchy");
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Minnow::new(9)));
+    let mut rng = Pcg64::seed_from_u64(5);
+
+    // Neither member could catch the minnow alone, but together they are faster.
+    assert!(!beach.get_crab_mut(0).hunt_in(&mut reef));
+    let hunt = beach.group_hunt("Pack", &mut reef, SharingRule::BySpeed, &mut rng).unwrap();
+    assert_eq!(hunt.prey.as_deref(), Some("Minnow"));
+    assert_eq!(hunt.shares, vec![(0, 1), (2, 3)]);
+    assert_eq!(beach.get_crab(2).energy(), 3);
+    assert_eq!(beach.get_crab(2).larder("Minnow"), 1);
+    assert_eq!(beach.get_crab(0).hunts(), 1);
+
+    let hunt = beach.group_hunt("Pack", &mut reef, SharingRule::Equal, &mut rng).unwrap();
+    assert_eq!(hunt, GroupHunt { prey: None, shares: vec![(0, 0), (2, 0)] });
+    assert!(matches!(beach.group_hunt("Nobody", &mut reef, SharingRule::Equal, &mut rng), Err(OceanError::ClanNotFound(_))));
+
+    assert_eq!(SharingRule::Equal.split(5, &[1, 1, 1]), vec![2, 2, 1]);
+    assert_eq!(SharingRule::FastestTakesAll.split(5, &[3, 7, 7]), vec![0, 5, 0]);
+    assert_eq!(SharingRule::BySpeed.split(3, &[0, 0]), vec![2, 1]);
+}
+
+#[test]
+fn student_crabs_pay_tribute_in_foreign_reefs() {
+    let mut ocean = Ocean::new();
+    let reef = ocean.add_reef(Reef::new()).unwrap();
+    for _ in 0..2 {
+        reef.borrow_mut().add_prey(Box::new(Minnow::new(0)));
+    }
+    let mut beach = Beach::new();
+    for name in ["Owner", "Intruder"] {
+        let mut crab = Crab::new(name.to_string(), 5, Color::new_red(), Diet::Fish);
+        crab.discover_reef(reef.clone());
+        beach.add_crab(crab);
+    }
+    beach.add_member_to_clan("Holders", "Owner");
+    ocean.add_beach(beach);
+    assert_eq!(ocean.claim_reef(0, "Nobody", 0), Err(OceanError::ClanNotFound("Nobody".to_string())));
+    assert_eq!(ocean.claim_reef(0, "Holders", 7), Err(OceanError::ReefNotFound("7".to_string())));
+    ocean.claim_reef(0, "Holders", 0).unwrap();
+    assert_eq!(reef.borrow().owner(), Some("Holders"));
+
+    let beach = ocean.beaches_mut().next().unwrap();
+    beach.feeding_round();
+    // Minnows are worth 4, and the intruder pays half of that to the reef's holders.
+    assert_eq!(beach.get_crab(0).energy(), 4);
+    assert_eq!(beach.get_crab(1).energy(), 2);
+    assert_eq!(beach.get_crab(1).clan(), None);
+    assert_eq!(reef.borrow_mut().collect_tribute(), 2);
+    assert_eq!(reef.borrow().tribute(), 0);
+}
+
+#[test]
+fn student_snapshot_diff_lists_changes() {
+    let mut ocean = Ocean::new();
+    let reef = ocean.add_reef(Reef::new()).unwrap();
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 2));
+    beach.get_crab_mut(0).discover_reef(reef.clone());
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    let before = Snapshot::of(simulation.ocean());
+    assert!(Snapshot::diff(&before, &before).is_empty());
+    let saved = simulation.snapshot().unwrap();
+
+    let beach = simulation.ocean_mut().beaches_mut().next().unwrap();
+    assert!(beach.get_crab_mut(0).hunt());
+    beach.add_member_to_clan("Pinchers", "Ferris");
+    beach.bury_crab(1);
+    beach.add_crab(new_crab("Pinchy", 4));
+    let after = Snapshot::of(simulation.ocean());
+
+    let diff = Snapshot::diff(&Snapshot::parse(&saved).unwrap(), &after);
+    let names = |crabs: &[CrabEntry]| crabs.iter().map(|crab| crab.name.clone()).collect::<Vec<_>>();
+    assert_eq!(names(&diff.added_crabs), vec!["Pinchy"]);
+    assert_eq!(names(&diff.removed_crabs), vec!["Corro"]);
+    assert_eq!(diff.changed_crabs.len(), 1);
+    assert_eq!(diff.changed_crabs[0].energy, Some((0, 1)));
+    assert_eq!(diff.changed_crabs[0].clan, Some((None, Some("Pinchers".to_string()))));
+    assert_eq!(diff.changed_crabs[0].speed, None);
+    assert_eq!(diff.reef_deltas, vec![(0, -1)]);
+    assert_eq!(diff.clan_changes[0].1.joined, vec![("Pinchers".to_string(), "Ferris".to_string())]);
+    assert_eq!(
+        diff.to_string().lines().nth(2),
+        Some("~ beach 0 crab 1 Ferris: energy 0 -> 1, clan none -> Pinchers")
+    );
+}
+
+#[test]
+fn student_clan_speed_distribution() {
+    let mut beach = Beach::new();
+    for (i, speed) in [2, 3, 3, 7, 9, 10].into_iter().enumerate() {
+        let name = format!("crab{}", i);
+        beach.add_crab(new_crab(&name, speed));
+        beach.add_member_to_clan("claws", &name);
+    }
+    beach.get_clan_system_mut().create_clan("empty".to_string());
+    assert_eq!(beach.clan_speed_distribution("empty"), None);
+    assert_eq!(beach.get_clan_system().speed_distribution("nobody"), None);
+
+    let speeds = beach.clan_speed_distribution("claws").unwrap();
+    assert_eq!(beach.get_clan_system().speed_distribution("claws"), Some(speeds.clone()));
+    assert_eq!((speeds.count(), speeds.min(), speeds.max()), (6, 2, 10));
+    assert!((speeds.mean() - 34.0 / 6.0).abs() < 1e-12);
+    assert_eq!(speeds.median(), 3);
+    assert_eq!(speeds.percentile(0.0), 2);
+    assert_eq!(speeds.percentile(90.0), 10);
+    assert_eq!(speeds.percentile(150.0), 10);
+    assert_eq!(speeds.histogram(4), vec![(0, 3), (4, 1), (8, 2)]);
+    assert_eq!(SpeedDistribution::of([5]).unwrap().histogram(0), vec![(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 1)]);
+}
+
+#[test]
+fn student_clan_diet_breakdown() {
+    let mut ocean = Ocean::new();
+    for crabs in [vec![("Ferris", Diet::Fish), ("Corro", Diet::Plants), ("Pinchy", Diet::Fish)], vec![("Shelly", Diet::Shellfish)]] {
+        let mut beach = Beach::new();
+        for (name, diet) in crabs {
+            beach.add_crab(Crab::new(name.to_string(), 3, Color::new_red(), diet));
+            beach.add_member_to_clan("Mixed", name);
+        }
+        ocean.add_beach(beach);
+    }
+
+    let beach = ocean.beaches().next().unwrap();
+    let breakdown = beach.get_clan_system().diet_breakdown("Mixed");
+    assert_eq!(breakdown, HashMap::from([(Diet::Fish, 2), (Diet::Plants, 1)]));
+    assert!(beach.get_clan_system().diet_breakdown("Nobody").is_empty());
+    assert_eq!(
+        ocean.clan_diet_breakdown("Mixed"),
+        HashMap::from([(Diet::Fish, 2), (Diet::Plants, 1), (Diet::Shellfish, 1)])
+    );
+}
+
+#[test]
+fn student_clan_reputation_from_competitions() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 10), ("Corro", 4), ("Pinchy", 10)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("Fast", "Ferris");
+    beach.add_member_to_clan("Slow", "Corro");
+    beach.add_member_to_clan("Also Fast", "Pinchy");
+
+    beach.get_winner_clan("Fast", "Slow").unwrap();
+    beach.get_winner_clan("Fast", "Also Fast").unwrap();
+
+    let clan_system = beach.get_clan_system();
+    assert_eq!(clan_system.reputation("Fast"), 4);
+    assert_eq!(clan_system.reputation("Also Fast"), 1);
+    assert_eq!(clan_system.reputation("Slow"), 0);
+    assert_eq!(
+        clan_system.leaderboard(),
+        vec![
+            (String::from("Fast"), 4),
+            (String::from("Also Fast"), 1),
+            (String::from("Slow"), 0)
+        ]
+    );
+
+    let reloaded = ClanSystem::load(&clan_system.save()).unwrap();
+    assert_eq!(reloaded.reputation("Fast"), 4);
+}
+
+#[test]
+fn student_clan_system_diff() {
+    let mut beach = Beach::new();
+    for name in ["Ferris", "Corro", "Pinchy"] {
+        beach.add_crab(new_crab(name, 5));
+    }
+    beach.add_member_to_clan("Reds", "Ferris");
+    beach.add_member_to_clan("Blues", "Corro");
+    let before = ClanSystem::load(&beach.get_clan_system().save()).unwrap();
+    assert!(before.diff(beach.get_clan_system()).is_empty());
+
+    beach.bury_crab(1);
+    beach.add_member_to_clan("Reds", "Pinchy");
+    let diff = before.diff(beach.get_clan_system());
+
+    assert!(diff.added_clans.is_empty());
+    assert!(diff.removed_clans.is_empty());
+    assert_eq!(diff.joined, vec![(String::from("Reds"), String::from("Pinchy"))]);
+    assert_eq!(diff.left, vec![(String::from("Blues"), String::from("Corro"))]);
+}
+
+#[test]
+fn student_beach_sample_crabs() {
+    let mut beach = Beach::new();
+    for i in 0..20 {
+        let diet = if i % 4 == 0 { Diet::Fish } else { Diet::Plants };
+        beach.add_crab(Crab::new(format!("Crab {}", i), i, Color::new_blue(), diet));
+    }
+    beach.add_member_to_clan("Reds", "Crab 1");
+    beach.add_member_to_clan("Reds", "Crab 2");
+
+    let sample = beach.sample_crabs(&mut Pcg64::seed_from_u64(7), 5);
+    assert_eq!(sample.len(), 5);
+    let mut names: Vec<&str> = sample.iter().map(|c| c.name()).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), 5);
+
+    // The same seed gives the same sample.
+    let again = beach.sample_crabs(&mut Pcg64::seed_from_u64(7), 5);
+    assert!(sample.iter().zip(again.iter()).all(|(a, b)| std::ptr::eq(*a, *b)));
+    assert_eq!(beach.sample_crabs(&mut Pcg64::seed_from_u64(7), 100).len(), 20);
+
+    let by_diet = beach.sample_crabs_by_diet(&mut Pcg64::seed_from_u64(7), 3);
+    assert_eq!(by_diet[&Diet::Fish].len(), 3);
+    assert!(by_diet[&Diet::Fish].iter().all(|c| c.diet() == Diet::Fish));
+    assert_eq!(by_diet[&Diet::Plants].len(), 3);
+
+    let by_clan = beach.sample_crabs_by_clan(&mut Pcg64::seed_from_u64(7), 3);
+    assert_eq!(by_clan.len(), 1);
+    assert_eq!(by_clan["Reds"].len(), 2);
+}
+
+#[test]
+fn student_clan_export_rosters() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Pinchy, Jr.", 7));
+    beach.add_member_to_clan("Reds", "Pinchy, Jr.");
+    beach.add_member_to_clan("Reds", "Ferris");
+    let clan_system = beach.get_clan_system();
+
+    assert_eq!(
+        clan_system.export_rosters(RosterFormat::Csv),
+        "clan_id,member_name,speed,diet\nReds,Ferris,5,Plants\nReds,\"Pinchy, Jr.\",7,Plants\n"
+    );
+    assert_eq!(
+        clan_system.export_rosters(RosterFormat::Json),
+        "[{\"clan_id\":\"Reds\",\"member_name\":\"Ferris\",\"speed\":5,\"diet\":\"Plants\"},\
+         {\"clan_id\":\"Reds\",\"member_name\":\"Pinchy, Jr.\",\"speed\":7,\"diet\":\"Plants\"}]"
+    );
+    assert_eq!(ClanSystem::<Crab>::new().export_rosters(RosterFormat::Json), "[]");
+}
+
+#[test]
+fn student_beach_get_clan_of_crab() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Corro", 5));
+    beach.add_member_to_clan("Reds", "Ferris");
+
+    assert_eq!(beach.get_clan_of_crab("Ferris"), Some("Reds"));
+    assert_eq!(beach.get_clan_of_crab("Corro"), None);
+
+    // Joining another clan leaves the first one.
+    beach.add_member_to_clan("Blues", "Ferris");
+    assert_eq!(beach.get_clan_of_crab("Ferris"), Some("Blues"));
+    assert_eq!(beach.get_clan_system().get_clan_member_count("Reds"), 0);
+
+    beach.bury_crab(0);
+    assert_eq!(beach.get_clan_of_crab("Ferris"), None);
+}
+
+#[cfg(feature = "economy")]
+#[test]
+fn student_economy_deposit_decay_purchase() {
+    use ocean::economy::*;
+
+    let mut economy = Economy::new(10);
+    let prey: Vec<Box<dyn Prey>> = (0..6).map(|_| Box::new(Clam::new()) as Box<dyn Prey>).collect();
+    assert_eq!(economy.deposit("Reds", prey), 60);
+
+    economy.decay();
+    assert_eq!(economy.balance("Reds"), 54);
+
+    assert!(economy.purchase("Reds", Purchase::TerritoryClaim(String::from("Kelp"))).is_ok());
+    assert_eq!(economy.balance("Reds"), 4);
+    assert!(economy.purchase("Reds", Purchase::TrainingSession).is_err());
+    assert!(economy.purchase("Blues", Purchase::TrainingSession).is_err());
+    assert_eq!(economy.balance("Reds"), 4);
+
+    let reloaded = Economy::load(&economy.save()).unwrap();
+    assert_eq!(reloaded.decay_percent(), 10);
+    assert_eq!(reloaded.ledger("Reds"), economy.ledger("Reds"));
+    assert_eq!(reloaded.ledger("Reds").unwrap().entries().count(), 3);
+}
+
+#[test]
+fn student_clan_limits_and_recruitment_rules() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Corro", 2));
+    beach.add_crab(new_crab("Pinchy", 9));
+    beach.add_crab(Crab::new(String::from("Shelly"), 8, Color::new_red(), Diet::Fish));
+
+    let clan_system = beach.get_clan_system_mut();
+    clan_system.create_clan_with_limit(String::from("Elite"), 2);
+    clan_system.add_recruitment_rule("Elite", MinSpeed(4));
+    clan_system.add_recruitment_rule("Elite", RequiredDiet(Diet::Plants));
+    assert_eq!(clan_system.get_clan_limit("Elite"), Some(2));
+
+    assert!(beach.try_add_member_to_clan("Elite", "Ferris").is_ok());
+    let too_slow = beach.try_add_member_to_clan("Elite", "Corro").unwrap_err();
+    assert!(matches!(&too_slow, OceanError::Rejected(reason) if reason.contains("below the minimum")));
+    let wrong_diet = beach.try_add_member_to_clan("Elite", "Shelly").unwrap_err();
+    assert!(wrong_diet.to_string().contains("requires Plants"));
+    assert!(beach.try_add_member_to_clan("Elite", "Pinchy").is_ok());
+
+    beach.add_crab(new_crab("Speedy", 20));
+    assert_eq!(
+        beach.try_add_member_to_clan("Elite", "Speedy"),
+        Err(OceanError::ClanFull { clan: String::from("Elite"), max_members: 2 })
+    );
+    assert_eq!(beach.try_add_member_to_clan("Elite", "Nobody"), Err(OceanError::CrabNotFound(String::from("Nobody"))));
+
+    // Rejected crabs keep their current clan.
+    beach.add_member_to_clan("Locals", "Corro");
+    assert!(beach.try_add_member_to_clan("Elite", "Corro").is_err());
+    assert_eq!(beach.get_clan_of_crab("Corro"), Some("Locals"));
+
+    let reloaded = ClanSystem::load(&beach.get_clan_system().save()).unwrap();
+    assert_eq!(reloaded.get_clan_limit("Elite"), Some(2));
+}
+
+#[test]
+fn student_capabilities_report_features() {
+    use ocean::capabilities::Capability;
+
+    let capabilities = ocean::capabilities();
+    assert_eq!(capabilities.has(Capability::Economy), cfg!(feature = "economy"));
+    assert_eq!(Capability::from_feature("economy"), Some(Capability::Economy));
+    assert_eq!(Capability::from_feature("teleportation"), None);
+    // Every cargo feature of the crate has a capability.
+    for (feature, capability) in [
+        ("economy", Capability::Economy),
+        ("trace", Capability::Trace),
+        ("proptest", Capability::Proptest),
+        ("wasm", Capability::Wasm),
+        ("fuzzing", Capability::Fuzzing),
+        ("async", Capability::Async),
+        ("deterministic", Capability::Deterministic),
+        ("server", Capability::Server),
+    ] {
+        assert_eq!(Capability::from_feature(feature), Some(capability));
+        assert_eq!(capability.feature(), feature);
+    }
+    assert_eq!(Capability::ALL.len(), 8);
+    assert_eq!(capabilities.has(Capability::Deterministic), cfg!(feature = "deterministic"));
+    assert_eq!(capabilities.has(Capability::Server), cfg!(feature = "server"));
+    if !capabilities.has(Capability::Economy) {
+        let err = capabilities.require(Capability::Economy).unwrap_err();
+        assert_eq!(err, OceanError::FeatureDisabled(Capability::Economy));
+        assert!(err.to_string().contains("--features economy"));
+    } else {
+        assert!(capabilities.require(Capability::Economy).is_ok());
+    }
+}
+
+#[test]
+fn student_reef_capacity() {
+    let mut reef = Reef::new();
+    assert_eq!(reef.capacity(), None);
+    reef.set_capacity(Some(2));
+
+    assert!(reef.add_prey(Box::new(Clam::new())));
+    assert!(reef.try_add_prey(Box::new(Algae::new())).is_ok());
+    assert!(reef.is_full());
+    assert!(!reef.add_prey(Box::new(Clam::new())));
+
+    let rejected = reef.try_add_prey(Box::new(Shrimp::new(3))).unwrap_err();
+    assert_eq!(rejected.0.diet(), Diet::Shellfish);
+    assert_eq!(reef.population(), 2);
+
+    reef.take_prey();
+    assert!(!reef.is_full());
+    assert!(reef.add_prey(rejected.0));
+}
+
+#[test]
+fn student_reef_census() {
+    let mut ocean = Ocean::new();
+    let reef = ocean.generate_reef(2, 1, 0, 3);
+    let census = reef.borrow().census();
+
+    assert_eq!(census.len(), 3);
+    assert_eq!(census["Minnow"], 2);
+    assert_eq!(census["Shrimp"], 1);
+    assert_eq!(census["Algae"], 3);
+    assert!(!census.contains_key("Clam"));
+    assert_eq!(reef.borrow().population(), 6);
+    assert_eq!(format!("{:?}", reef.borrow().prey().next().unwrap()), "Minnow");
+}
+
+#[test]
+fn student_reef_tick_regenerates_prey() {
+    let mut reef = Reef::new();
+    let mut rng = Pcg64::seed_from_u64(1);
+
+    // Without a policy, nothing grows back.
+    assert_eq!(reef.tick(&mut rng), 0);
+
+    reef.set_regen_policy(
+        RegenPolicy::new()
+            .with_rate(2.0, || Box::new(Algae::new()))
+            .with_rate(0.5, || Box::new(Clam::new())),
+    );
+    for _ in 0..100 {
+        let spawned = reef.tick(&mut rng);
+        assert!(spawned == 2 || spawned == 3);
+    }
+    // Prey die of old age, so only those spawned within their lifespans are left.
+    let census = reef.census();
+    assert_eq!(census["Algae"], 2 * Algae::new().lifespan().unwrap() as usize);
+    assert!(census["Clam"] > 15 && census["Clam"] < 45);
+
+    let mut reef = Reef::new();
+    reef.set_regen_policy(RegenPolicy::new().with_rate(2.0, || Box::new(Algae::new())));
+    reef.set_capacity(Some(1));
+    assert_eq!(reef.tick(&mut rng), 1);
+    assert_eq!(reef.tick(&mut rng), 0);
+}
+
+#[test]
+fn student_crab_meta_saves_with_crab() {
+    let mut beach = Beach::new();
+    let mut crab = new_crab("Ferris", 3);
+    assert_eq!(crab.set_meta("origin", "Tidepool\tNorth"), None);
+    assert_eq!(crab.set_meta("group", "control"), None);
+    assert_eq!(crab.set_meta("group", "treatment"), Some("control".to_string()));
+    beach.add_crab(crab);
+    beach.add_crab(new_crab("Plain", 2));
+
+    let loaded = Beach::load(&beach.save()).unwrap();
+    let ferris = loaded.get_crab(0);
+    assert_eq!(ferris.get_meta("origin"), Some("Tidepool\tNorth"));
+    assert_eq!(ferris.get_meta("group"), Some("treatment"));
+    assert_eq!(ferris.get_meta("notes"), None);
+    assert!(loaded.get_crab(1).meta().is_empty());
+
+    let mut ferris = ferris.clone();
+    assert_eq!(ferris.remove_meta("origin"), Some("Tidepool\tNorth".to_string()));
+    assert_eq!(ferris.meta().len(), 1);
+}
+
+#[test]
+fn student_reef_clone_is_independent() {
+    let mut reef = Reef::with_name("Home".to_string());
+    reef.set_regen_policy(RegenPolicy::new().with_rate(1.0, || Box::new(Algae::new())));
+    reef.add_prey(Box::new(Minnow::new(3)));
+    reef.add_prey(Box::new(Clam::new()));
+
+    let mut branch = reef.clone();
+    assert!(reef.take_prey().is_some());
+    assert_eq!(reef.census().values().sum::<usize>(), 1);
+    assert_eq!(branch.census(), HashMap::from([("Minnow".to_string(), 1), ("Clam".to_string(), 1)]));
+
+    // The clone keeps its own regeneration policy.
+    let mut rng = Pcg64::seed_from_u64(1);
+    assert_eq!(branch.tick(&mut rng), 1);
+    assert_eq!(branch.census()["Algae"], 1);
+    assert!(!reef.census().contains_key("Algae"));
+
+    let prey: Box<dyn Prey> = Box::new(Shrimp::new(4));
+    assert_eq!(prey.clone().diet(), Diet::Shellfish);
+}
+
+#[test]
+fn student_prey_tags_follow_prey() {
+    let mut minnow: Box<dyn Prey> = Box::new(Minnow::new(3));
+    let tags = minnow.tags_mut().unwrap();
+    assert!(tags.add_tag("tagged-by-researcher-3"));
+    assert!(!tags.add_tag("tagged-by-researcher-3"));
+    assert_eq!(tags.set("spawn_tick", "7"), None);
+
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Clam::new()));
+    reef.add_prey(minnow);
+    reef.add_prey(Box::new(Algae::new()));
+
+    // Recapture the tagged minnow from among the untagged prey.
+    let recaptured = reef.take_prey_where(|prey| prey.has_tag("tagged-by-researcher-3")).unwrap();
+    assert_eq!(recaptured.kind(), "Minnow");
+    assert_eq!(recaptured.tags().unwrap().get("spawn_tick"), Some("7"));
+    assert_eq!(recaptured.clone().tags(), recaptured.tags());
+    assert!(reef.take_prey_where(|prey| prey.has_tag("tagged-by-researcher-3")).is_none());
+
+    let mut clam = Clam::new();
+    assert!(clam.tags_mut().unwrap().add_tag("a"));
+    assert!(clam.tags_mut().unwrap().remove_tag("a"));
+    assert!(clam.tags().unwrap().tags().is_empty());
+}
+
+#[test]
+fn student_reef_take_policies() {
+    let stocked = |policy: TakePolicy| {
+        let mut reef = Reef::new();
+        reef.set_take_policy(policy);
+        reef.add_prey(Box::new(Clam::new()));
+        reef.add_prey(Box::new(Algae::new()));
+        reef.add_prey(Box::new(Minnow::new(3)));
+        reef
+    };
+
+    let mut fifo = stocked(TakePolicy::default());
+    assert_eq!(fifo.take_policy(), TakePolicy::Fifo);
+    assert_eq!(fifo.take_prey().unwrap().kind(), "Clam");
+
+    let mut lifo = stocked(TakePolicy::Lifo);
+    assert_eq!(lifo.take_prey().unwrap().kind(), "Minnow");
+    assert_eq!(lifo.take_prey().unwrap().kind(), "Algae");
+
+    let mut random = stocked(TakePolicy::Random);
+    let mut kinds: Vec<&str> = (0..3).map(|_| random.take_prey().unwrap().kind()).collect();
+    kinds.sort();
+    assert_eq!(kinds, vec!["Algae", "Clam", "Minnow"]);
+    assert!(random.take_prey().is_none());
+}
+
+#[test]
+fn student_sync_reef_shared_between_threads() {
+    let reef = SyncReef::new(Reef::new());
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let reef = reef.clone();
+            std::thread::spawn(move || {
+                for _ in 0..25 {
+                    reef.lock().add_prey(Box::new(Clam::new()));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(reef.lock().population(), 100);
+
+    let mut prinz = Crab::new(String::from("Prinz"), 20, Color::new_red(), Diet::Shellfish);
+    let hunter = std::thread::spawn({
+        let reef = reef.clone();
+        move || {
+            let mut sebastian = new_crab("Sebastian", 30);
+            // Sebastian eats plants, so every clam goes back to the reef.
+            sebastian.hunt_in(&mut reef.lock())
+        }
+    });
+    assert!(!hunter.join().unwrap());
+    assert!(prinz.hunt_in(&mut reef.lock()));
+    assert_eq!(prinz.hunts(), 1);
+    assert_eq!(reef.into_inner().unwrap().population(), 99);
+}
+
+#[test]
+fn student_reef_take_prey_matching() {
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Algae::new()));
+    reef.add_prey(Box::new(Clam::new()));
+    reef.add_prey(Box::new(Shrimp::new(0)));
+
+    assert_eq!(reef.take_prey_matching(Diet::Shellfish).unwrap().kind(), "Clam");
+    assert!(reef.take_prey_matching(Diet::Fish).is_none());
+    reef.set_take_policy(TakePolicy::Lifo);
+    assert_eq!(reef.take_prey_matching(Diet::Plants).unwrap().kind(), "Algae");
+    assert_eq!(reef.population(), 1);
+
+    // A hunting crab leaves inedible prey untouched: this shrimp keeps its energy.
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Shrimp::new(1)));
+    let mut sebastian = new_crab("Sebastian", 30);
+    sebastian.discover_reef(Rc::clone(&reef));
+    assert!(!sebastian.hunt());
+
+    let mut prinz = Crab::new(String::from("Prinz"), 20, Color::new_red(), Diet::Shellfish);
+    prinz.discover_reef(Rc::clone(&reef));
+    assert!(!prinz.hunt());
+    assert!(prinz.hunt());
+}
+
+#[test]
+fn student_reef_registry() {
+    use ocean::registry::ReefRegistry;
+
+    let mut registry = ReefRegistry::new();
+    let kelp = registry.register(Reef::with_name(String::from("Kelp"))).unwrap();
+    let anonymous = registry.register(Reef::new()).unwrap();
+    assert!(registry.register(Reef::with_name(String::from("Kelp"))).is_err());
+    assert_eq!(registry.len(), 2);
+
+    let handle = registry.by_name("Kelp").unwrap();
+    assert_eq!(handle.borrow().id(), Some(kelp));
+    assert_eq!(registry.id_of(&handle), Some(kelp));
+    assert_eq!(registry.id_of_name("Kelp"), Some(kelp));
+    assert_eq!(registry.get(anonymous).unwrap().borrow().name(), None);
+    assert!(registry.get(5).is_none());
+
+    let mut crab = new_crab("Ferris", 3);
+    crab.discover_reef(registry.get(kelp).unwrap());
+    assert_eq!(Rc::strong_count(&handle), 3);
+
+    let mut ocean = Ocean::new();
+    let reef = ocean.generate_reef(1, 0, 0, 0);
+    assert_eq!(ocean.reef_registry().id_of(&reef), Some(0));
+}
+
+#[test]
+fn student_reef_save_load_keeps_prey_state() {
+    let mut reef = Reef::with_name(String::from("Kelp\tForest"));
+    reef.set_capacity(Some(10));
+    reef.set_take_policy(TakePolicy::Lifo);
+    reef.add_prey(Box::new(Shrimp::new(2)));
+    reef.add_prey(Box::new(Minnow::new(40)));
+    reef.add_prey(Box::new(Clam::new()));
+
+    let saved = reef.save().unwrap();
+    let mut loaded = Reef::load(&saved).unwrap();
+    assert_eq!(loaded.name(), Some("Kelp\tForest"));
+    assert_eq!(loaded.capacity(), Some(10));
+    assert_eq!(loaded.take_policy(), TakePolicy::Lifo);
+    assert_eq!(loaded.census(), reef.census());
+    assert_eq!(loaded.save().unwrap(), saved);
+
+    // The minnow is still faster than a speed-30 crab, and the shrimp can still escape twice.
+    let crab = Crab::new(String::from("Prinz"), 30, Color::new_red(), Diet::Shellfish);
+    let mut minnow = loaded.take_prey_matching(Diet::Fish).unwrap();
+    assert!(minnow.try_escape(&crab));
+    assert_eq!(loaded.take_prey().unwrap().kind(), "Clam");
+    let mut shrimp = loaded.take_prey().unwrap();
+    assert!(shrimp.try_escape(&crab) && shrimp.try_escape(&crab) && !shrimp.try_escape(&crab));
+
+    assert!(Reef::load("prey\tClam").is_err());
+    assert_eq!(Reef::load("reef\tFifo\nprey\tKraken").unwrap_err().line(), 2);
+}
+
+#[test]
+fn student_reef_health_and_depletion_callback() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let mut rng = Pcg64::seed_from_u64(0);
+    let mut reef = Reef::new();
+    assert_eq!(reef.health(), 0.0);
+
+    reef.set_capacity(Some(40));
+    for _ in 0..40 {
+        reef.add_prey(Box::new(Clam::new()));
+    }
+    assert_eq!(reef.health(), 1.0);
+
+    let alerts = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&alerts);
+    reef.on_depletion(0.5, move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    // Taking 2 prey per tick: stock falls and extraction pressure builds.
+    for _ in 0..5 {
+        reef.take_prey();
+        reef.take_prey();
+        reef.tick(&mut rng);
+    }
+    assert_eq!(reef.extraction_rate(), 10.0 / 6.0);
+    assert_eq!(reef.health(), 0.75);
+    assert_eq!(alerts.load(Ordering::SeqCst), 0);
+
+    for _ in 0..15 {
+        reef.take_prey();
+    }
+    assert!(reef.health() < 0.5);
+    assert_eq!(alerts.load(Ordering::SeqCst), 1);
+
+    // Escaped prey returned by a hunting crab do not count as extracted.
+    let shared = Rc::new(std::cell::RefCell::new(Reef::new()));
+    shared.borrow_mut().add_prey(Box::new(Shrimp::new(1)));
+    let mut prinz = Crab::new(String::from("Prinz"), 20, Color::new_red(), Diet::Shellfish);
+    prinz.discover_reef(Rc::clone(&shared));
+    assert!(!prinz.hunt());
+    assert_eq!(shared.borrow().extraction_rate(), 0.0);
+}
+
+#[test]
+fn student_reef_links_and_migration() {
+    use ocean::registry::ReefRegistry;
+
+    let mut registry = ReefRegistry::new();
+    let crowded = registry.register(Reef::new()).unwrap();
+    let empty = registry.register(Reef::new()).unwrap();
+    let small = registry.register(Reef::new()).unwrap();
+    let isolated = registry.register(Reef::new()).unwrap();
+    for _ in 0..10 {
+        registry.get(crowded).unwrap().borrow_mut().add_prey(Box::new(Clam::new()));
+        registry.get(isolated).unwrap().borrow_mut().add_prey(Box::new(Clam::new()));
+    }
+    registry.get(small).unwrap().borrow_mut().set_capacity(Some(1));
+
+    registry.link(crowded, empty).unwrap();
+    registry.link(crowded, small).unwrap();
+    registry.link(empty, crowded).unwrap();
+    assert!(registry.link(crowded, crowded).is_err());
+    assert!(registry.link(crowded, 9).is_err());
+    assert_eq!(registry.links_of(crowded), &[empty, small]);
+    assert_eq!(registry.links_of(empty), &[crowded]);
+
+    // 5 of the 10 prey leave: 3 to `empty`, 2 to `small`, but `small` only has room for one.
+    assert_eq!(registry.migrate(0.5), 4);
+    let population = |id| registry.get(id).unwrap().borrow().population();
+    assert_eq!(population(crowded), 6);
+    assert_eq!(population(empty), 3);
+    assert_eq!(population(small), 1);
+    assert_eq!(population(isolated), 10);
+    assert_eq!(registry.get(crowded).unwrap().borrow().extraction_rate(), 0.0);
+}
+
+#[test]
+fn student_plankton_and_krill() {
+    let crab = new_crab("Ferris", 3);
+
+    let mut plankton = Plankton::new();
+    assert_eq!(plankton.diet(), Diet::Plants);
+    assert!(plankton.try_escape(&crab));
+    assert!(!plankton.try_escape(&crab));
+    assert!(plankton.try_escape(&crab));
+
+    let mut krill = Krill::new(4);
+    assert_eq!(krill.diet(), Diet::Shellfish);
+    assert!(krill.try_escape(&crab));
+    assert!(krill.try_escape(&crab));
+    assert!(!krill.try_escape(&crab));
+
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Plankton::new()));
+    reef.add_prey(Box::new(Krill::new(7)));
+    let loaded = Reef::load(&reef.save().unwrap()).unwrap();
+    assert_eq!(loaded.census().get("Plankton"), Some(&1));
+    assert_eq!(loaded.census().get("Krill"), Some(&1));
+}
+
+#[test]
+fn student_hunting_gains_prey_nutrition() {
+    assert_eq!(Algae::new().nutrition(), 1);
+    assert_eq!(Minnow::new(1).nutrition(), 4);
+
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Clam::new()));
+    reef.borrow_mut().add_prey(Box::new(Shrimp::new(0)));
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Shellfish);
+    crab.discover_reef(reef.clone());
+    assert_eq!(crab.energy(), 0);
+    assert!(crab.hunt());
+    assert!(crab.hunt());
+    assert_eq!(crab.energy(), 5);
+
+    let fields = crab.to_fields();
+    assert_eq!(Crab::from_fields(&fields, 1).unwrap().energy(), 5);
+    assert_eq!(Crab::from_fields(&fields[..8], 1).unwrap().energy(), 0);
+}
+
+#[test]
+fn student_escape_models() {
+    let crab = Crab::new(String::from("Ferris"), 4, Color::new_blue(), Diet::Fish);
+    let traits = PreyTraits {
+        speed: 6,
+        camouflage: 0.5,
+        size: Size::Small,
+    };
+    let chance = ChanceEscape::new(0.25, 0.1, 0.2, 0.0);
+    assert!((chance.probability(&traits, &crab) - 0.55).abs() < 1e-9);
+    assert_eq!(ChanceEscape::new(0.9, 0.1, 0.0, 0.0).probability(&traits, &crab), 1.0);
+    assert!(ChanceEscape::new(1.0, 0.0, 0.0, 0.0).escapes(&traits, &crab));
+    assert!(!ChanceEscape::new(0.0, 0.0, 0.0, 0.0).escapes(&traits, &crab));
+
+    let model = ScriptedEscape::new(vec![true, true, false]);
+    let prey = ModeledPrey::new("Guppy", Diet::Fish, traits, Box::new(model));
+    assert_eq!(prey.kind(), "Guppy");
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(prey));
+    let mut crab = crab;
+    crab.discover_reef(reef.clone());
+    assert!(!crab.hunt());
+    assert!(!crab.hunt());
+    assert!(crab.hunt());
+    assert_eq!(reef.borrow().population(), 0);
+}
+
+#[test]
+fn student_downcast_prey() {
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Minnow::new(7)));
+    reef.add_prey(Box::new(Clam::new()));
+
+    let mut prey = reef.take_prey().unwrap();
+    assert!(prey.is::<Minnow>());
+    assert!(prey.downcast_ref::<Clam>().is_none());
+    assert!(prey.downcast_mut::<Minnow>().is_some());
+    assert!(prey.as_any().is::<Minnow>());
+
+    let clams = reef.prey().filter(|prey| prey.is::<Clam>()).count();
+    assert_eq!(clams, 1);
+}
+
+#[test]
+fn student_prey_size() {
+    assert_eq!(Algae::new().size(), Size::Small);
+    assert_eq!(Minnow::new(1).size(), Size::Medium);
+    assert_eq!(Clam::new().size(), Size::Large);
+    assert!(Size::Large.nutrition() > Size::Small.nutrition());
+
+    // A large prey gets three chances to escape, so it takes a four-step script to catch it.
+    let crab = Crab::new(String::from("Ferris"), 4, Color::new_blue(), Diet::Fish);
+    let traits = PreyTraits {
+        speed: 1,
+        camouflage: 0.0,
+        size: Size::Large,
+    };
+    let chance = ChanceEscape::new(0.1, 0.0, 0.0, 0.2);
+    assert!((chance.probability(&traits, &crab) - 0.5).abs() < 1e-9);
+    let model = ScriptedEscape::new(vec![false, false, true, false, false, false]);
+    let grouper = ModeledPrey::new("Grouper", Diet::Fish, traits, Box::new(model));
+    assert_eq!(grouper.nutrition(), 3);
+
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(grouper));
+    let mut crab = crab;
+    crab.discover_reef(reef.clone());
+    assert!(!crab.hunt());
+    assert!(crab.hunt());
+
+    let mut reef = Reef::new();
+    reef.set_take_policy(TakePolicy::SmallestFirst);
+    reef.add_prey(Box::new(Clam::new()));
+    reef.add_prey(Box::new(Minnow::new(1)));
+    reef.add_prey(Box::new(Shrimp::new(1)));
+    assert_eq!(reef.take_prey().unwrap().kind(), "Shrimp");
+    assert_eq!(reef.take_prey_matching(Diet::Shellfish).unwrap().kind(), "Clam");
+}
+
+#[test]
+fn student_prey_age_and_die() {
+    let mut rng = Pcg64::seed_from_u64(0);
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Plankton::new()));
+    reef.add_prey(Box::new(Krill::new(1)));
+    reef.add_prey(Box::new(ModeledPrey::new(
+        "Jellyfish",
+        Diet::Fish,
+        PreyTraits {
+            speed: 0,
+            camouflage: 0.0,
+            size: Size::Small,
+        },
+        Box::new(ScriptedEscape::new(Vec::new())),
+    )));
+    assert_eq!(Plankton::new().lifespan(), Some(5));
+
+    for _ in 0..4 {
+        reef.tick(&mut rng);
+    }
+    assert_eq!(reef.population(), 3);
+    let ages: Vec<u32> = reef.prey_with_ages().map(|(_, age)| age).collect();
+    assert_eq!(ages, vec![4, 4, 4]);
+
+    // Ages survive saving and loading.
+    let mut without_jellyfish = Reef::new();
+    without_jellyfish.add_prey(Box::new(Krill::new(1)));
+    without_jellyfish.tick(&mut rng);
+    let loaded = Reef::load(&without_jellyfish.save().unwrap()).unwrap();
+    assert_eq!(loaded.prey_with_ages().next().unwrap().1, 1);
+
+    reef.tick(&mut rng);
+    assert_eq!(reef.census().get("Plankton"), None);
+    for _ in 0..5 {
+        reef.tick(&mut rng);
+    }
+    // Only the jellyfish, which has no lifespan, is left.
+    assert_eq!(reef.population(), 1);
+    assert_eq!(reef.prey_with_ages().next().unwrap().1, 10);
+}
+
+#[test]
+fn student_weighted_color_cross() {
+    let red = Color::new_red();
+    let blue = Color::new_blue();
+    let mut rng = Pcg64::seed_from_u64(3);
+
+    assert_eq!(Color::cross_weighted(&red, &blue, 1.0, &mut rng), red);
+    assert_eq!(Color::cross_weighted(&red, &blue, 0.0, &mut rng), blue);
+
+    // Every component comes whole from one parent, and both parents show up over many crosses.
+    let mut from_red = 0;
+    for _ in 0..200 {
+        let child = Color::cross_weighted(&red, &blue, 0.5, &mut rng);
+        assert!(child.r == 0 || child.r == 255);
+        assert!(child.b == 0 || child.b == 255);
+        if child.r == 255 {
+            from_red += 1;
+        }
+    }
+    assert!(from_red > 60 && from_red < 140);
+
+    let ferris = Crab::new(String::from("Ferris"), 5, red.clone(), Diet::Fish);
+    let corro = Crab::new(String::from("Corro"), 5, blue.clone(), Diet::Fish);
+    let junior = Crab::breed_weighted(String::from("Junior"), &ferris, &corro, 1.0, &mut rng);
+    assert_eq!(junior.color(), &red);
+    assert_eq!(junior.name(), "Junior");
+}
+
+#[test]
+fn student_blended_colors_drift_gradually() {
+    let red = Color::new_red();
+    let blue = Color::new_blue();
+    assert_eq!(Color::blend(&red, &blue, 0.5), Color::new(128, 0, 128));
+    assert_eq!(Color::blend(&red, &blue, 1.0), red);
+    assert_eq!(Color::blend(&red, &blue, 3.0), red);
+    assert_eq!(Color::new(240, 16, 8).nearest_variant(), red);
+    assert_eq!(Color::new(240, 16, 8).nearest_name(), "red");
+
+    // Each generation bred back to blue moves halfway there.
+    let blue_crab = Crab::new(String::from("Blue"), 5, blue.clone(), Diet::Fish);
+    let mut crab = Crab::new(String::from("Red"), 5, red, Diet::Fish);
+    for generation in 0..3 {
+        crab = Crab::breed_blended(format!("Gen {}", generation), &crab, &blue_crab);
+    }
+    assert_eq!(crab.color(), &Color::new(32, 0, 224));
+    assert_eq!(crab.color().nearest_variant(), blue);
+}
+
+#[test]
+fn student_color_hex() {
+    let gold = Color::new(255, 215, 0);
+    assert_eq!(gold.to_rgb(), (255, 215, 0));
+    assert_eq!(gold.to_hex(), "#ffd700");
+    assert_eq!(Color::from_hex("#FFD700"), Ok(gold.clone()));
+    assert_eq!(Color::from_hex("ffd700"), Ok(gold));
+    assert!(Color::from_hex("#ffd70").is_err());
+    assert!(Color::from_hex("#ffd7zz").is_err());
+    assert!(Color::from_hex("+ffd700").is_err());
+}
+
+#[test]
+fn student_crab_patterns_are_inherited() {
+    assert_eq!(Color::new_teal().to_hex(), "#008080");
+
+    let striped = Crab::new(String::from("Stripes"), 3, Color::new_orange(), Diet::Fish).with_pattern(Pattern::Striped);
+    let spotted = Crab::new(String::from("Spots"), 3, Color::new_violet(), Diet::Fish).with_pattern(Pattern::Spotted);
+    assert_eq!(new_crab("Ferris", 1).pattern(), Pattern::Plain);
+
+    let twin = Crab::breed(String::from("Twin"), &striped, &striped.clone());
+    assert_eq!(twin.pattern(), Pattern::Striped);
+
+    let mut seen = Vec::new();
+    for i in 0..20 {
+        let child = Crab::breed(format!("Child {}", i), &striped, &spotted);
+        assert!(child.pattern() == Pattern::Striped || child.pattern() == Pattern::Spotted);
+        seen.push(child.pattern());
+    }
+    assert!(seen.contains(&Pattern::Striped) && seen.contains(&Pattern::Spotted));
+
+    let reloaded = Crab::from_fields(&spotted.to_fields(), 1).unwrap();
+    assert_eq!(reloaded.pattern(), Pattern::Spotted);
+}
+
+#[test]
+fn student_recessive_colors_reappear_in_grandchildren() {
+    let black = Color::new(0, 0, 0);
+    let white = Color::new(255, 255, 255);
+    assert!(black.dominance_rank() > white.dominance_rank());
+
+    let mut rng = Pcg64::seed_from_u64(5);
+    let dark = Crab::new(String::from("Dark"), 3, black.clone(), Diet::Fish);
+    let light = Crab::new(String::from("Light"), 3, white.clone(), Diet::Fish);
+    assert!(!dark.genotype().is_heterozygous());
+
+    let child1 = Crab::breed_mendelian(String::from("Child 1"), &dark, &light, &mut rng);
+    let child2 = Crab::breed_mendelian(String::from("Child 2"), &light, &dark, &mut rng);
+    for child in [&child1, &child2] {
+        assert_eq!(child.color(), &black);
+        assert_eq!(child.genotype().recessive(), &white);
+        assert!(child.genotype().is_heterozygous());
+    }
+
+    let white_grandchildren = (0..100)
+        .map(|i| Crab::breed_mendelian(format!("Grandchild {}", i), &child1, &child2, &mut rng))
+        .filter(|grandchild| grandchild.color() == &white)
+        .count();
+    assert!(white_grandchildren > 10 && white_grandchildren < 40);
+
+    let reloaded = Crab::from_fields(&child1.to_fields(), 1).unwrap();
+    assert_eq!(reloaded.genotype(), child1.genotype());
+}
+
+#[test]
+fn student_diet_affinity_and_threshold() {
+    assert_eq!(Diet::Fish.affinity(Diet::Fish), 1.0);
+    assert!(Diet::Fish.affinity(Diet::Shellfish) > Diet::Fish.affinity(Diet::Plants));
+
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    reef.borrow_mut().add_prey(Box::new(Clam::new()));
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Fish);
+    crab.discover_reef(reef.clone());
+
+    // By default a crab only eats its own diet, and there is no recipe for plants.
+    assert!(!crab.hunt());
+    let vegan = new_crab("Vegan", 1);
+    assert!(vegan.choose_recipe(&Cookbook::new()).is_none());
+
+    // A starving crab settles for shellfish before plants.
+    crab.set_diet_threshold(0.5);
+    assert!(crab.will_eat(Diet::Shellfish) && !crab.will_eat(Diet::Plants));
+    assert!(crab.hunt());
+    assert_eq!(reef.borrow().census().get("Clam"), None);
+    assert_eq!(crab.choose_recipe(&Cookbook::new()).unwrap().name(), "cioppino");
+
+    let mut vegan = vegan;
+    vegan.set_diet_threshold(0.25);
+    assert_eq!(vegan.choose_recipe(&Cookbook::new()).unwrap().name(), "chowder");
+}
+
+#[test]
+fn student_seeded_breeding_and_escapes_are_reproducible() {
+    let diets = |seed: u64| {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let mut beach = Beach::new();
+        beach.add_crab(new_crab("Ferris", 1).with_pattern(Pattern::Spotted));
+        beach.add_crab(new_crab("Corro", 1));
+        for i in 0..10 {
+            beach.breed_crabs_with_rng(0, 1, format!("Crab {}", i), &mut rng).unwrap();
+        }
+        beach.crabs().skip(2).map(|c| (c.diet(), c.pattern())).collect::<Vec<_>>()
+    };
+    assert_eq!(diets(11), diets(11));
+    assert_ne!(diets(11), diets(12));
+    assert_eq!(
+        Diet::random_diet_from(&mut Pcg64::seed_from_u64(4)),
+        Diet::random_diet_from(&mut Pcg64::seed_from_u64(4))
+    );
+
+    let escapes = |seed: u64| {
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+        for _ in 0..10 {
+            let traits = PreyTraits {
+                speed: 1,
+                camouflage: 0.0,
+                size: Size::Small,
+            };
+            let model = ChanceEscape::new(0.5, 0.0, 0.0, 0.0);
+            reef.borrow_mut().add_prey(Box::new(ModeledPrey::new("Guppy", Diet::Fish, traits, Box::new(model))));
+        }
+        let mut crab = Crab::new(String::from("Ferris"), 1, Color::new_blue(), Diet::Fish);
+        crab.discover_reef(reef);
+        (0..10).map(|_| crab.hunt_with_rng(&mut rng)).collect::<Vec<bool>>()
+    };
+    assert_eq!(escapes(3), escapes(3));
+}
+
+#[test]
+fn student_scavengers_and_detritivores() {
+    let mut rng = Pcg64::seed_from_u64(9);
+    let diets: Vec<Diet> = (0..1000).map(|_| Diet::random_diet_from(&mut rng)).collect();
+    let rare = diets
+        .iter()
+        .filter(|d| matches!(d, Diet::Scavenger | Diet::Detritivore))
+        .count();
+    assert!(rare > 50 && rare < 150);
+    assert_eq!("Detritivore".parse::<Diet>(), Ok(Diet::Detritivore));
+
+    let mut dead = new_crab("Ferris", 5);
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    dead.discover_reef(reef.clone());
+    assert!(dead.hunt());
+
+    let carcass = Carcass::of(&dead);
+    assert_eq!(carcass.name(), "Ferris");
+    assert_eq!(carcass.nutrition(), 1);
+    reef.borrow_mut().add_prey(Box::new(carcass));
+    reef.borrow_mut().add_prey(Box::new(Detritus::new()));
+
+    let mut scavenger = Crab::new(String::from("Vulture"), 1, Color::new_red(), Diet::Scavenger);
+    scavenger.discover_reef(reef.clone());
+    assert!(scavenger.hunt());
+    assert!(!scavenger.hunt());
+
+    let mut detritivore = Crab::new(String::from("Sweeper"), 1, Color::new_red(), Diet::Detritivore);
+    detritivore.discover_reef(reef.clone());
+    assert!(detritivore.hunt());
+    assert_eq!(reef.borrow().population(), 0);
+}
+
+#[test]
+fn student_diet_compatibility_matrix() {
+    for diet in Diet::ALL {
+        assert!(diet.can_eat(diet));
+        assert_eq!(diet.affinity(diet), 1.0);
+        for other in Diet::ALL {
+            assert_eq!(diet.can_eat(other), diet.compatible_diets().contains(&other));
+        }
+    }
+    assert!(!Diet::Fish.can_eat(Diet::Plants));
+    assert_eq!(Diet::Plants.compatible_diets(), &[Diet::Plants]);
+}
+
+#[test]
+fn student_mutable_cookbook() {
+    let mut cookbook = Cookbook::new();
+    cookbook.add_recipe(Recipe::new(String::from("kelp salad"), Diet::Plants)).unwrap();
+    assert!(cookbook.add_recipe(Recipe::new(String::from("chowder"), Diet::Fish)).is_err());
+    assert_eq!(cookbook.recipes().count(), 3);
+
+    let old = cookbook.replace_recipe(Recipe::new(String::from("chowder"), Diet::Fish)).unwrap();
+    assert_eq!(old.diet(), Diet::Shellfish);
+    assert_eq!(cookbook.recipes().next().unwrap().diet(), Diet::Fish);
+    assert!(cookbook.replace_recipe(Recipe::new(String::from("gumbo"), Diet::Fish)).is_err());
+
+    assert_eq!(cookbook.remove_recipe("cioppino").unwrap().name(), "cioppino");
+    assert!(cookbook.remove_recipe("cioppino").is_none());
+    let names: Vec<&String> = cookbook.recipes().map(Recipe::name).collect();
+    assert_eq!(names, vec!["chowder", "kelp salad"]);
+    assert_eq!(new_crab("Ferris", 1).choose_recipe(&cookbook).unwrap().name(), "kelp salad");
+}
+
+#[test]
+fn student_cookbook_from_files() {
+    let toml = "# Our menu\n[[recipe]]\nname = \"chowder\"\ndiet = \"Shellfish\"\nprep_time = 30 # slow\n\n[[recipe]]\nname = \"kelp \\\"salad\\\"\"\ndiet = \"Plants\"\nnutrition = 2\n";
+    let cookbook = Cookbook::parse(toml, CookbookFormat::Toml).unwrap();
+    let recipes: Vec<&Recipe> = cookbook.recipes().collect();
+    assert_eq!(recipes.len(), 2);
+    assert_eq!(recipes[0].prep_time(), Some(30));
+    assert_eq!(recipes[0].nutrition(), None);
+    assert_eq!(recipes[1].name(), "kelp \"salad\"");
+    assert_eq!(recipes[1].nutrition(), Some(2));
+
+    let json = r#"{"recipes": [
+        {"name": "cioppino", "diet": "Fish", "nutrition": 4},
+        {"name": "kelp salad", "diet": "Plants"}
+    ]}"#;
+    let cookbook = Cookbook::parse(json, CookbookFormat::Json).unwrap();
+    assert_eq!(cookbook.find_by_name("cioppino").unwrap().nutrition(), Some(4));
+    assert_eq!(cookbook.find_by_name("kelp salad").unwrap().diet(), Diet::Plants);
+
+    let err = Cookbook::parse("[[recipe]]\nname = \"x\"\n\n[[recipe]]\nname = \"y\"\ndiet = \"Rocks\"\n", CookbookFormat::Toml).unwrap_err();
+    assert_eq!(err.line(), 1);
+    let err = Cookbook::parse("[\n{\"name\": \"x\", \"diet\": \"Fish\"},\n{\"name\": \"x\", \"diet\": \"Fish\"}\n]", CookbookFormat::Json).unwrap_err();
+    assert_eq!(err.line(), 3);
+    assert!(Cookbook::parse("[[recipe]]\nspicy = 3\n", CookbookFormat::Toml).is_err());
+    assert!(Cookbook::parse("[{\"name\": \"x\", \"diet\": \"Fish\"", CookbookFormat::Json).is_err());
+
+    let path = std::env::temp_dir().join(format!("ocean-cookbook-{}.json", std::process::id()));
+    std::fs::write(&path, json).unwrap();
+    assert_eq!(Cookbook::from_path(&path).unwrap().recipes().count(), 2);
+    std::fs::remove_file(&path).unwrap();
+    assert!(Cookbook::from_path(&path).is_err());
+    assert!(Cookbook::from_path("recipes.yaml").is_err());
+}
+
+#[test]
+fn student_recipes_with_ingredients() {
+    let chowder = Recipe::new(String::from("chowder"), Diet::Shellfish)
+        .with_ingredient("Clam", 1)
+        .with_ingredient("Shrimp", 1)
+        .with_ingredient("Clam", 1);
+    assert_eq!(chowder.ingredients().collect::<Vec<_>>(), vec![("Clam", 2), ("Shrimp", 1)]);
+
+    let reef = Rc::new(std::cell::RefCell::new(Reef::new()));
+    for _ in 0..2 {
+        reef.borrow_mut().add_prey(Box::new(Clam::new()));
+    }
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Shellfish);
+    crab.discover_reef(reef.clone());
+    assert!(crab.hunt() && crab.hunt());
+    assert_eq!(crab.larder("Clam"), 2);
+    assert!(!crab.can_cook(&chowder));
+    assert!(crab.cook(&chowder).is_err());
+
+    reef.borrow_mut().add_prey(Box::new(Shrimp::new(0)));
+    assert!(crab.hunt());
+    assert!(crab.can_cook(&chowder));
+    crab.cook(&chowder).unwrap();
+    assert_eq!(crab.larder("Clam"), 0);
+    assert!(!crab.can_cook(&chowder));
+
+    let toml = "[[recipe]]\nname = \"chowder\"\ndiet = \"Shellfish\"\n[recipe.ingredients]\nClam = 2\nShrimp = 1\n\n[[recipe]]\nname = \"salad\"\ndiet = \"Plants\"\n";
+    let from_toml = Cookbook::parse(toml, CookbookFormat::Toml).unwrap();
+    let json = r#"[{"name": "chowder", "diet": "Shellfish", "ingredients": {"Clam": 2, "Shrimp": 1}}]"#;
+    let from_json = Cookbook::parse(json, CookbookFormat::Json).unwrap();
+    for cookbook in [&from_toml, &from_json] {
+        let ingredients: Vec<_> = cookbook.find_by_name("chowder").unwrap().ingredients().collect();
+        assert_eq!(ingredients, vec![("Clam", 2), ("Shrimp", 1)]);
+    }
+    assert_eq!(from_toml.find_by_name("salad").unwrap().ingredients().count(), 0);
+    assert!(Cookbook::parse("[[recipe]]\n[recipe.ingredients]\nClam = \"two\"\n", CookbookFormat::Toml).is_err());
+}
+
+#[test]
+fn student_choose_recipe_with_filters() {
+    let mut cookbook = Cookbook::empty();
+    cookbook
+        .add_recipe(Recipe::new(String::from("bouillabaisse"), Diet::Fish).with_prep_time(120).with_difficulty(5))
+        .unwrap();
+    cookbook
+        .add_recipe(Recipe::new(String::from("ceviche"), Diet::Fish).with_prep_time(20).with_difficulty(2))
+        .unwrap();
+    cookbook.add_recipe(Recipe::new(String::from("fish sticks"), Diet::Fish)).unwrap();
+    let crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Fish);
+
+    assert_eq!(crab.choose_recipe(&cookbook).unwrap().name(), "bouillabaisse");
+    let quick = RecipeFilter {
+        max_prep_time: Some(30),
+        ..RecipeFilter::default()
+    };
+    assert_eq!(crab.choose_recipe_where(&cookbook, quick).unwrap().name(), "ceviche");
+    let easy_and_known = |recipe: &Recipe| recipe.difficulty().is_some_and(|d| d <= 2);
+    assert_eq!(crab.choose_recipe_where(&cookbook, easy_and_known).unwrap().name(), "ceviche");
+    let trivial = RecipeFilter {
+        max_difficulty: Some(1),
+        ..RecipeFilter::default()
+    };
+    assert_eq!(crab.choose_recipe_where(&cookbook, trivial).unwrap().name(), "fish sticks");
+    assert!(crab.choose_recipe_where(&cookbook, |_: &Recipe| false).is_none());
+
+    let json = r#"[{"name": "ceviche", "diet": "Fish", "difficulty": 2}]"#;
+    let loaded = Cookbook::parse(json, CookbookFormat::Json).unwrap();
+    assert_eq!(loaded.find_by_name("ceviche").unwrap().difficulty(), Some(2));
+}
+
+#[test]
+fn student_cookbook_queries() {
+    let mut cookbook = Cookbook::new();
+    assert_eq!(cookbook.len(), 2);
+    assert!(!cookbook.is_empty());
+    assert!(Cookbook::empty().is_empty());
+
+    cookbook.add_recipe(Recipe::new(String::from("fish tacos"), Diet::Fish)).unwrap();
+    let fish: Vec<&String> = cookbook.recipes_for_diet(Diet::Fish).map(Recipe::name).collect();
+    assert_eq!(fish, vec!["cioppino", "fish tacos"]);
+    assert_eq!(cookbook.recipes_for_diet(Diet::Plants).count(), 0);
+    assert_eq!(cookbook.find_by_name("chowder").unwrap().diet(), Diet::Shellfish);
+    assert!(cookbook.find_by_name("gumbo").is_none());
+}
+
+#[test]
+fn student_add_crabs_in_bulk() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("First", 1));
+    beach.add_crabs((0..1000).map(|i| new_crab(&format!("crab{}", i), i % 10)));
+    beach.extend(vec![new_crab("Ferris", 12), new_crab("crab7", 3)]);
+    assert_eq!(beach.size(), 1003);
+    assert_eq!(beach.get_crab(1).id(), 2);
+    assert_eq!(beach.get_crab(1002).id(), 1003);
+    assert_eq!(beach.find_crab_index("crab999"), Some(1000));
+    assert_eq!(beach.find_crabs_by_name("crab7").len(), 2);
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Ferris");
+
+    // Each crab is undone on its own.
+    assert!(beach.undo());
+    assert!(beach.undo());
+    assert_eq!(beach.size(), 1001);
+    assert_eq!(beach.find_crabs_by_name("crab7").len(), 1);
+}
+
+#[test]
+fn student_iterate_over_beach() {
+    let mut beach = Beach::new();
+    beach.extend(vec![new_crab("Ferris", 3), new_crab("Corro", 5)]);
+    beach.add_member_to_clan("pinchers", "Ferris");
+    assert_eq!(beach.get_crab(0).clan(), Some("pinchers"));
+
+    let mut speed = 0;
+    for crab in &beach {
+        speed += crab.speed();
+    }
+    assert_eq!(speed, 8);
+
+    // Draining the beach leaves its clans behind.
+    let crabs: Vec<Crab> = beach.into_iter().collect();
+    assert_eq!(crabs.iter().map(|crab| crab.name()).collect::<Vec<_>>(), vec!["Ferris", "Corro"]);
+    assert!(crabs.iter().all(|crab| crab.clan().is_none()));
+}
+
+#[test]
+fn student_find_crabs_by_name_with() {
+    let mut beach = Beach::new();
+    for name in ["Ferris", "ferris", "Ren\u{e9}e", "Rene\u{301}e", "Fernando"] {
+        beach.add_crab(new_crab(name, 3));
+    }
+    let names = |crabs: Vec<&Crab>| crabs.iter().map(|crab| crab.name().to_string()).collect::<Vec<_>>();
+
+    assert_eq!(names(beach.find_crabs_by_name_with("Ferris", NameSearch::new())), vec!["Ferris"]);
+    assert_eq!(names(beach.find_crabs_by_name_with("FERRIS", NameSearch::new().ignore_case())), vec!["Ferris", "ferris"]);
+    // Precomposed and combining accents match each other, in either direction.
+    assert_eq!(beach.find_crabs_by_name_with("Ren\u{e9}e", NameSearch::new()).len(), 2);
+    assert_eq!(beach.find_crabs_by_name_with("RENE\u{301}E", NameSearch::new().ignore_case()).len(), 2);
+    assert!(beach.find_crabs_by_name_with("Renee", NameSearch::new()).is_empty());
+
+    let prefix = NameSearch::new().ignore_case().with_mode(NameMatch::Prefix);
+    assert_eq!(names(beach.find_crabs_by_name_with("fer", prefix)), vec!["Ferris", "ferris", "Fernando"]);
+    let substring = NameSearch::new().with_mode(NameMatch::Substring);
+    assert_eq!(names(beach.find_crabs_by_name_with("rri", substring)), vec!["Ferris", "ferris"]);
+    assert_eq!(beach.query().name_like("\u{e9}", substring).count(), 2);
+}
+
+#[test]
+fn student_merge_cookbooks() {
+    let other = || {
+        let mut other = Cookbook::empty();
+        other.add_recipe(Recipe::new(String::from("chowder"), Diet::Shellfish).with_nutrition(5)).unwrap();
+        other.add_recipe(Recipe::new(String::from("paella"), Diet::Shellfish)).unwrap();
+        other
+    };
+    let names = |cookbook: &Cookbook| cookbook.recipes().map(|recipe| recipe.name().clone()).collect::<Vec<_>>();
+
+    let mut cookbook = Cookbook::new();
+    assert_eq!(cookbook.merge(other(), MergePolicy::KeepFirst), 1);
+    assert_eq!(names(&cookbook), vec!["chowder", "cioppino", "paella"]);
+    assert_eq!(cookbook.find_by_name("chowder").unwrap().nutrition(), None);
+
+    let mut cookbook = Cookbook::new();
+    assert_eq!(cookbook.merge(other(), MergePolicy::KeepHigherNutrition), 2);
+    assert_eq!(names(&cookbook), vec!["chowder", "cioppino", "paella"]);
+    assert_eq!(cookbook.find_by_name("chowder").unwrap().nutrition(), Some(5));
+    assert_eq!(cookbook.merge(other(), MergePolicy::KeepHigherNutrition), 0);
+
+    let mut cookbook = Cookbook::new();
+    assert_eq!(cookbook.merge(other(), MergePolicy::Rename), 2);
+    assert_eq!(cookbook.merge(other(), MergePolicy::Rename), 2);
+    assert_eq!(
+        names(&cookbook),
+        vec!["chowder", "cioppino", "chowder (2)", "paella", "chowder (3)", "paella (2)"]
+    );
+}
+
+#[test]
+fn student_generated_cookbook() {
+    let mut rng = seeded(5);
+    assert!(Cookbook::generate(&mut rng, &[], 10).is_empty());
+
+    let prey: Vec<Box<dyn Prey>> = vec![Box::new(Clam::new()), Box::new(Clam::new()), Box::new(Algae::new()), Box::new(Minnow::new(3))];
+    let kinds: Vec<PreyView> = prey.iter().map(|prey| PreyView::of(0, prey.as_ref())).collect();
+    let cookbook = Cookbook::generate(&mut rng, &kinds, 50);
+    assert_eq!(cookbook.len(), 50);
+    for recipe in cookbook.recipes() {
+        let ingredients: Vec<(&str, u32)> = recipe.ingredients().collect();
+        assert_eq!(ingredients.len(), 1);
+        let (kind, quantity) = ingredients[0];
+        let view = kinds.iter().find(|view| view.kind == kind).unwrap();
+        assert_eq!(recipe.diet(), view.diet);
+        assert_eq!(recipe.nutrition(), Some(view.nutrition * quantity));
+        assert!(recipe.name().contains(&kind.to_lowercase()));
+        assert!(recipe.prep_time().is_some() && recipe.difficulty().is_some());
+    }
+    for diet in [Diet::Shellfish, Diet::Plants, Diet::Fish] {
+        assert!(cookbook.recipes_for_diet(diet).count() > 0);
+    }
+
+    // Generated names never clash, so the recipes can be looked up by name.
+    let names: std::collections::HashSet<&String> = cookbook.recipes().map(Recipe::name).collect();
+    assert_eq!(names.len(), 50);
+}
+
+#[test]
+fn student_recipe_ratings() {
+    let mut cookbook = Cookbook::new();
+    cookbook.add_recipe(Recipe::new(String::from("fish tacos"), Diet::Fish)).unwrap();
+    cookbook.add_recipe(Recipe::new(String::from("sushi"), Diet::Fish)).unwrap();
+
+    let mut beach = Beach::new();
+    beach.add_crab(Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Fish));
+    beach.add_crab(Crab::new(String::from("Corro"), 3, Color::new_red(), Diet::Fish));
+    {
+        let mut ferris = beach.get_crab_mut(0);
+        assert!(ferris.rate_recipe("sushi", 6).is_err());
+        ferris.rate_recipe("fish tacos", 4).unwrap();
+        ferris.rate_recipe("sushi", 5).unwrap();
+        assert_eq!(ferris.rating("sushi"), Some(5));
+        assert_eq!(ferris.choose_best_recipe(&cookbook).unwrap().name(), "sushi");
+        ferris.rate_recipe("sushi", 1).unwrap();
+        assert_eq!(ferris.choose_best_recipe(&cookbook).unwrap().name(), "fish tacos");
+    }
+    beach.get_crab_mut(1).rate_recipe("sushi", 5).unwrap();
+    beach.get_crab_mut(1).rate_recipe("fish tacos", 4).unwrap();
+
+    // Ratings never make a crab choose food it will not eat.
+    assert!(new_crab("Vegan", 1).choose_best_recipe(&cookbook).is_none());
+    let top = cookbook.top_rated(beach.crabs(), 5);
+    let top: Vec<(&str, f64)> = top.iter().map(|(r, avg)| (r.name().as_str(), *avg)).collect();
+    assert_eq!(top, vec![("fish tacos", 4.0), ("sushi", 3.0)]);
+    assert_eq!(cookbook.top_rated(beach.crabs(), 1).len(), 1);
+}
+
+#[test]
+fn student_simulation_step_order() {
+    let mut ocean = Ocean::new();
+    let reef = ocean.add_reef(Reef::with_name(String::from("kelp"))).unwrap();
+    for _ in 0..3 {
+        reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    }
+    let mut ferris = new_crab("Ferris", 5);
+    ferris.discover_reef(reef.clone());
+    let mut beach = Beach::new();
+    beach.add_crab(ferris);
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 7);
+    // Scheduled events run after the step's hunts.
+    sim.schedule(0, |ocean: &mut Ocean, _: &mut dyn OceanRng| {
+        let ferris = ocean.beaches().next().unwrap().get_crab(0);
+        assert_eq!(ferris.hunts(), 1);
+        assert_eq!(ferris.age(), 1);
+    });
+    sim.schedule(2, |ocean: &mut Ocean, _: &mut dyn OceanRng| ocean.add_beach(Beach::new()));
+
+    let first = sim.step();
+    assert_eq!((first.tick, first.hunts, first.events_run), (0, 1, 1));
+    let reports = sim.run(2);
+    assert_eq!(reports.iter().map(|r| r.tick).collect::<Vec<_>>(), vec![1, 2]);
+    assert_eq!(reports[1].events_run, 1);
+    assert_eq!(sim.clock(), 3);
+    assert_eq!(sim.ocean().beaches().count(), 2);
+
+    let ferris = sim.ocean().beaches().next().unwrap().get_crab(0);
+    assert_eq!(ferris.age(), 3);
+    let catches: usize = std::iter::once(&first).chain(&reports).map(|r| r.catches).sum();
+    assert_eq!(ferris.energy() as usize, catches);
+    assert_eq!(reef.borrow().population(), 3 - catches);
+}
+
+#[test]
+fn student_simulation_publishes_events() {
+    let mut ocean = Ocean::new();
+    let reef = ocean.add_reef(Reef::with_name(String::from("kelp"))).unwrap();
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let mut ferris = new_crab("Ferris", 5);
+    ferris.discover_reef(reef.clone());
+    let mut beach = Beach::new();
+    beach.add_crab(ferris);
+    beach.add_crab(new_crab("Corro", 2));
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 1);
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let sink = log.clone();
+    sim.subscribe(Box::new(move |event: &OceanEvent| sink.borrow_mut().push(event.clone())));
+    sim.schedule(0, |ocean: &mut Ocean, _: &mut dyn OceanRng| {
+        let beach = ocean.beaches_mut().next().unwrap();
+        beach.breed_crabs(0, 1, String::from("Junior"));
+        beach.add_member_to_clan("pinchers", "Junior");
+    });
+    sim.step();
+
+    let kelp = Some(String::from("kelp"));
+    assert_eq!(
+        *log.borrow(),
+        vec![
+            OceanEvent::PreyCaught { crab: String::from("Ferris"), prey: String::from("Algae") },
+            OceanEvent::ReefDepleted { reef: kelp },
+            OceanEvent::CrabBorn {
+                name: String::from("Junior"),
+                parents: (String::from("Ferris"), String::from("Corro")),
+            },
+            OceanEvent::ClanJoined { clan: String::from("pinchers"), crab: String::from("Junior") },
+            OceanEvent::StepFinished { tick: 0 },
+        ]
+    );
+
+    // Changes made between steps are published on demand.
+    log.borrow_mut().clear();
+    sim.ocean_mut().beaches_mut().next().unwrap().bury_crab(2);
+    sim.publish_events();
+    assert_eq!(
+        *log.borrow(),
+        vec![
+            OceanEvent::CrabDied { name: String::from("Junior") },
+            OceanEvent::ClanLeft { clan: String::from("pinchers"), crab: String::from("Junior") },
+        ]
+    );
+}
+
+#[test]
+fn student_beach_save_and_load() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(Crab::new(String::from("Corro"), 3, Color::new_red(), Diet::Fish));
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    beach.add_member_to_clan("pinchers", "Junior");
+
+    let mut loaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(loaded.save(), beach.save());
+    let names: Vec<&str> = loaded.crabs().map(Crab::name).collect();
+    assert_eq!(names, vec!["Ferris", "Corro", "Junior"]);
+    assert_eq!(loaded.get_crab(0).offspring(), 1);
+    assert_eq!(loaded.get_clan_of_crab("Junior"), Some("pinchers"));
+    assert!(loaded.drain_events().is_empty());
+
+    let err = Beach::load("crab\tFerris\n").unwrap_err();
+    assert_eq!(err.line(), 1);
+}
+
+#[test]
+fn student_clan_ids_are_sorted() {
+    let mut clans: ClanSystem = ClanSystem::new();
+    assert!(clans.get_clan_ids().is_empty());
+    clans.create_clan(String::from("snappers"));
+    clans.create_clan(String::from("pinchers"));
+    assert_eq!(clans.get_clan_ids(), vec!["pinchers", "snappers"]);
+}
+
+#[test]
+fn student_seeded_simulations_repeat() {
+    fn run(seed: u64) -> (Vec<StepReport>, String) {
+        let mut ocean = Ocean::new();
+        let mut reef = Reef::with_name(String::from("kelp"));
+        reef.set_take_policy(TakePolicy::Random);
+        reef.set_regen_policy(RegenPolicy::new().with_rate(1.5, || Box::new(Krill::new(2))));
+        let reef = ocean.add_reef(reef).unwrap();
+        let mut beach = Beach::new();
+        for (name, speed) in [("Ferris", 3), ("Corro", 1)] {
+            let mut crab = Crab::new(String::from(name), speed, Color::new_red(), Diet::Shellfish);
+            crab.discover_reef(reef.clone());
+            beach.add_crab(crab);
+        }
+        ocean.add_beach(beach);
+
+        let mut sim = Simulation::new(ocean, seed);
+        for tick in 0..5 {
+            sim.schedule(tick, move |ocean: &mut Ocean, rng: &mut dyn OceanRng| {
+                let beach = ocean.beaches_mut().next().unwrap();
+                beach.breed_crabs_with_rng(0, 1, format!("Kid{}", tick), rng).unwrap();
+            });
+        }
+        let reports = sim.run(10);
+        (reports, sim.ocean().beaches().next().unwrap().save())
+    }
+
+    assert_eq!(run(3), run(3));
+    assert_ne!(run(3), run(4));
+
+    let pick = |seed: u64| {
+        let mut reef = Reef::new();
+        reef.set_take_policy(TakePolicy::Random);
+        for speed in 0..10 {
+            reef.add_prey(Box::new(Krill::new(speed)));
+        }
+        let mut rng = seeded(seed);
+        (0..5).map(|_| reef.take_prey_where_with(|_| true, &mut rng).unwrap().to_fields()).collect::<Vec<_>>()
+    };
+    assert_eq!(pick(9), pick(9));
+}
+
+#[test]
+fn student_ocean_errors_instead_of_panics() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    assert_eq!(
+        beach.try_breed_crabs(0, 3, String::from("Junior")),
+        Err(OceanError::CrabIndexOutOfBounds { index: 3, len: 1 })
+    );
+    assert_eq!(beach.size(), 1);
+    assert!(beach.try_bury_crab(1).is_err());
+    assert_eq!(beach.try_bury_crab(0).unwrap().name(), "Ferris");
+
+    beach.add_crab(new_crab("Corro", 1));
+    beach.add_member_to_clan("pinchers", "Corro");
+    let err = beach.get_winner_clan("pinchers", "snappers").unwrap_err();
+    assert_eq!(err, OceanError::ClanNotFound(String::from("snappers")));
+    assert_eq!(err.to_string(), "Clan snappers does not exist");
+
+    let mut cookbook = Cookbook::new();
+    let err = cookbook.add_recipe(Recipe::new(String::from("chowder"), Diet::Fish)).unwrap_err();
+    assert_eq!(err, OceanError::DuplicateName { kind: "recipe", name: String::from("chowder") });
+    let gumbo = Recipe::new(String::from("gumbo"), Diet::Shellfish).with_ingredient("Clam", 1);
+    let err = new_crab("Corro", 1).cook(&gumbo).unwrap_err();
+    assert!(matches!(err, OceanError::MissingIngredients { .. }));
+
+    // Loading errors keep their line numbers, and every error can be reported as a string.
+    let err: OceanError = Beach::load("crab\tFerris\n").unwrap_err().into();
+    assert!(matches!(&err, OceanError::Parse(parse) if parse.line() == 1));
+    let message: String = err.into();
+    assert!(message.starts_with("line 1:"));
+}
+
+#[test]
+#[should_panic(expected = "Index out of bounds")]
+fn student_breed_crabs_still_panics() {
+    Beach::new().breed_crabs(0, 0, String::from("Nobody"));
+}
+
+#[cfg(feature = "trace")]
+#[test]
+fn student_trace_records_hunts_and_competitions() {
+    use ocean::trace::*;
+
+    let reef = Rc::new(RefCell::new(Reef::with_name(String::from("kelp"))));
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let mut beach = Beach::new();
+    let mut ferris = new_crab("Ferris", 5);
+    ferris.discover_reef(reef.clone());
+    beach.add_crab(ferris);
+    beach.add_member_to_clan("pinchers", "Ferris");
+
+    let recorder = Recorder::new();
+    set_subscriber(Box::new(recorder.clone()));
+    beach.get_crab_mut(0).hunt();
+    beach.get_winner_clan("pinchers", "pinchers").unwrap();
+    take_subscriber();
+    beach.get_crab_mut(0).hunt();
+
+    let field = |name: &'static str, value: &str| (name, value.to_string());
+    let records = recorder.records();
+    assert_eq!(
+        records[..5],
+        [
+            Record::Enter { span: "hunt", fields: vec![field("crab", "Ferris"), field("reefs", "1")], depth: 0 },
+            Record::Event {
+                name: "take_prey",
+                fields: vec![
+                    field("reef", "kelp"),
+                    field("policy", "Fifo"),
+                    field("prey", "Algae"),
+                    field("remaining", "0"),
+                ],
+                depth: 1,
+            },
+            Record::Event { name: "caught", fields: vec![field("reef", "0"), field("prey", "Algae")], depth: 1 },
+            Record::Event { name: "finished", fields: vec![field("caught", "true")], depth: 1 },
+            Record::Exit { span: "hunt", depth: 0 },
+        ]
+    );
+    assert_eq!(records.last(), Some(&Record::Exit { span: "compete", depth: 0 }));
+    assert!(records.contains(&Record::Event { name: "result", fields: vec![field("winner", "draw")], depth: 1 }));
+    // Nothing is recorded once the subscriber is removed.
+    assert_eq!(recorder.records().len(), records.len());
+}
+
+#[cfg(feature = "proptest")]
+#[test]
+fn student_arbitrary_hunt_never_loses_prey() {
+    use ocean::arbitrary::*;
+
+    check::<Reef, _>(200, 7, |reef| {
+        let mut reef = Reef::load(&reef.save().unwrap()).unwrap();
+        let before = reef.population();
+        let caught = new_crab("Ferris", 50).hunt_in(&mut reef);
+        reef.population() + caught as usize == before
+    });
+    check::<Crab, _>(200, 7, |crab| !crab.name().is_empty() && crab.speed() <= 100);
+    check::<Beach, _>(100, 7, |beach| {
+        let names: std::collections::HashSet<&str> = beach.crabs().map(|crab| crab.name()).collect();
+        names.len() == beach.size()
+    });
+
+    // Shrinking keeps just enough of a failing value to still fail.
+    let mut rng = seeded(3);
+    let crowded = std::iter::repeat_with(|| Reef::arbitrary(&mut rng)).find(|reef| reef.population() > 5).unwrap();
+    let minimal = minimize(crowded, |reef| reef.population() < 3);
+    assert_eq!(minimal.population(), 3);
+    assert_eq!(minimal.capacity(), None);
+
+    let recipe = std::iter::repeat_with(|| Recipe::arbitrary(&mut rng)).find(|r| r.ingredients().count() == 3).unwrap();
+    let minimal = minimize(recipe, |recipe| recipe.ingredients().count() < 1);
+    assert_eq!(minimal.ingredients().count(), 1);
+    assert_eq!(minimal.name().len(), 1);
+    assert_eq!(minimal.nutrition(), None);
+}
+
+#[test]
+fn student_beach_lookups_stay_correct_as_crabs_come_and_go() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_crab(new_crab("Corro", 9));
+    beach.add_crab(new_crab("Ferris", 9));
+    beach.add_crab(new_crab("Pinchy", 2));
+
+    // Ties go to the crab added last.
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Ferris");
+    assert_eq!(beach.find_crab_index("Ferris"), Some(0));
+    assert_eq!(beach.find_crabs_by_name("Ferris").iter().map(|c| c.speed()).collect::<Vec<_>>(), vec![5, 9]);
+
+    beach.bury_crab(2);
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Corro");
+    assert_eq!(beach.find_crabs_by_name("Ferris").len(), 1);
+    assert_eq!(beach.find_crab_index("Pinchy"), Some(2));
+    beach.breed_crabs(0, 1, String::from("Speedy"));
+    assert_eq!(beach.find_crab_index("Speedy"), Some(3));
+    assert!(beach.find_crabs_by_name("Nobody").is_empty());
+
+    beach.add_member_to_clan("pinchers", "Ferris");
+    beach.add_member_to_clan("pinchers", "Corro");
+    beach.add_member_to_clan("snappers", "Pinchy");
+    assert_eq!(beach.get_clan_system().get_clan_total_speed("pinchers"), Some(14));
+    beach.add_member_to_clan("snappers", "Corro");
+    assert_eq!(beach.get_clan_system().get_clan_total_speed("pinchers"), Some(5));
+    assert_eq!(beach.get_clan_system().get_clan_average_speed("snappers"), Some(5));
+    assert_eq!(beach.get_clan_system().get_clan_total_speed("nobody"), None);
+    assert_eq!(beach.get_winner_clan("pinchers", "snappers").unwrap(), None);
+
+    let reloaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(reloaded.find_crab_index("Speedy"), Some(3));
+    assert_eq!(reloaded.get_fastest_crab().unwrap().name(), "Corro");
+    assert_eq!(reloaded.get_clan_system().get_clan_total_speed("snappers"), Some(11));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn student_wasm_handles_drive_a_simulation_with_json_snapshots() {
+    use ocean::persist::{parse_json, JsonValue};
+    use ocean::wasm::*;
+
+    let mut beach = BeachHandle::new();
+    assert_eq!(beach.add_crab("Ferris", 5, "#0000ff", "Plants"), Ok(0));
+    assert_eq!(beach.add_crab("Corro", 9, "#ff0000", "Plants"), Ok(1));
+    assert!(beach.add_crab("Pinchy", 2, "blue", "Plants").is_err());
+    assert!(beach.add_crab("Pinchy", 2, "#00ff00", "Rocks").is_err());
+    beach.join_clan("pinchers", "Ferris").unwrap();
+    assert!(beach.join_clan("pinchers", "Nobody").is_err());
+    assert_eq!(beach.crab(1).unwrap().color(), "#ff0000");
+    assert!(beach.crab(2).is_err());
+    assert_eq!(beach.find_crab("Corro"), Some(1));
+
+    let snapshot = parse_json(&beach.snapshot()).unwrap();
+    let Some(JsonValue::Array(crabs)) = snapshot.get("crabs") else { panic!("no crabs in {:?}", snapshot) };
+    assert_eq!(crabs[0].get("name").and_then(JsonValue::as_str), Some("Ferris"));
+    assert_eq!(crabs[1].get("speed").and_then(JsonValue::as_u32), Some(9));
+    let Some(JsonValue::Array(clans)) = snapshot.get("clans") else { panic!("no clans in {:?}", snapshot) };
+    assert_eq!(clans[0].get("id").and_then(JsonValue::as_str), Some("pinchers"));
+    let events = parse_json(&beach.take_events()).unwrap();
+    assert_eq!(events, parse_json(r#"[{"type": "ClanJoined", "clan": "pinchers", "crab": "Ferris"}]"#).unwrap());
+
+    let mut simulation = SimulationHandle::new(42);
+    assert_eq!(simulation.add_beach(beach), 0);
+    simulation.add_reef("kelp", 0, 0, 0, 3).unwrap();
+    assert!(simulation.add_reef("kelp", 0, 0, 0, 1).is_err());
+    simulation.discover_reef(0, 0, "kelp").unwrap();
+    assert!(simulation.discover_reef(0, 5, "kelp").is_err());
+    assert!(simulation.discover_reef(0, 0, "sand").is_err());
+
+    let report = parse_json(&simulation.step()).unwrap();
+    assert_eq!(report.get("catches").and_then(JsonValue::as_u32), Some(1));
+    let JsonValue::Array(reports) = parse_json(&simulation.run(2)).unwrap() else { panic!("run returned no array") };
+    assert_eq!(reports.len(), 2);
+    assert_eq!(simulation.clock(), 3.0);
+
+    let snapshot = parse_json(&simulation.snapshot()).unwrap();
+    let Some(JsonValue::Array(reefs)) = snapshot.get("reefs") else { panic!("no reefs in {:?}", snapshot) };
+    assert_eq!(reefs[0].get("population").and_then(JsonValue::as_u32), Some(0));
+    let JsonValue::Array(events) = parse_json(&simulation.take_events()).unwrap() else { panic!("no events") };
+    let caught = events.iter().filter(|e| e.get("type").and_then(JsonValue::as_str) == Some("PreyCaught")).count();
+    assert_eq!(caught, 3);
+    assert_eq!(simulation.take_events(), "[]");
+}
+
+#[test]
+fn student_simulation_snapshots_resume_where_they_left_off() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name(String::from("kelp"))).unwrap();
+    let sand = ocean.add_reef(Reef::new()).unwrap();
+    for _ in 0..6 {
+        kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+        sand.borrow_mut().add_prey(Box::new(Shrimp::new(2)));
+    }
+    kelp.borrow_mut().set_capacity(Some(20));
+    ocean.reef_registry_mut().link(0, 1).unwrap();
+
+    let mut beach = Beach::new();
+    let mut ferris = new_crab("Ferris\tthe\nfast", 5);
+    ferris.discover_reef(kelp.clone());
+    ferris.discover_reef(sand.clone());
+    beach.add_crab(ferris);
+    beach.add_crab(new_crab("Corro", 7));
+    beach.add_member_to_clan("pinchers", "Corro");
+    ocean.add_beach(beach);
+    ocean.add_beach(Beach::new());
+
+    let mut simulation = Simulation::new(ocean, 3);
+    simulation.set_migration_rate(0.25);
+    simulation.run(2);
+
+    let path = std::env::temp_dir().join(format!("ocean-snapshot-{}.txt", std::process::id()));
+    simulation.save(&path).unwrap();
+    let mut resumed = Simulation::load(&path).unwrap();
+    let mut again = Simulation::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(resumed.clock(), 2);
+    assert_eq!(resumed.snapshot().unwrap(), simulation.snapshot().unwrap());
+    assert_eq!(resumed.ocean().beaches().count(), 2);
+    let beach = resumed.ocean().beaches().next().unwrap();
+    assert_eq!(beach.get_crab(0).name(), "Ferris\tthe\nfast");
+    assert_eq!(beach.get_crab(0).reefs().count(), 2);
+    assert_eq!(beach.get_crab(0).hunts(), simulation.ocean().beaches().next().unwrap().get_crab(0).hunts());
+    assert_eq!(beach.get_clan_of_crab("Corro"), Some("pinchers"));
+    assert_eq!(resumed.ocean().reef_registry().links_of(1), &[0]);
+    assert_eq!(resumed.ocean().reef_by_name("kelp").unwrap().borrow().capacity(), Some(20));
+    assert_eq!(resumed.run(3), again.run(3));
+
+    // The crab hunts in the reloaded reefs, not copies of them.
+    let kelp = resumed.ocean().reef_by_name("kelp").unwrap();
+    let before = kelp.borrow().population() + resumed.ocean().reef_registry().get(1).unwrap().borrow().population();
+    resumed.ocean_mut().beaches_mut().next().unwrap().get_crab_mut(0).hunt();
+    let after = kelp.borrow().population() + resumed.ocean().reef_registry().get(1).unwrap().borrow().population();
+    assert_eq!(after + 1, before);
+
+    let bad = |text: &str| Simulation::from_snapshot(text, Box::new(seeded(0))).unwrap_err();
+    assert!(matches!(bad("clock\t1\n"), OceanError::Parse(e) if e.line() == 1));
+    assert!(bad("ocean\t2\n").to_string().contains("unsupported snapshot version 2"));
+    assert!(matches!(bad("ocean\t1\nreef\tFifo\nprey\tUnicorn\n"), OceanError::Parse(e) if e.line() == 3));
+    assert!(matches!(bad("ocean\t1\nbeach\ndiscovered\t0\t0\n"), OceanError::Parse(e) if e.line() == 3));
+    assert!(matches!(bad("ocean\t1\nlink\t0\t1\n"), OceanError::Parse(e) if e.line() == 2));
+}
+
+#[test]
+fn student_beach_hunt_all_parallel_matches_hunting_in_turn() {
+    let build = || {
+        let reefs: Vec<Rc<RefCell<Reef>>> = (0..6)
+            .map(|i| {
+                let mut reef = Reef::new();
+                for _ in 0..i {
+                    reef.add_prey(Box::new(Algae::new()));
+                    reef.add_prey(Box::new(Clam::new()));
+                }
+                if i == 3 {
+                    reef.set_take_policy(TakePolicy::Random);
+                }
+                Rc::new(RefCell::new(reef))
+            })
+            .collect();
+        let mut beach = Beach::new();
+        // Crabs 0-2 share reefs 1 and 2 through crab 1; crabs 3 and 4 have reefs of their own.
+        let homes: [&[usize]; 6] = [&[1], &[2, 1], &[2], &[3, 4], &[5], &[]];
+        for (i, home) in homes.iter().enumerate() {
+            let mut crab = new_crab(&format!("crab{}", i), 10);
+            for &reef in home.iter() {
+                crab.discover_reef(reefs[reef].clone());
+            }
+            beach.add_crab(crab);
+        }
+        (beach, reefs)
+    };
+    let populations = |reefs: &[Rc<RefCell<Reef>>]| reefs.iter().map(|r| r.borrow().population()).collect::<Vec<_>>();
+
+    let (mut one_thread, reefs1) = build();
+    let (mut four_threads, reefs4) = build();
+    assert_eq!(one_thread.hunt_all_parallel_on(1), 5);
+    assert_eq!(four_threads.hunt_all_parallel_on(4), 5);
+    assert_eq!(populations(&reefs1), populations(&reefs4));
+    // Crab 0 takes reef 1's only algae before crab 1, who shares it, gets a turn.
+    assert_eq!(populations(&reefs1), vec![0, 1, 2, 5, 8, 9]);
+    for beach in [&one_thread, &four_threads] {
+        let hunts: Vec<u32> = beach.crabs().map(|crab| crab.hunts()).collect();
+        assert_eq!(hunts, vec![1, 1, 1, 1, 1, 0]);
+        assert_eq!(beach.get_crab(1).larder("Algae"), 1);
+        assert_eq!(beach.get_crab(3).reefs().count(), 2);
+    }
+    let caught = |beach: &mut Beach| beach.drain_events().iter().filter(|e| matches!(e, OceanEvent::PreyCaught { .. })).count();
+    assert_eq!(caught(&mut one_thread), 5);
+    assert_eq!(caught(&mut four_threads), 5);
+
+    // The reefs are shared handles again, so ordinary hunts see the same prey.
+    assert!(four_threads.get_crab_mut(3).hunt());
+    assert_eq!(reefs4[3].borrow().population(), 4);
+    assert_eq!(Beach::new().hunt_all_parallel(), 0);
+}
+
+#[test]
+fn student_shared_simulation_is_send_and_sync() {
+    use ocean::shared::SharedSimulation;
+
+    fn assert_send_sync<T: Send + Sync + Clone + 'static>() {}
+    assert_send_sync::<SharedSimulation>();
+
+    let shared = SharedSimulation::spawn(|| {
+        let mut ocean = Ocean::new();
+        let reef = ocean.generate_reef(0, 0, 0, 100);
+        let mut beach = Beach::new();
+        for i in 0..4 {
+            let mut crab = new_crab(&format!("crab{}", i), 5);
+            crab.discover_reef(reef.clone());
+            beach.add_crab(crab);
+        }
+        ocean.add_beach(beach);
+        Simulation::new(ocean, 1)
+    });
+    let workers: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || shared.run(5).unwrap().iter().map(|report| report.catches).sum::<usize>())
+        })
+        .collect();
+    let caught: usize = workers.into_iter().map(|worker| worker.join().unwrap()).sum();
+    assert_eq!(shared.clock(), Ok(20));
+    let hunts = shared.with(|simulation| simulation.ocean().beaches().flat_map(Beach::crabs).map(Crab::hunts).sum::<u32>());
+    assert_eq!(hunts, Ok(caught as u32));
+
+    let path = std::env::temp_dir().join(format!("ocean-shared-{}.txt", std::process::id()));
+    shared.save(&path).unwrap();
+    let resumed = SharedSimulation::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(resumed.snapshot(), shared.snapshot());
+    assert!(matches!(SharedSimulation::load(&path), Err(OceanError::Io(_))));
+
+    let broken = shared.clone();
+    assert_eq!(broken.with(|_| -> u32 { panic!("a bad request") }), Err(OceanError::SimulationStopped));
+    assert_eq!(shared.step(), Err(OceanError::SimulationStopped));
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn student_shared_simulation_can_be_awaited() {
+    use ocean::shared::SharedSimulation;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    // A one-future executor that parks the test's thread until the future wakes it.
+    struct Unpark(std::thread::Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+            std::thread::park();
+        }
+    }
+
+    let shared = SharedSimulation::spawn(|| {
+        let mut ocean = Ocean::new();
+        let reef = ocean.generate_reef(0, 0, 0, 10);
+        let mut beach = Beach::new();
+        let mut crab = new_crab("Ferris", 5);
+        crab.discover_reef(reef);
+        beach.add_crab(crab);
+        ocean.add_beach(beach);
+        Simulation::new(ocean, 1)
+    });
+    let pending = shared.run_async(3);
+    let report = block_on(shared.step_async()).unwrap();
+    assert_eq!(report.tick, 3);
+    assert_eq!(block_on(pending).unwrap().len(), 3);
+    assert_eq!(block_on(shared.with_async(|simulation| simulation.clock())), Ok(4));
+
+    drop(block_on(shared.with_async(|_| -> u32 { panic!("a bad request") })));
+    assert_eq!(block_on(shared.step_async()), Err(OceanError::SimulationStopped));
+}
+
+#[test]
+fn student_crab_sees_prey_without_taking_it() {
+    let kelp = Rc::new(RefCell::new(Reef::new()));
+    kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    kelp.borrow_mut().add_prey(Box::new(Clam::new()));
+    let rocks = Rc::new(RefCell::new(Reef::new()));
+    rocks.borrow_mut().add_prey(Box::new(Minnow::new(3)));
+    let mut ferris = new_crab("Ferris", 3);
+    assert_eq!(ferris.visible_prey().count(), 0);
+    ferris.discover_reef(kelp.clone());
+    ferris.discover_reef(rocks.clone());
+
+    let seen: Vec<PreyView> = ferris.visible_prey().collect();
+    let kinds: Vec<(usize, &str)> = seen.iter().map(|view| (view.reef, view.kind)).collect();
+    assert_eq!(kinds, vec![(0, "Algae"), (0, "Clam"), (1, "Minnow")]);
+    assert_eq!(seen[0].diet, Diet::Plants);
+    assert_eq!(seen[0].nutrition, seen[0].size.nutrition());
+    assert_eq!(kelp.borrow().population() + rocks.borrow().population(), 3);
+}
+
+#[test]
+fn student_crab_can_hunt_from_its_best_reef_first() {
+    let sparse = Rc::new(RefCell::new(Reef::new()));
+    sparse.borrow_mut().add_prey(Box::new(Algae::new()));
+    sparse.borrow_mut().add_prey(Box::new(Minnow::new(0)));
+    let rich = Rc::new(RefCell::new(Reef::new()));
+    for _ in 0..3 {
+        rich.borrow_mut().add_prey(Box::new(Algae::new()));
+    }
+    let mut ferris = new_crab("Ferris", 3);
+    assert_eq!(ferris.best_reef(), None);
+    ferris.discover_reef(sparse.clone());
+    assert_eq!(ferris.best_reef(), Some(0));
+    ferris.discover_reef(rich.clone());
+    assert_eq!(ferris.best_reef(), Some(1));
+
+    assert!(!ferris.best_reef_first());
+    assert!(ferris.hunt());
+    assert_eq!((sparse.borrow().population(), rich.borrow().population()), (1, 3));
+    ferris.set_best_reef_first(true);
+    assert!(ferris.hunt());
+    assert_eq!((sparse.borrow().population(), rich.borrow().population()), (1, 2));
+}
+
+#[test]
+fn student_crab_hunt_stats() {
+    let open_water = Rc::new(RefCell::new(Reef::new()));
+    open_water.borrow_mut().add_prey(Box::new(Minnow::new(5)));
+    let shallows = Rc::new(RefCell::new(Reef::new()));
+    for _ in 0..2 {
+        shallows.borrow_mut().add_prey(Box::new(Minnow::new(1)));
+    }
+    let mut ferris = Crab::new(String::from("Ferris"), 3, Color::new_red(), Diet::Fish);
+    assert_eq!(ferris.hunt_stats(), HuntStats::default());
+    assert_eq!(ferris.hunt_stats().success_rate(), 0.0);
+    ferris.discover_reef(open_water);
+    ferris.discover_reef(shallows);
+
+    // The fast minnow gets away every time, and the slow ones are caught until they run out.
+    let caught: Vec<bool> = (0..4).map(|_| ferris.hunt()).collect();
+    assert_eq!(caught, vec![true, true, false, false]);
+    let stats = ferris.hunt_stats();
+    assert_eq!((stats.attempts, stats.successes, stats.escapes), (4, 2, 4));
+    assert_eq!(stats.favorite_reef, Some(1));
+    assert_eq!(stats.success_rate(), 0.5);
+    assert_eq!(ferris.hunts(), 2);
+
+    let reloaded = Crab::from_fields(&ferris.to_fields(), 1).unwrap();
+    assert_eq!(reloaded.to_fields(), ferris.to_fields());
+
+    let mut beach = Beach::new();
+    beach.add_crab(ferris);
+    beach.add_crab(new_crab("Corro", 2));
+    let board = beach.leaderboard(&["success".parse().unwrap()], 2);
+    assert_eq!(board[0].scores, vec![(RankBy::SuccessRate, 50)]);
+    assert_eq!(board[1].scores, vec![(RankBy::SuccessRate, 0)]);
+}
+
+#[test]
+fn student_beach_subscribers_receive_events_live() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 2));
+    beach.add_member_to_clan("pinchers", "Ferris");
+    let events = beach.subscribe();
+    let logger = std::thread::spawn(move || events.iter().collect::<Vec<OceanEvent>>());
+
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    beach.add_member_to_clan("snappers", "Corro");
+    beach.get_winner_clan("pinchers", "snappers").unwrap();
+    beach.bury_crab(2);
+    let drained = beach.drain_events();
+    drop(beach);
+
+    let received = logger.join().unwrap();
+    assert_eq!(
+        received,
+        vec![
+            OceanEvent::CrabBorn { name: "Junior".to_string(), parents: ("Ferris".to_string(), "Corro".to_string()) },
+            OceanEvent::ClanJoined { clan: "snappers".to_string(), crab: "Corro".to_string() },
+            OceanEvent::ClanWon { winner: "pinchers".to_string(), loser: "snappers".to_string() },
+            OceanEvent::CrabDied { name: "Junior".to_string() },
+        ]
+    );
+    // The events queued before subscribing are still drained, but were never sent.
+    assert_eq!(drained[0], OceanEvent::ClanJoined { clan: "pinchers".to_string(), crab: "Ferris".to_string() });
+    assert_eq!(drained[1..], received[..]);
+}
+
+#[test]
+fn student_beach_can_forbid_breeding_between_relatives() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 2));
+    beach.add_crab(new_crab("Pinchy", 4));
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    beach.breed_crabs(0, 1, String::from("Nipper"));
+    beach.breed_crabs(0, 2, String::from("Half"));
+    // Related crabs can breed until the beach sets a limit.
+    assert_eq!(beach.max_breeding_relatedness(), None);
+    beach.breed_crabs(3, 4, String::from("Inbred"));
+
+    beach.set_max_breeding_relatedness(Some(0.25));
+    let related = |beach: &mut Beach, i: usize, j: usize| match beach.try_breed_crabs(i, j, String::from("Child")) {
+        Err(OceanError::TooCloselyRelated { relation, .. }) => Some(relation),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(()) => None,
+    };
+    assert_eq!(related(&mut beach, 0, 0).as_deref(), Some("the same crab"));
+    assert_eq!(related(&mut beach, 0, 3).as_deref(), Some("parent and child"));
+    assert_eq!(related(&mut beach, 3, 4).as_deref(), Some("full siblings"));
+    assert_eq!(related(&mut beach, 1, 2), None);
+    assert_eq!(related(&mut beach, 3, 5), None);
+    let err = beach.try_breed_crabs(4, 3, String::from("Child")).unwrap_err();
+    assert_eq!(err.to_string(), "Nipper and Junior are too closely related to breed (full siblings)");
+}
+
+#[test]
+fn student_breeding_cooldown_rests_parents() {
+    let mut ocean = Ocean::new();
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 2));
+    beach.add_crab(new_crab("Pinchy", 4));
+    assert_eq!(beach.breeding_cooldown(), 0);
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    assert!(beach.get_crab(0).can_breed());
+
+    beach.set_breeding_cooldown(2);
+    beach.breed_crabs(0, 1, String::from("Nipper"));
+    assert_eq!(beach.get_crab(0).breeding_cooldown(), 2);
+    assert!(!beach.get_crab(1).can_breed());
+    assert_eq!(
+        beach.try_breed_crabs(2, 1, String::from("Child")),
+        Err(OceanError::BreedingCooldown { crab: "Corro".to_string(), steps: 2 })
+    );
+    assert!(beach.undo());
+    assert!(beach.get_crab(0).can_breed());
+    assert!(beach.redo());
+    let saved = Crab::from_fields(&beach.get_crab(0).to_fields(), 1).unwrap();
+    assert_eq!(saved.breeding_cooldown(), 2);
+
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 3);
+    simulation.run(1);
+    assert_eq!(simulation.ocean().beaches().next().unwrap().get_crab(0).breeding_cooldown(), 1);
+    simulation.run(1);
+    simulation.breed_crabs(0, 0, 1, String::from("Later")).unwrap();
+}
+
+#[test]
+fn student_beach_tracks_genealogy_through_breeding() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("ann", 3));
+    beach.add_crab(new_crab("bob", 4));
+    beach.add_crab(new_crab("cat", 5));
+    beach.breed_crabs(0, 1, "dee".to_string());
+    beach.breed_crabs(0, 1, "eve".to_string());
+    beach.breed_crabs(3, 2, "fay".to_string());
+    beach.breed_crabs(3, 4, "gus".to_string());
+    let id = |beach: &Beach, name: &str| beach.find_crabs_by_name(name)[0].id();
+    let [ann, bob, cat, dee, eve, fay, gus] = ["ann", "bob", "cat", "dee", "eve", "fay", "gus"].map(|name| id(&beach, name));
+
+    assert_eq!(beach.get_crab(0).parents(), None);
+    assert_eq!(beach.get_crab(3).parents(), Some((ann, bob)));
+    assert_eq!(beach.ancestors(fay), vec![dee, cat, ann, bob]);
+    assert_eq!(beach.ancestors(gus), vec![dee, eve, ann, bob]);
+    assert_eq!(beach.descendants(ann), vec![dee, eve, fay, gus]);
+    assert_eq!(beach.descendants(cat), vec![fay]);
+    assert!(beach.descendants(gus).is_empty());
+
+    assert_eq!(beach.relatedness(ann, ann), 1.0);
+    assert_eq!(beach.relatedness(ann, bob), 0.0);
+    assert_eq!(beach.relatedness(ann, dee), 0.5);
+    assert_eq!(beach.relatedness(dee, eve), 0.5);
+    assert_eq!(beach.relatedness(ann, fay), 0.25);
+    assert_eq!(beach.relatedness(fay, gus), beach.relatedness(gus, fay));
+    // Gus's parents are siblings, so he shares more with each of them than an outbred child would.
+    assert!(beach.relatedness(dee, gus) > 0.5);
+
+    // Buried crabs still count, and genealogy survives a save.
+    beach.bury_crab(3);
+    let loaded = Beach::load(&beach.save()).unwrap();
+    for beach in [&beach, &loaded] {
+        assert_eq!(beach.ancestors(gus), vec![dee, eve, ann, bob]);
+        assert_eq!(beach.descendants(bob), vec![dee, eve, fay, gus]);
+        assert_eq!(beach.relatedness(ann, fay), 0.25);
+    }
+    assert_eq!(id(&loaded, "gus"), gus);
+    let mut loaded = loaded;
+    loaded.add_crab(new_crab("hal", 1));
+    assert!(id(&loaded, "hal") > gus);
+}
+
+#[test]
+fn student_crab_traits_come_from_their_genome() {
+    let fast = Crab::new(String::from("Fast"), 9, Color::new(0, 0, 0), Diet::Fish)
+        .with_genome(Genome::new(9, Genotype::new(Color::new(0, 0, 0), Color::new(255, 255, 255)), 0.8));
+    let slow = new_crab("Slow", 2);
+    assert_eq!((fast.speed(), fast.escape_resistance()), (9, 0.8));
+    assert_eq!(fast.genotype().recessive(), &Color::new(255, 255, 255));
+    assert_eq!(slow.escape_resistance(), 0.0);
+    assert_eq!(Genome::new(1, Genotype::homozygous(Color::new_red()), 3.0).escape_resistance(), 1.0);
+
+    // Without mutation, every gene comes from one parent or the other.
+    let mut rng = seeded(8);
+    for _ in 0..50 {
+        let child = Genome::cross(fast.genome(), slow.genome(), 0.0, &mut rng);
+        assert!([9, 2].contains(&child.speed()));
+        assert!([0.8, 0.0].contains(&child.escape_resistance()));
+        let alleles = [fast.color(), fast.genotype().recessive(), slow.color()];
+        assert!(alleles.contains(&child.color().expressed()) && alleles.contains(&child.color().recessive()));
+    }
+    // With it, genes drift away from both parents, but speed never drops below 1.
+    let mut genome = Genome::new(1, Genotype::homozygous(Color::new_red()), 0.5);
+    let mut speeds = Vec::new();
+    for _ in 0..100 {
+        genome.mutate(1.0, &mut rng);
+        speeds.push(genome.speed());
+    }
+    assert!(speeds.iter().all(|&speed| speed >= 1) && speeds.iter().any(|&speed| speed > 2));
+
+    let mut beach = Beach::new();
+    beach.add_crab(fast.clone());
+    beach.add_crab(slow);
+    for i in 0..20 {
+        beach.breed_crabs_genetic(0, 1, format!("Kid {}", i), &mut rng).unwrap();
+    }
+    let kids: Vec<&Crab> = beach.crabs().skip(2).collect();
+    assert!(kids.iter().any(|kid| kid.speed() >= 8) && kids.iter().any(|kid| kid.speed() <= 3));
+    assert!(kids.iter().all(|kid| kid.parents().is_some()));
+    // The original rules still apply to `breed`.
+    assert_eq!(Crab::breed(String::from("Junior"), &fast, &fast).speed(), 1);
+
+    // Resistant crabs give prey less chance to get away, and keep their genes when saved.
+    let model = ChanceEscape::new(0.5, 0.0, 0.0, 0.0);
+    let traits = PreyTraits { speed: 1, camouflage: 0.0, size: Size::Small };
+    assert_eq!(model.probability(&traits, &new_crab("Ferris", 1)), 0.5);
+    assert!((model.probability(&traits, &fast) - 0.1).abs() < 1e-9);
+    assert_eq!(Crab::from_fields(&fast.to_fields(), 1).unwrap().genome(), fast.genome());
+}
+
+#[test]
+fn student_weather_changes_hunting_regrowth_and_breeding() {
+    let seasons = Seasons::new().then(Weather::Sunny, 2).then(Weather::Storm, 1).then(Weather::Winter, 0).then(Weather::Winter, 2);
+    assert_eq!(seasons.year(), 5);
+    let weathers: Vec<Weather> = (0..6).map(|tick| seasons.weather_at(tick)).collect();
+    use Weather::*;
+    assert_eq!(weathers, vec![Sunny, Sunny, Storm, Winter, Winter, Sunny]);
+    assert_eq!(Seasons::new().weather_at(7), Sunny);
+
+    let build = |seasons: Seasons| {
+        let mut ocean = Ocean::new();
+        let mut reef = Reef::new();
+        reef.set_regen_policy(RegenPolicy::new().with_rate(4.0, || Box::new(Algae::new())));
+        let reef = ocean.add_reef(reef).unwrap();
+        let mut beach = Beach::new();
+        for i in 0..20 {
+            let mut crab = new_crab(&format!("crab{}", i), 1);
+            crab.discover_reef(reef.clone());
+            beach.add_crab(crab);
+        }
+        ocean.add_beach(beach);
+        let mut simulation = Simulation::new(ocean, 3);
+        simulation.set_seasons(seasons);
+        simulation
+    };
+
+    let mut simulation = build(Seasons::new().then(Weather::Winter, 1).then(Weather::Storm, 1));
+    assert_eq!(simulation.weather(), Winter);
+    let bred = Rc::new(RefCell::new(Vec::new()));
+    for tick in 0..2 {
+        let bred = bred.clone();
+        simulation.schedule(tick, move |ocean: &mut Ocean, rng: &mut dyn OceanRng| {
+            let beach = ocean.beaches_mut().next().unwrap();
+            bred.borrow_mut().push(beach.breed_crabs_with_rng(0, 1, format!("Kid{}", tick), rng));
+        });
+    }
+    let reports = simulation.run(2);
+    assert_eq!(*bred.borrow(), vec![Err(OceanError::BreedingUnavailable), Ok(())]);
+    assert_eq!((reports[0].weather, reports[0].prey_spawned), (Winter, 1));
+    assert_eq!((reports[1].weather, reports[1].prey_spawned), (Storm, 2));
+    assert!(reports[0].hunts > 10 && reports[0].hunts < 20);
+    assert!(reports[1].hunts > 3 && reports[1].hunts < 17);
+
+    // Sunny steps leave the run exactly as it was without seasons.
+    let sunny = build(Seasons::new().then(Weather::Sunny, 3)).run(3);
+    assert_eq!(sunny, build(Seasons::new()).run(3));
+    assert!(sunny.iter().all(|report| report.hunts == 20 && report.prey_spawned == 4));
+
+    // Custom effects can change the conditions and the ocean.
+    let mut simulation = build(Seasons::new().then(Weather::Storm, 1));
+    simulation.add_seasonal_effect(|weather, conditions, ocean| {
+        if weather == Weather::Storm {
+            conditions.hunt_chance = 0.0;
+            conditions.regen_rate = 2.0;
+            ocean.beaches_mut().next().unwrap().bury_crab(0);
+        }
+    });
+    let report = simulation.step();
+    assert_eq!((report.hunts, report.prey_spawned), (0, 8));
+    assert_eq!(simulation.ocean().beaches().next().unwrap().size(), 19);
+}
+
+#[test]
+fn student_predators_hunt_crabs() {
+    let mut rng = seeded(2);
+    let fast = new_crab("Fast", 9);
+    let slow = new_crab("Slow", 2);
+    let striped = new_crab("Striped", 2).with_pattern(Pattern::Striped);
+    let mut gull = Seagull::new(5, 1.0);
+    assert!(!gull.try_catch(&fast, &mut rng));
+    assert!(gull.try_catch(&slow, &mut rng));
+    assert!(!Octopus::new(0.0).try_catch(&slow, &mut rng));
+    assert!(Octopus::new(1.0).try_catch(&fast, &mut rng));
+    let caught = (0..400).filter(|_| gull.try_catch(&striped, &mut rng)).count();
+    assert!(caught > 150 && caught < 250);
+
+    let mut beach = Beach::new();
+    beach.add_crab(fast);
+    beach.add_crab(slow);
+    beach.add_member_to_clan("pinchers", "Slow");
+    assert_eq!(beach.face_predators(&mut rng), 0);
+    beach.add_predator(Box::new(Seagull::new(5, 1.0)));
+    assert_eq!(beach.predators().map(|predator| predator.kind()).collect::<Vec<_>>(), vec!["Seagull"]);
+    let mut eaten = 0;
+    while beach.size() == 2 {
+        eaten += beach.face_predators(&mut rng);
+    }
+    assert_eq!(eaten, 1);
+    assert_eq!(beach.get_crab(0).name(), "Fast");
+    assert_eq!(beach.hall_of_fame().next().unwrap().clan(), Some("pinchers"));
+    assert!(beach.get_clan_system().get_clan_member_names("pinchers").is_empty());
+    assert!(beach
+        .drain_events()
+        .contains(&OceanEvent::CrabEaten { crab: "Slow".to_string(), predator: "Seagull".to_string() }));
+
+    // A simulation lets predators hunt once a step, after the crabs have hunted.
+    let mut ocean = Ocean::new();
+    let mut beach = Beach::new();
+    for i in 0..10 {
+        beach.add_crab(new_crab(&format!("crab{}", i), 1));
+    }
+    beach.add_predator(Box::new(Octopus::new(1.0)));
+    beach.add_predator(Box::new(Octopus::new(1.0)));
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 4);
+    let reports = simulation.run(3);
+    assert!(reports.iter().all(|report| report.crabs_eaten == 2));
+    assert_eq!(simulation.ocean().beaches().next().unwrap().size(), 4);
+}
+
+#[test]
+fn student_crabs_migrate_between_beaches() {
+    let mut ocean = Ocean::new();
+    let mut north = Beach::new();
+    north.add_crab(new_crab("Ferris", 3));
+    north.add_crab(new_crab("Corro", 4));
+    north.add_member_to_clan("pinchers", "Ferris");
+    north.add_member_to_clan("snappers", "Corro");
+    let mut south = Beach::new();
+    south.add_crab(new_crab("Pinchy", 5));
+    south.get_clan_system_mut().create_clan("pinchers".to_string());
+    ocean.add_beach(north);
+    ocean.add_beach(south);
+
+    let ferris = ocean.beaches().next().unwrap().get_crab(0).id();
+    let corro = ocean.beaches().next().unwrap().get_crab(1).id();
+    assert_eq!(ocean.migrate(ferris, 0, 2), Err(OceanError::BeachNotFound("at index 2".to_string())));
+    assert!(matches!(ocean.migrate(ferris, 0, 0), Err(OceanError::InvalidArgument(_))));
+    assert_eq!(ocean.migrate(99, 0, 1), Err(OceanError::CrabIdNotFound(99)));
+
+    // Ferris's clan exists on the south beach too, so Ferris stays a member; Corro's does not.
+    let new_id = ocean.migrate(ferris, 0, 1).unwrap();
+    ocean.migrate(corro, 0, 1).unwrap();
+    let beaches: Vec<&Beach> = ocean.beaches().collect();
+    assert_eq!(beaches[0].size(), 0);
+    assert!(beaches[0].get_clan_system().get_clan_member_names("pinchers").is_empty());
+    assert_eq!(beaches[1].get_crab(1).name(), "Ferris");
+    assert_eq!(beaches[1].get_crab(1).id(), new_id);
+    assert_eq!(beaches[1].get_clan_of_crab("Ferris"), Some("pinchers"));
+    assert_eq!(beaches[1].get_clan_of_crab("Corro"), None);
+    let events = ocean.beaches_mut().nth(1).unwrap().drain_events();
+    assert!(events.contains(&OceanEvent::CrabMigrated { name: "Ferris".to_string(), from: 0, to: 1 }));
+
+    // Overcrowded beaches send their newest crabs to the emptiest beach with room.
+    let policy = MigrationPolicy::new().with_max_population(2);
+    assert_eq!(ocean.apply_migration_policy(&policy), 1);
+    let names = |ocean: &Ocean, beach: usize| {
+        ocean.beaches().nth(beach).unwrap().crabs().map(|crab| crab.name().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(names(&ocean, 0), vec!["Corro"]);
+    assert_eq!(ocean.apply_migration_policy(&policy), 0);
+
+    // Crabs whose reefs are empty move to where the prey are, and hunt there.
+    let empty = ocean.add_reef(Reef::new()).unwrap();
+    let full = ocean.generate_reef(0, 0, 0, 3);
+    let mut beaches = ocean.beaches_mut();
+    beaches.next().unwrap().get_crab_mut(0).discover_reef(empty.clone());
+    beaches.next().unwrap().get_crab_mut(0).discover_reef(full.clone());
+    let policy = MigrationPolicy::new().with_leave_depleted(true);
+    assert_eq!(ocean.apply_migration_policy(&policy), 1);
+    assert_eq!(names(&ocean, 1), vec!["Pinchy", "Ferris", "Corro"]);
+    let mut corro = ocean.beaches_mut().nth(1).unwrap().get_crab_mut(2);
+    assert_eq!(corro.reefs().count(), 2);
+    assert!(corro.hunt());
+    drop(corro);
+
+    let mut simulation = Simulation::new(ocean, 1);
+    simulation.set_crab_migration_policy(MigrationPolicy::new().with_max_population(1));
+    assert_eq!(simulation.step().crabs_migrated, 1);
+}
+
+#[test]
+fn student_ocean_holds_named_beaches() {
+    let mut ocean = Ocean::new();
+    assert_eq!(ocean.total_population(), 0);
+    assert!(ocean.fastest_crab_overall().is_none());
+
+    let mut north = Beach::with_name("north".to_string());
+    north.add_crab(new_crab("Ferris", 3));
+    north.add_crab(new_crab("Corro", 7));
+    let mut south = Beach::with_name("south".to_string());
+    south.add_crab(new_crab("Pinchy", 7));
+    assert_eq!(ocean.try_add_beach(north), Ok(0));
+    ocean.add_beach(Beach::new());
+    assert_eq!(ocean.try_add_beach(south), Ok(2));
+    assert_eq!(
+        ocean.try_add_beach(Beach::with_name("north".to_string())).unwrap_err(),
+        OceanError::DuplicateName { kind: "beach", name: "north".to_string() }
+    );
+
+    assert_eq!(ocean.total_population(), 3);
+    assert_eq!(ocean.fastest_crab_overall().unwrap().name(), "Pinchy");
+    assert_eq!(ocean.beach_index("south"), Some(2));
+    assert_eq!(ocean.beach_by_name("north").unwrap().size(), 2);
+    assert!(ocean.beach_by_name("east").is_none());
+    ocean.beach_by_name_mut("south").unwrap().add_crab(new_crab("Speedy", 9));
+    assert_eq!(ocean.fastest_crab_overall().unwrap().name(), "Speedy");
+
+    let loaded = Beach::load(&ocean.beach_by_name("south").unwrap().save()).unwrap();
+    assert_eq!(loaded.name(), Some("south"));
+    assert_eq!(Beach::load(&Beach::new().save()).unwrap().name(), None);
+}
+
+#[test]
+fn student_ocean_exports_relationships_as_dot() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name("kelp".to_string())).unwrap();
+    ocean.generate_reef(0, 0, 0, 1);
+    let mut beach = Beach::with_name("north \"shore\"".to_string());
+    let mut ferris = new_crab("Ferris", 3);
+    ferris.discover_reef(kelp);
+    beach.add_crab(ferris);
+    beach.add_crab(Crab::new(String::from("Corro"), 4, Color::new_red(), Diet::Fish));
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    beach.add_member_to_clan("pinchers", "Junior");
+    ocean.add_beach(beach);
+    ocean.add_beach(Beach::new());
+
+    let dot = ocean.to_dot();
+    let expected = [
+        "digraph ocean {",
+        "    reef_0 [label=\"kelp\", shape=box];",
+        "    reef_1 [label=\"reef 1\", shape=box];",
+        "    subgraph cluster_beach_0 {",
+        "        label=\"north \\\"shore\\\"\";",
+        "        crab_0_1 [label=\"Ferris\", style=filled, fillcolor=\"#0000ff\"];",
+        "        crab_0_2 [label=\"Corro\", style=filled, fillcolor=\"#ff0000\"];",
+        "        clan_0_0 [label=\"pinchers\", shape=diamond];",
+        "    subgraph cluster_beach_1 {",
+        "        label=\"beach 1\";",
+        "    crab_0_1 -> reef_0;",
+        "    crab_0_1 -> crab_0_3 [style=bold];",
+        "    crab_0_2 -> crab_0_3 [style=bold];",
+        "    crab_0_3 -> clan_0_0 [style=dashed];",
+    ];
+    for line in expected {
+        assert!(dot.lines().any(|l| l == line), "missing {:?} in\n{}", line, dot);
+    }
+    assert!(dot.ends_with("}\n"));
+    assert_eq!(dot.matches("crab_0_3 [label=\"Junior\"").count(), 1);
+}
+
+#[test]
+fn student_beach_renders_as_ascii() {
+    let kelp = Rc::new(RefCell::new(Reef::new()));
+    kelp.borrow_mut().set_position(Position::new(1.5, 3.0));
+    let mut beach = Beach::new();
+    beach.set_tide_line(3.0);
+    let mut ferris = new_crab("Ferris", 3);
+    ferris.set_position(Position::new(0.0, 0.0));
+    ferris.discover_reef(kelp.clone());
+    beach.add_crab(ferris);
+    let mut corro = Crab::new(String::from("Corro"), 4, Color::new_red(), Diet::Fish);
+    corro.set_position(Position::new(4.9, 1.2));
+    beach.add_crab(corro);
+    let mut lost = new_crab("Lost", 1);
+    lost.set_position(Position::new(9.0, 0.0));
+    beach.add_crab(lost);
+    beach.add_crab(new_crab("Nowhere", 1));
+
+    assert_eq!(beach.render_ascii(5, 4), "B....\n....R\n.....\n~#~~~\n");
+    assert_eq!(Color::cross(&Color::new_blue(), &Color::new_green()).nearest_name(), "teal");
+
+    let loaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(loaded.tide_line(), Some(3.0));
+    assert_eq!(loaded.get_crab(1).position(), Some(Position::new(4.9, 1.2)));
+    assert_eq!(loaded.get_crab(3).position(), None);
+    assert_eq!(Position::new(0.0, 0.0).distance(Position::new(3.0, 4.0)), 5.0);
+}
+
+#[test]
+fn student_simulation_records_metrics() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name("kelp".to_string())).unwrap();
+    let mut beach = Beach::new();
+    let mut ferris = new_crab("Ferris", 3);
+    ferris.discover_reef(kelp);
+    beach.add_crab(ferris);
+    beach.add_crab(new_crab("Corro", 1));
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 7);
+    sim.run(1);
+    assert!(sim.metrics().is_none());
+    sim.enable_metrics();
+    sim.schedule(2, |ocean: &mut Ocean, _rng: &mut dyn OceanRng| {
+        let beach = ocean.beaches_mut().next().unwrap();
+        beach.breed_crabs(0, 1, String::from("Junior"));
+        beach.add_member_to_clan("pinchers", "Junior");
+    });
+    sim.run(3);
+
+    let metrics = sim.metrics().unwrap();
+    assert_eq!(metrics.ticks(), &[1, 2, 3]);
+    assert_eq!(metrics.series("population"), Some(&[(1, 2.0), (2, 3.0), (3, 3.0)][..]));
+    assert_eq!(metrics.series("average_speed").unwrap()[0], (1, 2.0));
+    assert_eq!(metrics.series("prey/kelp"), Some(&[(1, 0.0), (2, 0.0), (3, 0.0)][..]));
+    assert_eq!(metrics.series("clan/beach 0/pinchers"), Some(&[(2, 1.0), (3, 1.0)][..]));
+    assert_eq!(metrics.series("nothing"), None);
+    assert_eq!(
+        metrics.names().collect::<Vec<_>>(),
+        ["average_speed", "clan/beach 0/pinchers", "population", "prey/kelp"]
+    );
+    assert_eq!(
+        metrics.to_csv(),
+        "tick,average_speed,clan/beach 0/pinchers,population,prey/kelp\n\
+         1,2,,2,0\n\
+         2,1.6666666666666667,1,3,0\n\
+         3,1.6666666666666667,1,3,0\n"
+    );
+
+    let mut manual = Metrics::new();
+    manual.record(9, sim.ocean());
+    assert_eq!(manual.series("population"), Some(&[(9, 3.0)][..]));
+}
+
+#[test]
+fn student_recorded_runs_replay_exactly() {
+    let mut ocean = Ocean::new();
+    let mut kelp = Reef::with_name(String::from("kelp"));
+    kelp.set_take_policy(TakePolicy::Random);
+    let kelp = ocean.add_reef(kelp).unwrap();
+    for _ in 0..8 {
+        kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    }
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 1));
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 11);
+    sim.run(1);
+    let path = std::env::temp_dir().join(format!("ocean-replay-{}.txt", std::process::id()));
+    assert!(matches!(sim.save_recording(&path), Err(OceanError::InvalidArgument(_))));
+    sim.start_recording().unwrap();
+    let mut pinchy = new_crab("Pinchy", 4);
+    pinchy.discover_reef(kelp.clone());
+    sim.add_crab(0, pinchy).unwrap();
+    assert_eq!(sim.add_crab(3, new_crab("Lost", 1)), Err(OceanError::BeachNotFound("3".to_string())));
+    sim.breed_crabs(0, 0, 2, String::from("Junior")).unwrap();
+    sim.rng_mut().next_u32();
+    sim.step();
+    sim.breed_crabs(0, 1, 3, String::from("Nipper")).unwrap();
+    sim.run(3);
+    sim.save_recording(&path).unwrap();
+
+    let replayed = Simulation::replay(&path).unwrap();
+    assert_eq!(replayed.clock(), sim.clock());
+    assert_eq!(replayed.snapshot(), sim.snapshot());
+
+    // Dropping one of the first step's draws makes the replay run out of numbers there.
+    let text = std::fs::read_to_string(&path).unwrap();
+    let line = text.lines().position(|line| line.starts_with("step\t")).unwrap();
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let cut = lines[line].rfind('\t').unwrap();
+    lines[line].truncate(cut);
+    std::fs::write(&path, lines.join("\n")).unwrap();
+    let diverged = Simulation::replay(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(diverged.unwrap_err(), OceanError::ReplayDiverged { line: line + 1 });
+}
+
+#[test]
+fn student_state_at_rebuilds_earlier_ticks() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::new()).unwrap();
+    for _ in 0..6 {
+        kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    }
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.get_crab_mut(0).discover_reef(kelp.clone());
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 5);
+    assert!(matches!(sim.state_at(0), Err(OceanError::InvalidArgument(_))));
+    sim.run(1);
+    sim.start_recording().unwrap();
+    sim.run(2);
+    let mut pinchy = new_crab("Pinchy", 4);
+    pinchy.discover_reef(kelp.clone());
+    sim.add_crab(0, pinchy).unwrap();
+    sim.run(2);
+
+    let past = sim.state_at(3).unwrap();
+    assert_eq!(past.clock(), 3);
+    let names: Vec<&str> = past.ocean().beaches().next().unwrap().crabs().map(|crab| crab.name()).collect();
+    assert_eq!(names, vec!["Ferris", "Pinchy"]);
+    assert_eq!(sim.state_at(1).unwrap().ocean().beaches().next().unwrap().crabs().count(), 1);
+    assert_eq!(sim.state_at(5).unwrap().snapshot(), sim.snapshot());
+    assert!(matches!(sim.state_at(0), Err(OceanError::InvalidArgument(_))));
+    assert!(matches!(sim.state_at(6), Err(OceanError::InvalidArgument(_))));
+}
+
+#[test]
+fn student_beach_changes_can_be_undone_and_redone() {
+    let mut beach = Beach::new();
+    assert!(!beach.undo());
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 5));
+    beach.add_member_to_clan("pinchers", "Corro");
+    let before = beach.save();
+
+    beach.breed_crabs(0, 1, String::from("Junior"));
+    beach.add_member_to_clan("snappers", "Corro");
+    beach.bury_crab(0);
+    assert_eq!(beach.size(), 2);
+    assert_eq!(beach.hall_of_fame().count(), 1);
+    let after = beach.save();
+
+    assert!(beach.undo());
+    assert_eq!(beach.get_crab(0).name(), "Ferris");
+    assert_eq!(beach.hall_of_fame().count(), 0);
+    assert!(beach.undo());
+    assert_eq!(beach.get_clan_of_crab("Corro"), Some("pinchers"));
+    assert_eq!(beach.get_clan_system().get_clan_ids(), ["pinchers"]);
+    assert!(beach.undo());
+    assert_eq!(beach.get_crab(0).offspring(), 0);
+    assert_eq!(beach.save(), before);
+
+    assert!(beach.redo());
+    assert!(beach.redo());
+    assert!(beach.redo());
+    assert!(!beach.redo());
+    assert_eq!(beach.save(), after);
+    assert_eq!(beach.get_crab(1).parents(), Some((1, 2)));
+
+    // A new change replaces whatever could have been redone.
+    assert!(beach.undo());
+    beach.add_crab(new_crab("Pinchy", 1));
+    assert!(!beach.redo());
+    assert_eq!(beach.get_crab(3).id(), 4);
+
+    beach.clear_history();
+    assert!(!beach.undo());
+    assert_eq!(beach.size(), 4);
+}
+
+#[test]
+fn student_crab_queries_compose_filters() {
+    let mut beach = Beach::new();
+    beach.add_crab(Crab::new(String::from("Ferris"), 6, Color::new_blue(), Diet::Shellfish));
+    beach.add_crab(Crab::new(String::from("Corro"), 4, Color::new_blue(), Diet::Shellfish));
+    beach.add_crab(Crab::new(String::from("Pinchy"), 9, Color::new_red(), Diet::Shellfish));
+    beach.add_crab(Crab::new(String::from("Snappy"), 7, Color::new_blue(), Diet::Plants));
+    beach.add_crab(Crab::new(String::from("Nipper"), 5, Color::new_blue(), Diet::Shellfish));
+    beach.add_member_to_clan("reds", "Ferris");
+    beach.add_member_to_clan("reds", "Pinchy");
+    beach.add_member_to_clan("reds", "Snappy");
+
+    let names = |crabs: Vec<&Crab>| crabs.iter().map(|crab| crab.name().to_string()).collect::<Vec<_>>();
+    let query = beach.query().diet(Diet::Shellfish).min_speed(5).color(Color::new_blue()).in_clan("reds");
+    assert_eq!(names(query.collect()), ["Ferris"]);
+    assert_eq!(names(beach.query().min_speed(5).without_clan().collect()), ["Nipper"]);
+    assert_eq!(beach.query().max_speed(6).indices(), [0, 1, 4]);
+    assert_eq!(beach.query().diet(Diet::Shellfish).count(), 4);
+    assert_eq!(beach.query().matching(|crab| crab.speed() % 2 == 1).first().unwrap().name(), "Pinchy");
+    assert!(beach.query().name("Corro").in_clan("reds").first().is_none());
+    assert_eq!(beach.query().count(), 5);
+}
+
+#[test]
+fn student_scenarios_build_populated_simulations() {
+    let scenario = ScenarioBuilder::new(42)
+        .with_reef(ReefSpec::new("kelp").with_algae(20).with_shrimp(5))
+        .with_reef(ReefSpec::new("sand").with_clams(3).with_capacity(10))
+        .with_beach(
+            BeachSpec::new("north")
+                .with_crabs(
+                    CrabSpec::new(12)
+                        .with_speed(3, 8)
+                        .with_colors(vec![(Color::new_red(), 1), (Color::new_blue(), 3)])
+                        .with_diets(vec![(Diet::Plants, 1)])
+                        .with_reefs(&["kelp"]),
+                )
+                .with_crabs(CrabSpec::new(2).with_name_prefix("Scout").with_speed(9, 9))
+                .with_clan("reds", 4)
+                .with_clan("blues", 3),
+        )
+        .with_beach(BeachSpec::new("south"));
+    let sim = scenario.build().unwrap();
+
+    let ocean = sim.ocean();
+    assert_eq!(ocean.reef_by_name("kelp").unwrap().borrow().population(), 25);
+    assert_eq!(ocean.reef_by_name("sand").unwrap().borrow().capacity(), Some(10));
+    let north = ocean.beach_by_name("north").unwrap();
+    assert_eq!(north.size(), 14);
+    assert_eq!(ocean.beach_by_name("south").unwrap().size(), 0);
+    assert_eq!(north.get_crab(0).name(), "Crab1");
+    assert_eq!(north.get_crab(13).name(), "Scout14");
+    assert_eq!(north.get_crab(13).speed(), 9);
+    assert!(north.query().max_speed(8).collect().iter().all(|crab| crab.speed() >= 3 && crab.diet() == Diet::Plants));
+    let of_color = |color: Color| north.query().max_speed(8).color(color).count();
+    assert_eq!(of_color(Color::new_blue()) + of_color(Color::new_red()), 12);
+    assert!(north.get_crab(0).reefs().next().is_some());
+    assert_eq!(north.get_clan_system().get_clan_member_count("reds"), 4);
+    assert_eq!(north.get_clan_system().get_clan_member_count("blues"), 3);
+    assert_eq!(north.query().without_clan().count(), 7);
+
+    // The same scenario always builds the same world.
+    assert_eq!(scenario.build().unwrap().snapshot(), sim.snapshot());
+
+    let missing = ScenarioBuilder::new(1).with_beach(BeachSpec::new("b").with_crabs(CrabSpec::new(1).with_reefs(&["nowhere"])));
+    assert_eq!(missing.build().unwrap_err(), OceanError::ReefNotFound("nowhere".to_string()));
+    let crowded = ScenarioBuilder::new(1).with_beach(BeachSpec::new("b").with_crabs(CrabSpec::new(2)).with_clan("c", 3));
+    assert!(matches!(crowded.build(), Err(OceanError::InvalidArgument(_))));
+    let weightless = ScenarioBuilder::new(1).with_beach(BeachSpec::new("b").with_crabs(CrabSpec::new(2).with_diets(vec![])));
+    assert!(matches!(weightless.build(), Err(OceanError::InvalidArgument(_))));
+}
+
+#[test]
+fn student_simulations_load_config_files() {
+    let text = r#"
+        # A quick experiment
+        [simulation]
+        seed = 7
+        tick_length = 2
+        migration_rate = 0.25
+
+        [reefs]
+        capacity = 100
+        escape_chance = 0.5
+
+        [reefs.kelp]
+        capacity = 40
+
+        [reefs.kelp.regen]
+        Algae = 2
+
+        [breeding]
+        max_population = 2
+    "#;
+    let config = SimConfig::parse(text).unwrap();
+    assert_eq!(config.seed, 7);
+    assert_eq!(config.tick_length, 2);
+    assert_eq!(config.reefs["kelp"].regen, vec![("Algae".to_string(), 2.0)]);
+    assert!(config.breeding);
+
+    let mut ocean = Ocean::new();
+    ocean.add_reef(Reef::with_name("kelp".to_string())).unwrap();
+    ocean.add_reef(Reef::new()).unwrap();
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("A", 1));
+    beach.add_crab(new_crab("B", 2));
+    ocean.add_beach(beach);
+    let mut sim = Simulation::with_config(ocean, &config).unwrap();
+    assert_eq!(sim.tick_length(), 2);
+    let kelp = sim.ocean().reef_by_name("kelp").unwrap();
+    assert_eq!(kelp.borrow().capacity(), Some(40));
+    assert_eq!(kelp.borrow().escape_chance(), Some(0.5));
+    assert_eq!(sim.ocean().reefs().nth(1).unwrap().borrow().capacity(), Some(100));
+
+    // Two ticks a step at two algae a tick.
+    assert_eq!(sim.step().prey_spawned, 4);
+    assert_eq!(sim.breed_crabs(0, 0, 1, "C".to_string()), Err(OceanError::BreedingUnavailable));
+
+    let unknown = SimConfig::parse("[simulation]\nseeds = 1").unwrap_err();
+    assert_eq!(unknown.line(), 2);
+    assert!(SimConfig::parse("[reefs.kelp.regen]\nKraken = 1").is_err());
+    assert!(SimConfig::parse("[simulation]\ntick_length = 0").is_err());
+    let elsewhere = SimConfig::parse("[reefs.atlantis]\ncapacity = 1").unwrap();
+    assert_eq!(Simulation::with_config(Ocean::new(), &elsewhere).unwrap_err(), OceanError::ReefNotFound("atlantis".to_string()));
+}
+
+#[test]
+fn student_crab_handles_survive_removals() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("A", 1));
+    beach.add_crab(new_crab("B", 2));
+    beach.add_crab(new_crab("C", 3));
+    let b = beach.crab_handle(1).unwrap();
+    let c = beach.crab_handle(2).unwrap();
+    assert_eq!(beach.crab_handle(3), None);
+
+    beach.bury_crab(0);
+    assert_eq!(beach.get_crab_by_handle(c).unwrap().name(), "C");
+    assert_eq!(beach.find_crab_by_handle(c), Some(1));
+    assert!(beach.get_crab_by_handle_mut(b).is_some());
+
+    // A stale handle stays stale, even once its slot holds another crab.
+    beach.bury_crab(1);
+    beach.add_crab(new_crab("D", 4));
+    assert!(beach.get_crab_by_handle(c).is_none());
+    assert_eq!(beach.find_crab_by_handle(c), None);
+    assert_eq!(beach.get_crab_by_handle(b).unwrap().name(), "B");
+
+    let mut arena = Arena::new();
+    let x = arena.push('x');
+    let w = arena.insert(0, 'w');
+    assert_eq!(arena.as_slice(), &['w', 'x']);
+    assert_eq!(arena.remove_handle(w), Some('w'));
+    assert_eq!(arena.remove_handle(w), None);
+    assert_eq!(arena.position(x), Some(0));
+}
+
+#[test]
+fn student_names_are_interned() {
+    let mut names = Interner::new();
+    let ferris = names.intern("Ferris");
+    assert_eq!(names.intern("Ferris"), ferris);
+    assert_ne!(names.intern("Crusty"), ferris);
+    assert_eq!(names.get("Ferris"), Some(ferris));
+    assert_eq!(names.get("Nobody"), None);
+    assert_eq!(names.resolve(ferris), "Ferris");
+    assert_eq!(names.len(), 2);
+
+    // Interning stays behind the beach's and clan system's string APIs.
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.get_clan_system_mut().create_clan("pinchers".to_string());
+    beach.add_member_to_clan("pinchers", "Ferris");
+    assert_eq!(beach.find_crabs_by_name("Ferris").len(), 2);
+    assert_eq!(beach.get_clan_of_crab("Ferris"), Some("pinchers"));
+    let clans = beach.get_clan_system();
+    assert!(clans.has_clan("pinchers"));
+    assert!(!clans.has_clan("Ferris"));
+    assert_eq!(clans.get_clan("pinchers").unwrap().keys().collect::<Vec<_>>(), vec![&"Ferris"]);
+    assert!(clans.get_clan("snappers").is_none());
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+fn student_fuzz_drivers_survive_random_input() {
+    use rand::Rng;
+
+    let mut rng = seeded(624);
+    for _ in 0..2000 {
+        let len = rng.gen_range(0..256);
+        let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+        ocean::fuzz::beach_ops(&data);
+        ocean::fuzz::clan_ops(&data);
+        ocean::fuzz::crab_ops(&data);
+        ocean::fuzz::load_text(&data);
+    }
+}
+
+#[test]
+fn student_hardened_apis_return_errors() {
+    let mut beach = Beach::new();
+    assert_eq!(beach.try_get_crab(0).unwrap_err(), OceanError::CrabIndexOutOfBounds { index: 0, len: 0 });
+    beach.add_crab(new_crab("Ferris", 3));
+    assert_eq!(beach.try_get_crab_mut(0).unwrap().name(), "Ferris");
+
+    // Empty clans used to divide by zero.
+    beach.get_clan_system_mut().create_clan("empty".to_string());
+    beach.get_clan_system_mut().create_clan("solo".to_string());
+    beach.add_member_to_clan("solo", "Ferris");
+    assert_eq!(beach.get_winner_clan("solo", "empty"), Err(OceanError::ClanEmpty("empty".to_string())));
+    assert_eq!(beach.get_winner_clan("empty", "solo"), Err(OceanError::ClanEmpty("empty".to_string())));
+    assert_eq!(beach.get_winner_clan("solo", "solo"), Ok(None));
+    let overflow = OceanError::Overflow("total speed of clan solo".to_string());
+    assert_eq!(overflow.to_string(), "The total speed of clan solo is too large to compute");
+
+    // Ingredients needed 0 times need not be in the larder.
+    let mut crab = new_crab("Crusty", 1);
+    assert_eq!(crab.cook(&Recipe::new("Nothing".to_string(), Diet::Plants).with_ingredient("Algae", 0)), Ok(()));
+}
+
+#[test]
+fn student_beach_zones_modify_hunting_and_breeding() {
+    let mut beach = Beach::new();
+    beach.add_zone(Zone::new("rocky").with_hunt_chance(0.0)).unwrap();
+    beach.add_zone(Zone::new("tidepool").with_breeding(false)).unwrap();
+    assert_eq!(
+        beach.add_zone(Zone::new("rocky")),
+        Err(OceanError::DuplicateName { kind: "zone", name: "rocky".to_string() })
+    );
+    for (name, speed) in [("Ferris", 3), ("Crusty", 5), ("Pinchy", 7)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.assign_zone(0, "rocky").unwrap();
+    beach.assign_zone(1, "rocky").unwrap();
+    beach.assign_zone(2, "tidepool").unwrap();
+    assert_eq!(beach.assign_zone(0, "sandy"), Err(OceanError::ZoneNotFound("sandy".to_string())));
+    let rocky: Vec<&str> = beach.crabs_in_zone("rocky").iter().map(|crab| crab.name()).collect();
+    assert_eq!(rocky, ["Ferris", "Crusty"]);
+
+    // Tidepool crabs cannot breed, and offspring start out in their first parent's zone.
+    assert_eq!(beach.try_breed_crabs(0, 2, "Sandy".to_string()), Err(OceanError::BreedingUnavailable));
+    beach.try_breed_crabs(0, 1, "Sandy".to_string()).unwrap();
+    assert_eq!(beach.zone_of_crab(3).map(Zone::name), Some("rocky"));
+
+    // Burying forgets the crab's zone, and undoing the burial restores it.
+    beach.bury_crab(0);
+    assert_eq!(beach.crabs_in_zone("rocky").len(), 2);
+    beach.undo();
+    assert_eq!(beach.zone_of_crab(0).map(Zone::name), Some("rocky"));
+    assert_eq!(beach.crabs_in_zone("rocky").len(), 3);
+
+    let loaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(loaded.zones().map(Zone::name).collect::<Vec<_>>(), ["rocky", "tidepool"]);
+    assert_eq!(loaded.crabs_in_zone("tidepool")[0].name(), "Pinchy");
+
+    // Only the tidepool crab is left hunting.
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut sim = Simulation::new(ocean, 1);
+    assert_eq!(sim.step().hunts, 1);
+}
+
+#[test]
+fn student_find_crabs_by_predicate() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 2), ("Crusty", 4), ("Pinchy", 6), ("Sandy", 8)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("reds", "Pinchy");
+    let unclanned_mid = |crab: &Crab| (3..=7).contains(&crab.speed()) && beach.get_clan_of_crab(crab.name()).is_none();
+    let names: Vec<&str> = beach.find_crabs_by(unclanned_mid).iter().map(|crab| crab.name()).collect();
+    assert_eq!(names, ["Crusty"]);
+    assert_eq!(beach.find_crab_indices_by(|crab| crab.speed() > 3), [1, 2, 3]);
+    assert!(beach.find_crabs_by(|_| false).is_empty());
+}
+
+#[test]
+fn student_drain_matching_moves_crabs_out() {
+    let mut south = Beach::new();
+    south.add_crab(Crab::new("Ferris".to_string(), 3, Color::new_red(), Diet::Fish));
+    south.add_crab(Crab::new("Crusty".to_string(), 5, Color::new_blue(), Diet::Fish));
+    south.add_crab(Crab::new("Pinchy".to_string(), 7, Color::new_red(), Diet::Fish));
+    south.add_member_to_clan("reds", "Pinchy");
+    south.add_member_to_clan("reds", "Crusty");
+
+    let reds = south.drain_matching(|crab| *crab.color() == Color::new_red());
+    assert_eq!(reds.iter().map(Crab::name).collect::<Vec<_>>(), ["Ferris", "Pinchy"]);
+    assert_eq!(south.size(), 1);
+    assert_eq!(south.find_crab_index("Crusty"), Some(0));
+    assert_eq!(south.get_clan_system().get_clan_member_count("reds"), 1);
+    assert_eq!(south.hall_of_fame().count(), 0);
+    assert!(!south.undo());
+
+    let mut north = Beach::new();
+    for crab in reds {
+        north.add_crab(crab);
+    }
+    assert_eq!(north.size(), 2);
+    assert!(south.drain_matching(|_| false).is_empty());
+}
+
+#[test]
+fn student_life_stages_gate_breeding_and_clans() {
+    let mut beach = Beach::with_name("north".to_string());
+    beach.add_crab(new_crab("Ferris", 8));
+    beach.add_crab(new_crab("Crusty", 4));
+    assert_eq!(beach.get_crab(0).stage(), Stage::Juvenile);
+
+    // Juveniles breed freely until the beach enforces life stages.
+    beach.breed_crabs(0, 1, "Sandy".to_string());
+    beach.set_life_stages_enforced(true);
+    let too_young = OceanError::TooYoung { crab: "Ferris".to_string(), action: "breed" };
+    assert_eq!(beach.try_breed_crabs(0, 1, "Pinchy".to_string()), Err(too_young));
+    let too_young = OceanError::TooYoung { crab: "Crusty".to_string(), action: "join a clan" };
+    assert_eq!(beach.try_add_member_to_clan("reds", "Crusty"), Err(too_young));
+
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut sim = Simulation::new(ocean, 1);
+    for _ in 0..ADULT_AGE {
+        sim.step();
+    }
+    let beach = sim.ocean_mut().beach_by_name_mut("north").unwrap();
+    assert_eq!(beach.get_crab(0).stage(), Stage::Adult);
+    beach.try_breed_crabs(0, 1, "Pinchy".to_string()).unwrap();
+    assert!(matches!(beach.try_breed_crabs(0, 3, "Krabby".to_string()), Err(OceanError::TooYoung { .. })));
+    beach.try_add_member_to_clan("reds", "Crusty").unwrap();
+    assert_eq!(beach.get_crab(0).effective_speed(), 8);
+
+    for _ in ADULT_AGE..ELDER_AGE {
+        sim.step();
+    }
+    let ferris = sim.ocean().beaches().next().unwrap().get_crab(0);
+    assert_eq!(ferris.stage(), Stage::Elder);
+    // Two molts on the way made Ferris faster, but age slows it down again.
+    assert_eq!((ferris.speed(), ferris.effective_speed()), (10, 8));
+}
+
+#[test]
+fn student_crabs_molt_periodically() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name("kelp".to_string())).unwrap();
+    let mut beach = Beach::new();
+    let mut ferris = new_crab("Ferris", 3);
+    ferris.discover_reef(kelp.clone());
+    beach.add_crab(ferris);
+    ocean.add_beach(beach);
+    let mut sim = Simulation::new(ocean, 3);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let log = events.clone();
+    sim.subscribe(Box::new(move |event: &OceanEvent| {
+        if matches!(event, OceanEvent::MoltStarted { .. } | OceanEvent::MoltEnded { .. }) {
+            log.borrow_mut().push(event.clone());
+        }
+    }));
+
+    sim.run(MOLT_INTERVAL as u64);
+    let ferris = sim.ocean().beaches().next().unwrap().get_crab(0);
+    assert!(ferris.is_molting());
+    assert_eq!(ferris.effective_speed(), 0);
+    assert_eq!(*events.borrow(), [OceanEvent::MoltStarted { crab: "Ferris".to_string() }]);
+
+    // A molting crab stays out of the reef even when there is prey to catch.
+    kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    assert_eq!(sim.step().hunts, 0);
+    sim.run((MOLT_STEPS - 1) as u64);
+    let ferris = sim.ocean().beaches().next().unwrap().get_crab(0);
+    assert!(!ferris.is_molting());
+    assert_eq!(ferris.speed(), 3 + MOLT_SPEED_GAIN);
+    assert_eq!(events.borrow()[1], OceanEvent::MoltEnded { crab: "Ferris".to_string(), speed: 4 });
+    assert_eq!(sim.ocean().beaches().next().unwrap().get_fastest_crab().unwrap().speed(), 4);
+}
+
+#[test]
+fn student_diseases_spread_and_heal() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 10), ("Crusty", 10), ("Pinchy", 10)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("reds", "Ferris");
+    beach.add_member_to_clan("reds", "Crusty");
+    let flu = Disease::new("flu").with_contagion(0.0).with_clan_contagion(1.0).with_damage(30).with_recovery_chance(0.0);
+    let ferris = beach.get_crab(0).id();
+    assert_eq!(beach.infect(ferris, flu.clone()), Ok(true));
+    assert_eq!(beach.infect(ferris, flu.clone()), Ok(false));
+    assert_eq!(beach.infect(99, flu), Err(OceanError::CrabIdNotFound(99)));
+
+    // Only Ferris's clanmate catches it, and only Ferris suffers in the first step.
+    let mut rng = seeded(5);
+    assert_eq!(beach.spread_disease(&mut rng), 1);
+    assert!(beach.get_crab(1).is_sick() && !beach.get_crab(2).is_sick());
+    assert_eq!(beach.get_crab(0).health(), MAX_HEALTH - 30);
+    assert_eq!(beach.get_crab(1).health(), MAX_HEALTH);
+    assert_eq!(beach.get_crab(0).effective_speed(), 7);
+    assert_eq!(beach.get_crab(0).speed(), 10);
+    let events = beach.drain_events();
+    assert!(events.contains(&OceanEvent::CrabInfected { crab: "Crusty".to_string(), disease: "flu".to_string() }));
+
+    // Sick crabs can be loaded back, and a sure cure lets them heal.
+    let loaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(loaded.get_crab(0).disease().map(Disease::damage), Some(30));
+    assert_eq!(loaded.get_crab(0).health(), 70);
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Sandy", 4));
+    let id = beach.get_crab(0).id();
+    beach.infect(id, Disease::new("cold").with_damage(50).with_recovery_chance(1.0)).unwrap();
+    beach.spread_disease(&mut rng);
+    assert!(!beach.get_crab(0).is_sick());
+    assert_eq!(beach.get_crab(0).health(), 50);
+    beach.spread_disease(&mut rng);
+    assert_eq!(beach.get_crab(0).health(), 50 + HEALTH_REGEN);
+    assert!(beach.drain_events().contains(&OceanEvent::CrabRecovered { crab: "Sandy".to_string(), disease: "cold".to_string() }));
+}
+
+#[test]
+fn student_cook_and_eat_restores_energy_and_health() {
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Clam::new()));
+    let mut beach = Beach::new();
+    let mut ferris = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Shellfish);
+    ferris.discover_reef(reef.clone());
+    beach.add_crab(ferris);
+    let id = beach.get_crab(0).id();
+    beach.infect(id, Disease::new("flu").with_damage(40).with_recovery_chance(1.0)).unwrap();
+    beach.spread_disease(&mut seeded(1));
+
+    let soup = Recipe::new(String::from("soup"), Diet::Shellfish).with_ingredient("Clam", 1).with_nutrition(25);
+    let mut ferris = beach.get_crab_mut(0);
+    let missing = OceanError::MissingIngredients { crab: "Ferris".to_string(), recipe: "soup".to_string() };
+    assert_eq!(ferris.cook_and_eat(&soup), Err(missing));
+    assert!(ferris.hunt());
+    let energy = ferris.energy();
+    ferris.cook_and_eat(&soup).unwrap();
+    assert_eq!(ferris.larder("Clam"), 0);
+    assert_eq!(ferris.energy(), energy + 25);
+    assert_eq!(ferris.health(), 85);
+
+    // Health never goes past full.
+    ferris.cook_and_eat(&Recipe::new(String::from("feast"), Diet::Shellfish).with_nutrition(50)).unwrap();
+    assert_eq!(ferris.health(), MAX_HEALTH);
+}
+
+#[test]
+fn student_feeding_round_orders_the_hunt() {
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 2), ("Crusty", 9), ("Pinchy", 5)] {
+        let mut crab = Crab::new(name.to_string(), speed, Color::new_red(), Diet::Plants);
+        crab.discover_reef(reef.clone());
+        beach.add_crab(crab);
+    }
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let outcomes = beach.feeding_round();
+    assert_eq!(outcomes.iter().map(|outcome| outcome.index).collect::<Vec<_>>(), [0, 1, 2]);
+    assert!(outcomes[0].caught && !outcomes[1].caught && !outcomes[2].caught);
+    assert_eq!(outcomes[0].energy_gained, beach.get_crab(0).energy());
+
+    // Only one alga to go around, so the fastest crab gets it.
+    beach.set_feeding_order(FeedingOrder::FastestFirst);
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let outcomes = beach.feeding_round();
+    assert_eq!(outcomes.iter().map(|outcome| outcome.crab.as_str()).collect::<Vec<_>>(), ["Crusty", "Pinchy", "Ferris"]);
+    assert!(outcomes[0].caught);
+
+    // Now Pinchy is the hungriest, ahead of Ferris's and Crusty's full bellies.
+    beach.set_feeding_order(FeedingOrder::HungriestFirst);
+    reef.borrow_mut().add_prey(Box::new(Algae::new()));
+    let outcomes = beach.feeding_round();
+    assert_eq!(outcomes[0].crab, "Pinchy");
+    assert!(outcomes[0].caught);
+}
+
+#[test]
+fn student_crabs_discover_reefs_within_range() {
+    let mut ocean = Ocean::new();
+    let mut near = Reef::with_name("near".to_string());
+    near.set_position(Position::new(3.0, 4.0));
+    let near = ocean.add_reef(near).unwrap();
+    let mut far = Reef::with_name("far".to_string());
+    far.set_position(Position::new(20.0, 0.0));
+    let far = ocean.add_reef(far).unwrap();
+    ocean.add_reef(Reef::with_name("nowhere".to_string())).unwrap();
+
+    let mut beach = Beach::new();
+    let mut ferris = new_crab("Ferris", 3);
+    ferris.set_position(Position::new(0.0, 0.0));
+    beach.add_crab(ferris);
+    beach.add_crab(new_crab("Unplaced", 3));
+    assert_eq!(beach.discoverable_reefs(1, ocean.reefs()).unwrap().len(), 0);
+    assert_eq!(beach.discoverable_reefs(9, ocean.reefs()).unwrap_err(), OceanError::CrabIdNotFound(9));
+
+    beach.set_sensory_radius(Some(5.0));
+    let found = beach.discoverable_reefs(1, ocean.reefs()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert!(Rc::ptr_eq(&found[0], &near));
+    assert!(beach.discoverable_reefs(2, ocean.reefs()).unwrap().is_empty());
+    assert_eq!(beach.discover_nearby_reefs(ocean.reefs()), 1);
+    assert_eq!(beach.discover_nearby_reefs(ocean.reefs()), 0);
+    assert_eq!(Beach::load(&beach.save()).unwrap().sensory_radius(), Some(5.0));
+
+    // A simulation lets crabs discover reefs every step, so moving crabs find new ones.
+    beach.get_crab_mut(0).set_position(Position::new(18.0, 0.0));
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    assert_eq!(simulation.step().reefs_discovered, 1);
+    let ferris = simulation.ocean().beaches().next().unwrap().get_crab(0);
+    assert!(Rc::ptr_eq(&ferris.reefs().nth(1).unwrap(), &far));
+}
+
+#[test]
+fn student_peek_at_reef_prey() {
+    let mut rng = seeded(1);
+    let mut reef = Reef::new();
+    assert_eq!(reef.peek_prey(0), None);
+    reef.add_prey(Box::new(Clam::new()));
+    reef.tick(&mut rng);
+    reef.add_prey(Box::new(Minnow::new(4)));
+
+    let clam = reef.peek_prey(0).unwrap();
+    assert_eq!((clam.kind, clam.diet, clam.age), ("Clam", Diet::Shellfish, 1));
+    assert_eq!(reef.peek_prey(2), None);
+    let minnow = PreyInfo { kind: "Minnow", size: Size::Medium, diet: Diet::Fish, nutrition: 4, age: 0 };
+    assert_eq!(reef.iter_prey().collect::<Vec<_>>(), vec![clam, minnow]);
+    // Looking leaves every prey in place.
+    assert_eq!(reef.iter_prey().count(), 2);
+    assert_eq!(reef.population(), 2);
+}
+
+#[test]
+fn student_reef_biodiversity() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name("kelp".to_string())).unwrap();
+    let bay = ocean.add_reef(Reef::with_name("bay".to_string())).unwrap();
+    assert_eq!(kelp.borrow().biodiversity(), 0.0);
+    assert_eq!(kelp.borrow().simpson_diversity(), 0.0);
+    for _ in 0..2 {
+        kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    }
+    assert_eq!(kelp.borrow().biodiversity(), 0.0);
+    kelp.borrow_mut().add_prey(Box::new(Clam::new()));
+    kelp.borrow_mut().add_prey(Box::new(Clam::new()));
+    assert!((kelp.borrow().biodiversity() - 2f64.ln()).abs() < 1e-12);
+    assert!((kelp.borrow().simpson_diversity() - 0.5).abs() < 1e-12);
+
+    // The bay's minnows make the ocean as a whole more varied than the kelp alone.
+    bay.borrow_mut().add_prey(Box::new(Minnow::new(3)));
+    let mut ocean_only_clams = Ocean::new();
+    let clams = ocean_only_clams.add_reef(Reef::new()).unwrap();
+    clams.borrow_mut().add_prey(Box::new(Clam::new()));
+    assert_eq!(ocean_only_clams.biodiversity(), 0.0);
+    assert!(ocean.biodiversity() > kelp.borrow().biodiversity());
+}
+
+#[test]
+fn student_population_policy() {
+    let mut rng = seeded(4);
+    let mut beach = Beach::new();
+    assert_eq!(beach.population_policy(), &PopulationPolicy::new());
+    assert_eq!(beach.apply_population_policy(&mut rng), PopulationChange::default());
+
+    // Two arrivals a step, but never more than five crabs.
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    let mut resident = new_crab("Resident", 3);
+    resident.discover_reef(reef.clone());
+    beach.add_crab(resident);
+    beach.set_population_policy(PopulationPolicy::new().with_spawn_rate(2.0).with_capacity(5));
+    assert_eq!(beach.apply_population_policy(&mut rng), PopulationChange { spawned: 2, died: 0 });
+    assert_eq!(beach.apply_population_policy(&mut rng).spawned, 2);
+    assert_eq!(beach.apply_population_policy(&mut rng).spawned, 0);
+    assert_eq!(beach.size(), 5);
+    let wild = beach.get_crab(1);
+    assert!(wild.name().starts_with("Wild "));
+    assert!((1..=10).contains(&wild.speed()));
+    assert_eq!(wild.reefs().count(), 1);
+    let spawned = OceanEvent::CrabSpawned { name: wild.name().to_string() };
+    assert!(beach.drain_events().contains(&spawned));
+
+    // Lowering the capacity culls crabs at random; certain death empties the beach.
+    beach.set_population_policy(PopulationPolicy::new().with_capacity(3));
+    assert_eq!(beach.apply_population_policy(&mut rng), PopulationChange { spawned: 0, died: 2 });
+    assert_eq!(beach.hall_of_fame().count(), 2);
+    beach.set_population_policy(PopulationPolicy::new().with_mortality(2.0));
+    assert_eq!(beach.population_policy().mortality(), 1.0);
+    assert_eq!(beach.apply_population_policy(&mut rng).died, 3);
+    assert_eq!(beach.size(), 0);
+
+    // A simulation applies each beach's policy once a step.
+    let mut ocean = Ocean::new();
+    let mut beach = Beach::new();
+    beach.set_population_policy(PopulationPolicy::new().with_spawn_rate(1.0));
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    let report = simulation.step();
+    assert_eq!((report.crabs_spawned, report.crabs_died), (1, 0));
+    assert_eq!(simulation.ocean().beaches().next().unwrap().size(), 1);
+}
+
+#[test]
+fn student_starving_crabs_die_and_are_removed() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Corro", 3));
+    beach.add_crab(new_crab("Pinchy", 4));
+    beach.add_member_to_clan("pinchers", "Corro");
+    assert_eq!(beach.starvation_limit(), None);
+    beach.set_starvation_limit(Some(2));
+    beach.set_metabolism(1);
+    assert_eq!(beach.metabolism(), 1);
+
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&events);
+    simulation.subscribe(Box::new(move |event: &OceanEvent| sink.borrow_mut().push(event.clone())));
+    assert_eq!(simulation.step().crabs_starved, 0);
+    assert_eq!(simulation.ocean().beaches().next().unwrap().get_crab(0).starving_steps(), 1);
+    assert_eq!(simulation.step().crabs_starved, 2);
+    assert!(events.borrow().contains(&OceanEvent::CrabStarved { crab: "Corro".to_string() }));
+
+    // Dead crabs stay until the next step, but cannot breed or hunt.
+    let beach = simulation.ocean_mut().beaches_mut().next().unwrap();
+    let corro = beach.get_crab(0);
+    assert!(corro.is_dead());
+    assert_eq!(corro.effective_speed(), 0);
+    let saved = Crab::from_fields(&corro.to_fields(), 1).unwrap();
+    assert!(saved.is_dead());
+    assert_eq!(
+        beach.try_breed_crabs(0, 1, String::from("Junior")),
+        Err(OceanError::CrabDead("Corro".to_string()))
+    );
+
+    simulation.step();
+    let beach = simulation.ocean().beaches().next().unwrap();
+    assert_eq!(beach.size(), 0);
+    assert!(beach.get_clan_system().get_clan_member_names("pinchers").is_empty());
+    assert!(events.borrow().contains(&OceanEvent::CrabDied { name: "Pinchy".to_string() }));
+}
+
+#[test]
+fn student_scavengers_eat_carcasses_before_they_rot() {
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Carcass::of(&new_crab("Old Timer", 1))));
+    let mut vulture = Crab::new(String::from("Vulture"), 1, Color::new_red(), Diet::Scavenger);
+    vulture.discover_reef(reef);
+    assert!(vulture.hunt());
+
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(vulture);
+    beach.set_starvation_limit(Some(1));
+    beach.set_carcass_decay(Some(2));
+    assert_eq!(beach.carcass_decay(), Some(2));
+
+    // Ferris starves on the first step and is removed, leaving a carcass, on the second, when
+    // the scavenger eats it.
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&events);
+    simulation.subscribe(Box::new(move |event: &OceanEvent| sink.borrow_mut().push(event.clone())));
+    assert_eq!(simulation.step().crabs_starved, 1);
+    assert_eq!(simulation.step().carcasses_eaten, 1);
+    let eaten = OceanEvent::CarcassEaten { crab: "Vulture".to_string(), carcass: "Ferris".to_string() };
+    assert!(events.borrow().contains(&eaten));
+    let beach = simulation.ocean_mut().beaches_mut().next().unwrap();
+    assert_eq!(beach.get_crab(0).energy(), 2);
+    assert_eq!(beach.carcasses().count(), 0);
+
+    // Buried crabs leave no carcass, and uneaten ones rot away after the beach's carcass decay.
+    beach.bury_crab(0);
+    beach.add_crab(new_crab("Pinchy", 4));
+    assert_eq!(simulation.step().crabs_starved, 1);
+    simulation.step();
+    assert_eq!(simulation.ocean().beaches().next().unwrap().carcasses().next().unwrap().name(), "Pinchy");
+    simulation.step();
+    assert_eq!(simulation.ocean().beaches().next().unwrap().carcasses().count(), 0);
+}
+
+#[test]
+fn student_camouflaged_crabs_survive_and_hunt_better() {
+    assert_eq!(Color::new_blue().similarity(&Color::new_blue()), 1.0);
+    assert_eq!(Color::new(0, 0, 0).similarity(&Color::new(255, 255, 255)), 0.0);
+
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Corro", 3));
+    beach.add_crab(Crab::new(String::from("Rusty"), 3, Color::new_red(), Diet::Plants));
+    beach.add_zone(Zone::new("reef").with_background(Color::new_red())).unwrap();
+    beach.add_zone(Zone::new("sand")).unwrap();
+    beach.assign_zone(1, "reef").unwrap();
+    assert_eq!(beach.background_color(Some("reef")), Some(&Color::new_red()));
+    assert_eq!(beach.background_color(Some("sand")), None);
+    beach.set_background_color(Some(Color::new_blue()));
+    assert_eq!(beach.background_color(Some("sand")), Some(&Color::new_blue()));
+    assert_eq!(beach.background_color(None), Some(&Color::new_blue()));
+
+    // Both crabs match their backgrounds perfectly, so an octopus never finds them.
+    beach.add_predator(Box::new(Octopus::new(1.0)));
+    for _ in 0..10 {
+        assert_eq!(beach.face_predators(&mut seeded(1)), 0);
+    }
+    assert_eq!(beach.get_crab(0).camouflage(), 1.0);
+    assert_eq!(beach.get_crab(1).color_match(), 1.0);
+
+    // Prey notice a camouflaged crab later.
+    let traits = PreyTraits { speed: 3, camouflage: 0.0, size: Size::Small };
+    let chance = ChanceEscape::new(0.5, 0.0, 0.0, 0.0);
+    assert_eq!(chance.probability(&traits, beach.get_crab(0)), 0.25);
+    assert_eq!(chance.probability(&traits, &new_crab("Pinchy", 3)), 0.5);
+
+    let loaded = Beach::load(&beach.save()).unwrap();
+    assert_eq!(loaded.background_color(Some("reef")), Some(&Color::new_red()));
+    assert_eq!(loaded.background_color(None), Some(&Color::new_blue()));
+}
+
+#[test]
+fn student_fractional_speeds() {
+    let genome = Genome::new(3, Genotype::homozygous(Color::new_blue()), 0.0).with_speed_f(4.5);
+    assert_eq!((genome.speed(), genome.speed_f()), (4, 4.5));
+    assert_eq!(genome.clone().with_speed_f(-2.0).speed_f(), 0.0);
+
+    let crab = new_crab("Corro", 1).with_genome(genome);
+    assert_eq!(crab.speed(), 4);
+    assert_eq!(crab.effective_speed_f(), 4.5);
+    let saved = Crab::from_fields(&crab.to_fields(), 1).unwrap();
+    assert_eq!(saved.speed_f(), 4.5);
+    assert_eq!(new_crab("Pinchy", 3).to_fields()[1], "3");
+
+    let mut beach = Beach::new();
+    beach.add_crab(crab);
+    beach.add_crab(new_crab("Pinchy", 3));
+    beach.add_member_to_clan("pinchers", "Corro");
+    beach.add_member_to_clan("pinchers", "Pinchy");
+    let clans = beach.get_clan_system();
+    assert_eq!(clans.get_clan_average_speed("pinchers"), Some(3));
+    assert_eq!(clans.get_clan_average_speed_f("pinchers"), Some(3.75));
+    assert_eq!(clans.get_clan_average_speed_f("nobody"), None);
+}
+
+#[test]
+fn student_largest_clans_break_ties_by_name() {
+    let mut beach = Beach::new();
+    assert!(beach.get_clan_system().get_largest_clans().is_empty());
+    for name in ["Ferris", "Corro", "Pinchy"] {
+        beach.add_crab(new_crab(name, 2));
+    }
+    beach.get_clan_system_mut().create_clan("empty".to_string());
+    beach.add_member_to_clan("snappers", "Ferris");
+    beach.add_member_to_clan("pinchers", "Corro");
+    let clans = beach.get_clan_system();
+    assert_eq!(clans.get_largest_clans(), vec!["pinchers".to_string(), "snappers".to_string()]);
+    assert_eq!(clans.get_largest_clan_id(), Some("pinchers".to_string()));
+
+    beach.add_member_to_clan("snappers", "Pinchy");
+    assert_eq!(beach.get_clan_system().get_largest_clans(), vec!["snappers".to_string()]);
+}
+
+#[test]
+fn student_mutable_crab_access_keeps_the_beach_indexed() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 5));
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Corro");
+
+    // Replacing a crab outright re-indexes the beach once the guard is dropped.
+    *beach.get_crab_mut(0) = new_crab("Speedy", 9);
+    assert_eq!(beach.find_crab_index("Speedy"), Some(0));
+    assert_eq!(beach.find_crab_index("Ferris"), None);
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Speedy");
+
+    let id = beach.get_crab(1).id();
+    beach.get_crab_by_id_mut(id).unwrap().set_meta("shell", "spiky");
+    assert_eq!(beach.get_crab(1).meta()["shell"], "spiky");
+    assert!(beach.get_crab_by_id_mut(99).is_none());
+
+    for crab in &mut beach.crabs_mut() {
+        if crab.name() == "Speedy" {
+            *crab = new_crab("Slowpoke", 1);
+        }
+    }
+    assert_eq!(beach.find_crab_index("Slowpoke"), Some(0));
+    assert_eq!(beach.get_fastest_crab().unwrap().name(), "Corro");
+}
+
+#[test]
+fn student_clans_follow_renames_and_removals() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 5));
+    beach.add_crab(new_crab("Pinchy", 4));
+    beach.add_member_to_clan("snappers", "Ferris");
+    beach.add_member_to_clan("pinchers", "Corro");
+    assert_eq!(beach.get_winner_clan("snappers", "pinchers"), Ok(Some("pinchers".to_string())));
+
+    // A renamed crab keeps its clan under its new name.
+    beach.rename_crab(0, "Speedy").unwrap();
+    assert_eq!(beach.get_clan_of_crab("Speedy"), Some("snappers"));
+    assert_eq!(beach.get_clan_of_crab("Ferris"), None);
+    assert_eq!(beach.get_crab(0).clan(), Some("snappers"));
+    assert_eq!(
+        beach.rename_crab(0, "Pinchy"),
+        Err(OceanError::DuplicateName { kind: "crab", name: "Pinchy".to_string() })
+    );
+
+    // Replacing a crab updates its clan's speed, so competitions see the live crab.
+    *beach.get_crab_mut(0) = new_crab("Speedy", 9);
+    assert_eq!(beach.get_clan_system().get_clan_total_speed("snappers"), Some(9));
+    assert_eq!(beach.get_winner_clan("snappers", "pinchers"), Ok(Some("snappers".to_string())));
+    for crab in &mut beach.crabs_mut() {
+        if crab.name() == "Corro" {
+            *crab = new_crab("Rocket", 12);
+        }
+    }
+    assert_eq!(beach.get_clan_of_crab("Rocket"), Some("pinchers"));
+    assert_eq!(beach.get_winner_clan("snappers", "pinchers"), Ok(Some("pinchers".to_string())));
+
+    // Crabs that leave the beach leave their clans.
+    beach.bury_crab(1);
+    assert_eq!(beach.get_clan_of_crab("Rocket"), None);
+    assert_eq!(beach.get_winner_clan("snappers", "pinchers"), Err(OceanError::ClanEmpty("pinchers".to_string())));
+}
+
+#[test]
+fn student_ocean_clans_span_beaches() {
+    let mut ocean = Ocean::new();
+    for names in [["Ferris", "Corro"], ["Pinchy", "Rusty"]] {
+        let mut beach = Beach::new();
+        for (i, name) in names.iter().enumerate() {
+            beach.add_crab(new_crab(name, 2 + 4 * i as u32));
+        }
+        ocean.add_beach(beach);
+    }
+    // Ferris (2) and Rusty (6) against Corro (6) and Pinchy (2), then Corro alone.
+    ocean.join_ocean_clan("tide", 0, 1).unwrap();
+    ocean.join_ocean_clan("tide", 1, 2).unwrap();
+    ocean.join_ocean_clan("surf", 0, 2).unwrap();
+    ocean.join_ocean_clan("surf", 1, 1).unwrap();
+    assert_eq!(ocean.join_ocean_clan("surf", 2, 1), Err(OceanError::BeachNotFound("at index 2".to_string())));
+    assert_eq!(ocean.join_ocean_clan("surf", 0, 9), Err(OceanError::CrabIdNotFound(9)));
+    let names = |ocean: &Ocean, clan: &str| -> Vec<(usize, String)> {
+        ocean.ocean_clan_members(clan).into_iter().map(|(beach, crab)| (beach, crab.name().to_string())).collect()
+    };
+    assert_eq!(names(&ocean, "tide"), vec![(0, "Ferris".to_string()), (1, "Rusty".to_string())]);
+    assert_eq!(ocean.compete_ocean_clans("tide", "surf"), Ok(None));
+    assert_eq!(ocean.leave_ocean_clan(1, 1), Some("surf".to_string()));
+    assert_eq!(ocean.compete_ocean_clans("tide", "surf"), Ok(Some("surf".to_string())));
+    assert_eq!(ocean.ocean_clans().reputation("surf"), DRAW_POINTS + WIN_POINTS);
+
+    // Migrants stay in their ocean clan; crabs that leave the ocean stop counting.
+    let id = ocean.migrate(1, 0, 1).unwrap();
+    assert_eq!(names(&ocean, "tide"), vec![(1, "Rusty".to_string()), (1, "Ferris".to_string())]);
+    assert_eq!(ocean.ocean_clans().get_clan_of_member(&format!("1/{}", id)), Some("tide"));
+    ocean.beaches_mut().next().unwrap().bury_crab(0);
+    assert_eq!(ocean.compete_ocean_clans("tide", "surf"), Err(OceanError::ClanEmpty("surf".to_string())));
+    assert_eq!(ocean.compete_ocean_clans("tide", "reef"), Err(OceanError::ClanNotFound("reef".to_string())));
+}
+
+#[test]
+fn student_report_summarizes_ocean() {
+    let mut ocean = Ocean::new();
+    let mut beach = Beach::with_name(String::from("Sandy <Cove>"));
+    beach.add_crab(new_crab("Ferris", 10));
+    beach.add_crab(new_crab("Corro", 4));
+    beach.add_member_to_clan("Red|Claws", "Ferris");
+    ocean.add_beach(beach);
+    let kelp = ocean.add_reef(Reef::with_name(String::from("Kelp Forest"))).unwrap();
+    kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+
+    let report = Report::generate(&ocean);
+    assert!(report.starts_with("# Ocean report\n"));
+    assert!(report.contains("| Sandy \\<Cove\\> | 2 | Ferris (10) | 1 | 0 |"));
+    assert!(report.contains("| Red\\|Claws | 1 | 10 | 0 |"));
+    assert!(report.contains("| Kelp Forest | 1 |"));
+
+    // Only the notable events of the last tick, plus those of the unfinished one, are listed.
+    let events = vec![
+        OceanEvent::CrabDied { name: String::from("Old") },
+        OceanEvent::StepFinished { tick: 1 },
+        OceanEvent::PreyCaught { crab: String::from("Ferris"), prey: String::from("Algae") },
+        OceanEvent::CrabStarved { crab: String::from("Corro") },
+        OceanEvent::StepFinished { tick: 2 },
+        OceanEvent::ClanWon { winner: String::from("Red"), loser: String::from("Blue") },
+    ];
+    let report = Report::new().with_events(events.clone()).with_recent_ticks(1).render(&ocean);
+    assert!(!report.contains("Old died"));
+    assert!(!report.contains("Algae"));
+    assert!(report.contains("| 2 | Corro starved |"));
+    assert!(report.contains("| 3 | Red beat Blue |"));
+
+    let html = Report::new().with_format(ReportFormat::Html).with_events(events).render(&ocean);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<td>Sandy &lt;Cove&gt;</td>"));
+    assert!(html.contains("<tr><td>1</td><td>Old died</td></tr>"));
+    assert!(html.ends_with("</html>\n"));
+}
+
+#[test]
+fn student_batch_runs_one_simulation_per_seed() {
+    let world = ScenarioBuilder::new(7)
+        .with_reef(ReefSpec::new("kelp").with_algae(20).with_shrimp(5))
+        .with_beach(BeachSpec::new("north").with_crabs(CrabSpec::new(6).with_speed(3, 8).with_reefs(&["kelp"])))
+        .build()
+        .unwrap();
+    let config = BatchConfig::new(&world, 5).unwrap().with_config(SimConfig { seed: 99, ..SimConfig::default() });
+
+    let runs = run_batch(&config, &[1, 2, 1]).unwrap();
+    assert_eq!(runs.len(), 3);
+    assert_eq!(runs.iter().map(|run| run.seed).collect::<Vec<_>>(), vec![1, 2, 1]);
+    // The same seed gives the same run, and the config's own seed is not used.
+    assert_eq!(runs[0], runs[2]);
+    assert!(runs.iter().all(|run| run.ticks == 5 && run.hunts >= run.catches));
+
+    let csv = to_csv(&runs);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], CSV_COLUMNS.join(","));
+    assert_eq!(lines[1], runs[0].to_csv_row());
+    assert!(lines[1].starts_with("1,5,"));
+
+    // A config naming a reef the world does not have fails the whole batch.
+    let mut bad = SimConfig::default();
+    bad.reefs.insert("nowhere".to_string(), ReefConfig::default());
+    let config = BatchConfig::new(&world, 5).unwrap().with_config(bad);
+    assert_eq!(run_batch(&config, &[1]).unwrap_err(), OceanError::ReefNotFound("nowhere".to_string()));
+    assert!(BatchConfig::from_snapshot("not a snapshot", 5).is_err());
+}
+
+#[test]
+fn student_simulation_runs_behavior_trees() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name(String::from("Kelp Forest"))).unwrap();
+    kelp.borrow_mut().add_prey(Box::new(Algae::new()));
+    let mut north = Beach::with_name(String::from("North"));
+    // A hungry juvenile with no reef can neither hunt, train, nor breed, so it migrates.
+    north.add_crab(new_crab("Ferris", 5).with_behavior(Behavior::default()));
+    north.add_crab(new_crab("Corro", 5));
+    let mut hunter = new_crab("Pinchy", 5).with_behavior(Behavior::Sequence(vec![
+        Behavior::Condition(Condition::HasReef),
+        Behavior::Action(Action::Hunt),
+    ]));
+    hunter.discover_reef(kelp.clone());
+    north.add_crab(hunter);
+    ocean.add_beach(north);
+    ocean.add_beach(Beach::with_name(String::from("South")));
+    assert!(Condition::Hungry(HUNGRY_BELOW).holds(ocean.beaches().next().unwrap().get_crab(0)));
+
+    let mut simulation = Simulation::new(ocean, 3);
+    let report = simulation.step();
+    assert_eq!(report.crabs_migrated, 1);
+    assert_eq!(report.hunts, 2);
+    let north = simulation.ocean().beach_by_name("North").unwrap();
+    let south = simulation.ocean().beach_by_name("South").unwrap();
+    assert_eq!(south.crabs().map(|crab| crab.name()).collect::<Vec<_>>(), vec!["Ferris"]);
+    assert_eq!(south.get_crab(0).behavior(), Some(&Behavior::default()));
+    // Pinchy hunted once, following its tree, rather than also hunting with the other crabs.
+    let pinchy = north.find_crabs_by_name("Pinchy")[0];
+    assert_eq!(pinchy.hunt_stats().attempts, 1);
+
+    // A tree of conditions alone keeps a crab from doing anything, even hunting.
+    let mut ferris = simulation.ocean_mut().beach_by_name_mut("South").unwrap().get_crab_mut(0);
+    ferris.set_behavior(Some(Behavior::Invert(Box::new(Behavior::Condition(Condition::HasReef)))));
+    drop(ferris);
+    let report = simulation.step();
+    assert_eq!((report.crabs_migrated, report.hunts), (0, 2));
+    assert_eq!(simulation.ocean().beach_by_name("South").unwrap().size(), 1);
+}
+
+// Records every phase a simulation hook is called at.
+struct PhaseLog(Rc<RefCell<Vec<(u64, Phase)>>>);
+
+impl TickHook for PhaseLog {
+    fn after_phase(&mut self, tick: u64, phase: Phase, _ocean: &mut Ocean, _rng: &mut dyn OceanRng) {
+        self.0.borrow_mut().push((tick, phase));
+    }
+}
+
+// Brings a new crab to its beach at the end of every population phase.
+#[derive(Debug)]
+struct Immigration;
+
+impl BeachHook for Immigration {
+    fn after_phase(&mut self, tick: u64, phase: Phase, beach: &mut Beach, _rng: &mut dyn OceanRng) {
+        if phase == Phase::Population {
+            beach.add_crab(new_crab(&format!("Newcomer {}", tick), 3));
+        }
+    }
+}
+
+// Stocks its reef with algae whenever reefs tick.
+#[derive(Debug)]
+struct Restock;
+
+impl ReefHook for Restock {
+    fn after_phase(&mut self, _tick: u64, phase: Phase, reef: &mut Reef, _rng: &mut dyn OceanRng) {
+        if phase == Phase::Reefs {
+            reef.add_prey(Box::new(Algae::new()));
+        }
+    }
+}
+
+#[test]
+fn student_simulation_runs_hooks_each_phase() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::with_name(String::from("Kelp Forest"))).unwrap();
+    kelp.borrow_mut().register_hook(Box::new(Restock));
+    let mut beach = Beach::new();
+    beach.register_hook(Box::new(Immigration));
+    ocean.add_beach(beach);
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut simulation = Simulation::new(ocean, 5);
+    simulation.register_hook(Box::new(PhaseLog(Rc::clone(&log))));
+    simulation.run(2);
+
+    let phases = [
+        Phase::Weather,
+        Phase::Aging,
+        Phase::Reefs,
+        Phase::Discovery,
+        Phase::Hunting,
+        Phase::Predators,
+        Phase::Population,
+        Phase::Migration,
+        Phase::Scheduled,
+    ];
+    let expected: Vec<(u64, Phase)> = (0..2).flat_map(|tick| phases.iter().map(move |&phase| (tick, phase))).collect();
+    assert_eq!(*log.borrow(), expected);
+    let beach = simulation.ocean().beaches().next().unwrap();
+    assert_eq!(beach.crabs().map(|crab| crab.name()).collect::<Vec<_>>(), vec!["Newcomer 0", "Newcomer 1"]);
+    assert_eq!(kelp.borrow().population(), 2);
+}
+
+#[cfg(feature = "deterministic")]
+#[test]
+fn student_deterministic_strategies_replace_fixed_generator() {
+    use ocean::deterministic::*;
+
+    set_strategies(Strategies::default());
+    let first: Vec<Diet> = (0..20).map(|_| Diet::random_diet()).collect();
+    set_strategies(Strategies::default());
+    let second: Vec<Diet> = (0..20).map(|_| Diet::random_diet()).collect();
+    assert_eq!(first, second);
+    assert!(first.contains(&Diet::Fish) && first.contains(&Diet::Plants));
+    assert!((0..1000).all(|draw| (0.0..1.0).contains(&roll(draw))));
+
+    // Injected strategies decide every choice made without a generator.
+    set_strategies(Strategies {
+        diet: |_| Diet::Scavenger,
+        pattern: |_, _, p2| p2,
+        escape: |draw, _| draw % 2 == 0,
+        pick: |_, count| count - 1,
+    });
+    assert_eq!(Diet::random_diet(), Diet::Scavenger);
+    assert_eq!(Pattern::cross(Pattern::Plain, Pattern::Striped), Pattern::Striped);
+    let mut model = ChanceEscape::new(0.5, 0.0, 0.0, 0.0);
+    let traits = PreyTraits { speed: 0, size: Size::Small, camouflage: 0.0 };
+    let crab = new_crab("Ferris", 3);
+    // The diet and pattern took draws 0 and 1.
+    assert!(model.escapes(&traits, &crab));
+    assert!(!model.escapes(&traits, &crab));
+
+    let mut reef = Reef::new();
+    reef.set_take_policy(TakePolicy::Random);
+    reef.add_prey(Box::new(Algae::new()));
+    reef.add_prey(Box::new(Clam::new()));
+    assert_eq!(reef.take_prey().unwrap().kind(), "Clam");
+    set_strategies(Strategies::default());
+}
+
+#[test]
+fn student_paging_streams_and_pages_saved_crabs() {
+    let mut beach = Beach::with_name(String::from("Huge"));
+    for i in 0..10 {
+        beach.add_crab(new_crab(&format!("Crab {}", i), i + 1));
+    }
+    beach.add_member_to_clan("Reds", "Crab 3");
+    let saved = beach.save();
+
+    let loaded = Beach::load_from(saved.as_bytes()).unwrap();
+    assert_eq!(loaded.save(), saved);
+    assert!(matches!(Beach::load_from("crab\tbroken".as_bytes()), Err(OceanError::Parse(_))));
+
+    let chunks: Vec<Vec<Crab>> = crab_chunks(saved.as_bytes(), 4).collect::<Result<_, _>>().unwrap();
+    assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), vec![4, 4, 2]);
+    assert_eq!(chunks[2][1].name(), "Crab 9");
+    let mut failing = crab_chunks("crab\tFerris\t3\t0\t0\t255\tPlants\t0\t0\ncrab\tbroken\n".as_bytes(), 1);
+    assert_eq!(failing.next().unwrap().unwrap()[0].name(), "Ferris");
+    assert!(failing.next().unwrap().is_err());
+    assert!(failing.next().is_none());
+
+    let mut paged = PagedCrabs::load(saved.as_bytes()).unwrap();
+    paged.set_max_loaded(Some(2));
+    assert_eq!((paged.len(), paged.loaded_count()), (10, 0));
+    assert_eq!(paged.get(5).unwrap().name(), "Crab 5");
+    paged.get_mut(7).unwrap().set_meta("tag", "touched");
+    paged.get(1);
+    // Crab 5 was paged out to make room, and Crab 7 kept its change when it was.
+    assert_eq!(paged.loaded_count(), 2);
+    paged.get(2);
+    assert_eq!(paged.get(7).unwrap().get_meta("tag"), Some("touched"));
+    assert!(paged.get(10).is_none());
+
+    let mut out = Vec::new();
+    paged.save_to(&mut out).unwrap();
+    let restored = Beach::load(&String::from_utf8(out).unwrap()).unwrap();
+    assert_eq!(restored.size(), 10);
+    assert_eq!(restored.get_crab(7).get_meta("tag"), Some("touched"));
+    let beach = paged.into_beach();
+    assert_eq!(beach.crabs().map(Crab::id).collect::<Vec<_>>(), (1..=10).collect::<Vec<_>>());
+}
+
+#[test]
+fn student_weak_reefs_are_freed_and_pruned() {
+    let (kelp, sand) = (Rc::new(RefCell::new(Reef::new())), Rc::new(RefCell::new(Reef::new())));
+    sand.borrow_mut().add_prey(Box::new(Clam::new()));
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Shellfish);
+    crab.discover_reef(kelp.clone());
+    crab.set_weak_reefs(true);
+    crab.discover_reef(sand.clone());
+    assert!(crab.weak_reefs());
+    assert_eq!(Rc::strong_count(&kelp), 1);
+
+    let weak_kelp = Rc::downgrade(&kelp);
+    drop(kelp);
+    assert!(weak_kelp.upgrade().is_none());
+    assert_eq!(crab.reefs().len(), 1);
+    assert_eq!(crab.best_reef(), Some(0));
+    assert!(crab.hunt());
+    assert_eq!(crab.hunt_stats().favorite_reef, Some(0));
+    assert_eq!(crab.prune_reefs(), 0);
+
+    crab.set_weak_reefs(false);
+    assert_eq!(Rc::strong_count(&sand), 2);
+    crab.set_weak_reefs(true);
+    drop(sand);
+    assert_eq!(crab.prune_reefs(), 1);
+    assert!(!crab.hunt());
+}
+
+#[test]
+fn student_hibernating_crabs_sit_out_steps_and_clan_contests() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Corro", 3));
+    beach.add_crab(new_crab("Pinchy", 9));
+    beach.add_crab(new_crab("Ferris", 5));
+    beach.add_member_to_clan("pinchers", "Corro");
+    beach.add_member_to_clan("pinchers", "Pinchy");
+    beach.add_member_to_clan("racers", "Ferris");
+    assert_eq!(beach.get_winner_clan("pinchers", "racers"), Ok(Some("pinchers".to_string())));
+
+    beach.get_crab_mut(1).hibernate();
+    assert!(beach.get_crab(1).is_hibernating());
+    assert_eq!(beach.get_clan_system().get_clan_average_speed("pinchers"), Some(3));
+    assert_eq!(beach.get_clan_system().get_clan_member_count("pinchers"), 2);
+    assert_eq!(beach.get_winner_clan("pinchers", "racers"), Ok(Some("racers".to_string())));
+    assert_eq!(
+        beach.try_breed_crabs(0, 1, String::from("Junior")),
+        Err(OceanError::CrabHibernating("Pinchy".to_string()))
+    );
+    let saved = Crab::from_fields(&beach.get_crab(1).to_fields(), 1).unwrap();
+    assert!(saved.is_hibernating());
+
+    beach.set_starvation_limit(Some(1));
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 1);
+    assert_eq!(simulation.step().crabs_starved, 2);
+    let beach = simulation.ocean_mut().beaches_mut().next().unwrap();
+    let pinchy = beach.get_crab(1);
+    assert!(!pinchy.is_dead());
+    assert_eq!(pinchy.age(), 0);
+
+    beach.get_crab_mut(1).wake();
+    assert_eq!(beach.get_clan_system().get_clan_average_speed("pinchers"), Some(6));
+}
+
+#[test]
+fn student_simulation_plays_scheduled_clan_matches() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 2));
+    beach.add_crab(new_crab("Corro", 6));
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    ocean.join_ocean_clan("tide", 0, 1).unwrap();
+    ocean.join_ocean_clan("surf", 0, 2).unwrap();
+
+    let mut simulation = Simulation::new(ocean, 1);
+    simulation.schedule_match(1, "tide", "surf");
+    simulation.schedule_match(0, "tide", "reef");
+    simulation.schedule_match(1, "surf", "tide");
+    let planned: Vec<_> = simulation.calendar().iter().map(|planned| (planned.tick, planned.clans.0.as_str())).collect();
+    assert_eq!(planned, vec![(0, "tide"), (1, "tide"), (1, "surf")]);
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&events);
+    simulation.subscribe(Box::new(move |event: &OceanEvent| sink.borrow_mut().push(event.clone())));
+
+    assert_eq!(simulation.step().matches_played, 0);
+    assert!(events.borrow().contains(&OceanEvent::MatchCancelled {
+        clans: ("tide".to_string(), "reef".to_string()),
+        reason: OceanError::ClanNotFound("reef".to_string()).to_string(),
+    }));
+    assert_eq!(simulation.step().matches_played, 2);
+    assert!(simulation.calendar().is_empty());
+    assert_eq!(simulation.ocean().ocean_clans().reputation("surf"), 2 * WIN_POINTS);
+    let wins = events.borrow().iter().filter(|event| matches!(event, OceanEvent::ClanWon { winner, .. } if winner == "surf")).count();
+    assert_eq!(wins, 2);
+}
+
+#[test]
+fn student_bait_lures_prey_and_holds_it() {
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Clam::new()));
+    reef.borrow_mut().add_prey(Box::new(Shrimp::new(0)));
+    reef.borrow_mut().set_escape_chance(Some(1.0));
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Shellfish);
+    crab.discover_reef(reef.clone());
+    assert!(!crab.hunt());
+
+    let net = Bait::new(String::from("net"), 2.0).with_lure("Shrimp");
+    assert_eq!(net.hold(), 1.0);
+    assert!(crab.hunt_with_bait(net));
+    assert_eq!((crab.larder("Shrimp"), crab.larder("Clam")), (1, 0));
+
+    let bait = crab.cut_bait("Shrimp").unwrap();
+    assert_eq!((bait.name(), bait.hold()), ("Shrimp bait", PREY_BAIT_HOLD));
+    assert!(bait.attracts("Shrimp"));
+    assert_eq!(crab.larder("Shrimp"), 0);
+    assert!(crab.cut_bait("Shrimp").is_err());
+
+    reef.borrow_mut().set_escape_chance(Some(0.0));
+    assert!(crab.hunt());
+    let chowder = Recipe::new(String::from("chowder"), Diet::Shellfish).with_ingredient("Clam", 1);
+    let bait = crab.craft_bait(&chowder).unwrap();
+    assert_eq!(bait.lures().collect::<Vec<_>>(), vec!["Clam"]);
+    assert_eq!(bait.hold(), RECIPE_BAIT_HOLD);
+    assert!(crab.craft_bait(&chowder).is_err());
+}
+
+#[test]
+fn student_ocean_find_searches_beaches_and_reefs() {
+    let mut ocean = Ocean::new();
+    let mut north = Beach::new();
+    north.add_crab(new_crab("Ferris", 6));
+    north.add_crab(new_crab("Bob", 8));
+    ocean.add_beach(north);
+    let mut south = Beach::new();
+    south.add_crab(new_crab("ferrous", 2));
+    ocean.add_beach(south);
+    let mut reef = Reef::new();
+    reef.add_prey(Box::new(Clam::new()));
+    reef.add_prey(Box::new(Algae::new()));
+    ocean.add_reef(reef).unwrap();
+
+    let ferr = NameSearch::new().ignore_case().with_mode(NameMatch::Prefix);
+    let found = ocean.find(&OceanQuery::new().name_like("ferr", ferr));
+    let ferris = ocean.beaches().next().unwrap().get_crab(0).id();
+    let ferrous = ocean.beaches().nth(1).unwrap().get_crab(0).id();
+    assert_eq!(found, vec![Found::Crab { beach: 0, id: ferris }, Found::Crab { beach: 1, id: ferrous }]);
+    assert_eq!(ocean.find(&OceanQuery::new().name_like("ferr", ferr).min_speed(5).max_speed(7)).len(), 1);
+
+    let plants = ocean.find(&OceanQuery::new().diet(Diet::Plants));
+    assert_eq!(plants.len(), 4);
+    assert_eq!(plants[3], Found::Prey { reef: 0, index: 1, kind: "Algae", diet: Diet::Plants });
+    assert_eq!(ocean.find(&OceanQuery::new().prey_kind("Clam")), vec![Found::Prey { reef: 0, index: 0, kind: "Clam", diet: Diet::Shellfish }]);
+    assert_eq!(ocean.find(&OceanQuery::new()).len(), 5);
+}
+
+#[test]
+fn student_ocean_macro_builds_scenarios() {
+    let ocean = ocean::ocean! {
+        reef "kelp" [Algae, Algae, Shrimp]
+        beach "north" {
+            crab "Ferris" speed 5 color Blue diet Shellfish,
+            crab "Bob" reefs ["kelp"],
+            clan "reds" ["Ferris", "Bob"]
+            reef "shoal" [Minnow, Clam]
+        }
+        beach "south" {}
+    }
+    .unwrap();
+    assert_eq!(ocean.beaches().count(), 2);
+    assert_eq!(ocean.reefs().count(), 2);
+    assert_eq!(ocean.reef_by_name("kelp").unwrap().borrow().prey().count(), 3);
+    let north = ocean.beaches().next().unwrap();
+    let ferris = north.get_crab(0);
+    assert_eq!((ferris.speed(), ferris.color(), ferris.diet()), (5, &Color::new_blue(), Diet::Shellfish));
+    assert_eq!(ferris.reefs().count(), 1);
+    let bob = north.get_crab(1);
+    assert_eq!((bob.speed(), bob.diet()), (1, Diet::Fish));
+    assert_eq!(bob.reefs().count(), 2);
+    assert_eq!(north.get_clan_of_crab("Bob"), Some("reds"));
+
+    assert!(matches!(ocean::ocean! { reef "kelp" [Squid] }, Err(OceanError::InvalidArgument(_))));
+    assert!(matches!(ocean::ocean! { beach "north" { crab "Bob" reefs ["kelp"] } }, Err(OceanError::ReefNotFound(_))));
+    assert!(OceanSketch::new().with_crab("Ferris").build().is_err());
+}
+
+#[test]
+fn student_replay_regrows_reefs() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::new()).unwrap();
+    kelp.borrow_mut().set_regen_policy(RegenPolicy::new().with_rate(1.5, || Box::new(Algae::new())));
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.get_crab_mut(0).discover_reef(kelp.clone());
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 3);
+    sim.start_recording().unwrap();
+    sim.run(10);
+    let path = std::env::temp_dir().join(format!("ocean-regen-replay-{}.txt", std::process::id()));
+    sim.save_recording(&path).unwrap();
+    let replayed = Simulation::replay(&path);
+    std::fs::remove_file(&path).unwrap();
+    let replayed = replayed.unwrap();
+    assert_eq!(replayed.snapshot(), sim.snapshot());
+    assert!(replayed.ocean().reefs().next().unwrap().borrow().prey().count() > 0);
+}
+
+#[test]
+fn student_state_at_regrows_reefs() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::new()).unwrap();
+    kelp.borrow_mut().set_regen_policy(RegenPolicy::new().with_rate(1.5, || Box::new(Algae::new())));
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.get_crab_mut(0).discover_reef(kelp.clone());
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 3);
+    sim.start_recording().unwrap();
+    sim.run(10);
+    assert_eq!(sim.state_at(10).unwrap().snapshot(), sim.snapshot());
+    let past = sim.state_at(4).unwrap();
+    assert_eq!(past.clock(), 4);
+    assert_eq!(past.ocean().beaches().next().unwrap().crabs().count(), 1);
+}
+
+#[test]
+fn student_loaded_simulations_continue_the_saved_run() {
+    let mut ocean = Ocean::new();
+    let kelp = ocean.add_reef(Reef::new()).unwrap();
+    kelp.borrow_mut().set_regen_policy(RegenPolicy::new().with_rate(1.5, || Box::new(Algae::new())));
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    beach.add_crab(new_crab("Corro", 5));
+    beach.get_crab_mut(0).discover_reef(kelp.clone());
+    ocean.add_beach(beach);
+
+    let mut sim = Simulation::new(ocean, 17);
+    sim.run(5);
+    let path = std::env::temp_dir().join(format!("ocean-resume-{}.txt", std::process::id()));
+    sim.save(&path).unwrap();
+    let resumed = Simulation::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let mut resumed = resumed.unwrap();
+    sim.run(5);
+    resumed.run(5);
+    assert_eq!(resumed.snapshot(), sim.snapshot());
+    assert!(resumed.ocean().reefs().next().unwrap().borrow().prey().count() > 0);
+
+    use rand::RngCore;
+    let mut rng = ResumableRng::new(9);
+    assert_eq!(rng.next_u64(), seeded(9).next_u64());
+    let mut again = ResumableRng::resume(rng.seed(), rng.position());
+    assert_eq!(again.next_u32(), rng.next_u32());
+}
+
+#[test]
+fn student_parallel_hunts_draw_anew_each_call() {
+    let build = || {
+        let mut beach = Beach::new();
+        for i in 0..4 {
+            let reef = Rc::new(RefCell::new(Reef::new()));
+            for _ in 0..20 {
+                reef.borrow_mut().add_prey(Box::new(Algae::new()));
+            }
+            reef.borrow_mut().set_escape_chance(Some(0.5));
+            let mut crab = new_crab(&format!("crab{}", i), 5);
+            crab.discover_reef(reef);
+            beach.add_crab(crab);
+        }
+        beach
+    };
+    let mut beach = build();
+    for _ in 0..8 {
+        beach.hunt_all_parallel_on(4);
+    }
+    assert!(beach.crabs().all(|crab| crab.larder("Algae") > 0));
+
+    let (mut one_thread, mut four_threads) = (build(), build());
+    let (mut rng1, mut rng4) = (seeded(6), seeded(6));
+    for _ in 0..8 {
+        assert_eq!(
+            one_thread.hunt_all_parallel_on_with_rng(1, &mut rng1),
+            four_threads.hunt_all_parallel_on_with_rng(4, &mut rng4)
+        );
+    }
+    let larders = |beach: &Beach| beach.crabs().map(|crab| crab.larder("Algae")).collect::<Vec<_>>();
+    assert_eq!(larders(&one_thread), larders(&four_threads));
+    assert!(one_thread.crabs().all(|crab| crab.larder("Algae") > 0));
+}
+
+#[test]
+fn student_escaped_prey_keep_their_age() {
+    let reef = Rc::new(RefCell::new(Reef::new()));
+    reef.borrow_mut().add_prey(Box::new(Minnow::new(25)));
+    reef.borrow_mut().set_escape_chance(Some(1.0));
+    let mut crab = Crab::new(String::from("Ferris"), 3, Color::new_blue(), Diet::Fish);
+    crab.discover_reef(reef.clone());
+    let mut rng = seeded(1);
+    for _ in 0..24 {
+        assert!(!crab.hunt());
+        assert!(!crab.hunt_with_rng(&mut rng));
+        reef.borrow_mut().tick(&mut rng);
+    }
+    assert_eq!(reef.borrow().population(), 1);
+    assert!(!crab.hunt());
+    reef.borrow_mut().tick(&mut rng);
+    assert_eq!(reef.borrow().population(), 0);
+}
+
+#[test]
+fn student_seeded_breeding_ignores_the_global_generator() {
+    let mom = Crab::new(String::from("Mom"), 4, Color::new_red(), Diet::Fish);
+    let dad = Crab::new(String::from("Dad"), 6, Color::new_blue(), Diet::Plants);
+    let breed_all = || {
+        let mut rng = seeded(7);
+        let kids = [
+            Crab::breed_with_rng(String::from("A"), &mom, &dad, &mut rng),
+            Crab::breed_mendelian(String::from("B"), &mom, &dad, &mut rng),
+            Crab::breed_weighted(String::from("C"), &mom, &dad, 0.5, &mut rng),
+        ];
+        kids.map(|kid| (kid.diet(), kid.pattern(), kid.color().clone()))
+    };
+    let first = breed_all();
+    for i in 0..5 {
+        // Each unseeded breed moves the global generator along.
+        Crab::breed(format!("Extra{}", i), &mom, &dad);
+        assert_eq!(breed_all(), first);
+    }
+}
+
+#[test]
+fn student_stratified_samples_are_reproducible() {
+    let mut beach = Beach::new();
+    let diets = [Diet::Fish, Diet::Shellfish, Diet::Plants, Diet::Scavenger];
+    for i in 0..24 {
+        beach.add_crab(Crab::new(format!("crab{}", i), 3, Color::new_red(), diets[i % diets.len()]));
+    }
+    let sample = || {
+        let strata = beach.sample_crabs_by_diet(&mut seeded(4), 2);
+        let mut names: Vec<(String, Vec<String>)> = strata
+            .into_iter()
+            .map(|(diet, crabs)| (format!("{:?}", diet), crabs.iter().map(|crab| crab.name().to_string()).collect()))
+            .collect();
+        names.sort();
+        names
+    };
+    let first = sample();
+    assert_eq!(first.len(), 4);
+    for _ in 0..20 {
+        assert_eq!(sample(), first);
+    }
+}
+
+#[test]
+fn student_deeply_nested_json_is_rejected() {
+    use ocean::persist::{parse_json, MAX_JSON_DEPTH};
+    assert!(parse_json(&"[".repeat(200_000)).is_err());
+    assert!(Cookbook::parse(&"{\"a\":".repeat(200_000), CookbookFormat::Json).is_err());
+    let nested = format!("{}{}", "[".repeat(MAX_JSON_DEPTH), "]".repeat(MAX_JSON_DEPTH));
+    assert!(parse_json(&nested).is_ok());
+    let deeper = format!("[{}]", nested);
+    assert!(parse_json(&deeper).is_err());
+    #[cfg(feature = "fuzzing")]
+    ocean::fuzz::load_text("[".repeat(200_000).as_bytes());
+}
+
+#[test]
+fn student_undrained_events_stay_bounded() {
+    let mut beach = Beach::new();
+    let receiver = beach.subscribe();
+    let rounds = MAX_QUEUED_EVENTS * 2 + 1;
+    for i in 0..rounds {
+        beach.add_crab(new_crab(&format!("crab{}", i), 1));
+        beach.bury_crab(0);
+    }
+    let queued = beach.drain_events();
+    assert!(queued.len() <= MAX_QUEUED_EVENTS);
+    assert_eq!(queued.last(), Some(&OceanEvent::CrabDied { name: format!("crab{}", rounds - 1) }));
+    // Subscribers were sent every event as it happened.
+    assert_eq!(receiver.try_iter().count(), rounds);
+}
+
+#[test]
+fn student_clan_diff_tracks_territory() {
+    let mut beach = Beach::new();
+    for (name, speed) in [("Ferris", 100), ("Pinchy", 90), ("Corro", 1)] {
+        beach.add_crab(new_crab(name, speed));
+    }
+    beach.add_member_to_clan("Raiders", "Ferris");
+    beach.add_member_to_clan("Raiders", "Pinchy");
+    beach.add_member_to_clan("Homebodies", "Corro");
+    beach.get_clan_system_mut().claim_reef("Homebodies", 2).unwrap();
+    let before = ClanSystem::load(&beach.get_clan_system().save()).unwrap();
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let snapshot = Snapshot::of(&ocean);
+
+    let beach = ocean.beaches_mut().next().unwrap();
+    beach.get_clan_system_mut().claim_reef("Raiders", 4).unwrap();
+    let mut rng = Pcg64::seed_from_u64(3);
+    assert_eq!(beach.raid("Raiders", "Homebodies", 2, &mut rng), Ok(true));
+    let diff = before.diff(beach.get_clan_system());
+
+    assert!(diff.joined.is_empty() && diff.left.is_empty());
+    assert_eq!(diff.captured, vec![(2, String::from("Raiders")), (4, String::from("Raiders"))]);
+    assert_eq!(diff.lost, vec![(2, String::from("Homebodies"))]);
+    assert!(!diff.is_empty());
+
+    let ocean_diff = Snapshot::diff(&snapshot, &Snapshot::of(&ocean));
+    assert_eq!(ocean_diff.clan_changes, vec![(0, diff)]);
+    assert!(ocean_diff.to_string().contains("~ beach 0 clan Homebodies: lost reef 2"));
+}
+
+#[cfg(feature = "economy")]
+#[test]
+fn student_economy_handles_huge_balances() {
+    use ocean::economy::*;
+
+    let mut economy = Economy::load(&format!("economy\t100\nledger\tReds\t{}\n", u32::MAX - 15)).unwrap();
+    let prey: Vec<Box<dyn Prey>> = (0..2).map(|_| Box::new(Clam::new()) as Box<dyn Prey>).collect();
+    assert_eq!(economy.deposit("Reds", prey), 15);
+    assert_eq!(economy.balance("Reds"), u32::MAX);
+
+    economy.decay();
+    assert_eq!(economy.balance("Reds"), 0);
+}
+
+#[test]
+fn student_life_summaries_record_lifespan_and_notable_events() {
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 5));
+    let mut ocean = Ocean::new();
+    ocean.add_beach(beach);
+    let mut simulation = Simulation::new(ocean, 7);
+    simulation.run(MOLT_INTERVAL as u64 + MOLT_STEPS as u64);
+
+    let beach = simulation.ocean_mut().beaches_mut().next().unwrap();
+    // Draining the beach's events keeps the crab's notable ones.
+    beach.drain_events();
+    let summary = beach.bury_crab(0).clone();
+    assert_eq!(summary.lifespan(), MOLT_INTERVAL + MOLT_STEPS);
+    let speed = 5 + MOLT_SPEED_GAIN;
+    assert_eq!(summary.notable_events(), &[OceanEvent::MoltEnded { crab: String::from("Ferris"), speed }]);
+    assert_eq!(
+        summary.to_string(),
+        format!(
+            "Ferris (speed {}) lived {} step(s), made 0 successful hunt(s) and parented 0 crab(s), and never joined a clan. \
+             Along the way it molted to speed {}.",
+            speed,
+            MOLT_INTERVAL + MOLT_STEPS,
+            speed
+        )
+    );
+}
+
+#[test]
+fn student_undo_history_is_bounded() {
+    let mut beach = Beach::new();
+    assert_eq!(beach.undo_depth(), DEFAULT_UNDO_DEPTH);
+    beach.set_undo_depth(3);
+    for i in 0..5 {
+        beach.add_crab(new_crab(&format!("crab{}", i), 1));
+    }
+    assert!(beach.undo() && beach.undo() && beach.undo());
+    assert!(!beach.undo());
+    assert_eq!(beach.size(), 2);
+
+    // Shrinking the history keeps the changes nearest to being redone.
+    beach.set_undo_depth(1);
+    assert!(beach.redo());
+    assert!(!beach.redo());
+    assert_eq!(beach.crabs().map(Crab::name).collect::<Vec<_>>(), vec!["crab0", "crab1", "crab2"]);
+
+    beach.set_undo_depth(0);
+    beach.add_crab(new_crab("crab5", 1));
+    assert!(!beach.undo());
+}
+
+#[test]
+fn student_ocean_macro_builds_large_scenarios() {
+    // Writes out the crabs it is given four times over, on one beach.
+    macro_rules! big_beach {
+        ($($crab:tt)*) => {
+            ocean::ocean! {
+                reef "kelp" [Algae]
+                beach "big" { $($crab)* $($crab)* $($crab)* $($crab)* clan "reds" ["a", "b"] }
+            }
+        };
+    }
+    let ocean = big_beach! {
+        crab "a" speed 2 color Red diet Fish, crab "b" diet Plants speed 3 color Teal, crab "c" speed 4 reefs ["kelp"],
+        crab "d" speed 5 color Blue diet Shellfish, crab "e" speed 6 color Orange, crab "f" speed 7 diet Fish,
+        crab "g" speed 2 color Red diet Fish, crab "h" diet Plants speed 3 color Teal, crab "i" speed 4 reefs ["kelp"],
+        crab "j" speed 5 color Blue diet Shellfish, crab "k" speed 6 color Orange, crab "l" speed 7 diet Fish,
+        crab "m" speed 2 color Red diet Fish, crab "n" diet Plants speed 3 color Teal, crab "o" speed 4 reefs ["kelp"],
+        crab "p" speed 5 color Blue diet Shellfish, crab "q" speed 6 color Orange, crab "r" speed 7 diet Fish,
+        crab "s" speed 2 color Red diet Fish, crab "t" diet Plants speed 3 color Teal,
+    }
+    .unwrap();
+    let beach = ocean.beaches().next().unwrap();
+    assert_eq!(beach.size(), 80);
+    let b = beach.get_crab(1);
+    assert_eq!((b.speed(), b.color(), b.diet()), (3, &Color::new_teal(), Diet::Plants));
+    assert_eq!(beach.get_crab(22).reefs().count(), 1);
+    assert_eq!(beach.get_clan_of_crab("b"), Some("reds"));
+}
+
+#[test]
+fn student_configs_reject_non_finite_rates() {
+    for (text, line) in [
+        ("[simulation]\nregen_rate = inf", 2),
+        ("[simulation]\nregen_rate = NaN", 2),
+        ("[simulation]\nregen_rate = 1e400", 2),
+        ("[simulation]\nregen_rate = -1", 2),
+        ("[simulation]\nseed = 1\nmigration_rate = inf", 3),
+        ("[simulation]\nmigration_rate = 1.5", 2),
+        ("[reefs.kelp.regen]\nAlgae = inf", 2),
+        ("[reefs]\nescape_chance = nan", 2),
+    ] {
+        assert_eq!(SimConfig::parse(text).unwrap_err().line(), line, "{}", text);
+    }
+    assert_eq!(SimConfig::parse("[simulation]\nmigration_rate = 1\nregen_rate = 2.5").unwrap().regen_rate, 2.5);
+}
+
+#[test]
+fn student_extreme_regen_rates_do_not_panic() {
+    let mut reef = Reef::new();
+    reef.set_capacity(Some(5));
+    reef.set_regen_policy(
+        RegenPolicy::new()
+            .with_rate(f64::INFINITY, || Box::new(Algae::new()))
+            .with_rate(f64::NAN, || Box::new(Clam::new()))
+            .with_rate(1e300, || Box::new(Algae::new())),
+    );
+    let mut rng = Pcg64::seed_from_u64(1);
+    assert_eq!(reef.tick_scaled(f64::INFINITY, &mut rng), 5);
+    assert_eq!(reef.tick_scaled(f64::NAN, &mut rng), 0);
+
+    // Without a capacity, spawning stops at the cap on rates.
+    let mut open = Reef::new();
+    open.set_regen_policy(RegenPolicy::new().with_rate(f64::INFINITY, || Box::new(Algae::new())));
+    assert_eq!(open.tick_scaled(2.0, &mut rng), MAX_REGEN_RATE as usize);
+    assert!(Reef::load("reef\tFifo\nregen\t1.5\tAlgae\n").is_ok());
+    assert!(Reef::load("reef\tFifo\nregen\tinf\tAlgae\n").is_err());
+    assert!(Reef::load("reef\tFifo\nregen\tNaN\tAlgae\n").is_err());
+}
+
+#[test]
+fn student_beaches_reject_crab_ids_too_large_to_follow() {
+    let err = Beach::load("ancestor\t18446744073709551615\t\t\n").unwrap_err();
+    assert_eq!(err.line(), 1);
+    assert!(Beach::load("ancestor\t18446744073709551614\t\t\n").is_ok());
+
+    let mut beach = Beach::new();
+    beach.add_crab(new_crab("Ferris", 3));
+    let saved = beach.save().replace("\t1\t\t\t", "\t18446744073709551615\t\t\t");
+    assert!(saved.contains("18446744073709551615"));
+    assert!(Beach::load(&saved).is_err());
+}
+
+#[test]
+fn student_lineage_must_run_from_parents_to_children() {
+    let err = Beach::load("ancestor\t1\t2\t2\nancestor\t2\t1\t1\n").unwrap_err();
+    assert_eq!(err.line(), 1);
+    assert!(Beach::load("ancestor\t3\t1\t3\n").is_err());
+
+    // Lineages far deeper than the stack could recurse through still work out.
+    let mut text = String::from("ancestor\t1\t\t\n");
+    for id in 2..=100_000u64 {
+        text.push_str(&format!("ancestor\t{}\t{}\t{}\n", id, id - 1, id - 1));
+    }
+    let beach = Beach::load(&text).unwrap();
+    let relatedness = beach.relatedness(100_000, 99_999);
+    assert!(relatedness > 0.99 && relatedness <= 1.0, "{}", relatedness);
+}
+
+#[test]
+fn student_the_oldest_prey_still_age() {
+    let mut reef = Reef::load("reef\tFifo\nprey\tAlgae\nage\t4294967295\n").unwrap();
+    let mut rng = Pcg64::seed_from_u64(1);
+    reef.tick_scaled(1.0, &mut rng);
+    // Long past its lifespan, it dies of old age rather than overflowing.
+    assert_eq!(reef.population(), 0);
+}
+
+#[test]
+fn student_reputation_stops_at_its_maximum() {
+    let mut clans = ClanSystem::new();
+    clans.create_clan(String::from("reds"));
+    clans.create_clan(String::from("blues"));
+    let text = format!("{}reputation\treds\t{}\n", clans.save(), u32::MAX);
+    let mut clans = ClanSystem::load(&text).unwrap();
+    assert_eq!(clans.reputation("reds"), u32::MAX);
+    clans.record_result("reds", "blues", Some("reds"));
+    clans.record_result("reds", "blues", None);
+    assert_eq!(clans.reputation("reds"), u32::MAX);
+    assert_eq!(clans.reputation("blues"), DRAW_POINTS);
+}
+
+#[cfg(feature = "fuzzing")]
+#[test]
+fn student_load_driver_puts_what_loads_to_work() {
+    let mut clans = ClanSystem::new();
+    clans.create_clan(String::from("reds"));
+    clans.create_clan(String::from("blues"));
+    // Inputs that once loaded fine and then panicked when used.
+    for text in [
+        String::from("[simulation]\nregen_rate = inf\n[reefs.kelp.regen]\nAlgae = 1\n"),
+        String::from("reef\tFifo\nregen\t1e308\tAlgae\n"),
+        String::from("reef\tFifo\nprey\tAlgae\nage\t4294967295\n"),
+        String::from("ancestor\t18446744073709551615\t\t\n"),
+        String::from("ancestor\t1\t2\t2\nancestor\t2\t1\t1\n"),
+        format!("{}reputation\treds\t{}\n", clans.save(), u32::MAX),
+    ] {
+        ocean::fuzz::load_text(text.as_bytes());
+    }
+}
+
+#[test]
+fn student_arenas_remove_many_items_at_once() {
+    let mut arena = Arena::new();
+    let handles: Vec<Handle> = (0..10).map(|i| arena.push(i)).collect();
+    assert_eq!(arena.remove_many(&[1, 4, 5, 9]), vec![1, 4, 5, 9]);
+    assert_eq!(arena.as_slice(), &[0, 2, 3, 6, 7, 8]);
+    for (i, &handle) in handles.iter().enumerate() {
+        let expected = if [1, 4, 5, 9].contains(&i) { None } else { Some(i) };
+        assert_eq!(arena.position(handle).map(|position| arena[position]), expected);
+    }
+    assert!(arena.remove_many(&[]).is_empty());
+    // Freed slots are reused with fresh handles.
+    let new = arena.push(10);
+    assert!(!handles.contains(&new));
+    assert_eq!(arena.position(new), Some(6));
+
+    // Removing half of a large arena takes one pass, not one per item.
+    let mut arena: Arena<usize> = (0..200_000).fold(Arena::new(), |mut arena, i| {
+        arena.push(i);
+        arena
+    });
+    let evens: Vec<usize> = (0..200_000).step_by(2).collect();
+    let started = std::time::Instant::now();
+    assert_eq!(arena.remove_many(&evens).len(), 100_000);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert_eq!(arena[0], 1);
+}
+
+#[test]
+fn student_draining_large_beaches_is_quick() {
+    let mut beach = Beach::new();
+    beach.add_crabs((0..60_000).map(|i| new_crab(&format!("crab{}", i), i % 10)));
+    beach.get_clan_system_mut().create_clan(String::from("odds"));
+    beach.add_member_to_clan("odds", "crab1");
+    let started = std::time::Instant::now();
+    let drained = beach.drain_matching(|crab| crab.speed() % 2 == 1);
+    assert!(started.elapsed() < std::time::Duration::from_secs(5), "draining took {:?}", started.elapsed());
+    assert_eq!((drained.len(), beach.size()), (30_000, 30_000));
+    assert_eq!(drained[0].name(), "crab1");
+    assert_eq!(beach.get_crab(1).name(), "crab2");
+    assert_eq!(beach.find_crab_index("crab59998"), Some(29_999));
+    assert_eq!(beach.get_clan_system().get_clan_member_count("odds"), 0);
+}
+
+#[test]
+fn student_adding_a_beach_under_a_taken_name_does_not_panic() {
+    let mut ocean = Ocean::new();
+    let mut first = Beach::with_name("north".to_string());
+    first.add_crab(new_crab("Ferris", 3));
+    ocean.add_beach(first);
+    ocean.add_beach(Beach::with_name("north".to_string()));
+    assert_eq!(ocean.beaches().count(), 2);
+    assert_eq!(ocean.beach_index("north"), Some(0));
+    assert_eq!(ocean.beach_by_name("north").unwrap().size(), 1);
+    assert_eq!(
+        ocean.try_add_beach(Beach::with_name("north".to_string())).unwrap_err(),
+        OceanError::DuplicateName { kind: "beach", name: "north".to_string() }
+    );
+    assert_eq!(ocean.beaches().count(), 2);
+}
+
+#[test]
+fn student_nan_chances_do_not_panic() {
+    let mut rng = Pcg64::seed_from_u64(580);
+    let red = Color::new_red();
+    let blue = Color::new_blue();
+    for _ in 0..20 {
+        let child = Color::cross_weighted(&red, &blue, f64::NAN, &mut rng);
+        assert!([red.clone(), blue.clone()].iter().any(|parent| child.r == parent.r));
+    }
+
+    let genome = Genome::new(3, Genotype::homozygous(red.clone()), f64::NAN);
+    assert_eq!(genome.escape_resistance(), 0.0);
+    assert_eq!(genome.clone().with_escape_resistance(f64::NAN).escape_resistance(), 0.0);
+    let mut mutant = genome.clone();
+    mutant.mutate(f64::NAN, &mut rng);
+    assert_eq!(mutant, genome);
+
+    let crab = new_crab("Ferris", 4);
+    let traits = PreyTraits { speed: 6, camouflage: f64::NAN, size: Size::Small };
+    let mut chance = ChanceEscape::new(0.25, 0.1, 0.2, 0.0);
+    assert_eq!(chance.probability(&traits, &crab), 0.0);
+    assert!(!chance.escapes_with(&traits, &crab, &mut rng));
+    let mut nan_base = ChanceEscape::new(f64::NAN, 0.0, 0.0, 0.0);
+    assert!(!nan_base.escapes_with(&PreyTraits { camouflage: 0.0, ..traits }, &crab, &mut rng));
+
+    let mut fields = crab.to_fields();
+    fields[15] = String::from("NaN");
+    let err = Crab::from_fields(&fields, 7).unwrap_err();
+    assert_eq!(err.line(), 7);
+    assert_eq!(err.message(), "invalid escape resistance: \"NaN\"");
+}